@@ -0,0 +1,124 @@
+use crate::metrics::Metrics;
+use std::{
+    net::SocketAddr,
+    pin::Pin,
+    sync::{atomic::Ordering, Arc},
+    task::{Context, Poll},
+    time::Instant,
+};
+use tracing::info;
+use warp::{
+    http::{header::CONTENT_TYPE, HeaderMap, Method, StatusCode},
+    hyper::{
+        body::{Bytes, HttpBody, SizeHint},
+        Body, Request, Response,
+    },
+};
+
+/// Log target of the access lines, so they can be filtered on their own (`RUST_LOG=info,access=warn`).
+const TARGET: &str = "access";
+
+/// A web request being answered; logged once hyper is done with its response body.
+pub struct AccessEntry {
+    remote: SocketAddr,
+    method: Method,
+    path: String,
+    started: Instant,
+    metrics: Arc<Metrics>,
+}
+
+impl AccessEntry {
+    /// Start timing `request`. Only the path is kept: query strings may hold a token.
+    pub fn start(remote: SocketAddr, request: &Request<Body>, metrics: &Arc<Metrics>) -> Self {
+        Self {
+            remote,
+            method: request.method().clone(),
+            path: request.uri().path().to_string(),
+            started: Instant::now(),
+            metrics: Arc::clone(metrics),
+        }
+    }
+
+    /// Wrap the body of `response` so the line is written after the last byte was sent.
+    ///
+    /// WebSocket upgrades and event streams are logged but left out of the durations:
+    /// they stay open for as long as the client watches.
+    pub fn finish(self, response: Response<Body>) -> Response<LoggedBody> {
+        let status = response.status();
+        let timed = status != StatusCode::SWITCHING_PROTOCOLS && !is_event_stream(response.headers());
+        response.map(|body| LoggedBody {
+            body,
+            bytes: 0,
+            pending: Some((self, status, timed)),
+        })
+    }
+
+    fn log(self, status: StatusCode, bytes: u64, timed: bool) {
+        let elapsed = self.started.elapsed();
+        self.metrics.web_requests.fetch_add(1, Ordering::Relaxed);
+        if timed {
+            self.metrics.record_request_duration(elapsed);
+        }
+        info!(
+            target: TARGET,
+            "{} \"{} {}\" {} {} {:.1}ms",
+            self.remote.ip(),
+            self.method,
+            self.path,
+            status.as_u16(),
+            bytes,
+            elapsed.as_secs_f64() * 1000.0
+        );
+    }
+}
+
+fn is_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/event-stream"))
+}
+
+/// Response body counting the bytes sent, which writes the access line when dropped.
+///
+/// Hyper drops the body once it has written all of it (or the client went away), so
+/// logging never delays a response.
+pub struct LoggedBody {
+    body: Body,
+    bytes: u64,
+    pending: Option<(AccessEntry, StatusCode, bool)>,
+}
+
+impl HttpBody for LoggedBody {
+    type Data = Bytes;
+    type Error = warp::hyper::Error;
+
+    fn poll_data(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = self.get_mut();
+        let polled = Pin::new(&mut this.body).poll_data(cx);
+        if let Poll::Ready(Some(Ok(chunk))) = &polled {
+            this.bytes += chunk.len() as u64;
+        }
+        polled
+    }
+
+    fn poll_trailers(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.get_mut().body).poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+}
+
+impl Drop for LoggedBody {
+    fn drop(&mut self) {
+        if let Some((entry, status, timed)) = self.pending.take() {
+            entry.log(status, self.bytes, timed);
+        }
+    }
+}
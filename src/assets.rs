@@ -0,0 +1,235 @@
+use crate::{page_config::PageConfig, style::CustomCss, themes};
+use flate2::{write::GzEncoder, Compression};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::Hasher,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tracing::warn;
+use warp::hyper::body::Bytes;
+
+/// Files compiled into the binary, by URL path. Served when `--web-root` is unset or lacks the file.
+const EMBEDDED: [(&str, &[u8]); 16] = [
+    ("common.js", include_bytes!("../static/common.js")),
+    ("overlay.html", include_bytes!("../static/overlay.html")),
+    ("overlay.css", include_bytes!("../static/overlay.css")),
+    ("overlay.js", include_bytes!("../static/overlay.js")),
+    ("bug.html", include_bytes!("../static/bug.html")),
+    ("bug.css", include_bytes!("../static/bug.css")),
+    ("bug.js", include_bytes!("../static/bug.js")),
+    ("slate.html", include_bytes!("../static/slate.html")),
+    ("slate.css", include_bytes!("../static/slate.css")),
+    ("slate.js", include_bytes!("../static/slate.js")),
+    ("admin.html", include_bytes!("../static/admin.html")),
+    ("themes/minimal.html", include_bytes!("../static/themes/minimal.html")),
+    ("themes/minimal.css", include_bytes!("../static/themes/minimal.css")),
+    ("themes/broadcast.html", include_bytes!("../static/themes/broadcast.html")),
+    ("themes/broadcast.css", include_bytes!("../static/themes/broadcast.css")),
+    ("media/CAB.svg", include_bytes!("../static/media/CAB.svg")),
+];
+
+/// A file ready to be served.
+#[derive(Debug, Clone)]
+pub struct Asset {
+    pub body: Bytes,
+    /// `body` gzip-compressed, when that makes it smaller.
+    pub gzip: Option<Bytes>,
+    pub content_type: &'static str,
+    /// Strong validator of `body`, quoted (`"1a2b...-3f"`). The gzip copy gets a `-gz` suffix.
+    pub etag: String,
+}
+
+impl Asset {
+    fn new(body: Bytes, content_type: &'static str) -> Self {
+        Self {
+            gzip: compressible(content_type).then(|| gzip(&body)).flatten().map(Bytes::from),
+            etag: etag(&body),
+            body,
+            content_type,
+        }
+    }
+
+    /// ETag of the gzip representation; it must differ from the identity one.
+    pub fn gzip_etag(&self) -> String {
+        format!("{}-gz\"", self.etag.trim_end_matches('"'))
+    }
+}
+
+/// The overlay's static files: from `--web-root` when set, otherwise (or when missing there) embedded.
+#[derive(Debug, Clone)]
+pub struct Assets {
+    /// Canonical web root directory.
+    root: Option<PathBuf>,
+    /// Embedded files by path, with their compressed copy and ETag computed once at startup.
+    embedded: Arc<Vec<(&'static str, Asset)>>,
+    /// Added to the overlay pages.
+    custom_css: Option<Arc<CustomCss>>,
+}
+
+impl Assets {
+    /// Only the embedded files, compressed up front.
+    pub fn embedded() -> Self {
+        let embedded = EMBEDDED
+            .iter()
+            .map(|(path, body)| (*path, Asset::new(Bytes::from_static(body), content_type(path))))
+            .collect();
+        Self {
+            root: None,
+            embedded: Arc::new(embedded),
+            custom_css: None,
+        }
+    }
+
+    /// The embedded files, overridden by those under `root` when set.
+    pub fn new(root: Option<&Path>) -> io::Result<Self> {
+        let root = root.map(|root| root.canonicalize()).transpose()?;
+        Ok(Self { root, ..Self::embedded() })
+    }
+
+    /// Add `custom_css` to every overlay page served (see [`themes::is_overlay_page`]).
+    pub fn with_custom_css(self, custom_css: Arc<CustomCss>) -> Self {
+        Self {
+            custom_css: Some(custom_css),
+            ..self
+        }
+    }
+
+    /// Look up a file by its (percent-encoded) URL path, without the leading `/`.
+    ///
+    /// Paths that try to leave the web root and directories yield `None`. Overlay pages
+    /// come with the custom CSS, if any, and every HTML page with `config`.
+    pub async fn get(&self, url_path: &str, config: &PageConfig) -> Option<Asset> {
+        let path = sanitize(url_path)?;
+        let asset = self.find(&path).await?;
+        if !asset.content_type.starts_with("text/html") {
+            return Some(asset);
+        }
+        let custom_css = self.custom_css.as_ref().filter(|_| themes::is_overlay_page(&path));
+        let styled = custom_css.and_then(|css| css.inject(&asset.body));
+        match config.inject(styled.as_deref().unwrap_or(&asset.body)).or(styled) {
+            Some(page) => Some(Asset::new(Bytes::from(page), asset.content_type)),
+            None => Some(asset),
+        }
+    }
+
+    async fn find(&self, path: &str) -> Option<Asset> {
+        // Files on disk may change between requests, so they are compressed each time
+        if let Some(root) = &self.root {
+            if let Some(body) = read_under(root, path).await {
+                return Some(Asset::new(Bytes::from(body), content_type(path)));
+            }
+        }
+
+        self.embedded
+            .iter()
+            .find(|(name, _)| *name == path)
+            .map(|(_, asset)| asset.clone())
+    }
+}
+
+/// Whether an `Accept-Encoding` header value accepts gzip (`gzip` or `*` without `q=0`).
+pub fn accepts_gzip(accept_encoding: &str) -> bool {
+    accept_encoding.split(',').any(|coding| {
+        let mut parts = coding.split(';').map(str::trim);
+        let name = parts.next().unwrap_or_default();
+        let refused = parts.any(|param| {
+            param
+                .strip_prefix("q=")
+                .is_some_and(|q| q.parse::<f32>().is_ok_and(|q| q <= 0.0))
+        });
+        (name.eq_ignore_ascii_case("gzip") || name == "*") && !refused
+    })
+}
+
+/// Whether an `If-None-Match` header value matches `etag` (`*` matches anything).
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(|candidate| candidate.trim().trim_start_matches("W/"))
+        .any(|candidate| candidate == "*" || candidate == etag)
+}
+
+fn etag(body: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(body);
+    format!("\"{:016x}-{:x}\"", hasher.finish(), body.len())
+}
+
+/// Text formats worth compressing; images and fonts already are.
+fn compressible(content_type: &str) -> bool {
+    content_type.starts_with("text/") || matches!(content_type, "application/json" | "image/svg+xml")
+}
+
+/// `body` gzip-compressed, or `None` if that doesn't make it smaller.
+fn gzip(body: &[u8]) -> Option<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(body).ok()?;
+    let compressed = encoder.finish().ok()?;
+    (compressed.len() < body.len()).then_some(compressed)
+}
+
+/// Decode a URL path and check it only names something below the web root.
+///
+/// Rejects `..`, `.` and empty segments, backslashes, drive letters and NUL bytes,
+/// encoded or not.
+fn sanitize(url_path: &str) -> Option<String> {
+    let path = percent_decode(url_path)?;
+    let valid = !path.is_empty()
+        && path
+            .split('/')
+            .all(|segment| !matches!(segment, "" | "." | "..") && !segment.contains(['\\', ':', '\0']));
+    valid.then_some(path)
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Read `path` below `root`, refusing directories and symlinks that lead outside it.
+async fn read_under(root: &Path, path: &str) -> Option<Vec<u8>> {
+    let full = tokio::fs::canonicalize(root.join(path)).await.ok()?;
+    if !full.starts_with(root) {
+        warn!("Refusing to serve {:?}: outside the web root", path);
+        return None;
+    }
+    if !tokio::fs::metadata(&full).await.ok()?.is_file() {
+        return None;
+    }
+    tokio::fs::read(&full).await.ok()
+}
+
+fn content_type(path: &str) -> &'static str {
+    let extension = path.rsplit_once('.').map_or("", |(_, ext)| ext);
+    match extension.to_ascii_lowercase().as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "txt" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
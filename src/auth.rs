@@ -0,0 +1,205 @@
+use crate::state::lock;
+use ring::{digest::SHA256_OUTPUT_LEN as TAG_LEN, hmac};
+use std::{
+    collections::HashMap,
+    io::{self, Read},
+    net::{IpAddr, TcpStream},
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Time a new connection has to send `AUTH <token>\n`.
+pub const AUTH_GRACE_PERIOD: Duration = Duration::from_secs(5);
+/// Failed handshakes allowed per source IP within [`AUTH_FAILURE_WINDOW`].
+const MAX_AUTH_FAILURES: u32 = 5;
+/// Once an IP exceeds [`MAX_AUTH_FAILURES`], its connections are refused until the window ends.
+const AUTH_FAILURE_WINDOW: Duration = Duration::from_secs(60);
+/// Longest handshake line accepted.
+const MAX_AUTH_LINE: usize = 256;
+/// Source IPs an [`AuthLimiter`] keeps failures for; beyond it the oldest are forgotten.
+const MAX_TRACKED_IPS: usize = 4096;
+
+/// Compare two secrets in time independent of where they differ.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Random 128-bit token (hex) for when no admin token is configured, from the OS random source.
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).expect("the OS random source is available");
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Bytes of the counter between the payload of a signed datagram and its tag.
+const COUNTER_LEN: usize = 8;
+/// How far behind the newest accepted counter a datagram from a sender not seen yet may be,
+/// which allows for senders whose clocks disagree a little.
+const NEW_SENDER_WINDOW: Duration = Duration::from_secs(10);
+
+fn datagram_key(token: &str) -> hmac::Key {
+    hmac::Key::new(hmac::HMAC_SHA256, token.as_bytes())
+}
+
+fn unix_micros() -> u64 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_micros() as u64)
+}
+
+/// Signs the datagrams sent to a server with an auth token: each is the payload, a counter
+/// (the time in microseconds since the Unix epoch, big endian, never repeated) and the
+/// HMAC-SHA256 of both under the token.
+pub struct DatagramSigner {
+    key: hmac::Key,
+    last: u64,
+}
+
+impl DatagramSigner {
+    pub fn new(token: &str) -> Self {
+        Self { key: datagram_key(token), last: 0 }
+    }
+
+    /// `payload` signed, with a counter above the one of the previous datagram.
+    pub fn sign(&mut self, payload: &[u8]) -> Vec<u8> {
+        self.last = unix_micros().max(self.last + 1);
+        let mut datagram = payload.to_vec();
+        datagram.extend_from_slice(&self.last.to_be_bytes());
+        let tag = hmac::sign(&self.key, &datagram);
+        datagram.extend_from_slice(tag.as_ref());
+        datagram
+    }
+}
+
+/// Checks the datagrams of a [`DatagramSigner`] and refuses one sent again: its counter must
+/// be above the last one accepted from the same address. A sender not seen yet must be within
+/// [`NEW_SENDER_WINDOW`] of the newest counter accepted, so a captured datagram can't be
+/// replayed from another address either.
+pub struct DatagramVerifier {
+    key: hmac::Key,
+    last: HashMap<IpAddr, u64>,
+    newest: u64,
+}
+
+impl DatagramVerifier {
+    pub fn new(token: &str) -> Self {
+        Self {
+            key: datagram_key(token),
+            last: HashMap::new(),
+            newest: 0,
+        }
+    }
+
+    /// The payload of `datagram` from `ip`, or why it is refused.
+    pub fn verify<'a>(&mut self, ip: IpAddr, datagram: &'a [u8]) -> Result<&'a [u8], &'static str> {
+        let split = datagram.len().checked_sub(TAG_LEN + COUNTER_LEN).ok_or("datagram too short for its signature")?;
+        let (signed, tag) = datagram.split_at(split + COUNTER_LEN);
+        hmac::verify(&self.key, signed, tag).map_err(|_| "bad signature")?;
+        let (payload, counter) = signed.split_at(split);
+        let counter = u64::from_be_bytes(counter.try_into().expect("8 bytes"));
+
+        match self.last.get(&ip) {
+            Some(&last) if counter <= last => return Err("replayed or out of order"),
+            Some(_) => {}
+            None if counter.saturating_add(NEW_SENDER_WINDOW.as_micros() as u64) < self.newest => return Err("older than the feed"),
+            None => {
+                if self.last.len() >= MAX_TRACKED_IPS {
+                    let oldest = self.last.iter().min_by_key(|(_, last)| **last).map(|(ip, _)| *ip);
+                    if let Some(oldest) = oldest {
+                        self.last.remove(&oldest);
+                    }
+                }
+            }
+        }
+        self.last.insert(ip, counter);
+        self.newest = self.newest.max(counter);
+        Ok(payload)
+    }
+}
+
+/// Read the `AUTH <token>\n` line that must open an authenticated connection.
+///
+/// Bytes are read one at a time so nothing after the line is consumed. The whole
+/// line must arrive within [`AUTH_GRACE_PERIOD`], however slowly it trickles in;
+/// the stream is left with the read timeout of the last read.
+pub fn read_handshake(stream: &mut TcpStream, token: &str) -> Result<(), String> {
+    let deadline = Instant::now() + AUTH_GRACE_PERIOD;
+    let mut line = Vec::new();
+    let mut byte = [0u8; 1];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err("no AUTH received in time".to_string());
+        }
+        stream.set_read_timeout(Some(remaining)).map_err(|e| e.to_string())?;
+        match stream.read(&mut byte) {
+            Ok(0) => return Err("connection closed before AUTH".to_string()),
+            Ok(_) if byte[0] == b'\n' => break,
+            Ok(_) if line.len() >= MAX_AUTH_LINE => return Err("AUTH line too long".to_string()),
+            Ok(_) => line.push(byte[0]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                return Err("no AUTH received in time".to_string());
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+
+    let line = line.strip_suffix(b"\r").unwrap_or(&line);
+    let presented = line.strip_prefix(b"AUTH ").ok_or("expected AUTH <token>")?;
+    if constant_time_eq(presented, token.as_bytes()) {
+        Ok(())
+    } else {
+        Err("invalid token".to_string())
+    }
+}
+
+/// Tracks failed authentication attempts (TCP handshakes or web tokens) per source IP.
+///
+/// At most [`MAX_TRACKED_IPS`] addresses are kept, so a scan from many addresses can't grow
+/// it without bound: expired windows go first, then the oldest.
+#[derive(Debug, Default)]
+pub struct AuthLimiter {
+    // IP -> (failures, start of the current window)
+    failures: Mutex<HashMap<IpAddr, (u32, Instant)>>,
+}
+
+impl AuthLimiter {
+    /// Whether `ip` has failed too often recently and must be refused outright.
+    pub fn is_blocked(&self, ip: IpAddr) -> bool {
+        let mut failures = lock(&self.failures);
+        match failures.get(&ip) {
+            Some((_, since)) if since.elapsed() >= AUTH_FAILURE_WINDOW => {
+                failures.remove(&ip);
+                false
+            }
+            Some((count, _)) => *count >= MAX_AUTH_FAILURES,
+            None => false,
+        }
+    }
+
+    /// Record a failed attempt from `ip`.
+    pub fn record_failure(&self, ip: IpAddr) {
+        let mut failures = lock(&self.failures);
+        if failures.len() >= MAX_TRACKED_IPS && !failures.contains_key(&ip) {
+            failures.retain(|_, (_, since)| since.elapsed() < AUTH_FAILURE_WINDOW);
+            if failures.len() >= MAX_TRACKED_IPS {
+                let oldest = failures.iter().min_by_key(|(_, (_, since))| *since).map(|(ip, _)| *ip);
+                if let Some(oldest) = oldest {
+                    failures.remove(&oldest);
+                }
+            }
+        }
+        let entry = failures.entry(ip).or_insert((0, Instant::now()));
+        if entry.1.elapsed() >= AUTH_FAILURE_WINDOW {
+            *entry = (0, Instant::now());
+        }
+        entry.0 += 1;
+    }
+
+    /// Forget past failures of `ip` after a successful attempt.
+    pub fn record_success(&self, ip: IpAddr) {
+        lock(&self.failures).remove(&ip);
+    }
+}
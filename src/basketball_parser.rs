@@ -0,0 +1,601 @@
+use crate::{
+    events::{ActiveTimeout, OverlayEvent},
+    line_score::PeriodScore,
+    match_info::MatchInfo,
+    rules::Bonus,
+    state::SharedState,
+    teams::Teams,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+/// Protocol control characters.
+pub const SOH: u8 = 0x01;
+pub const STX: u8 = 0x02;
+pub const ETX: u8 = 0x03;
+
+/// Represents a parsed protocol frame.
+///
+/// ```
+/// use scoreboard_rust::basketball_parser::{parse_raw_data, ProtocolFrame};
+///
+/// // Message 30: scores 45 (home) and 38 (away)
+/// let frame = ProtocolFrame::new(0x7F, 0x20, b"305 45 38".to_vec());
+/// let parsed = parse_raw_data(&frame.to_bytes(), false).unwrap();
+/// assert_eq!(parsed, frame);
+/// assert!(parsed.validate_lrc());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolFrame {
+    pub soh: u8,          // should be 0x01
+    pub address: u8,      // included in LRC calculation
+    pub stx: u8,          // should be 0x02
+    pub ctrl: u8,         // included in LRC calculation
+    pub message: Vec<u8>, // variable-length payload
+    pub etx: u8,          // should be 0x03
+    pub lrc: u8,          // 1 byte checksum as transmitted
+}
+
+impl ProtocolFrame {
+    /// Compute LRC for a byte slice using the protocol rule:
+    /// XOR all bytes, mask with 0x7F, then if < 32 add 32.
+    ///
+    /// This function implements the canonical transformation and can be
+    /// used for both constructing and validating frames.
+    pub fn compute_lrc_bytes(bytes: &[u8]) -> u8 {
+        let mut xor: u8 = 0;
+        for &b in bytes {
+            xor ^= b;
+        }
+        let mut lrc = xor & 0x7F;
+        if lrc < 32 {
+            // use wrapping_add to be explicit about u8 arithmetic
+            lrc = lrc.wrapping_add(32);
+        }
+        lrc
+    }
+
+    /// Build the slice of bytes that are used for the LRC calculation:
+    /// Address, STX, CTRL, Message..., ETX (SOH excluded, ETX included).
+    fn lrc_input_bytes(&self) -> Vec<u8> {
+        let mut v = Vec::with_capacity(3 + self.message.len() + 1);
+        v.push(self.address);
+        v.push(self.stx);
+        v.push(self.ctrl);
+        v.extend_from_slice(&self.message);
+        v.push(self.etx);
+        v
+    }
+
+    /// Compute the expected LRC for this frame (based on current fields).
+    pub fn expected_lrc(&self) -> u8 {
+        let bytes = self.lrc_input_bytes();
+        Self::compute_lrc_bytes(&bytes)
+    }
+
+    /// Validate the stored LRC against the computed value.
+    pub fn validate_lrc(&self) -> bool {
+        self.expected_lrc() == self.lrc
+    }
+
+    /// Build a frame with a correct LRC.
+    pub fn new(address: u8, ctrl: u8, message: Vec<u8>) -> Self {
+        let mut frame = ProtocolFrame {
+            soh: SOH,
+            address,
+            stx: STX,
+            ctrl,
+            message,
+            etx: ETX,
+            lrc: 0,
+        };
+        frame.lrc = frame.expected_lrc();
+        frame
+    }
+
+    /// Serialize the frame as transmitted on the wire.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.message.len() + 6);
+        bytes.push(self.soh);
+        bytes.extend(self.lrc_input_bytes());
+        bytes.push(self.lrc);
+        bytes
+    }
+}
+
+/// Parse raw byte data into a ProtocolFrame.
+///
+/// In lenient mode an LRC mismatch is logged instead of rejecting the frame.
+pub fn parse_raw_data(data: &[u8], lenient: bool) -> Result<ProtocolFrame, String> {
+    if data.len() < 5 {
+        return Err("Data too short to be a valid frame".to_string());
+    }
+
+    if data[0] != SOH {
+        return Err("Invalid SOH".to_string());
+    }
+
+    if data[2] != STX {
+        return Err("Invalid STX".to_string());
+    }
+
+    if data[data.len() - 2] != ETX {
+        return Err("Invalid ETX".to_string());
+    }
+
+    let soh = data[0];
+    let address = data[1];
+    let stx = data[2];
+    let ctrl = data[3];
+    let message = data[4..data.len() - 2].to_vec();
+    let etx = data[data.len() - 2];
+    let lrc = data[data.len() - 1];
+
+    let frame = ProtocolFrame {
+        soh,
+        address,
+        stx,
+        ctrl,
+        message,
+        etx,
+        lrc,
+    };
+
+    if !frame.validate_lrc() {
+        if !lenient {
+            return Err("LRC validation failed".to_string());
+        }
+        warn!(
+            "LRC mismatch (expected {:02X}, got {:02X}), accepting frame in lenient mode",
+            frame.expected_lrc(),
+            frame.lrc
+        );
+    }
+
+    Ok(frame)
+}
+
+/// A message read through its [`MessageSpec`]: fields by name, at the offsets [`MESSAGES`]
+/// gives them.
+struct Fields<'a> {
+    spec: &'static MessageSpec,
+    message: &'a [u8],
+}
+
+impl Fields<'_> {
+    fn bytes(&self, name: &str) -> &[u8] {
+        let field = self.spec.fields.iter().find(|field| field.name == name).unwrap_or_else(|| panic!("message {} has no field {}", self.spec.id, name));
+        &self.message[field.offset..field.offset + field.size]
+    }
+
+    fn byte(&self, name: &str) -> u8 {
+        self.bytes(name)[0]
+    }
+
+    /// The field as the console sends it, one character per byte.
+    fn text(&self, name: &str) -> String {
+        self.bytes(name).iter().map(|&byte| byte as char).collect()
+    }
+}
+
+struct StatusWord18 {
+    clock_type: bool,          // bit 0
+    game_clock_off: bool,      // bit 1
+    horn_on: bool,             // bit 2
+    possession_in_tenth: bool, // bit 4
+    new_match: bool,           // bit 6
+    b7: bool,                  // bit 7
+}
+
+impl StatusWord18 {
+    fn from_byte(byte: u8) -> Self {
+        Self {
+            clock_type: (byte & (1 << 0)) != 0,
+            game_clock_off: (byte & (1 << 1)) != 0,
+            horn_on: (byte & (1 << 2)) != 0,
+            possession_in_tenth: (byte & (1 << 4)) != 0,
+            new_match: (byte & (1 << 6)) != 0,
+            b7: (byte & (1 << 7)) != 0,
+        }
+    }
+}
+
+#[allow(dead_code)]
+struct StatusWord50 {
+    b0: Option<bool>, // bit 0
+    status_possession_timer: bool,       
+    status_possession_horn: bool,              // bit 2
+    status_of_shot_clock: bool,                 // bit 2
+    possession_timer_in_tenths: bool,        // bit 4
+    b5: Option<bool>,                        // bit 6
+    b6: Option<bool>,                        // bit 7
+    b7: bool,                                // bit 7
+}
+
+impl StatusWord50 {
+    fn from_byte(byte: u8) -> Self {
+        Self {
+            b0: None,
+            status_possession_timer: (byte & (1 << 1)) != 0,
+            status_possession_horn: (byte & (1 << 2)) != 0,
+            status_of_shot_clock: (byte & (1 << 3)) != 0,
+            possession_timer_in_tenths: (byte & (1 << 4)) != 0,
+            b5: None,
+            b6: None,
+            b7: (byte & (1 << 7)) != 0,
+        }
+    }
+}
+
+/// The game as the scoreboard shows it: display fields as the console sends them (`" 45"`,
+/// `"08:12"`), plus what the server derives from them and what the operator sets.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct GameState {
+    pub home_score: String,
+    pub away_score: String,
+    pub time: String,
+    pub period_name: String,
+    pub home_fouls: String,
+    pub away_fouls: String,
+    pub home_timeouts: String,
+    pub away_timeouts: String,
+    pub game_state: String, // "paused" or "running".
+    pub shot_clock: String, // "SS" or "S.t"; "" while the console blanks it, "-" until received
+    #[serde(default)]
+    pub shot_clock_running: bool,
+    #[serde(default)]
+    pub possession: String, // "home", "away" or "" when unknown; set from the admin page
+    #[serde(default)]
+    pub stale: bool, // true once the input has gone away; last values are kept on screen
+    #[serde(default)]
+    pub teams: Teams, // set by the operator with PUT /api/teams, never by the console
+    #[serde(default)]
+    pub match_info: MatchInfo, // set by the operator with PUT /api/match, never by the console
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub display_clock: String, // `time` counted down between frames with --tick-clock, empty otherwise
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub display_shot_clock: String, // `shot_clock` counted down the same way
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock_valid_at_ms: Option<u64>, // Unix ms at which the running clock showed `time`; None while stopped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub home_bonus: Option<Bonus>, // derived from away_fouls under the --rules rule set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub away_bonus: Option<Bonus>, // derived from home_fouls
+    #[serde(default)]
+    pub horn: bool, // the console's horn is sounding
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<OverlayEvent>, // end of period and horn, until they expire; set when published
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_timeout: Option<ActiveTimeout>, // the time-out being played; kept up to date by the server
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub line_score: Vec<PeriodScore>, // points per finished period of the current game; set when published
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        Self {
+            home_score: "-".to_string(),
+            away_score: "-".to_string(),
+            time: "--:--".to_string(),
+            period_name: "-".to_string(),
+            home_fouls: "-".to_string(),
+            away_fouls: "-".to_string(),
+            home_timeouts: "-".to_string(),
+            away_timeouts: "-".to_string(),
+            game_state: "paused".to_string(),
+            shot_clock: "-".to_string(),
+            shot_clock_running: false,
+            possession: String::new(),
+            stale: false,
+            teams: Teams::default(),
+            match_info: MatchInfo::default(),
+            display_clock: String::new(),
+            display_shot_clock: String::new(),
+            clock_valid_at_ms: None,
+            home_bonus: None,
+            away_bonus: None,
+            horn: false,
+            events: Vec::new(),
+            active_timeout: None,
+            line_score: Vec::new(),
+        }
+    }
+}
+
+/// Numeric value of a display field such as `" 17"` or `"55 "`, if it holds any digits.
+pub fn numeric_value(field: &str) -> Option<u32> {
+    let digits: String = field.chars().filter(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+impl GameState {
+    /// Compare two states ignoring the game clock and shot clock.
+    pub fn eq_ignoring_clock(&self, other: &Self) -> bool {
+        Self {
+            time: other.time.clone(),
+            shot_clock: other.shot_clock.clone(),
+            display_clock: other.display_clock.clone(),
+            display_shot_clock: other.display_shot_clock.clone(),
+            clock_valid_at_ms: other.clock_valid_at_ms,
+            ..self.clone()
+        } == *other
+    }
+}
+
+/// Publish the state `frame`, read on the `source` input, leads to, if it changes anything.
+pub(crate) fn parse_valid_frame(frame: ProtocolFrame, source: &'static str, shared: &SharedState) {
+    let mut updated_state = shared.current();
+    if apply_frame(&frame, &mut updated_state) {
+        updated_state.stale = false;
+        shared.update_from(updated_state, source);
+    }
+}
+
+/// A protocol as `protocol info` lists it: its framing, messages and what the server adds to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ProtocolSpec {
+    pub id: &'static str,
+    pub name: &'static str,
+    /// The bytes of a frame, in order.
+    pub framing: &'static str,
+    /// Bytes of a frame around its message.
+    pub overhead: usize,
+    pub checksum: &'static str,
+    pub messages: &'static [MessageSpec],
+    /// What the server accepts besides the frames, with the option turning it on.
+    pub extensions: &'static [ExtensionSpec],
+}
+
+/// A message type: the two digits that start the message, and its fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MessageSpec {
+    pub id: &'static str,
+    pub name: &'static str,
+    /// Bytes [`apply_frame`] needs; shorter messages are refused.
+    pub length: usize,
+    pub fields: &'static [FieldSpec],
+}
+
+/// A field of a message, its offset counted from the first byte of the message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FieldSpec {
+    pub offset: usize,
+    pub size: usize,
+    pub name: &'static str,
+    pub meaning: &'static str,
+    /// The values the console sends, as characters unless said otherwise.
+    pub range: &'static str,
+    /// Whether [`apply_frame`] reads it into the game state.
+    pub read: bool,
+}
+
+/// A server extension to the console protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ExtensionSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub option: &'static str,
+}
+
+const fn field(offset: usize, size: usize, name: &'static str, meaning: &'static str, range: &'static str, read: bool) -> FieldSpec {
+    FieldSpec { offset, size, name, meaning, range, read }
+}
+
+/// Message types [`apply_frame`] reads, with their fields. The parser looks each message up
+/// here and reads its fields at these offsets, so the table is the one `protocol info` lists.
+pub const MESSAGES: [MessageSpec; 5] = [
+    MessageSpec {
+        id: "18",
+        name: "Game Time and Time-outs",
+        length: 14,
+        fields: &[
+            field(0, 2, "type", "message type", "\"18\"", true),
+            field(2, 1, "status", "status word: bit 1 game clock stopped, bit 2 horn, bit 4 last minute in tenths, bit 6 new match", "bit field", true),
+            field(3, 1, "sport", "sport", "'5' (basketball)", false),
+            field(4, 2, "minutes", "game clock minutes; seconds in tenths mode", "' 0'-'99'", true),
+            field(6, 2, "seconds", "game clock seconds; in tenths mode the second byte is the tenths", "'00'-'59'", true),
+            field(8, 1, "home_timeouts", "home time-outs taken", "'0'-'9'", true),
+            field(9, 1, "away_timeouts", "away time-outs taken", "'0'-'9'", true),
+            field(10, 2, "reserved", "reserved", "any", false),
+            field(12, 1, "period", "period", "'1'-'4', 'O' (overtime)", true),
+            field(13, 1, "reserved", "reserved", "any", false),
+        ],
+    },
+    MessageSpec {
+        id: "30",
+        name: "Scores",
+        length: 9,
+        fields: &[
+            field(0, 2, "type", "message type", "\"30\"", true),
+            field(2, 1, "sport", "sport", "'5' (basketball)", false),
+            field(3, 3, "home_score", "home score, right-aligned", "'  0'-'999'", true),
+            field(6, 3, "away_score", "away score, right-aligned", "'  0'-'999'", true),
+        ],
+    },
+    MessageSpec {
+        id: "31",
+        name: "Fouls and Player Info",
+        length: 11,
+        fields: &[
+            field(0, 2, "type", "message type", "\"31\"", true),
+            field(2, 1, "sport", "sport", "'5' (basketball)", false),
+            field(3, 1, "reserved", "reserved", "any", false),
+            field(4, 1, "home_fouls", "home team fouls", "'0'-'9'", true),
+            field(5, 1, "reserved", "reserved", "any", false),
+            field(6, 1, "away_fouls", "away team fouls", "'0'-'9'", true),
+            field(7, 2, "player", "number of the player who fouled", "' 0'-'99'", false),
+            field(9, 1, "player_fouls", "fouls of that player", "'0'-'5'", false),
+            field(10, 1, "player_team", "team of that player", "any", false),
+        ],
+    },
+    MessageSpec {
+        id: "36",
+        name: "Possession Time Last Minute",
+        length: 5,
+        fields: &[
+            field(0, 2, "type", "message type", "\"36\"", true),
+            field(2, 2, "seconds", "game clock seconds in the last minute", "' 0'-'59'", true),
+            field(4, 1, "tenths", "game clock tenths of a second", "'0'-'9'", true),
+        ],
+    },
+    MessageSpec {
+        id: "50",
+        name: "Shot Clock",
+        length: 5,
+        fields: &[
+            field(0, 2, "type", "message type", "\"50\"", true),
+            field(2, 1, "status", "status word: bit 1 shot clock stopped, bit 2 horn, bit 3 blanked, bit 4 in tenths", "bit field", true),
+            field(3, 2, "seconds", "shot clock seconds; in tenths mode seconds and tenths", "' 0'-'24'", true),
+        ],
+    },
+];
+
+/// The console protocol the server reads.
+pub const PROTOCOL: ProtocolSpec = ProtocolSpec {
+    id: "bodet-basketball",
+    name: "Bodet Scorepad, basketball",
+    framing: "SOH (0x01), address, STX (0x02), control, message, ETX (0x03), LRC",
+    overhead: 6,
+    checksum: "LRC: XOR of address to ETX, masked with 0x7F, plus 0x20 when below 0x20",
+    messages: &MESSAGES,
+    extensions: &[
+        ExtensionSpec { name: "heartbeat", description: "keepalive bytes between frames", option: "--heartbeat-byte" },
+        ExtensionSpec { name: "lenient", description: "frames with a bad LRC accepted", option: "--lenient" },
+        ExtensionSpec { name: "ack", description: "ACK or ERR <reason> line answering every TCP frame", option: "--ack" },
+        ExtensionSpec { name: "auth", description: "AUTH <token> line before the frames of a TCP connection; HMAC-SHA256 of the token after each UDP datagram", option: "--auth-token" },
+        ExtensionSpec { name: "udp", description: "frames in UDP datagrams, next to TCP", option: "--udp-addr" },
+    ],
+};
+
+/// Apply a console frame to `state`: the fields its message type carries are overwritten.
+///
+/// Returns whether the frame was a known message with enough data; unknown and short
+/// messages are logged and leave `state` alone.
+///
+/// ```
+/// use scoreboard_rust::basketball_parser::{apply_frame, GameState, ProtocolFrame};
+///
+/// let mut state = GameState::default();
+/// assert!(apply_frame(&ProtocolFrame::new(0x7F, 0x20, b"305 45 38".to_vec()), &mut state));
+/// assert_eq!((state.home_score.as_str(), state.away_score.as_str()), (" 45", " 38"));
+/// ```
+pub fn apply_frame(frame: &ProtocolFrame, state: &mut GameState) -> bool {
+    // Ensure there's enough data to read the message type
+    if frame.message.len() < 2 {
+        warn!("Message too short to determine type");
+        return false;
+    }
+
+    // First two bytes of the message indicate the message type
+    let Some(spec) = MESSAGES.iter().find(|spec| frame.message.starts_with(spec.id.as_bytes())) else {
+        warn!(
+            "Unknown message type: 0x{:02X} 0x{:02X}",
+            frame.message[0], frame.message[1]
+        );
+        return false;
+    };
+    debug!("Received Message Type {} ({})", spec.id, spec.name);
+    if frame.message.len() < spec.length {
+        warn!("Message Type {} too short", spec.id);
+        return false;
+    }
+    let message = Fields { spec, message: &frame.message };
+
+    match spec.id {
+        "18" => {
+            let status_word = StatusWord18::from_byte(message.byte("status"));
+
+            debug!(
+                "Status Word - Clock Type: {}, Game Clock Off: {}, Horn On: {}, Possession in Tenth: {}, New Match: {}, B7: {}",
+                status_word.clock_type,
+                status_word.game_clock_off,
+                status_word.horn_on,
+                status_word.possession_in_tenth,
+                status_word.new_match,
+                status_word.b7
+            );
+
+            state.horn = status_word.horn_on;
+
+            if status_word.game_clock_off {
+                debug!("Game Clock is OFF");
+                state.game_state = "paused".to_string();
+            } else {
+                debug!("Game Clock is ON");
+                state.game_state = "running".to_string();
+            }
+
+            // In tenths the minutes carry the seconds and the second seconds byte the tenths
+            let minutes = message.text("minutes");
+            state.time = if status_word.possession_in_tenth {
+                format!("{}.{}", minutes, message.bytes("seconds")[1] as char)
+            } else {
+                format!("{}:{}", minutes, message.text("seconds"))
+            };
+            debug!("{}", state.time);
+
+            state.home_timeouts = message.text("home_timeouts");
+            state.away_timeouts = message.text("away_timeouts");
+            let period_char = message.byte("period") as char;
+            debug!(
+                "Home Time-outs: {}, Guest Time-outs: {}, Period: {}",
+                state.home_timeouts, state.away_timeouts, period_char
+            );
+            state.period_name = match period_char {
+                'O' => "Overtime".to_string(),
+                '1' | '2' | '3' | '4' => format!("{} Quarter", period_char),
+                _ => String::new(),
+            };
+        }
+
+        "30" => {
+            state.home_score = message.text("home_score");
+            state.away_score = message.text("away_score");
+            debug!("Home Score: {}, Guest Score: {}", state.home_score, state.away_score);
+        }
+
+        "31" => {
+            state.home_fouls = message.text("home_fouls");
+            state.away_fouls = message.text("away_fouls");
+            debug!(
+                "Home Fouls: {}, Guest Fouls: {}, Player on Line: {}, Faults of Player: {}, Team of Player: {}",
+                state.home_fouls,
+                state.away_fouls,
+                message.text("player"),
+                message.text("player_fouls"),
+                message.text("player_team")
+            );
+        }
+
+        "36" => {
+            state.time = format!("{}.{}", message.text("seconds"), message.text("tenths"));
+        }
+
+        "50" => {
+            let status_word = StatusWord50::from_byte(message.byte("status"));
+            // Bit 1 is 0 while the shot clock runs
+            state.shot_clock_running = !status_word.status_possession_timer;
+
+            let seconds = message.bytes("seconds");
+            if status_word.status_of_shot_clock {
+                // Blanked by the operator, e.g. on a dead ball
+                debug!("Shot Clock blanked");
+                state.shot_clock = String::new();
+            } else if status_word.possession_timer_in_tenths {
+                state.shot_clock = format!("{}.{}", seconds[0] as char, seconds[1] as char);
+                debug!("Shot Clock Time: {}", state.shot_clock);
+            } else {
+                state.shot_clock = message.text("seconds");
+                debug!("Shot Clock Time: {}", state.shot_clock);
+            }
+        }
+
+        _ => {
+            warn!("Message Type {} is listed but not read", spec.id);
+            return false;
+        }
+    }
+
+    true
+}
@@ -0,0 +1,237 @@
+//! Captures of the console sessions: the bytes of every read as a bracketed hex list per line,
+//! `[01, 7F, 02, ...]`, in `data_log/session-<secs>.<millis>.log`.
+//!
+//! A session's capture can be split into parts (`session-<secs>.<millis>.2.log`, `.3`...) at a
+//! size and with every new game. With `--capture-compress` the parts that are done are gzipped
+//! in the background (`.log.gz`), which [`read_capture`] reads back as they were; with
+//! `--capture-keep` and `--capture-max-age` the oldest captures are deleted.
+
+use crate::{config::CaptureConfig, state::lock};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::{
+    collections::BTreeSet,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{info, warn};
+
+/// Parts being written, which retention leaves alone however old they are.
+static OPEN_PARTS: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// `session-1700000000.123` part 1 -> `session-1700000000.123.log`, part 2 -> `session-1700000000.123.2.log`.
+fn part_path(dir: &Path, session: &str, part: u32) -> PathBuf {
+    match part {
+        1 => dir.join(format!("{}.log", session)),
+        _ => dir.join(format!("{}.{}.log", session, part)),
+    }
+}
+
+/// `session-1700000000.123.2.log.gz` -> `(1700000000, 123, 2)`: the capture's order.
+fn capture_key(path: &Path) -> Option<(u64, u32, u32)> {
+    let name = path.file_name()?.to_str()?;
+    let name = name.strip_suffix(".gz").unwrap_or(name);
+    let mut fields = name.strip_prefix("session-")?.strip_suffix(".log")?.split('.');
+    let secs = fields.next()?.parse().ok()?;
+    let millis = fields.next()?.parse().ok()?;
+    let part = fields.next().map_or(Some(1), |part| part.parse().ok())?;
+    fields.next().is_none().then_some((secs, millis, part))
+}
+
+fn open(path: &Path) -> io::Result<File> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    lock(&OPEN_PARTS).insert(path.to_path_buf());
+    Ok(file)
+}
+
+/// The capture of one console session, written as it is read.
+///
+/// The part being written is synced when it is done (a new part starts, or the capture is
+/// dropped with the session); it is then compressed and the old captures deleted as the
+/// [`CaptureConfig`] asks, on a thread of their own.
+pub struct CaptureLog {
+    config: CaptureConfig,
+    /// `session-<secs>.<millis>`, from the time the session started.
+    session: String,
+    part: u32,
+    /// Taken when the capture is dropped, to be closed before it is compressed.
+    out: Option<File>,
+    size: u64,
+}
+
+impl CaptureLog {
+    /// Start the capture of a session in the capture directory, creating it if needed.
+    pub fn start(config: &CaptureConfig) -> io::Result<Self> {
+        fs::create_dir_all(&config.dir)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let session = format!("session-{}.{}", now.as_secs(), now.subsec_millis());
+        let out = open(&part_path(&config.dir, &session, 1))?;
+        // Old captures go now rather than when this one is done, hours from now
+        clean_up(config.clone(), None);
+        Ok(Self {
+            config: config.clone(),
+            session,
+            part: 1,
+            out: Some(out),
+            size: 0,
+        })
+    }
+
+    /// The part being written.
+    pub fn path(&self) -> PathBuf {
+        part_path(&self.config.dir, &self.session, self.part)
+    }
+
+    /// Append the bytes of one read as a line, in a new part if the line would take this one
+    /// over the size limit.
+    pub fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let line = format!("{:02X?}\n", bytes);
+        if self.size > 0 && self.config.max_size.is_some_and(|max| self.size + line.len() as u64 > max) {
+            self.roll()?;
+        }
+        self.out.as_mut().expect("open until dropped").write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Finish the part being written and go on in the next one (`--capture-per-game`).
+    pub fn roll(&mut self) -> io::Result<()> {
+        let next = part_path(&self.config.dir, &self.session, self.part + 1);
+        let done = self.out.replace(open(&next)?);
+        self.finish_part(done);
+        self.part += 1;
+        self.size = 0;
+        info!("Capture continued in {}", next.display());
+        Ok(())
+    }
+
+    fn finish_part(&self, out: Option<File>) {
+        let path = self.path();
+        if let Some(Err(e)) = out.map(|out| out.sync_data()) {
+            warn!("Failed to sync {}: {}", path.display(), e);
+        }
+        lock(&OPEN_PARTS).remove(&path);
+        clean_up(self.config.clone(), Some(path));
+    }
+}
+
+impl Drop for CaptureLog {
+    fn drop(&mut self) {
+        let out = self.out.take();
+        self.finish_part(out);
+    }
+}
+
+/// Compress the part just done and delete old captures, as `config` asks, on a new thread.
+fn clean_up(config: CaptureConfig, done: Option<PathBuf>) {
+    let compress = done.filter(|_| config.compress);
+    if compress.is_none() && config.keep.is_none() && config.max_age.is_none() {
+        return;
+    }
+    let spawned = thread::Builder::new().name("capture-cleanup".to_string()).spawn(move || {
+        if let Some(path) = compress {
+            if let Err(e) = compress_capture(&path) {
+                warn!("Failed to compress {}: {}", path.display(), e);
+            }
+        }
+        if let Err(e) = prune(&config) {
+            warn!("Failed to delete old captures in {}: {}", config.dir.display(), e);
+        }
+    });
+    if let Err(e) = spawned {
+        warn!("Failed to start the capture clean-up: {}", e);
+    }
+}
+
+/// Gzip the capture at `path` to `<path>.gz`, with the same modification time, and delete it.
+pub fn compress_capture(path: &Path) -> io::Result<PathBuf> {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".gz");
+    let gz = PathBuf::from(name);
+    // Under another name until it is complete, so it is never read half written
+    let partial = gz.with_extension("gz.part");
+    let written = (|| {
+        let mut input = File::open(path)?;
+        let modified = input.metadata()?.modified()?;
+        let mut encoder = GzEncoder::new(File::create(&partial)?, Compression::default());
+        io::copy(&mut input, &mut encoder)?;
+        let out = encoder.finish()?;
+        out.set_modified(modified)?;
+        out.sync_all()?;
+        fs::rename(&partial, &gz)
+    })();
+    if let Err(e) = written {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    fs::remove_file(path)?;
+    Ok(gz)
+}
+
+/// The captures in `dir`, compressed or not, oldest first; other files are left out.
+pub fn captures(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut captures = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if let Some(key) = capture_key(&path) {
+            captures.push((key, path));
+        }
+    }
+    captures.sort();
+    Ok(captures.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Delete the oldest captures of the capture directory beyond `config.keep`, and those last
+/// written longer ago than `config.max_age`. Parts still being written are kept. Every file
+/// deleted is logged and returned.
+pub fn prune(config: &CaptureConfig) -> io::Result<Vec<PathBuf>> {
+    let captures = captures(&config.dir)?;
+    let excess = config.keep.map_or(0, |keep| captures.len().saturating_sub(keep));
+    let open = lock(&OPEN_PARTS).clone();
+    let now = SystemTime::now();
+    let mut deleted = Vec::new();
+    for (index, path) in captures.into_iter().enumerate() {
+        if open.contains(&path) {
+            continue;
+        }
+        let reason = if index < excess {
+            "more than --capture-keep"
+        } else if config.max_age.is_some_and(|max_age| {
+            let modified = fs::metadata(&path).and_then(|meta| meta.modified());
+            modified.is_ok_and(|modified| now.duration_since(modified).is_ok_and(|age| age > max_age))
+        }) {
+            "older than --capture-max-age"
+        } else {
+            continue;
+        };
+        match fs::remove_file(&path) {
+            Ok(()) => {
+                info!("Deleted capture {} ({})", path.display(), reason);
+                deleted.push(path);
+            }
+            // Deleted by another session's clean-up
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to delete capture {}: {}", path.display(), e),
+        }
+    }
+    Ok(deleted)
+}
+
+/// The text of the capture at `path`, gunzipped when its name ends in `.gz`.
+pub fn read_capture(path: &Path) -> io::Result<String> {
+    if path.extension().is_some_and(|extension| extension == "gz") {
+        let mut text = String::new();
+        GzDecoder::new(File::open(path)?).read_to_string(&mut text)?;
+        Ok(text)
+    } else {
+        fs::read_to_string(path)
+    }
+}
@@ -0,0 +1,410 @@
+use crate::{
+    apply_frame, capture,
+    events::{self, clock_tenths},
+    framing::FrameDecoder,
+    game_log::write_record,
+    cli::replay::Capture,
+    line_score, logging, numeric_value,
+    summary::{GameSummary, SummaryTracker, FINAL_PERIOD},
+    teams::TeamInfo,
+    timestamp::UtcDateTime,
+    timezone::TimeZone,
+    GameState,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use tracing::{error, info, warn};
+
+/// One game of the season.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameLine {
+    /// Name of the file the game was read from.
+    pub file: String,
+    /// Unix milliseconds of the tip-off; that of the whole file for a capture, which records no times.
+    pub started_at_ms: u64,
+    /// Team abbreviations (or names); `HOME` and `AWAY` when the operator didn't enter them.
+    pub home: String,
+    pub away: String,
+    pub home_score: u32,
+    pub away_score: u32,
+    /// Team fouls of every period added up.
+    pub home_fouls: u32,
+    pub away_fouls: u32,
+    pub lead_changes: u32,
+    /// The game went to overtime.
+    pub overtime: bool,
+}
+
+impl GameLine {
+    fn new(file: &str, summary: &GameSummary) -> Option<Self> {
+        let team = |info: &TeamInfo, fallback: &str| {
+            [&info.abbrev, &info.name].into_iter().find(|name| !name.is_empty()).cloned().unwrap_or_else(|| fallback.to_string())
+        };
+        Some(Self {
+            file: file.to_string(),
+            started_at_ms: summary.started_at_ms,
+            home: team(&summary.teams.home, "HOME"),
+            away: team(&summary.teams.away, "AWAY"),
+            home_score: summary.home_score?,
+            away_score: summary.away_score?,
+            home_fouls: summary.home.fouls,
+            away_fouls: summary.away.fouls,
+            lead_changes: summary.lead_changes,
+            overtime: summary.line_score.iter().any(|period| period.period == "Overtime"),
+        })
+    }
+
+    /// `2026-03-14 19:05 UTC  CAB 80 - 74 SLB  (OT)`
+    pub fn line(&self) -> String {
+        let started = if self.started_at_ms == 0 {
+            "date unknown".to_string()
+        } else {
+            format!("{} UTC", &UtcDateTime::from_unix_millis(self.started_at_ms).spreadsheet()[..16])
+        };
+        format!(
+            "{}  {} {} - {} {}{}",
+            started,
+            self.home,
+            self.home_score,
+            self.away_score,
+            self.away,
+            if self.overtime { "  (OT)" } else { "" }
+        )
+    }
+}
+
+/// A team's totals over the season.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamSeason {
+    pub team: String,
+    pub games: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub points_for: u32,
+    pub points_against: u32,
+    /// Team fouls per game.
+    pub average_fouls: f64,
+    /// Games that went to overtime.
+    pub overtimes: u32,
+    #[serde(skip)]
+    fouls: u32,
+}
+
+/// A file, or a game in it, left out of the totals, and why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Skipped {
+    pub file: String,
+    pub reason: String,
+}
+
+/// What `aggregate` prints: the team totals, best record first, then every game in the
+/// order played, then what was left out.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Season {
+    pub teams: Vec<TeamSeason>,
+    pub games: Vec<GameLine>,
+    pub skipped: Vec<Skipped>,
+}
+
+impl Season {
+    /// The teams table, an empty line, then the games table.
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        write_record(out, &["team", "games", "wins", "losses", "points_for", "points_against", "average_fouls", "overtimes"])?;
+        for team in &self.teams {
+            let numbers = [team.games, team.wins, team.losses, team.points_for, team.points_against].map(|n| n.to_string());
+            let average_fouls = format!("{:.1}", team.average_fouls);
+            let overtimes = team.overtimes.to_string();
+            let mut fields = vec![team.team.as_str()];
+            fields.extend(numbers.iter().map(String::as_str));
+            fields.extend([average_fouls.as_str(), overtimes.as_str()]);
+            write_record(out, &fields)?;
+        }
+        write!(out, "\r\n")?;
+        write_record(
+            out,
+            &["started_utc", "home", "away", "home_score", "away_score", "home_fouls", "away_fouls", "lead_changes", "overtime", "file"],
+        )?;
+        for game in &self.games {
+            let started = if game.started_at_ms == 0 {
+                String::new()
+            } else {
+                UtcDateTime::from_unix_millis(game.started_at_ms).spreadsheet()
+            };
+            let numbers = [game.home_score, game.away_score, game.home_fouls, game.away_fouls, game.lead_changes].map(|n| n.to_string());
+            let mut fields = vec![started.as_str(), game.home.as_str(), game.away.as_str()];
+            fields.extend(numbers.iter().map(String::as_str));
+            fields.extend([if game.overtime { "yes" } else { "no" }, game.file.as_str()]);
+            write_record(out, &fields)?;
+        }
+        Ok(())
+    }
+}
+
+/// Plays the states of a recording through the server's own event diff, line score and
+/// game summary, as if they were being published again.
+#[derive(Default)]
+struct Rebuild {
+    last: GameState,
+    last_at: u64,
+    tracker: SummaryTracker,
+    games: Vec<GameSummary>,
+}
+
+impl Rebuild {
+    fn push(&mut self, mut state: GameState, timestamp_ms: u64) {
+        state.line_score = line_score::update(&self.last.line_score, &self.last, &state);
+        let events = events::diff(&self.last, &state);
+        // The season is written in UTC, only its times are used
+        if let Some(summary) = self.tracker.observe(&state, &events, timestamp_ms, &TimeZone::utc()) {
+            self.games.push(summary.clone());
+        }
+        self.last = state;
+        self.last_at = timestamp_ms;
+    }
+
+    /// The finished games. A game still open at the end of the recording counts as finished
+    /// if its last quarter or overtime ran out on a score that isn't tied: consoles never send
+    /// `Final`, only the operator does. Any other open game is returned as the error.
+    fn finish(mut self) -> (Vec<GameSummary>, Option<String>) {
+        if !self.tracker.in_progress() {
+            return (self.games, None);
+        }
+        let last = &self.last;
+        let period = last.period_name.trim();
+        let (home, away) = (numeric_value(&last.home_score), numeric_value(&last.away_score));
+        let ran_out = (period == "4 Quarter" || period == "Overtime") && clock_tenths(&last.time) == Some(0);
+        if ran_out && home.is_some() && home != away {
+            let mut over = last.clone();
+            over.period_name = FINAL_PERIOD.to_string();
+            self.push(over, self.last_at);
+            return (self.games, None);
+        }
+        let incomplete = format!(
+            "incomplete game: stops at {} in {} ({} - {})",
+            last.time.trim(),
+            if period.is_empty() { "no period" } else { period },
+            last.home_score.trim(),
+            last.away_score.trim()
+        );
+        (self.games, Some(incomplete))
+    }
+}
+
+/// The states of a JSON Lines file: a game recorded with `--games-dir` (its `snapshot` lines)
+/// or a `--log-jsonl` log (its `state` lines). Any line that isn't JSON makes the file corrupt.
+fn read_jsonl(text: &str, rebuild: &mut Rebuild) -> Result<(), String> {
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: Value = serde_json::from_str(line).map_err(|e| format!("line {}: {}", number + 1, e))?;
+        let timestamp_ms = match record["kind"].as_str() {
+            Some("snapshot") => record["timestampMs"].as_u64(),
+            Some("state") => record["ts"].as_str().and_then(UtcDateTime::parse_rfc3339),
+            _ => continue,
+        };
+        let state = serde_json::from_value(record["state"].clone()).map_err(|e| format!("line {}: {}", number + 1, e))?;
+        rebuild.push(state, timestamp_ms.unwrap_or_default());
+    }
+    Ok(())
+}
+
+/// The frames of a `data_log/` capture, decoded as the server would. The file name
+/// `session-<secs>.<millis>.log` gives the time of every state.
+fn read_capture(path: &Path, text: &str, rebuild: &mut Rebuild) -> Result<(), String> {
+    let capture = Capture::parse(text, 1.0)?;
+    let started_at_ms = path
+        .file_stem()
+        .and_then(|stem| stem.to_str()?.strip_prefix("session-")?.split_once('.'))
+        .and_then(|(secs, millis)| Some(secs.parse::<u64>().ok()? * 1000 + millis.parse::<u64>().ok()?))
+        .unwrap_or_default();
+
+    let mut decoder = FrameDecoder::new(false, &[0x00]);
+    let mut state = GameState::default();
+    for line in 0..capture.len() {
+        decoder.push(capture.bytes(line));
+        while let Some(frame) = decoder.next_frame() {
+            let Ok(frame) = frame else { continue };
+            let mut next = state.clone();
+            if apply_frame(&frame, &mut next) && next != state {
+                rebuild.push(next.clone(), started_at_ms);
+                state = next;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The games of a database recorded with `--games-db`, each rebuilt from its snapshots.
+#[cfg(feature = "sqlite")]
+fn read_db(path: &Path) -> io::Result<Vec<(u64, Rebuild)>> {
+    let db = crate::game_db::GameDb::open_read_only(path)?;
+    let mut games = Vec::new();
+    for row in db.list()? {
+        let mut rebuild = Rebuild::default();
+        for snapshot in db.game(row.id)?.map(|game| game.snapshots).unwrap_or_default() {
+            let state = serde_json::from_value(snapshot["state"].clone())?;
+            rebuild.push(state, snapshot["timestampMs"].as_u64().unwrap_or_default());
+        }
+        games.push((row.id, rebuild));
+    }
+    Ok(games)
+}
+
+impl Season {
+    /// Add the games `rebuild` finished from `file`, and skip the one it couldn't; `label`
+    /// names the game within the file in the reason.
+    fn add(&mut self, file: &str, label: &str, rebuild: Rebuild) {
+        let (games, incomplete) = rebuild.finish();
+        let reason = match incomplete {
+            Some(incomplete) => Some(incomplete),
+            None if games.is_empty() => Some("no game in it".to_string()),
+            None => None,
+        };
+        if let Some(reason) = reason {
+            self.skipped.push(Skipped { file: file.to_string(), reason: format!("{}{}", label, reason) });
+        }
+        self.games.extend(games.iter().filter_map(|summary| GameLine::new(file, summary)));
+    }
+}
+
+/// Rebuild every game recorded in the files of `dir`: games recorded with `--games-dir` and
+/// `--log-jsonl` logs (`.jsonl`), `data_log/` captures (`.log`, or `.log.gz` compressed) and,
+/// in builds with the `sqlite` feature, `--games-db` databases (`.db`, `.sqlite`, `.sqlite3`).
+/// Files that can't be read and games that didn't finish are listed as skipped; other files
+/// are ignored.
+pub fn aggregate(dir: &Path) -> io::Result<Season> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?.map(|entry| entry.map(|entry| entry.path())).collect::<io::Result<_>>()?;
+    paths.sort();
+
+    let mut season = Season::default();
+    for path in paths {
+        let file = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        // A gzipped capture (`--capture-compress`) is read as the capture it holds
+        let unzipped = file.strip_suffix(".gz").filter(|name| name.ends_with(".log")).map(Path::new);
+        let extension = unzipped.unwrap_or(&path).extension().and_then(|extension| extension.to_str()).unwrap_or_default().to_ascii_lowercase();
+        let mut skip = |reason: String| season.skipped.push(Skipped { file: file.clone(), reason });
+        if matches!(extension.as_str(), "db" | "sqlite" | "sqlite3") {
+            #[cfg(feature = "sqlite")]
+            match read_db(&path) {
+                Ok(games) => {
+                    for (id, rebuild) in games {
+                        season.add(&file, &format!("game {}: ", id), rebuild);
+                    }
+                }
+                Err(e) => skip(format!("corrupt: {}", e)),
+            }
+            #[cfg(not(feature = "sqlite"))]
+            skip("SQLite files need a build with the sqlite feature (cargo build --features sqlite)".to_string());
+            continue;
+        }
+        if extension != "jsonl" && extension != "log" {
+            continue;
+        }
+        let text = match capture::read_capture(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                skip(e.to_string());
+                continue;
+            }
+        };
+
+        let mut rebuild = Rebuild::default();
+        let read = match extension.as_str() {
+            "jsonl" => read_jsonl(&text, &mut rebuild),
+            _ => read_capture(&path, &text, &mut rebuild),
+        };
+        if let Err(e) = read {
+            skip(format!("corrupt: {}", e));
+            continue;
+        }
+        season.add(&file, "", rebuild);
+    }
+    season.games.sort_by_key(|game| game.started_at_ms);
+
+    let mut teams: BTreeMap<String, TeamSeason> = BTreeMap::new();
+    for game in &season.games {
+        let sides = [
+            (&game.home, game.home_score, game.away_score, game.home_fouls),
+            (&game.away, game.away_score, game.home_score, game.away_fouls),
+        ];
+        for (team, scored, conceded, fouls) in sides {
+            let totals = teams.entry(team.clone()).or_insert_with(|| TeamSeason { team: team.clone(), ..TeamSeason::default() });
+            totals.games += 1;
+            if scored > conceded {
+                totals.wins += 1;
+            } else {
+                totals.losses += 1;
+            }
+            totals.points_for += scored;
+            totals.points_against += conceded;
+            totals.fouls += fouls;
+            totals.overtimes += u32::from(game.overtime);
+        }
+    }
+    season.teams = teams.into_values().collect();
+    for team in &mut season.teams {
+        team.average_fouls = f64::from(team.fouls) / f64::from(team.games);
+    }
+    // Best record first; names settle ties, as the map had them
+    season.teams.sort_by(|a, b| b.wins.cmp(&a.wins).then(a.losses.cmp(&b.losses)));
+    Ok(season)
+}
+
+/// The arguments of `scoreboard-rust aggregate`.
+#[derive(Debug, Clone, clap::Args)]
+pub struct AggregateArgs {
+    /// Directory of the games recorded with `--games-dir` or `--games-db` and the captures of `data_log/`.
+    #[arg(value_name = "DIR")]
+    dir: PathBuf,
+    /// Print the team totals as CSV instead of the season as JSON.
+    #[arg(long)]
+    csv: bool,
+}
+
+/// Entry point of `scoreboard-rust aggregate <dir> [--csv]`; returns the process exit code.
+pub fn run_command(args: &AggregateArgs) -> i32 {
+    let AggregateArgs { dir, csv } = args;
+    let season = {
+        // The parser logs every frame it decodes, far too much for a season of captures
+        let _quiet = logging::quiet("error");
+        aggregate(dir)
+    };
+    let season = match season {
+        Ok(season) => season,
+        Err(e) => {
+            error!("Failed to read {}: {}", dir.display(), e);
+            return 1;
+        }
+    };
+    for game in &season.games {
+        info!("{}", game.line());
+    }
+    for skipped in &season.skipped {
+        warn!("Skipped {}: {}", skipped.file, skipped.reason);
+    }
+
+    let mut out = io::stdout().lock();
+    let written = if *csv {
+        season.write_csv(&mut out)
+    } else {
+        serde_json::to_writer_pretty(&mut out, &season).map_err(io::Error::from).and_then(|()| writeln!(out))
+    };
+    match written {
+        Ok(()) => 0,
+        // Piped into `head` or a pager that was closed
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => 0,
+        Err(e) => {
+            error!("Failed to write the season: {}", e);
+            1
+        }
+    }
+}
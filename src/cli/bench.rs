@@ -0,0 +1,306 @@
+use crate::{
+    cli::{send::parse_duration, simulate::RandomFrames},
+    metrics::percentile,
+    rules::RuleSet,
+};
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader, Write},
+    net::{Shutdown, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tracing::{error, info};
+
+/// Settings for `scoreboard-rust bench`.
+#[derive(Debug, Clone, clap::Args)]
+pub struct BenchOptions {
+    /// Ingest address of the server, started with `--ack`.
+    #[arg(long, value_name = "HOST:PORT", default_value = "127.0.0.1:4001", value_parser = host_port)]
+    pub target: String,
+    /// Connections sending at once, each playing its own random game.
+    #[arg(long, value_name = "N", default_value_t = 4, value_parser = at_least_one::<usize>)]
+    pub connections: usize,
+    /// Frames per second on each connection.
+    #[arg(long, value_name = "N", default_value_t = 10.0, value_parser = rate)]
+    pub rate: f64,
+    /// How long each connection sends (10s, 500ms).
+    #[arg(long, value_name = "DURATION", default_value = "10s", value_parser = parse_duration)]
+    pub duration: Duration,
+    /// Frames each connection sends at most, when the duration isn't over first.
+    #[arg(long, value_name = "N", value_parser = at_least_one::<u64>)]
+    pub frames: Option<u64>,
+    /// How long to wait for the last replies once sending is over.
+    #[arg(long, value_name = "DURATION", default_value = "2s", value_parser = parse_duration)]
+    pub timeout: Duration,
+    /// Seed of the first connection's game; the others get the next ones.
+    #[arg(long, value_name = "N")]
+    pub seed: Option<u64>,
+    /// Print the report as JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+fn host_port(value: &str) -> Result<String, String> {
+    if !value.contains(':') {
+        return Err("expected host:port".to_string());
+    }
+    Ok(value.to_string())
+}
+
+fn at_least_one<T: std::str::FromStr + PartialOrd + From<u8>>(value: &str) -> Result<T, String> {
+    value.parse().ok().filter(|n| *n >= T::from(1)).ok_or_else(|| "expected at least 1".to_string())
+}
+
+fn rate(value: &str) -> Result<f64, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|rate: &f64| rate.is_finite() && *rate > 0.0)
+        .ok_or_else(|| "expected frames per second, above 0".to_string())
+}
+
+/// Ack latency percentiles, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Latency {
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl Latency {
+    fn from_durations(mut durations: Vec<Duration>) -> Option<Self> {
+        durations.sort_unstable();
+        let max = *durations.last()?;
+        let ms = |duration: Duration| (duration.as_secs_f64() * 1000.0 * 100.0).round() / 100.0;
+        Some(Self {
+            p50_ms: ms(percentile(&durations, 50)),
+            p90_ms: ms(percentile(&durations, 90)),
+            p99_ms: ms(percentile(&durations, 99)),
+            max_ms: ms(max),
+        })
+    }
+}
+
+/// What a bench run saw, all connections together.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchReport {
+    pub connections: usize,
+    /// Connections that could be opened.
+    pub connected: usize,
+    /// From the first connection to the last reply, in seconds.
+    pub elapsed_secs: f64,
+    pub sent: u64,
+    /// Frames answered with `ACK`.
+    pub acked: u64,
+    /// Frames answered with `ERR <reason>`: every frame sent is valid, so each one is a mismatch.
+    pub refused: u64,
+    /// Frames still without a reply when the wait was over.
+    pub unanswered: u64,
+    /// Reply lines that weren't `ACK` or `ERR`, or came without a frame to answer.
+    pub unexpected: u64,
+    /// Frames sent per second, all connections together.
+    pub rate: f64,
+    /// From writing a frame to reading its reply; `None` without replies.
+    pub latency: Option<Latency>,
+    /// Why connections failed.
+    pub errors: Vec<String>,
+}
+
+impl BenchReport {
+    /// Every connection opened and every frame was acknowledged.
+    pub fn passed(&self) -> bool {
+        self.connected == self.connections && self.sent > 0 && self.acked == self.sent && self.unexpected == 0
+    }
+
+    /// Write the report as a table.
+    pub fn write_table(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "Connections   {} of {} opened", self.connected, self.connections)?;
+        writeln!(out, "Sent          {} frames in {:.1}s ({:.1}/s)", self.sent, self.elapsed_secs, self.rate)?;
+        writeln!(out, "Acked         {}", self.acked)?;
+        writeln!(out, "Refused       {}", self.refused)?;
+        writeln!(out, "Unanswered    {}", self.unanswered)?;
+        writeln!(out, "Unexpected    {}", self.unexpected)?;
+        match &self.latency {
+            Some(latency) => writeln!(
+                out,
+                "Ack latency   p50 {:.2}ms  p90 {:.2}ms  p99 {:.2}ms  max {:.2}ms",
+                latency.p50_ms, latency.p90_ms, latency.p99_ms, latency.max_ms
+            )?,
+            None => writeln!(out, "Ack latency   no replies; is the server started with --ack?")?,
+        }
+        for error in &self.errors {
+            writeln!(out, "Error         {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+/// What one connection saw.
+#[derive(Debug, Default)]
+struct Connection {
+    connected: bool,
+    sent: u64,
+    acked: u64,
+    refused: u64,
+    unanswered: u64,
+    unexpected: u64,
+    latencies: Vec<Duration>,
+    error: Option<String>,
+}
+
+/// Replies read on one connection. The server answers every frame in order, so each line
+/// goes with the oldest frame still waiting.
+#[derive(Debug, Default)]
+struct Replies {
+    acked: u64,
+    refused: u64,
+    unexpected: u64,
+    latencies: Vec<Duration>,
+}
+
+fn read_replies(stream: TcpStream, waiting: &Mutex<VecDeque<Instant>>) -> Replies {
+    let mut replies = Replies::default();
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { break };
+        let received = Instant::now();
+        let Some(sent) = waiting.lock().unwrap().pop_front() else {
+            replies.unexpected += 1;
+            continue;
+        };
+        if line == "ACK" {
+            replies.acked += 1;
+        } else if line.starts_with("ERR") {
+            replies.refused += 1;
+        } else {
+            replies.unexpected += 1;
+            continue;
+        }
+        replies.latencies.push(received - sent);
+    }
+    replies
+}
+
+/// Send a random game's frames on one connection at `options.rate` until the duration or
+/// frame count is reached, then wait for the replies.
+fn run_connection(options: &BenchOptions, seed: u64) -> Connection {
+    let mut connection = Connection::default();
+    let stream = match TcpStream::connect(&options.target) {
+        Ok(stream) => stream,
+        Err(e) => {
+            connection.error = Some(format!("connect to {}: {}", options.target, e));
+            return connection;
+        }
+    };
+    connection.connected = true;
+    // Each frame goes out as it's written, so the latency isn't Nagle's
+    let _ = stream.set_nodelay(true);
+    let waiting = Arc::new(Mutex::new(VecDeque::new()));
+    let reader = match stream.try_clone() {
+        Ok(replies) => {
+            let waiting = Arc::clone(&waiting);
+            thread::spawn(move || read_replies(replies, &waiting))
+        }
+        Err(e) => {
+            connection.error = Some(e.to_string());
+            return connection;
+        }
+    };
+
+    let mut frames = RandomFrames::new(RuleSet::default(), seed);
+    let interval = Duration::from_secs_f64(1.0 / options.rate);
+    let started = Instant::now();
+    let mut next = started;
+    let mut writer = &stream;
+    while started.elapsed() < options.duration && options.frames.is_none_or(|frames| connection.sent < frames) {
+        // On schedule: a send that ran late is followed by the next one at once
+        if let Some(wait) = next.checked_duration_since(Instant::now()) {
+            thread::sleep(wait);
+        }
+        next += interval;
+        let frame = frames.next_frame();
+        waiting.lock().unwrap().push_back(Instant::now());
+        if let Err(e) = writer.write_all(&frame) {
+            waiting.lock().unwrap().pop_back();
+            connection.error = Some(format!("send: {}", e));
+            break;
+        }
+        connection.sent += 1;
+    }
+
+    let deadline = Instant::now() + options.timeout;
+    while !waiting.lock().unwrap().is_empty() && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(5));
+    }
+    let _ = stream.shutdown(Shutdown::Both);
+    let replies = reader.join().unwrap_or_default();
+    connection.unanswered = waiting.lock().unwrap().len() as u64;
+    connection.acked = replies.acked;
+    connection.refused = replies.refused;
+    connection.unexpected = replies.unexpected;
+    connection.latencies = replies.latencies;
+    connection
+}
+
+/// Run the connections of `options` at once and put their results together.
+pub fn run(options: &BenchOptions) -> BenchReport {
+    let seed = options.seed.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_nanos() as u64).unwrap_or_default());
+    let started = Instant::now();
+    let connections: Vec<Connection> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..options.connections).map(|i| scope.spawn(move || run_connection(options, seed.wrapping_add(i as u64)))).collect();
+        workers.into_iter().map(|worker| worker.join().unwrap_or_default()).collect()
+    });
+    let elapsed = started.elapsed().as_secs_f64();
+
+    let mut report = BenchReport { connections: options.connections, elapsed_secs: (elapsed * 10.0).round() / 10.0, ..BenchReport::default() };
+    let mut latencies = Vec::new();
+    for connection in connections {
+        report.connected += usize::from(connection.connected);
+        report.sent += connection.sent;
+        report.acked += connection.acked;
+        report.refused += connection.refused;
+        report.unanswered += connection.unanswered;
+        report.unexpected += connection.unexpected;
+        latencies.extend(connection.latencies);
+        report.errors.extend(connection.error);
+    }
+    report.rate = (report.sent as f64 / elapsed.max(f64::EPSILON) * 10.0).round() / 10.0;
+    report.latency = Latency::from_durations(latencies);
+    report
+}
+
+/// Entry point of `scoreboard-rust bench`; returns the process exit code: 0 when every frame
+/// was acknowledged, 1 otherwise, 2 for bad arguments.
+pub fn run_command(options: &BenchOptions) -> i32 {
+    info!(
+        "{} connections to {} at {}/s each for {:?}{}",
+        options.connections,
+        options.target,
+        options.rate,
+        options.duration,
+        options.frames.map(|frames| format!(" or {} frames", frames)).unwrap_or_default()
+    );
+    let report = run(options);
+
+    let mut out = io::stdout().lock();
+    let written = if options.json {
+        serde_json::to_writer_pretty(&mut out, &report).map_err(io::Error::from).and_then(|()| writeln!(out))
+    } else {
+        report.write_table(&mut out)
+    };
+    if let Err(e) = written {
+        if e.kind() != io::ErrorKind::BrokenPipe {
+            error!("Failed to write the report: {}", e);
+        }
+    }
+    if report.passed() {
+        0
+    } else {
+        1
+    }
+}
@@ -0,0 +1,383 @@
+use crate::{
+    apply_frame,
+    cli::{
+        replay,
+        send::{clock_frame, fouls_frame, scores_frame, ClockTime},
+        validate::parse_hex_line,
+    },
+    framing::FrameDecoder,
+    logging, numeric_value, parse_raw_data, GameState, ProtocolFrame,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use tracing::{error, info, warn};
+
+/// Address and control bytes of frames given by their message alone, as `send` builds them.
+const DEFAULT_ADDRESS: u8 = 0x7F;
+const DEFAULT_CTRL: u8 = b'G';
+
+/// What `convert` reads and writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Frames as on the wire, back to back (`nc -l 4001 > game.bin`).
+    #[value(alias = "raw")]
+    Frames,
+    /// A `data_log/` capture: a bracketed hex list per line, `[01, 7F, 02, ...]`. Written one frame per line.
+    Capture,
+    /// Hex text, `01 7F 02` or `017F02`, with `#` comments. Written one frame per line.
+    Hex,
+    /// One [`FrameRecord`] per line. Read, a line may also hold a game state instead.
+    Jsonl,
+    /// The same records, indented.
+    Json,
+}
+
+/// Where a record is in the input: a byte offset in frames and JSON, a line in the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    Offset(u64),
+    Line(u64),
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Offset(offset) => write!(f, "offset {}", offset),
+            Self::Line(line) => write!(f, "line {}", line),
+        }
+    }
+}
+
+/// A frame as JSON, with the game state once it's applied.
+///
+/// The message keeps one character per byte (Latin-1), so the status bytes of message 18
+/// survive the trip and the frame converts back to the same bytes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrameRecord {
+    /// Where the frame starts in the input, for frames and JSON.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    /// The line of the input the frame ends on, for captures, hex and JSON Lines.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u64>,
+    pub address: u8,
+    pub ctrl: u8,
+    /// Message type, the message's first two characters (`"30"`).
+    #[serde(rename = "type")]
+    pub message_type: String,
+    pub message: String,
+    /// LRC as transmitted.
+    pub lrc: u8,
+    pub state: GameState,
+}
+
+/// Settings for `scoreboard-rust convert`.
+#[derive(Debug, Clone, clap::Args)]
+pub struct ConvertOptions {
+    /// Format of the input.
+    #[arg(long, value_name = "FORMAT")]
+    pub from: Format,
+    /// Format of the output.
+    #[arg(long, value_name = "FORMAT")]
+    pub to: Format,
+    /// Input file; standard input when missing or `-`.
+    pub input: Option<PathBuf>,
+    /// Output file; standard output when missing or `-`.
+    pub output: Option<PathBuf>,
+    /// Keep frames whose LRC does not match, with their LRC.
+    #[arg(long)]
+    pub lenient: bool,
+}
+
+/// `path`, unless it stands for standard input or output.
+fn file(path: &Option<PathBuf>) -> Option<&Path> {
+    path.as_deref().filter(|path| *path != Path::new("-"))
+}
+
+/// What a conversion did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConvertSummary {
+    /// Frames written.
+    pub frames: u64,
+    /// Records that couldn't be converted: invalid frames, lines that aren't hex or JSON.
+    pub errors: u64,
+    /// Bytes outside any frame, skipped.
+    pub skipped_bytes: u64,
+    /// JSON records with neither a frame nor a state, such as a game file's header.
+    pub skipped_records: u64,
+}
+
+/// Writes frames in the output format, applying them to the game state for the JSON records.
+struct Converter<'a, W: Write> {
+    to: Format,
+    out: &'a mut W,
+    lenient: bool,
+    state: GameState,
+    summary: ConvertSummary,
+}
+
+impl<W: Write> Converter<'_, W> {
+    fn frame(&mut self, frame: &ProtocolFrame, position: Position) -> io::Result<()> {
+        let bytes = frame.to_bytes();
+        match self.to {
+            Format::Frames => self.out.write_all(&bytes)?,
+            Format::Capture => writeln!(self.out, "{:02X?}", bytes)?,
+            Format::Hex => writeln!(self.out, "{}", bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<Vec<_>>().join(" "))?,
+            Format::Jsonl | Format::Json => {
+                apply_frame(frame, &mut self.state);
+                let record = FrameRecord {
+                    offset: match position {
+                        Position::Offset(offset) => Some(offset),
+                        Position::Line(_) => None,
+                    },
+                    line: match position {
+                        Position::Line(line) => Some(line),
+                        Position::Offset(_) => None,
+                    },
+                    address: frame.address,
+                    ctrl: frame.ctrl,
+                    message_type: frame.message.iter().take(2).map(|&byte| byte as char).collect(),
+                    message: frame.message.iter().map(|&byte| byte as char).collect(),
+                    lrc: frame.lrc,
+                    state: self.state.clone(),
+                };
+                if self.to == Format::Json {
+                    serde_json::to_writer_pretty(&mut *self.out, &record)?;
+                } else {
+                    serde_json::to_writer(&mut *self.out, &record)?;
+                }
+                writeln!(self.out)?;
+            }
+        }
+        self.summary.frames += 1;
+        Ok(())
+    }
+
+    fn error(&mut self, position: Position, message: impl fmt::Display) {
+        warn!("{}: {}", position, message);
+        self.summary.errors += 1;
+    }
+
+    /// Decode the frames of a byte stream, read in chunks or by line.
+    fn decoded(&mut self, decoder: &mut FrameDecoder, bytes: &[u8], position: impl Fn(&FrameDecoder) -> Position) -> io::Result<()> {
+        decoder.push(bytes);
+        while let Some(frame) = decoder.next_frame() {
+            match frame {
+                Ok(frame) => self.frame(&frame, position(decoder))?,
+                Err(e) => self.error(position(decoder), e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert the frames of a JSON record: its own, or those that set its game state.
+    fn record(&mut self, value: &Value, position: Position, previous: &mut Option<GameState>) -> io::Result<()> {
+        match record_frames(value, previous, self.lenient) {
+            Ok(frames) if frames.is_empty() => self.summary.skipped_records += 1,
+            Ok(frames) => {
+                for frame in frames {
+                    self.frame(&frame, position)?;
+                }
+            }
+            Err(e) => self.error(position, e),
+        }
+        Ok(())
+    }
+}
+
+/// The frames of a JSON record: a [`FrameRecord`] (only `message` is required), or a game
+/// state, bare as `dump` prints it or under `state` as in `--log-jsonl` logs and `--games-dir`
+/// files, given as the frames that change what `previous` showed.
+fn record_frames(value: &Value, previous: &mut Option<GameState>, lenient: bool) -> Result<Vec<ProtocolFrame>, String> {
+    let byte = |key: &str, default: u8| match &value[key] {
+        Value::Null => Ok(default),
+        value => value.as_u64().and_then(|n| u8::try_from(n).ok()).ok_or_else(|| format!("{} must be a byte, not {}", key, value)),
+    };
+    if let Some(message) = value.get("message") {
+        let message = message.as_str().ok_or("message must be a string")?;
+        let message = message
+            .chars()
+            .map(|c| u8::try_from(c).map_err(|_| format!("message character {:?} is not a byte", c)))
+            .collect::<Result<Vec<u8>, _>>()?;
+        let mut frame = ProtocolFrame::new(byte("address", DEFAULT_ADDRESS)?, byte("ctrl", DEFAULT_CTRL)?, message);
+        frame.lrc = byte("lrc", frame.lrc)?;
+        if !frame.validate_lrc() && !lenient {
+            return Err(format!("LRC validation failed (expected {:02X}, got {:02X})", frame.expected_lrc(), frame.lrc));
+        }
+        return Ok(vec![frame]);
+    }
+
+    let state = match value.get("state") {
+        Some(state) if state.is_object() => state,
+        _ if value.get("home_score").is_some() => value,
+        _ => return Ok(Vec::new()),
+    };
+    let state: GameState = serde_json::from_value(state.clone()).map_err(|e| format!("Invalid state: {}", e))?;
+    let frames = state_frames(&state, previous.as_ref())
+        .iter()
+        .map(|bytes| parse_raw_data(bytes, false))
+        .collect::<Result<_, _>>()?;
+    *previous = Some(state);
+    Ok(frames)
+}
+
+/// Frames a console would send to go from `previous` to `state`: the clock (message 18),
+/// scores (30) and fouls (31) that changed, or all of them for the first state. Fields the
+/// console never sent (`"-"`) aren't sent.
+fn state_frames(state: &GameState, previous: Option<&GameState>) -> Vec<Vec<u8>> {
+    let clock = |state: &GameState| (state.time.clone(), state.game_state.clone(), state.period_name.clone(), state.home_timeouts.clone(), state.away_timeouts.clone());
+    let scores = |state: &GameState| (state.home_score.clone(), state.away_score.clone());
+    let fouls = |state: &GameState| (state.home_fouls.clone(), state.away_fouls.clone());
+    let mut frames = Vec::new();
+
+    if let Some(time) = clock_time(&state.time).filter(|_| previous.is_none_or(|previous| clock(previous) != clock(state))) {
+        let period = match state.period_name.as_str() {
+            "Overtime" => b'O',
+            name => name.strip_suffix(" Quarter").and_then(|digit| digit.bytes().next()).unwrap_or(b' '),
+        };
+        let display = |value: &str| value.bytes().next().filter(u8::is_ascii_digit).unwrap_or(b' ');
+        frames.push(clock_frame(time, state.game_state == "running", period, (display(&state.home_timeouts), display(&state.away_timeouts))));
+    }
+
+    if let (Some(home), Some(away)) = (numeric_value(&state.home_score), numeric_value(&state.away_score)) {
+        if previous.is_none_or(|previous| scores(previous) != scores(state)) {
+            frames.push(scores_frame(home, away));
+        }
+    }
+
+    let digit = |value: &str| value.trim().bytes().next().filter(u8::is_ascii_digit);
+    if let (Some(home), Some(away)) = (digit(&state.home_fouls), digit(&state.away_fouls)) {
+        if previous.is_none_or(|previous| fouls(previous) != fouls(state)) {
+            frames.push(fouls_frame(home, away));
+        }
+    }
+    frames
+}
+
+/// `08:12` or `45.3` as the console's clock.
+fn clock_time(time: &str) -> Option<ClockTime> {
+    let number = |value: &str| value.trim().parse::<u32>().ok();
+    if let Some((minutes, seconds)) = time.split_once(':') {
+        Some(ClockTime::Minutes(number(minutes)?, number(seconds)?))
+    } else {
+        let (seconds, tenths) = time.split_once('.')?;
+        Some(ClockTime::Tenths(number(seconds)?, number(tenths)?))
+    }
+}
+
+/// Convert `input`, in the `from` format, to `to` on `out`. Records that can't be converted
+/// are logged with their position and counted; the rest of the input is still converted.
+/// Reads and writes as it goes, so a capture of any length takes little memory.
+pub fn convert<R: BufRead, W: Write>(mut input: R, out: &mut W, from: Format, to: Format, lenient: bool) -> io::Result<ConvertSummary> {
+    let mut converter = Converter { to, out, lenient, state: GameState::default(), summary: ConvertSummary::default() };
+    // Heartbeat bytes are skipped as bytes outside frames, which keeps the offsets right
+    let mut decoder = FrameDecoder::new(lenient, &[]);
+    let mut previous = None;
+
+    match from {
+        Format::Frames => {
+            let mut buffer = [0u8; 8192];
+            loop {
+                let n = match input.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(n) => n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                };
+                converter.decoded(&mut decoder, &buffer[..n], |decoder| Position::Offset(decoder.frame_offset()))?;
+            }
+        }
+        Format::Capture | Format::Hex | Format::Jsonl => {
+            let mut line = String::new();
+            let mut number = 0;
+            while input.read_line(&mut line)? > 0 {
+                number += 1;
+                let position = Position::Line(number);
+                match from {
+                    Format::Capture => match replay::parse_line(&line) {
+                        Ok(Some(bytes)) => converter.decoded(&mut decoder, &bytes, |_| position)?,
+                        Ok(None) => {}
+                        Err(e) => converter.error(position, e),
+                    },
+                    Format::Hex => match parse_hex_line(&line) {
+                        Ok(bytes) => converter.decoded(&mut decoder, &bytes, |_| position)?,
+                        Err(e) => converter.error(position, e),
+                    },
+                    _ if line.trim().is_empty() => {}
+                    _ => match serde_json::from_str::<Value>(&line) {
+                        Ok(value) => converter.record(&value, position, &mut previous)?,
+                        Err(e) => converter.error(position, e),
+                    },
+                }
+                line.clear();
+            }
+        }
+        Format::Json => {
+            let mut values = serde_json::Deserializer::from_reader(input).into_iter::<Value>();
+            loop {
+                let position = Position::Offset(values.byte_offset() as u64);
+                match values.next() {
+                    Some(Ok(value)) => converter.record(&value, position, &mut previous)?,
+                    // The rest can't be told apart from the broken value
+                    Some(Err(e)) => {
+                        converter.error(position, e);
+                        break;
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+
+    if decoder.buffered() > 0 {
+        converter.error(Position::Offset(decoder.frame_offset()), format!("{} bytes of an unfinished frame at the end", decoder.buffered()));
+    }
+    converter.summary.skipped_bytes = decoder.skipped();
+    converter.out.flush()?;
+    Ok(converter.summary)
+}
+
+fn open_input(options: &ConvertOptions) -> io::Result<Box<dyn BufRead>> {
+    Ok(match file(&options.input) {
+        Some(path) => Box::new(BufReader::new(File::open(path)?)),
+        None => Box::new(BufReader::new(io::stdin())),
+    })
+}
+
+/// Open the files in `options` and convert.
+pub fn run(options: &ConvertOptions) -> io::Result<ConvertSummary> {
+    let input = open_input(options)?;
+    let mut out: Box<dyn Write> = match file(&options.output) {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    // The parser's own warnings (every unknown message type) would drown the conversion errors
+    let _quiet = logging::quiet("info,scoreboard_rust::basketball_parser=error,scoreboard_rust::framing=error");
+    convert(input, &mut out, options.from, options.to, options.lenient)
+}
+
+/// Entry point of `scoreboard-rust convert`; returns the process exit code: 0 when every
+/// record converted, 1 when some couldn't be (the others are still written) or on I/O errors.
+pub fn run_command(options: &ConvertOptions) -> i32 {
+    match run(options) {
+        Ok(summary) => {
+            info!(
+                "Converted {} frames; {} errors, {} bytes outside frames, {} records without a frame or state skipped",
+                summary.frames, summary.errors, summary.skipped_bytes, summary.skipped_records
+            );
+            i32::from(summary.errors > 0)
+        }
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => 0,
+        Err(e) => {
+            error!("Convert failed: {}", e);
+            1
+        }
+    }
+}
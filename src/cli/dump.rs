@@ -0,0 +1,218 @@
+use crate::{apply_frame, events, framing::FrameDecoder, GameState};
+use serde::Serialize;
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{error, info, warn};
+
+/// Where `dump` reads the game from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DumpSource {
+    /// Subscribe to a server's `--subscribers` stream of JSON-line states.
+    Connect(String),
+    /// Wait for one connection that sends the feed, e.g. a server's `--forward` target.
+    Listen(String),
+}
+
+/// Settings for `scoreboard-rust dump`.
+#[derive(Debug, Clone)]
+pub struct DumpOptions {
+    pub source: DumpSource,
+    /// Write one line per game event instead of one per state.
+    pub events: bool,
+    /// Indent the JSON for reading instead of one object per line.
+    pub pretty: bool,
+    /// Accept frames whose LRC does not match.
+    pub lenient: bool,
+}
+
+/// The arguments of `scoreboard-rust dump`.
+#[derive(Debug, Clone, clap::Args)]
+pub struct DumpArgs {
+    /// Subscribe to the states a running server streams to its `--subscribers` address.
+    #[arg(long, value_name = "HOST:PORT", value_parser = host_port, required_unless_present = "listen", conflicts_with = "listen")]
+    connect: Option<String>,
+    /// Wait for one connection that sends the feed, e.g. a server's `--forward` target.
+    #[arg(long, value_name = "HOST:PORT", value_parser = host_port)]
+    listen: Option<String>,
+    /// Write one line per game event instead of one per state.
+    #[arg(long)]
+    events: bool,
+    /// Indent the JSON for reading instead of one object per line.
+    #[arg(long)]
+    pretty: bool,
+    /// Accept frames whose LRC does not match (with `--listen`).
+    #[arg(long, conflicts_with = "connect")]
+    lenient: bool,
+}
+
+impl From<DumpArgs> for DumpOptions {
+    fn from(args: DumpArgs) -> Self {
+        let source = match (args.connect, args.listen) {
+            (Some(address), _) => DumpSource::Connect(address),
+            (None, address) => DumpSource::Listen(address.unwrap_or_default()),
+        };
+        Self { source, events: args.events, pretty: args.pretty, lenient: args.lenient }
+    }
+}
+
+fn host_port(value: &str) -> Result<String, String> {
+    if !value.contains(':') {
+        return Err("expected host:port".to_string());
+    }
+    Ok(value.to_string())
+}
+
+/// What a dump saw before its input ended.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DumpSummary {
+    /// Frames (or subscriber lines) that parsed, whether or not they changed anything.
+    pub frames: u64,
+    /// Frames that were complete but invalid (bad LRC, bad framing), or lines that weren't a state.
+    pub invalid: u64,
+    /// JSON objects written.
+    pub written: u64,
+}
+
+/// Open the source in `options` and dump its feed to `out` until it closes.
+pub fn run<W: Write>(options: &DumpOptions, out: &mut W) -> io::Result<DumpSummary> {
+    match &options.source {
+        DumpSource::Connect(address) => {
+            let stream = TcpStream::connect(address)?;
+            info!("Subscribed to {}", address);
+            dump_states(BufReader::new(stream), options, out)
+        }
+        DumpSource::Listen(address) => {
+            let listener = TcpListener::bind(address)?;
+            info!("Waiting for the feed on {}", listener.local_addr()?);
+            let (stream, peer) = listener.accept()?;
+            info!("Reading the feed from {}", peer);
+            dump(stream, options, out)
+        }
+    }
+}
+
+/// Parse raw protocol bytes from `reader` and write the resulting states (or events) to `out`.
+///
+/// Every object is flushed as it is written, so a consumer sees it straight away. Invalid
+/// frames are logged and skipped; the dump only ends when `reader` reaches EOF or `out`
+/// fails.
+pub fn dump<R: Read, W: Write>(mut reader: R, options: &DumpOptions, out: &mut W) -> io::Result<DumpSummary> {
+    let mut decoder = FrameDecoder::new(options.lenient, &[0x00]);
+    let mut state = GameState::default();
+    let mut summary = DumpSummary::default();
+    let mut buffer = [0u8; 1024];
+
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(summary),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        decoder.push(&buffer[..n]);
+
+        while let Some(result) = decoder.next_frame() {
+            let frame = match result {
+                Ok(frame) => frame,
+                Err(e) => {
+                    warn!("Skipping invalid frame: {}", e);
+                    summary.invalid += 1;
+                    continue;
+                }
+            };
+            summary.frames += 1;
+
+            let mut next = state.clone();
+            if apply_frame(&frame, &mut next) {
+                write_change(&mut state, next, options, out, &mut summary)?;
+            }
+        }
+    }
+}
+
+/// Read the JSON-line states of a `--subscribers` stream from `reader` and write them (or
+/// their events) to `out`, as [`dump`] does for frames.
+///
+/// The stream opens with the server's current state, then sends one line per update. Lines
+/// that aren't a game state are logged and skipped.
+pub fn dump_states<R: BufRead, W: Write>(reader: R, options: &DumpOptions, out: &mut W) -> io::Result<DumpSummary> {
+    let mut state = GameState::default();
+    let mut summary = DumpSummary::default();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<GameState>(&line) {
+            Ok(next) => {
+                summary.frames += 1;
+                write_change(&mut state, next, options, out, &mut summary)?;
+            }
+            Err(e) => {
+                warn!("Skipping a line that isn't a state: {}", e);
+                summary.invalid += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Write `next` (or the events from `state` to it) if it changed anything, and make it the state.
+fn write_change<W: Write>(
+    state: &mut GameState,
+    next: GameState,
+    options: &DumpOptions,
+    out: &mut W,
+    summary: &mut DumpSummary,
+) -> io::Result<()> {
+    if next == *state {
+        return Ok(());
+    }
+    if options.events {
+        for event in events::diff(state, &next) {
+            write_json(out, &event, options.pretty)?;
+            summary.written += 1;
+        }
+    } else {
+        write_json(out, &next, options.pretty)?;
+        summary.written += 1;
+    }
+    *state = next;
+    Ok(())
+}
+
+fn write_json<W: Write>(out: &mut W, value: &impl Serialize, pretty: bool) -> io::Result<()> {
+    if pretty {
+        serde_json::to_writer_pretty(&mut *out, value)?;
+    } else {
+        serde_json::to_writer(&mut *out, value)?;
+    }
+    out.write_all(b"\n")?;
+    out.flush()
+}
+
+/// Entry point of `scoreboard-rust dump`; returns the process exit code.
+pub fn run_command(args: DumpArgs) -> i32 {
+    let options = DumpOptions::from(args);
+    match run(&options, &mut io::stdout().lock()) {
+        Ok(summary) => {
+            let read = match options.source {
+                DumpSource::Connect(_) => "states",
+                DumpSource::Listen(_) => "frames",
+            };
+            info!(
+                "Feed closed after {} {} ({} invalid), {} objects written",
+                summary.frames, read, summary.invalid, summary.written
+            );
+            0
+        }
+        // The reader of the pipe (`head`, a `jq` that failed) has gone away: nothing left to do
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => 0,
+        Err(e) => {
+            error!("Dump failed: {}", e);
+            1
+        }
+    }
+}
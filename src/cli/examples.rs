@@ -0,0 +1,92 @@
+use crate::{
+    apply_frame,
+    cli::send::{clock_frame, fouls_frame, scores_frame, ClockTime},
+    parse_raw_data, GameState,
+};
+use serde::Serialize;
+use std::io::{self, Write};
+use tracing::error;
+
+/// The arguments of `scoreboard-rust examples`.
+#[derive(Debug, Clone, clap::Args)]
+pub struct ExamplesArgs {
+    /// Print the frames and states as JSON.
+    #[arg(long)]
+    json: bool,
+}
+
+/// A frame as the console sends it, and the game state once the parser has read it.
+#[derive(Debug, Clone, Serialize)]
+pub struct Example {
+    pub label: &'static str,
+    /// The bytes on the wire, as `send --hex` takes them.
+    pub hex: String,
+    pub state: GameState,
+}
+
+/// The frames of a short game moment, each read on top of the previous ones: the scores,
+/// the team fouls, the clock with the period and time-outs, and the clock in its last minute.
+pub fn examples() -> Vec<Example> {
+    let frames: [(&'static str, Vec<u8>); 4] = [
+        ("scores 80-74 (message 30)", scores_frame(80, 74)),
+        ("fouls 4-5 (message 31)", fouls_frame(b'4', b'5')),
+        ("clock 02:30 running, 4th period, time-outs 3-2 (message 18)", clock_frame(ClockTime::Minutes(2, 30), true, b'4', (b'3', b'2'))),
+        ("clock 45.3 paused, last minute (message 18)", clock_frame(ClockTime::Tenths(45, 3), false, b'4', (b'3', b'2'))),
+    ];
+    let mut state = GameState::default();
+    frames
+        .into_iter()
+        .map(|(label, bytes)| {
+            let frame = parse_raw_data(&bytes, false).expect("example frames are valid");
+            apply_frame(&frame, &mut state);
+            Example {
+                label,
+                hex: bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<Vec<_>>().join(" "),
+                state: state.clone(),
+            }
+        })
+        .collect()
+}
+
+fn write_text(out: &mut impl Write, examples: &[Example]) -> io::Result<()> {
+    writeln!(out, "Frames of the Bodet console protocol, and the state the server reads from them.")?;
+    writeln!(out, "Send one to a server with: scoreboard-rust send --hex \"<bytes>\"")?;
+    for example in examples {
+        let state = &example.state;
+        writeln!(out)?;
+        writeln!(out, "{}", example.label)?;
+        writeln!(out, "  bytes: {}", example.hex)?;
+        writeln!(
+            out,
+            "  state: {} - {}, fouls {}-{}, {} {} ({}), time-outs {}-{}",
+            state.home_score.trim(),
+            state.away_score.trim(),
+            state.home_fouls.trim(),
+            state.away_fouls.trim(),
+            state.period_name,
+            state.time,
+            state.game_state,
+            state.home_timeouts.trim(),
+            state.away_timeouts.trim()
+        )?;
+    }
+    Ok(())
+}
+
+/// Entry point of `scoreboard-rust examples`; returns the process exit code.
+pub fn run_command(args: &ExamplesArgs) -> i32 {
+    let examples = examples();
+    let mut out = io::stdout().lock();
+    let written = if args.json {
+        serde_json::to_writer_pretty(&mut out, &examples).map_err(io::Error::from).and_then(|()| writeln!(out))
+    } else {
+        write_text(&mut out, &examples)
+    };
+    match written {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+            error!("Failed to write the examples: {}", e);
+            1
+        }
+        _ => 0,
+    }
+}
@@ -0,0 +1,166 @@
+use crate::{framing::FrameDecoder, ProtocolFrame};
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    net::TcpStream,
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+/// Message type of test frames; unknown to the parser so they never look like game data.
+const TEST_MESSAGE_TYPE: &[u8; 2] = b"99";
+const TEST_ADDRESS: u8 = 0x7F;
+const TEST_CTRL: u8 = b'G';
+
+/// Settings for a link test run.
+#[derive(Debug, Clone, clap::Args)]
+pub struct LinkTestOptions {
+    /// Address of a server started with `--echo`.
+    #[arg(value_name = "HOST:PORT", default_value = "127.0.0.1:4001", value_parser = host_port)]
+    pub address: String,
+    /// Number of test frames to send.
+    #[arg(long, value_name = "N", default_value_t = 20)]
+    pub count: u32,
+    /// Pause between two test frames, in milliseconds.
+    #[arg(long, value_name = "MS", default_value = "100", value_parser = millis)]
+    pub interval: Duration,
+    /// How long to wait for each echo before counting the frame as lost, in milliseconds.
+    #[arg(long, value_name = "MS", default_value = "1000", value_parser = millis)]
+    pub timeout: Duration,
+}
+
+fn host_port(value: &str) -> Result<String, String> {
+    if !value.contains(':') {
+        return Err("expected host:port".to_string());
+    }
+    Ok(value.to_string())
+}
+
+fn millis(value: &str) -> Result<Duration, String> {
+    value.parse().map(Duration::from_millis).map_err(|e: std::num::ParseIntError| e.to_string())
+}
+
+/// Outcome of a link test.
+#[derive(Debug, Clone, Default)]
+pub struct LinkTestSummary {
+    pub sent: u32,
+    pub received: u32,
+    /// Round-trip times of the frames that came back.
+    pub round_trips: Vec<Duration>,
+}
+
+impl LinkTestSummary {
+    pub fn lost(&self) -> u32 {
+        self.sent - self.received
+    }
+
+    pub fn loss_percent(&self) -> f64 {
+        if self.sent == 0 {
+            0.0
+        } else {
+            self.lost() as f64 * 100.0 / self.sent as f64
+        }
+    }
+}
+
+impl fmt::Display for LinkTestSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} sent, {} received, {} lost ({:.1}% loss)",
+            self.sent,
+            self.received,
+            self.lost(),
+            self.loss_percent()
+        )?;
+        if let (Some(min), Some(max)) = (self.round_trips.iter().min(), self.round_trips.iter().max()) {
+            let avg = self.round_trips.iter().sum::<Duration>() / self.round_trips.len() as u32;
+            write!(f, "; rtt min/avg/max = {:?}/{:?}/{:?}", min, avg, max)?;
+        }
+        Ok(())
+    }
+}
+
+/// Build the numbered test frame `seq`.
+fn test_frame(seq: u32) -> Vec<u8> {
+    let mut message = TEST_MESSAGE_TYPE.to_vec();
+    message.extend_from_slice(format!("{:08}", seq).as_bytes());
+    ProtocolFrame::new(TEST_ADDRESS, TEST_CTRL, message).to_bytes()
+}
+
+/// Sequence number of an echoed test frame.
+fn test_frame_seq(frame: &ProtocolFrame) -> Option<u32> {
+    let digits = frame.message.strip_prefix(TEST_MESSAGE_TYPE)?;
+    std::str::from_utf8(digits).ok()?.parse().ok()
+}
+
+/// Send numbered test frames over `stream` and wait for each one to be echoed back.
+pub fn run(mut stream: TcpStream, options: &LinkTestOptions) -> io::Result<LinkTestSummary> {
+    let mut summary = LinkTestSummary::default();
+    let mut decoder = FrameDecoder::new(false, &[]);
+    let mut buffer = [0u8; 1024];
+
+    for seq in 0..options.count {
+        if seq > 0 {
+            thread::sleep(options.interval);
+        }
+
+        let sent_at = Instant::now();
+        stream.write_all(&test_frame(seq))?;
+        summary.sent += 1;
+
+        let deadline = sent_at + options.timeout;
+        let mut echoed = false;
+        while !echoed {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                warn!("Test frame {} lost", seq);
+                break;
+            }
+            stream.set_read_timeout(Some(remaining))?;
+            match stream.read(&mut buffer) {
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "server closed the connection")),
+                Ok(n) => decoder.push(&buffer[..n]),
+                Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => continue,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+
+            while let Some(frame) = decoder.next_frame() {
+                match frame.map(|frame| test_frame_seq(&frame)) {
+                    Ok(Some(echo)) if echo == seq => {
+                        summary.received += 1;
+                        summary.round_trips.push(sent_at.elapsed());
+                        echoed = true;
+                    }
+                    // A late echo of a frame already counted as lost
+                    Ok(_) => {}
+                    Err(e) => warn!("Corrupted echo: {}", e),
+                }
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Entry point of `scoreboard-rust link-test ...`; returns the process exit code.
+pub fn run_command(options: &LinkTestOptions) -> i32 {
+    info!("Link test: sending {} frames to {}", options.count, options.address);
+    let result = TcpStream::connect(&options.address).and_then(|stream| run(stream, options));
+    match result {
+        Ok(summary) => {
+            println!("{}", summary);
+            if summary.lost() == 0 {
+                0
+            } else {
+                1
+            }
+        }
+        Err(e) => {
+            error!("Link test against {} failed: {}", options.address, e);
+            1
+        }
+    }
+}
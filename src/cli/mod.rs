@@ -0,0 +1,386 @@
+//! The `scoreboard-rust` command line: the server (`serve`) and the other commands.
+//!
+//! `src/main.rs` only calls [`main`]; the commands stay private to the crate.
+
+mod aggregate;
+mod bench;
+mod convert;
+mod dump;
+mod examples;
+mod link_test;
+mod protocol;
+mod replay;
+mod send;
+mod simulate;
+mod tui;
+mod validate;
+
+#[cfg(unix)]
+use crate::daemon;
+#[cfg(feature = "mdns")]
+use crate::mdns;
+use crate::{
+    config::ServeArgs, game_archive, lan, logging, service, systemd, web_server, Courts, Reloader, ServerConfig, SharedState,
+};
+use clap::{Parser, Subcommand};
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{debug, error, info, info_span, warn};
+
+/// How long a shutdown waits for the console connections to close, then for the logs to be written.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Bodet basketball scoreboard bridge: reads the console's frames and serves the overlays.
+///
+/// Without a command the options are the server's, as for `serve`.
+#[derive(Debug, Parser)]
+#[command(name = "scoreboard-rust", version, args_conflicts_with_subcommands = true, args_override_self = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    serve: ServeArgs,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Run the server (the default command).
+    Serve(Box<ServeArgs>),
+    /// Print example frames of the console protocol and the state read from them.
+    Examples(examples::ExamplesArgs),
+    /// Test the cable to a server started with --echo.
+    LinkTest(link_test::LinkTestOptions),
+    /// List the servers on the network (mdns feature).
+    Discover(DiscoverArgs),
+    /// Send test frames and print the ACK/ERR replies.
+    Send(send::SendArgs),
+    /// Play a random or scripted game over the wire.
+    Simulate(simulate::SimulateArgs),
+    /// Send a data_log/ capture again (space, n, g m:ss, q + Enter while it plays).
+    Replay(replay::ReplayOptions),
+    /// Print a raw frame feed as JSON lines.
+    Dump(dump::DumpArgs),
+    /// The games recorded with --games-dir.
+    #[command(subcommand)]
+    History(game_archive::HistoryCommand),
+    /// Season totals of the games and captures in a directory.
+    Aggregate(aggregate::AggregateArgs),
+    /// Check a test capture against the parser (exit 1 on failure).
+    Validate(validate::ValidateOptions),
+    /// Convert between frames, capture, hex, jsonl and json.
+    Convert(convert::ConvertOptions),
+    /// Load a server started with --ack and time its replies.
+    Bench(bench::BenchOptions),
+    /// What this build knows of the console protocols.
+    #[command(subcommand)]
+    Protocol(protocol::ProtocolCommand),
+    /// Terminal scoreboard of a server's --subscribers stream.
+    Tui(tui::TuiArgs),
+    /// Stop a server started with --pid-file (Unix).
+    Stop(StopArgs),
+    /// Run as a Windows service (windows-service feature).
+    #[command(subcommand)]
+    Service(service::ServiceCommand),
+}
+
+#[cfg(unix)]
+use daemon::StopArgs;
+#[cfg(feature = "mdns")]
+use mdns::DiscoverArgs;
+
+/// `stop` where there is no PID file to stop from.
+#[cfg(not(unix))]
+#[derive(Debug, Clone, clap::Args)]
+struct StopArgs {
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true, hide = true)]
+    args: Vec<String>,
+}
+
+/// `discover` in a build without mDNS.
+#[cfg(not(feature = "mdns"))]
+#[derive(Debug, Clone, clap::Args)]
+struct DiscoverArgs {
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true, hide = true)]
+    args: Vec<String>,
+}
+
+/// Run the command given by the process arguments and exit with its status.
+pub fn run() {
+    let cli = Cli::parse();
+    let command = match cli.command {
+        // The server's options say where its log goes; a service has no console
+        None | Some(Command::Serve(_)) => {
+            // Parsed again with the environment and config file under them
+            let args: Vec<String> = std::env::args().skip(1).collect();
+            let args = args.strip_prefix(&["serve".to_string()]).unwrap_or(&args);
+            std::process::exit(serve(args, &mut |_| {}));
+        }
+        Some(Command::Service(command)) => {
+            #[cfg(all(windows, feature = "windows-service"))]
+            std::process::exit(service::run_command(&command, serve));
+            #[cfg(not(all(windows, feature = "windows-service")))]
+            {
+                let _ = command;
+                logging::init_console();
+                error!("service requires a Windows build with service support (cargo build --features windows-service)");
+                std::process::exit(2);
+            }
+        }
+        Some(command) => command,
+    };
+    logging::init_console();
+    let code = match command {
+        Command::Examples(args) => examples::run_command(&args),
+        Command::LinkTest(options) => link_test::run_command(&options),
+        Command::Send(args) => send::run_command(args),
+        Command::Simulate(args) => simulate::run_command(args),
+        Command::Replay(options) => replay::run_command(&options),
+        Command::Dump(args) => dump::run_command(args),
+        Command::History(command) => game_archive::run_command(&command),
+        Command::Aggregate(args) => aggregate::run_command(&args),
+        Command::Validate(options) => validate::run_command(&options),
+        Command::Convert(options) => convert::run_command(&options),
+        Command::Bench(options) => bench::run_command(&options),
+        Command::Protocol(command) => protocol::run_command(&command),
+        Command::Tui(args) => tui::run_command(&args),
+        #[cfg(unix)]
+        Command::Stop(args) => daemon::run_command(&args),
+        #[cfg(not(unix))]
+        Command::Stop(_) => {
+            error!("stop is only available on Unix; stop the Windows service with sc stop");
+            2
+        }
+        #[cfg(feature = "mdns")]
+        Command::Discover(args) => mdns::run_discover(&args),
+        #[cfg(not(feature = "mdns"))]
+        Command::Discover(_) => {
+            error!("discover requires a build with mDNS support (cargo build --features mdns)");
+            2
+        }
+        Command::Serve(_) | Command::Service(_) => unreachable!("handled above"),
+    };
+    std::process::exit(code);
+}
+
+/// Run the server until Ctrl+C, SIGTERM or `stop` stops it; returns the exit status.
+/// `on_started` receives what stops it once both servers are starting (for the Windows service).
+fn serve(args: &[String], on_started: &mut dyn FnMut(service::Stop)) -> i32 {
+    let startup_log = logging::startup();
+    // When started with "dev", do NOT log TCP session bytes to files
+    let vars: Vec<(String, String)> = std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .collect();
+    let config = match ServerConfig::from_env_and_args(vars.iter().cloned(), args) {
+        Ok(config) => config,
+        Err(e) => {
+            error!("{}", e);
+            return 2;
+        }
+    };
+
+    // Before any thread starts, as forking only keeps the calling thread
+    #[cfg(unix)]
+    let pid_file = match daemon::start(&config) {
+        Ok(pid_file) => pid_file,
+        Err(e) => {
+            error!("{}", e);
+            return 1;
+        }
+    };
+
+    // After --daemon, which may send the console to a file
+    if let Err(e) = logging::init(config.log_format, config.log_dir.as_deref(), config.log_rotation, &config.timezone) {
+        error!("{}", e);
+        return 1;
+    }
+    drop(startup_log);
+
+    // Before any thread starts, as the systemd variables are taken out of the environment
+    let notifier = systemd::Notifier::from_env();
+
+    if !config.log_to_file {
+        info!("Starting in dev mode: TCP session bytes will NOT be logged to files");
+    }
+
+    // The URLs to paste into OBS, whichever interface the operator's machine is on
+    lan::log_reachable(&config);
+
+    // Let operators find the overlay and ingest port without knowing the IP address
+    #[cfg(feature = "mdns")]
+    let advertiser = mdns::advertise(&config);
+
+    // Shared game state, broadcast channel for SSE and metrics; one per court
+    let mut web_options = web_server::WebOptions::from_config(&config);
+    let reloader = Reloader::new(vars, args.to_vec(), config.clone());
+    let tui_enabled = config.tui;
+    let several_courts = !config.courts.is_empty();
+    let courts = Courts::new(&config);
+    if several_courts {
+        web_options.courts = Some(courts.clone());
+    }
+    // The court served at `/`, which the reloads, the dashboard and the watchdog follow
+    let shared = courts.primary().shared();
+    shared.set_reloader(reloader);
+    // Under systemd (Type=notify): ready once both servers listen, watchdog pings while they run
+    let supervisor = notifier.map(|notifier| systemd::Supervisor::start(notifier, Arc::clone(&shared)));
+
+    for court in courts.iter() {
+        log_game_events(&court.shared(), several_courts.then(|| court.id().to_string()));
+    }
+
+    // Ctrl+C or SIGTERM stop both servers; a second one exits at once. Watched on a thread
+    // of its own so it still works while the logs are being written after the web server stopped
+    let web_shutdown = web_server::ShutdownHandle::new();
+    let (web_stop, ingest_stop) = (web_shutdown.clone(), courts.clone());
+    let stop: Arc<dyn Fn() + Send + Sync> = Arc::new(move || {
+        if let Some(supervisor) = &supervisor {
+            supervisor.stopping();
+        }
+        web_stop.shutdown();
+        ingest_stop.shutdown();
+    });
+    let reload_shared = Arc::clone(&shared);
+    let signal_stop = Arc::clone(&stop);
+    thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async {
+            // SIGHUP reads the configuration again
+            #[cfg(unix)]
+            tokio::spawn(async move {
+                use tokio::signal::unix::{signal, SignalKind};
+                let Ok(mut hangup) = signal(SignalKind::hangup()) else {
+                    return;
+                };
+                while hangup.recv().await.is_some() {
+                    info!("SIGHUP received, reloading the configuration");
+                    if let Err(e) = reload_shared.reload() {
+                        error!("Reload failed, keeping the current configuration: {}", e);
+                    }
+                }
+            });
+            #[cfg(not(unix))]
+            drop(reload_shared);
+            if wait_for_signal().await {
+                info!("Shutting down (press Ctrl+C again to exit at once)");
+                signal_stop();
+                if wait_for_signal().await {
+                    warn!("Second signal received, exiting without waiting");
+                    std::process::exit(130);
+                }
+            }
+        });
+    });
+
+    // The operator's terminal scoreboard; quitting it stops the server like Ctrl+C
+    if tui_enabled {
+        let tui_shared = Arc::clone(&shared);
+        let tui_stop = Arc::clone(&stop);
+        thread::spawn(move || tui::run_embedded(tui_shared, move || tui_stop()));
+    }
+
+    // Start web server in a separate thread
+    let web_shared = Arc::clone(&shared);
+    let web = thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(web_options.workers)
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let result = web_server::start_with_shutdown(web_shared, web_options, web_shutdown).await;
+            if let Err(e) = &result {
+                error!("Web server failed: {}", e);
+            }
+            result.is_ok()
+        })
+    });
+
+    // Single-stream inputs return when they end: the overlay keeps the last known state.
+    // A failed court is reported by /healthz while the others play on; a single one ends the server
+    if several_courts {
+        courts.start();
+    } else {
+        let court = courts.primary().clone();
+        thread::spawn(move || {
+            if let Err(e) = court.run() {
+                error!("Ingest server failed: {}", e);
+                std::process::exit(1);
+            }
+        });
+    }
+    on_started(Box::new(move || stop()));
+
+    // The web server only stops on Ctrl+C or SIGTERM; then close the console connections and
+    // write what the logs, the game recorder and the session files still hold
+    let web_ok = web.join().unwrap_or(false);
+    courts.shutdown();
+    if !courts.wait(SHUTDOWN_TIMEOUT) {
+        warn!("Console connections still open, closing them with the process");
+    }
+    let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+    // Every court's, even after one ran out of time
+    let late = courts.iter().filter(|court| !court.shared().close_listeners(deadline.saturating_duration_since(Instant::now()))).count();
+    if late > 0 {
+        warn!("Logs not written within {:?}, their last lines may be missing", SHUTDOWN_TIMEOUT);
+    }
+    #[cfg(feature = "mdns")]
+    if let Some(advertiser) = advertiser {
+        advertiser.withdraw();
+    }
+    #[cfg(unix)]
+    if let Some(pid_file) = pid_file {
+        pid_file.remove();
+    }
+    info!("Shut down");
+    if web_ok {
+        0
+    } else {
+        1
+    }
+}
+
+/// Log the game events of a court (`court` names it when there are several); clock ticks are
+/// coalesced to at most one line per second.
+fn log_game_events(shared: &SharedState, court: Option<String>) {
+    shared.add_listener(Duration::from_secs(1), move |update| {
+        let _span = court.as_ref().map(|id| info_span!("court", id = %id).entered());
+        for event in &update.events {
+            if event.is_important() {
+                let state = &update.state;
+                info!(
+                    "Game event: {} [{}-{}, {} {}]",
+                    event,
+                    state.home_score.trim(),
+                    state.away_score.trim(),
+                    state.period_name,
+                    state.time
+                );
+            } else {
+                debug!("Game event: {}", event);
+            }
+        }
+    });
+}
+
+/// Complete on Ctrl+C or, on Unix, SIGTERM (as sent by systemd). Returns `false` if the
+/// signals can't be watched.
+async fn wait_for_signal() -> bool {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let Ok(mut terminate) = signal(SignalKind::terminate()) else {
+            return tokio::signal::ctrl_c().await.is_ok();
+        };
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result.is_ok(),
+            _ = terminate.recv() => true,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await.is_ok()
+    }
+}
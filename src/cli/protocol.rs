@@ -0,0 +1,113 @@
+use crate::basketball_parser::{self, ProtocolSpec};
+use serde::Serialize;
+use std::io::{self, Write};
+use tracing::error;
+
+/// The protocols the server reads, by id.
+pub const PROTOCOLS: &[ProtocolSpec] = &[basketball_parser::PROTOCOL];
+
+/// Optional features this binary was built with.
+pub fn features() -> Vec<&'static str> {
+    [
+        ("tls", cfg!(feature = "tls")),
+        ("mdns", cfg!(feature = "mdns")),
+        ("mqtt", cfg!(feature = "mqtt")),
+        ("raster", cfg!(feature = "raster")),
+        ("windows-service", cfg!(feature = "windows-service")),
+    ]
+    .into_iter()
+    .filter_map(|(feature, built)| built.then_some(feature))
+    .collect()
+}
+
+/// What `protocol info --json` prints.
+#[derive(Debug, Serialize)]
+pub struct Info {
+    pub version: &'static str,
+    pub features: Vec<&'static str>,
+    pub protocols: Vec<ProtocolSpec>,
+}
+
+/// The build and the protocols with `id`, or all of them; `None` when no protocol has that id.
+pub fn info(id: Option<&str>) -> Option<Info> {
+    let protocols: Vec<ProtocolSpec> = PROTOCOLS.iter().filter(|protocol| id.is_none_or(|id| protocol.id == id)).copied().collect();
+    if protocols.is_empty() {
+        return None;
+    }
+    Some(Info { version: env!("CARGO_PKG_VERSION"), features: features(), protocols })
+}
+
+impl Info {
+    /// Write the protocols as text: framing, extensions, then a field table per message.
+    pub fn write_text(&self, out: &mut impl Write) -> io::Result<()> {
+        let features = if self.features.is_empty() { "none".to_string() } else { self.features.join(", ") };
+        writeln!(out, "scoreboard-rust {} (features: {})", self.version, features)?;
+        for protocol in &self.protocols {
+            writeln!(out)?;
+            writeln!(out, "{}  {}", protocol.id, protocol.name)?;
+            writeln!(out, "  Framing     {} ({} bytes around the message)", protocol.framing, protocol.overhead)?;
+            writeln!(out, "  Checksum    {}", protocol.checksum)?;
+            for extension in protocol.extensions {
+                writeln!(out, "  Extension   {:<10} {} ({})", extension.name, extension.description, extension.option)?;
+            }
+            for message in protocol.messages {
+                writeln!(out)?;
+                writeln!(
+                    out,
+                    "  Message {}  {}  ({} bytes, frames of {})",
+                    message.id,
+                    message.name,
+                    message.length,
+                    message.length + protocol.overhead
+                )?;
+                writeln!(out, "    {:<7} {:<5} {:<14} {:<24} Meaning", "Offset", "Size", "Field", "Range")?;
+                for field in message.fields {
+                    let read = if field.read { "" } else { " (not read)" };
+                    writeln!(out, "    {:<7} {:<5} {:<14} {:<24} {}{}", field.offset, field.size, field.name, field.range, field.meaning, read)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The commands of `scoreboard-rust protocol`.
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum ProtocolCommand {
+    /// Message types, fields and extensions this build reads.
+    Info {
+        /// One protocol only, by its id.
+        id: Option<String>,
+        /// Print them as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Entry point of `scoreboard-rust protocol`; returns the process exit code.
+///
+/// `protocol info [id] [--json]` lists the protocols, their framing, message fields and
+/// extensions, from the same tables the parser is checked against.
+pub fn run_command(command: &ProtocolCommand) -> i32 {
+    let ProtocolCommand::Info { id, json } = command;
+    let id = id.as_deref();
+    let Some(info) = info(id) else {
+        let known: Vec<&str> = PROTOCOLS.iter().map(|protocol| protocol.id).collect();
+        error!("Unknown protocol {}: expected one of {}", id.unwrap_or_default(), known.join(", "));
+        return 2;
+    };
+
+    let mut out = io::stdout().lock();
+    let written = if *json {
+        serde_json::to_writer_pretty(&mut out, &info).map_err(io::Error::from).and_then(|()| writeln!(out))
+    } else {
+        info.write_text(&mut out)
+    };
+    match written {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+            error!("Failed to write the protocol info: {}", e);
+            1
+        }
+        _ => 0,
+    }
+}
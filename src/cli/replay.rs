@@ -0,0 +1,394 @@
+use crate::capture;
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::TcpStream,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+/// Lines per second of a capture by default, as `send_hex_stream_tcp.py` sends them.
+const DEFAULT_RATE: f64 = 1.0;
+
+/// Settings for `scoreboard-rust replay`.
+#[derive(Debug, Clone, clap::Args)]
+pub struct ReplayOptions {
+    /// Capture file, as written to `data_log/`.
+    #[arg(value_name = "CAPTURE")]
+    pub file: PathBuf,
+    /// Ingest address of the server.
+    #[arg(long, value_name = "HOST:PORT", default_value = "127.0.0.1:4001")]
+    pub target: String,
+    /// Lines per second of the capture: its files record no times, so this is its timeline.
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_RATE, value_parser = rate)]
+    pub rate: f64,
+    /// How many recorded seconds pass per real second, such as 0.5x; below 1 slows the replay down.
+    #[arg(long, value_name = "FACTOR", default_value = "1x", value_parser = speed)]
+    pub speed: f64,
+    /// Recorded time the replay starts at (and loops back to), `m:ss`.
+    #[arg(long, value_name = "TIME", value_parser = time)]
+    pub start_at: Option<Duration>,
+    /// Recorded time the replay ends at, `m:ss`.
+    #[arg(long, value_name = "TIME", value_parser = time)]
+    pub stop_at: Option<Duration>,
+    /// Start again from `--start-at` at the end, until stopped.
+    #[arg(long = "loop")]
+    pub looping: bool,
+}
+
+impl ReplayOptions {
+    /// Check the options that depend on each other.
+    fn check(&self) -> Result<(), String> {
+        if let (Some(start), Some(stop)) = (self.start_at, self.stop_at) {
+            if stop <= start {
+                return Err("--stop-at must come after --start-at".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+fn rate(value: &str) -> Result<f64, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|rate: &f64| rate.is_finite() && *rate > 0.0)
+        .ok_or_else(|| "expected lines per second, e.g. 10".to_string())
+}
+
+fn speed(value: &str) -> Result<f64, String> {
+    value
+        .trim_end_matches('x')
+        .parse()
+        .ok()
+        .filter(|speed: &f64| speed.is_finite() && *speed > 0.0 && *speed <= 10_000.0)
+        .ok_or_else(|| "expected a factor such as 0.5x".to_string())
+}
+
+fn time(value: &str) -> Result<Duration, String> {
+    parse_time(value).ok_or_else(|| "expected m:ss".to_string())
+}
+
+/// `m:ss` (minutes may go past 59) or a number of seconds.
+pub fn parse_time(value: &str) -> Option<Duration> {
+    let secs = match value.split_once(':') {
+        Some((minutes, seconds)) if seconds.len() == 2 => {
+            let seconds: u64 = seconds.parse().ok().filter(|seconds| *seconds < 60)?;
+            minutes.parse::<u64>().ok()? * 60 + seconds
+        }
+        Some(_) => return None,
+        None => value.parse().ok()?,
+    };
+    Some(Duration::from_secs(secs))
+}
+
+/// `m:ss`, as [`parse_time`] reads it.
+pub fn format_time(time: Duration) -> String {
+    let secs = time.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+/// The bytes of a capture line, `None` for a line without a bracketed list.
+pub fn parse_line(line: &str) -> Result<Option<Vec<u8>>, String> {
+    let Some(list) = line.split_once('[').and_then(|(_, rest)| rest.split_once(']')).map(|(list, _)| list) else {
+        return Ok(None);
+    };
+    list.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+        .map(|token| u8::from_str_radix(token.trim_start_matches("0x"), 16).map_err(|_| format!("Invalid byte {:?}", token)))
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+/// A capture file indexed by line, so a replay can seek to any recorded time.
+///
+/// `data_log/` files hold one TCP read per line as a bracketed hex list, `[01, 7F, 02, ...]`,
+/// without times, so the recorded time of a line is its number divided by the `rate`.
+#[derive(Debug, Clone)]
+pub struct Capture {
+    data: Vec<u8>,
+    /// Where each line's bytes are in `data`.
+    lines: Vec<Range<usize>>,
+    rate: f64,
+}
+
+impl Capture {
+    /// Index the bracketed hex lines of `text`; other lines are skipped. A line with a
+    /// token that isn't a hex byte is an error, so a broken file fails before anything is sent.
+    pub fn parse(text: &str, rate: f64) -> Result<Self, String> {
+        let mut data = Vec::new();
+        let mut lines = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let Some(bytes) = parse_line(line).map_err(|e| format!("{} on line {}", e, number + 1))? else {
+                continue;
+            };
+            let start = data.len();
+            data.extend(bytes);
+            lines.push(start..data.len());
+        }
+        Ok(Self { data, lines, rate })
+    }
+
+    /// Read and index the capture file at `path`, gzipped or not (`.log.gz`).
+    pub fn load(path: &Path, rate: f64) -> io::Result<Self> {
+        let text = capture::read_capture(path)?;
+        Self::parse(&text, rate).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Number of lines.
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// The bytes of line `index`.
+    pub fn bytes(&self, index: usize) -> &[u8] {
+        &self.data[self.lines[index].clone()]
+    }
+
+    /// Recorded time of line `index`.
+    pub fn time_of(&self, index: usize) -> Duration {
+        Duration::from_secs_f64(index as f64 / self.rate)
+    }
+
+    /// First line recorded at or after `time`, [`len`](Self::len) past the end.
+    pub fn index_at(&self, time: Duration) -> usize {
+        ((time.as_secs_f64() * self.rate).ceil() as usize).min(self.len())
+    }
+
+    /// Recorded time of the whole capture.
+    pub fn duration(&self) -> Duration {
+        self.time_of(self.len())
+    }
+}
+
+/// Where a replay is in its capture, between `--start-at` and `--stop-at`.
+#[derive(Debug, Clone)]
+pub struct Player {
+    capture: Capture,
+    position: usize,
+    start: usize,
+    stop: usize,
+    looping: bool,
+}
+
+impl Player {
+    pub fn new(capture: Capture, start_at: Option<Duration>, stop_at: Option<Duration>, looping: bool) -> Self {
+        let start = start_at.map_or(0, |time| capture.index_at(time));
+        let stop = stop_at.map_or(capture.len(), |time| capture.index_at(time)).max(start);
+        Self {
+            capture,
+            position: start,
+            start,
+            stop,
+            looping,
+        }
+    }
+
+    pub fn capture(&self) -> &Capture {
+        &self.capture
+    }
+
+    /// Recorded time of the line sent next.
+    pub fn recorded_time(&self) -> Duration {
+        self.capture.time_of(self.position)
+    }
+
+    /// Continue from the first line recorded at or after `time`, kept between the start and stop.
+    pub fn seek(&mut self, time: Duration) {
+        self.position = self.capture.index_at(time).clamp(self.start, self.stop);
+    }
+
+    /// The next line, moving past it. `None` at the stop, unless looping goes back to the start.
+    pub fn step(&mut self) -> Option<&[u8]> {
+        if self.position >= self.stop {
+            if !self.looping || self.start == self.stop {
+                return None;
+            }
+            self.position = self.start;
+        }
+        self.position += 1;
+        Some(self.capture.bytes(self.position - 1))
+    }
+
+    /// `12:31 / 98:20, line 751`: where the replay is.
+    pub fn status(&self) -> String {
+        format!(
+            "{} / {}, line {}",
+            format_time(self.recorded_time()),
+            format_time(self.capture.duration()),
+            self.position
+        )
+    }
+}
+
+/// A command typed during a replay, followed by Enter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    /// A space or an empty line: pause, or resume.
+    Pause,
+    /// `n`: send one line and stay paused.
+    Step,
+    /// `g <m:ss>`: continue from this recorded time.
+    Seek(Duration),
+    /// `q`: stop the replay.
+    Quit,
+}
+
+impl Control {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        match line.trim() {
+            "" => Ok(Control::Pause),
+            "n" => Ok(Control::Step),
+            "q" => Ok(Control::Quit),
+            other => {
+                let time = other
+                    .strip_prefix('g')
+                    .map(str::trim)
+                    .ok_or_else(|| format!("Unknown command {:?}: space to pause/resume, n to step, g <m:ss> to seek, q to quit", other))?;
+                parse_time(time).map(Control::Seek).ok_or_else(|| format!("Invalid time {:?}: expected m:ss", time))
+            }
+        }
+    }
+}
+
+/// Send the lines of `player` to `out`, `rate × speed` per second, applying the `controls`
+/// as they arrive and describing each one with `say`. Returns the number of lines sent.
+///
+/// The replay ends at the stop (never when looping), on [`Control::Quit`], or when paused
+/// with no more controls to come.
+pub fn play<W: Write>(player: &mut Player, out: &mut W, speed: f64, controls: &Receiver<Control>, mut say: impl FnMut(&str)) -> io::Result<u64> {
+    let interval = Duration::from_secs_f64(1.0 / (player.capture.rate * speed));
+    let mut sent = 0;
+    let mut paused = false;
+    let mut next_at = Instant::now();
+    loop {
+        let control = if paused {
+            match controls.recv() {
+                Ok(control) => Some(control),
+                Err(_) => return Ok(sent),
+            }
+        } else {
+            let wait = next_at.saturating_duration_since(Instant::now());
+            match controls.recv_timeout(wait) {
+                Ok(control) => Some(control),
+                Err(RecvTimeoutError::Timeout) => None,
+                // Nobody at the keyboard: play on
+                Err(RecvTimeoutError::Disconnected) => {
+                    thread::sleep(wait);
+                    None
+                }
+            }
+        };
+
+        match control {
+            Some(Control::Pause) => {
+                paused = !paused;
+                say(&format!("{} at {}", if paused { "Paused" } else { "Playing" }, player.status()));
+                next_at = Instant::now();
+                continue;
+            }
+            Some(Control::Step) => {
+                paused = true;
+                match player.step() {
+                    Some(bytes) => {
+                        out.write_all(bytes)?;
+                        out.flush()?;
+                        sent += 1;
+                        say(&format!("Stepped to {}", player.status()));
+                    }
+                    None => say("End of the capture"),
+                }
+                continue;
+            }
+            Some(Control::Seek(time)) => {
+                player.seek(time);
+                say(&format!("Jumped to {}", player.status()));
+                next_at = Instant::now();
+                continue;
+            }
+            Some(Control::Quit) => return Ok(sent),
+            None => {}
+        }
+
+        let Some(bytes) = player.step() else {
+            return Ok(sent);
+        };
+        out.write_all(bytes)?;
+        out.flush()?;
+        sent += 1;
+        // After a slow write carry on from now rather than burst through the lines missed
+        let now = Instant::now();
+        next_at = if next_at + interval < now { now } else { next_at + interval };
+    }
+}
+
+/// Controls typed on standard input, one per line; ends when the input does.
+fn read_controls() -> Receiver<Control> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { return };
+            match Control::parse(&line) {
+                Ok(control) => {
+                    if tx.send(control).is_err() || control == Control::Quit {
+                        return;
+                    }
+                }
+                Err(e) => warn!("{}", e),
+            }
+        }
+    });
+    rx
+}
+
+/// Replay the capture of `options` to its target, with controls from standard input.
+pub fn run(options: &ReplayOptions, say: impl FnMut(&str)) -> io::Result<u64> {
+    let capture = Capture::load(&options.file, options.rate)?;
+    let mut player = Player::new(capture, options.start_at, options.stop_at, options.looping);
+    let mut stream = TcpStream::connect(&options.target)?;
+    // Replies of a server started with --ack; read so they never fill its send buffer
+    let replies = stream.try_clone()?;
+    thread::spawn(move || {
+        for line in BufReader::new(replies).lines() {
+            match line {
+                Ok(line) if line.starts_with("ERR") => warn!("The server refused a frame: {}", line),
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+    });
+
+    info!(
+        "Replaying {} ({} lines, {}) to {} at {}x from {}",
+        options.file.display(),
+        player.capture().len(),
+        format_time(player.capture().duration()),
+        options.target,
+        options.speed,
+        format_time(player.recorded_time())
+    );
+    info!("Commands, then Enter: space to pause/resume, n to step, g <m:ss> to seek, q to quit");
+    play(&mut player, &mut stream, options.speed, &read_controls(), say)
+}
+
+/// Entry point of `scoreboard-rust replay ...`; returns the process exit code.
+pub fn run_command(options: &ReplayOptions) -> i32 {
+    if let Err(e) = options.check() {
+        error!("{}", e);
+        return 2;
+    }
+
+    match run(options, |line| println!("{}", line)) {
+        Ok(sent) => {
+            info!("Replay over, {} lines sent", sent);
+            0
+        }
+        Err(e) => {
+            error!("Replay of {} to {} failed: {}", options.file.display(), options.target, e);
+            1
+        }
+    }
+}
@@ -0,0 +1,470 @@
+use crate::{auth, framing::FrameDecoder, ProtocolFrame};
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    net::{TcpStream, UdpSocket},
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{error, info};
+
+/// Address and control bytes of the frames `send` builds, as a console sends them.
+const FRAME_ADDRESS: u8 = 0x7F;
+const FRAME_CTRL: u8 = b'G';
+
+/// Settings for `scoreboard-rust send`.
+#[derive(Debug, Clone)]
+pub struct SendOptions {
+    /// Ingest address of the server.
+    pub address: String,
+    /// Send each frame as a UDP datagram instead of over a TCP connection.
+    pub udp: bool,
+    /// What to send, in order; sent once per repetition.
+    pub payloads: Vec<Payload>,
+    /// Number of times the payloads are sent.
+    pub repeat: u32,
+    /// Pause between two repetitions.
+    pub interval: Duration,
+    /// How long to wait for the server's reply to a frame.
+    pub timeout: Duration,
+    /// The server's `--auth-token`: sent as `AUTH <token>` first over TCP, signing each datagram
+    /// over UDP.
+    pub auth_token: Option<String>,
+}
+
+/// Bytes to send and how to describe them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Payload {
+    pub label: String,
+    pub bytes: Vec<u8>,
+}
+
+/// The arguments of `scoreboard-rust send`, checked by [`SendOptions::from_args`].
+#[derive(Debug, Clone, clap::Args)]
+pub struct SendArgs {
+    /// Ingest address of the server.
+    #[arg(long, value_name = "HOST:PORT", default_value = "127.0.0.1:4001")]
+    to: String,
+    /// Send each frame as a UDP datagram instead of over a TCP connection.
+    #[arg(long)]
+    udp: bool,
+    /// Home score (message 30), with `--away`.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(0..=999))]
+    home: Option<u32>,
+    /// Away score (message 30), with `--home`.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(0..=999))]
+    away: Option<u32>,
+    /// Team fouls, 0-9 (message 31).
+    #[arg(long, value_name = "HOME,AWAY", value_parser = pair)]
+    fouls: Option<(u32, u32)>,
+    /// Game clock (message 18), `m:ss` or `ss.t` in the last minute.
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    time: Option<ClockTime>,
+    /// Period sent with the clock: 1-4 or OT.
+    #[arg(long, value_name = "PERIOD", value_parser = parse_period)]
+    period: Option<u8>,
+    /// Time-outs left, sent with the clock, 0-9.
+    #[arg(long, value_name = "HOME,AWAY", value_parser = pair)]
+    timeouts: Option<(u32, u32)>,
+    /// Whether the clock runs.
+    #[arg(long, value_name = "running|paused", value_parser = clock_state)]
+    state: Option<bool>,
+    #[arg(long, hide = true)]
+    possession: Option<String>,
+    /// Raw bytes sent as given, instead of the fields: "01 7F 02", 017F02 or 0x01 0x7F.
+    // Spelled out so that clap takes the bytes as one value rather than a list of them
+    #[arg(long, value_name = "BYTES", value_parser = parse_hex, conflicts_with_all = ["home", "away", "fouls", "time", "period", "timeouts", "state"])]
+    hex: Option<std::vec::Vec<u8>>,
+    /// Number of times everything is sent.
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    repeat: u32,
+    /// Pause between two repetitions (100ms, 2s).
+    #[arg(long, value_name = "DURATION", default_value = "100ms", value_parser = parse_duration)]
+    interval: Duration,
+    /// How long to wait for each ACK / ERR reply.
+    #[arg(long, value_name = "DURATION", default_value = "1s", value_parser = parse_duration)]
+    timeout: Duration,
+    /// The server's token, to authenticate the connection or sign datagrams.
+    #[arg(long, value_name = "TOKEN")]
+    auth_token: Option<String>,
+}
+
+impl SendOptions {
+    /// The payloads the `send` arguments describe: scores, fouls and the clock, each in the
+    /// frame the console sends it in, or the `--hex` bytes.
+    pub fn from_args(args: SendArgs) -> Result<Self, String> {
+        if args.possession.is_some() {
+            return Err("--possession isn't part of the console protocol: set it from the admin page".to_string());
+        }
+        let mut options = Self {
+            address: args.to,
+            udp: args.udp,
+            payloads: Vec::new(),
+            repeat: args.repeat,
+            interval: args.interval,
+            timeout: args.timeout,
+            auth_token: args.auth_token,
+        };
+        if let Some(bytes) = args.hex {
+            options.payloads.push(Payload {
+                label: format!("{} raw bytes", bytes.len()),
+                bytes,
+            });
+            return Ok(options);
+        }
+
+        match (args.home, args.away) {
+            (Some(home), Some(away)) => options.payloads.push(scores_payload(home, away)),
+            (None, None) => {}
+            _ => return Err("--home and --away must be given together".to_string()),
+        }
+        if let Some((home, away)) = args.fouls {
+            options.payloads.push(fouls_payload(home, away)?);
+        }
+        let clock = ClockFields {
+            time: args.time,
+            period: args.period,
+            timeouts: args.timeouts,
+            running: args.state,
+        };
+        if clock.is_set() {
+            options.payloads.push(clock.payload()?);
+        }
+        if options.payloads.is_empty() {
+            return Err("Nothing to send: give --home/--away, --fouls, --time or --hex".to_string());
+        }
+        Ok(options)
+    }
+}
+
+/// Message 18 fields; sent together since the console sends them in one frame.
+#[derive(Debug)]
+struct ClockFields {
+    time: Option<ClockTime>,
+    period: Option<u8>,
+    timeouts: Option<(u32, u32)>,
+    running: Option<bool>,
+}
+
+/// Game clock of a message 18 frame.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ClockTime {
+    /// Minutes and seconds.
+    Minutes(u32, u32),
+    /// Seconds and tenths, shown in the last minute.
+    Tenths(u32, u32),
+}
+
+impl ClockFields {
+    fn is_set(&self) -> bool {
+        self.time.is_some() || self.period.is_some() || self.timeouts.is_some() || self.running.is_some()
+    }
+
+    fn payload(&self) -> Result<Payload, String> {
+        let time = self
+            .time
+            .ok_or_else(|| "--period, --timeouts and --state are sent with the clock and need --time".to_string())?;
+        let running = self.running.unwrap_or(false);
+        let (timeout_home, timeout_away) = match self.timeouts {
+            Some((home, away)) => (digit("--timeouts", home)?, digit("--timeouts", away)?),
+            None => (b' ', b' '),
+        };
+        let period = self.period.unwrap_or(b' ');
+
+        let mut label = format!("clock {} {}", time, if running { "running" } else { "paused" });
+        match period {
+            b' ' => {}
+            b'O' => label.push_str(", overtime"),
+            _ => label.push_str(&format!(", period {}", period as char)),
+        }
+        if let Some((home, away)) = self.timeouts {
+            label.push_str(&format!(", time-outs {}-{}", home, away));
+        }
+        Ok(Payload {
+            label,
+            bytes: clock_frame(time, running, period, (timeout_home, timeout_away)),
+        })
+    }
+}
+
+impl fmt::Display for ClockTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Minutes(minutes, seconds) => write!(f, "{:02}:{:02}", minutes, seconds),
+            Self::Tenths(seconds, tenths) => write!(f, "{:02}.{}", seconds, tenths),
+        }
+    }
+}
+
+/// Message 18 frame: clock, whether it runs, period character (`1`-`4`, `O`, blank) and
+/// the time-outs as display characters.
+pub(crate) fn clock_frame(time: ClockTime, running: bool, period: u8, timeouts: (u8, u8)) -> Vec<u8> {
+    let mut status = 0x80;
+    if !running {
+        status |= 0x02;
+    }
+    let digits = match time {
+        ClockTime::Minutes(minutes, seconds) => format!("{:02}{:02}", minutes, seconds),
+        ClockTime::Tenths(seconds, tenths) => {
+            status |= 0x10;
+            format!("{:02} {}", seconds, tenths)
+        }
+    };
+
+    let mut message = b"18".to_vec();
+    message.push(status);
+    message.push(b'5');
+    message.extend_from_slice(digits.as_bytes());
+    message.extend_from_slice(&[timeouts.0, timeouts.1, b' ', b' ', period, b' ']);
+    ProtocolFrame::new(FRAME_ADDRESS, FRAME_CTRL, message).to_bytes()
+}
+
+/// Message 30 frame.
+pub(crate) fn scores_frame(home: u32, away: u32) -> Vec<u8> {
+    let message = format!("305{:>3}{:>3}", home, away);
+    ProtocolFrame::new(FRAME_ADDRESS, FRAME_CTRL, message.into_bytes()).to_bytes()
+}
+
+/// Message 31 frame, the fouls as display characters.
+pub(crate) fn fouls_frame(home: u8, away: u8) -> Vec<u8> {
+    let message = [b'3', b'1', b'5', b' ', home, b' ', away, b' ', b' ', b' ', b' '];
+    ProtocolFrame::new(FRAME_ADDRESS, FRAME_CTRL, message.to_vec()).to_bytes()
+}
+
+fn scores_payload(home: u32, away: u32) -> Payload {
+    Payload {
+        label: format!("scores {}-{}", home, away),
+        bytes: scores_frame(home, away),
+    }
+}
+
+fn fouls_payload(home: u32, away: u32) -> Result<Payload, String> {
+    Ok(Payload {
+        label: format!("fouls {}-{}", home, away),
+        bytes: fouls_frame(digit("--fouls", home)?, digit("--fouls", away)?),
+    })
+}
+
+/// The single display digit of `value`.
+fn digit(flag: &str, value: u32) -> Result<u8, String> {
+    if value > 9 {
+        return Err(format!("Invalid {} {}: the console shows a single digit", flag, value));
+    }
+    Ok(b'0' + value as u8)
+}
+
+/// `<home>,<away>`, each 0-9.
+fn pair(value: &str) -> Result<(u32, u32), String> {
+    let digit = |n: &str| n.trim().parse().ok().filter(|n| *n <= 9);
+    value
+        .split_once(',')
+        .and_then(|(home, away)| Some((digit(home)?, digit(away)?)))
+        .ok_or_else(|| "expected <home>,<away>, each 0 to 9".to_string())
+}
+
+fn clock_state(value: &str) -> Result<bool, String> {
+    match value {
+        "running" => Ok(true),
+        "paused" => Ok(false),
+        _ => Err("expected running or paused".to_string()),
+    }
+}
+
+/// `m:ss` / `mm:ss`, or `ss.t` for the last minute.
+fn parse_time(value: &str) -> Result<ClockTime, String> {
+    let invalid = || "expected m:ss or ss.t".to_string();
+    if let Some((minutes, seconds)) = value.split_once(':') {
+        let minutes = minutes.parse().ok().filter(|m| *m <= 99).ok_or_else(invalid)?;
+        let seconds = seconds.parse().ok().filter(|s| *s <= 59).ok_or_else(invalid)?;
+        Ok(ClockTime::Minutes(minutes, seconds))
+    } else if let Some((seconds, tenths)) = value.split_once('.') {
+        let seconds = seconds.parse().ok().filter(|s| *s <= 59).ok_or_else(invalid)?;
+        let tenths = tenths.parse().ok().filter(|t| *t <= 9).ok_or_else(invalid)?;
+        Ok(ClockTime::Tenths(seconds, tenths))
+    } else {
+        Err(invalid())
+    }
+}
+
+/// Period character of message 18: `1`-`4`, or `O` for overtime.
+fn parse_period(value: &str) -> Result<u8, String> {
+    match value.to_ascii_uppercase().as_str() {
+        "1" | "2" | "3" | "4" => Ok(value.as_bytes()[0]),
+        "OT" | "O" => Ok(b'O'),
+        _ => Err("expected 1-4 or OT".to_string()),
+    }
+}
+
+/// Hex bytes, separated by spaces or not: `"01 7F 02"`, `017F02`, `0x01 0x7F`.
+fn parse_hex(value: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    for token in value.split_whitespace() {
+        let digits = token.trim_start_matches("0x");
+        if digits.len() % 2 != 0 {
+            return Err(format!("odd number of digits in {}", token));
+        }
+        for i in (0..digits.len()).step_by(2) {
+            let byte = digits
+                .get(i..i + 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| format!("{} is not a hex byte", token))?;
+            bytes.push(byte);
+        }
+    }
+    if bytes.is_empty() {
+        return Err("expected at least one byte".to_string());
+    }
+    Ok(bytes)
+}
+
+/// `100ms`, `2s` or a bare number of milliseconds.
+pub(crate) fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = |e: std::num::ParseIntError| e.to_string();
+    if let Some(millis) = value.strip_suffix("ms") {
+        millis.parse().map(Duration::from_millis).map_err(invalid)
+    } else if let Some(secs) = value.strip_suffix('s') {
+        secs.parse().map(Duration::from_secs).map_err(invalid)
+    } else {
+        value.parse().map(Duration::from_millis).map_err(invalid)
+    }
+}
+
+/// What became of one payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SendResult {
+    pub label: String,
+    /// The server's `ACK` / `ERR <reason>` lines, one per frame in the payload; empty when
+    /// it didn't answer (not started with `--ack`, or UDP).
+    pub replies: Vec<String>,
+}
+
+impl SendResult {
+    pub fn is_error(&self) -> bool {
+        self.replies.iter().any(|reply| reply.starts_with("ERR"))
+    }
+}
+
+impl fmt::Display for SendResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.replies.is_empty() {
+            write!(f, "{}: sent", self.label)
+        } else {
+            write!(f, "{}: {}", self.label, self.replies.join(", "))
+        }
+    }
+}
+
+/// Send the payloads, calling `on_result` as each one is answered (or not).
+pub fn run(options: &SendOptions, mut on_result: impl FnMut(&SendResult)) -> io::Result<Vec<SendResult>> {
+    let mut results = Vec::new();
+    if options.udp {
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        let mut signer = options.auth_token.as_deref().map(auth::DatagramSigner::new);
+        for round in 0..options.repeat {
+            if round > 0 {
+                thread::sleep(options.interval);
+            }
+            for payload in &options.payloads {
+                match &mut signer {
+                    Some(signer) => socket.send_to(&signer.sign(&payload.bytes), &options.address)?,
+                    None => socket.send_to(&payload.bytes, &options.address)?,
+                };
+                let result = SendResult {
+                    label: payload.label.clone(),
+                    replies: Vec::new(),
+                };
+                on_result(&result);
+                results.push(result);
+            }
+        }
+        return Ok(results);
+    }
+
+    let mut stream = TcpStream::connect(&options.address)?;
+    if let Some(token) = &options.auth_token {
+        stream.write_all(format!("AUTH {}\n", token).as_bytes())?;
+    }
+    let mut pending = Vec::new();
+    // Stop waiting once the server has shown it doesn't reply
+    let mut expect_replies = true;
+    for round in 0..options.repeat {
+        if round > 0 {
+            thread::sleep(options.interval);
+        }
+        for payload in &options.payloads {
+            stream.write_all(&payload.bytes)?;
+            let replies = if expect_replies {
+                read_replies(&mut stream, &mut pending, frame_count(&payload.bytes), options.timeout)?
+            } else {
+                Vec::new()
+            };
+            if replies.is_empty() && expect_replies {
+                info!("No reply within {:?}; start the server with --ack to get ACK/ERR lines", options.timeout);
+                expect_replies = false;
+            }
+            let result = SendResult {
+                label: payload.label.clone(),
+                replies,
+            };
+            on_result(&result);
+            results.push(result);
+        }
+    }
+    Ok(results)
+}
+
+/// Number of frames (valid or not) the server will see in `bytes`, each answered with a line.
+fn frame_count(bytes: &[u8]) -> usize {
+    let mut decoder = FrameDecoder::new(false, &[0x00]);
+    decoder.push(bytes);
+    std::iter::from_fn(|| decoder.next_frame()).count()
+}
+
+/// Read up to `count` reply lines, giving up `timeout` after the last one arrived.
+fn read_replies(stream: &mut TcpStream, pending: &mut Vec<u8>, count: usize, timeout: Duration) -> io::Result<Vec<String>> {
+    let mut replies = Vec::new();
+    let mut deadline = Instant::now() + timeout;
+    let mut buffer = [0u8; 256];
+    while replies.len() < count {
+        if let Some(end) = pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = pending.drain(..=end).collect();
+            replies.push(String::from_utf8_lossy(&line).trim().to_string());
+            deadline = Instant::now() + timeout;
+            continue;
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        stream.set_read_timeout(Some(remaining))?;
+        match stream.read(&mut buffer) {
+            Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "server closed the connection")),
+            Ok(n) => pending.extend_from_slice(&buffer[..n]),
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(replies)
+}
+
+/// Entry point of `scoreboard-rust send ...`; returns the process exit code.
+///
+/// 0 when everything was sent and no frame was refused, 1 otherwise, 2 for bad arguments.
+pub fn run_command(args: SendArgs) -> i32 {
+    let options = match SendOptions::from_args(args) {
+        Ok(options) => options,
+        Err(e) => {
+            error!("{}", e);
+            return 2;
+        }
+    };
+
+    match run(&options, |result| println!("{}", result)) {
+        Ok(results) if results.iter().any(SendResult::is_error) => 1,
+        Ok(_) => 0,
+        Err(e) => {
+            error!("Sending to {} failed: {}", options.address, e);
+            1
+        }
+    }
+}
@@ -0,0 +1,633 @@
+use crate::{
+    cli::send::{self, ClockTime},
+    events::Side,
+    rules::RuleSet,
+};
+use std::{
+    collections::VecDeque,
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    net::{Shutdown, TcpStream},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::{error, info, warn};
+
+/// Overtime periods last 5 minutes under every rule set.
+const OVERTIME_LENGTH: u32 = 3000;
+/// Breaks of the random game, in simulated seconds: between quarters and at halftime.
+const QUARTER_BREAK: u32 = 120;
+const HALFTIME_BREAK: u32 = 900;
+
+/// Settings for `scoreboard-rust simulate`.
+#[derive(Debug, Clone)]
+pub struct SimulateOptions {
+    /// Ingest address of the server.
+    pub target: String,
+    /// Seed of the random game; taken from the time of day when unset.
+    pub seed: Option<u64>,
+    /// How many simulated seconds pass per real second.
+    pub speed: f64,
+    /// Period lengths, foul resets and time-out allowances.
+    pub rules: RuleSet,
+    /// Scripted steps played instead of a random game.
+    pub scenario: Option<Scenario>,
+}
+
+/// The arguments of `scoreboard-rust simulate`, read by [`SimulateOptions::from_args`].
+#[derive(Debug, Clone, clap::Args)]
+pub struct SimulateArgs {
+    /// Ingest address of the server.
+    #[arg(long, value_name = "HOST:PORT", default_value = "127.0.0.1:4001")]
+    target: String,
+    /// Seed of the random game; taken from the time of day when unset.
+    #[arg(long, value_name = "N", conflicts_with = "scenario")]
+    seed: Option<u64>,
+    /// How many simulated seconds pass per real second, such as 20x.
+    #[arg(long, value_name = "FACTOR", default_value = "1x", value_parser = speed)]
+    speed: f64,
+    /// Period lengths, foul resets and time-out allowances.
+    #[arg(long, visible_alias = "ruleset", value_name = "fiba|nba|ncaa", default_value = "fiba", ignore_case = true, hide_possible_values = true)]
+    rules: RuleSet,
+    /// Scripted steps played instead of a random game, `- <m:ss> <event>` per line.
+    #[arg(long, value_name = "FILE")]
+    scenario: Option<PathBuf>,
+}
+
+impl SimulateOptions {
+    /// The options of the `simulate` arguments. The scenario is read and checked here, so a
+    /// broken script fails before anything is sent.
+    pub fn from_args(args: SimulateArgs) -> Result<Self, String> {
+        Ok(Self {
+            target: args.target,
+            seed: args.seed,
+            speed: args.speed,
+            rules: args.rules,
+            scenario: args.scenario.as_deref().map(Scenario::load).transpose()?,
+        })
+    }
+}
+
+/// `20x` or `20`: simulated seconds per real second.
+fn speed(value: &str) -> Result<f64, String> {
+    value
+        .trim_end_matches('x')
+        .parse()
+        .ok()
+        .filter(|speed: &f64| speed.is_finite() && *speed > 0.0 && *speed <= 10_000.0)
+        .ok_or_else(|| "expected a factor such as 20x".to_string())
+}
+
+/// A scripted game: one step per line, `- <m:ss> <event> [arguments]`, for instance
+///
+/// ```text
+/// - 0:00 start
+/// - 0:05 score home 2
+/// - 0:12 foul away
+/// - 0:30 timeout home
+/// - 1:00 period
+/// ```
+///
+/// Times are simulated time since the start of the run, in order. Events are `start`,
+/// `stop`, `clock <m:ss>`, `score <home|away> <1-3>`, `foul <side>`, `timeout <side>`,
+/// `period` (on to the next period, clock stopped) and `end`. Blank lines and `#` comments
+/// are skipped, so the file also reads as a YAML list of strings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scenario {
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    /// Simulated seconds since the start of the run.
+    pub at: u32,
+    /// Line of the step in its file, for messages.
+    pub line: usize,
+    pub action: Action,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Start,
+    Stop,
+    /// Set the game clock, in tenths of a second.
+    Clock(u32),
+    Score(Side, u32),
+    Foul(Side),
+    Timeout(Side),
+    Period,
+    End,
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Can't read scenario {}: {}", path.display(), e))?;
+        Self::parse(&text, &path.display().to_string())
+    }
+
+    /// Parse a scenario; errors read `<name>:<line>: <problem>`.
+    pub fn parse(text: &str, name: &str) -> Result<Self, String> {
+        let mut steps: Vec<Step> = Vec::new();
+        for (index, raw) in text.lines().enumerate() {
+            let line = index + 1;
+            let error = |message: String| format!("{}:{}: {}", name, line, message);
+            let content = match raw.find('#') {
+                Some(comment) if comment == 0 || raw[..comment].ends_with(char::is_whitespace) => &raw[..comment],
+                _ => raw,
+            };
+            let content = content.trim();
+            if content.is_empty() || content == "---" {
+                continue;
+            }
+            let item = content
+                .strip_prefix('-')
+                .map(|item| item.trim().trim_matches(|c| c == '"' || c == '\''))
+                .ok_or_else(|| error(format!("expected a step `- <m:ss> <event>`, found `{}`", content)))?;
+
+            let mut words = item.split_whitespace();
+            let at = words
+                .next()
+                .and_then(parse_seconds)
+                .ok_or_else(|| error("a step starts with its time, m:ss".to_string()))?;
+            if let Some(previous) = steps.last() {
+                if previous.action == Action::End {
+                    return Err(error("nothing can follow `end`".to_string()));
+                }
+                if at < previous.at {
+                    return Err(error(format!("{} comes before the previous step at {}", mmss(at), mmss(previous.at))));
+                }
+            }
+
+            let event = words.next().ok_or_else(|| error("missing event after the time".to_string()))?;
+            let args: Vec<&str> = words.collect();
+            let side = |args: &[&str]| match args.first() {
+                Some(&"home") => Ok(Side::Home),
+                Some(&"away") => Ok(Side::Away),
+                Some(other) => Err(error(format!("`{}` needs home or away, found `{}`", event, other))),
+                None => Err(error(format!("`{}` needs home or away", event))),
+            };
+            let (action, arity) = match event {
+                "start" => (Action::Start, 0),
+                "stop" => (Action::Stop, 0),
+                "period" => (Action::Period, 0),
+                "end" => (Action::End, 0),
+                "clock" => {
+                    let clock = args
+                        .first()
+                        .and_then(|value| parse_seconds(value))
+                        .filter(|seconds| *seconds < 100 * 60)
+                        .ok_or_else(|| error("`clock` needs a time, m:ss".to_string()))?;
+                    (Action::Clock(clock * 10), 1)
+                }
+                "score" => {
+                    let side = side(&args)?;
+                    let points = match args.get(1).map(|points| points.parse::<u32>()) {
+                        Some(Ok(points @ 1..=3)) => points,
+                        _ => return Err(error("`score` needs a side and 1, 2 or 3 points".to_string())),
+                    };
+                    (Action::Score(side, points), 2)
+                }
+                "foul" => (Action::Foul(side(&args)?), 1),
+                "timeout" => (Action::Timeout(side(&args)?), 1),
+                other => {
+                    return Err(error(format!(
+                        "unknown event `{}` (expected start, stop, clock, score, foul, timeout, period or end)",
+                        other
+                    )))
+                }
+            };
+            if args.len() > arity {
+                return Err(error(format!("unexpected `{}` after `{}`", args[arity..].join(" "), event)));
+            }
+            steps.push(Step { at, line, action });
+        }
+
+        if steps.is_empty() {
+            return Err(format!("{}: the scenario has no steps", name));
+        }
+        Ok(Self { steps })
+    }
+}
+
+/// `m:ss` (or plain seconds) as seconds.
+fn parse_seconds(value: &str) -> Option<u32> {
+    match value.split_once(':') {
+        Some((minutes, seconds)) if seconds.len() == 2 => {
+            let seconds: u32 = seconds.parse().ok().filter(|s| *s < 60)?;
+            Some(minutes.parse::<u32>().ok()? * 60 + seconds)
+        }
+        Some(_) => None,
+        None => value.parse().ok(),
+    }
+}
+
+fn mmss(seconds: u32) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Home => "HOME",
+        Side::Away => "AWAY",
+    }
+}
+
+fn index(side: Side) -> usize {
+    match side {
+        Side::Home => 0,
+        Side::Away => 1,
+    }
+}
+
+/// The simulated game and the console it stands in for, writing its frames to `out`.
+struct Game<W: Write> {
+    rules: RuleSet,
+    /// 1-4, overtime from 5.
+    period: u32,
+    /// Game clock in tenths of a second.
+    clock: u32,
+    running: bool,
+    scores: [u32; 2],
+    fouls: [u32; 2],
+    timeouts: [u32; 2],
+    out: W,
+}
+
+impl<W: Write> Game<W> {
+    fn new(rules: RuleSet, out: W) -> Self {
+        let mut game = Self {
+            rules,
+            period: 1,
+            clock: 0,
+            running: false,
+            scores: [0; 2],
+            fouls: [0; 2],
+            timeouts: [0; 2],
+            out,
+        };
+        game.enter_period(1);
+        game
+    }
+
+    /// Reset the clock, fouls and time-outs for `period`.
+    fn enter_period(&mut self, period: u32) {
+        self.period = period;
+        self.running = false;
+        self.clock = if period > 4 {
+            OVERTIME_LENGTH
+        } else {
+            self.rules.period_length().as_secs() as u32 * 10
+        };
+        if self.rules.resets_team_fouls(period) {
+            self.fouls = [0; 2];
+        }
+        // Roughly each rule set's allowances
+        let timeouts = match (self.rules, period) {
+            (RuleSet::Nba, 1) => Some(7),
+            (RuleSet::Ncaa, 1) => Some(4),
+            (_, 1) => Some(2),
+            (RuleSet::Fiba, 3) => Some(3),
+            (RuleSet::Nba, 5..) => Some(2),
+            (RuleSet::Fiba, 5..) => Some(1),
+            (RuleSet::Ncaa, 5..) => Some(self.timeouts.iter().max().copied().unwrap_or(0) + 1),
+            _ => None,
+        };
+        if let Some(timeouts) = timeouts {
+            self.timeouts = [timeouts; 2];
+        }
+    }
+
+    /// `Q2 07:32` or `OT1 01:05`.
+    fn when(&self) -> String {
+        let period = if self.period > 4 {
+            format!("OT{}", self.period - 4)
+        } else {
+            format!("Q{}", self.period)
+        };
+        format!("{} {}", period, self.clock_time())
+    }
+
+    fn clock_time(&self) -> ClockTime {
+        if self.clock < 600 {
+            ClockTime::Tenths(self.clock / 10, self.clock % 10)
+        } else {
+            let seconds = self.clock.div_ceil(10);
+            ClockTime::Minutes(seconds / 60, seconds % 60)
+        }
+    }
+
+    fn score_line(&self) -> String {
+        format!("HOME {} - {} AWAY", self.scores[0], self.scores[1])
+    }
+
+    fn send_clock(&mut self) -> io::Result<()> {
+        let period = if self.period > 4 { b'O' } else { b'0' + self.period as u8 };
+        let timeouts = (display_digit(self.timeouts[0]), display_digit(self.timeouts[1]));
+        let frame = send::clock_frame(self.clock_time(), self.running, period, timeouts);
+        self.out.write_all(&frame)
+    }
+
+    fn send_scores(&mut self) -> io::Result<()> {
+        let frame = send::scores_frame(self.scores[0], self.scores[1]);
+        self.out.write_all(&frame)
+    }
+
+    fn send_fouls(&mut self) -> io::Result<()> {
+        let frame = send::fouls_frame(display_digit(self.fouls[0]), display_digit(self.fouls[1]));
+        self.out.write_all(&frame)
+    }
+
+    fn send_all(&mut self) -> io::Result<()> {
+        self.send_scores()?;
+        self.send_fouls()?;
+        self.send_clock()
+    }
+
+    /// One simulated second of a running clock; returns whether the period just ended.
+    fn tick(&mut self) -> io::Result<bool> {
+        if !self.running {
+            return Ok(false);
+        }
+        self.clock = self.clock.saturating_sub(10);
+        if self.clock == 0 {
+            self.running = false;
+        }
+        self.send_clock()?;
+        Ok(self.clock == 0)
+    }
+
+    /// Play `action`, returning the commentary line.
+    fn apply(&mut self, action: Action) -> io::Result<String> {
+        let when = self.when();
+        let line = match action {
+            Action::Start => {
+                self.running = true;
+                self.send_clock()?;
+                format!("[{}] Clock running", when)
+            }
+            Action::Stop => {
+                self.running = false;
+                self.send_clock()?;
+                format!("[{}] Clock stopped", when)
+            }
+            Action::Clock(tenths) => {
+                self.clock = tenths;
+                self.send_clock()?;
+                format!("[{}] Clock set to {}", when, self.clock_time())
+            }
+            Action::Score(side, points) => {
+                self.scores[index(side)] += points;
+                self.send_scores()?;
+                format!("[{}] {} scores {} ({})", when, side_name(side), points, self.score_line())
+            }
+            Action::Foul(side) => {
+                self.fouls[index(side)] += 1;
+                self.running = false;
+                self.send_fouls()?;
+                self.send_clock()?;
+                format!("[{}] Foul on {} (team fouls {})", when, side_name(side), self.fouls[index(side)])
+            }
+            Action::Timeout(side) => {
+                let left = &mut self.timeouts[index(side)];
+                *left = left.saturating_sub(1);
+                let left = *left;
+                self.running = false;
+                self.send_clock()?;
+                format!("[{}] {} time-out ({} left)", when, side_name(side), left)
+            }
+            Action::Period => {
+                let ended = format!("End of {}: {}", when.split(' ').next().unwrap_or_default(), self.score_line());
+                self.enter_period(self.period + 1);
+                self.send_all()?;
+                format!("{}; {} next", ended, self.when())
+            }
+            Action::End => {
+                self.running = false;
+                self.send_clock()?;
+                format!("Final: {}", self.score_line())
+            }
+        };
+        Ok(line)
+    }
+}
+
+/// Display character of a count the console shows as one digit.
+fn display_digit(count: u32) -> u8 {
+    b'0' + count.min(9) as u8
+}
+
+/// SplitMix64: small, seedable and the same on every platform, so a seed replays its game.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn one_in(&mut self, n: u64) -> bool {
+        self.next().is_multiple_of(n)
+    }
+
+    fn side(&mut self) -> Side {
+        if self.one_in(2) {
+            Side::Home
+        } else {
+            Side::Away
+        }
+    }
+}
+
+/// Play the game over a TCP connection to `options.target`, calling `say` with a line per
+/// event. Returns the final scores (home, away).
+pub fn run(options: &SimulateOptions, mut say: impl FnMut(&str)) -> io::Result<(u32, u32)> {
+    let stream = TcpStream::connect(&options.target)?;
+    // Replies of a server started with --ack; read so they never fill its send buffer
+    let replies = stream.try_clone()?;
+    let reader = thread::spawn(move || {
+        for line in BufReader::new(replies).lines() {
+            match line {
+                Ok(line) if line.starts_with("ERR") => warn!("The server refused a frame: {}", line),
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+    });
+
+    let step = Duration::from_secs_f64(1.0 / options.speed);
+    let mut game = Game::new(options.rules, stream);
+    game.send_all()?;
+
+    match &options.scenario {
+        Some(scenario) => play_scenario(&mut game, scenario, step, &mut say)?,
+        None => {
+            let seed = options.seed.unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|now| now.as_nanos() as u64)
+                    .unwrap_or_default()
+            });
+            say(&format!("Random game, seed {} (replay it with --seed {})", seed, seed));
+            let mut random = RandomGame::new(seed);
+            while !random.step(&mut game, &mut say)? {
+                thread::sleep(step);
+            }
+        }
+    }
+
+    // Let the server answer the last frames and close, rather than leave it writing to nobody
+    game.out.shutdown(Shutdown::Write)?;
+    let _ = reader.join();
+    Ok((game.scores[0], game.scores[1]))
+}
+
+fn play_scenario(game: &mut Game<TcpStream>, scenario: &Scenario, step: Duration, say: &mut impl FnMut(&str)) -> io::Result<()> {
+    let mut steps = scenario.steps.iter().peekable();
+    let mut elapsed = 0;
+    loop {
+        while let Some(next) = steps.next_if(|next| next.at <= elapsed) {
+            say(&game.apply(next.action)?);
+        }
+        if steps.peek().is_none() {
+            return Ok(());
+        }
+        if game.tick()? {
+            say(&format!("[{}] End of the period", game.when()));
+        }
+        thread::sleep(step);
+        elapsed += 1;
+    }
+}
+
+/// The random game's play, one simulated second per [`step`](Self::step).
+struct RandomGame {
+    rng: Rng,
+    /// Simulated seconds until the clock starts again, and whether that starts a period
+    pause: u32,
+    tip_off: bool,
+}
+
+impl RandomGame {
+    fn new(seed: u64) -> Self {
+        Self { rng: Rng(seed), pause: 0, tip_off: true }
+    }
+
+    /// Play one simulated second of `game`; returns whether the game is over.
+    fn step<W: Write>(&mut self, game: &mut Game<W>, say: &mut impl FnMut(&str)) -> io::Result<bool> {
+        let rng = &mut self.rng;
+        if game.running {
+            if rng.one_in(34) {
+                let points = match rng.next() % 20 {
+                    0..=2 => 1,
+                    3..=14 => 2,
+                    _ => 3,
+                };
+                let side = rng.side();
+                say(&game.apply(Action::Score(side, points))?);
+            } else if rng.one_in(60) {
+                let side = rng.side();
+                say(&game.apply(Action::Foul(side))?);
+                self.pause = 4;
+            } else if rng.one_in(300) {
+                let side = rng.side();
+                if game.timeouts[index(side)] > 0 {
+                    say(&game.apply(Action::Timeout(side))?);
+                    self.pause = 60;
+                }
+            }
+
+            if game.tick()? {
+                let tied = game.scores[0] == game.scores[1];
+                if game.period >= 4 && !tied {
+                    say(&game.apply(Action::End)?);
+                    return Ok(true);
+                }
+                say(&game.apply(Action::Period)?);
+                self.pause = if game.period == 3 { HALFTIME_BREAK } else { QUARTER_BREAK };
+                self.tip_off = true;
+            }
+        } else if self.pause > 0 {
+            self.pause -= 1;
+        } else {
+            // Restarts after fouls and time-outs go without a line
+            let line = game.apply(Action::Start)?;
+            if self.tip_off {
+                say(&line);
+                self.tip_off = false;
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Keeps every frame the game writes apart (each is a single write), instead of sending it.
+#[derive(Default)]
+struct FrameQueue(VecDeque<Vec<u8>>);
+
+impl Write for FrameQueue {
+    fn write(&mut self, frame: &[u8]) -> io::Result<usize> {
+        self.0.push_back(frame.to_vec());
+        Ok(frame.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The frames of endless random games, one after the other, as fast as they are asked for:
+/// load for `bench`, which sends and times them one by one.
+pub(crate) struct RandomFrames {
+    rules: RuleSet,
+    game: Game<FrameQueue>,
+    random: RandomGame,
+    over: bool,
+}
+
+impl RandomFrames {
+    pub(crate) fn new(rules: RuleSet, seed: u64) -> Self {
+        let mut game = Game::new(rules, FrameQueue::default());
+        // Into memory: it can't fail
+        let _ = game.send_all();
+        Self { rules, game, random: RandomGame::new(seed), over: false }
+    }
+
+    /// The next frame the console would send.
+    pub(crate) fn next_frame(&mut self) -> Vec<u8> {
+        loop {
+            if let Some(frame) = self.game.out.0.pop_front() {
+                return frame;
+            }
+            if self.over {
+                // The next game, with the next seed
+                *self = Self::new(self.rules, self.random.rng.next());
+            } else {
+                self.over = self.random.step(&mut self.game, &mut |_| {}).unwrap_or(true);
+            }
+        }
+    }
+}
+
+/// Entry point of `scoreboard-rust simulate ...`; returns the process exit code.
+pub fn run_command(args: SimulateArgs) -> i32 {
+    let options = match SimulateOptions::from_args(args) {
+        Ok(options) => options,
+        Err(e) => {
+            error!("{}", e);
+            return 2;
+        }
+    };
+
+    info!("Simulating a game against {} at {}x", options.target, options.speed);
+    match run(&options, |line| println!("{}", line)) {
+        Ok(_) => 0,
+        Err(e) => {
+            error!("Simulation against {} failed: {}", options.target, e);
+            1
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{
+    dashboard::{self, Dashboard},
+    state::{SharedState, LINK_TIMEOUT},
+    LinkStatus,
+};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::DefaultTerminal;
+use std::{
+    io::{self, IsTerminal},
+    sync::Arc,
+    time::Duration,
+};
+use tracing::error;
+
+/// How long a frame waits for a key; the states that came meanwhile are drawn with the next one.
+const TICK: Duration = Duration::from_millis(100);
+
+/// Draw `dashboard` until the user quits, calling `refresh` before each frame to bring it up
+/// to date. The terminal is restored on the way out, a panic included.
+fn run(dashboard: &mut Dashboard, refresh: impl FnMut(&mut Dashboard)) -> io::Result<()> {
+    if !io::stdout().is_terminal() {
+        return Err(io::Error::other("the dashboard needs a terminal on standard output"));
+    }
+    let mut terminal = ratatui::try_init()?;
+    let result = draw_until_quit(&mut terminal, dashboard, refresh);
+    ratatui::restore();
+    result
+}
+
+fn draw_until_quit(terminal: &mut DefaultTerminal, dashboard: &mut Dashboard, mut refresh: impl FnMut(&mut Dashboard)) -> io::Result<()> {
+    loop {
+        refresh(dashboard);
+        terminal.draw(|frame| frame.render_widget(&*dashboard, frame.area()))?;
+        if !event::poll(TICK)? {
+            continue;
+        }
+        // A resize is drawn by the next frame
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            // Raw mode turns Ctrl+C into a key
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+            KeyCode::Char('e') => dashboard.toggle_events(),
+            _ => {}
+        }
+    }
+}
+
+/// The terminal dashboard of a server in this process (`--tui`); calls `on_quit` when the
+/// user quits.
+pub fn run_embedded(shared: Arc<SharedState>, on_quit: impl FnOnce()) {
+    let states = shared.subscribe_states();
+    let mut dashboard = Dashboard::default();
+    if shared.state_age().is_some() || shared.manual_override() || shared.restored() {
+        dashboard.apply(shared.current());
+    }
+    let result = run(&mut dashboard, |dashboard| {
+        while let Some(state) = states.try_recv() {
+            dashboard.apply(state);
+        }
+        let link = match shared.link_status(LINK_TIMEOUT) {
+            LinkStatus::Active => "console active",
+            LinkStatus::Idle => "console idle (heartbeats only)",
+            LinkStatus::Dead => "no console",
+        };
+        dashboard.set_link(link);
+    });
+    match result {
+        Ok(()) => on_quit(),
+        // The server goes on without it
+        Err(e) => error!("Cannot show the dashboard: {}", e),
+    }
+}
+
+/// The arguments of `scoreboard-rust tui`.
+#[derive(Debug, Clone, clap::Args)]
+pub struct TuiArgs {
+    /// The server's `--subscribers` address.
+    #[arg(value_name = "HOST:PORT")]
+    address: String,
+}
+
+/// Entry point of `scoreboard-rust tui <host:port>`: the terminal dashboard of the server
+/// streaming states on `host:port` (its `--subscribers` address). Returns the exit code.
+pub fn run_command(args: &TuiArgs) -> i32 {
+    let updates = dashboard::subscribe(&args.address);
+    let mut dashboard = Dashboard::default();
+    let result = run(&mut dashboard, |dashboard| {
+        for update in updates.try_iter() {
+            dashboard.update(update);
+        }
+    });
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            error!("Cannot show the dashboard: {}", e);
+            1
+        }
+    }
+}
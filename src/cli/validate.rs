@@ -0,0 +1,341 @@
+use crate::{
+    apply_frame,
+    basketball_parser::MESSAGES,
+    cli::replay::Capture,
+    events::{self, clock_tenths, GameEvent},
+    framing::FrameDecoder,
+    logging, GameState,
+};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+use tracing::error;
+
+/// Points a single frame may add to a score before the change looks like a misread.
+const MAX_SCORE_STEP: i64 = 3;
+
+/// How the bytes of a file to validate are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum InputFormat {
+    /// Whichever of the others the file looks like.
+    #[default]
+    Auto,
+    /// The bytes as they came off the wire, e.g. from `nc -l 4001 > capture.bin`.
+    Raw,
+    /// A `data_log/` capture: one bracketed hex list per read, `[01, 7F, 02, ...]`.
+    Capture,
+    /// Hex bytes separated by spaces, commas or nothing (`01 7F 02`, `017F02`), with `#` comments.
+    Hex,
+}
+
+impl InputFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Raw => "raw",
+            Self::Capture => "capture",
+            Self::Hex => "hex",
+        }
+    }
+
+    /// The format `data` is in: raw when it holds control characters or isn't text, a capture
+    /// when a line holds a bracketed list, hex otherwise.
+    pub fn detect(data: &[u8]) -> Self {
+        let Ok(text) = std::str::from_utf8(data) else {
+            return Self::Raw;
+        };
+        if text.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+            Self::Raw
+        } else if text.lines().any(|line| line.contains('[') && line.contains(']')) {
+            Self::Capture
+        } else {
+            Self::Hex
+        }
+    }
+
+    /// The protocol bytes `data` holds, written in this format (not [`Auto`](Self::Auto)).
+    pub fn decode(self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let text = || std::str::from_utf8(data).map_err(|_| "not a text file".to_string());
+        match self {
+            Self::Auto => Self::detect(data).decode(data),
+            Self::Raw => Ok(data.to_vec()),
+            Self::Capture => {
+                let capture = Capture::parse(text()?, 1.0)?;
+                Ok((0..capture.len()).flat_map(|line| capture.bytes(line).iter().copied()).collect())
+            }
+            Self::Hex => parse_hex(text()?),
+        }
+    }
+}
+
+/// Hex bytes of every line.
+fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        bytes.extend(parse_hex_line(line).map_err(|e| format!("line {}: {}", number + 1, e))?);
+    }
+    Ok(bytes)
+}
+
+/// Hex bytes of a line, up to a `#`.
+pub(crate) fn parse_hex_line(line: &str) -> Result<Vec<u8>, String> {
+    let line = line.split_once('#').map_or(line, |(data, _)| data);
+    let mut bytes = Vec::new();
+    for token in line.split(|c: char| c == ',' || c.is_whitespace()).filter(|token| !token.is_empty()) {
+        let digits = token.trim_start_matches("0x");
+        if digits.len() % 2 != 0 {
+            return Err(format!("Invalid hex {}: odd number of digits", token));
+        }
+        for i in (0..digits.len()).step_by(2) {
+            let byte = digits
+                .get(i..i + 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| format!("Invalid hex {}: not a hex byte", token))?;
+            bytes.push(byte);
+        }
+    }
+    Ok(bytes)
+}
+
+/// Settings for `scoreboard-rust validate`.
+#[derive(Debug, Clone, clap::Args)]
+pub struct ValidateOptions {
+    /// The capture to check.
+    #[arg(value_name = "FILE")]
+    pub path: PathBuf,
+    /// How the bytes of the file are written.
+    #[arg(long, value_enum, default_value_t)]
+    pub format: InputFormat,
+    /// Accept frames whose LRC does not match, as the server's `--lenient` does.
+    #[arg(long)]
+    pub lenient: bool,
+    /// Fail on suspicious transitions too, not only on frames the parser refuses.
+    #[arg(long)]
+    pub strict: bool,
+    /// Print the report as JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// A state change the console wouldn't normally make, worth a look before the game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Transition {
+    /// Number of the frame that made it, from 1, counting invalid frames.
+    pub frame: u64,
+    pub description: String,
+}
+
+/// What the parser made of a file.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    /// The format the file was read as.
+    pub format: InputFormat,
+    /// Protocol bytes in the file.
+    pub bytes: u64,
+    /// Complete frames, valid or not.
+    pub frames: u64,
+    /// Frames the parser read into the game state.
+    pub parsed: u64,
+    /// Parsed frames per message type (`"30"`).
+    pub message_types: BTreeMap<String, u64>,
+    /// Valid frames of message types the server doesn't read, per type; consoles send plenty.
+    pub ignored: BTreeMap<String, u64>,
+    /// Frames that weren't parsed, per reason (`"LRC validation failed"`, `"message type 30 too short"`).
+    pub errors: BTreeMap<String, u64>,
+    /// Bytes outside any frame, including an unfinished frame at the end.
+    pub skipped_bytes: u64,
+    /// Keepalive `0x00` bytes between frames, which the server ignores.
+    pub heartbeats: u64,
+    /// The state after the first frame that changed it, and at the end.
+    pub first_state: Option<GameState>,
+    pub last_state: Option<GameState>,
+    pub suspicious: Vec<Transition>,
+}
+
+impl Report {
+    /// Whether the file passes: frames were parsed and none was refused; with `strict`, no
+    /// transition was suspicious either.
+    pub fn passed(&self, strict: bool) -> bool {
+        self.parsed > 0 && self.errors.is_empty() && (!strict || self.suspicious.is_empty())
+    }
+
+    /// Write the report for reading.
+    pub fn write_text(&self, out: &mut impl Write, strict: bool) -> io::Result<()> {
+        let counts = |counts: &BTreeMap<String, u64>| counts.iter().map(|(name, count)| format!("{} ×{}", name, count)).collect::<Vec<_>>().join(", ");
+        writeln!(out, "Format:        {} ({} bytes)", self.format.name(), self.bytes)?;
+        writeln!(out, "Frames:        {} parsed of {}", self.parsed, self.frames)?;
+        if !self.message_types.is_empty() {
+            writeln!(out, "Message types: {}", counts(&self.message_types))?;
+        }
+        if !self.ignored.is_empty() {
+            writeln!(out, "Ignored types: {}", counts(&self.ignored))?;
+        }
+        if !self.errors.is_empty() {
+            writeln!(out, "Errors:        {}", counts(&self.errors))?;
+        }
+        writeln!(out, "Skipped bytes: {}, heartbeats: {}", self.skipped_bytes, self.heartbeats)?;
+        if let Some(state) = &self.first_state {
+            writeln!(out, "First state:   {}", describe(state))?;
+        }
+        if let Some(state) = &self.last_state {
+            writeln!(out, "Last state:    {}", describe(state))?;
+        }
+        for transition in &self.suspicious {
+            writeln!(out, "Suspicious:    frame {}: {}", transition.frame, transition.description)?;
+        }
+        let verdict = if self.passed(strict) {
+            "PASS"
+        } else if self.parsed == 0 {
+            "FAIL: no frame could be parsed"
+        } else if !self.errors.is_empty() {
+            "FAIL: some frames were refused"
+        } else {
+            "FAIL: suspicious transitions (--strict)"
+        };
+        writeln!(out, "{}", verdict)
+    }
+}
+
+/// `home 45, away 38, 4 Quarter, 01:12 running`.
+fn describe(state: &GameState) -> String {
+    let field = |value: &str| if value.trim().is_empty() { "-".to_string() } else { value.trim().to_string() };
+    format!(
+        "home {}, away {}, {}, {} {}",
+        field(&state.home_score),
+        field(&state.away_score),
+        field(&state.period_name),
+        field(&state.time),
+        field(&state.game_state)
+    )
+}
+
+/// Order of the periods the console reports; overtimes all rank the same.
+fn period_rank(period: &str) -> Option<u32> {
+    match period {
+        "Overtime" => Some(5),
+        _ => period.strip_suffix(" Quarter")?.parse().ok(),
+    }
+}
+
+/// What's odd about going from `old` to `new` in one frame.
+fn suspicious(old: &GameState, new: &GameState) -> Vec<String> {
+    let same_period = old.period_name == new.period_name;
+    let mut found = Vec::new();
+    for event in events::diff(old, new) {
+        match event {
+            GameEvent::ScoreChange { side, from: Some(from), to: Some(to), delta } if !(0..=MAX_SCORE_STEP).contains(&delta) => {
+                let verb = if delta < 0 { "went down" } else { "jumped" };
+                found.push(format!("{} score {} from {} to {}", side.as_str(), verb, from, to));
+            }
+            // The console clears them at the end of a period, a few frames before the next one
+            GameEvent::FoulChange { side, from: Some(from), to: Some(to) } if to < from && to > 0 && same_period => {
+                found.push(format!("{} fouls went down from {} to {} within the period", side.as_str(), from, to));
+            }
+            GameEvent::PeriodChange { from, to } if matches!((period_rank(&from), period_rank(&to)), (Some(from), Some(to)) if to < from) => {
+                found.push(format!("period went back from {} to {}", from, to));
+            }
+            _ => {}
+        }
+    }
+    let running = old.game_state == "running" && new.game_state == "running";
+    if running && same_period && matches!((clock_tenths(&old.time), clock_tenths(&new.time)), (Some(from), Some(to)) if to > from) {
+        found.push(format!("clock went up from {} to {} while running", old.time.trim(), new.time.trim()));
+    }
+    found
+}
+
+
+/// Run `bytes` through the decoder and parser as the server would and report on them.
+pub fn validate(bytes: &[u8], lenient: bool) -> Report {
+    let mut decoder = FrameDecoder::new(lenient, &[0x00]);
+    let mut report = Report { bytes: bytes.len() as u64, ..Report::default() };
+    report.heartbeats = decoder.push(bytes) as u64;
+
+    let mut state = GameState::default();
+    while let Some(frame) = decoder.next_frame() {
+        report.frames += 1;
+        let frame = match frame {
+            Ok(frame) => frame,
+            Err(e) => {
+                *report.errors.entry(e).or_default() += 1;
+                continue;
+            }
+        };
+        let mut next = state.clone();
+        let Some(id) = frame.message.get(..2).map(|id| String::from_utf8_lossy(id).escape_debug().to_string()) else {
+            *report.errors.entry("message too short to have a type".to_string()).or_default() += 1;
+            continue;
+        };
+        if !MESSAGES.iter().any(|message| message.id == id) {
+            *report.ignored.entry(id).or_default() += 1;
+            continue;
+        }
+        if !apply_frame(&frame, &mut next) {
+            *report.errors.entry(format!("message type {} too short", id)).or_default() += 1;
+            continue;
+        }
+        report.parsed += 1;
+        *report.message_types.entry(id).or_default() += 1;
+        if next == state {
+            continue;
+        }
+        for description in suspicious(&state, &next) {
+            report.suspicious.push(Transition { frame: report.frames, description });
+        }
+        report.first_state.get_or_insert_with(|| next.clone());
+        state = next;
+    }
+    report.skipped_bytes = decoder.skipped() + decoder.buffered() as u64;
+    if report.first_state.is_some() {
+        report.last_state = Some(state);
+    }
+    report
+}
+
+/// Read the file in `options` and report on it.
+pub fn run(options: &ValidateOptions) -> Result<Report, String> {
+    let data = fs::read(&options.path).map_err(|e| format!("Failed to read {}: {}", options.path.display(), e))?;
+    let format = match options.format {
+        InputFormat::Auto => InputFormat::detect(&data),
+        format => format,
+    };
+    let bytes = format.decode(&data).map_err(|e| format!("{} is not a valid {} file: {}", options.path.display(), format.name(), e))?;
+    // The report counts the parser's complaints; logging each of them would drown it
+    let _quiet = logging::quiet("error");
+    Ok(Report { format, ..validate(&bytes, options.lenient) })
+}
+
+/// Entry point of `scoreboard-rust validate`; returns the process exit code: 0 when the file
+/// passes, 1 when it fails or can't be read.
+pub fn run_command(options: &ValidateOptions) -> i32 {
+    let report = match run(options) {
+        Ok(report) => report,
+        Err(e) => {
+            error!("{}", e);
+            return 1;
+        }
+    };
+
+    let mut out = io::stdout().lock();
+    let written = if options.json {
+        serde_json::to_writer_pretty(&mut out, &report).map_err(io::Error::from).and_then(|()| writeln!(out))
+    } else {
+        report.write_text(&mut out, options.strict)
+    };
+    if let Err(e) = written {
+        if e.kind() != io::ErrorKind::BrokenPipe {
+            error!("Failed to write the report: {}", e);
+        }
+    }
+    if report.passed(options.strict) {
+        0
+    } else {
+        1
+    }
+}
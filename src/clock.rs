@@ -0,0 +1,159 @@
+use crate::{events::clock_tenths, state::SharedState, GameState};
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often the interpolated clock is recomputed (10 Hz, the console's finest resolution).
+pub const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Periods during which the clock never runs, whatever the console's clock status says.
+const STOPPED_PERIODS: [&str; 2] = ["Halftime", "Final"];
+
+/// Counts the game clock and shot clock down between console frames (`--tick-clock`).
+///
+/// Consoles that only send a frame when something else changes would otherwise leave
+/// the overlay clock frozen. The ticker is anchored on the last authoritative clock and
+/// re-anchored whenever a frame changes it, so drift never outlives the next frame.
+#[derive(Debug, Clone, Default)]
+pub struct ClockTicker {
+    /// Authoritative clock in tenths and when it was received; `None` while the clock is stopped.
+    anchor: Option<(u32, Instant)>,
+    /// The same for the shot clock, with whether the console showed tenths.
+    shot_anchor: Option<(u32, bool, Instant)>,
+}
+
+impl ClockTicker {
+    /// Take the authoritative clock from `state`, received at `now`.
+    pub fn sync(&mut self, state: &GameState, now: Instant) {
+        self.anchor = if is_running(state) {
+            clock_tenths(&state.time).map(|tenths| (tenths, now))
+        } else {
+            None
+        };
+    }
+
+    /// The clock to show at `now`, or `None` while it is stopped (show the console's value).
+    pub fn display(&self, now: Instant) -> Option<String> {
+        let (tenths, at) = self.anchor?;
+        let elapsed = now.saturating_duration_since(at).as_millis() / 100;
+        let remaining = u128::from(tenths).saturating_sub(elapsed) as u32;
+        Some(format_tenths(remaining))
+    }
+
+    /// Take the authoritative shot clock from `state`, received at `now`.
+    pub fn sync_shot_clock(&mut self, state: &GameState, now: Instant) {
+        self.shot_anchor = if state.shot_clock_running {
+            shot_clock_tenths(&state.shot_clock).map(|tenths| (tenths, state.shot_clock.contains('.'), now))
+        } else {
+            None
+        };
+    }
+
+    /// The shot clock to show at `now`, or `None` while it is stopped or blank.
+    pub fn display_shot_clock(&self, now: Instant) -> Option<String> {
+        let (tenths, in_tenths, at) = self.shot_anchor?;
+        let elapsed = now.saturating_duration_since(at).as_millis() / 100;
+        let remaining = u128::from(tenths).saturating_sub(elapsed) as u32;
+        Some(if in_tenths { format!("{}.{}", remaining / 10, remaining % 10) } else { remaining.div_ceil(10).to_string() })
+    }
+}
+
+/// Tenths on a shot clock display such as `"14"` or `"4.3"`, `None` when blank.
+pub fn shot_clock_tenths(shot_clock: &str) -> Option<u32> {
+    let shot_clock = shot_clock.trim();
+    if shot_clock.contains('.') {
+        return clock_tenths(shot_clock);
+    }
+    if shot_clock.is_empty() || !shot_clock.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    shot_clock.parse::<u32>().ok().map(|seconds| seconds * 10)
+}
+
+/// One `GET /api/time` round trip as an overlay page timed it: its own clock when the request
+/// went out and when the reply came back, and the server's clock in the reply (Unix milliseconds).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockSample {
+    pub sent_ms: f64,
+    pub received_ms: f64,
+    pub server_time_ms: f64,
+}
+
+impl ClockSample {
+    /// Samples as the pages send them in `?samples=`: `sent,received,server` triples
+    /// separated by `;`.
+    pub fn parse_list(list: &str) -> Result<Vec<Self>, String> {
+        list.split(';')
+            .filter(|sample| !sample.is_empty())
+            .map(|sample| {
+                let times: Vec<f64> = sample
+                    .split(',')
+                    .map(|time| time.trim().parse::<f64>())
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| format!("Invalid clock sample {:?}: {}", sample, e))?;
+                match times[..] {
+                    [sent_ms, received_ms, server_time_ms] if times.iter().all(|time| time.is_finite()) => {
+                        Ok(Self { sent_ms, received_ms, server_time_ms })
+                    }
+                    _ => Err(format!("Invalid clock sample {:?}: expected sent,received,server milliseconds", sample)),
+                }
+            })
+            .collect()
+    }
+
+    fn round_trip_ms(&self) -> f64 {
+        self.received_ms - self.sent_ms
+    }
+}
+
+/// How far the server's clock is ahead of the page's, NTP-style: the server is taken to have
+/// answered halfway through the round trip, and of `samples` the one with the shortest round
+/// trip wins (the first of equal ones). Samples whose reply came back before the request went
+/// out, from a clock set back meanwhile, are skipped; `None` when no sample is left.
+///
+/// The overlay pages get their offset from this through `GET /api/time?samples=`.
+pub fn estimate_clock_offset(samples: &[ClockSample]) -> Option<f64> {
+    let best = samples
+        .iter()
+        .filter(|sample| sample.round_trip_ms() >= 0.0)
+        .reduce(|best, sample| if sample.round_trip_ms() < best.round_trip_ms() { sample } else { best })?;
+    Some(best.server_time_ms - (best.sent_ms + best.received_ms) / 2.0)
+}
+
+/// Whether the clock in `state` counts down: the console says running and the period isn't over.
+pub fn is_running(state: &GameState) -> bool {
+    state.game_state == "running" && !STOPPED_PERIODS.iter().any(|period| state.period_name.trim().eq_ignore_ascii_case(period))
+}
+
+/// Format like the console: `MM:SS` from one minute up, `SS.t` below.
+///
+/// Whole seconds are rounded up, so `04:12` stays on screen for the full second after it was received.
+fn format_tenths(tenths: u32) -> String {
+    if tenths >= 600 {
+        let seconds = tenths.div_ceil(10);
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        format!("{:02}.{}", tenths / 10, tenths % 10)
+    }
+}
+
+/// Count the time-outs on `shared` down every [`TICK_INTERVAL`] on its own thread.
+pub fn start_timeout_countdown(shared: &Arc<SharedState>) {
+    let shared = Arc::clone(shared);
+    thread::spawn(move || loop {
+        thread::sleep(TICK_INTERVAL);
+        shared.tick_timeout();
+    });
+}
+
+/// Enable the ticker on `shared` and advance it every [`TICK_INTERVAL`] on its own thread.
+pub fn start_ticker(shared: &Arc<SharedState>) {
+    shared.enable_clock_ticker();
+    let shared = Arc::clone(shared);
+    thread::spawn(move || loop {
+        thread::sleep(TICK_INTERVAL);
+        shared.tick_clock();
+    });
+}
@@ -0,0 +1,1998 @@
+use crate::{
+    events::GameEvent,
+    game_archive,
+    http_client::HttpUrl,
+    jsonl_logger,
+    locale::Lang,
+    logging::{LogFormat, LogRotation},
+    notify::{self, Service},
+    page_config::DEFAULT_POLL_INTERVAL,
+    rules::RuleSet,
+    state::{Dedupe, OverrideMode},
+    themes::DEFAULT_THEME,
+    timezone::TimeZone,
+    xml,
+};
+use clap::{parser::ValueSource, Args, FromArgMatches};
+use std::{
+    fs,
+    net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs},
+    num::ParseIntError,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::info;
+
+/// Default address the TCP ingest listener binds to.
+const DEFAULT_TCP_ADDRESS: &str = "0.0.0.0:4001";
+
+/// Default interval between per-peer traffic summaries in the log.
+const DEFAULT_PEER_SUMMARY_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Default file the team details from `PUT /api/teams` are saved to.
+const DEFAULT_TEAMS_FILE: &str = "teams.json";
+/// Default file the match details from `PUT /api/match` are saved to.
+const DEFAULT_MATCH_FILE: &str = "match.json";
+/// Default file of the custom overlay CSS.
+const DEFAULT_CUSTOM_CSS_FILE: &str = "custom.css";
+
+/// Default directory team logos uploaded from the admin page are saved to.
+const DEFAULT_UPLOAD_DIR: &str = "uploads";
+
+/// Default directory the raw bytes of the console sessions are captured to.
+const DEFAULT_CAPTURE_DIR: &str = "data_log";
+
+/// Default time a running game may go without frames before `/healthz` reports 503.
+const DEFAULT_HEALTH_STALE_AFTER: Duration = Duration::from_secs(15);
+
+/// Default address of the overlay web server.
+const DEFAULT_WEB_ADDRESS: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 3030);
+
+/// Default address of the HTTPS listener.
+const DEFAULT_HTTPS_ADDRESS: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 3443);
+
+/// Port of an MQTT broker given without one.
+const DEFAULT_MQTT_PORT: u16 = 1883;
+
+/// Topic prefix Home Assistant subscribes to for MQTT discovery.
+const DEFAULT_DISCOVERY_PREFIX: &str = "homeassistant";
+
+/// Port of a StatsD server given without one.
+const DEFAULT_STATSD_PORT: u16 = 8125;
+
+/// Default time between two StatsD flushes of the counters and gauges.
+const DEFAULT_STATSD_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Default share of the frames whose parse time is sent to StatsD.
+const DEFAULT_STATSD_SAMPLE_RATE: f64 = 0.1;
+
+/// Default shortest time between two chat notifications to one channel.
+const DEFAULT_NOTIFY_MIN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Default shortest time between two pushes of the state.
+const DEFAULT_PUSH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Default number of events kept for the push while the remote can't be reached.
+const DEFAULT_PUSH_BUFFER: usize = 1000;
+
+/// Default age beyond which a saved state file is not restored.
+const DEFAULT_STATE_MAX_AGE: Duration = Duration::from_secs(30 * 60);
+
+/// Default size at which the `--log-file` is rotated.
+const DEFAULT_LOG_MAX_SIZE: u64 = 10 << 20;
+
+/// Default time a hook command may run.
+const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default time a web server shutdown waits for open connections.
+const DEFAULT_WEB_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default number of threads answering web requests.
+const DEFAULT_WEB_WORKERS: usize = 4;
+
+/// Default number of web connections served at once.
+const DEFAULT_WEB_MAX_CONNECTIONS: usize = 256;
+
+/// Default time a web client gets to send its request headers.
+const DEFAULT_WEB_HEADER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default interval between pings to each WebSocket client.
+const DEFAULT_WS_PING_INTERVAL: Duration = Duration::from_secs(20);
+
+/// Default number of published states kept for `/api/history`.
+const DEFAULT_HISTORY_CAPACITY: usize = 2000;
+
+/// Default time a subscriber may leave its queue full before it is disconnected.
+const DEFAULT_BACKPRESSURE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Prefix of the environment variables that set server options, see [`ServerConfig::from_env_and_args`].
+pub const ENV_PREFIX: &str = "SCOREBOARD_";
+
+/// How an option is written in its environment variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EnvValue {
+    /// A switch: `1`, `true`, `yes` or `on` gives it, `0`, `false`, `no`, `off` or empty doesn't.
+    Switch,
+    /// The option's value.
+    One,
+    /// The values of a repeatable option, separated by commas.
+    List,
+}
+
+/// Options that can be set from the environment, named `SCOREBOARD_` plus the option in upper
+/// case with `_` for `-` (`--web-address` -> `SCOREBOARD_WEB_ADDRESS`). Keep in step with
+/// [`ServeArgs`].
+const ENV_OPTIONS: &[(&str, EnvValue)] = &[
+    ("dev", EnvValue::Switch),
+    ("stdin", EnvValue::Switch),
+    ("--listen", EnvValue::One),
+    ("--tcp-host", EnvValue::One),
+    ("--tcp-port", EnvValue::One),
+    ("--udp-addr", EnvValue::One),
+    ("--follow", EnvValue::One),
+    ("--follow-offset", EnvValue::One),
+    ("--lenient", EnvValue::Switch),
+    ("--ack", EnvValue::Switch),
+    ("--echo", EnvValue::Switch),
+    ("--heartbeat-byte", EnvValue::List),
+    ("--auth-token", EnvValue::One),
+    ("--subscribers", EnvValue::One),
+    ("--backpressure-timeout", EnvValue::One),
+    ("--history-capacity", EnvValue::One),
+    ("--capture-dir", EnvValue::One),
+    ("--capture-max-size", EnvValue::One),
+    ("--capture-per-game", EnvValue::Switch),
+    ("--capture-compress", EnvValue::Switch),
+    ("--capture-keep", EnvValue::One),
+    ("--capture-max-age", EnvValue::One),
+    ("--log-csv", EnvValue::One),
+    ("--log-jsonl", EnvValue::One),
+    ("--log-jsonl-max-size", EnvValue::One),
+    ("--games-dir", EnvValue::One),
+    ("--games-db", EnvValue::One),
+    ("--game-idle-gap", EnvValue::One),
+    ("--auto-reset", EnvValue::One),
+    ("--state-file", EnvValue::One),
+    ("--state-max-age", EnvValue::One),
+    ("--summary-webhook", EnvValue::One),
+    ("--slack-webhook", EnvValue::List),
+    ("--discord-webhook", EnvValue::List),
+    ("--notify-events", EnvValue::One),
+    // One template per variable: templates may contain commas; the config file takes several
+    ("--notify-template", EnvValue::One),
+    ("--notify-min-interval", EnvValue::One),
+    ("--push-url", EnvValue::One),
+    ("--push-token", EnvValue::One),
+    ("--push-interval", EnvValue::One),
+    ("--push-diff", EnvValue::Switch),
+    ("--push-buffer", EnvValue::One),
+    ("--health-stale-after", EnvValue::One),
+    ("--peer-summary-interval", EnvValue::One),
+    ("--dedupe", EnvValue::One),
+    ("--theme", EnvValue::One),
+    ("--lang", EnvValue::One),
+    ("--teams-file", EnvValue::One),
+    ("--match-file", EnvValue::One),
+    ("--custom-css", EnvValue::One),
+    ("--upload-dir", EnvValue::One),
+    ("--web-root", EnvValue::One),
+    ("--cors-origin", EnvValue::List),
+    ("--admin-token", EnvValue::One),
+    ("--lock-read-api", EnvValue::Switch),
+    ("--tick-clock", EnvValue::Switch),
+    ("--swap-sides", EnvValue::Switch),
+    ("--no-websocket", EnvValue::Switch),
+    ("--poll-interval", EnvValue::One),
+    ("--rules", EnvValue::One),
+    ("--timeout-length", EnvValue::One),
+    ("--no-keep-alive", EnvValue::Switch),
+    ("--web-workers", EnvValue::One),
+    ("--web-max-connections", EnvValue::One),
+    ("--web-drain-timeout", EnvValue::One),
+    ("--web-header-timeout", EnvValue::One),
+    ("--ws-ping-interval", EnvValue::One),
+    ("--tls-cert", EnvValue::One),
+    ("--tls-key", EnvValue::One),
+    ("--web-address", EnvValue::One),
+    ("--web-host", EnvValue::One),
+    ("--web-port", EnvValue::One),
+    ("--public", EnvValue::Switch),
+    ("--https-address", EnvValue::One),
+    ("--no-http", EnvValue::Switch),
+    ("--no-mdns", EnvValue::Switch),
+    ("--court-id", EnvValue::One),
+    ("--mqtt-broker", EnvValue::One),
+    ("--mqtt-qos", EnvValue::One),
+    ("--mqtt-discovery", EnvValue::Switch),
+    ("--mqtt-discovery-prefix", EnvValue::One),
+    ("--osc", EnvValue::List),
+    ("--osc-map", EnvValue::One),
+    ("--statsd", EnvValue::One),
+    ("--statsd-prefix", EnvValue::One),
+    ("--statsd-interval", EnvValue::One),
+    ("--statsd-sample-rate", EnvValue::One),
+    ("--xml-file", EnvValue::One),
+    ("--xml-interval", EnvValue::One),
+    ("--xml-name", EnvValue::List),
+    // One hook per variable: commands may contain commas; the config file takes several
+    ("--hook", EnvValue::One),
+    ("--hook-timeout", EnvValue::One),
+    ("--tui", EnvValue::Switch),
+    ("--daemon", EnvValue::Switch),
+    ("--pid-file", EnvValue::One),
+    ("--log-file", EnvValue::One),
+    ("--log-max-size", EnvValue::One),
+    ("--log-format", EnvValue::One),
+    ("--log-dir", EnvValue::One),
+    ("--log-rotation", EnvValue::One),
+    ("--timezone", EnvValue::One),
+    ("--override-mode", EnvValue::One),
+    ("--forward", EnvValue::List),
+    ("--config", EnvValue::One),
+];
+
+/// Shorter names for the addresses containers set most often, and the address of each
+/// section whose option isn't named after it.
+const ENV_ALIASES: &[(&str, &str)] = &[
+    ("SCOREBOARD_TCP_ADDR", "--listen"),
+    ("SCOREBOARD_WEB_ADDR", "--web-address"),
+    ("SCOREBOARD_TLS__ADDRESS", "--https-address"),
+    ("SCOREBOARD_STATSD__ADDRESS", "--statsd"),
+];
+
+/// Sections of the configuration whose options are also set as `SCOREBOARD_<SECTION>__<FIELD>`,
+/// the field being the rest of the option: `SCOREBOARD_MQTT__QOS` for `--mqtt-qos`.
+const ENV_SECTIONS: [&str; 3] = ["MQTT", "TLS", "STATSD"];
+
+/// Prefix of the variables setting an option of one court, `SCOREBOARD_COURT__2__LISTEN`.
+const COURT_ENV_PREFIX: &str = "SCOREBOARD_COURT__";
+
+/// Options that pick the input: one given on the command line replaces any from the environment.
+const INPUT_OPTIONS: [&str; 4] = ["stdin", "--listen", "--tcp-addr", "--follow"];
+
+/// Options of the whole process (the web server, the log, running in the background), refused
+/// in a `[[court]]` section of the config file.
+const SERVER_OPTIONS: &[&str] = &[
+    "dev",
+    "--theme",
+    "--lang",
+    "--swap-sides",
+    "--poll-interval",
+    "--no-websocket",
+    "--custom-css",
+    "--upload-dir",
+    "--web-root",
+    "--cors-origin",
+    "--admin-token",
+    "--lock-read-api",
+    "--health-stale-after",
+    "--no-keep-alive",
+    "--web-workers",
+    "--web-max-connections",
+    "--web-drain-timeout",
+    "--web-header-timeout",
+    "--ws-ping-interval",
+    "--tls-cert",
+    "--tls-key",
+    "--web-address",
+    "--web-host",
+    "--web-port",
+    "--public",
+    "--https-address",
+    "--no-http",
+    "--no-mdns",
+    "--tui",
+    "--daemon",
+    "--pid-file",
+    "--log-file",
+    "--log-max-size",
+    "--log-format",
+    "--log-dir",
+    "--log-rotation",
+    "--timezone",
+];
+
+/// Header of a section of the config file holding the options of one court.
+const COURT_SECTION: &str = "[[court]]";
+
+/// An option taken from the environment or the config file.
+struct Setting {
+    option: &'static str,
+    values: Vec<String>,
+    /// The variable or file line that set it, for error messages.
+    source: String,
+}
+
+impl Setting {
+    /// The option as command line arguments (repeated for each value of a list).
+    fn args(&self) -> Vec<String> {
+        if self.values.is_empty() {
+            return vec![self.option.to_string()];
+        }
+        self.values.iter().flat_map(|value| [self.option.to_string(), value.clone()]).collect()
+    }
+}
+
+/// `1`, `true`, `yes`, `on` -> `Some(true)`; `0`, `false`, `no`, `off` and empty -> `Some(false)`.
+fn switch_value(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "" | "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// A `[[court]]` section of the config file.
+struct CourtSection {
+    settings: Vec<Setting>,
+    /// The header line, for error messages.
+    source: String,
+}
+
+/// The options of a `--config` file.
+#[derive(Default)]
+struct ConfigFile {
+    /// Options before the first `[[court]]` section, which every court starts from.
+    settings: Vec<Setting>,
+    courts: Vec<CourtSection>,
+}
+
+/// Read the options of a `--config` file: `option [value]` per line, `#` comments, and a
+/// `[[court]]` line before the options of each court.
+fn read_config_file(path: &Path) -> Result<ConfigFile, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Cannot read config file {}: {}", path.display(), e))?;
+    let mut file = ConfigFile::default();
+    for (number, line) in text.lines().enumerate() {
+        let source = format!("{} line {}", path.display(), number + 1);
+        let line = line.split_once('#').map_or(line, |(line, _)| line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line == COURT_SECTION {
+            file.courts.push(CourtSection { settings: Vec::new(), source });
+            continue;
+        }
+        if line.starts_with('[') {
+            return Err(format!("Unknown section {} in {}: expected {}", line, source, COURT_SECTION));
+        }
+        let settings = match file.courts.last_mut() {
+            Some(court) => &mut court.settings,
+            None => &mut file.settings,
+        };
+        let (name, value) = line.split_once(char::is_whitespace).map_or((line, ""), |(name, value)| (name, value.trim()));
+        let name = name.trim_start_matches("--");
+        let (option, kind) = ENV_OPTIONS
+            .iter()
+            .copied()
+            .find(|(option, _)| option.trim_start_matches("--") == name)
+            .filter(|(option, _)| *option != "--config")
+            .ok_or_else(|| format!("Unknown option {} in {}", name, source))?;
+        let values = match kind {
+            // A bare switch is on
+            EnvValue::Switch if value.is_empty() => vec![],
+            EnvValue::Switch => match switch_value(value) {
+                Some(true) => vec![],
+                Some(false) => continue,
+                None => return Err(format!("Invalid {} in {}: expected true or false, found {:?}", name, source, value)),
+            },
+            _ if value.is_empty() => return Err(format!("{} requires a value in {}", name, source)),
+            EnvValue::One => vec![value.to_string()],
+            EnvValue::List => value.split(',').map(str::trim).filter(|item| !item.is_empty()).map(String::from).collect(),
+        };
+        settings.push(Setting { option, values, source });
+    }
+    Ok(file)
+}
+
+/// `e` with the variable or file line that set the option it is about.
+fn blame(e: String, sources: &[&Setting]) -> String {
+    // The longest match, so `--log-jsonl-max-size` isn't blamed on `--log-jsonl`
+    match sources.iter().filter(|setting| e.contains(setting.option)).max_by_key(|setting| setting.option.len()) {
+        Some(setting) => format!("{} (set by {})", e, setting.source),
+        None => e,
+    }
+}
+
+/// `args` without the options picking the input and their values.
+fn without_input(args: &[String]) -> Vec<String> {
+    let mut kept = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "stdin" => {}
+            option if INPUT_OPTIONS.contains(&option) => {
+                args.next();
+            }
+            _ => kept.push(arg.clone()),
+        }
+    }
+    kept
+}
+
+/// `path` moved into a `court-<id>` directory beside it: `teams.json` -> `court-2/teams.json`.
+fn court_path(path: &Path, id: &str) -> PathBuf {
+    let dir = path.parent().unwrap_or(Path::new("")).join(format!("court-{}", id));
+    dir.join(path.file_name().unwrap_or_default())
+}
+
+/// `--web-address` -> `SCOREBOARD_WEB_ADDRESS`
+fn env_name(option: &str) -> String {
+    format!("{}{}", ENV_PREFIX, option.trim_start_matches("--").to_uppercase().replace('-', "_"))
+}
+
+/// Look up the option of a `SCOREBOARD_` variable.
+fn env_option(name: &str) -> Option<(&'static str, EnvValue)> {
+    let alias = ENV_ALIASES.iter().find(|(alias, _)| *alias == name).map(|(_, option)| *option);
+    // `SCOREBOARD_MQTT__QOS` is `SCOREBOARD_MQTT_QOS`
+    let name = match name.strip_prefix(ENV_PREFIX).and_then(|rest| rest.split_once("__")) {
+        Some((section, field)) if ENV_SECTIONS.contains(&section) => format!("{}{}_{}", ENV_PREFIX, section, field),
+        _ => name.to_string(),
+    };
+    ENV_OPTIONS
+        .iter()
+        .copied()
+        .find(|(option, _)| alias == Some(*option) || env_name(option) == name)
+}
+
+/// Look up a `SCOREBOARD_` variable: the court whose option it sets, numbered from 1 in the
+/// order of the config file (`SCOREBOARD_COURT__2__MQTT__QOS`), or `None` for every court,
+/// and the option.
+fn env_setting(name: &str) -> Option<(Option<usize>, &'static str, EnvValue)> {
+    let Some(rest) = name.strip_prefix(COURT_ENV_PREFIX) else {
+        return env_option(name).map(|(option, kind)| (None, option, kind));
+    };
+    let (number, name) = rest.split_once("__")?;
+    let number = number.parse().ok().filter(|&number| number > 0)?;
+    let (option, kind) = env_option(&format!("{}{}", ENV_PREFIX, name))?;
+    Some((Some(number), option, kind))
+}
+
+/// HTTPS settings of the web server (`--tls-cert`, `--tls-key`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    /// PEM certificate chain, leaf first.
+    pub cert: PathBuf,
+    /// PEM private key of the leaf certificate.
+    pub key: PathBuf,
+    /// Address of the HTTPS listener (`0.0.0.0:3443`).
+    pub address: SocketAddr,
+    /// Keep serving plain HTTP too, redirecting the admin routes to HTTPS.
+    pub serve_http: bool,
+}
+
+/// MQTT publishing of the state and events (`--mqtt-broker`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MqttConfig {
+    pub host: String,
+    /// 1883 unless the broker address names another.
+    pub port: u16,
+    /// QoS of the published messages: 0 (at most once) or 1 (at least once).
+    pub qos: u8,
+    /// Discovery prefix of Home Assistant (`homeassistant`), to announce the sensors under;
+    /// none when discovery is off.
+    pub discovery: Option<String>,
+}
+
+/// StatsD metrics over UDP (`--statsd`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsdConfig {
+    pub host: String,
+    /// 8125 unless the address names another.
+    pub port: u16,
+    /// Prepended to every metric name with a `.`, e.g. `arena.court2`.
+    pub prefix: String,
+    /// Time between two flushes of the counters and gauges.
+    pub interval: Duration,
+    /// Share of the frames whose parse time is sent, from 0 (none) to 1 (all).
+    pub sample_rate: f64,
+}
+
+/// A Slack or Discord webhook posted chat messages (`--slack-webhook`, `--discord-webhook`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotifyTarget {
+    pub service: Service,
+    pub url: HttpUrl,
+}
+
+/// Chat notifications on game events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotifyConfig {
+    /// None by default: no notification is sent.
+    pub targets: Vec<NotifyTarget>,
+    /// Kinds a message is sent for, see [`notify::kinds`]: `PeriodEnded` and `Final` by default.
+    pub events: Vec<&'static str>,
+    /// Message templates replacing the defaults, by kind.
+    pub templates: Vec<(&'static str, String)>,
+    /// Shortest time between two messages to one channel.
+    pub min_interval: Duration,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            targets: Vec::new(),
+            events: vec!["PeriodEnded", notify::FINAL],
+            templates: Vec::new(),
+            min_interval: DEFAULT_NOTIFY_MIN_INTERVAL,
+        }
+    }
+}
+
+/// Pushing the state to a remote URL (`--push-url`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PushConfig {
+    pub url: HttpUrl,
+    /// Sent as `Authorization: Bearer <token>`.
+    pub token: Option<String>,
+    /// Shortest time between two requests; updates in between are coalesced.
+    pub interval: Duration,
+    /// Send only the state fields that changed since the last delivered state.
+    pub diff: bool,
+    /// Events kept while the remote can't be reached; the oldest are dropped beyond it.
+    pub buffer: usize,
+}
+
+/// Rolling, compressing and deleting the session captures (`--capture-*`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureConfig {
+    /// Directory of the captures, `data_log` by default.
+    pub dir: PathBuf,
+    /// Start a new part of the session's capture when the next read would take it over this size.
+    pub max_size: Option<u64>,
+    /// Start a new part of the session's capture with every new game.
+    pub per_game: bool,
+    /// Gzip the parts and sessions that are done, in the background.
+    pub compress: bool,
+    /// Captures kept in the directory; the oldest are deleted beyond it.
+    pub keep: Option<usize>,
+    /// Captures last written longer ago than this are deleted.
+    pub max_age: Option<Duration>,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from(DEFAULT_CAPTURE_DIR),
+            max_size: None,
+            per_game: false,
+            compress: false,
+            keep: None,
+            max_age: None,
+        }
+    }
+}
+
+/// A command run on a game event (`--hook <event>=<command>`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookConfig {
+    /// The event's `type`, e.g. `PeriodEnded`.
+    pub event: &'static str,
+    /// Shell command line (`sh -c`, `cmd /C` on Windows).
+    pub command: String,
+}
+
+/// Where raw protocol bytes are read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InputMode {
+    /// Accept console connections on the TCP listener.
+    Tcp,
+    /// Read a single stream from standard input (e.g. `socat ... | scoreboard-rust stdin`).
+    Stdin,
+    /// Follow a growing file of raw frames (e.g. written by a bridge appliance).
+    Follow(PathBuf),
+    /// Read from a Windows named pipe (`\\.\pipe\<name>`), reconnecting when it breaks.
+    #[cfg(windows)]
+    Pipe(String),
+}
+
+/// Where `--follow` starts reading the file it opens first; a file that replaces it is read
+/// from its start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FollowStart {
+    /// This many bytes in, or the end of a shorter file; 0 replays the whole file.
+    Offset(u64),
+    /// The end of the file, so only frames written from now on are read.
+    End,
+}
+
+impl Default for FollowStart {
+    fn default() -> Self {
+        Self::Offset(0)
+    }
+}
+
+fn follow_start(value: &str) -> Result<FollowStart, String> {
+    match value {
+        "end" => Ok(FollowStart::End),
+        bytes => bytes.parse().map(FollowStart::Offset).map_err(|_| "expected a byte offset or end".to_string()),
+    }
+}
+
+/// Parse an ingest address: `host:port` (TCP) or `pipe:<name>` (Windows named pipe).
+///
+/// Returns the input mode and, for TCP, the address to bind.
+fn parse_listen_address(value: &str) -> Result<(InputMode, Option<String>), String> {
+    if let Some(name) = value.strip_prefix("pipe:") {
+        if name.is_empty() || name.contains(['\\', '/']) {
+            return Err(format!("Invalid pipe name: {:?}", name));
+        }
+        #[cfg(windows)]
+        return Ok((InputMode::Pipe(name.to_string()), None));
+        #[cfg(not(windows))]
+        return Err("Named pipe input (pipe:<name>) is only available on Windows".to_string());
+    }
+
+    let address = value.strip_prefix("tcp:").unwrap_or(value);
+    let (host, port) = parse_host_port(address).map_err(|e| format!("Invalid listen address {}: {}, or pipe:<name>", value, e))?;
+    Ok((InputMode::Tcp, Some(join_host_port(&host, port))))
+}
+
+/// Parse a host: a name, an IPv4 address, or an IPv6 address, bracketed or not.
+///
+/// Returns the host without brackets.
+fn parse_host(value: &str) -> Result<String, String> {
+    let host = value.strip_prefix('[').and_then(|host| host.strip_suffix(']')).unwrap_or(value);
+    if host.parse::<IpAddr>().is_ok() {
+        return Ok(host.to_string());
+    }
+    let label = |label: &str| !label.is_empty() && !label.starts_with('-') && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if host.len() <= 253 && host.split('.').all(label) {
+        Ok(host.to_string())
+    } else {
+        Err("expected a host name, an IPv4 address or an IPv6 address".to_string())
+    }
+}
+
+/// Parse `host:port`, with an IPv6 host in brackets (`[::1]:4001`).
+///
+/// Returns the host without brackets and the port.
+pub(crate) fn parse_host_port(value: &str) -> Result<(String, u16), String> {
+    let (host, port) = value.rsplit_once(':').ok_or("expected host:port")?;
+    if host.contains(':') && !(host.starts_with('[') && host.ends_with(']')) {
+        return Err("expected host:port, with an IPv6 address in brackets ([::1]:4001)".to_string());
+    }
+    let port = port.parse().map_err(|_| format!("invalid port {:?}", port))?;
+    Ok((parse_host(host)?, port))
+}
+
+/// `host:port`, bracketing an IPv6 host.
+fn join_host_port(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+/// The socket address of `host` and `port`, looking up a host name.
+fn resolve(option: &str, host: &str, port: u16) -> Result<SocketAddr, String> {
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, port));
+    }
+    (host, port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Invalid {} {}: {}", option, host, e))?
+        .next()
+        .ok_or_else(|| format!("Invalid {} {}: the name has no address", option, host))
+}
+
+/// Parse a size in bytes, with an optional `K`, `M` or `G` suffix (powers of 1024).
+fn parse_size(value: &str) -> Option<u64> {
+    let (digits, unit) = match value.char_indices().last()? {
+        (i, 'K' | 'k') => (&value[..i], 1 << 10),
+        (i, 'M' | 'm') => (&value[..i], 1 << 20),
+        (i, 'G' | 'g') => (&value[..i], 1 << 30),
+        _ => (value, 1),
+    };
+    digits.parse::<u64>().ok()?.checked_mul(unit).filter(|&size| size > 0)
+}
+
+/// A bare word before or among the server options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum Mode {
+    /// Don't capture the TCP sessions to `data_log/`.
+    Dev,
+    /// Read raw bytes from standard input instead of the TCP listener.
+    Stdin,
+}
+
+// The sections of `--help`
+const INPUT: &str = "Input (pick one; TCP on 0.0.0.0:4001 by default)";
+const GAME: &str = "Game";
+const WEB: &str = "Web server";
+const OVERLAYS: &str = "Overlays";
+const BACKGROUND: &str = "Background (Unix)";
+const LOGGING: &str = "Logging (RUST_LOG=debug, default info)";
+
+/// The server's options, as given on the command line, in the environment or in a config
+/// file; [`ServerConfig::from_args`] checks how they combine.
+#[derive(Debug, Clone, clap::Args)]
+#[command(after_long_help = SERVE_AFTER_HELP)]
+pub(crate) struct ServeArgs {
+    /// `dev`: don't capture TCP sessions to data_log/; `stdin`: read raw bytes from standard input
+    #[arg(value_enum, value_name = "dev|stdin", help_heading = INPUT)]
+    pub modes: Vec<Mode>,
+    /// TCP listener address, or a Windows named pipe
+    #[arg(long, value_name = "HOST:PORT|pipe:NAME", value_parser = listen_address, help_heading = INPUT)]
+    pub listen: Option<(InputMode, Option<String>)>,
+    /// TCP listener address (--tcp-host, --tcp-port set one half)
+    #[arg(long, value_name = "HOST:PORT", value_parser = host_port, help_heading = INPUT)]
+    pub tcp_addr: Option<String>,
+    /// Host of the TCP listener, keeping its port
+    #[arg(long, value_name = "HOST", value_parser = parse_host, help_heading = INPUT)]
+    pub tcp_host: Option<String>,
+    /// Port of the TCP listener, keeping its host
+    #[arg(long, value_name = "PORT", help_heading = INPUT)]
+    pub tcp_port: Option<u16>,
+    /// Also read frames from UDP datagrams, next to TCP
+    #[arg(long, value_name = "HOST:PORT", value_parser = host_port, help_heading = INPUT)]
+    pub udp_addr: Option<String>,
+    /// Follow a file of raw frames as it grows
+    #[arg(long, value_name = "FILE", help_heading = INPUT)]
+    pub follow: Option<PathBuf>,
+    /// Where --follow starts reading: a byte offset or `end` (default 0)
+    #[arg(long, value_name = "BYTES|end", value_parser = follow_start, help_heading = INPUT)]
+    pub follow_offset: Option<FollowStart>,
+    /// Accept frames with a bad LRC
+    #[arg(long, help_heading = INPUT)]
+    pub lenient: bool,
+    /// Answer every TCP frame with ACK / ERR <reason>
+    #[arg(long, help_heading = INPUT)]
+    pub ack: bool,
+    /// Require AUTH <token> first on every TCP connection, and an HMAC-SHA256 with it on every UDP datagram
+    #[arg(long, value_name = "TOKEN", value_parser = not_empty, help_heading = INPUT)]
+    pub auth_token: Option<String>,
+    /// Link test mode: echo bytes back, answer PING with PONG
+    #[arg(long, help_heading = INPUT)]
+    pub echo: bool,
+    /// Keepalive byte between frames (repeatable, default 00)
+    #[arg(long, value_name = "HEX|none", value_parser = heartbeat_byte, help_heading = INPUT)]
+    pub heartbeat_byte: Vec<Option<u8>>,
+    /// Relay the raw stream to this address (repeatable)
+    #[arg(long, value_name = "ADDR", help_heading = INPUT)]
+    pub forward: Vec<SocketAddr>,
+    /// Stream every state as JSON lines to TCP clients on this address
+    #[arg(long, value_name = "ADDR", help_heading = INPUT)]
+    pub subscribers: Option<String>,
+    /// Drop subscribers that stop reading for this long (default 10)
+    #[arg(long, value_name = "SECS", value_parser = seconds, help_heading = INPUT)]
+    pub backpressure_timeout: Option<Duration>,
+    /// Log per-peer traffic totals this often (default 3600, 0 = off)
+    #[arg(long, value_name = "SECS", value_parser = seconds, help_heading = INPUT)]
+    pub peer_summary_interval: Option<Duration>,
+    /// Capture the TCP sessions to this directory (default data_log)
+    #[arg(long, value_name = "DIR", help_heading = INPUT)]
+    pub capture_dir: Option<PathBuf>,
+    /// Start a new part of a capture at this size (500K, 10M, 1G)
+    #[arg(long, value_name = "SIZE", value_parser = size, help_heading = INPUT)]
+    pub capture_max_size: Option<u64>,
+    /// Start a new part of a capture with every new game
+    #[arg(long, help_heading = INPUT)]
+    pub capture_per_game: bool,
+    /// Gzip the captures that are done (.log.gz)
+    #[arg(long, help_heading = INPUT)]
+    pub capture_compress: bool,
+    /// Keep the newest captures, deleting older ones
+    #[arg(long, value_name = "N", value_parser = at_least_one, help_heading = INPUT)]
+    pub capture_keep: Option<usize>,
+    /// Delete captures last written more than this many days ago
+    #[arg(long, value_name = "DAYS", value_parser = days, help_heading = INPUT)]
+    pub capture_max_age: Option<Duration>,
+
+    /// Bonus rules and time-out length (default fiba)
+    #[arg(long, visible_alias = "ruleset", value_name = "fiba|nba|ncaa", ignore_case = true, hide_possible_values = true, help_heading = GAME)]
+    pub rules: Option<RuleSet>,
+    /// Length of the time-out countdown (default 60, 75 with --rules nba)
+    #[arg(long, value_name = "SECS", value_parser = whole_seconds, help_heading = GAME)]
+    pub timeout_length: Option<Duration>,
+    /// Count the running clock down between frames
+    #[arg(long, help_heading = GAME)]
+    pub tick_clock: bool,
+    /// Duplicate state suppression (default exact)
+    #[arg(long, value_name = "off|exact|ignore-clock", hide_possible_values = true, help_heading = GAME)]
+    pub dedupe: Option<Dedupe>,
+    /// Whether the console takes over after a manual override (default resume)
+    #[arg(long, value_name = "resume|hold", hide_possible_values = true, help_heading = GAME)]
+    pub override_mode: Option<OverrideMode>,
+    /// States kept for /api/history (default 2000, 0 = off)
+    #[arg(long, value_name = "N", help_heading = GAME)]
+    pub history_capacity: Option<usize>,
+    /// Append a CSV row per state change (score, foul, period...)
+    #[arg(long, value_name = "FILE", help_heading = GAME)]
+    pub log_csv: Option<PathBuf>,
+    /// Append every state and event as JSON lines, e.g. scores-%Y%m%d.jsonl
+    #[arg(long, value_name = "TEMPLATE", value_parser = jsonl_template, help_heading = GAME)]
+    pub log_jsonl: Option<String>,
+    /// Also start a new JSON lines file at this size (500K, 10M, 1G)
+    #[arg(long, value_name = "SIZE", value_parser = size, help_heading = GAME)]
+    pub log_jsonl_max_size: Option<u64>,
+    /// Record every game to this directory (see history)
+    #[arg(long, value_name = "DIR", help_heading = GAME)]
+    pub games_dir: Option<PathBuf>,
+    /// Record every game to this SQLite database (sqlite feature; see history)
+    #[arg(long, value_name = "FILE", help_heading = GAME)]
+    pub games_db: Option<PathBuf>,
+    /// Time without updates that ends a recorded game (default 1800)
+    #[arg(long, value_name = "SECS", value_parser = seconds, help_heading = GAME)]
+    pub game_idle_gap: Option<Duration>,
+    /// Start a new game when the console is reset after the clock hasn't run for this long
+    #[arg(long, value_name = "SECS", value_parser = whole_seconds, help_heading = GAME)]
+    pub auto_reset: Option<Duration>,
+    /// Save the state there and restore it at startup
+    #[arg(long, value_name = "PATH", help_heading = GAME)]
+    pub state_file: Option<PathBuf>,
+    /// Ignore a state file older than this (default 1800)
+    #[arg(long, value_name = "SECS", value_parser = seconds, help_heading = GAME)]
+    pub state_max_age: Option<Duration>,
+    /// POST the box score to a URL when the game goes Final
+    #[arg(long, value_name = "URL", value_parser = HttpUrl::parse, help_heading = GAME)]
+    pub summary_webhook: Option<HttpUrl>,
+    /// Post a chat message to a Slack webhook on game events (repeatable)
+    #[arg(long, value_name = "URL", value_parser = HttpUrl::parse, help_heading = GAME)]
+    pub slack_webhook: Vec<HttpUrl>,
+    /// Post a chat message to a Discord webhook on game events (repeatable)
+    #[arg(long, value_name = "URL", value_parser = HttpUrl::parse, help_heading = GAME)]
+    pub discord_webhook: Vec<HttpUrl>,
+    /// Event types messages are sent for (default PeriodEnded,Final)
+    #[arg(long, value_name = "KINDS", value_delimiter = ',', value_parser = notify_kind, help_heading = GAME)]
+    pub notify_events: Option<Vec<&'static str>>,
+    /// Message of a kind, e.g. `Final={home_name} {home_score}` (repeatable)
+    #[arg(long, value_name = "KIND=TEMPLATE", value_parser = notify_template, help_heading = GAME)]
+    pub notify_template: Vec<(&'static str, String)>,
+    /// Shortest time between two messages to a channel (default 5)
+    #[arg(long, value_name = "SECS", value_parser = seconds, help_heading = GAME)]
+    pub notify_min_interval: Option<Duration>,
+    /// POST the state, gzipped, to a remote URL as it changes
+    #[arg(long, value_name = "URL", value_parser = HttpUrl::parse, help_heading = GAME)]
+    pub push_url: Option<HttpUrl>,
+    /// Bearer token of the pushes
+    #[arg(long, value_name = "TOKEN", help_heading = GAME)]
+    pub push_token: Option<String>,
+    /// Shortest time between two pushes (default 500)
+    #[arg(long, value_name = "MS", value_parser = milliseconds, help_heading = GAME)]
+    pub push_interval: Option<Duration>,
+    /// Push only the state fields that changed
+    #[arg(long, help_heading = GAME)]
+    pub push_diff: bool,
+    /// Events kept while the remote is unreachable (default 1000)
+    #[arg(long, value_name = "EVENTS", value_parser = at_least_one, help_heading = GAME)]
+    pub push_buffer: Option<usize>,
+    /// Publish state and events to scoreboard/<court>/... (mqtt feature)
+    #[arg(long, value_name = "HOST[:PORT]", value_parser = mqtt_broker, help_heading = GAME)]
+    pub mqtt_broker: Option<(String, u16)>,
+    /// QoS of the MQTT messages (default 0)
+    #[arg(long, value_name = "0|1", value_parser = clap::value_parser!(u8).range(0..=1), help_heading = GAME)]
+    pub mqtt_qos: Option<u8>,
+    /// Announce score, clock, period and game state to Home Assistant
+    #[arg(long, help_heading = GAME)]
+    pub mqtt_discovery: bool,
+    /// Home Assistant's discovery prefix (default homeassistant)
+    #[arg(long, value_name = "PREFIX", value_parser = discovery_prefix, help_heading = GAME)]
+    pub mqtt_discovery_prefix: Option<String>,
+    /// Send OSC over UDP to this address for lighting/audio consoles (repeatable)
+    #[arg(long, value_name = "ADDR", help_heading = GAME)]
+    pub osc: Vec<SocketAddr>,
+    /// OSC address per value or trigger (`home.score /cue/12`, `clock off`)
+    #[arg(long, value_name = "FILE", help_heading = GAME)]
+    pub osc_map: Option<PathBuf>,
+    /// Send counters, gauges and frame timings to StatsD over UDP (port 8125)
+    #[arg(long, value_name = "HOST[:PORT]", value_parser = statsd_address, help_heading = GAME)]
+    pub statsd: Option<(String, u16)>,
+    /// Prefix of the metric names (default scoreboard)
+    #[arg(long, value_name = "PREFIX", value_parser = statsd_prefix, help_heading = GAME)]
+    pub statsd_prefix: Option<String>,
+    /// Time between two flushes of the counters and gauges (default 10)
+    #[arg(long, value_name = "SECS", value_parser = whole_seconds, help_heading = GAME)]
+    pub statsd_interval: Option<Duration>,
+    /// Share of the frames whose parse time is sent (default 0.1)
+    #[arg(long, value_name = "0..1", value_parser = sample_rate, help_heading = GAME)]
+    pub statsd_sample_rate: Option<f64>,
+    /// Rewrite this file with the state as XML for CG systems (as /api/state.xml)
+    #[arg(long, value_name = "PATH", help_heading = GAME)]
+    pub xml_file: Option<PathBuf>,
+    /// Rewrite it at most this often while only the clock runs (default 500)
+    #[arg(long, value_name = "MS", value_parser = milliseconds, help_heading = GAME)]
+    pub xml_interval: Option<Duration>,
+    /// XML element or attribute name, e.g. home=Team1, home.score=Pts (repeatable)
+    #[arg(long, value_name = "KEY=NAME", value_parser = xml_name, help_heading = GAME)]
+    pub xml_name: Vec<(&'static str, String)>,
+    /// Run a shell command on each event, e.g. PeriodEnded=/opt/relay.sh (repeatable; event JSON on stdin, SB_HOME_SCORE... in the environment)
+    #[arg(long, value_name = "EVENT=COMMAND", value_parser = hook, help_heading = GAME)]
+    pub hook: Vec<HookConfig>,
+    /// Kill hook commands running longer (default 30)
+    #[arg(long, value_name = "SECS", value_parser = seconds, help_heading = GAME)]
+    pub hook_timeout: Option<Duration>,
+
+    /// Overlay web server (default 0.0.0.0:3030; IPv6 in brackets, [::]:3030)
+    #[arg(long, visible_alias = "web-addr", value_name = "HOST:PORT", value_parser = parse_host_port, help_heading = WEB)]
+    pub web_address: Option<(String, u16)>,
+    /// Host of the web server, keeping its port
+    #[arg(long, value_name = "HOST", value_parser = parse_host, help_heading = WEB)]
+    pub web_host: Option<String>,
+    /// Port of the web server, keeping its host
+    #[arg(long, value_name = "PORT", help_heading = WEB)]
+    pub web_port: Option<u16>,
+    /// Bind every listener on all interfaces (0.0.0.0)
+    #[arg(long, help_heading = WEB)]
+    pub public: bool,
+    /// Admin page and control token (generated if unset)
+    #[arg(long, value_name = "TOKEN", value_parser = not_empty, help_heading = WEB)]
+    pub admin_token: Option<String>,
+    /// Require the token on the read endpoints too
+    #[arg(long, help_heading = WEB)]
+    pub lock_read_api: bool,
+    /// Allow browser apps on this origin to call /api/* (repeatable, `*` for any)
+    #[arg(long, value_name = "ORIGIN", value_parser = cors_origin, help_heading = WEB)]
+    pub cors_origin: Vec<String>,
+    /// Also serve HTTPS with this certificate chain (tls feature)
+    #[arg(long, value_name = "PEM", help_heading = WEB)]
+    pub tls_cert: Option<PathBuf>,
+    /// Private key of --tls-cert
+    #[arg(long, value_name = "PEM", help_heading = WEB)]
+    pub tls_key: Option<PathBuf>,
+    /// HTTPS listener (default 0.0.0.0:3443)
+    #[arg(long, value_name = "HOST:PORT", value_parser = parse_host_port, help_heading = WEB)]
+    pub https_address: Option<(String, u16)>,
+    /// With TLS, don't serve plain HTTP
+    #[arg(long, help_heading = WEB)]
+    pub no_http: bool,
+    /// Threads answering web requests (default 4)
+    #[arg(long, value_name = "N", value_parser = at_least_one, help_heading = WEB)]
+    pub web_workers: Option<usize>,
+    /// Web connections served at once (default 256)
+    #[arg(long, value_name = "N", value_parser = at_least_one, help_heading = WEB)]
+    pub web_max_connections: Option<usize>,
+    /// Time a web client gets to send its headers (default 10)
+    #[arg(long, value_name = "SECS", value_parser = seconds, help_heading = WEB)]
+    pub web_header_timeout: Option<Duration>,
+    /// How long Ctrl+C waits for web connections to finish (default 5)
+    #[arg(long, value_name = "SECS", value_parser = seconds, help_heading = WEB)]
+    pub web_drain_timeout: Option<Duration>,
+    /// How often WebSocket clients are pinged (default 20)
+    #[arg(long, value_name = "SECS", value_parser = seconds, help_heading = WEB)]
+    pub ws_ping_interval: Option<Duration>,
+    /// Close web connections after every response
+    #[arg(long, help_heading = WEB)]
+    pub no_keep_alive: bool,
+    /// Frame age after which /healthz fails during play (default 15)
+    #[arg(long, value_name = "SECS", value_parser = seconds, help_heading = WEB)]
+    pub health_stale_after: Option<Duration>,
+    /// Court advertised over mDNS and named in the MQTT topics, e.g. court-2
+    #[arg(long, value_name = "ID", value_parser = court_id, help_heading = WEB)]
+    pub court_id: Option<String>,
+    /// Don't advertise the server on the local network (mdns feature)
+    #[arg(long, help_heading = WEB)]
+    pub no_mdns: bool,
+
+    /// Theme served at / (default classic)
+    #[arg(long, value_name = "NAME", help_heading = OVERLAYS)]
+    pub theme: Option<String>,
+    /// Default overlay language
+    #[arg(long, value_name = "en|pt|fr", value_parser = lang, help_heading = OVERLAYS)]
+    pub lang: Option<Lang>,
+    /// Show the away team on the home side
+    #[arg(long, help_heading = OVERLAYS)]
+    pub swap_sides: bool,
+    /// Polling interval while the WebSocket is down (default 1000, at least 100)
+    #[arg(long, value_name = "MS", value_parser = poll_interval, help_heading = OVERLAYS)]
+    pub poll_interval: Option<Duration>,
+    /// Pages only poll
+    #[arg(long, help_heading = OVERLAYS)]
+    pub no_websocket: bool,
+    /// Terminal scoreboard on stdout (q stops; send logs elsewhere: 2>log)
+    #[arg(long, help_heading = OVERLAYS)]
+    pub tui: bool,
+    /// Where team details are saved (default teams.json)
+    #[arg(long, value_name = "PATH", help_heading = OVERLAYS)]
+    pub teams_file: Option<PathBuf>,
+    /// Where match details are saved (default match.json)
+    #[arg(long, value_name = "PATH", help_heading = OVERLAYS)]
+    pub match_file: Option<PathBuf>,
+    /// CSS added to every overlay page, also saved there by PUT /api/style (default custom.css)
+    #[arg(long, value_name = "FILE", help_heading = OVERLAYS)]
+    pub custom_css: Option<PathBuf>,
+    /// Where team logos uploaded from the admin page are saved (default uploads)
+    #[arg(long, value_name = "DIR", help_heading = OVERLAYS)]
+    pub upload_dir: Option<PathBuf>,
+    /// Serve overlay files from this directory, falling back to the embedded ones
+    #[arg(long, value_name = "DIR", value_parser = directory, help_heading = OVERLAYS)]
+    pub web_root: Option<PathBuf>,
+
+    /// Detach from the terminal
+    #[arg(long, help_heading = BACKGROUND)]
+    pub daemon: bool,
+    /// Write and lock the PID file; refuse to start if another instance holds it
+    #[arg(long, value_name = "PATH", help_heading = BACKGROUND)]
+    pub pid_file: Option<PathBuf>,
+    /// Send the log there instead of the terminal
+    #[arg(long, value_name = "PATH", help_heading = BACKGROUND)]
+    pub log_file: Option<PathBuf>,
+    /// Rotate the log file at this size, keeping 5 (default 10M)
+    #[arg(long, value_name = "SIZE", value_parser = size, help_heading = BACKGROUND)]
+    pub log_max_size: Option<u64>,
+
+    /// Log lines as text or JSON objects (for Loki and the like)
+    #[arg(long, visible_alias = "format", value_name = "text|json", hide_possible_values = true, help_heading = LOGGING)]
+    pub log_format: Option<LogFormat>,
+    /// Also write the log to DIR/scoreboard.<date>.log, keeping 14 files
+    #[arg(long, value_name = "DIR", help_heading = LOGGING)]
+    pub log_dir: Option<PathBuf>,
+    /// When the next file in --log-dir starts (default daily)
+    #[arg(long, value_name = "daily|hourly|never", hide_possible_values = true, help_heading = LOGGING)]
+    pub log_rotation: Option<LogRotation>,
+    /// Zone of every timestamp written out: Europe/Lisbon, +01:00, a POSIX TZ rule, UTC or system (default)
+    #[arg(long, value_name = "ZONE", value_parser = TimeZone::parse, allow_hyphen_values = true, help_heading = LOGGING)]
+    pub timezone: Option<TimeZone>,
+
+    /// Read options from a file, one per line without the dashes
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+}
+
+/// What `--help` says after the server options.
+const SERVE_AFTER_HELP: &str = "\
+Config file: --config <file> reads options from a file, one per line without the dashes
+  (`web-address 0.0.0.0:8080`, `no-mdns`, # comments). SIGHUP, POST /api/reload or editing the file
+  applies theme, lang, swap-sides, poll-interval, websocket, summary-webhook and the teams file at once.
+  Several courts: a [[court]] line starts the options of each court (listen, court-id, files,
+  outputs) after the shared ones; courts are served at /court/<id>/, the first also at /.
+
+Environment: every option can also be set as SCOREBOARD_<OPTION>, e.g. SCOREBOARD_WEB_ADDRESS=0.0.0.0:8080,
+  SCOREBOARD_NO_MDNS=1, SCOREBOARD_FORWARD=a:1,b:2 (SCOREBOARD_TCP_ADDR, SCOREBOARD_WEB_ADDR for short);
+  the command line wins over the environment, which wins over the config file.";
+
+/// The server's options as a command of their own, for [`ServerConfig::from_args`]. Each
+/// option may be repeated, the last value winning, as the environment and config file add
+/// theirs before the command line's.
+fn serve_command() -> clap::Command {
+    ServeArgs::augment_args(clap::Command::new("serve").no_binary_name(true).args_override_self(true))
+}
+
+/// A clap error as one line, without the usage and tips it ends with on a terminal:
+/// `invalid value 'x' for '--rules <fiba|nba|ncaa>' [possible values: ...]`.
+pub(crate) fn clap_message(e: &clap::Error) -> String {
+    let rendered = e.render().to_string();
+    let lines: Vec<&str> = rendered.lines().take_while(|line| !line.trim().is_empty()).map(str::trim).collect();
+    let message = lines.join(" ");
+    let message = message.strip_prefix("error: ").unwrap_or(&message);
+    let mut chars = message.chars();
+    chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect())
+}
+
+/// `args` with every option under its own name and in the order given, one value per
+/// occurrence: `--web-addr=a:1` -> `--web-address a:1`, so the environment and config file
+/// can be layered under them by name.
+fn canonical_args(args: &[String]) -> Result<Vec<String>, String> {
+    let command = serve_command();
+    let matches = command.clone().try_get_matches_from(args).map_err(|e| clap_message(&e))?;
+    let mut given: Vec<(usize, Vec<String>)> = Vec::new();
+    for arg in command.get_arguments() {
+        let id = arg.get_id().as_str();
+        if matches.value_source(id) != Some(ValueSource::CommandLine) {
+            continue;
+        }
+        let name = arg.get_long().map(|long| format!("--{}", long));
+        if !arg.get_action().takes_values() {
+            given.extend(matches.index_of(id).map(|index| (index, name.into_iter().collect())));
+            continue;
+        }
+        let values = matches.get_raw(id).into_iter().flatten().map(|value| value.to_string_lossy().into_owned());
+        for (index, value) in matches.indices_of(id).into_iter().flatten().zip(values) {
+            // The modes are bare words
+            given.push((index, name.iter().cloned().chain([value]).collect()));
+        }
+    }
+    given.sort_by_key(|(index, _)| *index);
+    Ok(given.into_iter().flat_map(|(_, words)| words).collect())
+}
+
+/// `--listen`: `host:port` (TCP) or `pipe:<name>` (Windows named pipe), with the address to bind for TCP.
+fn listen_address(value: &str) -> Result<(InputMode, Option<String>), String> {
+    parse_listen_address(value)
+}
+
+/// `host:port`, with an IPv6 host in brackets, as the address to bind.
+fn host_port(value: &str) -> Result<String, String> {
+    let (host, port) = parse_host_port(value)?;
+    Ok(join_host_port(&host, port))
+}
+
+fn not_empty(value: &str) -> Result<String, String> {
+    if value.is_empty() {
+        return Err("must not be empty".to_string());
+    }
+    Ok(value.to_string())
+}
+
+/// A hex byte, or `none` for no heartbeat.
+fn heartbeat_byte(value: &str) -> Result<Option<u8>, String> {
+    if value == "none" {
+        return Ok(None);
+    }
+    u8::from_str_radix(value.trim_start_matches("0x"), 16).map(Some).map_err(|e| e.to_string())
+}
+
+fn seconds(value: &str) -> Result<Duration, String> {
+    value.parse().map(Duration::from_secs).map_err(|e: ParseIntError| e.to_string())
+}
+
+/// Seconds, at least 1.
+fn whole_seconds(value: &str) -> Result<Duration, String> {
+    match value.parse() {
+        Ok(secs) if secs > 0 => Ok(Duration::from_secs(secs)),
+        _ => Err("expected a number of seconds above 0".to_string()),
+    }
+}
+
+fn milliseconds(value: &str) -> Result<Duration, String> {
+    value.parse().map(Duration::from_millis).map_err(|e: ParseIntError| e.to_string())
+}
+
+fn days(value: &str) -> Result<Duration, String> {
+    match value.parse::<u64>() {
+        Ok(days) if days > 0 => Ok(Duration::from_secs(days * 24 * 3600)),
+        _ => Err("expected a number of days above 0".to_string()),
+    }
+}
+
+fn poll_interval(value: &str) -> Result<Duration, String> {
+    let interval = milliseconds(value)?;
+    if interval < Duration::from_millis(100) {
+        return Err("must be at least 100 milliseconds".to_string());
+    }
+    Ok(interval)
+}
+
+fn at_least_one(value: &str) -> Result<usize, String> {
+    value.parse().ok().filter(|&n| n > 0).ok_or_else(|| "expected at least 1".to_string())
+}
+
+fn size(value: &str) -> Result<u64, String> {
+    parse_size(value).ok_or_else(|| "expected bytes or 500K, 10M, 1G".to_string())
+}
+
+fn jsonl_template(value: &str) -> Result<String, String> {
+    jsonl_logger::validate_template(value)?;
+    Ok(value.to_string())
+}
+
+fn lang(value: &str) -> Result<Lang, String> {
+    Lang::parse(value).ok_or_else(|| "expected en, pt or fr".to_string())
+}
+
+fn directory(value: &str) -> Result<PathBuf, String> {
+    let dir = PathBuf::from(value);
+    if !dir.is_dir() {
+        return Err("not a directory".to_string());
+    }
+    Ok(dir)
+}
+
+fn cors_origin(value: &str) -> Result<String, String> {
+    if value != "*" && !value.starts_with("http://") && !value.starts_with("https://") {
+        return Err("expected http(s)://host[:port] or `*`".to_string());
+    }
+    Ok(value.to_string())
+}
+
+fn court_id(value: &str) -> Result<String, String> {
+    if value.is_empty() || value.len() > 63 {
+        return Err("expected 1 to 63 characters".to_string());
+    }
+    Ok(value.to_string())
+}
+
+/// A game event kind of the chat messages, as [`notify::kinds`] names them.
+fn notify_kind(value: &str) -> Result<&'static str, String> {
+    let kinds = notify::kinds();
+    kinds
+        .iter()
+        .find(|known| known.eq_ignore_ascii_case(value.trim()))
+        .copied()
+        .ok_or_else(|| format!("unknown type {}: expected one of {}", value.trim(), kinds.join(", ")))
+}
+
+/// `<kind>=<template>`.
+fn notify_template(value: &str) -> Result<(&'static str, String), String> {
+    let (kind, template) = value
+        .split_once('=')
+        .filter(|(_, template)| !template.trim().is_empty())
+        .ok_or("expected <kind>=<template>")?;
+    let kind = notify_kind(kind)?;
+    notify::check_template(template)?;
+    Ok((kind, template.trim().to_string()))
+}
+
+/// `host[:port]` of the MQTT broker, 1883 by default.
+fn mqtt_broker(value: &str) -> Result<(String, u16), String> {
+    let (host, port) = match value.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().map_err(|e| format!("invalid port: {}", e))?),
+        None => (value, DEFAULT_MQTT_PORT),
+    };
+    if host.is_empty() {
+        return Err("no host".to_string());
+    }
+    Ok((host.to_string(), port))
+}
+
+fn discovery_prefix(value: &str) -> Result<String, String> {
+    if value.is_empty() || value.contains(['+', '#']) || value.starts_with('/') || value.ends_with('/') {
+        return Err("expected a topic without wildcards".to_string());
+    }
+    Ok(value.to_string())
+}
+
+/// `host[:port]` of the StatsD server, 8125 by default.
+fn statsd_address(value: &str) -> Result<(String, u16), String> {
+    match parse_host_port(value) {
+        Ok(address) => Ok(address),
+        Err(e) => parse_host(value).map(|host| (host, DEFAULT_STATSD_PORT)).map_err(|_| e),
+    }
+}
+
+fn statsd_prefix(value: &str) -> Result<String, String> {
+    // StatsD splits lines at ':' and '|', and graphite paths at '.'
+    let part = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !value.split('.').all(part) {
+        return Err("expected letters, digits, _ or -, in parts separated by .".to_string());
+    }
+    Ok(value.to_string())
+}
+
+fn sample_rate(value: &str) -> Result<f64, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|rate| (0.0..=1.0).contains(rate))
+        .ok_or_else(|| "expected a number from 0 to 1".to_string())
+}
+
+/// `<key>=<name>` of an XML element or attribute.
+fn xml_name(value: &str) -> Result<(&'static str, String), String> {
+    let (key, name) = value
+        .split_once('=')
+        .map(|(key, name)| (key.trim(), name.trim()))
+        .ok_or("expected <key>=<name>")?;
+    let key = xml::KEYS
+        .iter()
+        .find(|&&known| known == key)
+        .ok_or_else(|| format!("unknown key {} (known: {})", key, xml::KEYS.join(", ")))?;
+    if !xml::valid_name(name) {
+        return Err(format!("{:?} is not an XML name (a letter or _, then letters, digits, _, - or .)", name));
+    }
+    Ok((key, name.to_string()))
+}
+
+/// `<event>=<command>`.
+fn hook(value: &str) -> Result<HookConfig, String> {
+    let (event, command) = value
+        .split_once('=')
+        .filter(|(_, command)| !command.trim().is_empty())
+        .ok_or("expected <event>=<command>")?;
+    let event = GameEvent::KINDS
+        .iter()
+        .find(|kind| kind.eq_ignore_ascii_case(event.trim()))
+        .ok_or_else(|| format!("unknown event {}: expected one of {}", event, GameEvent::KINDS.join(", ")))?;
+    Ok(HookConfig { event, command: command.trim().to_string() })
+}
+
+/// Runtime configuration for the TCP ingest side of the server.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ServerConfig {
+    /// Where raw protocol bytes come from.
+    pub input: InputMode,
+    /// Where a followed file is first read from (`--follow-offset`).
+    pub follow_start: FollowStart,
+    /// Address the ingest listener binds to.
+    pub tcp_address: String,
+    /// Also read frames from datagrams on this address, next to the TCP listener.
+    pub udp_address: Option<String>,
+    /// Every listener binds all interfaces (`--public`), whatever host it was given.
+    pub public: bool,
+    /// Write the raw bytes of every TCP session to the capture directory (`data_log/`).
+    pub log_to_file: bool,
+    /// How the session captures are rolled, compressed and deleted.
+    pub capture: CaptureConfig,
+    /// Downstream targets that receive a copy of every raw chunk read from the console.
+    pub forward_to: Vec<SocketAddr>,
+    /// Accept frames whose LRC does not match (logged as a warning).
+    pub lenient: bool,
+    /// How repeated identical states are handled.
+    pub dedupe: Dedupe,
+    /// Overlay theme served at `/` when the URL doesn't pick one with `?theme=`.
+    pub theme: String,
+    /// Language of the overlay's display strings when the request doesn't ask for one.
+    pub lang: Lang,
+    /// Show the away team on the home side of the overlays unless the URL has `?swap=`.
+    pub swap_sides: bool,
+    /// Interval of the overlays' `/api/game` polling while their WebSocket is down (or off).
+    pub poll_interval: Duration,
+    /// Let the overlay pages open the WebSocket; with `--no-websocket` they only poll.
+    pub websocket: bool,
+    /// File the team details are saved to and restored from at startup.
+    pub teams_file: PathBuf,
+    /// File the match details are saved to and restored from at startup.
+    pub match_file: PathBuf,
+    /// File the custom overlay CSS is read from at startup and saved to by `PUT /api/style`.
+    pub custom_css_file: PathBuf,
+    /// Directory uploaded team logos are saved to and served from (`/uploads/`).
+    pub upload_dir: PathBuf,
+    /// Directory of overlay files served instead of the embedded ones, where present.
+    pub web_root: Option<PathBuf>,
+    /// Origins allowed to call the JSON API from browser apps (`*` for any); none by default.
+    pub cors_allowed_origins: Vec<String>,
+    /// Token required by the admin page and control endpoints; generated at startup when unset.
+    pub admin_token: Option<String>,
+    /// Also require the admin token on the read-only web endpoints (state, streams, overlay data).
+    pub lock_read_api: bool,
+    /// What console frames do while the operator has overridden the state over HTTP.
+    pub override_mode: OverrideMode,
+    /// Reply `ACK\n` / `ERR <reason>\n` to every frame received over TCP.
+    pub ack: bool,
+    /// Shared secret TCP clients must present with `AUTH <token>\n` before sending frames, and
+    /// the key of the HMAC-SHA256 every UDP datagram must end with.
+    pub auth_token: Option<String>,
+    /// Address of the TCP port streaming every published state as JSON lines.
+    pub subscriber_address: Option<String>,
+    /// Published states kept for `/api/history`; zero disables the history.
+    pub history_capacity: usize,
+    /// CSV file every state change is appended to, one row each.
+    pub log_csv: Option<PathBuf>,
+    /// Path template of the JSON Lines log of every state and event, e.g. `scores-%Y%m%d.jsonl`.
+    pub log_jsonl: Option<String>,
+    /// Size at which the JSON Lines log moves on to a new file; only the date rolls it over when unset.
+    pub log_jsonl_max_size: Option<u64>,
+    /// Directory every game is recorded to, one file per game, for `history`.
+    pub games_dir: Option<PathBuf>,
+    /// SQLite database every game is recorded to, for `history` and SQL queries (builds with
+    /// the `sqlite` feature).
+    pub games_db: Option<PathBuf>,
+    /// Pause in the state updates after which the recorded game is over.
+    pub game_idle_gap: Duration,
+    /// Time without the game clock running after which the console being reset for the next
+    /// game (scores back to 0, or the first period) starts a new game: the game log, summary
+    /// and line score start again.
+    pub auto_reset: Option<Duration>,
+    /// File the state and its derived data are saved to, and restored from at startup.
+    pub state_file: Option<PathBuf>,
+    /// Age beyond which the state file is ignored at startup.
+    pub state_max_age: Duration,
+    /// URL every game summary is POSTed to when the game ends.
+    pub summary_webhook: Option<HttpUrl>,
+    /// Slack and Discord messages on game events.
+    pub notify: NotifyConfig,
+    /// POST the state to a remote URL as it changes.
+    pub push: Option<PushConfig>,
+    /// File the options were read from (`--config`), read again on a reload.
+    pub config_file: Option<PathBuf>,
+    /// Rules deciding when team fouls put the other team in the bonus.
+    pub rules: RuleSet,
+    /// Length of a time-out on the overlay countdown; `None` uses the rule set's.
+    pub timeout_length: Option<Duration>,
+    /// Count the game clock down between console frames (`display_clock`).
+    pub tick_clock: bool,
+    /// How long a running game may go without frames before `/healthz` reports unhealthy.
+    pub health_stale_after: Duration,
+    /// Threads answering web requests.
+    pub web_workers: usize,
+    /// Web connections served at once; further clients wait in the listen backlog.
+    pub web_max_connections: usize,
+    /// Time a web client gets to send its request headers before it is disconnected.
+    pub web_header_timeout: Duration,
+    /// Interval between pings to each WebSocket client; one that doesn't answer within half
+    /// of it is disconnected.
+    pub ws_ping_interval: Duration,
+    /// Keep web connections open between requests (HTTP/1.1 keep-alive).
+    pub web_keep_alive: bool,
+    /// Address of the overlay web server (`0.0.0.0:3030`).
+    pub web_address: SocketAddr,
+    /// How long a web server shutdown (Ctrl+C) waits for open connections to finish.
+    pub web_drain_timeout: Duration,
+    /// Serve the web server over HTTPS; plain HTTP only when unset.
+    pub tls: Option<TlsConfig>,
+    /// Advertise the overlay and ingest port over mDNS/DNS-SD (builds with the `mdns` feature).
+    pub mdns: bool,
+    /// Court identifier advertised in the mDNS TXT records and used in the MQTT topics, for
+    /// venues with several courts.
+    pub court_id: Option<String>,
+    /// Publish the state and events to an MQTT broker (builds with the `mqtt` feature).
+    pub mqtt: Option<MqttConfig>,
+    /// UDP targets of the OSC messages for lighting and audio consoles.
+    pub osc_targets: Vec<SocketAddr>,
+    /// File mapping the OSC messages to the addresses a console expects; defaults when unset.
+    pub osc_map: Option<PathBuf>,
+    /// Send counters, gauges and frame timings to a StatsD server.
+    pub statsd: Option<StatsdConfig>,
+    /// File rewritten with the state as XML for CG systems that poll one.
+    pub xml_file: Option<PathBuf>,
+    /// Shortest time between two rewrites of `xml_file` while only the clock changes.
+    pub xml_interval: Duration,
+    /// Element and attribute names replacing the defaults of the XML output, by [`xml::KEYS`] key.
+    pub xml_names: Vec<(&'static str, String)>,
+    /// Commands run on game events.
+    pub hooks: Vec<HookConfig>,
+    /// Time a hook command may run before it is killed.
+    pub hook_timeout: Duration,
+    /// Show the terminal dashboard on standard output while serving.
+    pub tui: bool,
+    /// Detach from the terminal and run in the background (Unix).
+    pub daemon: bool,
+    /// File holding the server's PID, locked while it runs, for `stop` (Unix).
+    pub pid_file: Option<PathBuf>,
+    /// File standard output and error (the log) are sent to (Unix).
+    pub log_file: Option<PathBuf>,
+    /// Size at which the log file is rotated.
+    pub log_max_size: u64,
+    /// Text or JSON log lines, on the console and in `log_dir`.
+    pub log_format: LogFormat,
+    /// Directory the log is also written to, a file per day or hour.
+    pub log_dir: Option<PathBuf>,
+    /// When the next file in `log_dir` starts.
+    pub log_rotation: LogRotation,
+    /// Zone the timestamps of the logs, exports and API are written in, as RFC 3339 with its
+    /// UTC offset.
+    pub timezone: TimeZone,
+    /// How long a subscriber may stop reading before it is disconnected.
+    pub backpressure_timeout: Duration,
+    /// How often per-peer traffic summaries are logged; zero disables them.
+    pub peer_summary_interval: Duration,
+    /// Link test mode: echo every TCP connection back instead of ingesting it.
+    pub echo: bool,
+    /// Single-byte keepalives sent between frames, consumed and counted as heartbeats.
+    pub heartbeat_bytes: Vec<u8>,
+    /// Courts of the `[[court]]` sections of the config file, each with its own input, court id
+    /// and outputs; the top-level options then only configure the web server and the process.
+    /// Empty when there is a single court.
+    pub courts: Vec<ServerConfig>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            input: InputMode::Tcp,
+            follow_start: FollowStart::default(),
+            tcp_address: DEFAULT_TCP_ADDRESS.to_string(),
+            udp_address: None,
+            public: false,
+            log_to_file: true,
+            capture: CaptureConfig::default(),
+            forward_to: Vec::new(),
+            lenient: false,
+            dedupe: Dedupe::Exact,
+            theme: DEFAULT_THEME.to_string(),
+            lang: Lang::default(),
+            swap_sides: false,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            websocket: true,
+            web_root: None,
+            teams_file: PathBuf::from(DEFAULT_TEAMS_FILE),
+            match_file: PathBuf::from(DEFAULT_MATCH_FILE),
+            custom_css_file: PathBuf::from(DEFAULT_CUSTOM_CSS_FILE),
+            upload_dir: PathBuf::from(DEFAULT_UPLOAD_DIR),
+            cors_allowed_origins: Vec::new(),
+            admin_token: None,
+            lock_read_api: false,
+            override_mode: OverrideMode::Resume,
+            ack: false,
+            echo: false,
+            peer_summary_interval: DEFAULT_PEER_SUMMARY_INTERVAL,
+            subscriber_address: None,
+            backpressure_timeout: DEFAULT_BACKPRESSURE_TIMEOUT,
+            history_capacity: DEFAULT_HISTORY_CAPACITY,
+            log_csv: None,
+            log_jsonl: None,
+            log_jsonl_max_size: None,
+            games_dir: None,
+            games_db: None,
+            game_idle_gap: game_archive::DEFAULT_IDLE_GAP,
+            auto_reset: None,
+            state_file: None,
+            state_max_age: DEFAULT_STATE_MAX_AGE,
+            summary_webhook: None,
+            notify: NotifyConfig::default(),
+            push: None,
+            config_file: None,
+            rules: RuleSet::Fiba,
+            timeout_length: None,
+            tick_clock: false,
+            health_stale_after: DEFAULT_HEALTH_STALE_AFTER,
+            web_workers: DEFAULT_WEB_WORKERS,
+            web_max_connections: DEFAULT_WEB_MAX_CONNECTIONS,
+            web_header_timeout: DEFAULT_WEB_HEADER_TIMEOUT,
+            ws_ping_interval: DEFAULT_WS_PING_INTERVAL,
+            web_keep_alive: true,
+            web_address: DEFAULT_WEB_ADDRESS,
+            web_drain_timeout: DEFAULT_WEB_DRAIN_TIMEOUT,
+            tls: None,
+            mdns: true,
+            court_id: None,
+            mqtt: None,
+            osc_targets: Vec::new(),
+            osc_map: None,
+            statsd: None,
+            xml_file: None,
+            xml_interval: xml::DEFAULT_XML_INTERVAL,
+            xml_names: Vec::new(),
+            hooks: Vec::new(),
+            hook_timeout: DEFAULT_HOOK_TIMEOUT,
+            tui: false,
+            daemon: false,
+            pid_file: None,
+            log_file: None,
+            log_max_size: DEFAULT_LOG_MAX_SIZE,
+            log_format: LogFormat::Text,
+            log_dir: None,
+            log_rotation: LogRotation::Daily,
+            timezone: TimeZone::system(),
+            auth_token: None,
+            heartbeat_bytes: vec![0x00],
+            courts: Vec::new(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Build the configuration from the process arguments (program name excluded): the
+    /// options of [`ServeArgs`], checked for how they combine.
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        Self::from_env_and_args(std::iter::empty(), args)
+    }
+
+    /// Parse the server options from a config file, environment variables, then the command line.
+    ///
+    /// Every option of [`from_args`](Self::from_args) can be set with a variable named
+    /// [`ENV_PREFIX`] plus the option in upper case, `_` for `-`: `SCOREBOARD_WEB_ADDRESS=0.0.0.0:8080`,
+    /// `SCOREBOARD_ADMIN_TOKEN=...`; `SCOREBOARD_TCP_ADDR` and `SCOREBOARD_WEB_ADDR` are short
+    /// for `--listen` and `--web-address`. Switches take `1`/`0`, `true`/`false`, `yes`/`no` or
+    /// `on`/`off`, repeatable options a comma-separated list. The options of the MQTT, TLS and
+    /// StatsD sections are also `SCOREBOARD_<SECTION>__<FIELD>`: `SCOREBOARD_MQTT__QOS`,
+    /// `SCOREBOARD_STATSD__ADDRESS` for `--statsd`. Unknown `SCOREBOARD_` variables are
+    /// refused. `vars` is usually `std::env::vars()`.
+    ///
+    /// `--config <file>` (or `SCOREBOARD_CONFIG`) names a file of options, one per line as on
+    /// the command line without the dashes: `web-address 0.0.0.0:8080`, `no-mdns`; `#` starts
+    /// a comment. An option on the command line wins over its variable, which wins over the
+    /// file (for the input, any input option wins over the others); defaults apply to the
+    /// rest. Errors name the variable or file line at fault.
+    ///
+    /// A venue with several courts gives each one a `[[court]]` section at the end of the file,
+    /// with the court's input, `court-id` (its number by default) and outputs; the courts
+    /// start from the options before the first section. Files a court doesn't name itself go
+    /// in a `court-<id>` directory beside the top-level one (`court-2/teams.json`). The
+    /// environment and command line apply to every court, except for the input;
+    /// `SCOREBOARD_COURT__<n>__<OPTION>` sets an option of the n-th section only, its input
+    /// included.
+    pub fn from_env_and_args(vars: impl IntoIterator<Item = (String, String)>, args: &[String]) -> Result<Self, String> {
+        let args = &canonical_args(args)?;
+        let mut vars: Vec<(String, String)> = vars.into_iter().filter(|(name, _)| name.starts_with(ENV_PREFIX)).collect();
+        vars.sort();
+        let (mut env, mut court_env) = (Vec::new(), Vec::new());
+        for (name, value) in &vars {
+            let (court, option, kind) = env_setting(name).ok_or_else(|| format!("Unknown setting {}: no such option (see --help)", name))?;
+            let values = match kind {
+                EnvValue::Switch => vec![],
+                EnvValue::One => vec![value.clone()],
+                EnvValue::List => value.split(',').map(str::trim).filter(|item| !item.is_empty()).map(String::from).collect(),
+            };
+            if kind != EnvValue::Switch || switch_value(value).ok_or_else(|| format!("Invalid {}: expected true or false, found {:?}", name, value.trim()))? {
+                let setting = Setting { option, values, source: name.clone() };
+                match court {
+                    Some(number) => court_env.push((number, setting)),
+                    None => env.push(setting),
+                }
+            }
+        }
+
+        let config_file = match args.iter().position(|arg| arg == "--config") {
+            Some(i) => Some(args.get(i + 1).ok_or_else(|| "--config requires a file path".to_string())?.clone()),
+            None => env.iter().find(|setting| setting.option == "--config").and_then(|setting| setting.values.first().cloned()),
+        };
+        let ConfigFile { settings: file, courts } = match &config_file {
+            Some(path) => read_config_file(Path::new(path))?,
+            None => ConfigFile::default(),
+        };
+
+        // Command line over environment over file
+        let given = |option: &str, layer: &[String]| {
+            let input = INPUT_OPTIONS.contains(&option);
+            layer.iter().any(|arg| arg == option || (input && INPUT_OPTIONS.contains(&arg.as_str())))
+        };
+        let env: Vec<Setting> = env.into_iter().filter(|setting| !given(setting.option, args)).collect();
+        let env_options: Vec<String> = env.iter().map(|setting| setting.option.to_string()).collect();
+        let file: Vec<Setting> = file
+            .into_iter()
+            .filter(|setting| !given(setting.option, args) && !given(setting.option, &env_options))
+            .collect();
+
+        // The command line still wins over a court's variables, but picks no court's input
+        let court_env: Vec<(usize, Setting)> = court_env
+            .into_iter()
+            .filter(|(_, setting)| INPUT_OPTIONS.contains(&setting.option) || !given(setting.option, args))
+            .collect();
+        if let Some((_, setting)) = court_env.iter().find(|(number, _)| *number > courts.len()) {
+            return Err(format!("{} names a court the config file doesn't have ({} [[court]] sections)", setting.source, courts.len()));
+        }
+
+        let sources: Vec<&Setting> = file.iter().chain(&env).collect();
+        let names: Vec<&str> = env.iter().chain(court_env.iter().map(|(_, setting)| setting)).map(|setting| setting.source.as_str()).collect();
+        if !names.is_empty() {
+            // Names only: the values may be tokens
+            info!("Settings from the environment: {}", names.join(", "));
+        }
+        if let Some(path) = &config_file {
+            info!("Settings from {}: {} options", path, file.len());
+        }
+
+        let layered: Vec<String> = sources.iter().flat_map(|setting| setting.args()).chain(args.iter().cloned()).collect();
+        let mut config = Self::parse_args(&layered).map_err(|e| blame(e, &sources))?;
+
+        for (index, section) in courts.iter().enumerate() {
+            let own_env: Vec<&Setting> = court_env.iter().filter(|(number, _)| *number == index + 1).map(|(_, setting)| setting).collect();
+            let court = config.court(index + 1, section, &file, &env, &own_env, args)?;
+            if config.courts.iter().any(|other| other.court_id == court.court_id) {
+                return Err(format!("Duplicate court-id {:?} in {}", court.court_id.unwrap_or_default(), section.source));
+            }
+            config.courts.push(court);
+        }
+        if !courts.is_empty() {
+            info!("{} courts: {}", courts.len(), config.courts.iter().filter_map(|court| court.court_id.as_deref()).collect::<Vec<_>>().join(", "));
+        }
+        Ok(config)
+    }
+
+    /// The configuration of court `number` from its `section` of the config file, on top of
+    /// the options before the first section (`file`); the environment and command line still
+    /// win, but pick no input. The court's own variables (`own_env`) win over the section and
+    /// the other variables, and may pick its input.
+    fn court(&self, number: usize, section: &CourtSection, file: &[Setting], env: &[Setting], own_env: &[&Setting], args: &[String]) -> Result<Self, String> {
+        let input = |setting: &&Setting| INPUT_OPTIONS.contains(&setting.option);
+        if let Some(setting) = section.settings.iter().chain(own_env.iter().copied()).find(|setting| SERVER_OPTIONS.contains(&setting.option)) {
+            return Err(format!(
+                "{} applies to the whole server, not one court, in {}",
+                setting.option.trim_start_matches("--"),
+                setting.source
+            ));
+        }
+        let env_input = own_env.iter().any(input);
+        let listed: Vec<&Setting> = section.settings.iter().filter(|setting| !(env_input && input(setting))).collect();
+        let own: Vec<&Setting> = listed.iter().chain(own_env).copied().collect();
+        let own_input = own.iter().any(input);
+        let inherited = file
+            .iter()
+            .filter(|setting| !own.iter().any(|own| own.option == setting.option))
+            .filter(|setting| !(own_input && input(setting)));
+        let env = env.iter().filter(|setting| !input(setting) && !own_env.iter().any(|own| own.option == setting.option));
+        // Later settings win
+        let sources: Vec<&Setting> = inherited.chain(listed.iter().copied()).chain(env).chain(own_env.iter().copied()).collect();
+        let layered: Vec<String> = ["--court-id".to_string(), number.to_string()]
+            .into_iter()
+            .chain(sources.iter().flat_map(|setting| setting.args()))
+            .chain(without_input(args))
+            .collect();
+        let mut court = Self::parse_args(&layered).map_err(|e| format!("{} (court from {})", blame(e, &sources), section.source))?;
+
+        let id = court.court_id.clone().unwrap_or_default();
+        if !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!(
+                "Invalid court-id {:?} in {}: courts are served at /court/<id>, so only letters, digits, '-' and '_' are allowed",
+                id, section.source
+            ));
+        }
+        // Courts don't share the files they write
+        let own = |path: &PathBuf, shared: &PathBuf| if path == shared { court_path(path, &id) } else { path.clone() };
+        let own_option = |path: &Option<PathBuf>, shared: &Option<PathBuf>| path.as_ref().map(|path| shared.as_ref().map_or(path.clone(), |shared| own(path, shared)));
+        court.teams_file = own(&court.teams_file, &self.teams_file);
+        court.match_file = own(&court.match_file, &self.match_file);
+        court.state_file = own_option(&court.state_file, &self.state_file);
+        court.log_csv = own_option(&court.log_csv, &self.log_csv);
+        court.games_dir = own_option(&court.games_dir, &self.games_dir);
+        court.games_db = own_option(&court.games_db, &self.games_db);
+        court.xml_file = own_option(&court.xml_file, &self.xml_file);
+        court.capture.dir = own(&court.capture.dir, &self.capture.dir);
+        if court.log_jsonl.is_some() && court.log_jsonl == self.log_jsonl {
+            court.log_jsonl = court.log_jsonl.map(|template| court_path(Path::new(&template), &id).display().to_string());
+        }
+        // Only the first court watches the file, and a reload applies to it
+        if number > 1 {
+            court.config_file = None;
+        }
+        Ok(court)
+    }
+
+    /// The configuration of the court served at `/`: the first `[[court]]`, or the whole
+    /// configuration when there are none.
+    pub fn primary_court(&self) -> &Self {
+        self.courts.first().unwrap_or(self)
+    }
+
+    fn parse_args(args: &[String]) -> Result<Self, String> {
+        let matches = serve_command().try_get_matches_from(args).map_err(|e| clap_message(&e))?;
+        // Each of these picks the input, so only one may be given
+        let stdin = matches
+            .indices_of("modes")
+            .into_iter()
+            .flatten()
+            .zip(matches.get_many::<Mode>("modes").into_iter().flatten())
+            .find(|(_, mode)| **mode == Mode::Stdin)
+            .map(|(index, _)| (index, "stdin"));
+        let mut inputs: Vec<(usize, &str)> = [("listen", "--listen"), ("tcp_addr", "--tcp-addr"), ("follow", "--follow")]
+            .into_iter()
+            .filter_map(|(id, option)| matches.index_of(id).map(|index| (index, option)))
+            .chain(stdin)
+            .collect();
+        inputs.sort();
+        if let [(_, first), (_, second), ..] = inputs[..] {
+            return Err(format!("{} conflicts with {}: give only one input", second, first));
+        }
+        let args = ServeArgs::from_arg_matches(&matches).map_err(|e| clap_message(&e))?;
+
+        let mut config = Self { log_to_file: !args.modes.contains(&Mode::Dev), ..Self::default() };
+        if args.modes.contains(&Mode::Stdin) {
+            config.input = InputMode::Stdin;
+        }
+        if let Some((input, tcp_address)) = args.listen {
+            config.input = input;
+            if let Some(tcp_address) = tcp_address {
+                config.tcp_address = tcp_address;
+            }
+        }
+        if let Some(tcp_address) = args.tcp_addr {
+            config.tcp_address = tcp_address;
+        }
+        if let Some(path) = args.follow {
+            config.input = InputMode::Follow(path);
+        }
+        if let Some(start) = args.follow_offset {
+            if !matches!(config.input, InputMode::Follow(_)) {
+                return Err("--follow-offset only applies to --follow".to_string());
+            }
+            config.follow_start = start;
+        }
+        config.udp_address = args.udp_addr;
+        config.lenient = args.lenient;
+        config.ack = args.ack;
+        config.auth_token = args.auth_token;
+        config.echo = args.echo;
+        if !args.heartbeat_byte.is_empty() {
+            // Given bytes replace the default; `none` alone leaves none
+            config.heartbeat_bytes = args.heartbeat_byte.into_iter().flatten().collect();
+        }
+        config.forward_to = args.forward;
+        config.subscriber_address = args.subscribers;
+        config.backpressure_timeout = args.backpressure_timeout.unwrap_or(config.backpressure_timeout);
+        config.peer_summary_interval = args.peer_summary_interval.unwrap_or(config.peer_summary_interval);
+        config.capture.dir = args.capture_dir.unwrap_or(config.capture.dir);
+        config.capture.max_size = args.capture_max_size;
+        config.capture.per_game = args.capture_per_game;
+        config.capture.compress = args.capture_compress;
+        config.capture.keep = args.capture_keep;
+        config.capture.max_age = args.capture_max_age;
+
+        config.rules = args.rules.unwrap_or(config.rules);
+        config.timeout_length = args.timeout_length;
+        config.tick_clock = args.tick_clock;
+        config.dedupe = args.dedupe.unwrap_or(config.dedupe);
+        config.override_mode = args.override_mode.unwrap_or(config.override_mode);
+        config.history_capacity = args.history_capacity.unwrap_or(config.history_capacity);
+        config.log_csv = args.log_csv;
+        config.log_jsonl = args.log_jsonl;
+        config.log_jsonl_max_size = args.log_jsonl_max_size;
+        config.games_dir = args.games_dir;
+        config.games_db = args.games_db;
+        config.game_idle_gap = args.game_idle_gap.unwrap_or(config.game_idle_gap);
+        config.auto_reset = args.auto_reset;
+        config.state_file = args.state_file;
+        config.state_max_age = args.state_max_age.unwrap_or(config.state_max_age);
+        config.summary_webhook = args.summary_webhook;
+        let slack = args.slack_webhook.into_iter().map(|url| NotifyTarget { service: Service::Slack, url });
+        let discord = args.discord_webhook.into_iter().map(|url| NotifyTarget { service: Service::Discord, url });
+        config.notify.targets = slack.chain(discord).collect();
+        if config.notify.targets.is_empty() {
+            let notify_options = [
+                ("--notify-events", args.notify_events.is_some()),
+                ("--notify-template", !args.notify_template.is_empty()),
+                ("--notify-min-interval", args.notify_min_interval.is_some()),
+            ];
+            if let Some((option, _)) = notify_options.iter().find(|(_, set)| *set) {
+                return Err(format!("{} requires --slack-webhook or --discord-webhook", option));
+            }
+        }
+        if let Some(events) = args.notify_events {
+            config.notify.events = events;
+        }
+        for (kind, template) in args.notify_template {
+            config.notify.templates.retain(|(templated, _)| *templated != kind);
+            config.notify.templates.push((kind, template));
+        }
+        config.notify.min_interval = args.notify_min_interval.unwrap_or(config.notify.min_interval);
+        if let Some(token) = &args.push_token {
+            if token.is_empty() || token.chars().any(|c| c.is_control() || c.is_whitespace()) {
+                return Err("Invalid --push-token: expected no spaces or control characters".to_string());
+            }
+        }
+        config.push = match args.push_url {
+            Some(url) => Some(PushConfig {
+                url,
+                token: args.push_token,
+                interval: args.push_interval.unwrap_or(DEFAULT_PUSH_INTERVAL),
+                diff: args.push_diff,
+                buffer: args.push_buffer.unwrap_or(DEFAULT_PUSH_BUFFER),
+            }),
+            None if args.push_token.is_some() || args.push_interval.is_some() || args.push_diff || args.push_buffer.is_some() => {
+                return Err("--push-token, --push-interval, --push-diff and --push-buffer require --push-url".to_string());
+            }
+            None => None,
+        };
+        // A prefix alone turns discovery on
+        let discovery = args.mqtt_discovery_prefix.or_else(|| args.mqtt_discovery.then(|| DEFAULT_DISCOVERY_PREFIX.to_string()));
+        config.mqtt = match args.mqtt_broker {
+            Some((host, port)) => Some(MqttConfig { host, port, qos: args.mqtt_qos.unwrap_or(0), discovery }),
+            None if args.mqtt_qos.is_some() => return Err("--mqtt-qos requires --mqtt-broker".to_string()),
+            None if discovery.is_some() => return Err("--mqtt-discovery requires --mqtt-broker".to_string()),
+            None => None,
+        };
+        config.osc_targets = args.osc;
+        config.osc_map = args.osc_map;
+        if config.osc_map.is_some() && config.osc_targets.is_empty() {
+            return Err("--osc-map requires --osc".to_string());
+        }
+        config.statsd = match args.statsd {
+            Some((host, port)) => Some(StatsdConfig {
+                host,
+                port,
+                prefix: args.statsd_prefix.unwrap_or_else(|| "scoreboard".to_string()),
+                interval: args.statsd_interval.unwrap_or(DEFAULT_STATSD_INTERVAL),
+                sample_rate: args.statsd_sample_rate.unwrap_or(DEFAULT_STATSD_SAMPLE_RATE),
+            }),
+            None if args.statsd_prefix.is_some() || args.statsd_interval.is_some() || args.statsd_sample_rate.is_some() => {
+                return Err("--statsd-prefix, --statsd-interval and --statsd-sample-rate require --statsd".to_string());
+            }
+            None => None,
+        };
+        config.xml_file = args.xml_file;
+        config.xml_interval = args.xml_interval.unwrap_or(config.xml_interval);
+        for (key, name) in args.xml_name {
+            config.xml_names.retain(|(renamed, _)| *renamed != key);
+            config.xml_names.push((key, name));
+        }
+        config.hooks = args.hook;
+        config.hook_timeout = args.hook_timeout.unwrap_or(config.hook_timeout);
+
+        config.public = args.public;
+        config.admin_token = args.admin_token;
+        config.lock_read_api = args.lock_read_api;
+        config.cors_allowed_origins = args.cors_origin;
+        config.web_workers = args.web_workers.unwrap_or(config.web_workers);
+        config.web_max_connections = args.web_max_connections.unwrap_or(config.web_max_connections);
+        config.web_header_timeout = args.web_header_timeout.map_or(config.web_header_timeout, |timeout| timeout.max(Duration::from_secs(1)));
+        config.web_drain_timeout = args.web_drain_timeout.unwrap_or(config.web_drain_timeout);
+        config.ws_ping_interval = args.ws_ping_interval.map_or(config.ws_ping_interval, |interval| interval.max(Duration::from_secs(1)));
+        config.web_keep_alive = !args.no_keep_alive;
+        config.health_stale_after = args.health_stale_after.unwrap_or(config.health_stale_after);
+        config.court_id = args.court_id;
+        config.mdns = !args.no_mdns;
+
+        config.theme = args.theme.unwrap_or(config.theme);
+        config.lang = args.lang.unwrap_or(config.lang);
+        config.swap_sides = args.swap_sides;
+        config.poll_interval = args.poll_interval.unwrap_or(config.poll_interval);
+        config.websocket = !args.no_websocket;
+        config.tui = args.tui;
+        config.teams_file = args.teams_file.unwrap_or(config.teams_file);
+        config.match_file = args.match_file.unwrap_or(config.match_file);
+        config.custom_css_file = args.custom_css.unwrap_or(config.custom_css_file);
+        config.upload_dir = args.upload_dir.unwrap_or(config.upload_dir);
+        config.web_root = args.web_root;
+
+        config.daemon = args.daemon;
+        config.pid_file = args.pid_file;
+        config.log_file = args.log_file;
+        config.log_max_size = args.log_max_size.unwrap_or(config.log_max_size);
+        config.log_format = args.log_format.unwrap_or(config.log_format);
+        config.log_dir = args.log_dir;
+        config.log_rotation = args.log_rotation.unwrap_or(config.log_rotation);
+        config.timezone = args.timezone.unwrap_or(config.timezone);
+        config.config_file = args.config;
+
+        let (mut tcp_host, mut web_host) = (args.tcp_host, args.web_host);
+        let mut https_address = match args.https_address {
+            Some((host, port)) => resolve("--https-address", &host, port)?,
+            None => DEFAULT_HTTPS_ADDRESS,
+        };
+        if config.public {
+            if let Some(option) = [("--tcp-host", &tcp_host), ("--web-host", &web_host)].iter().find_map(|(option, host)| host.as_ref().map(|_| option)) {
+                return Err(format!("--public conflicts with {}: it binds all interfaces", option));
+            }
+            let any = Ipv4Addr::UNSPECIFIED.to_string();
+            if config.input == InputMode::Tcp {
+                tcp_host = Some(any.clone());
+            }
+            web_host = Some(any.clone());
+            https_address.set_ip(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+            if let Some(udp) = &mut config.udp_address {
+                if let Ok((_, port)) = parse_host_port(udp) {
+                    *udp = join_host_port(&any, port);
+                }
+            }
+        }
+        if tcp_host.is_some() || args.tcp_port.is_some() {
+            if config.input != InputMode::Tcp {
+                return Err("--tcp-host and --tcp-port only apply to TCP input".to_string());
+            }
+            let (host, port) = parse_host_port(&config.tcp_address)?;
+            config.tcp_address = join_host_port(&tcp_host.unwrap_or(host), args.tcp_port.unwrap_or(port));
+        }
+        let (host, port) = args.web_address.unwrap_or_else(|| (DEFAULT_WEB_ADDRESS.ip().to_string(), DEFAULT_WEB_ADDRESS.port()));
+        config.web_address = resolve("--web-address", &web_host.unwrap_or(host), args.web_port.unwrap_or(port))?;
+
+        let input = match &config.input {
+            InputMode::Tcp => None,
+            InputMode::Stdin => Some("stdin"),
+            InputMode::Follow(_) => Some("--follow"),
+            #[cfg(windows)]
+            InputMode::Pipe(_) => Some("a named pipe"),
+        };
+        if let Some(input) = input {
+            let tcp_only = [
+                ("--ack", config.ack),
+                ("--echo", config.echo),
+                ("--auth-token", config.auth_token.is_some()),
+                ("--udp-addr", config.udp_address.is_some()),
+            ];
+            if let Some((flag, _)) = tcp_only.iter().find(|(_, set)| *set) {
+                return Err(format!("{} only applies to TCP input, not {}", flag, input));
+            }
+        }
+        if config.tui && config.input == InputMode::Stdin {
+            return Err("--tui reads its keys from standard input, so it can't be combined with stdin".to_string());
+        }
+        if config.daemon {
+            if config.tui {
+                return Err("--tui needs the terminal --daemon leaves".to_string());
+            }
+            if config.input == InputMode::Stdin {
+                return Err("--daemon leaves standard input, so it can't be combined with stdin".to_string());
+            }
+        }
+        if !cfg!(unix) && (config.daemon || config.pid_file.is_some() || config.log_file.is_some()) {
+            return Err("--daemon, --pid-file and --log-file are only available on Unix".to_string());
+        }
+        if config.echo && config.ack {
+            return Err("--echo and --ack can't be combined: echo mode doesn't parse frames".to_string());
+        }
+        if config.echo && config.udp_address.is_some() {
+            return Err("--echo and --udp-addr can't be combined: datagrams aren't echoed".to_string());
+        }
+
+        config.tls = match (args.tls_cert, args.tls_key) {
+            (Some(cert), Some(key)) => Some(TlsConfig {
+                cert,
+                key,
+                address: https_address,
+                serve_http: !args.no_http,
+            }),
+            (None, None) if args.no_http => return Err("--no-http requires --tls-cert and --tls-key".to_string()),
+            (None, None) => None,
+            _ => return Err("--tls-cert and --tls-key must be given together".to_string()),
+        };
+        if config.tls.is_some() && !cfg!(feature = "tls") {
+            return Err("HTTPS requires a build with TLS support (cargo build --features tls)".to_string());
+        }
+        if config.games_db.is_some() && !cfg!(feature = "sqlite") {
+            return Err("--games-db requires a build with SQLite support (cargo build --features sqlite)".to_string());
+        }
+        if config.mqtt.is_some() {
+            if !cfg!(feature = "mqtt") {
+                return Err("MQTT requires a build with MQTT support (cargo build --features mqtt)".to_string());
+            }
+            // The court is a topic level: MQTT wildcards and separators would break the topics
+            if let Some(court) = config.court_id.as_deref().filter(|court| court.contains(['/', '+', '#'])) {
+                return Err(format!("Invalid --court-id {:?} for MQTT: '/', '+' and '#' are not allowed", court));
+            }
+        }
+
+        Ok(config)
+    }
+}
@@ -0,0 +1,79 @@
+use warp::{
+    http::{header, HeaderValue, StatusCode},
+    reply::Response,
+};
+
+const ALLOWED_METHODS: &str = "GET, POST, PUT, PATCH, DELETE, OPTIONS";
+const ALLOWED_HEADERS: &str = "Authorization, Content-Type, If-None-Match, Last-Event-ID";
+/// How long browsers may cache a preflight answer, in seconds.
+const PREFLIGHT_MAX_AGE: &str = "600";
+
+/// Which other origins may call the JSON API (`/api/*`) from a browser.
+///
+/// Only API responses get CORS headers; the overlay and admin pages are unaffected.
+#[derive(Debug, Clone, Default)]
+pub struct CorsPolicy {
+    any: bool,
+    origins: Vec<String>,
+}
+
+impl CorsPolicy {
+    /// Build the policy from `--cors-origin` values; `*` allows every origin.
+    pub fn new(allowed: &[String]) -> Self {
+        Self {
+            any: allowed.iter().any(|origin| origin == "*"),
+            origins: allowed
+                .iter()
+                .filter(|origin| *origin != "*")
+                .map(|origin| origin.trim_end_matches('/').to_ascii_lowercase())
+                .collect(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.any || !self.origins.is_empty()
+    }
+
+    fn allows(&self, origin: &str) -> bool {
+        self.any || self.origins.iter().any(|allowed| allowed.eq_ignore_ascii_case(origin))
+    }
+
+    /// Answer an `OPTIONS` preflight for an API path: 204 when the origin and method are allowed, 403 otherwise.
+    pub fn preflight(&self, origin: &str, method: &str) -> Response {
+        let method_allowed = ALLOWED_METHODS.split(", ").any(|allowed| allowed == method);
+        let status = if self.allows(origin) && method_allowed {
+            StatusCode::NO_CONTENT
+        } else {
+            StatusCode::FORBIDDEN
+        };
+        let mut response = Response::default();
+        *response.status_mut() = status;
+        response
+    }
+
+    /// Add the CORS headers to a response for `path` when the request's origin is allowed.
+    pub fn decorate(&self, path: &str, origin: Option<&str>, mut response: Response) -> Response {
+        let Some(origin) = origin else {
+            return response;
+        };
+        if !path.starts_with("/api/") || !self.allows(origin) {
+            return response;
+        }
+        let Ok(origin) = HeaderValue::from_str(origin) else {
+            return response;
+        };
+
+        let headers = response.headers_mut();
+        if self.any {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+        } else {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+            headers.append(header::VARY, HeaderValue::from_static("Origin"));
+        }
+        headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, HeaderValue::from_static(ALLOWED_METHODS));
+        headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, HeaderValue::from_static(ALLOWED_HEADERS));
+        headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, HeaderValue::from_static("Age, ETag, X-State-Version, X-Total-Count"));
+        headers.insert(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from_static(PREFLIGHT_MAX_AGE));
+        response
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{config::ServerConfig, state::lock, tcp_server::BasketballServer, SharedState};
+use std::{
+    fmt, fs, io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{error, info_span, warn};
+
+/// One court of a venue: its ingest server and game state, served at `/court/<id>/`.
+#[derive(Clone)]
+pub struct Court {
+    id: String,
+    server: Arc<BasketballServer>,
+    teams_file: PathBuf,
+    match_file: PathBuf,
+    /// Why the court's input stopped, reported by `/healthz`.
+    failure: Arc<Mutex<Option<String>>>,
+}
+
+impl Court {
+    fn new(config: ServerConfig) -> Self {
+        Self {
+            id: config.court_id.clone().unwrap_or_else(|| "1".to_string()),
+            teams_file: config.teams_file.clone(),
+            match_file: config.match_file.clone(),
+            server: Arc::new(BasketballServer::new(config)),
+            failure: Arc::default(),
+        }
+    }
+
+    /// The court id (`--court-id`), its number when it has none.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn shared(&self) -> Arc<SharedState> {
+        self.server.shared()
+    }
+
+    /// Where `PUT /api/teams` saves this court's teams.
+    pub fn teams_file(&self) -> &Path {
+        &self.teams_file
+    }
+
+    /// Where `PUT /api/match` saves this court's match details.
+    pub fn match_file(&self) -> &Path {
+        &self.match_file
+    }
+
+    /// Whether the court has a game to show: a frame arrived, the operator set the state or
+    /// it was restored from `--state-file`.
+    pub fn has_data(&self) -> bool {
+        let shared = self.server.shared();
+        shared.state_age().is_some() || shared.manual_override() || shared.restored()
+    }
+
+    /// Why the court's input failed (its listener couldn't bind, ...); `None` while it runs.
+    pub fn failure(&self) -> Option<String> {
+        lock(&self.failure).clone()
+    }
+
+    /// Bind the court's TCP input ahead of [`run`](Self::run), see [`BasketballServer::bind`].
+    pub fn bind(&self) -> io::Result<SocketAddr> {
+        self.server.bind()
+    }
+
+    /// Run the court's ingest server, see [`BasketballServer::run`]; an error is kept as the
+    /// court's [`failure`](Self::failure).
+    pub fn run(&self) -> io::Result<()> {
+        let result = self.server.run();
+        if let Err(e) = &result {
+            *lock(&self.failure) = Some(e.to_string());
+        }
+        result
+    }
+}
+
+impl fmt::Debug for Court {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Court").field("id", &self.id).field("failure", &self.failure()).finish_non_exhaustive()
+    }
+}
+
+/// The courts one server runs: a [`Court`] per `[[court]]` section of the config file, or a
+/// single one from the top-level options.
+///
+/// Each court has its own input, state and outputs (logs, MQTT topics, webhooks...); one web
+/// server serves them all, the first one at `/` as well.
+#[derive(Debug, Clone)]
+pub struct Courts(Arc<[Court]>);
+
+impl Courts {
+    /// Set up the ingest server of every court of `config`; nothing runs yet.
+    pub fn new(config: &ServerConfig) -> Self {
+        if config.courts.is_empty() {
+            return Self(Arc::new([Court::new(config.clone())]));
+        }
+        let courts: Vec<Court> = config
+            .courts
+            .iter()
+            .map(|court| {
+                create_parent_dirs(court);
+                Court::new(court.clone())
+            })
+            .collect();
+        Self(courts.into())
+    }
+
+    /// The court served at `/`.
+    pub fn primary(&self) -> &Court {
+        &self.0[0]
+    }
+
+    pub fn find(&self, id: &str) -> Option<&Court> {
+        self.0.iter().find(|court| court.id == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Court> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Always `false`: there is at least one court.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Run every court's ingest server on a thread of its own. A court that fails (its port is
+    /// taken, ...) is logged and reported by `/healthz`; the others keep running.
+    pub fn start(&self) {
+        for court in self.iter() {
+            let court = court.clone();
+            thread::spawn(move || {
+                let _span = info_span!("court", id = %court.id).entered();
+                if let Err(e) = court.run() {
+                    error!("Court {} failed, the other courts keep running: {}", court.id, e);
+                }
+            });
+        }
+    }
+
+    /// Ask every court's ingest server to stop, see [`ShutdownHandle::shutdown`](crate::tcp_server::ShutdownHandle::shutdown).
+    pub fn shutdown(&self) {
+        for court in self.iter() {
+            court.server.shutdown_handle().shutdown();
+        }
+    }
+
+    /// Wait up to `timeout` for the console connections of every court to close; returns
+    /// whether they all did.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        self.iter().all(|court| court.server.shutdown_handle().wait(deadline.saturating_duration_since(Instant::now())))
+    }
+}
+
+/// Create the directories of the files a court writes, such as the `court-<id>` directories
+/// its files are put in by default.
+fn create_parent_dirs(config: &ServerConfig) {
+    let jsonl = config.log_jsonl.as_ref().map(PathBuf::from);
+    let files = [Some(&config.teams_file), Some(&config.match_file), config.state_file.as_ref(), config.log_csv.as_ref(), config.xml_file.as_ref(), jsonl.as_ref(), config.games_db.as_ref()];
+    let dirs = files.into_iter().flatten().filter_map(|file| file.parent()).chain(config.games_dir.as_deref());
+    for dir in dirs.filter(|dir| !dir.as_os_str().is_empty()) {
+        if let Err(e) = fs::create_dir_all(dir) {
+            warn!("Cannot create {}: {}", dir.display(), e);
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//! Running in the background without systemd: `--daemon`, `--pid-file`, `--log-file` and
+//! the `stop` subcommand.
+
+use crate::ServerConfig;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, Write},
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+/// PID file `stop` reads when not given `--pid-file`.
+pub const DEFAULT_PID_FILE: &str = "/run/scoreboard.pid";
+
+/// How long `stop` waits for the server to exit (its own shutdown waits up to 5 s twice).
+const DEFAULT_STOP_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// How often `stop` checks whether the server is gone.
+const STOP_POLL: Duration = Duration::from_millis(100);
+
+/// How often the log file's size is checked against `--log-max-size`.
+const ROTATE_CHECK: Duration = Duration::from_secs(5);
+
+/// Rotated log files kept next to the current one: `scoreboard.log.1` (newest) to `.5`.
+pub const LOG_FILES_KEPT: u32 = 5;
+
+/// A PID file locked for as long as the server runs, so a second instance refuses to start.
+///
+/// The lock (`flock`) goes with the process: once it exits, however it exits, the file is
+/// free again and a leftover PID in it is known to be stale.
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+    file: File,
+}
+
+impl PidFile {
+    /// Create and lock `path`. Fails with [`io::ErrorKind::WouldBlock`] naming the PID when
+    /// another process holds it.
+    pub fn acquire(path: &Path) -> io::Result<Self> {
+        // Not truncated before the lock is ours: it may hold the running instance's PID
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        if !try_lock(&file, libc::LOCK_EX)? {
+            let mut pid = String::new();
+            let _ = file.read_to_string(&mut pid);
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                format!("{} is locked: another instance is running (PID {})", path.display(), pid.trim()),
+            ));
+        }
+        Ok(Self { path: path.to_path_buf(), file })
+    }
+
+    /// Write `pid` into the file, replacing what was there.
+    pub fn write_pid(&mut self, pid: u32) -> io::Result<()> {
+        self.file.set_len(0)?;
+        self.file.rewind()?;
+        writeln!(self.file, "{}", pid)?;
+        self.file.sync_all()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Delete the file on a clean shutdown; the lock goes with it.
+    pub fn remove(self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("Failed to remove {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Lock `file` with `flock`, `false` when another open file holds a conflicting lock.
+fn try_lock(file: &File, operation: libc::c_int) -> io::Result<bool> {
+    // SAFETY: flock only takes the descriptor, which `file` keeps open
+    if unsafe { libc::flock(file.as_raw_fd(), operation | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+    let e = io::Error::last_os_error();
+    if e.kind() == io::ErrorKind::WouldBlock {
+        Ok(false)
+    } else {
+        Err(e)
+    }
+}
+
+/// PID of the server holding the PID file at `path`; `None` when no file is there or its
+/// process is gone (the file is left over from a crash).
+pub fn running_pid(path: &Path) -> io::Result<Option<u32>> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    // Closing the file drops the lock if we got it
+    if try_lock(&file, libc::LOCK_SH)? {
+        return Ok(None);
+    }
+    let mut pid = String::new();
+    file.read_to_string(&mut pid)?;
+    pid.trim()
+        .parse()
+        .map(Some)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("{} holds no PID: {:?}", path.display(), pid.trim())))
+}
+
+/// How a `stop` ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopOutcome {
+    /// No server holds the PID file.
+    NotRunning,
+    /// The server with this PID was told to stop and exited.
+    Stopped(u32),
+    /// It was told to stop but still runs after the timeout.
+    StillRunning(u32),
+}
+
+/// Tell the server holding the PID file to stop with `terminate` (SIGTERM), then wait up to
+/// `timeout` for it to exit.
+pub fn stop(path: &Path, timeout: Duration, terminate: impl FnOnce(u32) -> io::Result<()>) -> io::Result<StopOutcome> {
+    let Some(pid) = running_pid(path)? else {
+        return Ok(StopOutcome::NotRunning);
+    };
+    terminate(pid)?;
+    let started = Instant::now();
+    while running_pid(path)? == Some(pid) {
+        if started.elapsed() >= timeout {
+            return Ok(StopOutcome::StillRunning(pid));
+        }
+        thread::sleep(STOP_POLL);
+    }
+    Ok(StopOutcome::Stopped(pid))
+}
+
+/// Send SIGTERM to `pid`, which shuts the server down like Ctrl+C.
+fn send_sigterm(pid: u32) -> io::Result<()> {
+    let pid = libc::pid_t::try_from(pid).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "PID out of range"))?;
+    // SAFETY: kill only takes numbers
+    if unsafe { libc::kill(pid, libc::SIGTERM) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// The arguments of `scoreboard-rust stop`.
+#[derive(Debug, Clone, clap::Args)]
+pub struct StopArgs {
+    /// The server's `--pid-file`.
+    #[arg(long, value_name = "PATH", default_value = DEFAULT_PID_FILE)]
+    pub pid_file: PathBuf,
+    /// How long to wait for the server to exit, in seconds.
+    #[arg(long, value_name = "SECS", default_value_t = DEFAULT_STOP_TIMEOUT.as_secs())]
+    pub timeout: u64,
+}
+
+/// Entry point of `scoreboard-rust stop [--pid-file <path>] [--timeout <secs>]`; returns the
+/// process exit code.
+pub fn run_command(args: &StopArgs) -> i32 {
+    let (path, timeout) = (&args.pid_file, Duration::from_secs(args.timeout));
+    match stop(path, timeout, send_sigterm) {
+        Ok(StopOutcome::NotRunning) => {
+            info!("Not running (no process holds {})", path.display());
+            // Same as a successful stop, as init scripts expect
+            0
+        }
+        Ok(StopOutcome::Stopped(pid)) => {
+            info!("Stopped (PID {})", pid);
+            0
+        }
+        Ok(StopOutcome::StillRunning(pid)) => {
+            error!("PID {} still running after {}s", pid, timeout.as_secs());
+            1
+        }
+        Err(e) => {
+            error!("Failed to stop the server of {}: {}", path.display(), e);
+            1
+        }
+    }
+}
+
+/// Shift the rotated logs up (`.4` to `.5`, ..., the current file to `.1`), dropping the
+/// oldest, so the current file can start again empty.
+pub fn rotate_files(path: &Path, kept: u32) -> io::Result<()> {
+    let numbered = |n: u32| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    };
+    for n in (1..kept).rev() {
+        match fs::rename(numbered(n), numbered(n + 1)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    fs::rename(path, numbered(1))
+}
+
+/// Point standard output and error at `file`: the log, and anything printed or a panic.
+fn redirect_output(file: &File) -> io::Result<()> {
+    for fd in [libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+        // SAFETY: dup2 only takes descriptors; `file` keeps its own open
+        if unsafe { libc::dup2(file.as_raw_fd(), fd) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn open_log(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Send the output to `path`, starting a new file whenever it grows past `max_size`.
+fn log_to_file(path: &Path, max_size: u64) -> io::Result<()> {
+    redirect_output(&open_log(path)?)?;
+    let path = path.to_path_buf();
+    thread::Builder::new().name("log-rotation".to_string()).spawn(move || loop {
+        thread::sleep(ROTATE_CHECK);
+        if fs::metadata(&path).map_or(true, |meta| meta.len() < max_size) {
+            continue;
+        }
+        match rotate_files(&path, LOG_FILES_KEPT).and_then(|()| open_log(&path)).and_then(|file| redirect_output(&file)) {
+            Ok(()) => info!("Log continued in a new {} ({} older files kept)", path.display(), LOG_FILES_KEPT),
+            Err(e) => warn!("Failed to rotate {}: {}", path.display(), e),
+        }
+    })?;
+    Ok(())
+}
+
+/// Detach from the terminal: fork twice, with a new session in between, so the server is
+/// neither a session leader nor a child of the shell. The parents exit at once.
+fn daemonize() -> io::Result<()> {
+    for step in 0..2 {
+        // SAFETY: no other thread runs yet, so the child is a full copy of the process
+        match unsafe { libc::fork() } {
+            -1 => return Err(io::Error::last_os_error()),
+            0 => {}
+            // SAFETY: _exit ends the parent without running anything of the child's
+            _ => unsafe { libc::_exit(0) },
+        }
+        // SAFETY: setsid only acts on the calling process
+        if step == 0 && unsafe { libc::setsid() } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    let null = OpenOptions::new().read(true).write(true).open("/dev/null")?;
+    // SAFETY: as in redirect_output
+    if unsafe { libc::dup2(null.as_raw_fd(), libc::STDIN_FILENO) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Lock the PID file, go to the background and send the output to the log file, as the
+/// configuration asks. Must run before any thread starts. Errors come before the fork, on
+/// the terminal, where they can: another instance holding the PID file, a log file that
+/// can't be opened.
+pub fn start(config: &ServerConfig) -> io::Result<Option<PidFile>> {
+    let mut pid_file = config.pid_file.as_deref().map(PidFile::acquire).transpose()?;
+    if let Some(path) = &config.log_file {
+        // Opened once here so a bad path is reported on the terminal
+        open_log(path)?;
+    }
+    if config.daemon {
+        info!("Going to the background");
+        daemonize()?;
+    }
+    if let Some(pid_file) = &mut pid_file {
+        pid_file.write_pid(std::process::id())?;
+    }
+    match &config.log_file {
+        Some(path) => log_to_file(path, config.log_max_size)?,
+        None if config.daemon => redirect_output(&OpenOptions::new().write(true).open("/dev/null")?)?,
+        None => {}
+    }
+    if config.daemon {
+        info!("Running in the background as PID {}", std::process::id());
+    }
+    Ok(pid_file)
+}
@@ -0,0 +1,250 @@
+use crate::{
+    events::{self, GameEvent},
+    GameState,
+};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Text},
+    widgets::{Block, Borders, Widget},
+};
+use std::{
+    collections::VecDeque,
+    io::{BufRead, BufReader},
+    net::TcpStream,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+/// Event lines kept for the event pane.
+const MAX_EVENTS: usize = 100;
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+/// Rows of the big digits.
+pub const BIG_ROWS: usize = 5;
+
+/// The big-digit glyph of `c`, three columns wide (one for `:` and `.`).
+fn glyph(c: char) -> [&'static str; BIG_ROWS] {
+    match c {
+        '0' => ["███", "█ █", "█ █", "█ █", "███"],
+        '1' => ["  █", "  █", "  █", "  █", "  █"],
+        '2' => ["███", "  █", "███", "█  ", "███"],
+        '3' => ["███", "  █", "███", "  █", "███"],
+        '4' => ["█ █", "█ █", "███", "  █", "  █"],
+        '5' => ["███", "█  ", "███", "  █", "███"],
+        '6' => ["███", "█  ", "███", "█ █", "███"],
+        '7' => ["███", "  █", "  █", "  █", "  █"],
+        '8' => ["███", "█ █", "███", "█ █", "███"],
+        '9' => ["███", "█ █", "███", "  █", "███"],
+        ':' => [" ", "█", " ", "█", " "],
+        '.' => [" ", " ", " ", " ", "█"],
+        '-' => ["   ", "   ", "███", "   ", "   "],
+        _ => ["   ", "   ", "   ", "   ", "   "],
+    }
+}
+
+/// `text` in big digits, five rows high; glyphs are one column apart.
+pub fn big_text(text: &str) -> [String; BIG_ROWS] {
+    let mut rows: [String; BIG_ROWS] = Default::default();
+    for (i, c) in text.chars().enumerate() {
+        for (row, part) in rows.iter_mut().zip(glyph(c)) {
+            if i > 0 {
+                row.push(' ');
+            }
+            row.push_str(part);
+        }
+    }
+    rows
+}
+
+/// Render `left` and `right` against the edges of `area` and `center` in the middle, moved
+/// right of `left` when that is too wide for it.
+fn columns(area: Rect, buf: &mut Buffer, left: Text, center: Text, right: Text) {
+    let slot = |x: u16, text: &Text| Rect::new(area.x.saturating_add(x), area.y, text.width() as u16, area.height).intersection(area);
+    let left_width = left.width() as u16;
+    let center_x = (area.width.saturating_sub(center.width() as u16) / 2).max(if left_width > 0 { left_width + 1 } else { 0 });
+    let right_x = area.width.saturating_sub(right.width() as u16);
+    let (left_area, center_area, right_area) = (slot(0, &left), slot(center_x, &center), slot(right_x, &right));
+    center.render(center_area, buf);
+    left.render(left_area, buf);
+    right.render(right_area, buf);
+}
+
+/// Big digits as a block of text in `style`.
+fn big(text: &str, style: Style) -> Text<'static> {
+    Text::from_iter(big_text(text.trim())).style(style)
+}
+
+/// What the terminal dashboard shows. Drawn as a ratatui widget (`&Dashboard`), so its layout
+/// is tested on a plain [`Buffer`] with no terminal I/O.
+#[derive(Debug, Clone)]
+pub struct Dashboard {
+    state: Option<GameState>,
+    /// Newest last.
+    events: VecDeque<String>,
+    show_events: bool,
+    link: String,
+}
+
+impl Default for Dashboard {
+    fn default() -> Self {
+        Self {
+            state: None,
+            events: VecDeque::new(),
+            show_events: true,
+            link: "waiting for data".to_string(),
+        }
+    }
+}
+
+impl Dashboard {
+    /// Show `state`, adding the events since the previous one to the event pane (clock
+    /// ticks excepted).
+    pub fn apply(&mut self, state: GameState) {
+        if let Some(previous) = &self.state {
+            for event in events::diff(previous, &state).iter().filter(|event| event.is_important()) {
+                self.push_event(&state, event);
+            }
+        }
+        self.state = Some(state);
+    }
+
+    fn push_event(&mut self, state: &GameState, event: &GameEvent) {
+        if self.events.len() == MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(format!("{:>7}  {}", state.time.trim(), event));
+    }
+
+    /// The link line's text, e.g. `console active`.
+    pub fn set_link(&mut self, link: impl Into<String>) {
+        self.link = link.into();
+    }
+
+    pub fn toggle_events(&mut self) {
+        self.show_events = !self.show_events;
+    }
+
+    /// Apply what a [`subscribe`] feed reported.
+    pub fn update(&mut self, update: Update) {
+        match update {
+            Update::State(state) => self.apply(*state),
+            Update::Link(link) => self.set_link(link),
+        }
+    }
+
+    fn render_state(&self, state: &GameState, area: Rect, buf: &mut Buffer) {
+        let [teams, _, digits, _, counts, link, pane] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(BIG_ROWS as u16),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Fill(1),
+        ])
+        .areas(area);
+
+        let team = |abbrev: &str, name: &str, fallback: &str| {
+            let label = if abbrev.is_empty() { name } else { abbrev };
+            Text::from(if label.is_empty() { fallback.to_string() } else { label.to_string() }).bold()
+        };
+        let home = team(&state.teams.home.abbrev, &state.teams.home.name, "HOME");
+        let away = team(&state.teams.away.abbrev, &state.teams.away.name, "AWAY");
+        columns(teams, buf, home, Text::from(state.period_name.trim()), away);
+
+        let clock = if state.display_clock.is_empty() { &state.time } else { &state.display_clock };
+        let score = Style::new().bold();
+        columns(digits, buf, big(&state.home_score, score), big(clock, Style::new().fg(Color::Yellow)), big(&state.away_score, score));
+
+        let team_line = |fouls: &str, timeouts: &str| Text::from(format!("Fouls {}  Time-outs {}", fouls.trim(), timeouts.trim()));
+        let possession = match state.possession.as_str() {
+            "home" => "< possession",
+            "away" => "possession >",
+            _ => "",
+        };
+        columns(
+            counts,
+            buf,
+            team_line(&state.home_fouls, &state.home_timeouts),
+            Text::from(possession).fg(Color::Cyan),
+            team_line(&state.away_fouls, &state.away_timeouts),
+        );
+
+        let shot_clock = match state.shot_clock.trim() {
+            "" | "-" => String::new(),
+            shot_clock => format!("Shot clock {}", shot_clock),
+        };
+        let mut link_line = Line::from(format!("Link: {}", self.link));
+        if state.stale {
+            link_line.push_span(" (stale)".red());
+        }
+        columns(link, buf, Text::from(link_line), Text::from(shot_clock), Text::from(state.game_state.trim()));
+
+        if self.show_events {
+            let block = Block::new().borders(Borders::TOP).border_style(Style::new().fg(Color::DarkGray)).title(" Events ");
+            let inner = block.inner(pane);
+            block.render(pane, buf);
+            // The newest events that fit, oldest at the top
+            let skip = self.events.len().saturating_sub(inner.height as usize);
+            Text::from_iter(self.events.iter().skip(skip).map(String::as_str)).render(inner, buf);
+        }
+    }
+}
+
+impl Widget for &Dashboard {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let [body, help] = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+        let pane = if self.show_events { "hide" } else { "show" };
+        Line::from(format!("q quit, e {} events", pane)).add_modifier(Modifier::DIM).render(help, buf);
+
+        match &self.state {
+            Some(state) => self.render_state(state, body, buf),
+            None => Text::from(vec![Line::from("No game state yet"), Line::from(format!("Link: {}", self.link))])
+                .centered()
+                .render(body, buf),
+        }
+    }
+}
+
+/// What the feed of a server elsewhere reports to its dashboard.
+#[derive(Debug, Clone)]
+pub enum Update {
+    /// A state the server published.
+    State(Box<GameState>),
+    /// The link line, e.g. `connected to host:port`.
+    Link(String),
+}
+
+/// Follow the states a server streams to its `--subscribers` clients at `address`, reconnecting
+/// when it goes away. The feed runs on its own thread until the receiver is dropped.
+pub fn subscribe(address: &str) -> Receiver<Update> {
+    let (tx, rx) = mpsc::channel();
+    let address = address.to_string();
+    thread::spawn(move || {
+        while follow(&address, &tx).is_ok() {
+            thread::sleep(RECONNECT_DELAY);
+        }
+    });
+    rx
+}
+
+/// One connection of [`subscribe`]; fails once nobody listens any more.
+fn follow(address: &str, tx: &Sender<Update>) -> Result<(), mpsc::SendError<Update>> {
+    let stream = match TcpStream::connect(address) {
+        Ok(stream) => stream,
+        Err(e) => return tx.send(Update::Link(format!("cannot connect to {}: {}, retrying", address, e))),
+    };
+    tx.send(Update::Link(format!("connected to {}", address)))?;
+    // Each line is a published state with its events; the events are derived again
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if let Ok(state) = serde_json::from_str::<GameState>(&line) {
+            tx.send(Update::State(Box::new(state)))?;
+        }
+    }
+    tx.send(Update::Link(format!("disconnected from {}, retrying", address)))
+}
@@ -0,0 +1,66 @@
+use crate::state::Update;
+use std::time::{Duration, Instant};
+
+/// Coalesces clock-only updates for consumers that don't need every tick.
+///
+/// An update is delivered immediately when it carries an important event (see
+/// [`GameEvent::is_important`](crate::events::GameEvent::is_important)) or when
+/// `min_interval` has elapsed since the last delivery. Otherwise it is held as
+/// pending, merged with later updates, and must be flushed once [`deadline`](Self::deadline)
+/// passes so the final state is always delivered.
+pub struct Debouncer {
+    min_interval: Duration,
+    last_flush: Option<Instant>,
+    pending: Option<Update>,
+}
+
+impl Debouncer {
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            last_flush: None,
+            pending: None,
+        }
+    }
+
+    /// Offer a new update. Returns the (possibly merged) update to deliver now, if any.
+    pub fn offer(&mut self, update: Update, now: Instant) -> Option<Update> {
+        let merged = match self.pending.take() {
+            Some(mut pending) => {
+                pending.events.extend(update.events);
+                pending.logged_events.extend(update.logged_events);
+                Update {
+                    events: pending.events,
+                    logged_events: pending.logged_events,
+                    summary: update.summary.or(pending.summary),
+                    ..update
+                }
+            }
+            None => update,
+        };
+
+        let due = self
+            .last_flush
+            .is_none_or(|last| now.duration_since(last) >= self.min_interval);
+        if due || merged.events.iter().any(|e| e.is_important()) {
+            self.last_flush = Some(now);
+            Some(merged)
+        } else {
+            self.pending = Some(merged);
+            None
+        }
+    }
+
+    /// When the pending update must be flushed, if there is one.
+    pub fn deadline(&self) -> Option<Instant> {
+        let last = self.last_flush?;
+        self.pending.as_ref().map(|_| last + self.min_interval)
+    }
+
+    /// Take the pending update for delivery.
+    pub fn flush(&mut self, now: Instant) -> Option<Update> {
+        let pending = self.pending.take()?;
+        self.last_flush = Some(now);
+        Some(pending)
+    }
+}
@@ -0,0 +1,434 @@
+use crate::{
+    clock::shot_clock_tenths,
+    courts::Court,
+    events::{ActiveTimeout, OverlayEvent},
+    history::HistoryEntry,
+    line_score::PeriodScore,
+    locale::Lang,
+    match_info::{Countdown, MatchInfo},
+    metrics::DurationSummary,
+    numeric_value,
+    rules::Bonus,
+    state::{LinkStatus, SharedState, LINK_TIMEOUT},
+    teams::Teams,
+    timezone::TimeZone,
+    GameState,
+};
+use serde::Serialize;
+use std::{collections::hash_map::DefaultHasher, hash::Hasher, time::Duration};
+
+/// Machine-readable game state for external tools (`GET /api/state`).
+///
+/// Counts are numbers (`null` while the console shows a blank), text fields are trimmed.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateDto {
+    pub home_score: Option<u32>,
+    pub away_score: Option<u32>,
+    pub clock: String,
+    /// `clock` counted down between frames with `--tick-clock`; equal to `clock` otherwise.
+    pub display_clock: String,
+    /// Server time (Unix milliseconds, see `GET /api/time`) at which the running clock showed
+    /// `clock`; `null` while the clock is stopped.
+    pub clock_valid_at_ms: Option<u64>,
+    /// `null` until the console sends a shot clock and while it is blanked (dead balls).
+    pub shot_clock: Option<ShotClockDto>,
+    pub period_name: String,
+    pub home_fouls: Option<u32>,
+    pub away_fouls: Option<u32>,
+    pub home_timeouts: Option<u32>,
+    pub away_timeouts: Option<u32>,
+    /// `"home"`, `"away"`, or `null` when unknown. Not sent by the console; set from the admin page.
+    pub possession: Option<String>,
+    /// `"bonus"`, `"doubleBonus"` or `null`: the away team's fouls give home free throws (`--rules`).
+    pub home_bonus: Option<Bonus>,
+    pub away_bonus: Option<Bonus>,
+    /// `"running"` or `"paused"`.
+    pub game_state: String,
+    pub teams: Teams,
+    /// Competition, round, venue, tip-off and officials (`PUT /api/match`).
+    pub match_info: MatchDto,
+    /// End of period and horn events, each until its `expiresAtMs`.
+    pub events: Vec<OverlayEvent>,
+    /// `{"side", "remainingSeconds", "endsAtMs"}` while a time-out is played, `null` otherwise.
+    pub active_timeout: Option<ActiveTimeout>,
+    /// `[{"period": "1 Quarter", "home": 18, "away": 22}, ...]`: points per finished period.
+    pub line_score: Vec<PeriodScore>,
+    /// `periodName` and `gameState` translated for display.
+    pub display: DisplayStrings,
+}
+
+/// The shot clock of a [`StateDto`], counted down between frames with `--tick-clock`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShotClockDto {
+    pub seconds: u32,
+    /// The tenths digit when the console shows tenths (`4.3`), `null` for whole seconds.
+    pub tenths: Option<u32>,
+    pub running: bool,
+}
+
+impl ShotClockDto {
+    fn from_state(state: &GameState) -> Option<Self> {
+        let shot_clock = if state.display_shot_clock.is_empty() { &state.shot_clock } else { &state.display_shot_clock };
+        let tenths = shot_clock_tenths(shot_clock)?;
+        Some(Self {
+            seconds: tenths / 10,
+            tenths: shot_clock.contains('.').then_some(tenths % 10),
+            running: state.shot_clock_running,
+        })
+    }
+}
+
+/// Match details with the tip-off in Unix milliseconds: part of every [`StateDto`], and the body
+/// of `GET /api/match` with the countdown.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchDto {
+    #[serde(flatten)]
+    pub info: MatchInfo,
+    /// `tipOff` in Unix milliseconds, `null` without one.
+    pub tip_off_ms: Option<u64>,
+    /// Time left to the tip-off on the server's clock; only in `GET /api/match`, and `null`
+    /// there without a tip-off or once it has passed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub countdown: Option<Option<Countdown>>,
+}
+
+impl MatchDto {
+    /// Without `now_ms` the countdown is left out: a state is served again long after it was
+    /// built, and the countdown would stand still.
+    pub fn new(info: &MatchInfo, now_ms: Option<u64>) -> Self {
+        Self {
+            info: info.clone(),
+            tip_off_ms: info.tip_off_ms(),
+            countdown: now_ms.map(|now_ms| info.countdown(now_ms)),
+        }
+    }
+}
+
+/// Display strings of a [`StateDto`] in the request's language (`?lang=`, `Accept-Language`, `--lang`).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisplayStrings {
+    pub lang: Lang,
+    pub period_name: String,
+    pub game_state: String,
+}
+
+/// With English display strings.
+impl From<&GameState> for StateDto {
+    fn from(state: &GameState) -> Self {
+        Self::localized(state, Lang::default())
+    }
+}
+
+impl StateDto {
+    pub fn localized(state: &GameState, lang: Lang) -> Self {
+        let text = |s: &str| s.trim().to_string();
+        Self {
+            home_score: numeric_value(&state.home_score),
+            away_score: numeric_value(&state.away_score),
+            clock: text(&state.time),
+            display_clock: if state.display_clock.is_empty() { text(&state.time) } else { text(&state.display_clock) },
+            clock_valid_at_ms: state.clock_valid_at_ms,
+            shot_clock: ShotClockDto::from_state(state),
+            period_name: text(&state.period_name),
+            home_fouls: numeric_value(&state.home_fouls),
+            away_fouls: numeric_value(&state.away_fouls),
+            home_timeouts: numeric_value(&state.home_timeouts),
+            away_timeouts: numeric_value(&state.away_timeouts),
+            possession: (!state.possession.is_empty()).then(|| state.possession.clone()),
+            home_bonus: state.home_bonus,
+            away_bonus: state.away_bonus,
+            game_state: text(&state.game_state),
+            teams: state.teams.clone(),
+            match_info: MatchDto::new(&state.match_info, None),
+            events: state.events.clone(),
+            active_timeout: state.active_timeout,
+            line_score: state.line_score.clone(),
+            display: DisplayStrings {
+                lang,
+                period_name: lang.period_name(&state.period_name),
+                game_state: lang.game_state(&state.game_state).to_string(),
+            },
+        }
+    }
+}
+
+/// Summary of the server metrics included in [`ApiState`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsSummary {
+    pub link: LinkStatus,
+    pub frames_received: u64,
+    pub frame_errors: u64,
+    pub duplicates_suppressed: u64,
+    pub heartbeats_received: u64,
+    pub auth_failures: u64,
+    pub web_auth_failures: u64,
+    pub peers_dropped_backpressure: u64,
+    pub handler_panics: u64,
+}
+
+/// Body of `GET /api/state`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiState {
+    /// `null` until the first frame is received, the operator sets the state or one is restored.
+    pub state: Option<StateDto>,
+    /// The input has gone away; `state` holds the last known values.
+    pub stale: bool,
+    /// `state` was set by the operator through `POST`/`PATCH /api/state`.
+    pub manual_override: bool,
+    /// `state` was restored from `--state-file` at startup; no frame has arrived since.
+    pub restored: bool,
+    /// Milliseconds since the last frame, `null` if none was received yet.
+    pub age_ms: Option<u64>,
+    /// When `state` was last published, as RFC 3339 in the server's zone (`--timezone`);
+    /// `null` before the first one.
+    pub last_update: Option<String>,
+    /// Version of `state`, for long polling (`?wait=<secs>&version=<n>`).
+    pub version: u64,
+    pub metrics: MetricsSummary,
+}
+
+impl ApiState {
+    /// Validator of the body, without the quotes: it changes with the state version, the override
+    /// flag, the display language and the state itself, not with `ageMs` or `metrics`, which are
+    /// only refreshed along. The state is hashed because some changes are stored without being
+    /// published (clock changes under `--dedupe ignore-clock`), so the version stays the same.
+    pub fn etag(&self, lang: Lang) -> String {
+        let source = if self.manual_override { "manual" } else { "console" };
+        let mut hasher = DefaultHasher::new();
+        hasher.write(&serde_json::to_vec(&self.state).unwrap_or_default());
+        format!("{}-{}-{}-{:016x}", self.version, source, lang.code(), hasher.finish())
+    }
+
+    pub fn from_shared(shared: &SharedState, lang: Lang) -> Self {
+        let (game, version) = shared.current_versioned();
+        let age = shared.state_age();
+        let manual_override = shared.manual_override();
+        let restored = shared.restored();
+        let metrics = shared.metrics.snapshot();
+        let peers = metrics.peers.values();
+
+        Self {
+            state: (age.is_some() || manual_override || restored).then(|| StateDto::localized(&game, lang)),
+            stale: game.stale,
+            manual_override,
+            restored,
+            age_ms: age.map(|age| age.as_millis() as u64),
+            last_update: shared.published_at_ms().map(|at| shared.timezone().rfc3339(at)),
+            version,
+            metrics: MetricsSummary {
+                link: shared.link_status(LINK_TIMEOUT),
+                frames_received: peers.clone().map(|peer| peer.frames).sum(),
+                frame_errors: peers.map(|peer| peer.errors).sum(),
+                duplicates_suppressed: metrics.duplicates_suppressed,
+                heartbeats_received: metrics.heartbeats_received,
+                auth_failures: metrics.auth_failures,
+                web_auth_failures: metrics.web_auth_failures,
+                peers_dropped_backpressure: metrics.peers_dropped_backpressure,
+                handler_panics: metrics.handler_panics,
+            },
+        }
+    }
+}
+
+/// Ingest side of [`Health`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestHealth {
+    pub link: LinkStatus,
+    /// Ingest connections currently open (TCP input only).
+    pub connected_peers: u64,
+    /// Milliseconds since the last frame, `null` if none was received yet.
+    pub last_frame_age_ms: Option<u64>,
+    pub frames_received: u64,
+    pub frame_errors: u64,
+    /// `frameErrors` as a fraction of all frames, 0 when none were received.
+    pub error_rate: f64,
+}
+
+/// Body of `GET /api/overlay`: overlay defaults the page URL may override.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverlaySettings {
+    /// `--swap-sides`, unless the URL has `?swap=`.
+    pub swap_sides: bool,
+    /// Scale from the browser's `scoreboard_prefs` cookie, unless the URL has `?scale=`.
+    pub scale: Option<f64>,
+    /// Names accepted by `/?theme=` and the settings widget.
+    pub themes: Vec<&'static str>,
+}
+
+/// Body of `GET /api/time`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerTime {
+    /// Unix milliseconds on the server's clock.
+    pub server_time_ms: u64,
+    /// Version of the current state, as in `/api/state`.
+    pub version: u64,
+    /// How far the server's clock is ahead of the page's, from the round trips in `?samples=`
+    /// (see [`estimate_clock_offset`](crate::clock::estimate_clock_offset)); `null` without any.
+    pub offset_ms: Option<f64>,
+}
+
+/// Body of `GET /healthz`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Health {
+    /// `false` (and HTTP 503) when the game clock is running but no frame arrived within the threshold.
+    pub healthy: bool,
+    pub version: &'static str,
+    pub uptime_secs: u64,
+    pub web_requests: u64,
+    /// Percentiles of the recent web request durations, `null` before the first one.
+    pub request_durations: Option<DurationSummary>,
+    pub ingest: IngestHealth,
+    /// The input has gone away and the overlay shows the last known values.
+    pub stale: bool,
+    /// `"running"` or `"paused"`.
+    pub game_state: String,
+    /// Every court when the server runs several, or the court of `/court/<id>/healthz`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub courts: Vec<CourtHealth>,
+}
+
+impl Health {
+    /// Check the pipeline; `stale_after` is how long a running game may go without frames.
+    pub fn from_shared(shared: &SharedState, stale_after: Duration) -> Self {
+        let game = shared.current();
+        let age = shared.state_age();
+        let metrics = shared.metrics.snapshot();
+        let frames_received: u64 = metrics.peers.values().map(|peer| peer.frames).sum();
+        let frame_errors: u64 = metrics.peers.values().map(|peer| peer.errors).sum();
+        let total = frames_received + frame_errors;
+
+        let running = game.game_state.trim() == "running";
+        let link_stale = age.is_none_or(|age| age > stale_after);
+        Self {
+            healthy: !(running && link_stale),
+            version: env!("CARGO_PKG_VERSION"),
+            uptime_secs: shared.uptime().as_secs(),
+            web_requests: metrics.web_requests,
+            request_durations: metrics.web_request_durations,
+            ingest: IngestHealth {
+                link: shared.link_status(LINK_TIMEOUT),
+                connected_peers: metrics.ingest_connected,
+                last_frame_age_ms: age.map(|age| age.as_millis() as u64),
+                frames_received,
+                frame_errors,
+                error_rate: if total == 0 { 0.0 } else { frame_errors as f64 / total as f64 },
+            },
+            stale: game.stale,
+            game_state: game.game_state.trim().to_string(),
+            courts: Vec::new(),
+        }
+    }
+}
+
+/// One court of [`Health`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CourtHealth {
+    pub id: String,
+    /// `false` when the court's input failed or its running game gets no frames.
+    pub healthy: bool,
+    /// Why the court's input failed (its port was taken, ...), `null` while it runs.
+    pub error: Option<String>,
+    pub ingest: IngestHealth,
+    pub stale: bool,
+    pub game_state: String,
+}
+
+impl CourtHealth {
+    pub fn new(court: &Court, stale_after: Duration) -> Self {
+        let health = Health::from_shared(&court.shared(), stale_after);
+        let error = court.failure();
+        Self {
+            id: court.id().to_string(),
+            healthy: health.healthy && error.is_none(),
+            error,
+            ingest: health.ingest,
+            stale: health.stale,
+            game_state: health.game_state,
+        }
+    }
+}
+
+/// One court of `GET /api/courts` and the `/courts` page.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CourtSummary {
+    pub id: String,
+    /// Where the court's overlay is, relative to the server root: `court/<id>/`.
+    pub path: String,
+    /// `false` until the court's first frame, or a state set by hand or restored.
+    pub has_data: bool,
+    /// Seconds since the court's state last changed, `null` before the first frame.
+    pub age_secs: Option<u64>,
+    pub home_score: String,
+    pub away_score: String,
+    pub period: String,
+    pub time: String,
+    /// As in the court's `/healthz`.
+    pub healthy: bool,
+}
+
+impl CourtSummary {
+    pub fn new(court: &Court, stale_after: Duration) -> Self {
+        let state = court.shared().current();
+        Self {
+            id: court.id().to_string(),
+            path: format!("court/{}/", court.id()),
+            has_data: court.has_data(),
+            age_secs: court.shared().state_age().map(|age| age.as_secs()),
+            home_score: state.home_score.trim().to_string(),
+            away_score: state.away_score.trim().to_string(),
+            period: state.period_name,
+            time: state.time,
+            healthy: CourtHealth::new(court, stale_after).healthy,
+        }
+    }
+}
+
+/// One entry of `GET /api/history`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntryDto {
+    /// When the state was published, in Unix milliseconds.
+    pub timestamp_ms: u64,
+    /// The same time as RFC 3339 in the server's zone (`--timezone`).
+    pub timestamp: String,
+    /// State version, as used for `/events` ids.
+    pub version: u64,
+    /// Game clock at that moment.
+    pub clock: String,
+    pub state: StateDto,
+}
+
+impl HistoryEntryDto {
+    pub fn new(entry: &HistoryEntry, zone: &TimeZone) -> Self {
+        let state = StateDto::from(&entry.update.state);
+        Self {
+            timestamp_ms: entry.timestamp_ms,
+            timestamp: zone.rfc3339(entry.timestamp_ms),
+            version: entry.update.version,
+            clock: state.clock.clone(),
+            state,
+        }
+    }
+}
+
+/// Body of `GET /api/history`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryResponse {
+    /// Oldest first.
+    pub entries: Vec<HistoryEntryDto>,
+    /// Older entries matching the query were left out because of `limit`.
+    pub truncated: bool,
+}
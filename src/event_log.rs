@@ -0,0 +1,106 @@
+use crate::events::GameEvent;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Events kept by the event log; older ones are dropped.
+pub const EVENT_LOG_CAPACITY: usize = 5000;
+
+/// A game event with its place in the feed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoggedEvent {
+    /// Increases by one per event, starting at 1 when the server starts.
+    pub id: u64,
+    /// When the state it belongs to was published, in Unix milliseconds.
+    pub timestamp_ms: u64,
+    /// Version of that state.
+    pub version: u64,
+    #[serde(flatten)]
+    pub event: GameEvent,
+}
+
+/// Part of the log, as answered by `GET /api/events`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventPage {
+    /// Oldest first.
+    pub events: Vec<LoggedEvent>,
+    /// More events follow the last one; ask again with its id.
+    pub truncated: bool,
+    /// Id of the oldest event still kept. Events between the requested id and this one were dropped.
+    pub first_id: Option<u64>,
+    /// Id of the last event logged, 0 before the first one.
+    pub last_id: u64,
+}
+
+/// The most recent game events, oldest first: score, foul, time-out, period, game state,
+/// possession and link changes, period ends and horns. Clock ticks are not logged.
+///
+/// Like [`History`](crate::history::History), storage is allocated up front and the
+/// oldest event is dropped once full. Ids are never reused, so after rotation the
+/// feed starts above 1 and a client that polls too rarely sees a gap in the ids.
+#[derive(Debug)]
+pub struct EventLog {
+    entries: VecDeque<LoggedEvent>,
+    capacity: usize,
+    last_id: u64,
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+            last_id: 0,
+        }
+    }
+
+    /// Log the events of a state published at `timestamp_ms`, returning them with their ids.
+    pub fn append(&mut self, events: &[GameEvent], version: u64, timestamp_ms: u64) -> Vec<LoggedEvent> {
+        let mut logged = Vec::new();
+        for event in events.iter().filter(|event| event.is_important()) {
+            self.last_id += 1;
+            let entry = LoggedEvent {
+                id: self.last_id,
+                timestamp_ms,
+                version,
+                event: event.clone(),
+            };
+            if self.entries.len() == self.capacity {
+                self.entries.pop_front();
+            }
+            self.entries.push_back(entry.clone());
+            logged.push(entry);
+        }
+        logged
+    }
+
+    /// The first `limit` events after id `since_id`.
+    pub fn since(&self, since_id: u64, limit: usize) -> EventPage {
+        let start = self.entries.partition_point(|entry| entry.id <= since_id);
+        let events: Vec<LoggedEvent> = self.entries.range(start..).take(limit).cloned().collect();
+        EventPage {
+            truncated: self.entries.len() - start > events.len(),
+            events,
+            first_id: self.entries.front().map(|entry| entry.id),
+            last_id: self.last_id,
+        }
+    }
+
+    /// Every event kept, oldest first.
+    pub fn entries(&self) -> impl Iterator<Item = &LoggedEvent> {
+        self.entries.iter()
+    }
+
+    /// Id of the last event logged, 0 before the first one.
+    pub fn last_id(&self) -> u64 {
+        self.last_id
+    }
+
+    /// Replace the log with `entries` saved earlier, new events continuing after `last_id`.
+    pub fn restore(&mut self, entries: Vec<LoggedEvent>, last_id: u64) {
+        let skip = entries.len().saturating_sub(self.capacity);
+        self.entries = entries.into_iter().skip(skip).collect();
+        self.last_id = last_id;
+    }
+}
@@ -0,0 +1,274 @@
+use crate::{numeric_value, GameState};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Team side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Home,
+    Away,
+}
+
+impl Side {
+    /// Name used in [`GameState::possession`].
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Side::Home => "home",
+            Side::Away => "away",
+        }
+    }
+}
+
+/// A change between two consecutive game states.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[non_exhaustive]
+pub enum GameEvent {
+    ScoreChange { side: Side, from: Option<u32>, to: Option<u32>, delta: i64 },
+    FoulChange { side: Side, from: Option<u32>, to: Option<u32> },
+    TimeoutChange { side: Side, from: Option<u32>, to: Option<u32> },
+    /// A team's time-outs went down: it called a time-out.
+    TimeoutCalled { side: Side },
+    /// The time-out ran out, the clock started again or the operator took it back.
+    TimeoutEnded { side: Side },
+    PeriodChange { from: String, to: String },
+    GameStateChange { from: String, to: String },
+    PossessionChange { from: String, to: String },
+    /// The game clock reached zero.
+    PeriodEnded { period: String },
+    /// The console sounded the horn.
+    Horn,
+    ClockChange { time: String },
+    ShotClockChange { shot_clock: String },
+    StaleChange { stale: bool },
+    /// The trackers of the game (game log, summary, line score) started again: `reason` is
+    /// `idle` after `--auto-reset`, `manual` for `POST /api/control/new-game`.
+    NewGame { reason: String },
+}
+
+impl GameEvent {
+    /// Every [`kind`](Self::kind), in declaration order.
+    pub const KINDS: &'static [&'static str] = &[
+        "ScoreChange",
+        "FoulChange",
+        "TimeoutChange",
+        "TimeoutCalled",
+        "TimeoutEnded",
+        "PeriodChange",
+        "GameStateChange",
+        "PossessionChange",
+        "PeriodEnded",
+        "Horn",
+        "ClockChange",
+        "ShotClockChange",
+        "StaleChange",
+        "NewGame",
+    ];
+
+    /// The event's `type` in its JSON, e.g. `PeriodEnded`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            GameEvent::ScoreChange { .. } => "ScoreChange",
+            GameEvent::FoulChange { .. } => "FoulChange",
+            GameEvent::TimeoutChange { .. } => "TimeoutChange",
+            GameEvent::TimeoutCalled { .. } => "TimeoutCalled",
+            GameEvent::TimeoutEnded { .. } => "TimeoutEnded",
+            GameEvent::PeriodChange { .. } => "PeriodChange",
+            GameEvent::GameStateChange { .. } => "GameStateChange",
+            GameEvent::PossessionChange { .. } => "PossessionChange",
+            GameEvent::PeriodEnded { .. } => "PeriodEnded",
+            GameEvent::Horn => "Horn",
+            GameEvent::ClockChange { .. } => "ClockChange",
+            GameEvent::ShotClockChange { .. } => "ShotClockChange",
+            GameEvent::StaleChange { .. } => "StaleChange",
+            GameEvent::NewGame { .. } => "NewGame",
+        }
+    }
+
+    /// Whether this event must reach consumers immediately.
+    ///
+    /// Clock ticks are the only updates that may be coalesced by a debounced consumer.
+    pub fn is_important(&self) -> bool {
+        !matches!(self, GameEvent::ClockChange { .. } | GameEvent::ShotClockChange { .. })
+    }
+}
+
+impl fmt::Display for GameEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = |v: &Option<u32>| v.map_or("-".to_string(), |v| v.to_string());
+        match self {
+            GameEvent::ScoreChange { side, from, to, delta } => {
+                write!(f, "{:?} score {} -> {} ({:+})", side, count(from), count(to), delta)
+            }
+            GameEvent::FoulChange { side, from, to } => {
+                write!(f, "{:?} fouls {} -> {}", side, count(from), count(to))
+            }
+            GameEvent::TimeoutChange { side, from, to } => {
+                write!(f, "{:?} time-outs {} -> {}", side, count(from), count(to))
+            }
+            GameEvent::TimeoutCalled { side } => write!(f, "{:?} time-out", side),
+            GameEvent::TimeoutEnded { side } => write!(f, "{:?} time-out over", side),
+            GameEvent::PeriodChange { from, to } => write!(f, "Period {:?} -> {:?}", from, to),
+            GameEvent::GameStateChange { from, to } => write!(f, "Game {} -> {}", from, to),
+            GameEvent::PossessionChange { from, to } => write!(f, "Possession {:?} -> {:?}", from, to),
+            GameEvent::PeriodEnded { period } => write!(f, "End of period {:?}", period),
+            GameEvent::Horn => write!(f, "Horn"),
+            GameEvent::ClockChange { time } => write!(f, "Clock {}", time),
+            GameEvent::ShotClockChange { shot_clock } => write!(f, "Shot clock {}", shot_clock),
+            GameEvent::StaleChange { stale } => write!(f, "Input stale: {}", stale),
+            GameEvent::NewGame { reason } => write!(f, "New game ({})", reason),
+        }
+    }
+}
+
+/// What an [`OverlayEvent`] announces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum OverlayEventKind {
+    PeriodEnded,
+    Horn,
+}
+
+/// A moment the overlay animates (end-of-period banner, horn flash).
+///
+/// Sent with every state until `expires_at_ms`, so clients that reconnect meanwhile still
+/// see it; they play each `id` once. Expired events are never sent again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverlayEvent {
+    pub id: u64,
+    #[serde(rename = "type")]
+    pub kind: OverlayEventKind,
+    /// Period that was being played, e.g. `3 Quarter`.
+    pub period: String,
+    /// Unix time in milliseconds.
+    pub expires_at_ms: u64,
+}
+
+impl OverlayEventKind {
+    /// The overlay event started by `event`, if any.
+    pub fn from_event(event: &GameEvent) -> Option<Self> {
+        match event {
+            GameEvent::PeriodEnded { .. } => Some(Self::PeriodEnded),
+            GameEvent::Horn => Some(Self::Horn),
+            _ => None,
+        }
+    }
+}
+
+/// A time-out being played, counted down by the server (`--rules`, `--timeout-length`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveTimeout {
+    pub side: Side,
+    /// Whole seconds left, rounded up; updated every second.
+    pub remaining_seconds: u32,
+    /// Unix time in milliseconds at which the time-out is over.
+    pub ends_at_ms: u64,
+}
+
+/// Game clock in tenths of a second, for both the `MM:SS` and `SS.t` formats.
+pub fn clock_tenths(time: &str) -> Option<u32> {
+    let digits = |s: &str| -> Option<u32> {
+        let s = s.trim();
+        if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        s.parse().ok()
+    };
+
+    if let Some((minutes, seconds)) = time.split_once(':') {
+        Some((digits(minutes)? * 60 + digits(seconds)?) * 10)
+    } else if let Some((seconds, tenths)) = time.split_once('.') {
+        Some(digits(seconds)? * 10 + digits(tenths)?)
+    } else {
+        None
+    }
+}
+
+/// Compute the events that turn `old` into `new`.
+pub fn diff(old: &GameState, new: &GameState) -> Vec<GameEvent> {
+    let mut events = Vec::new();
+
+    let sides = [
+        (Side::Home, &old.home_score, &new.home_score),
+        (Side::Away, &old.away_score, &new.away_score),
+    ];
+    for (side, from, to) in sides {
+        if from != to {
+            let (from, to) = (numeric_value(from), numeric_value(to));
+            let delta = match (from, to) {
+                (Some(from), Some(to)) => to as i64 - from as i64,
+                _ => 0,
+            };
+            events.push(GameEvent::ScoreChange { side, from, to, delta });
+        }
+    }
+
+    let fouls = [
+        (Side::Home, &old.home_fouls, &new.home_fouls),
+        (Side::Away, &old.away_fouls, &new.away_fouls),
+    ];
+    for (side, from, to) in fouls {
+        if from != to {
+            events.push(GameEvent::FoulChange { side, from: numeric_value(from), to: numeric_value(to) });
+        }
+    }
+
+    let timeouts = [
+        (Side::Home, &old.home_timeouts, &new.home_timeouts),
+        (Side::Away, &old.away_timeouts, &new.away_timeouts),
+    ];
+    for (side, from, to) in timeouts {
+        if from != to {
+            let (from, to) = (numeric_value(from), numeric_value(to));
+            events.push(GameEvent::TimeoutChange { side, from, to });
+            if matches!((from, to), (Some(from), Some(to)) if to < from) {
+                events.push(GameEvent::TimeoutCalled { side });
+            }
+        }
+    }
+
+    if old.period_name != new.period_name {
+        events.push(GameEvent::PeriodChange {
+            from: old.period_name.clone(),
+            to: new.period_name.clone(),
+        });
+    }
+
+    if old.game_state != new.game_state {
+        events.push(GameEvent::GameStateChange {
+            from: old.game_state.clone(),
+            to: new.game_state.clone(),
+        });
+    }
+
+    if old.possession != new.possession {
+        events.push(GameEvent::PossessionChange {
+            from: old.possession.clone(),
+            to: new.possession.clone(),
+        });
+    }
+
+    if old.time != new.time {
+        events.push(GameEvent::ClockChange { time: new.time.clone() });
+        if clock_tenths(&new.time) == Some(0) && clock_tenths(&old.time).is_some_and(|t| t > 0) {
+            events.push(GameEvent::PeriodEnded { period: new.period_name.clone() });
+        }
+    }
+
+    if new.horn && !old.horn {
+        events.push(GameEvent::Horn);
+    }
+
+    if old.shot_clock != new.shot_clock {
+        events.push(GameEvent::ShotClockChange { shot_clock: new.shot_clock.clone() });
+    }
+
+    if old.stale != new.stale {
+        events.push(GameEvent::StaleChange { stale: new.stale });
+    }
+
+    events
+}
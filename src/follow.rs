@@ -0,0 +1,110 @@
+use crate::config::FollowStart;
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    path::PathBuf,
+    thread,
+    time::Duration,
+};
+use tracing::info;
+
+/// How long to sleep at EOF before checking for new data.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Follows a growing file of raw frames, like `tail -f`.
+///
+/// Reads never return EOF: at the end of the file the follower sleeps and
+/// retries. If the file is truncated or replaced (rotation), it is reopened and
+/// read from the start.
+pub struct FileFollower {
+    path: PathBuf,
+    file: Option<File>,
+    offset: u64,
+    /// Where the next file opened is read from; only the first one starts elsewhere than 0.
+    start: FollowStart,
+    #[cfg(unix)]
+    inode: u64,
+}
+
+impl FileFollower {
+    /// Follow `path` from `start` (see [`FollowStart`]), e.g. its end to skip what a long
+    /// bridge log already holds.
+    pub fn with_offset(path: PathBuf, start: FollowStart) -> Self {
+        Self {
+            path,
+            file: None,
+            offset: 0,
+            start,
+            #[cfg(unix)]
+            inode: 0,
+        }
+    }
+
+    fn open(&mut self) -> io::Result<()> {
+        let mut file = File::open(&self.path)?;
+        let len = file.metadata()?.len();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            self.inode = file.metadata()?.ino();
+        }
+        let offset = match std::mem::take(&mut self.start) {
+            FollowStart::Offset(offset) => offset.min(len),
+            FollowStart::End => len,
+        };
+        file.seek(SeekFrom::Start(offset))?;
+        info!("Following {} from byte {}", self.path.display(), offset);
+        self.file = Some(file);
+        self.offset = offset;
+        Ok(())
+    }
+
+    // Whether the path no longer refers to the bytes we have been reading.
+    fn truncated_or_replaced(&self) -> bool {
+        let Ok(meta) = fs::metadata(&self.path) else {
+            // Removed mid-rotation; keep the old handle until a new file appears
+            return false;
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            if meta.ino() != self.inode {
+                return true;
+            }
+        }
+
+        meta.len() < self.offset
+    }
+}
+
+impl Read for FileFollower {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let Some(file) = self.file.as_mut() else {
+                match self.open() {
+                    Ok(()) => continue,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                        thread::sleep(POLL_INTERVAL);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                }
+            };
+
+            let n = file.read(buf)?;
+            if n > 0 {
+                self.offset += n as u64;
+                return Ok(n);
+            }
+
+            if self.truncated_or_replaced() {
+                info!("{} was truncated or rotated, reopening", self.path.display());
+                self.file = None;
+                continue;
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::metrics::{ForwardTargetStats, Metrics};
+use std::{
+    io::Write,
+    net::{SocketAddr, TcpStream},
+    sync::{
+        atomic::Ordering,
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+use tracing::{info, warn};
+
+/// Chunks buffered per target before new ones are dropped. What a target missed while down is
+/// dropped when it comes back, so this only absorbs short stalls.
+const QUEUE_CAPACITY: usize = 256;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+struct Target {
+    addr: SocketAddr,
+    tx: SyncSender<Arc<[u8]>>,
+    stats: Arc<ForwardTargetStats>,
+}
+
+/// Relays every raw chunk read from the console to a set of downstream TCP targets.
+///
+/// Each target has its own writer thread and bounded queue, so a slow or dead
+/// target only loses its own data and never blocks ingestion. A target that
+/// reconnects resumes with the live stream: the chunks queued while it was down
+/// would replay old scores, so they are dropped and counted.
+pub struct Forwarder {
+    targets: Vec<Target>,
+}
+
+impl Forwarder {
+    /// Spawn one writer thread per target. With no targets this is a no-op forwarder.
+    pub fn start(addrs: &[SocketAddr], metrics: &Metrics) -> Self {
+        let targets = addrs
+            .iter()
+            .map(|&addr| {
+                let (tx, rx) = mpsc::sync_channel(QUEUE_CAPACITY);
+                let stats = metrics.forward_target(addr);
+                let thread_stats = Arc::clone(&stats);
+                thread::spawn(move || run_target(addr, rx, thread_stats));
+                info!("Forwarding raw stream to {}", addr);
+                Target { addr, tx, stats }
+            })
+            .collect();
+
+        Self { targets }
+    }
+
+    /// Queue a copy of `chunk` for every target, dropping it for targets whose queue is full.
+    pub fn forward(&self, chunk: &[u8]) {
+        if self.targets.is_empty() {
+            return;
+        }
+
+        let chunk: Arc<[u8]> = Arc::from(chunk);
+        for target in &self.targets {
+            match target.tx.try_send(Arc::clone(&chunk)) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => target.stats.add_dropped(),
+                Err(TrySendError::Disconnected(_)) => {
+                    warn!("Forwarding thread for {} has stopped", target.addr);
+                    target.stats.add_dropped();
+                }
+            }
+        }
+    }
+}
+
+// Writer loop for a single target: connect with exponential backoff, then drain the queue.
+fn run_target(addr: SocketAddr, rx: Receiver<Arc<[u8]>>, stats: Arc<ForwardTargetStats>) {
+    let mut backoff = INITIAL_BACKOFF;
+    // Whether the target has been unreachable since the first connection attempt; every
+    // connection after that is a reconnect
+    let mut was_down = false;
+
+    loop {
+        let mut stream = match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+            Ok(stream) => {
+                info!("Connected to forwarding target {}", addr);
+                backoff = INITIAL_BACKOFF;
+                stream
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to connect to forwarding target {}: {} (retrying in {:?})",
+                    addr, e, backoff
+                );
+                was_down = true;
+                thread::sleep(backoff);
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                continue;
+            }
+        };
+
+        if was_down {
+            let stale = rx.try_iter().count();
+            if stale > 0 {
+                stats.chunks_dropped.fetch_add(stale as u64, Ordering::Relaxed);
+                info!("Dropped {} chunks queued for {} while it was down", stale, addr);
+            }
+        }
+
+        if let Err(e) = stream.set_write_timeout(Some(WRITE_TIMEOUT)) {
+            warn!("Failed to set write timeout for {}: {}", addr, e);
+        }
+        let _ = stream.set_nodelay(true);
+        stats.connected.store(true, Ordering::Relaxed);
+
+        loop {
+            // The forwarder has been dropped: nothing more will ever arrive.
+            let Ok(chunk) = rx.recv() else {
+                return;
+            };
+
+            if let Err(e) = stream.write_all(&chunk) {
+                stats.add_dropped();
+                warn!(
+                    "Lost connection to forwarding target {}: {} ({} bytes forwarded, {} chunks dropped so far)",
+                    addr,
+                    e,
+                    stats.bytes_forwarded.load(Ordering::Relaxed),
+                    stats.chunks_dropped.load(Ordering::Relaxed)
+                );
+                was_down = true;
+                break;
+            }
+            stats.add_forwarded(chunk.len());
+        }
+
+        stats.connected.store(false, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{parse_raw_data, ProtocolFrame, ETX, SOH};
+use tracing::warn;
+
+/// Upper bound for a single frame; anything longer without an ETX is treated as garbage.
+const MAX_FRAME_LEN: usize = 256;
+
+/// Splits an arbitrary byte stream into protocol frames.
+///
+/// Bytes may arrive in any chunking (a TCP read, a pipe read, a file tail), so
+/// frames split across reads or several frames in one read are both handled.
+/// Bytes before an SOH are skipped.
+///
+/// Heartbeat bytes (e.g. a bridge's `0x00` keepalive) arriving between frames
+/// are consumed and counted instead of entering the frame buffer.
+pub struct FrameDecoder {
+    buf: Vec<u8>,
+    lenient: bool,
+    heartbeat_bytes: Vec<u8>,
+    // Frame boundary tracking for heartbeat filtering
+    in_frame: bool,
+    expect_lrc: bool,
+    frame_pos: usize,
+    /// Times garbage was skipped to find a frame, since the last `take_resyncs`.
+    resyncs: u64,
+    /// Bytes skipped to find frames, heartbeats not included.
+    skipped: u64,
+    /// Complete frames returned so far, valid or not.
+    frames: u64,
+    /// Bytes removed from the buffer so far, and where the frame last returned started.
+    consumed: u64,
+    frame_offset: u64,
+}
+
+impl FrameDecoder {
+    pub fn new(lenient: bool, heartbeat_bytes: &[u8]) -> Self {
+        Self {
+            buf: Vec::with_capacity(MAX_FRAME_LEN),
+            lenient,
+            heartbeat_bytes: heartbeat_bytes.to_vec(),
+            in_frame: false,
+            expect_lrc: false,
+            frame_pos: 0,
+            resyncs: 0,
+            skipped: 0,
+            frames: 0,
+            consumed: 0,
+            frame_offset: 0,
+        }
+    }
+
+    /// Append newly received bytes. Returns the number of heartbeat bytes consumed.
+    pub fn push(&mut self, bytes: &[u8]) -> usize {
+        if self.heartbeat_bytes.is_empty() {
+            self.buf.extend_from_slice(bytes);
+            return 0;
+        }
+
+        let mut heartbeats = 0;
+        for &b in bytes {
+            if self.in_frame {
+                if self.expect_lrc || self.frame_pos > MAX_FRAME_LEN {
+                    // This byte is the LRC (or the frame was abandoned): the frame is over
+                    self.in_frame = false;
+                    self.expect_lrc = false;
+                } else if b == ETX && self.frame_pos >= 4 {
+                    self.expect_lrc = true;
+                }
+                self.frame_pos += 1;
+            } else if self.heartbeat_bytes.contains(&b) {
+                heartbeats += 1;
+                continue;
+            } else if b == SOH {
+                self.in_frame = true;
+                self.frame_pos = 1;
+            }
+            self.buf.push(b);
+        }
+        heartbeats
+    }
+
+    /// Extract the next complete frame, if any.
+    ///
+    /// Returns `Some(Err(..))` for a complete but invalid frame so the caller can
+    /// report it; decoding continues with the following bytes.
+    pub fn next_frame(&mut self) -> Option<Result<ProtocolFrame, String>> {
+        loop {
+            // Resynchronise on the next SOH
+            match self.buf.iter().position(|&b| b == SOH) {
+                Some(0) => {}
+                Some(start) => {
+                    warn!("Skipping {} bytes before SOH", start);
+                    self.resyncs += 1;
+                    self.skipped += start as u64;
+                    self.consumed += start as u64;
+                    self.buf.drain(..start);
+                }
+                None => {
+                    if !self.buf.is_empty() {
+                        warn!("Skipping {} bytes without SOH", self.buf.len());
+                        self.resyncs += 1;
+                        self.skipped += self.buf.len() as u64;
+                        self.consumed += self.buf.len() as u64;
+                        self.buf.clear();
+                    }
+                    return None;
+                }
+            }
+
+            // SOH, address, STX and CTRL precede the message; ETX is followed by the LRC
+            let etx = self.buf.iter().skip(4).position(|&b| b == ETX).map(|i| i + 4);
+            match etx {
+                Some(etx) if etx + 1 < self.buf.len() => {
+                    let frame: Vec<u8> = self.buf.drain(..=etx + 1).collect();
+                    self.frames += 1;
+                    self.frame_offset = self.consumed;
+                    self.consumed += frame.len() as u64;
+                    return Some(parse_raw_data(&frame, self.lenient));
+                }
+                Some(_) => return None,
+                None if self.buf.len() > MAX_FRAME_LEN => {
+                    // Drop this SOH and look for the next one
+                    warn!("Discarding {} bytes without ETX", self.buf.len());
+                    self.resyncs += 1;
+                    self.skipped += 1;
+                    self.consumed += 1;
+                    self.buf.drain(..1);
+                }
+                None => return None,
+            }
+        }
+    }
+
+    /// Sequence number of the frame last returned by [`next_frame`](Self::next_frame), from 1.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Offset in the input of the frame last returned, heartbeat bytes not counted.
+    pub fn frame_offset(&self) -> u64 {
+        self.frame_offset
+    }
+
+    /// Bytes skipped so far because they weren't part of a frame.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Bytes held back for a frame that isn't complete yet.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Number of resynchronisations since the previous call.
+    pub fn take_resyncs(&mut self) -> u64 {
+        std::mem::take(&mut self.resyncs)
+    }
+}
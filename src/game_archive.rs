@@ -0,0 +1,494 @@
+use crate::{
+    event_log::LoggedEvent,
+    events::GameEvent,
+    numeric_value,
+    state::{SharedState, Update},
+    summary::FINAL_PERIOD,
+    teams::{TeamInfo, Teams},
+    timestamp::UtcDateTime,
+    GameState,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::{error, info, warn};
+
+/// Directory `history` reads when `--dir` isn't given.
+const DEFAULT_GAMES_DIR: &str = "games";
+
+/// Default pause in the state updates after which the game being recorded is over.
+pub const DEFAULT_IDLE_GAP: Duration = Duration::from_secs(30 * 60);
+
+/// One line of a game file.
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum Record<'a> {
+    /// First line: the game started.
+    #[serde(rename_all = "camelCase")]
+    Game { id: u64, started_at_ms: u64, teams: &'a Teams },
+    /// A state published with an important event.
+    #[serde(rename_all = "camelCase")]
+    Snapshot { timestamp_ms: u64, version: u64, state: &'a GameState },
+    /// A game event of that state.
+    Event(&'a LoggedEvent),
+    /// Last line: the game is over.
+    #[serde(rename_all = "camelCase")]
+    End {
+        ended_at_ms: u64,
+        home_score: Option<u32>,
+        away_score: Option<u32>,
+        /// `final` when the operator set the `Final` period, `idle` after the idle gap, `reset`
+        /// when a new game was started (`--auto-reset`, `POST /api/control/new-game`).
+        reason: &'a str,
+    },
+}
+
+fn game_path(dir: &Path, id: u64) -> PathBuf {
+    dir.join(format!("game-{:06}.jsonl", id))
+}
+
+fn game_id(path: &Path) -> Option<u64> {
+    path.file_name()?.to_str()?.strip_prefix("game-")?.strip_suffix(".jsonl")?.parse().ok()
+}
+
+/// Where a [`GameRecorder`] writes the games it splits the published states into.
+pub trait GameStore {
+    /// The highest game number already stored, so new games are numbered on from it.
+    fn last_id(&self) -> io::Result<Option<u64>>;
+    /// Game `id` started with these teams.
+    fn start_game(&mut self, id: u64, started_at_ms: u64, teams: &Teams) -> io::Result<()>;
+    /// A state published during game `id` with an important event, and the events it logged.
+    fn snapshot(&mut self, id: u64, timestamp_ms: u64, version: u64, state: &GameState, events: &[LoggedEvent]) -> io::Result<()>;
+    /// Game `id` is over; the scores are `None` when the console never sent them.
+    fn end_game(&mut self, id: u64, ended_at_ms: u64, scores: (Option<u32>, Option<u32>), reason: &str) -> io::Result<()>;
+}
+
+struct OpenGame {
+    id: u64,
+    out: BufWriter<File>,
+}
+
+/// The games as one JSON Lines file each in a directory (`--games-dir`).
+pub struct GameFiles {
+    dir: PathBuf,
+    current: Option<OpenGame>,
+}
+
+impl GameFiles {
+    pub fn new(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(Self { dir: dir.to_path_buf(), current: None })
+    }
+
+    fn open(&mut self, id: u64) -> io::Result<&mut BufWriter<File>> {
+        match &mut self.current {
+            Some(game) if game.id == id => Ok(&mut game.out),
+            _ => Err(io::Error::other(format!("game {} is not being recorded", id))),
+        }
+    }
+}
+
+impl GameStore for GameFiles {
+    fn last_id(&self) -> io::Result<Option<u64>> {
+        Ok(GameArchive::new(&self.dir).ids()?.last().copied())
+    }
+
+    fn start_game(&mut self, id: u64, started_at_ms: u64, teams: &Teams) -> io::Result<()> {
+        let path = game_path(&self.dir, id);
+        let mut out = BufWriter::new(OpenOptions::new().create_new(true).write(true).open(&path)?);
+        write_record(&mut out, &Record::Game { id, started_at_ms, teams })?;
+        out.flush()?;
+        info!("Game {} started, recording to {}", id, path.display());
+        self.current = Some(OpenGame { id, out });
+        Ok(())
+    }
+
+    fn snapshot(&mut self, id: u64, timestamp_ms: u64, version: u64, state: &GameState, events: &[LoggedEvent]) -> io::Result<()> {
+        let out = self.open(id)?;
+        write_record(out, &Record::Snapshot { timestamp_ms, version, state })?;
+        for event in events {
+            write_record(out, &Record::Event(event))?;
+        }
+        out.flush()
+    }
+
+    fn end_game(&mut self, id: u64, ended_at_ms: u64, (home_score, away_score): (Option<u32>, Option<u32>), reason: &str) -> io::Result<()> {
+        let out = self.open(id)?;
+        write_record(out, &Record::End { ended_at_ms, home_score, away_score, reason })?;
+        out.flush()?;
+        out.get_ref().sync_data()?;
+        self.current = None;
+        Ok(())
+    }
+}
+
+/// Splits the published states into games and writes each game to a [`GameStore`].
+///
+/// A game starts with the first running clock while none is open and ends when the operator
+/// sets the `Final` period, when a new game is started or when no state was published for
+/// the idle gap; the end of an idle game is written when the next state arrives. Games are numbered on from the highest
+/// number in the store.
+pub struct GameRecorder<S: GameStore = GameFiles> {
+    store: S,
+    idle_gap: Duration,
+    next_id: u64,
+    /// The number of the game being recorded.
+    current: Option<u64>,
+    /// The previous update's state and publish time.
+    last: Option<(GameState, u64)>,
+}
+
+impl GameRecorder {
+    pub fn new(dir: &Path, idle_gap: Duration) -> io::Result<Self> {
+        GameRecorder::with_store(GameFiles::new(dir)?, idle_gap)
+    }
+}
+
+impl<S: GameStore> GameRecorder<S> {
+    pub fn with_store(store: S, idle_gap: Duration) -> io::Result<Self> {
+        let next_id = store.last_id()?.map_or(1, |id| id + 1);
+        Ok(Self {
+            store,
+            idle_gap,
+            next_id,
+            current: None,
+            last: None,
+        })
+    }
+
+    /// Record a published update.
+    pub fn record(&mut self, update: &Update) -> io::Result<()> {
+        let now = update.timestamp_ms;
+        let previous = self.last.replace((update.state.clone(), now));
+        if let Some((state, at)) = previous {
+            if self.current.is_some() && now.saturating_sub(at) > self.idle_gap.as_millis() as u64 {
+                self.end(&state, at, "idle")?;
+            } else if update.events.iter().any(|event| matches!(event, GameEvent::NewGame { .. })) {
+                self.end(&state, now, "reset")?;
+            }
+        }
+
+        let state = &update.state;
+        let over = state.period_name.trim() == FINAL_PERIOD;
+        if self.current.is_none() && state.game_state == "running" && !over {
+            self.store.start_game(self.next_id, now, &state.teams)?;
+            self.current = Some(self.next_id);
+            self.next_id += 1;
+        }
+        let Some(id) = self.current else {
+            return Ok(());
+        };
+
+        if update.events.iter().any(|event| event.is_important()) {
+            self.store.snapshot(id, now, update.version, state, &update.logged_events)?;
+        }
+        if over {
+            self.end(state, now, "final")?;
+        }
+        Ok(())
+    }
+
+    fn end(&mut self, state: &GameState, at: u64, reason: &str) -> io::Result<()> {
+        let Some(id) = self.current.take() else {
+            return Ok(());
+        };
+        let scores = (numeric_value(&state.home_score), numeric_value(&state.away_score));
+        self.store.end_game(id, at, scores, reason)?;
+        info!("Game {} over ({})", id, reason);
+        Ok(())
+    }
+}
+
+fn write_record(out: &mut impl Write, record: &Record) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, record)?;
+    out.write_all(b"\n")
+}
+
+/// Record every game into `dir` from a state listener (`--games-dir`).
+pub fn start(dir: &Path, idle_gap: Duration, shared: &SharedState) -> io::Result<()> {
+    let recorder = GameRecorder::new(dir, idle_gap)?;
+    info!("Recording games to {}", dir.display());
+    record(recorder, dir.display().to_string(), shared);
+    Ok(())
+}
+
+/// Feed every published update to `recorder` from a state listener; `name` says where the
+/// games go in the log.
+pub fn record<S: GameStore + Send + 'static>(mut recorder: GameRecorder<S>, name: String, shared: &SharedState) {
+    shared.add_listener(Duration::ZERO, move |update| {
+        if let Err(e) = recorder.record(update) {
+            error!("Failed to record the game in {}: {}", name, e);
+        }
+    });
+}
+
+/// A recorded game as listed by `history list`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameSummaryRow {
+    pub id: u64,
+    pub started_at_ms: u64,
+    /// `None` while the game is still being recorded (or the server stopped during it).
+    pub ended_at_ms: Option<u64>,
+    pub teams: Teams,
+    /// The final score, or the last one recorded.
+    pub home_score: Option<u32>,
+    pub away_score: Option<u32>,
+}
+
+/// A recorded game with all its snapshots and events, as printed by `history show`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedGame {
+    #[serde(flatten)]
+    pub summary: GameSummaryRow,
+    pub end_reason: Option<String>,
+    /// States published with an important event, oldest first.
+    pub snapshots: Vec<Value>,
+    /// The game events, oldest first, as on `/api/events`.
+    pub events: Vec<Value>,
+}
+
+/// The games recorded in a directory by [`GameRecorder`].
+pub struct GameArchive {
+    dir: PathBuf,
+}
+
+impl GameArchive {
+    pub fn new(dir: &Path) -> Self {
+        Self { dir: dir.to_path_buf() }
+    }
+
+    /// Ids of the recorded games, in order.
+    fn ids(&self) -> io::Result<Vec<u64>> {
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            if let Some(id) = game_id(&entry?.path()) {
+                ids.push(id);
+            }
+        }
+        ids.sort_unstable();
+        Ok(ids)
+    }
+
+    /// Every recorded game, oldest first. Unreadable files are skipped with a warning.
+    pub fn list(&self) -> io::Result<Vec<GameSummaryRow>> {
+        let mut games = Vec::new();
+        for id in self.ids()? {
+            match self.game(id) {
+                Ok(Some(game)) => games.push(game.summary),
+                Ok(None) => {}
+                Err(e) => warn!("Skipping {}: {}", game_path(&self.dir, id).display(), e),
+            }
+        }
+        Ok(games)
+    }
+
+    /// The game numbered `id`, if it was recorded.
+    pub fn game(&self, id: u64) -> io::Result<Option<ArchivedGame>> {
+        let file = match File::open(game_path(&self.dir, id)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        let mut lines = BufReader::new(file).lines();
+        let header: Value = serde_json::from_str(&lines.next().ok_or_else(|| invalid("empty game file"))??)?;
+        if header["kind"] != "game" {
+            return Err(invalid("the first line is not the game"));
+        }
+        let mut game = ArchivedGame {
+            summary: GameSummaryRow {
+                id,
+                started_at_ms: header["startedAtMs"].as_u64().unwrap_or_default(),
+                ended_at_ms: None,
+                teams: serde_json::from_value(header["teams"].clone()).unwrap_or_default(),
+                home_score: None,
+                away_score: None,
+            },
+            end_reason: None,
+            snapshots: Vec::new(),
+            events: Vec::new(),
+        };
+
+        let score = |value: &Value| value.as_u64().map(|score| score as u32);
+        for line in lines {
+            let line = line?;
+            // A line cut short by a crash ends the readable part of the game
+            let Ok(mut record) = serde_json::from_str::<Value>(&line) else {
+                break;
+            };
+            let kind = record["kind"].as_str().unwrap_or_default().to_string();
+            if let Some(object) = record.as_object_mut() {
+                object.remove("kind");
+            }
+            match kind.as_str() {
+                "snapshot" => {
+                    // Team names may have been entered after the tip-off
+                    if let Ok(teams) = serde_json::from_value(record["state"]["teams"].clone()) {
+                        game.summary.teams = teams;
+                    }
+                    game.summary.home_score = numeric_value(record["state"]["home_score"].as_str().unwrap_or_default());
+                    game.summary.away_score = numeric_value(record["state"]["away_score"].as_str().unwrap_or_default());
+                    game.snapshots.push(record);
+                }
+                "event" => game.events.push(record),
+                "end" => {
+                    game.summary.ended_at_ms = record["endedAtMs"].as_u64();
+                    game.summary.home_score = score(&record["homeScore"]);
+                    game.summary.away_score = score(&record["awayScore"]);
+                    game.end_reason = record["reason"].as_str().map(str::to_string);
+                }
+                _ => {}
+            }
+        }
+        Ok(Some(game))
+    }
+}
+
+impl GameSummaryRow {
+    /// `#3  2026-03-14 19:05 UTC  CAB 80 - 74 BEN  (final)`
+    pub fn line(&self) -> String {
+        let team = |info: &TeamInfo, fallback: &str| {
+            [&info.abbrev, &info.name].into_iter().find(|name| !name.is_empty()).cloned().unwrap_or_else(|| fallback.to_string())
+        };
+        let score = |score: Option<u32>| score.map_or("-".to_string(), |score| score.to_string());
+        let started = UtcDateTime::from_unix_millis(self.started_at_ms).spreadsheet();
+        format!(
+            "#{}  {} UTC  {} {} - {} {}  ({})",
+            self.id,
+            &started[..16],
+            team(&self.teams.home, "HOME"),
+            score(self.home_score),
+            score(self.away_score),
+            team(&self.teams.away, "AWAY"),
+            if self.ended_at_ms.is_some() { "over" } else { "open" }
+        )
+    }
+}
+
+/// The commands of `scoreboard-rust history`.
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum HistoryCommand {
+    /// List the recorded games.
+    List {
+        /// The server's `--games-dir`.
+        #[arg(long, value_name = "DIR", default_value = DEFAULT_GAMES_DIR)]
+        dir: PathBuf,
+        /// The server's `--games-db` instead (sqlite feature).
+        #[arg(long, value_name = "FILE", conflicts_with = "dir")]
+        db: Option<PathBuf>,
+        /// Print them as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print a recorded game as JSON.
+    Show {
+        /// Its number, as listed (`12` or `#12`).
+        #[arg(value_parser = game_number)]
+        id: u64,
+        /// The server's `--games-dir`.
+        #[arg(long, value_name = "DIR", default_value = DEFAULT_GAMES_DIR)]
+        dir: PathBuf,
+        /// The server's `--games-db` instead (sqlite feature).
+        #[arg(long, value_name = "FILE", conflicts_with = "dir")]
+        db: Option<PathBuf>,
+    },
+}
+
+/// Where `history` reads the games from.
+enum History {
+    Files(GameArchive),
+    #[cfg(feature = "sqlite")]
+    Db(crate::game_db::GameDb),
+}
+
+impl History {
+    fn open(dir: &Path, db: Option<&Path>) -> io::Result<Self> {
+        match db {
+            None => Ok(Self::Files(GameArchive::new(dir))),
+            #[cfg(feature = "sqlite")]
+            Some(path) => crate::game_db::GameDb::open_read_only(path).map(Self::Db),
+            #[cfg(not(feature = "sqlite"))]
+            Some(_) => Err(io::Error::other("reading a games database requires a build with SQLite support (cargo build --features sqlite)")),
+        }
+    }
+
+    fn list(&self) -> io::Result<Vec<GameSummaryRow>> {
+        match self {
+            Self::Files(archive) => archive.list(),
+            #[cfg(feature = "sqlite")]
+            Self::Db(db) => db.list(),
+        }
+    }
+
+    fn game(&self, id: u64) -> io::Result<Option<ArchivedGame>> {
+        match self {
+            Self::Files(archive) => archive.game(id),
+            #[cfg(feature = "sqlite")]
+            Self::Db(db) => db.game(id),
+        }
+    }
+}
+
+fn game_number(value: &str) -> Result<u64, String> {
+    value.trim_start_matches('#').parse().map_err(|_| "expected a game number".to_string())
+}
+
+/// Entry point of `scoreboard-rust history`; returns the process exit code.
+///
+/// `history list [--dir games | --db games.sqlite]` lists the recorded games, `history show
+/// <id>` prints one as JSON.
+pub fn run_command(command: &HistoryCommand) -> i32 {
+    let (HistoryCommand::List { dir, db, .. } | HistoryCommand::Show { dir, db, .. }) = command;
+    let source = db.as_deref().unwrap_or(dir);
+    let archive = match History::open(dir, db.as_deref()) {
+        Ok(archive) => archive,
+        Err(e) => {
+            error!("Failed to open {}: {}", source.display(), e);
+            return 1;
+        }
+    };
+    let mut out = io::stdout().lock();
+    let result = match *command {
+        HistoryCommand::List { json, .. } => archive.list().and_then(|games| {
+            if json {
+                writeln!(out, "{}", serde_json::to_string_pretty(&games)?)?;
+            } else if games.is_empty() {
+                writeln!(out, "No games recorded in {}", source.display())?;
+            } else {
+                for game in &games {
+                    writeln!(out, "{}", game.line())?;
+                }
+            }
+            Ok(true)
+        }),
+        HistoryCommand::Show { id, .. } => archive.game(id).and_then(|game| match game {
+            Some(game) => {
+                writeln!(out, "{}", serde_json::to_string_pretty(&game)?)?;
+                Ok(true)
+            }
+            None => {
+                error!("No game {} in {}", id, source.display());
+                Ok(false)
+            }
+        }),
+    };
+
+    match result {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        // Piped into `head` or a pager that was closed
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => 0,
+        Err(e) => {
+            error!("Failed to read the games in {}: {}", source.display(), e);
+            1
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{
+    event_log::LoggedEvent,
+    game_archive::{ArchivedGame, GameRecorder, GameStore, GameSummaryRow},
+    numeric_value,
+    state::SharedState,
+    teams::{TeamInfo, Teams},
+    GameState,
+};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use serde_json::{json, Value};
+use std::{
+    io,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tracing::info;
+
+/// Tables of the games database. The JSON columns hold what `history show` prints; the others
+/// are there to query on.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS games (
+    id INTEGER PRIMARY KEY,
+    started_at_ms INTEGER NOT NULL,
+    ended_at_ms INTEGER,
+    home_team TEXT NOT NULL,
+    away_team TEXT NOT NULL,
+    teams TEXT NOT NULL,
+    home_score INTEGER,
+    away_score INTEGER,
+    end_reason TEXT
+);
+CREATE TABLE IF NOT EXISTS snapshots (
+    game_id INTEGER NOT NULL REFERENCES games (id),
+    timestamp_ms INTEGER NOT NULL,
+    version INTEGER NOT NULL,
+    period TEXT NOT NULL,
+    clock TEXT NOT NULL,
+    home_score INTEGER,
+    away_score INTEGER,
+    state TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS events (
+    game_id INTEGER NOT NULL REFERENCES games (id),
+    event_id INTEGER NOT NULL,
+    timestamp_ms INTEGER NOT NULL,
+    type TEXT NOT NULL,
+    event TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS snapshots_game ON snapshots (game_id);
+CREATE INDEX IF NOT EXISTS events_game ON events (game_id);
+";
+
+fn sql(e: rusqlite::Error) -> io::Error {
+    io::Error::other(e)
+}
+
+/// The recorded games in an SQLite database (`--games-db`, `sqlite` feature): one row per game
+/// with its teams and final score, and its snapshots and events in their own tables.
+///
+/// Clones share the connection, so a recorder and a reader can use the same in-memory database.
+#[derive(Clone)]
+pub struct GameDb {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl GameDb {
+    /// Open the database at `path`, creating it and its tables if needed.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Self::new(Connection::open(path).map_err(sql)?)
+    }
+
+    /// Open the database at `path` to read the games in it, as `history` and `aggregate` do.
+    pub fn open_read_only(path: &Path) -> io::Result<Self> {
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(sql)?;
+        Ok(Self { connection: Arc::new(Mutex::new(connection)) })
+    }
+
+    /// A database that lives as long as its clones.
+    pub fn open_in_memory() -> io::Result<Self> {
+        Self::new(Connection::open_in_memory().map_err(sql)?)
+    }
+
+    fn new(connection: Connection) -> io::Result<Self> {
+        connection.execute_batch(SCHEMA).map_err(sql)?;
+        Ok(Self { connection: Arc::new(Mutex::new(connection)) })
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Every recorded game, oldest first.
+    pub fn list(&self) -> io::Result<Vec<GameSummaryRow>> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare("SELECT id, started_at_ms, ended_at_ms, teams, home_score, away_score FROM games ORDER BY id")
+            .map_err(sql)?;
+        let rows = statement.query_map([], summary_row).map_err(sql)?;
+        rows.collect::<Result<_, _>>().map_err(sql)
+    }
+
+    /// The game numbered `id`, if it was recorded.
+    pub fn game(&self, id: u64) -> io::Result<Option<ArchivedGame>> {
+        let connection = self.connection();
+        let game = connection
+            .query_row(
+                "SELECT id, started_at_ms, ended_at_ms, teams, home_score, away_score, end_reason FROM games WHERE id = ?1",
+                [id],
+                |row| Ok((summary_row(row)?, row.get::<_, Option<String>>(6)?)),
+            )
+            .optional()
+            .map_err(sql)?;
+        let Some((summary, end_reason)) = game else {
+            return Ok(None);
+        };
+
+        let mut statement = connection
+            .prepare("SELECT timestamp_ms, version, state FROM snapshots WHERE game_id = ?1 ORDER BY rowid")
+            .map_err(sql)?;
+        let snapshots = statement
+            .query_map([id], |row| {
+                let state: String = row.get(2)?;
+                Ok(json!({
+                    "timestampMs": row.get::<_, u64>(0)?,
+                    "version": row.get::<_, u64>(1)?,
+                    "state": serde_json::from_str::<Value>(&state).unwrap_or_default(),
+                }))
+            })
+            .map_err(sql)?
+            .collect::<Result<_, _>>()
+            .map_err(sql)?;
+        let mut statement = connection.prepare("SELECT event FROM events WHERE game_id = ?1 ORDER BY rowid").map_err(sql)?;
+        let events = statement
+            .query_map([id], |row| Ok(serde_json::from_str::<Value>(&row.get::<_, String>(0)?).unwrap_or_default()))
+            .map_err(sql)?
+            .collect::<Result<_, _>>()
+            .map_err(sql)?;
+        Ok(Some(ArchivedGame { summary, end_reason, snapshots, events }))
+    }
+}
+
+fn summary_row(row: &rusqlite::Row) -> rusqlite::Result<GameSummaryRow> {
+    let teams: String = row.get(3)?;
+    Ok(GameSummaryRow {
+        id: row.get(0)?,
+        started_at_ms: row.get(1)?,
+        ended_at_ms: row.get(2)?,
+        teams: serde_json::from_str(&teams).unwrap_or_default(),
+        home_score: row.get(4)?,
+        away_score: row.get(5)?,
+    })
+}
+
+/// The names the teams go by in the `games` table: their abbreviations, else their names.
+fn team_names(teams: &Teams) -> (&str, &str) {
+    (team_name(&teams.home), team_name(&teams.away))
+}
+
+fn team_name(team: &TeamInfo) -> &str {
+    if team.abbrev.is_empty() {
+        &team.name
+    } else {
+        &team.abbrev
+    }
+}
+
+impl GameStore for GameDb {
+    fn last_id(&self) -> io::Result<Option<u64>> {
+        self.connection().query_row("SELECT MAX(id) FROM games", [], |row| row.get(0)).map_err(sql)
+    }
+
+    fn start_game(&mut self, id: u64, started_at_ms: u64, teams: &Teams) -> io::Result<()> {
+        let (home, away) = team_names(teams);
+        self.connection()
+            .execute(
+                "INSERT INTO games (id, started_at_ms, home_team, away_team, teams) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![id, started_at_ms, home, away, serde_json::to_string(teams)?],
+            )
+            .map_err(sql)?;
+        info!("Game {} started, recording to the games database", id);
+        Ok(())
+    }
+
+    fn snapshot(&mut self, id: u64, timestamp_ms: u64, version: u64, state: &GameState, events: &[LoggedEvent]) -> io::Result<()> {
+        let (home_score, away_score) = (numeric_value(&state.home_score), numeric_value(&state.away_score));
+        let (home, away) = team_names(&state.teams);
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(sql)?;
+        transaction
+            .execute(
+                "INSERT INTO snapshots (game_id, timestamp_ms, version, period, clock, home_score, away_score, state)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![id, timestamp_ms, version, state.period_name.trim(), state.time.trim(), home_score, away_score, serde_json::to_string(state)?],
+            )
+            .map_err(sql)?;
+        for event in events {
+            let value = serde_json::to_value(event)?;
+            transaction
+                .execute(
+                    "INSERT INTO events (game_id, event_id, timestamp_ms, type, event) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![id, event.id, event.timestamp_ms, value["type"].as_str().unwrap_or_default(), value.to_string()],
+                )
+                .map_err(sql)?;
+        }
+        // Team names may have been entered after the tip-off
+        transaction
+            .execute(
+                "UPDATE games SET home_team = ?2, away_team = ?3, teams = ?4, home_score = ?5, away_score = ?6 WHERE id = ?1",
+                params![id, home, away, serde_json::to_string(&state.teams)?, home_score, away_score],
+            )
+            .map_err(sql)?;
+        transaction.commit().map_err(sql)
+    }
+
+    fn end_game(&mut self, id: u64, ended_at_ms: u64, (home_score, away_score): (Option<u32>, Option<u32>), reason: &str) -> io::Result<()> {
+        self.connection()
+            .execute(
+                "UPDATE games SET ended_at_ms = ?2, home_score = ?3, away_score = ?4, end_reason = ?5 WHERE id = ?1",
+                params![id, ended_at_ms, home_score, away_score, reason],
+            )
+            .map_err(sql)?;
+        Ok(())
+    }
+}
+
+/// Record every game into the database at `path` from a state listener (`--games-db`).
+pub fn start(path: &Path, idle_gap: Duration, shared: &SharedState) -> io::Result<()> {
+    let recorder = GameRecorder::with_store(GameDb::open(path)?, idle_gap)?;
+    info!("Recording games to the database {}", path.display());
+    crate::game_archive::record(recorder, path.display().to_string(), shared);
+    Ok(())
+}
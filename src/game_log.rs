@@ -0,0 +1,210 @@
+use crate::{
+    events::GameEvent,
+    state::SharedState,
+    timezone::TimeZone,
+    GameState,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    fs::OpenOptions,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+use tracing::{error, info};
+
+/// Rows kept by the game log; older ones are dropped.
+pub const GAME_LOG_CAPACITY: usize = 10_000;
+
+/// Columns written by [`GameLog::write_csv`], in order.
+///
+/// Spreadsheets and scripts address the columns by position, so new ones only ever go at the end.
+pub const CSV_HEADER: [&str; 10] = [
+    "wall_clock",
+    "game_clock",
+    "period",
+    "home_score",
+    "away_score",
+    "home_fouls",
+    "away_fouls",
+    "home_timeouts",
+    "away_timeouts",
+    "event",
+];
+
+/// One state change of the game log: the state as published and what changed it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameLogRow {
+    /// When the state was published, in Unix milliseconds.
+    pub timestamp_ms: u64,
+    pub game_clock: String,
+    pub period: String,
+    /// Counts as shown by the console, without padding; empty while it blanks them.
+    pub home_score: String,
+    pub away_score: String,
+    pub home_fouls: String,
+    pub away_fouls: String,
+    pub home_timeouts: String,
+    pub away_timeouts: String,
+    /// The events that produced the state, e.g. `Home score 10 -> 13 (+3)`, separated by `; `.
+    pub event: String,
+}
+
+impl GameLogRow {
+    /// The row for `state`, published at `timestamp_ms` because of `events`.
+    ///
+    /// Clock ticks don't make a row: `None` unless one of `events` is important.
+    pub fn new(timestamp_ms: u64, state: &GameState, events: &[GameEvent]) -> Option<Self> {
+        let event = events
+            .iter()
+            .filter(|event| event.is_important())
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ");
+        if event.is_empty() {
+            return None;
+        }
+        let field = |value: &str| value.trim().to_string();
+        Some(Self {
+            timestamp_ms,
+            game_clock: field(&state.time),
+            period: field(&state.period_name),
+            home_score: field(&state.home_score),
+            away_score: field(&state.away_score),
+            home_fouls: field(&state.home_fouls),
+            away_fouls: field(&state.away_fouls),
+            home_timeouts: field(&state.home_timeouts),
+            away_timeouts: field(&state.away_timeouts),
+            event,
+        })
+    }
+
+    /// Write the row as a CSV record with the columns of [`CSV_HEADER`], the wall clock as
+    /// RFC 3339 in `zone`.
+    pub fn write_csv(&self, out: &mut impl Write, zone: &TimeZone) -> io::Result<()> {
+        let wall_clock = zone.rfc3339(self.timestamp_ms);
+        write_record(
+            out,
+            &[
+                &wall_clock,
+                &self.game_clock,
+                &self.period,
+                &self.home_score,
+                &self.away_score,
+                &self.home_fouls,
+                &self.away_fouls,
+                &self.home_timeouts,
+                &self.away_timeouts,
+                &self.event,
+            ],
+        )
+    }
+}
+
+/// The state changes of the game, oldest first, for the statisticians' spreadsheet.
+///
+/// Only states published with an important event are kept (see [`GameLogRow::new`]), so a
+/// whole game fits; once full, the oldest row is dropped.
+#[derive(Debug, Clone)]
+pub struct GameLog {
+    rows: VecDeque<GameLogRow>,
+    capacity: usize,
+}
+
+impl Default for GameLog {
+    fn default() -> Self {
+        Self::new(GAME_LOG_CAPACITY)
+    }
+}
+
+impl GameLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            rows: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Add the row of a published state, if it gets one. Returns whether it did.
+    pub fn record(&mut self, timestamp_ms: u64, state: &GameState, events: &[GameEvent]) -> bool {
+        let Some(row) = GameLogRow::new(timestamp_ms, state, events) else {
+            return false;
+        };
+        if self.rows.len() == self.capacity {
+            self.rows.pop_front();
+        }
+        self.rows.push_back(row);
+        true
+    }
+
+    /// Oldest first.
+    pub fn rows(&self) -> impl Iterator<Item = &GameLogRow> {
+        self.rows.iter()
+    }
+
+    /// Replace the rows with `rows` saved earlier, keeping the newest ones that fit.
+    pub(crate) fn restore(&mut self, rows: Vec<GameLogRow>) {
+        let skip = rows.len().saturating_sub(self.capacity);
+        self.rows = rows.into_iter().skip(skip).collect();
+    }
+
+    /// Write the header row and then every row as CSV (RFC 4180: CRLF line ends, fields
+    /// quoted when they hold a comma, quote or line break), times in `zone`.
+    pub fn write_csv(&self, out: &mut impl Write, zone: &TimeZone) -> io::Result<()> {
+        write_csv_header(out)?;
+        for row in &self.rows {
+            row.write_csv(out, zone)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write the [`CSV_HEADER`] record.
+pub fn write_csv_header(out: &mut impl Write) -> io::Result<()> {
+    write_record(out, &CSV_HEADER)
+}
+
+/// Write one CSV record, quoting the fields that need it.
+pub(crate) fn write_record(out: &mut impl Write, fields: &[&str]) -> io::Result<()> {
+    let line: Vec<Cow<str>> = fields.iter().map(|field| quote(field)).collect();
+    write!(out, "{}\r\n", line.join(","))
+}
+
+fn quote(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\r', '\n']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// Append a row to the CSV file at `path` for every state change (`--log-csv`).
+///
+/// The header is only written to a new or empty file, so restarting the server during a
+/// game keeps adding to the same sheet. Rows are flushed as they are written: each one is
+/// an important event, and a crash must not lose the last of them.
+pub fn start_file_log(path: &Path, shared: &SharedState) -> io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let new = file.metadata()?.len() == 0;
+    let mut out = BufWriter::new(file);
+    if new {
+        write_csv_header(&mut out)?;
+        out.flush()?;
+    }
+    info!("Appending the game log to {}", path.display());
+
+    let path = path.to_path_buf();
+    let zone = shared.timezone();
+    shared.add_listener(Duration::ZERO, move |update| {
+        let Some(row) = GameLogRow::new(update.timestamp_ms, &update.state, &update.events) else {
+            return;
+        };
+        if let Err(e) = row.write_csv(&mut out, &zone).and_then(|_| out.flush()) {
+            error!("Failed to write the game log to {}: {}", path.display(), e);
+        }
+    });
+    Ok(())
+}
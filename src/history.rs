@@ -0,0 +1,92 @@
+use crate::state::Update;
+use std::{collections::VecDeque, sync::Arc};
+
+/// A published state with the wall-clock time it was published.
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    /// Unix time in milliseconds.
+    pub timestamp_ms: u64,
+    pub update: Arc<Update>,
+}
+
+/// The most recent published states, oldest first.
+///
+/// Storage is allocated up front and the oldest entry is overwritten once full,
+/// so appending under the state lock never allocates.
+#[derive(Debug)]
+pub struct History {
+    entries: VecDeque<HistoryEntry>,
+    capacity: usize,
+}
+
+impl History {
+    /// A history keeping `capacity` entries; zero disables it.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, entry: HistoryEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// The entries selected by `filter`, oldest first.
+    ///
+    /// Only the returned entries are cloned; the rest of the buffer is just indexed.
+    /// Timestamps come from the wall clock and are assumed not to go backwards.
+    pub fn query(&self, filter: &HistoryFilter) -> HistoryPage {
+        let start = filter
+            .from_ms
+            .map_or(0, |from| self.entries.partition_point(|entry| entry.timestamp_ms < from));
+        let end = filter
+            .to_ms
+            .map_or(self.entries.len(), |to| self.entries.partition_point(|entry| entry.timestamp_ms <= to))
+            .max(start);
+
+        // Every `every`th entry of the window, starting with its oldest
+        let total = (end - start).div_ceil(filter.every);
+        let newest = total.saturating_sub(filter.offset);
+        let oldest = newest.saturating_sub(filter.limit);
+        let entries = (oldest..newest)
+            .map(|n| self.entries[start + n * filter.every].clone())
+            .collect();
+        HistoryPage {
+            entries,
+            total,
+            truncated: oldest > 0,
+        }
+    }
+}
+
+/// Which entries [`History::query`] returns.
+#[derive(Debug, Clone, Copy)]
+pub struct HistoryFilter {
+    /// Only entries published at or after this Unix time in milliseconds.
+    pub from_ms: Option<u64>,
+    /// Only entries published at or before this Unix time in milliseconds.
+    pub to_ms: Option<u64>,
+    /// Keep every `every`th matching entry; at least 1.
+    pub every: usize,
+    /// Skip this many of the newest selected entries.
+    pub offset: usize,
+    pub limit: usize,
+}
+
+/// Entries returned by [`History::query`].
+#[derive(Debug)]
+pub struct HistoryPage {
+    /// Oldest first.
+    pub entries: Vec<HistoryEntry>,
+    /// Entries selected by the filter's window and `every`, before `offset` and `limit`.
+    pub total: usize,
+    /// Older selected entries were left out because of `limit`.
+    pub truncated: bool,
+}
@@ -0,0 +1,139 @@
+use crate::{
+    config::HookConfig,
+    event_log::LoggedEvent,
+    numeric_value,
+    state::SharedState,
+    GameState,
+};
+use std::{
+    io::Write,
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+/// Hook commands running at once; events beyond it are skipped.
+const MAX_RUNNING: usize = 4;
+/// How often a running hook is checked for its exit.
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The shell running a hook's command line.
+fn shell(command: &str) -> Command {
+    #[cfg(windows)]
+    let mut shell = {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    };
+    #[cfg(not(windows))]
+    let mut shell = {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command);
+    shell
+}
+
+/// `SB_` variables describing the state the event produced.
+fn environment(event: &LoggedEvent, state: &GameState) -> Vec<(&'static str, String)> {
+    let count = |value: &str| numeric_value(value).map_or_else(String::new, |value| value.to_string());
+    vec![
+        ("SB_EVENT", event.event.kind().to_string()),
+        ("SB_EVENT_ID", event.id.to_string()),
+        ("SB_HOME_SCORE", count(&state.home_score)),
+        ("SB_AWAY_SCORE", count(&state.away_score)),
+        ("SB_HOME_FOULS", count(&state.home_fouls)),
+        ("SB_AWAY_FOULS", count(&state.away_fouls)),
+        ("SB_HOME_TIMEOUTS", count(&state.home_timeouts)),
+        ("SB_AWAY_TIMEOUTS", count(&state.away_timeouts)),
+        ("SB_CLOCK", state.time.trim().to_string()),
+        ("SB_PERIOD", state.period_name.trim().to_string()),
+        ("SB_GAME_STATE", state.game_state.clone()),
+    ]
+}
+
+/// Wait for `child` to exit, killing it after `timeout`, and log how it ended.
+fn wait(mut child: Child, command: &str, timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => {
+                info!("Hook {:?} finished", command);
+                return;
+            }
+            Ok(Some(status)) => {
+                warn!("Hook {:?} failed: {}", command, status);
+                return;
+            }
+            Ok(None) if Instant::now() >= deadline => {
+                warn!("Hook {:?} still running after {:?}, killing it", command, timeout);
+                let _ = child.kill();
+                let _ = child.wait();
+                return;
+            }
+            Ok(None) => thread::sleep(WAIT_POLL_INTERVAL),
+            Err(e) => {
+                error!("Failed to wait for hook {:?}: {}", command, e);
+                return;
+            }
+        }
+    }
+}
+
+/// Run the commands of `hooks` on the events they name (`--hook`).
+///
+/// Each command runs through the shell on a thread of its own, with the event's JSON (as in
+/// `/api/events`) on its standard input and `SB_` variables describing the resulting state:
+/// `SB_EVENT`, `SB_EVENT_ID`, `SB_HOME_SCORE`, `SB_AWAY_SCORE`, `SB_HOME_FOULS`,
+/// `SB_AWAY_FOULS`, `SB_HOME_TIMEOUTS`, `SB_AWAY_TIMEOUTS`, `SB_CLOCK`, `SB_PERIOD` and
+/// `SB_GAME_STATE`. At most four run at once, further events are skipped with a warning,
+/// and a command still running after `timeout` is killed.
+pub fn start(hooks: &[HookConfig], timeout: Duration, shared: &SharedState) {
+    for hook in hooks {
+        info!("Running {:?} on {} events", hook.command, hook.event);
+    }
+    let hooks = hooks.to_vec();
+    let running = Arc::new(AtomicUsize::new(0));
+    shared.add_listener(Duration::ZERO, move |update| {
+        for event in &update.logged_events {
+            for hook in hooks.iter().filter(|hook| hook.event == event.event.kind()) {
+                if running.fetch_add(1, Ordering::SeqCst) >= MAX_RUNNING {
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    warn!("{} hooks already running, skipping {:?} for event {}", MAX_RUNNING, hook.command, event.id);
+                    continue;
+                }
+
+                let spawned = shell(&hook.command)
+                    .envs(environment(event, &update.state))
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .spawn();
+                let mut child = match spawned {
+                    Ok(child) => child,
+                    Err(e) => {
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        error!("Failed to start hook {:?}: {}", hook.command, e);
+                        continue;
+                    }
+                };
+                let input = serde_json::to_vec(event).unwrap_or_default();
+                let command = hook.command.clone();
+                let running = Arc::clone(&running);
+                thread::spawn(move || {
+                    // A command that doesn't read its input closes the pipe early; that's fine
+                    if let Some(mut stdin) = child.stdin.take() {
+                        let _ = stdin.write_all(&input);
+                    }
+                    wait(child, &command, timeout);
+                    running.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        }
+    });
+}
@@ -0,0 +1,165 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+/// Parts of an `http[s]://host[:port]/path` URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpUrl {
+    /// `https://`: the request goes over TLS.
+    pub https: bool,
+    pub host: String,
+    pub port: u16,
+    /// Path and query, `/` when the URL has none.
+    pub path: String,
+}
+
+impl HttpUrl {
+    /// Parse an HTTP URL; `https://` needs a build with the `tls` feature.
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let (https, rest) = match (url.strip_prefix("http://"), url.strip_prefix("https://")) {
+            (Some(rest), _) => (false, rest),
+            (_, Some(_)) if !cfg!(feature = "tls") => {
+                return Err(format!("Invalid URL {}: https:// needs a build with TLS support (cargo build --features tls)", url))
+            }
+            (_, Some(rest)) => (true, rest),
+            _ => return Err(format!("Invalid URL {}: expected http:// or https://", url)),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| format!("Invalid port in URL {}", url))?),
+            None => (authority, if https { 443 } else { 80 }),
+        };
+        if host.is_empty() {
+            return Err(format!("Invalid URL {}: no host", url));
+        }
+        Ok(Self {
+            https,
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+/// Send a `POST` with `body` and return the response status code.
+///
+/// A small blocking HTTP/1.1 client for the server's outgoing notifications: one request
+/// per connection (`Connection: close`), the response body is not read. `headers` are
+/// extra `(name, value)` pairs. `https://` URLs are checked against the system's CA
+/// certificates, see [`tls::connect`].
+pub fn post(url: &HttpUrl, content_type: &str, headers: &[(&str, &str)], body: &[u8], timeout: Duration) -> io::Result<u16> {
+    let address = (url.host.as_str(), url.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} has no address", url.host)))?;
+    let stream = TcpStream::connect_timeout(&address, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let mut request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\nUser-Agent: scoreboard-rust/{}\r\n",
+        url.path,
+        url.host,
+        url.port,
+        content_type,
+        body.len(),
+        env!("CARGO_PKG_VERSION")
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    if url.https {
+        exchange(tls::connect(stream, &url.host)?, &request, body)
+    } else {
+        exchange(stream, &request, body)
+    }
+}
+
+/// Write the request and its body, then read the status code of the response.
+fn exchange(mut stream: impl Read + Write, request: &str, body: &[u8]) -> io::Result<u16> {
+    stream.write_all(request.as_bytes())?;
+    stream.write_all(body)?;
+    stream.flush()?;
+
+    let mut status_line = String::new();
+    BufReader::new(stream).read_line(&mut status_line)?;
+    status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid HTTP response: {:?}", status_line.trim())))
+}
+
+#[cfg(feature = "tls")]
+mod tls {
+    use std::{
+        env, io,
+        net::TcpStream,
+        path::Path,
+        sync::{Arc, OnceLock},
+    };
+    use tokio_rustls::rustls::{
+        self,
+        pki_types::{pem::PemObject, CertificateDer, ServerName},
+        ClientConnection, RootCertStore, StreamOwned,
+    };
+
+    /// Where Linux distributions, the BSDs and Homebrew keep the CA bundle.
+    const CA_BUNDLES: &[&str] = &[
+        "/etc/ssl/certs/ca-certificates.crt",
+        "/etc/pki/tls/certs/ca-bundle.crt",
+        "/etc/ssl/cert.pem",
+        "/usr/local/etc/openssl/cert.pem",
+        "/opt/homebrew/etc/openssl@3/cert.pem",
+    ];
+
+    /// The client configuration, built from the CA bundle once.
+    fn config() -> io::Result<Arc<rustls::ClientConfig>> {
+        static CONFIG: OnceLock<Result<Arc<rustls::ClientConfig>, String>> = OnceLock::new();
+        CONFIG.get_or_init(load).clone().map_err(io::Error::other)
+    }
+
+    fn load() -> Result<Arc<rustls::ClientConfig>, String> {
+        let bundle = env::var_os("SSL_CERT_FILE")
+            .map(Into::into)
+            .or_else(|| CA_BUNDLES.iter().map(Path::new).find(|path| path.is_file()).map(Path::to_path_buf))
+            .ok_or("no CA certificates found: set SSL_CERT_FILE to a PEM bundle")?;
+        let mut roots = RootCertStore::empty();
+        let certs = CertificateDer::pem_file_iter(&bundle).map_err(|e| format!("Invalid CA bundle {}: {}", bundle.display(), e))?;
+        // A bundle may hold certificates rustls can't use; the others are enough
+        let (added, _) = roots.add_parsable_certificates(certs.filter_map(Result::ok));
+        if added == 0 {
+            return Err(format!("Invalid CA bundle {}: no certificate found", bundle.display()));
+        }
+        let config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| e.to_string())?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Ok(Arc::new(config))
+    }
+
+    /// Start TLS on `stream` to `host`, checking its certificate against the CA bundle of
+    /// `SSL_CERT_FILE` or the system's.
+    pub fn connect(stream: TcpStream, host: &str) -> io::Result<StreamOwned<ClientConnection, TcpStream>> {
+        let name = ServerName::try_from(host.to_string()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let connection = ClientConnection::new(config()?, name).map_err(io::Error::other)?;
+        Ok(StreamOwned::new(connection, stream))
+    }
+}
+
+/// Never called: `https://` URLs are refused without the `tls` feature.
+#[cfg(not(feature = "tls"))]
+mod tls {
+    use std::{io, net::TcpStream};
+
+    pub fn connect(_stream: TcpStream, _host: &str) -> io::Result<TcpStream> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "built without TLS support"))
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{
+    event_log::LoggedEvent,
+    events::GameEvent,
+    state::{SharedState, Update},
+    timezone::TimeZone,
+    GameState,
+};
+use serde::Serialize;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::{error, info};
+
+/// One line of the JSON Lines log.
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum Line<'a> {
+    /// A published state.
+    State {
+        ts: &'a str,
+        version: u64,
+        state: &'a GameState,
+    },
+    /// A game event that produced it, with its `/api/events` id.
+    Event {
+        ts: &'a str,
+        #[serde(flatten)]
+        event: &'a LoggedEvent,
+    },
+}
+
+/// Check a path template: `%Y`, `%m`, `%d`, `%H` and `%%` are the only `%` sequences allowed.
+pub fn validate_template(template: &str) -> Result<(), String> {
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        if c == '%' && !matches!(chars.next(), Some('Y' | 'm' | 'd' | 'H' | '%')) {
+            return Err(format!("Invalid log path template {}: only %Y, %m, %d, %H and %% are supported", template));
+        }
+    }
+    Ok(())
+}
+
+/// The path of `template` at `timestamp_ms`, e.g. `scores-%Y%m%d.jsonl` -> `scores-20260314.jsonl`
+/// (the date in `zone`).
+pub fn expand_template(template: &str, timestamp_ms: u64, zone: &TimeZone) -> PathBuf {
+    let time = zone.date_time(timestamp_ms);
+    let mut path = String::with_capacity(template.len() + 8);
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            path.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => path.push_str(&format!("{:04}", time.year)),
+            Some('m') => path.push_str(&format!("{:02}", time.month)),
+            Some('d') => path.push_str(&format!("{:02}", time.day)),
+            Some('H') => path.push_str(&format!("{:02}", time.hour)),
+            Some(other) => path.push(other),
+            None => path.push('%'),
+        }
+    }
+    PathBuf::from(path)
+}
+
+/// `scores.jsonl` with part 2 -> `scores.2.jsonl`; part 0 is the path itself.
+fn part_path(path: &Path, part: u32) -> PathBuf {
+    if part == 0 {
+        return path.to_path_buf();
+    }
+    match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(extension)) => {
+            path.with_file_name(format!("{}.{}.{}", stem.to_string_lossy(), part, extension.to_string_lossy()))
+        }
+        _ => PathBuf::from(format!("{}.{}", path.display(), part)),
+    }
+}
+
+struct OpenLog {
+    /// The template's expansion; the file written is one of its parts.
+    base: PathBuf,
+    part: u32,
+    out: BufWriter<File>,
+    size: u64,
+}
+
+/// Appends lines to files named by a date template, starting a new file whenever the
+/// template's expansion changes (at midnight in its zone for `%d`, every hour for `%H`) and, with a
+/// size cap, when the next write would take the file over it (`scores-20260314.1.jsonl`,
+/// `.2`, ...).
+pub struct JsonlLog {
+    template: String,
+    max_size: Option<u64>,
+    zone: TimeZone,
+    current: Option<OpenLog>,
+}
+
+impl JsonlLog {
+    /// The template's dates are those of `zone`.
+    pub fn new(template: impl Into<String>, max_size: Option<u64>, zone: TimeZone) -> Self {
+        Self {
+            template: template.into(),
+            max_size,
+            zone,
+            current: None,
+        }
+    }
+
+    /// The file written last, if any.
+    pub fn path(&self) -> Option<PathBuf> {
+        self.current.as_ref().map(|log| part_path(&log.base, log.part))
+    }
+
+    /// Append `lines` (without their line ends) at `timestamp_ms` as one batch, in the same file.
+    ///
+    /// The batch is flushed to the file; with `sync` it is also synced to the disk.
+    pub fn append(&mut self, timestamp_ms: u64, lines: &[String], sync: bool) -> io::Result<()> {
+        let batch: usize = lines.iter().map(|line| line.len() + 1).sum();
+        let base = expand_template(&self.template, timestamp_ms, &self.zone);
+
+        let rollover = match &self.current {
+            None => Some(0),
+            Some(log) if log.base != base => Some(0),
+            Some(log) if log.size > 0 && self.max_size.is_some_and(|max| log.size + batch as u64 > max) => Some(log.part + 1),
+            Some(_) => None,
+        };
+        if let Some(part) = rollover {
+            if let Some(mut log) = self.current.take() {
+                log.out.flush()?;
+            }
+            self.current = Some(self.open(base, part, batch as u64)?);
+        }
+
+        let log = self.current.as_mut().expect("log file opened above");
+        for line in lines {
+            log.out.write_all(line.as_bytes())?;
+            log.out.write_all(b"\n")?;
+        }
+        log.size += batch as u64;
+        log.out.flush()?;
+        if sync {
+            log.out.get_ref().sync_data()?;
+        }
+        Ok(())
+    }
+
+    /// Open part `part` of `base`, or the first later part with room for `batch` bytes when
+    /// it is already full (after a restart).
+    fn open(&self, base: PathBuf, mut part: u32, batch: u64) -> io::Result<OpenLog> {
+        if let Some(parent) = base.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        loop {
+            let path = part_path(&base, part);
+            let size = fs::metadata(&path).map_or(0, |metadata| metadata.len());
+            if size > 0 && self.max_size.is_some_and(|max| size + batch > max) {
+                part += 1;
+                continue;
+            }
+            let file = OpenOptions::new().create(true).append(true).open(&path)?;
+            info!("Writing the JSON Lines log to {}", path.display());
+            return Ok(OpenLog {
+                base,
+                part,
+                out: BufWriter::new(file),
+                size,
+            });
+        }
+    }
+}
+
+/// The lines of an update: the state, then the logged events that produced it, stamped in `zone`.
+fn update_lines(update: &Update, zone: &TimeZone) -> Vec<String> {
+    let ts = zone.rfc3339(update.timestamp_ms);
+    let state = Line::State {
+        ts: &ts,
+        version: update.version,
+        state: &update.state,
+    };
+    std::iter::once(state)
+        .chain(update.logged_events.iter().map(|event| Line::Event { ts: &ts, event }))
+        .filter_map(|line| serde_json::to_string(&line).ok())
+        .collect()
+}
+
+/// Whether the update closes part of the game (end of a period, a new period such as `Final`),
+/// so its lines must be on the disk before anything else happens.
+fn is_transition(update: &Update) -> bool {
+    update
+        .events
+        .iter()
+        .any(|event| matches!(event, GameEvent::PeriodEnded { .. } | GameEvent::PeriodChange { .. }))
+}
+
+/// Append every published state and its events to the JSON Lines log (`--log-jsonl`).
+///
+/// Files are written on the listener's own thread, fed by a bounded queue: a stalled disk
+/// loses lines but never holds up ingest.
+pub fn start(template: &str, max_size: Option<u64>, shared: &SharedState) {
+    let zone = shared.timezone();
+    let mut log = JsonlLog::new(template, max_size, zone.clone());
+    let template = template.to_string();
+    let mut failing = false;
+    shared.add_listener(Duration::ZERO, move |update| {
+        match log.append(update.timestamp_ms, &update_lines(update, &zone), is_transition(update)) {
+            Ok(()) if failing => {
+                info!("JSON Lines log writable again");
+                failing = false;
+            }
+            Ok(()) => {}
+            // Logged once per failure streak, not once per update
+            Err(e) if !failing => {
+                error!("Failed to write the JSON Lines log {}: {}", template, e);
+                failing = true;
+            }
+            Err(_) => {}
+        }
+    });
+}
@@ -0,0 +1,118 @@
+use crate::config::{InputMode, ServerConfig};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use tracing::{info, warn};
+
+/// An address of one of this machine's network interfaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interface {
+    /// `eth0`, `en0`, `Ethernet 2`...
+    pub name: String,
+    pub ip: IpAddr,
+}
+
+impl Interface {
+    pub fn new(name: &str, ip: IpAddr) -> Self {
+        Self { name: name.to_string(), ip }
+    }
+}
+
+/// The addresses of this machine's network interfaces; none when they can't be listed.
+pub fn local_interfaces() -> Vec<Interface> {
+    match if_addrs::get_if_addrs() {
+        Ok(interfaces) => interfaces.into_iter().map(|interface| Interface { ip: interface.ip(), name: interface.name }).collect(),
+        Err(e) => {
+            warn!("Can't list the network interfaces: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// The addresses a listener bound to `bind` is reached at from other machines, each with the
+/// interface it belongs to, LAN addresses first and loopback last.
+///
+/// A listener on `0.0.0.0` is reached at every IPv4 address of `interfaces`, one on `::` at
+/// every address; link-local IPv6 addresses are left out, as a browser needs the zone to use
+/// them. A listener on a given address is only reached there.
+pub fn reachable_addresses(bind: SocketAddr, interfaces: &[Interface]) -> Vec<(SocketAddr, String)> {
+    let ip = bind.ip();
+    let mut addresses: Vec<(SocketAddr, String)> = interfaces
+        .iter()
+        .filter(|interface| match ip {
+            IpAddr::V4(ip) if ip.is_unspecified() => interface.ip.is_ipv4(),
+            IpAddr::V6(ip) if ip.is_unspecified() => !matches!(interface.ip, IpAddr::V6(v6) if v6.is_unicast_link_local()),
+            ip => interface.ip == ip,
+        })
+        .map(|interface| (SocketAddr::new(interface.ip, bind.port()), interface.name.clone()))
+        .collect();
+    if addresses.is_empty() && !ip.is_unspecified() {
+        addresses.push((bind, String::new()));
+    }
+    // Stable: interfaces keep their order within each group
+    addresses.sort_by_key(|(address, _)| (address.ip().is_loopback(), address.is_ipv6()));
+    addresses.dedup_by_key(|(address, _)| *address);
+    addresses
+}
+
+/// Log where the overlay and the console input are reached from the network, for pasting into
+/// OBS or a bridge's settings, and warn when they are open to it without authentication.
+pub fn log_reachable(config: &ServerConfig) {
+    let interfaces = local_interfaces();
+    let log = |what: &str, bind: SocketAddr, show: &dyn Fn(SocketAddr) -> String| {
+        for (address, interface) in reachable_addresses(bind, &interfaces) {
+            match interface.as_str() {
+                "" => info!("{}: {}", what, show(address)),
+                interface => info!("{}: {} ({})", what, show(address), interface),
+            }
+        }
+    };
+
+    if config.tls.as_ref().is_none_or(|tls| tls.serve_http) {
+        log("Overlay", config.web_address, &|address| format!("http://{}/", address));
+    }
+    if let Some(tls) = &config.tls {
+        log("Overlay", tls.address, &|address| format!("https://{}/", address));
+    }
+    if config.input == InputMode::Tcp {
+        // A host name is looked up as the listener binds it
+        if let Some(bind) = config.tcp_address.to_socket_addrs().ok().and_then(|mut addresses| addresses.next()) {
+            log("Console input", bind, &|address| address.to_string());
+        }
+    }
+
+    for warning in exposure_warnings(config) {
+        warn!("{}", warning);
+    }
+}
+
+/// What `config` opens to other machines without authentication: a warning for the console
+/// input without `--auth-token` and one for the read API without `--lock-read-api`, each when
+/// it listens beyond loopback. The default addresses bind all interfaces, so a plain `serve`
+/// gets both, `--public` or not.
+pub fn exposure_warnings(config: &ServerConfig) -> Vec<String> {
+    // A host name is looked up as the listener binds it
+    let lookup = |address: &str| address.to_socket_addrs().map(Iterator::collect).unwrap_or_else(|_| Vec::new());
+    let mut input: Vec<SocketAddr> = Vec::new();
+    if config.input == InputMode::Tcp {
+        input.extend(lookup(&config.tcp_address));
+    }
+    if let Some(udp) = &config.udp_address {
+        input.extend(lookup(udp));
+    }
+    let mut web = Vec::new();
+    if config.tls.as_ref().is_none_or(|tls| tls.serve_http) {
+        web.push(config.web_address);
+    }
+    if let Some(tls) = &config.tls {
+        web.push(tls.address);
+    }
+    let open = |binds: &[SocketAddr]| binds.iter().find(|bind| !bind.ip().is_loopback()).copied();
+
+    let mut warnings = Vec::new();
+    if let (None, Some(bind)) = (&config.auth_token, open(&input)) {
+        warnings.push(format!("The console input listens on {} without --auth-token: anyone on the network can send frames to the scoreboard", bind));
+    }
+    if let (false, Some(bind)) = (config.lock_read_api, open(&web)) {
+        warnings.push(format!("The web server listens on {} without --lock-read-api: anyone on the network can read the game state and history", bind));
+    }
+    warnings
+}
@@ -0,0 +1,136 @@
+//! Bodet basketball scoreboard bridge: reads the console's serial protocol (over TCP, a
+//! serial port, a pipe or a capture file) and serves the game as JSON and browser overlays.
+//!
+//! The `scoreboard-rust` binary is a thin command line over this crate. Applications can
+//! parse frames themselves with [`basketball_parser`], run the ingest side with
+//! [`BasketballServer`] and serve the overlays with [`web_server`]:
+//!
+//! ```no_run
+//! use scoreboard_rust::{web_server::{self, WebOptions}, BasketballServer, ServerConfig};
+//! use std::thread;
+//!
+//! let config = ServerConfig::default();
+//! let options = WebOptions::from_config(&config);
+//! let server = BasketballServer::new(config);
+//! let shared = server.shared();
+//! thread::spawn(move || server.run());
+//!
+//! tokio::runtime::Runtime::new()
+//!     .unwrap()
+//!     .block_on(web_server::start_web_server(shared, options));
+//! ```
+
+// The warp route tree is a deeply nested type; wrapping its future (access log) needs more room
+#![recursion_limit = "256"]
+
+mod access_log;
+mod assets;
+mod auth;
+/// The console protocol: frames, their checksum, and the game state they describe.
+pub mod basketball_parser;
+/// Session captures in `data_log/`: rolling, compression and retention (`--capture-*`).
+pub mod capture;
+/// The `scoreboard-rust` command line, which `src/main.rs` runs through [`run`].
+mod cli;
+mod clock;
+mod config;
+mod cors;
+mod courts;
+/// The terminal scoreboard drawn by `--tui` and `scoreboard-rust tui`, as a ratatui widget.
+pub mod dashboard;
+/// Running in the background without systemd (`--daemon`, `--pid-file`, `stop`).
+#[cfg(unix)]
+pub mod daemon;
+mod debounce;
+mod dto;
+mod event_log;
+mod events;
+mod follow;
+/// Games recorded to disk and the `history` subcommand of the binary.
+pub mod game_archive;
+/// Games recorded to an SQLite database (`--games-db`).
+#[cfg(feature = "sqlite")]
+pub mod game_db;
+mod game_log;
+mod forward;
+mod framing;
+mod history;
+mod hooks;
+mod http_client;
+mod jsonl_logger;
+mod line_score;
+/// The addresses the servers are reached at from the local network, logged at startup.
+pub mod lan;
+mod locale;
+/// Console and rolling file logs, as text or JSON, with the ingest spans.
+pub mod logging;
+mod manual;
+mod match_info;
+#[cfg(feature = "mdns")]
+mod mdns;
+mod metrics;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+/// Slack and Discord messages on game events (`--slack-webhook`, `--discord-webhook`).
+pub mod notify;
+/// OSC output for lighting and audio consoles (`--osc`).
+pub mod osc;
+mod page_config;
+mod persist;
+/// Named pipe input on Windows (`--listen pipe:<name>`) and the reader that reconnects it.
+pub mod pipe;
+mod prefs;
+/// Pushing the state to a remote URL (`--push-url`).
+pub mod push;
+mod raster;
+mod reload;
+/// Method and path matching for the web server's routes.
+pub mod router;
+mod rules;
+/// Windows service mode (`service` subcommand) and its control event state machine.
+pub mod service;
+mod state;
+/// StatsD metrics over UDP (`--statsd`).
+pub mod statsd;
+mod style;
+mod subscribers;
+mod summary;
+/// systemd readiness and watchdog notifications (`Type=notify`) for the binary.
+pub mod systemd;
+/// The ingest side: console input, acks and forwarding, feeding the shared state.
+pub mod tcp_server;
+mod teams;
+mod themes;
+mod timestamp;
+mod timezone;
+mod tls;
+mod uploads;
+/// The overlay pages, JSON API and push streams.
+pub mod web_server;
+/// XML output for legacy graphics systems (`--xml-file`, `/api/state.xml`).
+pub mod xml;
+
+pub use basketball_parser::{apply_frame, numeric_value, parse_raw_data, GameState, ProtocolFrame};
+pub use cli::run;
+pub use clock::{estimate_clock_offset, ClockSample, ClockTicker};
+pub(crate) use basketball_parser::{parse_valid_frame, ETX, SOH};
+pub use config::{CaptureConfig, FollowStart, HookConfig, InputMode, MqttConfig, NotifyConfig, NotifyTarget, PushConfig, ServerConfig, StatsdConfig, TlsConfig};
+pub use courts::{Court, Courts};
+pub use dto::{ShotClockDto, StateDto};
+pub use events::{ActiveTimeout, GameEvent, OverlayEvent, OverlayEventKind, Side};
+pub use game_log::{GameLog, GameLogRow};
+pub use http_client::HttpUrl;
+pub use jsonl_logger::JsonlLog;
+pub use line_score::PeriodScore;
+pub use locale::Lang;
+pub use logging::{LogFormat, LogRotation};
+pub use match_info::{Countdown, MatchInfo};
+pub use metrics::{DurationSummary, ForwardTargetSnapshot, LoopBeat, LoopState, Metrics, MetricsSnapshot, PeerSnapshot};
+pub use page_config::{forwarded_prefix, PageConfig};
+pub use reload::{LiveSettings, ReloadReport, Reloader};
+pub use rules::{Bonus, RuleSet};
+pub use state::{Dedupe, LinkStatus, OverrideMode, SharedState, StateReceiver, StatusReport, Update};
+pub use summary::{GameSummary, TeamTotals};
+pub use tcp_server::BasketballServer;
+pub use teams::{TeamInfo, Teams};
+pub use timezone::TimeZone;
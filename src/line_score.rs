@@ -0,0 +1,48 @@
+use crate::{numeric_value, GameState};
+use serde::{Deserialize, Serialize};
+
+/// Period that starts a new game: entering it clears the line score.
+pub(crate) const FIRST_PERIOD: &str = "1 Quarter";
+
+/// Points each team scored in one period, recorded when the period ends.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeriodScore {
+    /// Period name as sent by the console, e.g. `2 Quarter` or `Overtime`.
+    pub period: String,
+    /// `None` when the console showed a blank at either end of the period.
+    pub home: Option<u32>,
+    pub away: Option<u32>,
+}
+
+/// Quarters and overtime count in the line score; `Halftime` and `Final` don't.
+fn is_played(period: &str) -> bool {
+    period.ends_with(" Quarter") || period == "Overtime"
+}
+
+/// The line score after `old` became `new`, `line` being the one of `old`.
+///
+/// A period is closed when the console moves on to another one, with the points scored
+/// since the previous periods (the score of `new`, as the frame that changes the period may
+/// also carry the last basket). Going back to a period the operator already left replaces
+/// it rather than adding it twice.
+pub fn update(line: &[PeriodScore], old: &GameState, new: &GameState) -> Vec<PeriodScore> {
+    let (from, to) = (old.period_name.trim(), new.period_name.trim());
+    if from == to {
+        return line.to_vec();
+    }
+    if to == FIRST_PERIOD {
+        return Vec::new();
+    }
+
+    let mut line: Vec<PeriodScore> = line.iter().filter(|score| score.period != from && score.period != to).cloned().collect();
+    if is_played(from) {
+        let points = |total: &str, side: fn(&PeriodScore) -> Option<u32>| {
+            let earlier = line.iter().try_fold(0, |sum, score| Some(sum + side(score)?))?;
+            numeric_value(total)?.checked_sub(earlier)
+        };
+        let home = points(&new.home_score, |score| score.home);
+        let away = points(&new.away_score, |score| score.away);
+        line.push(PeriodScore { period: from.to_string(), home, away });
+    }
+    line
+}
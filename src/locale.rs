@@ -0,0 +1,124 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Period names the parser and the admin page produce, see [`Lang::period_name`].
+const PERIODS: [&str; 7] = ["1 Quarter", "2 Quarter", "3 Quarter", "4 Quarter", "Overtime", "Halftime", "Final"];
+/// Values of `game_state`.
+const GAME_STATES: [&str; 2] = ["running", "paused"];
+
+/// Language of the display strings (`?lang=`, `Accept-Language`, `--lang`).
+///
+/// The state itself always keeps the console's English names; only display strings are translated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Lang {
+    #[default]
+    En,
+    Pt,
+    Fr,
+}
+
+/// Everything an overlay page translates, as served by `GET /api/locale`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Strings {
+    pub lang: Lang,
+    /// Period name as sent by the server -> display name.
+    pub period_names: BTreeMap<&'static str, String>,
+    /// `running` / `paused` -> display name.
+    pub game_states: BTreeMap<&'static str, &'static str>,
+    /// Template labels by key (`fouls`, `timeouts`).
+    pub labels: BTreeMap<&'static str, &'static str>,
+}
+
+impl Lang {
+    /// Parse a language tag such as `pt`, `pt-PT` or `FR_ca`; only the primary subtag counts.
+    pub fn parse(tag: &str) -> Option<Self> {
+        let primary = tag.trim().split(['-', '_']).next().unwrap_or_default();
+        match primary.to_ascii_lowercase().as_str() {
+            "en" => Some(Self::En),
+            "pt" => Some(Self::Pt),
+            "fr" => Some(Self::Fr),
+            _ => None,
+        }
+    }
+
+    /// The language's tag, as accepted by [`parse`](Self::parse).
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::Pt => "pt",
+            Self::Fr => "fr",
+        }
+    }
+
+    /// The supported language the client prefers most in an `Accept-Language` header value.
+    pub fn from_accept_language(header: &str) -> Option<Self> {
+        let mut best: Option<(Self, f32)> = None;
+        for range in header.split(',') {
+            let mut parts = range.split(';').map(str::trim);
+            let Some(lang) = parts.next().and_then(Self::parse) else {
+                continue;
+            };
+            let quality = parts
+                .find_map(|param| param.strip_prefix("q="))
+                .map_or(1.0, |q| q.parse().unwrap_or(0.0));
+            if quality > 0.0 && best.is_none_or(|(_, best)| quality > best) {
+                best = Some((lang, quality));
+            }
+        }
+        best.map(|(lang, _)| lang)
+    }
+
+    /// Display name of a period; names it doesn't know (set by hand) are kept as they are.
+    pub fn period_name(self, period_name: &str) -> String {
+        let period_name = period_name.trim();
+        if let Some(quarter) = period_name.strip_suffix(" Quarter") {
+            return match self {
+                Self::En => period_name.to_string(),
+                Self::Pt => format!("{}º Período", quarter),
+                Self::Fr if quarter == "1" => "1er quart-temps".to_string(),
+                Self::Fr => format!("{}e quart-temps", quarter),
+            };
+        }
+        let translated = match (self, period_name) {
+            (Self::Pt, "Overtime") => "Prorrogação",
+            (Self::Pt, "Halftime") => "Intervalo",
+            (Self::Pt, "Final") => "Fim do jogo",
+            (Self::Fr, "Overtime") => "Prolongation",
+            (Self::Fr, "Halftime") => "Mi-temps",
+            (Self::Fr, "Final") => "Fin du match",
+            _ => period_name,
+        };
+        translated.to_string()
+    }
+
+    /// Display name of a `game_state` value.
+    pub fn game_state(self, game_state: &str) -> &str {
+        match (self, game_state.trim()) {
+            (Self::Pt, "running") => "em jogo",
+            (Self::Pt, "paused") => "parado",
+            (Self::Fr, "running") => "en cours",
+            (Self::Fr, "paused") => "arrêté",
+            (_, game_state) => game_state,
+        }
+    }
+
+    fn labels(self) -> [(&'static str, &'static str); 2] {
+        match self {
+            Self::En => [("fouls", "Fouls"), ("timeouts", "Timeouts")],
+            Self::Pt => [("fouls", "Faltas"), ("timeouts", "Descontos de tempo")],
+            Self::Fr => [("fouls", "Fautes"), ("timeouts", "Temps morts")],
+        }
+    }
+
+    pub fn strings(self) -> Strings {
+        Strings {
+            lang: self,
+            period_names: PERIODS.iter().map(|period| (*period, self.period_name(period))).collect(),
+            game_states: GAME_STATES.iter().map(|state| (*state, self.game_state(state))).collect(),
+            labels: self.labels().into_iter().collect(),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Where the log goes: the console (standard error) and, with `--log-dir`, files started
+//! anew every day or hour; as text or, with `--log-format json`, one JSON object per line.
+//!
+//! `RUST_LOG` picks the levels (`info` by default, `RUST_LOG=debug,access=warn`...). Events
+//! carry the spans they happen in: `connection{id=3 peer=10.0.0.7:50122}` around everything
+//! a console connection logs, `frame{seq=812}` around each of its frames at debug level.
+//! Lines are stamped in the `--timezone` zone.
+
+use crate::{state::unix_millis, timezone::TimeZone};
+use std::{
+    io::{self, IsTerminal},
+    path::Path,
+};
+use tracing::subscriber::DefaultGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{
+    fmt,
+    fmt::{format::Writer, time::FormatTime},
+    layer::SubscriberExt,
+    util::SubscriberInitExt,
+    EnvFilter, Layer,
+};
+
+/// Log files kept in `--log-dir`; older ones are deleted as new ones start.
+pub const LOG_FILES_KEPT: usize = 14;
+
+/// Names of the files in `--log-dir`: `scoreboard.2026-03-14.log`.
+const FILE_PREFIX: &str = "scoreboard";
+const FILE_SUFFIX: &str = "log";
+
+/// How log lines are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum LogFormat {
+    /// `2026-03-14T20:05:42.250+01:00  WARN connection{id=3 peer=10.0.0.7:50122}: scoreboard_rust::tcp_server: ...`
+    #[default]
+    Text,
+    /// One JSON object per line with the spans' fields, for Loki or another log store.
+    Json,
+}
+
+impl LogFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "text" => Some(Self::Text),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// When the next log file starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum LogRotation {
+    Hourly,
+    #[default]
+    Daily,
+    /// One file, `scoreboard.log`, for outside tools such as logrotate.
+    Never,
+}
+
+impl LogRotation {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "hourly" => Some(Self::Hourly),
+            "daily" => Some(Self::Daily),
+            "never" => Some(Self::Never),
+            _ => None,
+        }
+    }
+}
+
+/// Stamps log lines with the time in a zone, as RFC 3339 to the millisecond.
+#[derive(Clone)]
+struct ZoneTimer(TimeZone);
+
+impl FormatTime for ZoneTimer {
+    fn format_time(&self, w: &mut Writer<'_>) -> std::fmt::Result {
+        write!(w, "{}", self.0.rfc3339(unix_millis()))
+    }
+}
+
+fn filter() -> EnvFilter {
+    EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"))
+}
+
+/// A text console logger for this thread until the guard is dropped: for the server's
+/// startup, until its options say where the log goes.
+pub fn startup() -> DefaultGuard {
+    let console = fmt().with_env_filter(filter()).with_writer(io::stderr).with_ansi(io::stderr().is_terminal()).finish();
+    tracing::subscriber::set_default(console)
+}
+
+/// A text console logger at `filter` (`error`, `info,scoreboard_rust::framing=error`...) for
+/// this thread until the guard is dropped: for commands whose input would make the parser log
+/// far too much.
+pub fn quiet(filter: &str) -> DefaultGuard {
+    let console = fmt().with_env_filter(EnvFilter::new(filter)).with_writer(io::stderr).with_ansi(io::stderr().is_terminal()).finish();
+    tracing::subscriber::set_default(console)
+}
+
+/// Log text to the console, for the commands other than the server.
+pub fn init_console() {
+    let _ = init(LogFormat::Text, None, LogRotation::Daily, &TimeZone::system());
+}
+
+/// Set up the process-wide log: the console, plus files in `dir` when given. Records of
+/// the libraries still on the `log` crate (warp, hyper) come along; times are those of `zone`.
+/// Fails when the directory can't be created or a logger is already set.
+pub fn init(format: LogFormat, dir: Option<&Path>, rotation: LogRotation, zone: &TimeZone) -> Result<(), String> {
+    // Decided now, after `--daemon` may have sent the console to a file
+    let ansi = io::stderr().is_terminal();
+    let timer = ZoneTimer(zone.clone());
+    let console = match format {
+        LogFormat::Text => fmt::layer().with_timer(timer.clone()).with_writer(io::stderr).with_ansi(ansi).boxed(),
+        LogFormat::Json => fmt::layer().json().with_timer(timer.clone()).with_writer(io::stderr).boxed(),
+    };
+    let files = match dir {
+        Some(dir) => {
+            let rotation = match rotation {
+                LogRotation::Hourly => Rotation::HOURLY,
+                LogRotation::Daily => Rotation::DAILY,
+                LogRotation::Never => Rotation::NEVER,
+            };
+            let appender = RollingFileAppender::builder()
+                .rotation(rotation)
+                .filename_prefix(FILE_PREFIX)
+                .filename_suffix(FILE_SUFFIX)
+                .max_log_files(LOG_FILES_KEPT)
+                .build(dir)
+                .map_err(|e| format!("Cannot write logs to {}: {}", dir.display(), e))?;
+            // Written as they come, so nothing is lost when the process exits
+            Some(match format {
+                LogFormat::Text => fmt::layer().with_timer(timer).with_writer(appender).with_ansi(false).boxed(),
+                LogFormat::Json => fmt::layer().json().with_timer(timer).with_writer(appender).boxed(),
+            })
+        }
+        None => None,
+    };
+    tracing_subscriber::registry()
+        .with(filter())
+        .with(console)
+        .with(files)
+        .try_init()
+        .map_err(|e| format!("Cannot set up the log: {}", e))
+}
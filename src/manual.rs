@@ -0,0 +1,213 @@
+use crate::{
+    events::{clock_tenths, Side},
+    numeric_value, GameState,
+};
+use serde::Deserialize;
+
+/// Highest score the console can show (3 digits).
+const MAX_SCORE: u32 = 999;
+/// Fouls and time-outs are single digits on the console.
+const MAX_COUNT: u32 = 9;
+const MAX_PERIOD_NAME_LEN: usize = 32;
+/// Periods in the order [`Command::AdvancePeriod`] steps through them, named like the parser does.
+const PERIODS: [&str; 5] = ["1 Quarter", "2 Quarter", "3 Quarter", "4 Quarter", "Overtime"];
+
+/// Operator-supplied values for `POST`/`PATCH /api/state`, using the field names of
+/// [`StateDto`](crate::dto::StateDto). Missing fields are left unchanged.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct StatePatch {
+    pub home_score: Option<u32>,
+    pub away_score: Option<u32>,
+    pub clock: Option<String>,
+    /// `"SS"` or `"S.t"`, the console's text rather than the DTO's object.
+    pub shot_clock: Option<String>,
+    pub period_name: Option<String>,
+    pub home_fouls: Option<u32>,
+    pub away_fouls: Option<u32>,
+    pub home_timeouts: Option<u32>,
+    pub away_timeouts: Option<u32>,
+    /// `"home"`, `"away"` or `"none"`.
+    pub possession: Option<String>,
+    pub game_state: Option<String>,
+}
+
+impl StatePatch {
+    /// Validate every present field and write it into `state` in the console's text format.
+    ///
+    /// Nothing is changed if any field is invalid.
+    pub fn apply(&self, state: &mut GameState) -> Result<(), String> {
+        let mut next = state.clone();
+
+        let count = |name: &str, value: u32, max: u32| -> Result<String, String> {
+            if value > max {
+                return Err(format!("{} must be at most {}", name, max));
+            }
+            Ok(value.to_string())
+        };
+
+        if let Some(score) = self.home_score {
+            next.home_score = format!("{:>3}", count("homeScore", score, MAX_SCORE)?);
+        }
+        if let Some(score) = self.away_score {
+            next.away_score = format!("{:>3}", count("awayScore", score, MAX_SCORE)?);
+        }
+        if let Some(fouls) = self.home_fouls {
+            next.home_fouls = count("homeFouls", fouls, MAX_COUNT)?;
+        }
+        if let Some(fouls) = self.away_fouls {
+            next.away_fouls = count("awayFouls", fouls, MAX_COUNT)?;
+        }
+        if let Some(timeouts) = self.home_timeouts {
+            next.home_timeouts = count("homeTimeouts", timeouts, MAX_COUNT)?;
+        }
+        if let Some(timeouts) = self.away_timeouts {
+            next.away_timeouts = count("awayTimeouts", timeouts, MAX_COUNT)?;
+        }
+
+        if let Some(clock) = &self.clock {
+            let clock = clock.trim();
+            // Same formats the console sends: `MM:SS`, or `SS.t` in the last minute
+            let valid = clock.len() <= 5
+                && clock_tenths(clock).is_some()
+                && match clock.split_once(':') {
+                    Some((_, seconds)) => seconds.len() == 2 && seconds < "60",
+                    None => clock.split_once('.').is_some_and(|(_, tenths)| tenths.len() == 1),
+                };
+            if !valid {
+                return Err(format!("Invalid clock {:?}: expected MM:SS or SS.t", clock));
+            }
+            next.time = if clock.contains(':') {
+                format!("{:0>5}", clock)
+            } else {
+                format!("{:0>4}", clock)
+            };
+        }
+        if let Some(shot_clock) = &self.shot_clock {
+            let shot_clock = shot_clock.trim();
+            let valid = match shot_clock.split_once('.') {
+                Some((seconds, tenths)) => seconds.len() == 1 && tenths.len() == 1 && clock_tenths(shot_clock).is_some(),
+                None => !shot_clock.is_empty() && shot_clock.len() <= 2 && shot_clock.chars().all(|c| c.is_ascii_digit()),
+            };
+            if !valid {
+                return Err(format!("Invalid shotClock {:?}: expected SS or S.t", shot_clock));
+            }
+            next.shot_clock = shot_clock.to_string();
+        }
+        if let Some(period_name) = &self.period_name {
+            if period_name.len() > MAX_PERIOD_NAME_LEN {
+                return Err(format!("periodName must be at most {} bytes", MAX_PERIOD_NAME_LEN));
+            }
+            next.period_name = period_name.trim().to_string();
+        }
+        if let Some(game_state) = &self.game_state {
+            match game_state.as_str() {
+                "running" | "paused" => next.game_state = game_state.clone(),
+                other => return Err(format!("Invalid gameState {:?}: expected running or paused", other)),
+            }
+        }
+
+        if let Some(possession) = &self.possession {
+            next.possession = match possession.as_str() {
+                "home" | "away" => possession.clone(),
+                "none" => String::new(),
+                other => return Err(format!("Invalid possession {:?}: expected home, away or none", other)),
+            };
+        }
+
+        next.stale = false;
+        *state = next;
+        Ok(())
+    }
+}
+
+fn one() -> i32 {
+    1
+}
+
+/// An operator action from the admin page (`POST /api/control/*`).
+///
+/// Commands are turned into a [`StatePatch`], so they are validated exactly like
+/// manual overrides.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "cmd", rename_all = "camelCase", rename_all_fields = "camelCase", deny_unknown_fields)]
+pub enum Command {
+    /// Add (or, to correct a mistake, remove) points.
+    AddPoints { side: Side, points: i32 },
+    AddFoul {
+        side: Side,
+        #[serde(default = "one")]
+        delta: i32,
+    },
+    AddTimeout {
+        side: Side,
+        #[serde(default = "one")]
+        delta: i32,
+    },
+    /// Give possession to `side`, or clear it with `null`.
+    SetPossession { side: Option<Side> },
+    /// Swap possession (home when unknown).
+    TogglePossession,
+    /// Move to the next period; stays in overtime.
+    AdvancePeriod,
+    /// Set the game clock and/or start or stop it.
+    SetClock { clock: Option<String>, running: Option<bool> },
+}
+
+impl Command {
+    /// Apply the command to `state`; nothing is changed if it is invalid.
+    pub fn apply(&self, state: &mut GameState) -> Result<(), String> {
+        self.to_patch(state)?.apply(state)
+    }
+
+    fn to_patch(&self, state: &GameState) -> Result<StatePatch, String> {
+        // Blank console fields count as zero
+        let adjusted = |name: &str, current: &str, delta: i32| -> Result<Option<u32>, String> {
+            let value = numeric_value(current).unwrap_or(0) as i64 + delta as i64;
+            u32::try_from(value)
+                .map(Some)
+                .map_err(|_| format!("{} cannot go below 0", name))
+        };
+
+        let mut patch = StatePatch::default();
+        match self {
+            Command::AddPoints { side: Side::Home, points } => {
+                patch.home_score = adjusted("homeScore", &state.home_score, *points)?;
+            }
+            Command::AddPoints { side: Side::Away, points } => {
+                patch.away_score = adjusted("awayScore", &state.away_score, *points)?;
+            }
+            Command::AddFoul { side: Side::Home, delta } => {
+                patch.home_fouls = adjusted("homeFouls", &state.home_fouls, *delta)?;
+            }
+            Command::AddFoul { side: Side::Away, delta } => {
+                patch.away_fouls = adjusted("awayFouls", &state.away_fouls, *delta)?;
+            }
+            Command::AddTimeout { side: Side::Home, delta } => {
+                patch.home_timeouts = adjusted("homeTimeouts", &state.home_timeouts, *delta)?;
+            }
+            Command::AddTimeout { side: Side::Away, delta } => {
+                patch.away_timeouts = adjusted("awayTimeouts", &state.away_timeouts, *delta)?;
+            }
+            Command::SetPossession { side } => {
+                patch.possession = Some(side.map_or("none", Side::as_str).to_string());
+            }
+            Command::TogglePossession => {
+                let next = if state.possession == Side::Home.as_str() { Side::Away } else { Side::Home };
+                patch.possession = Some(next.as_str().to_string());
+            }
+            Command::AdvancePeriod => {
+                let next = match PERIODS.iter().position(|p| *p == state.period_name) {
+                    Some(i) => PERIODS[(i + 1).min(PERIODS.len() - 1)],
+                    None => PERIODS[0],
+                };
+                patch.period_name = Some(next.to_string());
+            }
+            Command::SetClock { clock, running } => {
+                patch.clock = clock.clone();
+                patch.game_state = running.map(|running| if running { "running" } else { "paused" }.to_string());
+            }
+        }
+        Ok(patch)
+    }
+}
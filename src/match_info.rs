@@ -0,0 +1,134 @@
+use crate::timestamp::UtcDateTime;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
+};
+
+const MAX_COMPETITION_LEN: usize = 64;
+const MAX_ROUND_LEN: usize = 32;
+const MAX_VENUE_LEN: usize = 64;
+const MAX_OFFICIALS: usize = 5;
+const MAX_OFFICIAL_LEN: usize = 48;
+
+/// What the game is part of and where and when it is played, set by the operator
+/// (`PUT /api/match`).
+///
+/// Like the teams, the console never sends these; every field may be left empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MatchInfo {
+    /// `Liga Betclic`.
+    #[serde(default)]
+    pub competition: String,
+    /// `Round 12`, `Semi-final, game 2`.
+    #[serde(default)]
+    pub round: String,
+    #[serde(default)]
+    pub venue: String,
+    /// Scheduled tip-off, RFC 3339 with the venue's UTC offset: `2026-03-14T20:30:00+01:00`.
+    /// Kept as entered, so the local time can be shown as is.
+    #[serde(default)]
+    pub tip_off: Option<String>,
+    /// Referees and table officials, in the order they are credited.
+    #[serde(default)]
+    pub officials: Vec<String>,
+}
+
+impl MatchInfo {
+    /// Check every field and normalize it (trimmed, blank officials and tip-off dropped).
+    pub fn validate(&mut self) -> Result<(), String> {
+        for (field, value, max) in [
+            ("competition", &mut self.competition, MAX_COMPETITION_LEN),
+            ("round", &mut self.round, MAX_ROUND_LEN),
+            ("venue", &mut self.venue, MAX_VENUE_LEN),
+        ] {
+            *value = value.trim().to_string();
+            if value.chars().count() > max {
+                return Err(format!("{} must be at most {} characters", field, max));
+            }
+        }
+
+        // A blank tip-off, as an empty form field sends it, is no tip-off
+        self.tip_off = self.tip_off.as_deref().map(str::trim).filter(|tip_off| !tip_off.is_empty()).map(String::from);
+        if let Some(tip_off) = &self.tip_off {
+            if UtcDateTime::parse_rfc3339(tip_off).is_none() {
+                return Err(format!(
+                    "Invalid tipOff {:?}: expected a date and time with its UTC offset, e.g. 2026-03-14T20:30:00+01:00",
+                    tip_off
+                ));
+            }
+        }
+
+        self.officials = self.officials.iter().map(|name| name.trim().to_string()).filter(|name| !name.is_empty()).collect();
+        if self.officials.len() > MAX_OFFICIALS {
+            return Err(format!("At most {} officials", MAX_OFFICIALS));
+        }
+        if self.officials.iter().any(|name| name.chars().count() > MAX_OFFICIAL_LEN) {
+            return Err(format!("Official names must be at most {} characters", MAX_OFFICIAL_LEN));
+        }
+        Ok(())
+    }
+
+    /// Nothing was set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The tip-off in Unix milliseconds; `None` if none was set.
+    pub fn tip_off_ms(&self) -> Option<u64> {
+        self.tip_off.as_deref().and_then(UtcDateTime::parse_rfc3339)
+    }
+
+    /// Time left to the tip-off at `now_ms`; `None` without a tip-off or once it has passed.
+    pub fn countdown(&self, now_ms: u64) -> Option<Countdown> {
+        countdown(self.tip_off_ms()?, now_ms)
+    }
+
+    /// Read match details saved by [`save`](Self::save); `None` if the file doesn't exist yet.
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let mut info: Self = serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        info.validate().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Some(info))
+    }
+
+    /// Write the match details to `path`, replacing it atomically like [`Teams::save`](crate::Teams::save).
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        let tmp = path.with_extension("json.tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(json.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    }
+}
+
+/// Time left to the tip-off, as the pregame slate shows it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Countdown {
+    /// Whole seconds left, rounded up: `1` until the very moment of the tip-off.
+    pub seconds: u64,
+    /// `MM:SS` under an hour, `H:MM:SS` beyond (hours keep counting past 24).
+    pub text: String,
+}
+
+/// The [`Countdown`] from `now_ms` to `tip_off_ms` (Unix milliseconds, so midnight and time
+/// zones don't come into it); `None` once the tip-off time is reached.
+fn countdown(tip_off_ms: u64, now_ms: u64) -> Option<Countdown> {
+    let left_ms = tip_off_ms.checked_sub(now_ms).filter(|&left| left > 0)?;
+    let seconds = left_ms.div_ceil(1000);
+    let (hours, minutes, secs) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    let text = if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{:02}:{:02}", minutes, secs)
+    };
+    Some(Countdown { seconds, text })
+}
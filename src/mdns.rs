@@ -0,0 +1,212 @@
+use crate::config::{InputMode, ServerConfig};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+/// DNS-SD type of the overlay web server.
+const HTTP_SERVICE: &str = "_http._tcp.local.";
+/// DNS-SD type of the HTTPS listener (`--tls-cert`).
+const HTTPS_SERVICE: &str = "_https._tcp.local.";
+/// DNS-SD type of the console ingest port.
+const INGEST_SERVICE: &str = "_bodet-score._tcp.local.";
+
+/// Sport advertised in the TXT records; the parser only understands basketball frames.
+const SPORT: &str = "basketball";
+
+/// How long a withdrawal waits for the goodbye packets to go out.
+const WITHDRAW_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Services announced on the local network (mDNS/DNS-SD) while the server runs.
+///
+/// Dropping it without [`withdraw`](Self::withdraw) leaves the records to expire on
+/// their own, which takes browsers up to their TTL (two minutes).
+pub struct Advertiser {
+    daemon: ServiceDaemon,
+    /// Full names of the registered instances.
+    services: Vec<String>,
+}
+
+impl Advertiser {
+    /// Send goodbye packets for every service so browsers forget them at once.
+    pub fn withdraw(self) {
+        for service in &self.services {
+            match self.daemon.unregister(service) {
+                Ok(status) => {
+                    if status.recv_timeout(WITHDRAW_TIMEOUT).is_err() {
+                        warn!("mDNS: no confirmation that {} was withdrawn", service);
+                    }
+                }
+                Err(e) => warn!("mDNS: could not withdraw {}: {}", service, e),
+            }
+        }
+        if let Err(e) = self.daemon.shutdown() {
+            warn!("mDNS: daemon shutdown failed: {}", e);
+        }
+        info!("mDNS advertisement withdrawn");
+    }
+}
+
+/// Advertise the overlay (`_http._tcp`, `_https._tcp` with TLS) and the ingest port
+/// (`_bodet-score._tcp`), each with `sport` and `court` TXT records.
+///
+/// Failures are logged and leave the server running without advertisement.
+pub fn advertise(config: &ServerConfig) -> Option<Advertiser> {
+    if !config.mdns {
+        return None;
+    }
+    let daemon = match ServiceDaemon::new() {
+        Ok(daemon) => daemon,
+        Err(e) => {
+            warn!("mDNS advertisement disabled: {}", e);
+            return None;
+        }
+    };
+
+    let host = hostname();
+    let court = config.court_id.as_deref().unwrap_or_default();
+    let instance = match &config.court_id {
+        Some(court) => format!("Scoreboard {}", court),
+        None => format!("Scoreboard on {}", host),
+    };
+    let properties = [("sport", SPORT), ("court", court), ("path", "/")];
+
+    let mut services = Vec::new();
+    if config.tls.as_ref().is_none_or(|tls| tls.serve_http) {
+        services.push((HTTP_SERVICE, config.web_address.port()));
+    }
+    if let Some(tls) = &config.tls {
+        services.push((HTTPS_SERVICE, tls.address.port()));
+    }
+    // Other inputs have no listener to connect to
+    if config.input == InputMode::Tcp {
+        match config.tcp_address.rsplit_once(':').and_then(|(_, port)| port.parse().ok()) {
+            Some(port) => services.push((INGEST_SERVICE, port)),
+            None => warn!("mDNS: no port in {}, ingest not advertised", config.tcp_address),
+        }
+    }
+
+    let mut registered = Vec::new();
+    for (service_type, port) in services {
+        let info = ServiceInfo::new(service_type, &instance, &format!("{}.local.", host), "", port, &properties[..])
+            .map(ServiceInfo::enable_addr_auto);
+        let result = info.and_then(|info| {
+            let fullname = info.get_fullname().to_string();
+            daemon.register(info).map(|()| fullname)
+        });
+        match result {
+            Ok(fullname) => {
+                info!("mDNS: advertising {} on port {}", fullname, port);
+                registered.push(fullname);
+            }
+            Err(e) => warn!("mDNS: could not advertise {} on port {}: {}", service_type, port, e),
+        }
+    }
+
+    Some(Advertiser {
+        daemon,
+        services: registered,
+    })
+}
+
+/// This machine's host name as a DNS label (`scoreboard` when it can't be found).
+fn hostname() -> String {
+    let name = std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .or_else(|_| std::fs::read_to_string("/etc/hostname"))
+        .unwrap_or_default();
+    let label: String = name
+        .trim()
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let label = label.trim_matches('-');
+    if label.is_empty() {
+        "scoreboard".to_string()
+    } else {
+        label.to_ascii_lowercase()
+    }
+}
+
+/// The arguments of `scoreboard-rust discover`.
+#[derive(Debug, Clone, clap::Args)]
+pub struct DiscoverArgs {
+    /// How long to listen for servers, in seconds.
+    #[arg(long, value_name = "SECS", default_value_t = 3)]
+    pub timeout: u64,
+}
+
+/// Entry point of `scoreboard-rust discover [--timeout <secs>]`; returns the process exit code.
+///
+/// Browses the local network for scoreboard servers and prints their overlay and ingest
+/// addresses as they are found. Other `_http._tcp` devices (printers, cameras) are skipped:
+/// only services with a `sport` TXT record are listed. Exits with 1 if none was found.
+pub fn run_discover(args: &DiscoverArgs) -> i32 {
+    let timeout = Duration::from_secs(args.timeout);
+    let daemon = match ServiceDaemon::new() {
+        Ok(daemon) => daemon,
+        Err(e) => {
+            error!("mDNS unavailable: {}", e);
+            return 1;
+        }
+    };
+    let mut browsers = Vec::new();
+    for service_type in [HTTP_SERVICE, HTTPS_SERVICE, INGEST_SERVICE] {
+        match daemon.browse(service_type) {
+            Ok(receiver) => browsers.push(receiver),
+            Err(e) => error!("Could not browse {}: {}", service_type, e),
+        }
+    }
+
+    info!("Looking for scoreboard servers for {} s...", timeout.as_secs());
+    let deadline = Instant::now() + timeout;
+    let mut seen = HashSet::new();
+    while Instant::now() < deadline {
+        for receiver in &browsers {
+            while let Ok(event) = receiver.try_recv() {
+                let ServiceEvent::ServiceResolved(service) = event else {
+                    continue;
+                };
+                let Some(sport) = service.get_property_val_str("sport") else {
+                    continue;
+                };
+                if !seen.insert(service.get_fullname().to_string()) {
+                    continue;
+                }
+
+                let mut addresses: Vec<String> = service.get_addresses().iter().map(|ip| ip.to_string()).collect();
+                addresses.sort();
+                let role = match service.ty_domain.as_str() {
+                    INGEST_SERVICE => "ingest",
+                    HTTPS_SERVICE => "overlay (https)",
+                    _ => "overlay (http)",
+                };
+                let court = service.get_property_val_str("court").filter(|court| !court.is_empty());
+                println!(
+                    "{:<16} {:<30} {}:{} [{}] sport={} court={}",
+                    role,
+                    service.get_fullname().split("._").next().unwrap_or_default(),
+                    service.get_hostname().trim_end_matches('.'),
+                    service.get_port(),
+                    addresses.join(", "),
+                    sport,
+                    court.unwrap_or("-"),
+                );
+            }
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    let _ = daemon.shutdown();
+    if seen.is_empty() {
+        println!("No scoreboard server found");
+        1
+    } else {
+        0
+    }
+}
@@ -0,0 +1,488 @@
+use crate::state::{lock, unix_millis};
+use serde::Serialize;
+use std::{
+    any::Any,
+    collections::{BTreeMap, VecDeque},
+    fmt, mem,
+    net::{IpAddr, SocketAddr},
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tracing::error;
+
+/// Web request durations kept for [`DurationSummary`]: the most recent ones only.
+const REQUEST_DURATION_WINDOW: usize = 1024;
+
+/// Sampled frame timings kept until taken; later ones are dropped.
+const FRAME_TIMING_BACKLOG: usize = 1024;
+
+/// Percentiles of the recent web request durations, in milliseconds.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DurationSummary {
+    /// Requests in the window, at most 1024.
+    pub count: usize,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+impl DurationSummary {
+    fn from_window(window: &VecDeque<Duration>) -> Option<Self> {
+        let mut sorted: Vec<Duration> = window.iter().copied().collect();
+        sorted.sort_unstable();
+        let max = *sorted.last()?;
+        let ms = |duration: Duration| (duration.as_secs_f64() * 1000.0 * 10.0).round() / 10.0;
+        Some(Self {
+            count: sorted.len(),
+            p50_ms: ms(percentile(&sorted, 50)),
+            p95_ms: ms(percentile(&sorted, 95)),
+            max_ms: ms(max),
+        })
+    }
+}
+
+/// Nearest rank: the smallest of the `sorted` durations that at least `p` percent of them
+/// don't exceed. `sorted` must not be empty.
+pub(crate) fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    sorted[(sorted.len() * p).div_ceil(100).max(1) - 1]
+}
+
+/// Counters for a single forwarding target.
+#[derive(Debug, Default)]
+pub struct ForwardTargetStats {
+    pub bytes_forwarded: AtomicU64,
+    pub chunks_dropped: AtomicU64,
+    pub connected: AtomicBool,
+}
+
+impl ForwardTargetStats {
+    pub fn add_forwarded(&self, bytes: usize) {
+        self.bytes_forwarded.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn add_dropped(&self) {
+        self.chunks_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Lifetime counters for one ingest peer, aggregated across its reconnects.
+#[derive(Debug, Default)]
+pub struct PeerStats {
+    pub connections: AtomicU64,
+    pub bytes: AtomicU64,
+    pub frames: AtomicU64,
+    pub errors: AtomicU64,
+    /// Times the decoder had to skip garbage to find the next frame.
+    pub resyncs: AtomicU64,
+}
+
+impl PeerStats {
+    pub fn add_connection(&self) {
+        self.connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_bytes(&self, bytes: usize) {
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn add_frame(&self, ok: bool) {
+        let counter = if ok { &self.frames } else { &self.errors };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_resyncs(&self, resyncs: u64) {
+        self.resyncs.fetch_add(resyncs, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> PeerSnapshot {
+        PeerSnapshot {
+            connections: self.connections.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            frames: self.frames.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            resyncs: self.resyncs.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Point-in-time copy of [`PeerStats`].
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerSnapshot {
+    pub connections: u64,
+    pub bytes: u64,
+    pub frames: u64,
+    pub errors: u64,
+    pub resyncs: u64,
+}
+
+impl fmt::Display for PeerSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} frames, {} errors, {} resyncs ({} connections, {} bytes)",
+            self.frames, self.errors, self.resyncs, self.connections, self.bytes
+        )
+    }
+}
+
+/// Point-in-time copy of [`ForwardTargetStats`].
+#[derive(Debug, Clone, Serialize)]
+pub struct ForwardTargetSnapshot {
+    pub bytes_forwarded: u64,
+    pub chunks_dropped: u64,
+    pub connected: bool,
+}
+
+/// Point-in-time copy of all [`Metrics`], as reported by the web status endpoint.
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsSnapshot {
+    pub duplicates_suppressed: u64,
+    pub heartbeats_received: u64,
+    pub auth_failures: u64,
+    pub auth_rate_limited: u64,
+    pub web_auth_failures: u64,
+    pub web_auth_rate_limited: u64,
+    pub peers_dropped_backpressure: u64,
+    pub handler_panics: u64,
+    pub web_requests: u64,
+    /// `None` until the first request was answered.
+    pub web_request_durations: Option<DurationSummary>,
+    pub ingest_connected: u64,
+    pub ws_clients: u64,
+    pub ws_reaped: u64,
+    pub ws_refused: u64,
+    pub notifications_sent: u64,
+    pub notification_retries: u64,
+    pub notifications_failed: u64,
+    pub notifications_dropped: u64,
+    pub listener_updates_coalesced: u64,
+    pub push_sent: u64,
+    pub push_failures: u64,
+    pub push_events_dropped: u64,
+    /// Age of the oldest update not pushed yet; 0 when the remote has them all.
+    pub push_lag_ms: u64,
+    /// Time the last push delivery took from the oldest update it carried to the answer.
+    pub push_last_lag_ms: u64,
+    pub last_panic: Option<String>,
+    pub forward_targets: BTreeMap<SocketAddr, ForwardTargetSnapshot>,
+    pub peers: BTreeMap<IpAddr, PeerSnapshot>,
+}
+
+impl MetricsSnapshot {
+    /// The web requests and their durations in the Prometheus text format, for `GET /metrics`.
+    ///
+    /// The durations are a summary of the recent window: p50, p95 and the max as quantile 1.
+    pub fn to_prometheus(&self) -> String {
+        let mut text = String::new();
+        text.push_str("# HELP scoreboard_web_requests_total Web requests answered.\n");
+        text.push_str("# TYPE scoreboard_web_requests_total counter\n");
+        text.push_str(&format!("scoreboard_web_requests_total {}\n", self.web_requests));
+        text.push_str(&format!(
+            "# HELP scoreboard_web_request_duration_ms Durations of the last {} timed web requests, in milliseconds.\n",
+            REQUEST_DURATION_WINDOW
+        ));
+        text.push_str("# TYPE scoreboard_web_request_duration_ms summary\n");
+        let count = match self.web_request_durations {
+            Some(summary) => {
+                for (quantile, ms) in [("0.5", summary.p50_ms), ("0.95", summary.p95_ms), ("1", summary.max_ms)] {
+                    text.push_str(&format!("scoreboard_web_request_duration_ms{{quantile=\"{}\"}} {}\n", quantile, ms));
+                }
+                summary.count
+            }
+            None => 0,
+        };
+        text.push_str(&format!("scoreboard_web_request_duration_ms_count {}\n", count));
+        text
+    }
+}
+
+/// What a [`LoopBeat`] last saw of its loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopState {
+    /// The loop hasn't bound its listener yet.
+    NotStarted,
+    /// The loop runs but can't show it: inputs read with a blocking read (stdin, `--follow`).
+    Unwatched,
+    /// The loop last went round at this time, in Unix milliseconds.
+    LastSeen(u64),
+}
+
+/// Set by a main loop each time round, so a supervisor can tell it is not stuck.
+#[derive(Debug, Default)]
+pub struct LoopBeat(AtomicU64);
+
+impl LoopBeat {
+    const UNWATCHED: u64 = u64::MAX;
+
+    /// The loop is alive now.
+    pub fn beat(&self) {
+        self.0.store(unix_millis(), Ordering::Relaxed);
+    }
+
+    /// The loop started but won't beat.
+    pub fn unwatched(&self) {
+        self.0.store(Self::UNWATCHED, Ordering::Relaxed);
+    }
+
+    pub fn state(&self) -> LoopState {
+        match self.0.load(Ordering::Relaxed) {
+            0 => LoopState::NotStarted,
+            Self::UNWATCHED => LoopState::Unwatched,
+            at => LoopState::LastSeen(at),
+        }
+    }
+}
+
+/// Process-wide counters shared between the ingest and web sides.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Frames whose state was identical to the current one and was not re-published.
+    pub duplicates_suppressed: AtomicU64,
+    /// Keepalive bytes received between frames.
+    pub heartbeats_received: AtomicU64,
+    /// Ingest connections that failed the `AUTH` handshake, and datagrams with a bad signature.
+    pub auth_failures: AtomicU64,
+    /// Ingest connections refused because their IP failed the handshake too often.
+    pub auth_rate_limited: AtomicU64,
+    /// Web requests with a wrong admin token.
+    pub web_auth_failures: AtomicU64,
+    /// Web requests refused because their IP presented wrong admin tokens too often.
+    pub web_auth_rate_limited: AtomicU64,
+    /// Ingest clients and subscribers disconnected because they stopped reading.
+    pub peers_dropped_backpressure: AtomicU64,
+    /// Panics caught in client handlers, input readers and update listeners.
+    pub handler_panics: AtomicU64,
+    /// Requests answered by the web server, counted by the access log.
+    pub web_requests: AtomicU64,
+    /// Durations of the last [`REQUEST_DURATION_WINDOW`] timed web requests, oldest first.
+    request_durations: Mutex<VecDeque<Duration>>,
+    /// Ingest connections currently open, see [`IngestConnection`].
+    pub ingest_connected: AtomicU64,
+    /// WebSocket clients currently connected, see [`WsConnection`].
+    pub ws_clients: AtomicU64,
+    /// WebSocket clients disconnected because they didn't answer a ping or take a frame in time.
+    pub ws_reaped: AtomicU64,
+    /// WebSocket clients closed right after the upgrade because the server was full.
+    pub ws_refused: AtomicU64,
+    /// Chat notifications posted (`--slack-webhook`, `--discord-webhook`).
+    pub notifications_sent: AtomicU64,
+    /// Posts of a notification tried again after a failure.
+    pub notification_retries: AtomicU64,
+    /// Notifications given up after their last attempt or refused by the webhook.
+    pub notifications_failed: AtomicU64,
+    /// Notifications dropped because the channel's queue was full.
+    pub notifications_dropped: AtomicU64,
+    /// Updates merged into the last one queued for a listener that fell behind, see
+    /// [`SharedState::add_listener`](crate::SharedState::add_listener).
+    pub listener_updates_coalesced: AtomicU64,
+    /// Requests of `--push-url` the remote accepted.
+    pub push_sent: AtomicU64,
+    /// Requests of `--push-url` that failed, each retried later.
+    pub push_failures: AtomicU64,
+    /// Events dropped from the push outbox because it was full.
+    pub push_events_dropped: AtomicU64,
+    /// Unix milliseconds of the oldest update not pushed yet; 0 for none.
+    pub push_pending_since_ms: AtomicU64,
+    pub push_last_lag_ms: AtomicU64,
+    /// The ingest accept loop going round, watched by the systemd watchdog.
+    pub ingest_beat: LoopBeat,
+    /// The web server's runtime running its tasks, watched the same way.
+    pub web_beat: LoopBeat,
+    /// Every how many frames one is timed, see [`time_next_frame`](Self::time_next_frame); 0 for none.
+    frame_timing_every: AtomicU64,
+    frames_seen: AtomicU64,
+    /// Sampled frame timings not taken yet.
+    frame_timings: Mutex<Vec<Duration>>,
+    /// Message of the most recent caught panic.
+    last_panic: Mutex<Option<String>>,
+    forward_targets: Mutex<BTreeMap<SocketAddr, Arc<ForwardTargetStats>>>,
+    peers: Mutex<BTreeMap<IpAddr, Arc<PeerStats>>>,
+}
+
+impl Metrics {
+    /// Get (or create) the counters for a forwarding target.
+    pub fn forward_target(&self, addr: SocketAddr) -> Arc<ForwardTargetStats> {
+        let mut targets = lock(&self.forward_targets);
+        Arc::clone(targets.entry(addr).or_default())
+    }
+
+    /// Get (or create) the lifetime counters for an ingest peer.
+    pub fn peer(&self, ip: IpAddr) -> Arc<PeerStats> {
+        let mut peers = lock(&self.peers);
+        Arc::clone(peers.entry(ip).or_default())
+    }
+
+    /// Snapshot of every peer's counters, ordered by IP.
+    pub fn peer_snapshots(&self) -> BTreeMap<IpAddr, PeerSnapshot> {
+        lock(&self.peers)
+            .iter()
+            .map(|(ip, stats)| (*ip, stats.snapshot()))
+            .collect()
+    }
+
+    /// Record a caught panic.
+    pub fn record_panic(&self, context: &str, message: &str) {
+        self.handler_panics.fetch_add(1, Ordering::Relaxed);
+        *lock(&self.last_panic) = Some(format!("{}: {}", context, message));
+    }
+
+    /// Add a web request duration to the rolling window behind [`DurationSummary`].
+    pub fn record_request_duration(&self, duration: Duration) {
+        let mut durations = lock(&self.request_durations);
+        if durations.len() == REQUEST_DURATION_WINDOW {
+            durations.pop_front();
+        }
+        durations.push_back(duration);
+    }
+
+    /// Percentiles of the recent web request durations; `None` before the first request.
+    pub fn request_durations(&self) -> Option<DurationSummary> {
+        DurationSummary::from_window(&lock(&self.request_durations))
+    }
+
+    /// Time one frame in `every` from now on (`--statsd-sample-rate`); 0 stops it.
+    pub(crate) fn sample_frame_timings(&self, every: u64) {
+        self.frame_timing_every.store(every, Ordering::Relaxed);
+    }
+
+    /// Whether the frame about to be applied is one of the sampled ones.
+    pub(crate) fn time_next_frame(&self) -> bool {
+        let every = self.frame_timing_every.load(Ordering::Relaxed);
+        every > 0 && self.frames_seen.fetch_add(1, Ordering::Relaxed).is_multiple_of(every)
+    }
+
+    /// Keep the timing of a sampled frame. Called on the ingest path: while the timings are
+    /// being taken, or the backlog is full, it is dropped rather than waited for.
+    pub(crate) fn record_frame_timing(&self, duration: Duration) {
+        if let Ok(mut timings) = self.frame_timings.try_lock() {
+            if timings.len() < FRAME_TIMING_BACKLOG {
+                timings.push(duration);
+            }
+        }
+    }
+
+    /// The sampled frame timings recorded since the last call.
+    pub(crate) fn take_frame_timings(&self) -> Vec<Duration> {
+        mem::take(&mut *lock(&self.frame_timings))
+    }
+
+    /// Message of the most recent caught panic, if any.
+    pub fn last_panic(&self) -> Option<String> {
+        lock(&self.last_panic).clone()
+    }
+
+    /// Copy every counter.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let forward_targets = lock(&self.forward_targets)
+            .iter()
+            .map(|(addr, stats)| {
+                let snapshot = ForwardTargetSnapshot {
+                    bytes_forwarded: stats.bytes_forwarded.load(Ordering::Relaxed),
+                    chunks_dropped: stats.chunks_dropped.load(Ordering::Relaxed),
+                    connected: stats.connected.load(Ordering::Relaxed),
+                };
+                (*addr, snapshot)
+            })
+            .collect();
+
+        MetricsSnapshot {
+            duplicates_suppressed: self.duplicates_suppressed.load(Ordering::Relaxed),
+            heartbeats_received: self.heartbeats_received.load(Ordering::Relaxed),
+            auth_failures: self.auth_failures.load(Ordering::Relaxed),
+            auth_rate_limited: self.auth_rate_limited.load(Ordering::Relaxed),
+            web_auth_failures: self.web_auth_failures.load(Ordering::Relaxed),
+            web_auth_rate_limited: self.web_auth_rate_limited.load(Ordering::Relaxed),
+            peers_dropped_backpressure: self.peers_dropped_backpressure.load(Ordering::Relaxed),
+            handler_panics: self.handler_panics.load(Ordering::Relaxed),
+            web_requests: self.web_requests.load(Ordering::Relaxed),
+            web_request_durations: self.request_durations(),
+            ingest_connected: self.ingest_connected.load(Ordering::Relaxed),
+            ws_clients: self.ws_clients.load(Ordering::Relaxed),
+            ws_reaped: self.ws_reaped.load(Ordering::Relaxed),
+            ws_refused: self.ws_refused.load(Ordering::Relaxed),
+            notifications_sent: self.notifications_sent.load(Ordering::Relaxed),
+            notification_retries: self.notification_retries.load(Ordering::Relaxed),
+            notifications_failed: self.notifications_failed.load(Ordering::Relaxed),
+            notifications_dropped: self.notifications_dropped.load(Ordering::Relaxed),
+            listener_updates_coalesced: self.listener_updates_coalesced.load(Ordering::Relaxed),
+            push_sent: self.push_sent.load(Ordering::Relaxed),
+            push_failures: self.push_failures.load(Ordering::Relaxed),
+            push_events_dropped: self.push_events_dropped.load(Ordering::Relaxed),
+            push_lag_ms: match self.push_pending_since_ms.load(Ordering::Relaxed) {
+                0 => 0,
+                since => unix_millis().saturating_sub(since),
+            },
+            push_last_lag_ms: self.push_last_lag_ms.load(Ordering::Relaxed),
+            last_panic: self.last_panic(),
+            forward_targets,
+            peers: self.peer_snapshots(),
+        }
+    }
+}
+
+/// Counts an open ingest connection in [`Metrics::ingest_connected`] until dropped,
+/// so connections ended by an error or a caught panic are uncounted too.
+pub struct IngestConnection<'a> {
+    metrics: &'a Metrics,
+}
+
+impl<'a> IngestConnection<'a> {
+    pub fn open(metrics: &'a Metrics) -> Self {
+        metrics.ingest_connected.fetch_add(1, Ordering::Relaxed);
+        Self { metrics }
+    }
+}
+
+impl Drop for IngestConnection<'_> {
+    fn drop(&mut self) {
+        self.metrics.ingest_connected.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Counts an open WebSocket client in [`Metrics::ws_clients`] until dropped.
+pub struct WsConnection<'a> {
+    metrics: &'a Metrics,
+}
+
+impl<'a> WsConnection<'a> {
+    pub fn open(metrics: &'a Metrics) -> Self {
+        metrics.ws_clients.fetch_add(1, Ordering::Relaxed);
+        Self { metrics }
+    }
+}
+
+impl Drop for WsConnection<'_> {
+    fn drop(&mut self) {
+        self.metrics.ws_clients.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Run `f`, catching a panic so it only takes down the current unit of work.
+///
+/// The panic is logged and recorded in `metrics`. Returns `None` if `f` panicked.
+pub fn isolate<T>(context: &str, metrics: &Metrics, f: impl FnOnce() -> T) -> Option<T> {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(value) => Some(value),
+        Err(payload) => {
+            let message = panic_message(payload.as_ref());
+            error!("Panic in {}: {}", context, message);
+            metrics.record_panic(context, &message);
+            None
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
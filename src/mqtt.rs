@@ -0,0 +1,311 @@
+use crate::{config::MqttConfig, dto::StateDto, state::SharedState};
+use rumqttc::{Client, ClientError, Connection, Event, Incoming, LastWill, MqttOptions, Outgoing, QoS};
+use serde_json::json;
+use std::{
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use tracing::{info, warn};
+
+/// Messages buffered for the broker before new ones are dropped.
+const QUEUE_CAPACITY: usize = 256;
+/// Seconds a connection attempt gets.
+const CONNECT_TIMEOUT: u64 = 3;
+/// Keep alive announced to the broker; the client pings it when nothing else went out.
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+/// Largest packet either way; a state with long team rosters is a few kilobytes.
+const MAX_PACKET_SIZE: usize = 256 * 1024;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Court in the topics when `--court-id` isn't set.
+const DEFAULT_COURT: &str = "default";
+const ONLINE: &[u8] = b"online";
+/// Also the last will: the broker publishes it when the connection drops.
+const OFFLINE: &[u8] = b"offline";
+
+/// One message for the broker.
+#[derive(Clone)]
+struct Message {
+    topic: Arc<str>,
+    payload: Vec<u8>,
+    retain: bool,
+}
+
+/// The topics of one court: `scoreboard/<court>/state`, `/events` and `/status`.
+#[derive(Clone)]
+struct Topics {
+    state: Arc<str>,
+    events: Arc<str>,
+    status: Arc<str>,
+}
+
+impl Topics {
+    fn new(court: &str) -> Self {
+        let topic = |name: &str| Arc::from(format!("scoreboard/{}/{}", court, name));
+        Self {
+            state: topic("state"),
+            events: topic("events"),
+            status: topic("status"),
+        }
+    }
+}
+
+/// The Home Assistant sensors: object id suffix, name, `StateDto` field, icon, and whether
+/// the value is a number.
+const SENSORS: [(&str, &str, &str, &str, bool); 5] = [
+    ("home_score", "Home score", "homeScore", "mdi:scoreboard", true),
+    ("away_score", "Away score", "awayScore", "mdi:scoreboard", true),
+    ("clock", "Clock", "clock", "mdi:timer-outline", false),
+    ("period", "Period", "periodName", "mdi:numeric", false),
+    ("game_state", "Game state", "gameState", "mdi:play-pause", false),
+];
+
+/// The retained discovery configs announcing the court's sensors to Home Assistant, under
+/// `<prefix>/sensor/scoreboard_<court>_<field>/config`.
+///
+/// The sensors read the retained state and follow `status` for their availability; they are
+/// grouped under one device per court.
+fn discovery(prefix: &str, court: &str, topics: &Topics) -> Vec<Message> {
+    // Object ids only take letters, digits, _ and -
+    let id: String = court.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' }).collect();
+    let device_name = if court == DEFAULT_COURT { "Scoreboard".to_string() } else { format!("Scoreboard {}", court) };
+    SENSORS
+        .iter()
+        .map(|&(field, name, key, icon, number)| {
+            let object_id = format!("scoreboard_{}_{}", id, field);
+            let mut config = json!({
+                "name": name,
+                "unique_id": object_id,
+                "state_topic": &*topics.state,
+                // A blank count is null, which Home Assistant shows as unknown
+                "value_template": format!("{{{{ value_json.{} }}}}", key),
+                "availability_topic": &*topics.status,
+                "payload_available": "online",
+                "payload_not_available": "offline",
+                "icon": icon,
+                "device": {
+                    "identifiers": [format!("scoreboard_{}", id)],
+                    "name": device_name,
+                    "manufacturer": "Bodet",
+                    "model": "Scoreboard (scoreboard-rust bridge)",
+                    "sw_version": env!("CARGO_PKG_VERSION"),
+                },
+                "origin": { "name": "scoreboard-rust", "sw_version": env!("CARGO_PKG_VERSION") },
+            });
+            if number {
+                config["state_class"] = json!("measurement");
+            }
+            if field == "game_state" {
+                config["device_class"] = json!("enum");
+                config["options"] = json!(["paused", "running"]);
+            }
+            Message {
+                topic: Arc::from(format!("{}/sensor/{}/config", prefix, object_id)),
+                payload: serde_json::to_vec(&config).unwrap_or_default(),
+                retain: true,
+            }
+        })
+        .collect()
+}
+
+/// Publish every state and event to the MQTT broker (`--mqtt-broker`).
+///
+/// The state goes to `scoreboard/<court>/state` as JSON ([`StateDto`]), retained so a
+/// subscriber gets the current one at once; each event goes to `scoreboard/<court>/events`.
+/// `scoreboard/<court>/status` is `online` while the bridge is connected and `offline`
+/// otherwise, the broker publishing it as the last will when the connection drops.
+///
+/// With `config.discovery`, the score, clock, period and game state are announced to Home
+/// Assistant on each connection (see [`discovery`]) and removed again on shutdown.
+///
+/// Messages go through the client's bounded request queue to a connection thread, so a slow or
+/// unreachable broker never holds up ingest: when the queue is full new messages are dropped.
+/// The connection is retried with backoff and the latest state is sent again once connected.
+pub fn start(config: &MqttConfig, court_id: Option<&str>, shared: &Arc<SharedState>) {
+    let court = court_id.unwrap_or(DEFAULT_COURT);
+    let topics = Topics::new(court);
+    let qos = if config.qos == 0 { QoS::AtMostOnce } else { QoS::AtLeastOnce };
+
+    let mut options = MqttOptions::new(format!("scoreboard-{}", court), config.host.as_str(), config.port);
+    options
+        .set_keep_alive(KEEP_ALIVE)
+        .set_clean_session(true)
+        .set_max_packet_size(MAX_PACKET_SIZE, MAX_PACKET_SIZE)
+        .set_last_will(LastWill::new(&*topics.status, OFFLINE, qos, true));
+    let (client, mut connection) = Client::new(options, QUEUE_CAPACITY);
+    connection.eventloop.network_options.set_connection_timeout(CONNECT_TIMEOUT);
+
+    let discovery = config.discovery.as_deref().map_or_else(Vec::new, |prefix| discovery(prefix, court, &topics));
+    let latest_state = Arc::new(Mutex::new(None));
+    let (stop, stopped) = mpsc::channel();
+    let bridge = Bridge {
+        client,
+        qos,
+        topics,
+        discovery,
+        latest_state: Arc::clone(&latest_state),
+    };
+    let broker = format!("{}:{}", config.host, config.port);
+    let thread_bridge = bridge.clone();
+    let connection = thread::spawn(move || run_connection(&thread_bridge, &mut connection, &broker, &stopped));
+    info!(
+        "Publishing to MQTT broker {}:{} under scoreboard/{}/ (QoS {})",
+        config.host, config.port, court, config.qos
+    );
+    if let Some(prefix) = &config.discovery {
+        info!("Announcing the sensors to Home Assistant under {}/sensor/", prefix);
+    }
+    let queue = Queue { bridge, stop: Some(stop), connection: Some(connection) };
+
+    let state = Arc::clone(shared);
+    let mut dropping = false;
+    shared.add_listener(Duration::ZERO, move |update| {
+        let dto = StateDto::localized(&update.state, state.settings().lang);
+        let payload = serde_json::to_vec(&dto).unwrap_or_default();
+        *latest_state.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(payload.clone());
+        let state_message = Message {
+            topic: Arc::clone(&queue.bridge.topics.state),
+            payload,
+            retain: true,
+        };
+        let events = update.logged_events.iter().map(|event| Message {
+            topic: Arc::clone(&queue.bridge.topics.events),
+            payload: serde_json::to_vec(event).unwrap_or_default(),
+            retain: false,
+        });
+
+        for message in std::iter::once(state_message).chain(events) {
+            match queue.bridge.try_publish(message) {
+                Ok(()) if dropping => {
+                    info!("MQTT queue has room again");
+                    dropping = false;
+                }
+                Ok(()) => {}
+                // Logged once per streak, not once per message
+                Err(ClientError::TryRequest(_)) if !dropping => {
+                    warn!("MQTT queue full, dropping messages until the broker catches up");
+                    dropping = true;
+                }
+                Err(ClientError::TryRequest(_)) => {}
+                Err(ClientError::Request(_)) => return,
+            }
+        }
+    });
+}
+
+/// What the listener and the connection thread share to publish.
+#[derive(Clone)]
+struct Bridge {
+    client: Client,
+    qos: QoS,
+    topics: Topics,
+    discovery: Vec<Message>,
+    /// The last state published, sent again on each connection.
+    latest_state: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+impl Bridge {
+    fn try_publish(&self, message: Message) -> Result<(), ClientError> {
+        self.client.try_publish(&*message.topic, self.qos, message.retain, message.payload)
+    }
+
+    /// Queue the discovery configs, `online` and the latest state for a new connection. Home
+    /// Assistant reads the configs before the sensors' first state.
+    fn announce(&self) -> Result<(), ClientError> {
+        self.discovery.iter().try_for_each(|config| self.try_publish(config.clone()))?;
+        self.try_publish(Message {
+            topic: Arc::clone(&self.topics.status),
+            payload: ONLINE.to_vec(),
+            retain: true,
+        })?;
+        let latest = self.latest_state.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+        match latest {
+            Some(payload) => self.try_publish(Message { topic: Arc::clone(&self.topics.state), payload, retain: true }),
+            None => Ok(()),
+        }
+    }
+
+    /// Queue the removal of the discovered sensors (an empty retained config), `offline` and a
+    /// disconnect: a clean disconnect doesn't trigger the last will.
+    fn say_goodbye(&self) -> Result<(), ClientError> {
+        for config in &self.discovery {
+            self.try_publish(Message {
+                topic: Arc::clone(&config.topic),
+                payload: Vec::new(),
+                retain: true,
+            })?;
+        }
+        self.try_publish(Message {
+            topic: Arc::clone(&self.topics.status),
+            payload: OFFLINE.to_vec(),
+            retain: true,
+        })?;
+        self.client.try_disconnect()
+    }
+}
+
+/// The listener's end of the bridge. Dropped when the server shuts down: the goodbye is queued
+/// and the connection thread gets the time to send it before the listener ends.
+struct Queue {
+    bridge: Bridge,
+    /// Dropped to stop a connection thread waiting to reconnect.
+    stop: Option<Sender<()>>,
+    connection: Option<JoinHandle<()>>,
+}
+
+impl Drop for Queue {
+    fn drop(&mut self) {
+        let goodbye = self.bridge.say_goodbye();
+        self.stop = None;
+        let Some(connection) = self.connection.take() else {
+            return;
+        };
+        match goodbye {
+            Ok(()) => {
+                let _ = connection.join();
+            }
+            // The thread would never see the disconnect; it ends with the process
+            Err(e) => warn!("Leaving the MQTT broker without a goodbye: {}", e),
+        }
+    }
+}
+
+// Connection loop: drive the client until the goodbye went out, reconnecting with exponential
+// backoff.
+fn run_connection(bridge: &Bridge, connection: &mut Connection, broker: &str, stopped: &Receiver<()>) {
+    let mut connected = false;
+    let mut backoff = INITIAL_BACKOFF;
+
+    for event in connection.iter() {
+        match event {
+            Ok(Event::Incoming(Incoming::ConnAck(_))) => {
+                info!("Connected to MQTT broker {}", broker);
+                connected = true;
+                backoff = INITIAL_BACKOFF;
+                if let Err(e) = bridge.announce() {
+                    warn!("MQTT queue full, not announcing the bridge: {}", e);
+                }
+            }
+            // The server is shutting down
+            Ok(Event::Outgoing(Outgoing::Disconnect)) => return,
+            Ok(_) => {}
+            Err(e) => {
+                if connected {
+                    warn!("Lost connection to MQTT broker {}: {}", broker, e);
+                    connected = false;
+                    continue;
+                }
+                warn!("Failed to connect to MQTT broker {}: {} (retrying in {:?})", broker, e, backoff);
+                if stopped.recv_timeout(backoff) != Err(RecvTimeoutError::Timeout) {
+                    return;
+                }
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
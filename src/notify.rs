@@ -0,0 +1,377 @@
+use crate::{
+    config::{NotifyConfig, NotifyTarget},
+    events::GameEvent,
+    http_client::{self, HttpUrl},
+    metrics::Metrics,
+    numeric_value,
+    state::SharedState,
+    summary::FINAL_PERIOD,
+    GameState,
+};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    sync::{
+        atomic::Ordering,
+        mpsc::{self, TrySendError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+/// Kind of the notification sent when the operator sets the `Final` period.
+pub const FINAL: &str = "Final";
+
+/// Messages waiting for a channel; more are dropped, as a backed-up channel only falls behind.
+const QUEUE_CAPACITY: usize = 16;
+
+/// Attempts at sending one message before it is given up.
+const ATTEMPTS: u32 = 4;
+
+/// Wait before the first retry, doubled for each one after.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// How long a webhook gets to answer.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Placeholders of the templates.
+pub const PLACEHOLDERS: &[&str] = &[
+    "home_name",
+    "away_name",
+    "home_abbrev",
+    "away_abbrev",
+    "home_score",
+    "away_score",
+    "home_fouls",
+    "away_fouls",
+    "period",
+    "clock",
+    "event",
+    "court",
+    "competition",
+    "round",
+    "venue",
+];
+
+/// The chat service a webhook belongs to, which decides the shape of the payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Service {
+    /// Slack incoming webhook: `text` and Block Kit `blocks`.
+    Slack,
+    /// Discord webhook: an embed.
+    Discord,
+}
+
+impl Service {
+    pub fn name(self) -> &'static str {
+        match self {
+            Service::Slack => "Slack",
+            Service::Discord => "Discord",
+        }
+    }
+}
+
+/// The kinds a notification can be sent for: the game events, clock ticks left out, and
+/// [`FINAL`].
+pub fn kinds() -> Vec<&'static str> {
+    GameEvent::KINDS
+        .iter()
+        .copied()
+        .filter(|kind| !matches!(*kind, "ClockChange" | "ShotClockChange"))
+        .chain([FINAL])
+        .collect()
+}
+
+/// Check that `template` only uses known placeholders and closes each `{`.
+pub fn check_template(template: &str) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').ok_or_else(|| format!("unclosed {{ in {:?}", template))?;
+        let name = &rest[start + 1..start + end];
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(format!("unknown placeholder {{{}}} (known: {})", name, PLACEHOLDERS.join(", ")));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    Ok(())
+}
+
+/// The message templates: the defaults with those of `--notify-template` applied.
+#[derive(Debug, Clone, Default)]
+pub struct Templates {
+    templates: HashMap<&'static str, String>,
+}
+
+impl Templates {
+    /// Templates are checked when the options are parsed.
+    pub fn new(templates: &[(&'static str, String)]) -> Self {
+        Self { templates: templates.iter().cloned().collect() }
+    }
+
+    fn get(&self, kind: &str) -> &str {
+        match self.templates.get(kind) {
+            Some(template) => template,
+            None if kind == FINAL => "Final: {home_name} {home_score} - {away_score} {away_name}",
+            None if kind == "PeriodEnded" => "End of {period}: {home_name} {home_score} - {away_score} {away_name}",
+            None => "{event}: {home_name} {home_score} - {away_score} {away_name} ({period}, {clock})",
+        }
+    }
+}
+
+/// A notification ready to be rendered for a service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub kind: &'static str,
+    /// `Final`, `Period ended`, `Score change`...
+    pub title: String,
+    /// The template filled in.
+    pub text: String,
+    pub home_name: String,
+    pub away_name: String,
+    pub home_score: String,
+    pub away_score: String,
+    /// `#RRGGBB` of the team ahead, if it has one.
+    pub color: Option<String>,
+    /// `2 Quarter · 04:12 · court-2`
+    pub footer: String,
+    /// `Liga Betclic · Round 12 · Pavilhão do Funchal`, empty without match details.
+    pub context: String,
+}
+
+impl Message {
+    /// The message for `kind` after `state`; `event` is the game event it is sent for, none for
+    /// [`FINAL`].
+    pub fn new(kind: &'static str, event: Option<&GameEvent>, state: &GameState, court: Option<&str>, templates: &Templates) -> Self {
+        let name = |name: &str, abbrev: &str, fallback: &str| {
+            [name, abbrev].into_iter().map(str::trim).find(|name| !name.is_empty()).unwrap_or(fallback).to_string()
+        };
+        let (home_name, away_name) = (
+            name(&state.teams.home.name, &state.teams.home.abbrev, "Home"),
+            name(&state.teams.away.name, &state.teams.away.abbrev, "Away"),
+        );
+        let period = state.period_name.trim();
+        let clock = state.time.trim();
+        let event_text = event.map_or_else(|| kind.to_string(), |event| event.to_string());
+
+        let mut text = String::new();
+        let mut rest = templates.get(kind);
+        while let Some(start) = rest.find('{') {
+            text.push_str(&rest[..start]);
+            let Some(end) = rest[start..].find('}') else {
+                break;
+            };
+            let value = match &rest[start + 1..start + end] {
+                "home_name" => home_name.as_str(),
+                "away_name" => away_name.as_str(),
+                "home_abbrev" => state.teams.home.abbrev.as_str(),
+                "away_abbrev" => state.teams.away.abbrev.as_str(),
+                "home_score" => state.home_score.trim(),
+                "away_score" => state.away_score.trim(),
+                "home_fouls" => state.home_fouls.trim(),
+                "away_fouls" => state.away_fouls.trim(),
+                "period" => period,
+                "clock" => clock,
+                "event" => event_text.as_str(),
+                "court" => court.unwrap_or_default(),
+                "competition" => state.match_info.competition.as_str(),
+                "round" => state.match_info.round.as_str(),
+                "venue" => state.match_info.venue.as_str(),
+                _ => "",
+            };
+            text.push_str(value);
+            rest = &rest[start + end + 1..];
+        }
+        text.push_str(rest);
+
+        let color = match (numeric_value(&state.home_score), numeric_value(&state.away_score)) {
+            (Some(home), Some(away)) if home > away => state.teams.home.color.clone(),
+            (Some(home), Some(away)) if away > home => state.teams.away.color.clone(),
+            _ => None,
+        };
+        let mut footer = period.to_string();
+        for part in [if kind == FINAL { "" } else { clock }, court.unwrap_or_default()] {
+            if !part.is_empty() {
+                let _ = write!(footer, "{}{}", if footer.is_empty() { "" } else { " · " }, part);
+            }
+        }
+        let info = &state.match_info;
+        let context = [&info.competition, &info.round, &info.venue]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" · ");
+        Self {
+            kind,
+            title: title(kind),
+            text,
+            home_name,
+            away_name,
+            home_score: state.home_score.trim().to_string(),
+            away_score: state.away_score.trim().to_string(),
+            color,
+            footer,
+            context,
+        }
+    }
+
+    /// The JSON body posted to a `service` webhook.
+    pub fn payload(&self, service: Service) -> Value {
+        let score = |score: &str| if score.is_empty() { "-".to_string() } else { score.to_string() };
+        match service {
+            Service::Slack => {
+                // Slack reads &, < and > in mrkdwn as markup
+                let escape = |text: &str| text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+                let mut context = vec![json!({ "type": "mrkdwn", "text": escape(&self.footer) })];
+                if !self.context.is_empty() {
+                    context.push(json!({ "type": "mrkdwn", "text": escape(&self.context) }));
+                }
+                json!({
+                    "text": escape(&self.text),
+                    "blocks": [
+                        { "type": "header", "text": { "type": "plain_text", "text": self.title } },
+                        { "type": "section", "text": { "type": "mrkdwn", "text": escape(&self.text) } },
+                        {
+                            "type": "section",
+                            "fields": [
+                                { "type": "mrkdwn", "text": format!("*{}*\n{}", escape(&self.home_name), score(&self.home_score)) },
+                                { "type": "mrkdwn", "text": format!("*{}*\n{}", escape(&self.away_name), score(&self.away_score)) },
+                            ],
+                        },
+                        { "type": "context", "elements": context },
+                    ],
+                })
+            }
+            Service::Discord => {
+                let mut embed = json!({
+                    "title": self.title,
+                    "description": self.text,
+                    "fields": [
+                        { "name": self.home_name, "value": score(&self.home_score), "inline": true },
+                        { "name": self.away_name, "value": score(&self.away_score), "inline": true },
+                    ],
+                    "footer": { "text": self.footer },
+                });
+                if !self.context.is_empty() {
+                    embed["author"] = json!({ "name": self.context });
+                }
+                if let Some(color) = self.color.as_deref().and_then(|color| u32::from_str_radix(color.trim_start_matches('#'), 16).ok()) {
+                    embed["color"] = json!(color);
+                }
+                json!({ "username": "Scoreboard", "embeds": [embed] })
+            }
+        }
+    }
+}
+
+/// `PeriodEnded` -> `Period ended`.
+fn title(kind: &str) -> String {
+    let mut title = String::with_capacity(kind.len() + 4);
+    for (i, c) in kind.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            title.push(' ');
+            title.push(c.to_ascii_lowercase());
+        } else {
+            title.push(c);
+        }
+    }
+    title
+}
+
+/// The notifications of one update: one per enabled event, and [`FINAL`] when the operator
+/// set the `Final` period.
+fn messages(events: &[GameEvent], state: &GameState, config: &NotifyConfig, court: Option<&str>, templates: &Templates) -> Vec<Message> {
+    let enabled = |kind: &str| config.events.contains(&kind);
+    let mut messages: Vec<Message> = events
+        .iter()
+        .filter(|event| enabled(event.kind()))
+        .map(|event| Message::new(event.kind(), Some(event), state, court, templates))
+        .collect();
+    let over = events.iter().any(|event| matches!(event, GameEvent::PeriodChange { to, .. } if to.trim() == FINAL_PERIOD));
+    if over && enabled(FINAL) {
+        messages.push(Message::new(FINAL, None, state, court, templates));
+    }
+    messages
+}
+
+/// Post `body` to `url`, retrying server errors and unanswered requests with backoff.
+fn deliver(service: Service, url: &HttpUrl, body: &[u8], metrics: &Metrics) {
+    let mut delay = RETRY_DELAY;
+    for attempt in 1..=ATTEMPTS {
+        let failure = match http_client::post(url, "application/json", &[], body, TIMEOUT) {
+            Ok(status) if (200..300).contains(&status) => {
+                metrics.notifications_sent.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            // Refused as it is (bad URL, bad payload): sending it again won't help
+            Ok(status) if status != 429 && (400..500).contains(&status) => {
+                error!("{} webhook {}{} refused the notification with {}", service.name(), url.host, url.path, status);
+                break;
+            }
+            Ok(status) => format!("answered {}", status),
+            Err(e) => e.to_string(),
+        };
+        if attempt == ATTEMPTS {
+            error!("{} webhook {}{} failed {} times, last {}: notification dropped", service.name(), url.host, url.path, ATTEMPTS, failure);
+            break;
+        }
+        warn!("{} webhook {}{} {}, retrying in {:?}", service.name(), url.host, url.path, failure, delay);
+        metrics.notification_retries.fetch_add(1, Ordering::Relaxed);
+        thread::sleep(delay);
+        delay *= 2;
+    }
+    metrics.notifications_failed.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A sender thread per webhook, posting the messages queued for it no closer together than
+/// `min_interval`.
+fn start_target(target: &NotifyTarget, min_interval: Duration, metrics: Arc<Metrics>) -> mpsc::SyncSender<Value> {
+    let (tx, rx) = mpsc::sync_channel::<Value>(QUEUE_CAPACITY);
+    let (service, url) = (target.service, target.url.clone());
+    info!("Sending notifications to the {} webhook {}{}", service.name(), url.host, url.path);
+    thread::spawn(move || {
+        let mut last_sent: Option<Instant> = None;
+        for payload in rx {
+            if let Some(wait) = last_sent.map(|at| min_interval.saturating_sub(at.elapsed())) {
+                thread::sleep(wait);
+            }
+            let body = serde_json::to_vec(&payload).unwrap_or_default();
+            deliver(service, &url, &body, &metrics);
+            last_sent = Some(Instant::now());
+        }
+    });
+    tx
+}
+
+/// Post a chat message to the Slack and Discord webhooks of `config` for each enabled event
+/// (`--slack-webhook`, `--discord-webhook`), naming `court` in the footer.
+///
+/// Messages to a channel are at least `config.min_interval` apart; beyond a small queue they
+/// are dropped and counted, so a burst of events can't flood it. Clock ticks are never sent.
+pub fn start(config: &NotifyConfig, court: Option<&str>, shared: &SharedState) {
+    let templates = Templates::new(&config.templates);
+    let senders: Vec<(Service, mpsc::SyncSender<Value>)> = config
+        .targets
+        .iter()
+        .map(|target| (target.service, start_target(target, config.min_interval, Arc::clone(&shared.metrics))))
+        .collect();
+    let metrics = Arc::clone(&shared.metrics);
+    let (config, court) = (config.clone(), court.map(str::to_string));
+    shared.add_listener(Duration::ZERO, move |update| {
+        for message in messages(&update.events, &update.state, &config, court.as_deref(), &templates) {
+            for (service, sender) in &senders {
+                match sender.try_send(message.payload(*service)) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(_)) => {
+                        metrics.notifications_dropped.fetch_add(1, Ordering::Relaxed);
+                        warn!("{} notification queue full: {} dropped", service.name(), message.kind);
+                    }
+                    Err(TrySendError::Disconnected(_)) => {}
+                }
+            }
+        }
+    });
+}
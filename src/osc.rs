@@ -0,0 +1,217 @@
+use crate::{
+    events::{GameEvent, Side},
+    state::{SharedState, Update},
+};
+use std::{
+    collections::HashMap,
+    fs, io,
+    net::{SocketAddr, UdpSocket},
+    path::Path,
+    time::Duration,
+};
+use tracing::{info, warn};
+
+/// Keys of the address map: values sent with their new value, then triggers sent without
+/// arguments. The default address of `home.score` is `/scoreboard/home/score`.
+pub const KEYS: &[&str] = &[
+    "home.score",
+    "away.score",
+    "home.fouls",
+    "away.fouls",
+    "home.timeouts",
+    "away.timeouts",
+    "clock",
+    "shot_clock",
+    "period",
+    "event.home_score",
+    "event.away_score",
+    "event.home_timeout",
+    "event.away_timeout",
+    "event.period_end",
+    "event.horn",
+    "event.clock_start",
+    "event.clock_stop",
+];
+
+/// An OSC argument.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscArg {
+    /// `i`: 32-bit big-endian two's complement.
+    Int(i32),
+    /// `f`: 32-bit big-endian IEEE 754.
+    Float(f32),
+    /// `s`: bytes, a null terminator, then nulls up to a multiple of 4 bytes.
+    Str(String),
+}
+
+impl OscArg {
+    fn type_tag(&self) -> char {
+        match self {
+            OscArg::Int(_) => 'i',
+            OscArg::Float(_) => 'f',
+            OscArg::Str(_) => 's',
+        }
+    }
+}
+
+/// Append an OSC string: the bytes, at least one null, padded to a multiple of 4 bytes.
+fn put_str(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(s.as_bytes());
+    let padding = 4 - s.len() % 4;
+    buf.resize(buf.len() + padding, 0);
+}
+
+/// Encode an OSC 1.0 message: the address pattern, the type tag string, then the arguments.
+pub fn encode(address: &str, args: &[OscArg]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(address.len() + 8 + args.len() * 8);
+    put_str(&mut packet, address);
+    let tags: String = std::iter::once(',').chain(args.iter().map(OscArg::type_tag)).collect();
+    put_str(&mut packet, &tags);
+    for arg in args {
+        match arg {
+            OscArg::Int(value) => packet.extend_from_slice(&value.to_be_bytes()),
+            OscArg::Float(value) => packet.extend_from_slice(&value.to_be_bytes()),
+            OscArg::Str(value) => put_str(&mut packet, value),
+        }
+    }
+    packet
+}
+
+/// OSC address of each [`KEYS`] entry; a key mapped to `off` sends nothing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressMap {
+    addresses: HashMap<&'static str, String>,
+}
+
+impl Default for AddressMap {
+    fn default() -> Self {
+        let addresses = KEYS
+            .iter()
+            .map(|&key| (key, format!("/scoreboard/{}", key.replace('.', "/"))))
+            .collect();
+        Self { addresses }
+    }
+}
+
+impl AddressMap {
+    /// Read a map file (`--osc-map`): `key /address` or `key off` per line, `#` comments.
+    /// Keys not in the file keep their default address.
+    pub fn parse(text: &str, source: &str) -> Result<Self, String> {
+        let mut map = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let at = || format!("{} line {}", source, number + 1);
+            let (key, address) = line
+                .split_once(char::is_whitespace)
+                .map(|(key, address)| (key, address.trim()))
+                .ok_or_else(|| format!("Expected a key and an OSC address in {}", at()))?;
+            let key = KEYS
+                .iter()
+                .find(|&&known| known == key)
+                .ok_or_else(|| format!("Unknown OSC key {} in {} (known: {})", key, at(), KEYS.join(", ")))?;
+            if address == "off" {
+                map.addresses.remove(key);
+            } else if address.starts_with('/') && !address.contains(char::is_whitespace) {
+                map.addresses.insert(key, address.to_string());
+            } else {
+                return Err(format!("Invalid OSC address {:?} in {}: expected /path or off", address, at()));
+            }
+        }
+        Ok(map)
+    }
+
+    /// The address of `key`, `None` when it is off.
+    pub fn address(&self, key: &str) -> Option<&str> {
+        self.addresses.get(key).map(String::as_str)
+    }
+
+    /// The encoded message for `key`, if it is mapped.
+    fn message(&self, key: &str, args: &[OscArg]) -> Option<Vec<u8>> {
+        self.address(key).map(|address| encode(address, args))
+    }
+}
+
+fn side_key(side: Side, name: &str) -> String {
+    format!("{}.{}", side.as_str(), name)
+}
+
+/// The messages for an update, in the order of its events.
+fn messages(update: &Update, map: &AddressMap) -> Vec<Vec<u8>> {
+    let count = |value: &Option<u32>| value.map(|value| OscArg::Int(value as i32));
+    let mut messages = Vec::new();
+    let mut push = |key: &str, args: &[OscArg]| messages.extend(map.message(key, args));
+    for event in &update.events {
+        match event {
+            GameEvent::ScoreChange { side, to, delta, .. } => {
+                if let Some(to) = count(to) {
+                    push(&side_key(*side, "score"), &[to]);
+                }
+                if *delta > 0 {
+                    push(&format!("event.{}_score", side.as_str()), &[]);
+                }
+            }
+            GameEvent::FoulChange { side, to, .. } => {
+                if let Some(to) = count(to) {
+                    push(&side_key(*side, "fouls"), &[to]);
+                }
+            }
+            GameEvent::TimeoutChange { side, to, .. } => {
+                if let Some(to) = count(to) {
+                    push(&side_key(*side, "timeouts"), &[to]);
+                }
+            }
+            GameEvent::TimeoutCalled { side } => push(&format!("event.{}_timeout", side.as_str()), &[]),
+            GameEvent::ClockChange { time } => push("clock", &[OscArg::Str(time.trim().to_string())]),
+            GameEvent::ShotClockChange { shot_clock } => push("shot_clock", &[OscArg::Str(shot_clock.trim().to_string())]),
+            GameEvent::PeriodChange { to, .. } => push("period", &[OscArg::Str(to.trim().to_string())]),
+            GameEvent::PeriodEnded { .. } => push("event.period_end", &[]),
+            GameEvent::Horn => push("event.horn", &[]),
+            GameEvent::GameStateChange { to, .. } if to == "running" => push("event.clock_start", &[]),
+            GameEvent::GameStateChange { .. } => push("event.clock_stop", &[]),
+            _ => {}
+        }
+    }
+    messages
+}
+
+/// Send OSC messages over UDP to `targets` for every change (`--osc`), with the addresses
+/// of `map_file` (`--osc-map`) or the defaults.
+pub fn start(targets: &[SocketAddr], map_file: Option<&Path>, shared: &SharedState) -> io::Result<()> {
+    let map = match map_file {
+        Some(path) => {
+            let text = fs::read_to_string(path)?;
+            AddressMap::parse(&text, &path.display().to_string()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        }
+        None => AddressMap::default(),
+    };
+    let mut sockets = Vec::with_capacity(targets.len());
+    for target in targets {
+        let local: SocketAddr = if target.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+        sockets.push((*target, UdpSocket::bind(local)?, false));
+        info!("Sending OSC to {}", target);
+    }
+
+    shared.add_listener(Duration::ZERO, move |update| {
+        for message in messages(update, &map) {
+            for (target, socket, failing) in &mut sockets {
+                match socket.send_to(&message, *target) {
+                    Ok(_) if *failing => {
+                        info!("Sending OSC to {} again", target);
+                        *failing = false;
+                    }
+                    Ok(_) => {}
+                    // Logged once per failure streak, not once per message
+                    Err(e) if !*failing => {
+                        warn!("Failed to send OSC to {}: {}", target, e);
+                        *failing = true;
+                    }
+                    Err(_) => {}
+                }
+            }
+        }
+    });
+    Ok(())
+}
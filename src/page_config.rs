@@ -0,0 +1,74 @@
+use crate::locale::Lang;
+use serde::Serialize;
+use std::time::Duration;
+
+/// Default interval of the overlays' `/api/game` polling while the WebSocket is down.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// Settings the server writes into every HTML page it serves, read by `common.js` as
+/// `window.SCOREBOARD_CONFIG`.
+///
+/// They go in a JSON data block (`<script type="application/json">`) rather than a script:
+/// the overlay pages' Content-Security-Policy only runs script files from the server.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageConfig {
+    /// Path the API and WebSockets are reached under, from `X-Forwarded-Prefix`; empty at the root.
+    pub api_base: String,
+    /// Interval of the `/api/game` polling, in milliseconds.
+    pub poll_ms: u64,
+    /// Whether the pages open the WebSocket; they only poll otherwise (`--no-websocket`).
+    pub ws_enabled: bool,
+    /// Display language of the request, as `/api/locale` would pick it.
+    pub lang: Lang,
+    /// Whether the page shows the away team on the home side, see [`swap_sides`].
+    pub swap_sides: bool,
+}
+
+impl PageConfig {
+    /// `html` with the config as a JSON block just before `</head>`, or `None` when the page
+    /// has no `</head>`.
+    pub fn inject(&self, html: &[u8]) -> Option<Vec<u8>> {
+        let end = html.windows(7).position(|window| window.eq_ignore_ascii_case(b"</head>"))?;
+        // `<` escaped so no value can close the element
+        let json = serde_json::to_string(self).ok()?.replace('<', "\\u003c");
+        let mut injected = Vec::with_capacity(html.len() + json.len() + 80);
+        injected.extend_from_slice(&html[..end]);
+        injected.extend_from_slice(b"<script type=\"application/json\" id=\"scoreboard-config\">");
+        injected.extend_from_slice(json.as_bytes());
+        injected.extend_from_slice(b"</script>\n");
+        injected.extend_from_slice(&html[end..]);
+        Some(injected)
+    }
+}
+
+/// API base from an `X-Forwarded-Prefix` header value: the path without its trailing `/`,
+/// or empty when the header isn't a plain absolute path (`/scoreboard`, `/venue/court-1/`).
+pub fn forwarded_prefix(header: &str) -> String {
+    let prefix = header.trim().trim_end_matches('/');
+    let plain = prefix.starts_with('/')
+        && !prefix.starts_with("//")
+        && prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '-' | '_' | '.' | '~'))
+        && prefix.split('/').all(|segment| segment != "." && segment != "..");
+    if plain {
+        prefix.to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// Whether an overlay page shows the away team on the home side: the page's `?swap=` value
+/// (`1`/`true` or `0`/`false`) wins over `--swap-sides` (`default`); other values are ignored.
+///
+/// The two never add up to swapping back: both set to swap is swapped once. The swap is a
+/// display choice applied where a page fills its slots (`swapSides` in `common.js`); the
+/// published state and the API always keep the console's real sides.
+pub fn swap_sides(query: Option<&str>, default: bool) -> bool {
+    match query {
+        Some("1" | "true") => true,
+        Some("0" | "false") => false,
+        _ => default,
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{
+    event_log::LoggedEvent,
+    game_log::GameLogRow,
+    state::{unix_millis, SharedState},
+    summary::SummaryTracker,
+    GameState,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tracing::{error, info, warn};
+
+/// Shortest time between two saves while only the clock changes; other changes are saved at once.
+const SAVE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What a restarted server needs to carry on with the game, as saved to `--state-file`.
+///
+/// The states kept for `/api/history` are not saved: they start again empty.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    /// When it was saved, in Unix milliseconds.
+    pub saved_at_ms: u64,
+    pub state: GameState,
+    /// Version of `state`; versions go on from it so polling clients see the next states as new.
+    pub version: u64,
+    /// Id of the last overlay event attached to a state.
+    pub last_overlay_event_id: u64,
+    /// The event log, oldest first, and the id of the last event logged.
+    pub events: Vec<LoggedEvent>,
+    pub last_event_id: u64,
+    /// The game log behind `/api/game-log.csv`, oldest first.
+    pub game_log: Vec<GameLogRow>,
+    /// Totals of the game being played and the summary of the last finished one.
+    pub summary: SummaryTracker,
+}
+
+/// Write the state of `shared` to `path`, replacing it atomically so a crash never leaves half a file.
+pub fn save(path: &Path, shared: &SharedState) -> io::Result<()> {
+    let json = serde_json::to_vec(&shared.snapshot()).map_err(io::Error::other)?;
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let mut file = File::create(&tmp)?;
+    file.write_all(&json)?;
+    // On disk before the rename, or a power cut could leave an empty file behind it
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+/// Read the state saved to `path`; `None` when there is no file or it was saved more than
+/// `max_age` ago.
+pub fn load(path: &Path, max_age: Duration) -> io::Result<Option<Snapshot>> {
+    let json = match fs::read(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let snapshot: Snapshot = serde_json::from_slice(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let age = Duration::from_millis(unix_millis().saturating_sub(snapshot.saved_at_ms));
+    if age > max_age {
+        info!("Not restoring {}: saved {}s ago (--state-max-age {}s)", path.display(), age.as_secs(), max_age.as_secs());
+        return Ok(None);
+    }
+    Ok(Some(snapshot))
+}
+
+/// Restore the state saved to `path` into `shared`, unless it is missing, unreadable or older
+/// than `max_age`. The state is flagged stale until the next console frame.
+pub fn restore(path: &Path, max_age: Duration, shared: &SharedState) {
+    match load(path, max_age) {
+        Ok(Some(snapshot)) => {
+            info!(
+                "Restored the state saved to {} ({} events, version {})",
+                path.display(),
+                snapshot.events.len(),
+                snapshot.version
+            );
+            shared.restore(snapshot);
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Failed to restore the state from {}: {}", path.display(), e),
+    }
+}
+
+/// Saves the state once more when the listener is dropped at shutdown, so the last clock
+/// (which with `--dedupe ignore-clock` may not have been published) is kept too.
+struct Saver {
+    path: PathBuf,
+    shared: Arc<SharedState>,
+    failing: bool,
+}
+
+impl Saver {
+    fn save(&mut self) {
+        match save(&self.path, &self.shared) {
+            Ok(()) if self.failing => {
+                info!("Saving the state to {} again", self.path.display());
+                self.failing = false;
+            }
+            Ok(()) => {}
+            // Logged once per failure streak, not once per save
+            Err(e) if !self.failing => {
+                error!("Failed to save the state to {}: {}", self.path.display(), e);
+                self.failing = true;
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+impl Drop for Saver {
+    fn drop(&mut self) {
+        self.save();
+    }
+}
+
+/// Save the state to `path` on every change (`--state-file`), at most every two seconds
+/// while only the clock runs, and once more at shutdown.
+pub fn start(path: &Path, shared: &Arc<SharedState>) {
+    info!("Saving the state to {}", path.display());
+    let mut saver = Saver {
+        path: path.to_path_buf(),
+        shared: Arc::clone(shared),
+        failing: false,
+    };
+    shared.add_listener(SAVE_INTERVAL, move |_| saver.save());
+}
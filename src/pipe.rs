@@ -0,0 +1,94 @@
+#[cfg(windows)]
+use std::fs::{File, OpenOptions};
+use std::{
+    io::{self, Read},
+    thread,
+    time::Duration,
+};
+use tracing::{info, warn};
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Win32 `ERROR_BROKEN_PIPE`: the server end of the pipe was closed.
+const ERROR_BROKEN_PIPE: i32 = 109;
+/// Win32 `ERROR_PIPE_NOT_CONNECTED`: no process is on the other end of the pipe.
+const ERROR_PIPE_NOT_CONNECTED: i32 = 233;
+
+/// Full path of a named pipe, e.g. `scoreboard` -> `\\.\pipe\scoreboard`.
+pub fn pipe_path(name: &str) -> String {
+    format!(r"\\.\pipe\{}", name)
+}
+
+/// Open a reader on the named pipe `name` that reconnects whenever the pipe breaks.
+#[cfg(windows)]
+pub fn open(name: &str) -> ReconnectingReader<File, impl FnMut() -> io::Result<File>> {
+    let path = pipe_path(name);
+    ReconnectingReader::new(path.clone(), move || OpenOptions::new().read(true).open(&path))
+}
+
+/// A reader that (re)opens its source on demand.
+///
+/// States: disconnected -> `open` succeeds -> connected -> EOF or broken pipe ->
+/// disconnected. Failed opens are retried with exponential backoff, so reads
+/// block until data is available again instead of returning EOF.
+pub struct ReconnectingReader<R, F> {
+    label: String,
+    open: F,
+    current: Option<R>,
+    backoff: Duration,
+}
+
+impl<R: Read, F: FnMut() -> io::Result<R>> ReconnectingReader<R, F> {
+    /// A reader of what `open` returns, called on the first read and after each disconnect;
+    /// `label` names the source in the log.
+    pub fn new(label: String, open: F) -> Self {
+        Self {
+            label,
+            open,
+            current: None,
+            backoff: INITIAL_BACKOFF,
+        }
+    }
+
+    fn is_disconnect(e: &io::Error) -> bool {
+        e.kind() == io::ErrorKind::BrokenPipe
+            || matches!(e.raw_os_error(), Some(ERROR_BROKEN_PIPE | ERROR_PIPE_NOT_CONNECTED))
+    }
+}
+
+impl<R: Read, F: FnMut() -> io::Result<R>> Read for ReconnectingReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let Some(reader) = self.current.as_mut() else {
+                match (self.open)() {
+                    Ok(reader) => {
+                        info!("Connected to {}", self.label);
+                        self.current = Some(reader);
+                        self.backoff = INITIAL_BACKOFF;
+                    }
+                    Err(e) => {
+                        warn!("Failed to open {}: {} (retrying in {:?})", self.label, e, self.backoff);
+                        thread::sleep(self.backoff);
+                        self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+                    }
+                }
+                continue;
+            };
+
+            match reader.read(buf) {
+                Ok(0) => {
+                    info!("{} closed, reconnecting", self.label);
+                    self.current = None;
+                }
+                Ok(n) => return Ok(n),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) if Self::is_disconnect(&e) => {
+                    info!("{} broke ({}), reconnecting", self.label, e);
+                    self.current = None;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
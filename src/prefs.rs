@@ -0,0 +1,50 @@
+use crate::{
+    locale::Lang,
+    themes::{self, Theme},
+};
+
+/// Cookie written by the settings widget of the overlay and admin pages.
+pub const PREFS_COOKIE: &str = "scoreboard_prefs";
+
+/// Largest accepted scale; anything bigger is a typo, not a preference.
+const MAX_SCALE: f64 = 5.0;
+
+/// Display defaults a browser chose in the settings widget (`scoreboard_prefs` cookie).
+///
+/// They replace the server defaults (`--theme`, `--lang`, scale 1) for that browser; URL
+/// parameters still win over them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Prefs {
+    pub theme: Option<&'static Theme>,
+    pub scale: Option<f64>,
+    pub lang: Option<Lang>,
+}
+
+impl Prefs {
+    /// Read the preferences from a `Cookie` header value, e.g.
+    /// `scoreboard_prefs=theme=minimal&scale=1.5&lang=pt; other=1`.
+    ///
+    /// Browsers send whatever was stored, so unknown keys and values that don't parse
+    /// (unknown themes, scales outside 0..5, unsupported languages) are skipped one by one.
+    pub fn from_cookie_header(header: &str) -> Self {
+        let mut prefs = Self::default();
+        let Some(value) = header
+            .split(';')
+            .filter_map(|cookie| cookie.split_once('='))
+            .find(|(name, _)| name.trim() == PREFS_COOKIE)
+            .map(|(_, value)| value.trim().trim_matches('"'))
+        else {
+            return prefs;
+        };
+
+        for (key, value) in value.split('&').filter_map(|pair| pair.split_once('=')) {
+            match key {
+                "theme" => prefs.theme = themes::find(value),
+                "scale" => prefs.scale = value.parse().ok().filter(|scale: &f64| *scale > 0.0 && *scale <= MAX_SCALE),
+                "lang" => prefs.lang = Lang::parse(value),
+                _ => {}
+            }
+        }
+        prefs
+    }
+}
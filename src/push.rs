@@ -0,0 +1,234 @@
+use crate::{
+    config::PushConfig,
+    dto::StateDto,
+    http_client,
+    metrics::Metrics,
+    state::{lock, unix_millis, SharedState},
+};
+use flate2::{write::GzEncoder, Compression};
+use serde_json::{json, Map, Value};
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+    mem,
+    sync::{atomic::Ordering, Arc, Condvar, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// Wait before the first retry, doubled after each failure up to [`MAX_BACKOFF`].
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How long the remote gets to answer.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What hasn't been delivered yet: the latest state only, and every event in order.
+#[derive(Default)]
+struct Pending {
+    /// The newest state, as [`StateDto`] JSON, with its version and time.
+    state: Option<(Value, u64, u64)>,
+    events: VecDeque<Value>,
+    /// Events that didn't fit in the buffer since the last delivery.
+    dropped: u64,
+    /// When the oldest undelivered update was published, in Unix milliseconds.
+    since_ms: Option<u64>,
+    /// The server is shutting down: deliver what is left once, then stop.
+    closed: bool,
+}
+
+/// The buffer between the state listener and the sender thread.
+struct Outbox {
+    pending: Mutex<Pending>,
+    changed: Condvar,
+    capacity: usize,
+}
+
+impl Outbox {
+    /// Add an update: its state replaces any undelivered one, its events are appended and the
+    /// oldest ones dropped beyond the capacity.
+    fn offer(&self, state: Value, version: u64, timestamp_ms: u64, events: impl IntoIterator<Item = Value>, metrics: &Metrics) {
+        let mut pending = lock(&self.pending);
+        pending.state = Some((state, version, timestamp_ms));
+        pending.events.extend(events);
+        while pending.events.len() > self.capacity {
+            pending.events.pop_front();
+            pending.dropped += 1;
+            metrics.push_events_dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        let since = *pending.since_ms.get_or_insert(timestamp_ms);
+        metrics.push_pending_since_ms.store(since, Ordering::Relaxed);
+        self.changed.notify_one();
+    }
+}
+
+/// The listener's end of the outbox. Dropped when the server shuts down: the sender thread
+/// gets one last try at delivering before the listener ends.
+struct Handle {
+    outbox: Arc<Outbox>,
+    sender: Option<JoinHandle<()>>,
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        lock(&self.outbox.pending).closed = true;
+        self.outbox.changed.notify_one();
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.join();
+        }
+    }
+}
+
+/// The top-level fields of `state` that differ from `previous`.
+pub fn diff(previous: &Value, state: &Value) -> Value {
+    let (Some(previous), Some(state)) = (previous.as_object(), state.as_object()) else {
+        return state.clone();
+    };
+    let changed: Map<String, Value> = state
+        .iter()
+        .filter(|(key, value)| previous.get(key.as_str()) != Some(*value))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    Value::Object(changed)
+}
+
+/// Gzip `body`.
+pub fn gzip(body: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::with_capacity(body.len() / 4), Compression::default());
+    encoder.write_all(body)?;
+    encoder.finish()
+}
+
+/// POST the state to `config.url` as it changes (`--push-url`), for a remote page that can't
+/// reach into the venue network.
+///
+/// Updates are coalesced to one request per `config.interval`. Each request carries the state,
+/// or with `config.diff` only its fields that changed since the last delivered one, and the
+/// events since the last delivery, gzipped, with `config.token` as a bearer token. While the
+/// remote can't be reached, requests are retried with backoff and updates wait in the outbox:
+/// the newest state replaces older ones, events are kept up to `config.buffer`.
+pub fn start(config: &PushConfig, shared: &Arc<SharedState>) {
+    let outbox = Arc::new(Outbox {
+        pending: Mutex::new(Pending::default()),
+        changed: Condvar::new(),
+        capacity: config.buffer,
+    });
+    info!("Pushing the state to {}{} at most every {:?}", config.url.host, config.url.path, config.interval);
+    let (sender_config, sender_outbox, metrics) = (config.clone(), Arc::clone(&outbox), Arc::clone(&shared.metrics));
+    let sender = thread::spawn(move || run_sender(&sender_config, &sender_outbox, &metrics));
+
+    let handle = Handle { outbox, sender: Some(sender) };
+    let state = Arc::clone(shared);
+    shared.add_listener(Duration::ZERO, move |update| {
+        let dto = StateDto::localized(&update.state, state.settings().lang);
+        let events = update.logged_events.iter().map(|event| serde_json::to_value(event).unwrap_or_default());
+        handle
+            .outbox
+            .offer(serde_json::to_value(&dto).unwrap_or_default(), update.version, update.timestamp_ms, events, &state.metrics);
+    });
+}
+
+fn run_sender(config: &PushConfig, outbox: &Outbox, metrics: &Metrics) {
+    let url = &config.url;
+    let authorization = config.token.as_ref().map(|token| format!("Bearer {}", token));
+    let mut headers = vec![("Content-Encoding", "gzip")];
+    if let Some(authorization) = &authorization {
+        headers.push(("Authorization", authorization));
+    }
+    // The last state the remote has, for diffs
+    let mut delivered: Option<Value> = None;
+    let mut last_sent: Option<Instant> = None;
+    let mut backoff = INITIAL_BACKOFF;
+    let mut failing = false;
+
+    loop {
+        // Wait for an update, then let others coalesce with it until the interval is up
+        let mut pending = lock(&outbox.pending);
+        while pending.state.is_none() && !pending.closed {
+            pending = outbox.changed.wait(pending).unwrap_or_else(|e| e.into_inner());
+        }
+        let closing = pending.closed;
+        if let Some(wait) = last_sent.map(|at| config.interval.saturating_sub(at.elapsed())).filter(|wait| !wait.is_zero() && !closing) {
+            drop(pending);
+            thread::sleep(wait);
+            pending = lock(&outbox.pending);
+        }
+        let Some((state, version, timestamp_ms)) = pending.state.take() else {
+            return;
+        };
+        let events: Vec<Value> = pending.events.drain(..).collect();
+        let dropped = mem::take(&mut pending.dropped);
+        let since_ms = pending.since_ms.take();
+        drop(pending);
+
+        let (body_state, full) = match (&delivered, config.diff) {
+            (Some(previous), true) => (diff(previous, &state), false),
+            _ => (state.clone(), true),
+        };
+        let mut body = json!({
+            "version": version,
+            "timestampMs": timestamp_ms,
+            "full": full,
+            "state": body_state,
+            "events": events,
+        });
+        if dropped > 0 {
+            body["eventsDropped"] = json!(dropped);
+        }
+        let result = gzip(&serde_json::to_vec(&body).unwrap_or_default())
+            .and_then(|gzipped| http_client::post(url, "application/json", &headers, &gzipped, TIMEOUT));
+        last_sent = Some(Instant::now());
+
+        let failure = match result {
+            Ok(status) if (200..300).contains(&status) => None,
+            Ok(status) => Some(format!("answered {}", status)),
+            Err(e) => Some(e.to_string()),
+        };
+        let Some(failure) = failure else {
+            if failing {
+                info!("Pushing to {}{} again", url.host, url.path);
+                failing = false;
+            }
+            backoff = INITIAL_BACKOFF;
+            delivered = Some(state);
+            metrics.push_sent.fetch_add(1, Ordering::Relaxed);
+            if let Some(since_ms) = since_ms {
+                metrics.push_last_lag_ms.store(unix_millis().saturating_sub(since_ms), Ordering::Relaxed);
+            }
+            let pending = lock(&outbox.pending);
+            metrics.push_pending_since_ms.store(pending.since_ms.unwrap_or(0), Ordering::Relaxed);
+            if closing && pending.state.is_none() {
+                return;
+            }
+            continue;
+        };
+
+        metrics.push_failures.fetch_add(1, Ordering::Relaxed);
+        // Logged once per failure streak, not once per retry
+        if !failing {
+            warn!("Failed to push to {}{}: {} (retrying with backoff)", url.host, url.path, failure);
+            failing = true;
+        }
+        // Put the update back, behind nothing: a newer state wins, older events go first
+        let mut pending = lock(&outbox.pending);
+        if pending.state.is_none() {
+            pending.state = Some((state, version, timestamp_ms));
+        }
+        let newer = mem::take(&mut pending.events);
+        pending.events = events.into_iter().chain(newer).collect();
+        while pending.events.len() > outbox.capacity {
+            pending.events.pop_front();
+            pending.dropped += 1;
+            metrics.push_events_dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        pending.dropped += dropped;
+        pending.since_ms = since_ms.or(pending.since_ms);
+        if pending.closed {
+            warn!("Shutting down with updates not pushed to {}{}", url.host, url.path);
+            return;
+        }
+        let _ = outbox.changed.wait_timeout_while(pending, backoff, |pending| !pending.closed);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
@@ -0,0 +1,297 @@
+use crate::GameState;
+use std::sync::Mutex;
+use warp::hyper::body::Bytes;
+
+/// Size of `/render.png` when the query doesn't give one.
+pub const DEFAULT_WIDTH: u32 = 800;
+pub const DEFAULT_HEIGHT: u32 = 200;
+/// Accepted sizes, in pixels; larger images would cost too much memory and CPU per state.
+const WIDTH_RANGE: std::ops::RangeInclusive<u32> = 100..=1920;
+const HEIGHT_RANGE: std::ops::RangeInclusive<u32> = 40..=1080;
+/// Sizes kept per state version; switchers poll one or two.
+const MAX_CACHED_SIZES: usize = 4;
+
+/// Why no snapshot could be made.
+#[derive(Debug)]
+pub enum RenderError {
+    /// The requested size is out of range (400).
+    BadSize(String),
+    /// Built without the `raster` feature (501).
+    #[cfg(not(feature = "raster"))]
+    Unsupported,
+    /// Rendering or encoding failed (500).
+    #[cfg(feature = "raster")]
+    Failed(String),
+}
+
+/// PNG snapshots of the scoreboard for devices that can't render HTML (`GET /render.png`).
+///
+/// Images are cached by state version and size, so polling an unchanged state only
+/// costs a lookup.
+#[derive(Debug, Default)]
+pub struct Snapshots {
+    cache: Mutex<Cache>,
+}
+
+/// Images of one state version.
+#[derive(Debug, Default)]
+struct Cache {
+    version: u64,
+    /// PNGs by width and height, oldest first.
+    images: Vec<((u32, u32), Bytes)>,
+}
+
+impl Snapshots {
+    /// `state` (at `version`) as a `width` x `height` PNG.
+    pub fn png(&self, state: &GameState, version: u64, width: u32, height: u32) -> Result<Bytes, RenderError> {
+        if !WIDTH_RANGE.contains(&width) || !HEIGHT_RANGE.contains(&height) {
+            return Err(RenderError::BadSize(format!(
+                "Size {}x{} out of range: width {}-{}, height {}-{}",
+                width,
+                height,
+                WIDTH_RANGE.start(),
+                WIDTH_RANGE.end(),
+                HEIGHT_RANGE.start(),
+                HEIGHT_RANGE.end()
+            )));
+        }
+
+        {
+            let cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+            let cached = cache.images.iter().find(|(size, _)| *size == (width, height));
+            if let (true, Some((_, png))) = (cache.version == version, cached) {
+                return Ok(png.clone());
+            }
+        }
+
+        // Rendered without the lock; two requests for a new state may both render it
+        let png = Bytes::from(render(state, width, height)?);
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if cache.version != version {
+            *cache = Cache {
+                version,
+                images: Vec::new(),
+            };
+        }
+        if cache.images.len() >= MAX_CACHED_SIZES {
+            cache.images.remove(0);
+        }
+        cache.images.push(((width, height), png.clone()));
+        Ok(png)
+    }
+}
+
+#[cfg(not(feature = "raster"))]
+fn render(_state: &GameState, _width: u32, _height: u32) -> Result<Vec<u8>, RenderError> {
+    Err(RenderError::Unsupported)
+}
+
+#[cfg(feature = "raster")]
+fn render(state: &GameState, width: u32, height: u32) -> Result<Vec<u8>, RenderError> {
+    draw::scoreboard(state, width, height).map_err(RenderError::Failed)
+}
+
+/// The scoreboard drawn with a built-in pixel font, so no system fonts are needed.
+#[cfg(feature = "raster")]
+mod draw {
+    use crate::{numeric_value, teams::TeamInfo, GameState};
+    use tiny_skia::{Color, Paint, Pixmap, Rect, Transform};
+
+    const BACKGROUND: (u8, u8, u8) = (0x14, 0x18, 0x22);
+    const PANEL: (u8, u8, u8) = (0x1f, 0x25, 0x33);
+    const TEXT: (u8, u8, u8) = (0xff, 0xff, 0xff);
+    const CLOCK: (u8, u8, u8) = (0xff, 0xc1, 0x07);
+    const DIMMED: (u8, u8, u8) = (0x80, 0x80, 0x80);
+
+    const GLYPH_WIDTH: u32 = 5;
+    const GLYPH_HEIGHT: u32 = 7;
+
+    /// Rows of a character, top to bottom, 5 pixels wide (bit 4 is the leftmost); blank if unknown.
+    fn glyph(c: char) -> [u8; 7] {
+        match c {
+            '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+            '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+            '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+            '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+            '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+            '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+            '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+            '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+            '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+            '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+            'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+            'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+            'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+            'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+            'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+            'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+            'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+            'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+            'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+            'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+            'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+            'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+            'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+            'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+            'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+            'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+            'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+            'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+            'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+            'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+            'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+            'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+            'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+            'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+            'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+            'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+            ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+            '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+            '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+            '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+            _ => [0; 7],
+        }
+    }
+
+    /// Uppercase ASCII for the pixel font: accents are dropped (`SÃO` -> `SAO`).
+    fn font_text(text: &str) -> String {
+        text.trim()
+            .to_uppercase()
+            .chars()
+            .map(|c| match c {
+                'À' | 'Á' | 'Â' | 'Ã' | 'Ä' => 'A',
+                'Ç' => 'C',
+                'È' | 'É' | 'Ê' | 'Ë' => 'E',
+                'Ì' | 'Í' | 'Î' | 'Ï' => 'I',
+                'Ñ' => 'N',
+                'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' => 'O',
+                'Ù' | 'Ú' | 'Û' | 'Ü' => 'U',
+                c => c,
+            })
+            .collect()
+    }
+
+    fn paint(rgb: (u8, u8, u8)) -> Paint<'static> {
+        let mut paint = Paint::default();
+        paint.set_color_rgba8(rgb.0, rgb.1, rgb.2, 0xff);
+        paint.anti_alias = false;
+        paint
+    }
+
+    fn fill(pixmap: &mut Pixmap, x: f32, y: f32, w: f32, h: f32, rgb: (u8, u8, u8)) {
+        if let Some(rect) = Rect::from_xywh(x, y, w, h) {
+            pixmap.fill_rect(rect, &paint(rgb), Transform::identity(), None);
+        }
+    }
+
+    /// Draw `text` centered in the box, as large as `min_chars` characters would fit,
+    /// with whole-pixel scaling.
+    fn text(pixmap: &mut Pixmap, text: &str, min_chars: u32, (x, y, w, h): (f32, f32, f32, f32), rgb: (u8, u8, u8)) {
+        let text = font_text(text);
+        let chars = text.chars().count() as u32;
+        if chars == 0 {
+            return;
+        }
+        let fit = |chars: u32| (chars * (GLYPH_WIDTH + 1) - 1) as f32;
+        let columns = fit(chars);
+        let scale = (w / fit(chars.max(min_chars))).min(h / GLYPH_HEIGHT as f32).floor().max(1.0);
+        let left = (x + (w - columns * scale) / 2.0).round();
+        let top = (y + (h - GLYPH_HEIGHT as f32 * scale) / 2.0).round();
+
+        for (i, c) in text.chars().enumerate() {
+            let glyph_left = left + (i as u32 * (GLYPH_WIDTH + 1)) as f32 * scale;
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (0x10 >> column) != 0 {
+                        let px = glyph_left + column as f32 * scale;
+                        let py = top + row as f32 * scale;
+                        fill(pixmap, px, py, scale, scale, rgb);
+                    }
+                }
+            }
+        }
+    }
+
+    /// `#RRGGBB` as a color.
+    fn team_color(team: &TeamInfo) -> Option<(u8, u8, u8)> {
+        let hex = team.color.as_deref()?.strip_prefix('#')?;
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        Some(((value >> 16) as u8, (value >> 8) as u8, value as u8))
+    }
+
+    /// Abbreviation shown for a team, as on the score bug: the operator's, else the first
+    /// 3 letters of the name, else `HOM`/`AWA`.
+    fn abbrev(team: &TeamInfo, side: &str) -> String {
+        if !team.abbrev.is_empty() {
+            return team.abbrev.clone();
+        }
+        let name = if team.name.is_empty() { side } else { &team.name };
+        name.chars().filter(|c| c.is_alphanumeric()).take(3).collect()
+    }
+
+    /// `2 Quarter` -> `Q2`, `Overtime` -> `OT`, like the score bug.
+    fn short_period(period_name: &str) -> String {
+        let period_name = period_name.trim();
+        match period_name.strip_suffix(" Quarter") {
+            Some(quarter) => format!("Q{}", quarter),
+            None if period_name == "Overtime" => "OT".to_string(),
+            None if period_name == "Halftime" => "HALF".to_string(),
+            None if period_name.is_empty() => "-".to_string(),
+            None => period_name.to_string(),
+        }
+    }
+
+    fn or_dash(value: &str) -> &str {
+        let value = value.trim();
+        if value.is_empty() {
+            "-"
+        } else {
+            value
+        }
+    }
+
+    /// Team panels left and right, clock and period in the middle; greyed out while stale.
+    pub fn scoreboard(state: &GameState, width: u32, height: u32) -> Result<Vec<u8>, String> {
+        let mut pixmap = Pixmap::new(width, height).ok_or("cannot allocate the image")?;
+        let bg = BACKGROUND;
+        pixmap.fill(Color::from_rgba8(bg.0, bg.1, bg.2, 0xff));
+
+        let (w, h) = (width as f32, height as f32);
+        let pad = (h * 0.08).round();
+        let stripe = (h * 0.06).round().max(2.0);
+        let panel_w = (w * 0.36).round();
+        let (text_rgb, clock_rgb) = if state.stale { (DIMMED, DIMMED) } else { (TEXT, CLOCK) };
+
+        let sides = [
+            ("home", &state.teams.home, &state.home_score, 0.0),
+            ("away", &state.teams.away, &state.away_score, w - panel_w),
+        ];
+        for (side, team, score, x) in sides {
+            fill(&mut pixmap, x, 0.0, panel_w, h, PANEL);
+            let stripe_x = if side == "home" { x } else { x + panel_w - stripe };
+            fill(&mut pixmap, stripe_x, 0.0, stripe, h, team_color(team).unwrap_or(DIMMED));
+
+            // Abbreviation on the outer side, score on the inner side
+            let inner_w = panel_w - stripe - 2.0 * pad;
+            let abbrev_box_w = inner_w * 0.45;
+            let score_box_w = inner_w - abbrev_box_w - pad;
+            let (abbrev_x, score_x) = if side == "home" {
+                (x + stripe + pad, x + stripe + pad + abbrev_box_w + pad)
+            } else {
+                (x + panel_w - stripe - pad - abbrev_box_w, x + pad)
+            };
+            text(&mut pixmap, &abbrev(team, side), 3, (abbrev_x, h * 0.3, abbrev_box_w, h * 0.4), text_rgb);
+            let score = numeric_value(score).map_or_else(|| "-".to_string(), |score| score.to_string());
+            // Scores keep the size of 3 digits, so 8 and 108 look alike
+            text(&mut pixmap, &score, 3, (score_x, pad, score_box_w, h - 2.0 * pad), text_rgb);
+        }
+
+        let center_x = panel_w + pad;
+        let center_w = w - 2.0 * panel_w - 2.0 * pad;
+        let clock = if state.display_clock.is_empty() { &state.time } else { &state.display_clock };
+        text(&mut pixmap, or_dash(clock), 5, (center_x, pad, center_w, h * 0.5), clock_rgb);
+        text(&mut pixmap, &short_period(&state.period_name), 2, (center_x, h * 0.62, center_w, h * 0.26), text_rgb);
+
+        pixmap.encode_png().map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{
+    config::ServerConfig,
+    http_client::HttpUrl,
+    locale::Lang,
+    match_info::MatchInfo,
+    state::{lock, SharedState},
+    teams::Teams,
+    themes::{self, DEFAULT_THEME},
+};
+use serde::Serialize;
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, SystemTime},
+};
+use tracing::{error, info, warn};
+
+/// How often [`watch`] looks at the config file.
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Settings that can change while the server runs, read by the web server and the summary
+/// webhook each time they are used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiveSettings {
+    /// Overlay theme served when neither the URL nor the browser picks one (`--theme`).
+    pub theme: String,
+    /// Overlay language when neither the URL nor the browser picks one (`--lang`).
+    pub lang: Lang,
+    pub swap_sides: bool,
+    pub poll_interval: Duration,
+    pub websocket: bool,
+    pub summary_webhook: Option<HttpUrl>,
+}
+
+impl LiveSettings {
+    pub fn from_config(config: &ServerConfig) -> Self {
+        Self {
+            theme: config.theme.clone(),
+            lang: config.lang,
+            swap_sides: config.swap_sides,
+            poll_interval: config.poll_interval,
+            websocket: config.websocket,
+            summary_webhook: config.summary_webhook.clone(),
+        }
+    }
+}
+
+impl Default for LiveSettings {
+    fn default() -> Self {
+        Self::from_config(&ServerConfig::default())
+    }
+}
+
+/// [`ServerConfig`] fields a reload applies; the others only take effect after a restart.
+/// `teams` and `match` stand for the contents of the teams and match files.
+pub const RELOADABLE: [&str; 8] = ["theme", "lang", "swap_sides", "poll_interval", "websocket", "summary_webhook", "teams", "match"];
+
+/// What a reload changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReloadReport {
+    /// Fields now in effect with their new value.
+    pub applied: Vec<&'static str>,
+    /// Fields that changed but keep their old value until a restart (bind addresses, ...).
+    pub restart_required: Vec<&'static str>,
+}
+
+/// Names of the [`ServerConfig`] fields that differ between `old` and `new`.
+fn changed_fields(old: &ServerConfig, new: &ServerConfig) -> Vec<&'static str> {
+    macro_rules! changed {
+        ($($field:ident),* $(,)?) => {{
+            let mut changed = Vec::new();
+            $(
+                if format!("{:?}", old.$field) != format!("{:?}", new.$field) {
+                    changed.push(stringify!($field));
+                }
+            )*
+            changed
+        }};
+    }
+    changed!(
+        input, follow_start, tcp_address, udp_address, public, log_to_file, capture, forward_to, lenient, dedupe, theme, lang, swap_sides, poll_interval, websocket,
+        teams_file, match_file, custom_css_file, upload_dir, web_root, cors_allowed_origins, admin_token, lock_read_api, override_mode,
+        ack, auth_token, subscriber_address, history_capacity, log_csv, log_jsonl, log_jsonl_max_size, games_dir, games_db,
+        game_idle_gap, auto_reset, state_file, state_max_age, summary_webhook, notify, push, config_file, rules, timeout_length, tick_clock, health_stale_after, web_workers,
+        web_max_connections, web_header_timeout, ws_ping_interval, web_keep_alive, web_address, web_drain_timeout, tls, mdns, court_id, mqtt,
+        osc_targets, osc_map, statsd, xml_file, xml_interval, xml_names, hooks, hook_timeout, tui, backpressure_timeout, peer_summary_interval, echo,
+        heartbeat_bytes, timezone, courts,
+    )
+}
+
+/// Reads the configuration again (`SIGHUP`, `POST /api/reload`, a changed `--config` file) and
+/// applies what can change without a restart.
+///
+/// The configuration is parsed from the same command line and environment as at startup, so
+/// in practice a reload picks up edits of the config file. Either all reloadable changes are
+/// applied or, when the file has an error, none.
+pub struct Reloader {
+    vars: Vec<(String, String)>,
+    args: Vec<String>,
+    /// The configuration in effect: startup values for the fields that need a restart.
+    running: Mutex<ServerConfig>,
+}
+
+impl Reloader {
+    /// `vars` and `args` are what `config` was parsed from.
+    pub fn new(vars: Vec<(String, String)>, args: Vec<String>, config: ServerConfig) -> Self {
+        Self {
+            vars,
+            args,
+            running: Mutex::new(config),
+        }
+    }
+
+    /// Read the configuration again and apply the reloadable changes to `shared`, the state of
+    /// the court served at `/` when there are several (the others keep their teams and match
+    /// details until a restart).
+    ///
+    /// On an error nothing is changed and the old configuration stays in effect.
+    pub fn reload(&self, shared: &SharedState) -> Result<ReloadReport, String> {
+        let new = ServerConfig::from_env_and_args(self.vars.iter().cloned(), &self.args)?;
+        let mut running = lock(&self.running);
+        // The teams are saved where they were at startup, so that file is read again
+        let court = running.primary_court();
+        let teams = Teams::load(&court.teams_file)
+            .map_err(|e| format!("Cannot load teams from {}: {}", court.teams_file.display(), e))?;
+        let match_info = MatchInfo::load(&court.match_file)
+            .map_err(|e| format!("Cannot load match details from {}: {}", court.match_file.display(), e))?;
+
+        let mut report = ReloadReport::default();
+        for field in changed_fields(&running, &new) {
+            if RELOADABLE.contains(&field) {
+                report.applied.push(field);
+            } else {
+                report.restart_required.push(field);
+            }
+        }
+        running.theme = new.theme;
+        running.lang = new.lang;
+        running.swap_sides = new.swap_sides;
+        running.poll_interval = new.poll_interval;
+        running.websocket = new.websocket;
+        running.summary_webhook = new.summary_webhook;
+        shared.set_settings(LiveSettings::from_config(&running));
+        if let Some(teams) = teams.filter(|teams| *teams != shared.current().teams) {
+            shared.set_teams(teams);
+            report.applied.push("teams");
+        }
+        if let Some(match_info) = match_info.filter(|info| *info != shared.current().match_info) {
+            shared.set_match(match_info);
+            report.applied.push("match");
+        }
+
+        if report.applied.contains(&"theme") && themes::find(&running.theme).is_none() {
+            warn!("Unknown theme {:?}, serving {:?}", running.theme, DEFAULT_THEME);
+        }
+        if report.applied.is_empty() {
+            info!("Configuration reloaded, nothing to apply");
+        } else {
+            info!("Configuration reloaded, applied: [{}]", report.applied.join(", "));
+        }
+        if !report.restart_required.is_empty() {
+            warn!("Changed settings that need a restart: [{}]", report.restart_required.join(", "));
+        }
+        Ok(report)
+    }
+}
+
+/// Reload the configuration of `shared` whenever the config file at `path` is modified,
+/// checking every 2 seconds.
+pub fn watch(path: PathBuf, shared: Arc<SharedState>) {
+    thread::spawn(move || {
+        let modified = || fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+        let mut last_modified: Option<SystemTime> = modified();
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+            let Some(modified) = modified() else {
+                continue;
+            };
+            if last_modified == Some(modified) {
+                continue;
+            }
+            last_modified = Some(modified);
+            info!("{} changed, reloading the configuration", path.display());
+            if let Err(e) = shared.reload() {
+                error!("Reload failed, keeping the current configuration: {}", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,175 @@
+use std::str::FromStr;
+use warp::http::Method;
+
+/// Method and path matching for the web server: a table of routes, each a method, a path
+/// pattern and a value (what the web server runs for it), looked up once per request.
+///
+/// Patterns are `/`-separated segments: literal ones, `:name` for any one segment and
+/// `*name` (last only) for one or more. Paths are split the same way, empty segments
+/// dropped, so `/api/state/` is `/api/state`. Segments are compared as sent, without
+/// percent-decoding.
+///
+/// When several patterns take a path, the most specific wins: segment by segment from the
+/// left, a literal beats `:name`, which beats `*name`; between equal patterns, the one
+/// added first. A path some route takes for another method is [`Found::MethodNotAllowed`],
+/// unless only `*name` patterns take it: they stand for whatever may be below them, so they
+/// don't make a path known, and `POST /no/such/file` is not found rather than not allowed.
+#[derive(Debug)]
+pub struct Router<T> {
+    routes: Vec<Route<T>>,
+}
+
+#[derive(Debug)]
+struct Route<T> {
+    method: Method,
+    segments: Vec<Segment>,
+    value: T,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Param(String),
+    Rest(String),
+}
+
+impl Segment {
+    /// Rank in the precedence: lower is more specific.
+    fn rank(&self) -> u8 {
+        match self {
+            Segment::Literal(_) => 0,
+            Segment::Param(_) => 1,
+            Segment::Rest(_) => 2,
+        }
+    }
+}
+
+/// What [`Router::find`] found for a request.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Found<'a, T> {
+    /// The value of the route taking the method and path, and the path's parameters.
+    Route(&'a T, Params),
+    /// Routes take the path, for these methods only (the `Allow` header of a 405).
+    MethodNotAllowed(Vec<Method>),
+    NotFound,
+}
+
+/// The `:name` and `*name` segments of a matched path, by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Params(Vec<(String, String)>);
+
+impl Params {
+    /// The segment of `:name`, or the segments of `*name` joined with `/`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.iter().find(|(param, _)| param == name).map(|(_, value)| value.as_str())
+    }
+
+    /// [`get`](Self::get) parsed as a `T`; `None` when missing or not a `T`.
+    pub fn parse<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.get(name)?.parse().ok()
+    }
+}
+
+impl<T> Default for Router<T> {
+    fn default() -> Self {
+        Self { routes: Vec::new() }
+    }
+}
+
+impl<T> Router<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Route `method` requests for paths matching `pattern` to `value`.
+    ///
+    /// # Panics
+    ///
+    /// If `pattern` doesn't start with `/`, has a `*name` before its last segment, an
+    /// unnamed parameter or a name twice: patterns are fixed in the code, so these are bugs.
+    pub fn add(&mut self, method: Method, pattern: &str, value: T) -> &mut Self {
+        assert!(pattern.starts_with('/'), "route pattern {:?} must start with /", pattern);
+        let parts: Vec<&str> = split(pattern).collect();
+        let mut segments = Vec::with_capacity(parts.len());
+        for (n, part) in parts.iter().enumerate() {
+            let segment = if let Some(name) = part.strip_prefix(':') {
+                Segment::Param(name.to_string())
+            } else if let Some(name) = part.strip_prefix('*') {
+                assert!(n + 1 == parts.len(), "*{} must be the last segment of {:?}", name, pattern);
+                Segment::Rest(name.to_string())
+            } else {
+                Segment::Literal(part.to_string())
+            };
+            if let Segment::Param(name) | Segment::Rest(name) = &segment {
+                assert!(!name.is_empty(), "unnamed parameter in {:?}", pattern);
+                let taken = segments.iter().any(|other| matches!(other, Segment::Param(n) | Segment::Rest(n) if n == name));
+                assert!(!taken, "parameter {} twice in {:?}", name, pattern);
+            }
+            segments.push(segment);
+        }
+        self.routes.push(Route { method, segments, value });
+        self
+    }
+
+    /// The route for `method` and `path` (without the query).
+    pub fn find(&self, method: &Method, path: &str) -> Found<'_, T> {
+        let path: Vec<&str> = split(path).collect();
+        let mut best: Option<(&Route<T>, Params)> = None;
+        let mut allow = Vec::new();
+        for route in &self.routes {
+            let Some(params) = route.matches(&path) else {
+                continue;
+            };
+            if route.method != *method {
+                if !route.is_catch_all() && !allow.contains(&route.method) {
+                    allow.push(route.method.clone());
+                }
+                continue;
+            }
+            // Ties keep the route added first
+            if best.as_ref().is_none_or(|(other, _)| route.ranks().lt(other.ranks())) {
+                best = Some((route, params));
+            }
+        }
+        match best {
+            Some((route, params)) => Found::Route(&route.value, params),
+            None if allow.is_empty() => Found::NotFound,
+            None => Found::MethodNotAllowed(allow),
+        }
+    }
+}
+
+impl<T> Route<T> {
+    /// The parameters of `path` when this route's pattern matches it.
+    fn matches(&self, path: &[&str]) -> Option<Params> {
+        let mut params = Vec::new();
+        for (n, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Literal(literal) => {
+                    if path.get(n) != Some(&literal.as_str()) {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => params.push((name.clone(), path.get(n)?.to_string())),
+                Segment::Rest(name) => {
+                    let rest = path.get(n..).filter(|rest| !rest.is_empty())?;
+                    params.push((name.clone(), rest.join("/")));
+                    return Some(Params(params));
+                }
+            }
+        }
+        (path.len() == self.segments.len()).then_some(Params(params))
+    }
+
+    fn is_catch_all(&self) -> bool {
+        matches!(self.segments.last(), Some(Segment::Rest(_)))
+    }
+
+    fn ranks(&self) -> impl Iterator<Item = u8> + '_ {
+        self.segments.iter().map(Segment::rank)
+    }
+}
+
+fn split(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Competition rules deciding when team fouls put the other team in the bonus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+#[non_exhaustive]
+pub enum RuleSet {
+    /// A team is in the bonus once its opponent has 4 team fouls in the quarter (FIBA art. 41).
+    #[default]
+    Fiba,
+    /// From the opponent's 5th team foul in the quarter. The last-two-minutes rule is not applied.
+    Nba,
+    /// One-and-one from the opponent's 7th team foul in the half, two shots from the 10th.
+    Ncaa,
+}
+
+/// Free throw situation of a team, shown as `BONUS` / `+BONUS` on the overlay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Bonus {
+    #[serde(rename = "bonus")]
+    Single,
+    /// NCAA only.
+    #[serde(rename = "doubleBonus")]
+    Double,
+}
+
+impl RuleSet {
+    /// Parse a `--rules` value.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "fiba" => Some(Self::Fiba),
+            "nba" => Some(Self::Nba),
+            "ncaa" => Some(Self::Ncaa),
+            _ => None,
+        }
+    }
+
+    /// Length of a time-out, counted down on the overlay unless `--timeout-length` sets another.
+    pub fn timeout_length(self) -> Duration {
+        match self {
+            Self::Fiba | Self::Ncaa => Duration::from_secs(60),
+            Self::Nba => Duration::from_secs(75),
+        }
+    }
+
+    /// Length of a regulation period (quarter); overtime periods last 5 minutes under every rule set.
+    pub fn period_length(self) -> Duration {
+        match self {
+            Self::Fiba | Self::Ncaa => Duration::from_secs(600),
+            Self::Nba => Duration::from_secs(720),
+        }
+    }
+
+    /// Whether team fouls start again from zero in `period` (1-based, overtime from 5): every
+    /// quarter, every half under NCAA rules. FIBA and NCAA overtime continue the last count.
+    pub fn resets_team_fouls(self, period: u32) -> bool {
+        match self {
+            Self::Fiba => period <= 4,
+            Self::Nba => true,
+            Self::Ncaa => period == 1 || period == 3,
+        }
+    }
+
+    /// Bonus of a team whose opponent has `opponent_fouls` team fouls (`None` while the console shows a blank).
+    pub fn bonus(self, opponent_fouls: Option<u32>) -> Option<Bonus> {
+        let fouls = opponent_fouls?;
+        let (single, double) = match self {
+            Self::Fiba => (4, None),
+            Self::Nba => (5, None),
+            Self::Ncaa => (7, Some(10)),
+        };
+        if double.is_some_and(|double| fouls >= double) {
+            Some(Bonus::Double)
+        } else if fouls >= single {
+            Some(Bonus::Single)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,414 @@
+//! Running as a Windows service: `service install`, `service uninstall` and `service run`.
+//!
+//! The service control manager talks to the service through control events; [`ControlHandler`]
+//! decides what each one does, on every platform so it can be tested anywhere. The Windows
+//! side (the `windows-service` feature) only carries the decisions out.
+
+use std::time::Duration;
+
+/// Name the service is registered under (`sc query scoreboard-rust`).
+pub const SERVICE_NAME: &str = "scoreboard-rust";
+/// Name shown in the Services console.
+pub const DISPLAY_NAME: &str = "Bodet scoreboard bridge";
+
+/// How long the service manager is told a stop may take: the web server drain, then the
+/// console connections and the logs.
+pub const STOP_WAIT_HINT: Duration = Duration::from_secs(20);
+
+/// Stops the server the way Ctrl+C does.
+pub type Stop = Box<dyn Fn() + Send + Sync>;
+
+/// Runs the server with the given options until it is shut down and returns its exit status.
+/// It hands over its [`Stop`] as soon as both servers are started.
+pub type Serve = fn(&[String], &mut dyn FnMut(Stop)) -> i32;
+
+/// The state of the service, as reported to the service manager.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceState {
+    StartPending,
+    Running,
+    StopPending,
+    Stopped,
+}
+
+impl ServiceState {
+    /// Whether the service manager may send stop and shutdown events in this state.
+    pub fn accepts_stop(self) -> bool {
+        self == ServiceState::Running
+    }
+}
+
+/// The control events the service reacts to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    /// `sc stop`, `net stop` or the Services console.
+    Stop,
+    /// Windows is shutting down.
+    Shutdown,
+    /// The service manager asks for the current state.
+    Interrogate,
+    /// Pause, parameter changes, power events...: not handled.
+    Other,
+}
+
+/// What to do about a control event or a change of the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Response {
+    /// The event is one the service handles.
+    pub handled: bool,
+    /// Start the graceful shutdown now.
+    pub shut_down: bool,
+    /// Report this state to the service manager.
+    pub report: Option<ServiceState>,
+}
+
+/// The service's state machine: StartPending -> Running -> StopPending -> Stopped.
+///
+/// A stop asked for while the server is still starting is remembered and carried out once
+/// it has started, as there is nothing to stop before. The shutdown is started only once,
+/// however many stop and shutdown events arrive.
+#[derive(Debug)]
+pub struct ControlHandler {
+    state: ServiceState,
+    started: bool,
+}
+
+impl Default for ControlHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ControlHandler {
+    pub fn new() -> Self {
+        Self { state: ServiceState::StartPending, started: false }
+    }
+
+    pub fn state(&self) -> ServiceState {
+        self.state
+    }
+
+    /// A control event from the service manager.
+    pub fn control(&mut self, control: Control) -> Response {
+        match control {
+            Control::Stop | Control::Shutdown => match self.state {
+                ServiceState::StartPending | ServiceState::Running => {
+                    self.state = ServiceState::StopPending;
+                    Response { handled: true, shut_down: self.started, report: Some(ServiceState::StopPending) }
+                }
+                ServiceState::StopPending | ServiceState::Stopped => Response { handled: true, ..Response::default() },
+            },
+            Control::Interrogate => Response { handled: true, shut_down: false, report: Some(self.state) },
+            Control::Other => Response::default(),
+        }
+    }
+
+    /// Both servers are started and can be stopped.
+    pub fn started(&mut self) -> Response {
+        self.started = true;
+        match self.state {
+            ServiceState::StartPending => {
+                self.state = ServiceState::Running;
+                Response { handled: true, shut_down: false, report: Some(ServiceState::Running) }
+            }
+            // Stopped while starting
+            ServiceState::StopPending => Response { handled: true, shut_down: true, report: None },
+            ServiceState::Running | ServiceState::Stopped => Response { handled: true, ..Response::default() },
+        }
+    }
+
+    /// The server has exited.
+    pub fn stopped(&mut self) -> Response {
+        self.state = ServiceState::Stopped;
+        Response { handled: true, shut_down: false, report: Some(ServiceState::Stopped) }
+    }
+}
+
+/// Directory of the service's log files, under the executable's folder, unless the server
+/// options give a `--log-dir`.
+pub const DEFAULT_LOG_DIR: &str = "logs";
+
+/// The server options of `service install` and `service run`, stored with the service at
+/// install and passed to the server.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ServiceOptions {
+    pub server_args: Vec<String>,
+}
+
+impl ServiceOptions {
+    /// A service has no console, so its log goes to [`DEFAULT_LOG_DIR`] unless the options
+    /// pick a directory.
+    pub fn from_args(args: &[String]) -> Self {
+        let mut server_args = args.to_vec();
+        if !args.iter().any(|arg| arg == "--log-dir") {
+            server_args.extend(["--log-dir".to_string(), DEFAULT_LOG_DIR.to_string()]);
+        }
+        Self { server_args }
+    }
+}
+
+/// The commands of `scoreboard-rust service`.
+#[derive(Debug, Clone, PartialEq, Eq, clap::Subcommand)]
+pub enum ServiceCommand {
+    /// Install the service, started with these server options.
+    Install {
+        #[arg(value_name = "SERVER OPTIONS", trailing_var_arg = true, allow_hyphen_values = true)]
+        server_args: Vec<String>,
+    },
+    /// Stop and remove the service.
+    Uninstall,
+    /// Run as the service, as the service manager starts it.
+    #[command(hide = true)]
+    Run {
+        #[arg(value_name = "SERVER OPTIONS", trailing_var_arg = true, allow_hyphen_values = true)]
+        server_args: Vec<String>,
+    },
+}
+
+/// Entry point of `scoreboard-rust service <install|uninstall|run> [options]`; returns the
+/// process exit code.
+#[cfg(all(windows, feature = "windows-service"))]
+pub fn run_command(command: &ServiceCommand, serve: Serve) -> i32 {
+    windows::run_command(command, serve)
+}
+
+#[cfg(all(windows, feature = "windows-service"))]
+mod windows {
+    use super::{Control, ControlHandler, Response, Serve, ServiceCommand, ServiceOptions, ServiceState, Stop, DEFAULT_LOG_DIR, DISPLAY_NAME, SERVICE_NAME, STOP_WAIT_HINT};
+    use crate::{logging, ServerConfig};
+    use std::{
+        ffi::OsString,
+        path::PathBuf,
+        sync::{Arc, Mutex, OnceLock},
+        time::Duration,
+    };
+    use tracing::{
+        error, info,
+        subscriber::{DefaultGuard, NoSubscriber},
+    };
+    use tracing_appender::rolling::RollingFileAppender;
+    use windows_service::{
+        define_windows_service,
+        service::{
+            ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode, ServiceInfo,
+            ServiceStartType, ServiceStatus, ServiceType,
+        },
+        service_control_handler::{self, ServiceControlHandlerResult, ServiceStatusHandle},
+        service_dispatcher,
+        service_manager::{ServiceManager, ServiceManagerAccess},
+    };
+
+    /// Win32 `ERROR_FAILED_SERVICE_CONTROLLER_CONNECT`: `service run` started from a console.
+    const ERROR_FAILED_SERVICE_CONTROLLER_CONNECT: i32 = 1063;
+
+    /// What the service main needs, set before the dispatcher calls it.
+    static LAUNCH: OnceLock<(ServiceOptions, Serve)> = OnceLock::new();
+
+    define_windows_service!(ffi_service_main, service_main);
+
+    pub fn run_command(command: &ServiceCommand, serve: Serve) -> i32 {
+        match command {
+            ServiceCommand::Run { server_args } => run(ServiceOptions::from_args(server_args), serve),
+            ServiceCommand::Install { server_args } => {
+                logging::init_console();
+                install(server_args, &ServiceOptions::from_args(server_args))
+            }
+            ServiceCommand::Uninstall => {
+                logging::init_console();
+                uninstall()
+            }
+        }
+    }
+
+    fn install(args: &[String], options: &ServiceOptions) -> i32 {
+        // Refused now rather than when the service starts with nobody watching
+        if let Err(e) = ServerConfig::from_args(&options.server_args) {
+            error!("{}", e);
+            return 2;
+        }
+        let result = (|| -> windows_service::Result<()> {
+            let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE)?;
+            let executable_path = std::env::current_exe().map_err(windows_service::Error::Winapi)?;
+            let launch_arguments = ["service", "run"].into_iter().map(OsString::from).chain(args.iter().map(OsString::from)).collect();
+            let info = ServiceInfo {
+                name: OsString::from(SERVICE_NAME),
+                display_name: OsString::from(DISPLAY_NAME),
+                service_type: ServiceType::OWN_PROCESS,
+                start_type: ServiceStartType::AutoStart,
+                error_control: ServiceErrorControl::Normal,
+                executable_path,
+                launch_arguments,
+                dependencies: vec![],
+                account_name: None,
+                account_password: None,
+            };
+            let service = manager.create_service(&info, ServiceAccess::CHANGE_CONFIG)?;
+            service.set_description("Reads the Bodet console's frames and serves the scoreboard overlays")
+        })();
+        match result {
+            Ok(()) => {
+                info!("Installed the {} service; start it with: sc start {}", SERVICE_NAME, SERVICE_NAME);
+                0
+            }
+            Err(e) => {
+                error!("Failed to install the service (run as administrator?): {}", e);
+                1
+            }
+        }
+    }
+
+    fn uninstall() -> i32 {
+        let result = (|| -> windows_service::Result<()> {
+            let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)?;
+            let service = manager.open_service(SERVICE_NAME, ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE)?;
+            if service.query_status()?.current_state != windows_service::service::ServiceState::Stopped {
+                info!("Stopping the {} service", SERVICE_NAME);
+                service.stop()?;
+            }
+            // Removed once stopped and every handle to it is closed
+            service.delete()
+        })();
+        match result {
+            Ok(()) => {
+                info!("Uninstalled the {} service", SERVICE_NAME);
+                0
+            }
+            Err(e) => {
+                error!("Failed to uninstall the service (run as administrator?): {}", e);
+                1
+            }
+        }
+    }
+
+    fn run(options: ServiceOptions, serve: Serve) -> i32 {
+        // Services start in the system folder: relative paths (data_log/, --config...) are
+        // taken from the executable's folder instead
+        let folder = std::env::current_exe().ok().and_then(|exe| exe.parent().map(PathBuf::from));
+        if let Some(folder) = &folder {
+            let _ = std::env::set_current_dir(folder);
+        }
+        let _log = service_log();
+        let _ = LAUNCH.set((options, serve));
+        match service_dispatcher::start(SERVICE_NAME, ffi_service_main) {
+            Ok(()) => 0,
+            Err(windows_service::Error::Winapi(e)) if e.raw_os_error() == Some(ERROR_FAILED_SERVICE_CONTROLLER_CONNECT) => {
+                error!("service run is started by the service manager; run the server without it in a console");
+                2
+            }
+            Err(e) => {
+                error!("Service dispatcher failed: {}", e);
+                1
+            }
+        }
+    }
+
+    /// The service's own messages, until the server has set up its log: `logs/service.log`.
+    fn service_log() -> DefaultGuard {
+        match RollingFileAppender::builder().filename_prefix("service.log").build(DEFAULT_LOG_DIR) {
+            Ok(file) => tracing::subscriber::set_default(tracing_subscriber::fmt().with_writer(file).with_ansi(false).finish()),
+            Err(_) => tracing::subscriber::set_default(NoSubscriber::default()),
+        }
+    }
+
+    /// The state machine and what carries its decisions out.
+    struct Controller {
+        handler: ControlHandler,
+        status: Option<ServiceStatusHandle>,
+        stop: Option<Stop>,
+        exit_code: u32,
+    }
+
+    impl Controller {
+        fn apply(&mut self, response: Response) {
+            if response.shut_down {
+                if let Some(stop) = &self.stop {
+                    info!("Service stop requested, shutting down");
+                    stop();
+                }
+            }
+            if let (Some(state), Some(status)) = (response.report, self.status) {
+                let (current_state, wait_hint) = match state {
+                    ServiceState::StartPending => (windows_service::service::ServiceState::StartPending, Duration::from_secs(10)),
+                    ServiceState::Running => (windows_service::service::ServiceState::Running, Duration::ZERO),
+                    ServiceState::StopPending => (windows_service::service::ServiceState::StopPending, STOP_WAIT_HINT),
+                    ServiceState::Stopped => (windows_service::service::ServiceState::Stopped, Duration::ZERO),
+                };
+                let controls_accepted = if state.accepts_stop() {
+                    ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN
+                } else {
+                    ServiceControlAccept::empty()
+                };
+                let exit_code = match self.exit_code {
+                    0 => ServiceExitCode::NO_ERROR,
+                    code => ServiceExitCode::ServiceSpecific(code),
+                };
+                let report = ServiceStatus {
+                    service_type: ServiceType::OWN_PROCESS,
+                    current_state,
+                    controls_accepted,
+                    exit_code,
+                    checkpoint: 0,
+                    wait_hint,
+                    process_id: None,
+                };
+                if let Err(e) = status.set_service_status(report) {
+                    error!("Failed to report the service status: {}", e);
+                }
+            }
+        }
+    }
+
+    fn lock(controller: &Mutex<Controller>) -> std::sync::MutexGuard<'_, Controller> {
+        controller.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn service_main(_arguments: Vec<OsString>) {
+        let Some((options, serve)) = LAUNCH.get() else {
+            return;
+        };
+        let service_log = service_log();
+        let controller = Arc::new(Mutex::new(Controller { handler: ControlHandler::new(), status: None, stop: None, exit_code: 0 }));
+        let events = Arc::clone(&controller);
+        let registered = service_control_handler::register(SERVICE_NAME, move |control| {
+            let control = match control {
+                ServiceControl::Stop => Control::Stop,
+                ServiceControl::Shutdown => Control::Shutdown,
+                ServiceControl::Interrogate => Control::Interrogate,
+                _ => Control::Other,
+            };
+            let mut controller = lock(&events);
+            let response = controller.handler.control(control);
+            controller.apply(response);
+            if response.handled {
+                ServiceControlHandlerResult::NoError
+            } else {
+                ServiceControlHandlerResult::NotImplemented
+            }
+        });
+        match registered {
+            Ok(status) => {
+                let mut controller = lock(&controller);
+                controller.status = Some(status);
+                controller.apply(Response { report: Some(ServiceState::StartPending), ..Response::default() });
+            }
+            Err(e) => {
+                error!("Failed to register the service control handler: {}", e);
+                return;
+            }
+        }
+        info!("Starting as the {} service", SERVICE_NAME);
+        drop(service_log);
+
+        let started = Arc::clone(&controller);
+        let code = serve(&options.server_args, &mut move |stop| {
+            let mut controller = lock(&started);
+            controller.stop = Some(stop);
+            let response = controller.handler.started();
+            controller.apply(response);
+        });
+        let mut controller = lock(&controller);
+        controller.exit_code = u32::try_from(code).unwrap_or(1);
+        let response = controller.handler.stopped();
+        controller.apply(response);
+    }
+}
@@ -0,0 +1,1105 @@
+use crate::{
+    clock::{self, ClockTicker},
+    debounce::Debouncer,
+    events::{self, ActiveTimeout, GameEvent, OverlayEvent, OverlayEventKind},
+    event_log::{EventLog, EventPage, LoggedEvent, EVENT_LOG_CAPACITY},
+    game_log::GameLog,
+    history::{History, HistoryEntry, HistoryFilter, HistoryPage},
+    line_score::{self, FIRST_PERIOD},
+    match_info::MatchInfo,
+    metrics::{isolate, Metrics, MetricsSnapshot},
+    numeric_value,
+    persist::Snapshot,
+    reload::{LiveSettings, ReloadReport, Reloader},
+    rules::RuleSet,
+    summary::{GameSummary, SummaryTracker},
+    teams::Teams,
+    timezone::TimeZone,
+    GameState,
+};
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::Ordering,
+        Arc, Condvar, Mutex, MutexGuard, OnceLock, PoisonError, Weak,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime},
+};
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+
+/// Capacity of the broadcast channel feeding SSE clients.
+const BROADCAST_CAPACITY: usize = 100;
+/// Updates queued per listener before new ones are merged into the last queued one.
+const LISTENER_QUEUE_CAPACITY: usize = 64;
+/// States queued per [`StateReceiver`] before the oldest ones are dropped.
+pub const SUBSCRIBER_CAPACITY: usize = 64;
+
+/// Frames or heartbeats older than this no longer count the link as up.
+pub const LINK_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Lock a mutex, recovering the data if a panicking thread poisoned it.
+///
+/// The protected values are always left consistent between statements, so a panic
+/// elsewhere must not take the web server and every other client down with it.
+pub fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// How long an end-of-period or horn event stays in the published states.
+const OVERLAY_EVENT_TTL: Duration = Duration::from_secs(5);
+
+/// Current Unix time in milliseconds.
+pub fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+/// How repeated states coming from the console are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Dedupe {
+    /// Every accepted frame is published, even if nothing changed.
+    Off,
+    /// Skip publishing a state identical to the current one.
+    Exact,
+    /// Like `Exact`, but also skip changes that only touch the game or shot clock.
+    /// The stored state is still updated so the JSON API shows the latest clock.
+    IgnoreClock,
+}
+
+/// What a console frame does while a manual override is active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OverrideMode {
+    /// The console takes over again with its next frame.
+    Resume,
+    /// Console frames are ignored until the operator releases the override.
+    Hold,
+}
+
+/// A published state together with the events that produced it.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Update {
+    pub state: GameState,
+    pub events: Vec<GameEvent>,
+    /// The `events` added to the event log, with their ids.
+    pub logged_events: Vec<LoggedEvent>,
+    /// Version of `state`, see [`SharedState::version`].
+    pub version: u64,
+    /// When `state` was published, in Unix milliseconds.
+    pub timestamp_ms: u64,
+    /// `state` serialized once for all push clients, with the `logged_events` as its `changes`.
+    pub json: String,
+    /// The input whose frames `state` comes from (`tcp`, `udp`, `stdin`...); `None` once the
+    /// operator has edited it.
+    pub source: Option<&'static str>,
+    /// The summary of the game `state` ended: set with the `Final` period or a new game.
+    pub summary: Option<GameSummary>,
+}
+
+/// A published state as pushed: the state plus the logged events that produced it.
+///
+/// Pages animate the `changes` (e.g. `+3` over a score) once per event id. Only pushes carry
+/// them; the current state sent to new clients doesn't, so reconnecting replays nothing.
+#[derive(Serialize)]
+struct PushedState<'a> {
+    #[serde(flatten)]
+    state: &'a GameState,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    changes: &'a [LoggedEvent],
+}
+
+struct StateInner {
+    game: GameState,
+    /// `game` serialized, so new push clients get it without re-serializing.
+    json: String,
+    /// Incremented every time a state is published.
+    version: u64,
+    last_received: Option<Instant>,
+    last_heartbeat: Option<Instant>,
+    /// The current state was set by the operator, see [`SharedState::set_manual`].
+    manual_override: bool,
+    /// Interpolates `game.display_clock` and `game.display_shot_clock` between frames; `None` unless `--tick-clock` is set.
+    ticker: Option<ClockTicker>,
+    /// Id of the last [`OverlayEvent`].
+    last_event_id: u64,
+    /// `game` was restored from the state file and no frame has arrived since.
+    restored: bool,
+    /// The input of the last frame received, see [`SharedState::update_from`].
+    source: Option<&'static str>,
+    /// Time without the clock running after which a console reset starts a new game; `None`
+    /// unless `--auto-reset` is set.
+    auto_reset: Option<Duration>,
+    /// When a frame or the operator last had the game clock running, since the last new game.
+    last_run: Option<Instant>,
+    /// Unix milliseconds of the last published state.
+    published_at_ms: Option<u64>,
+    /// Zone the game summary's times are written in (`--timezone`).
+    timezone: TimeZone,
+}
+
+impl StateInner {
+    // Whether `state`, from a console frame, starts a new game under `--auto-reset`: the clock
+    // hasn't run for the idle time (the game is over, or was left as it was) and the frame
+    // clears the board for the next one, the scores going back to 0 or the period to the
+    // first. Either is enough, not both: the console resets its fields in several frames
+    // (scores in message 30, the period in message 18), in either order, and the first one
+    // must start the game so the last game is closed out as it ended rather than at 0-0 or in
+    // the 1st period. The others then land in the new game, whose clock hasn't run yet.
+    fn auto_reset_due(&self, state: &GameState) -> bool {
+        let Some(idle) = self.auto_reset else {
+            return false;
+        };
+        let pointless = |state: &GameState| {
+            [&state.home_score, &state.away_score]
+                .iter()
+                .all(|score| numeric_value(score).is_none_or(|points| points == 0))
+        };
+        let first = |state: &GameState| state.period_name.trim() == FIRST_PERIOD;
+        self.last_run.is_some_and(|at| at.elapsed() >= idle)
+            && ((pointless(state) && !pointless(&self.game)) || (first(state) && !first(&self.game)))
+    }
+
+    // Whether `state` has a new clock compared to the current one. After a stale spell the
+    // console's clock is the only one to trust, so it counts as new too.
+    fn clock_changed(&self, state: &GameState) -> bool {
+        state.time != self.game.time
+            || state.game_state != self.game.game_state
+            || state.period_name != self.game.period_name
+            || self.game.stale
+    }
+
+    // Set `state.clock_valid_at_ms` to now when the running clock changed; frames repeating
+    // the same clock keep the time it was first received.
+    fn stamp_clock(&self, state: &mut GameState) {
+        state.clock_valid_at_ms = if !clock::is_running(state) {
+            None
+        } else if self.clock_changed(state) || self.game.clock_valid_at_ms.is_none() {
+            Some(unix_millis())
+        } else {
+            self.game.clock_valid_at_ms
+        };
+    }
+
+    // Set `state.display_clock` and `state.display_shot_clock`, re-anchoring the ticker when
+    // the console's clocks changed.
+    // Other frames (e.g. scores) repeat the last clock and must not snap it back.
+    fn sync_clock(&mut self, state: &mut GameState) {
+        self.stamp_clock(state);
+        let clock_changed = self.clock_changed(state);
+        let Some(ticker) = &mut self.ticker else {
+            return;
+        };
+        let now = Instant::now();
+        if clock_changed {
+            ticker.sync(state, now);
+        }
+        state.display_clock = ticker.display(now).unwrap_or_else(|| state.time.clone());
+
+        let shot_clock_changed = state.shot_clock != self.game.shot_clock || state.shot_clock_running != self.game.shot_clock_running;
+        if shot_clock_changed || self.game.stale {
+            ticker.sync_shot_clock(state, now);
+        }
+        state.display_shot_clock = ticker.display_shot_clock(now).unwrap_or_else(|| state.shot_clock.clone());
+    }
+
+    // Carry the unexpired overlay events of the current state over to `state` and add the
+    // ones `events` start. Whatever the caller put in `state.events` is replaced.
+    fn attach_overlay_events(&mut self, state: &mut GameState, events: &[GameEvent]) {
+        let now = unix_millis();
+        state.events = self.game.events.iter().filter(|event| event.expires_at_ms > now).cloned().collect();
+        for event in events {
+            if let Some(kind) = OverlayEventKind::from_event(event) {
+                self.last_event_id += 1;
+                state.events.push(OverlayEvent {
+                    id: self.last_event_id,
+                    kind,
+                    period: state.period_name.trim().to_string(),
+                    expires_at_ms: now + OVERLAY_EVENT_TTL.as_millis() as u64,
+                });
+            }
+        }
+    }
+
+    // Start, count down or end the time-out in `state`, `events` being what produced it from
+    // the current state. A team's time-outs going down while the clock is stopped starts one
+    // of `length`, replacing any other (back-to-back time-outs); it ends when it runs out, when
+    // the clock runs again, or when the operator puts the count back up. Ending it adds a
+    // [`GameEvent::TimeoutEnded`] to `events`.
+    fn track_timeout(&self, state: &mut GameState, events: &mut Vec<GameEvent>, length: Duration) {
+        let now = unix_millis();
+        let previous = self.game.active_timeout;
+        let mut timeout = previous;
+        for event in events.iter() {
+            match *event {
+                GameEvent::TimeoutCalled { side } => {
+                    timeout = Some(ActiveTimeout {
+                        side,
+                        remaining_seconds: 0,
+                        ends_at_ms: now + length.as_millis() as u64,
+                    });
+                }
+                GameEvent::TimeoutChange { side, from: Some(from), to: Some(to) } if to > from => {
+                    timeout = timeout.filter(|timeout| timeout.side != side);
+                }
+                _ => {}
+            }
+        }
+        // Also covers a frame that both takes the time-out and restarts the clock
+        if clock::is_running(state) {
+            timeout = None;
+        }
+        timeout = timeout.filter(|timeout| timeout.ends_at_ms > now).map(|timeout| ActiveTimeout {
+            remaining_seconds: (timeout.ends_at_ms - now).div_ceil(1000) as u32,
+            ..timeout
+        });
+
+        if let Some(previous) = previous {
+            if timeout.is_none_or(|timeout| timeout.ends_at_ms != previous.ends_at_ms) {
+                events.push(GameEvent::TimeoutEnded { side: previous.side });
+            }
+        }
+        state.active_timeout = timeout;
+    }
+
+    // Keep `state` as the current one without publishing it: values that aren't pushed (a
+    // repeated frame, clock changes under `Dedupe::IgnoreClock`) still reach the JSON API and
+    // the state new push clients start from.
+    fn store(&mut self, state: GameState) {
+        self.json = serde_json::to_string(&state).unwrap_or_default();
+        self.game = state;
+    }
+
+    // Drop expired overlay events from the current state so new clients never get them.
+    // Clients that already have them clear them on their own; nothing is published.
+    fn prune_overlay_events(&mut self) {
+        let now = unix_millis();
+        if self.game.events.iter().any(|event| event.expires_at_ms <= now) {
+            self.game.events.retain(|event| event.expires_at_ms > now);
+            self.json = serde_json::to_string(&self.game).unwrap_or_default();
+        }
+    }
+}
+
+/// Health of the console link, see [`SharedState::link_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkStatus {
+    /// Frames are arriving.
+    Active,
+    /// No recent frames, but the bridge's heartbeats show the link is up (game idle).
+    Idle,
+    /// Neither frames nor heartbeats recently.
+    Dead,
+}
+
+/// Link health and counters, as served by the web status endpoint.
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct StatusReport {
+    pub link: LinkStatus,
+    pub last_frame_age_ms: Option<u64>,
+    pub last_heartbeat_age_ms: Option<u64>,
+    /// The input the current state comes from, see [`Update::source`].
+    pub source: Option<&'static str>,
+    pub metrics: MetricsSnapshot,
+}
+
+/// The updates waiting for a listener of [`SharedState::add_listener`].
+///
+/// Bounded without losing an event: once full, a new update is merged into the last queued
+/// one, which then carries the newest state and the events of both. Clock ticks are dropped
+/// from the older of the two, since the newest state has the clock anyway.
+#[derive(Default)]
+struct ListenerQueue {
+    items: Mutex<ListenerItems>,
+    ready: Condvar,
+}
+
+#[derive(Default)]
+struct ListenerItems {
+    updates: VecDeque<Arc<Update>>,
+    /// Set by [`SharedState::close_listeners`]: the listener ends once `updates` is empty.
+    closed: bool,
+    /// Whether the listener is behind since its queue last ran empty, warned once.
+    lagging: bool,
+}
+
+/// What a listener gets from its [`ListenerQueue`].
+enum Received {
+    Update(Arc<Update>),
+    Timeout,
+    Closed,
+}
+
+impl ListenerQueue {
+    fn push(&self, update: &Arc<Update>, metrics: &Metrics) {
+        let mut items = lock(&self.items);
+        if items.updates.len() < LISTENER_QUEUE_CAPACITY {
+            items.updates.push_back(Arc::clone(update));
+        } else {
+            let last = items.updates.pop_back().expect("the queue is full");
+            items.updates.push_back(Arc::new(merge_updates(&last, update)));
+            metrics.listener_updates_coalesced.fetch_add(1, Ordering::Relaxed);
+            if !items.lagging {
+                items.lagging = true;
+                warn!(
+                    "An update listener is {} updates behind: merging new updates until it catches up",
+                    LISTENER_QUEUE_CAPACITY
+                );
+            }
+        }
+        self.ready.notify_one();
+    }
+
+    /// Wait for the next update, until `deadline` if given.
+    fn recv(&self, deadline: Option<Instant>) -> Received {
+        let mut items = lock(&self.items);
+        loop {
+            if let Some(update) = items.updates.pop_front() {
+                if items.updates.is_empty() {
+                    items.lagging = false;
+                }
+                return Received::Update(update);
+            }
+            if items.closed {
+                return Received::Closed;
+            }
+            items = match deadline {
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    if timeout.is_zero() {
+                        return Received::Timeout;
+                    }
+                    self.ready.wait_timeout(items, timeout).unwrap_or_else(PoisonError::into_inner).0
+                }
+                None => self.ready.wait(items).unwrap_or_else(PoisonError::into_inner),
+            };
+        }
+    }
+
+    fn close(&self) {
+        lock(&self.items).closed = true;
+        self.ready.notify_one();
+    }
+}
+
+/// `older` followed by `newer` as one update: the state of `newer`, the events of both.
+fn merge_updates(older: &Update, newer: &Update) -> Update {
+    let mut events: Vec<GameEvent> = older.events.iter().filter(|event| event.is_important()).cloned().collect();
+    events.extend(newer.events.iter().cloned());
+    let mut logged_events = older.logged_events.clone();
+    logged_events.extend(newer.logged_events.iter().cloned());
+    Update {
+        events,
+        logged_events,
+        summary: newer.summary.clone().or_else(|| older.summary.clone()),
+        ..newer.clone()
+    }
+}
+
+/// Bounded drop-oldest queue behind a [`StateReceiver`].
+struct StateQueue {
+    items: Mutex<VecDeque<GameState>>,
+    ready: Condvar,
+}
+
+impl StateQueue {
+    fn push(&self, state: GameState) {
+        let mut items = lock(&self.items);
+        if items.len() == SUBSCRIBER_CAPACITY {
+            items.pop_front();
+        }
+        items.push_back(state);
+        self.ready.notify_one();
+    }
+}
+
+/// Receives every published state, see [`SharedState::subscribe_states`].
+pub struct StateReceiver {
+    queue: Arc<StateQueue>,
+}
+
+impl StateReceiver {
+    /// Block until the next state arrives.
+    pub fn recv(&self) -> GameState {
+        let mut items = lock(&self.queue.items);
+        loop {
+            if let Some(state) = items.pop_front() {
+                return state;
+            }
+            items = self.queue.ready.wait(items).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Block until the next state arrives or `timeout` elapses.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<GameState> {
+        let items = lock(&self.queue.items);
+        let (mut items, _) = self
+            .queue
+            .ready
+            .wait_timeout_while(items, timeout, |items| items.is_empty())
+            .unwrap_or_else(PoisonError::into_inner);
+        items.pop_front()
+    }
+
+    /// Take the next state if one is queued.
+    pub fn try_recv(&self) -> Option<GameState> {
+        lock(&self.queue.items).pop_front()
+    }
+}
+
+/// Game state shared between the ingest side and the web server.
+pub struct SharedState {
+    inner: Mutex<StateInner>,
+    /// Signalled whenever `inner.version` changes.
+    changed: Condvar,
+    broadcast_tx: broadcast::Sender<Arc<Update>>,
+    listeners: Mutex<Vec<Arc<ListenerQueue>>>,
+    /// Threads running the listeners, joined by [`close_listeners`](SharedState::close_listeners).
+    listener_threads: Mutex<Vec<JoinHandle<()>>>,
+    subscribers: Mutex<Vec<Weak<StateQueue>>>,
+    history: Mutex<History>,
+    event_log: Mutex<EventLog>,
+    game_log: Mutex<GameLog>,
+    summary: Mutex<SummaryTracker>,
+    settings: Mutex<Arc<LiveSettings>>,
+    reloader: OnceLock<Reloader>,
+    dedupe: Dedupe,
+    override_mode: OverrideMode,
+    rules: RuleSet,
+    /// Length of a time-out, see [`StateInner::track_timeout`].
+    timeout_length: Duration,
+    started: Instant,
+    pub metrics: Arc<Metrics>,
+}
+
+impl SharedState {
+    /// `history_capacity` is the number of published states kept for [`history`](Self::history);
+    /// `rules` decides when a team is in the bonus; time-outs last `timeout_length`.
+    pub fn new(dedupe: Dedupe, override_mode: OverrideMode, history_capacity: usize, rules: RuleSet, timeout_length: Duration) -> Self {
+        let (broadcast_tx, _) = broadcast::channel(BROADCAST_CAPACITY);
+        Self {
+            inner: Mutex::new(StateInner {
+                game: GameState::default(),
+                json: serde_json::to_string(&GameState::default()).unwrap_or_default(),
+                version: 0,
+                last_received: None,
+                last_heartbeat: None,
+                manual_override: false,
+                ticker: None,
+                last_event_id: 0,
+                restored: false,
+                source: None,
+                auto_reset: None,
+                last_run: None,
+                published_at_ms: None,
+                timezone: TimeZone::system(),
+            }),
+            changed: Condvar::new(),
+            broadcast_tx,
+            listeners: Mutex::new(Vec::new()),
+            listener_threads: Mutex::new(Vec::new()),
+            subscribers: Mutex::new(Vec::new()),
+            history: Mutex::new(History::new(history_capacity)),
+            event_log: Mutex::new(EventLog::new(EVENT_LOG_CAPACITY)),
+            game_log: Mutex::new(GameLog::default()),
+            summary: Mutex::new(SummaryTracker::default()),
+            settings: Mutex::new(Arc::new(LiveSettings::default())),
+            reloader: OnceLock::new(),
+            dedupe,
+            override_mode,
+            rules,
+            timeout_length,
+            started: Instant::now(),
+            metrics: Arc::new(Metrics::default()),
+        }
+    }
+
+    /// Snapshot of the current game state.
+    pub fn current(&self) -> GameState {
+        self.lock_current().game.clone()
+    }
+
+    /// [`current`](Self::current) together with its version.
+    pub fn current_versioned(&self) -> (GameState, u64) {
+        let inner = self.lock_current();
+        (inner.game.clone(), inner.version)
+    }
+
+    /// The current game state as sent to push clients.
+    pub fn current_json(&self) -> String {
+        self.lock_current().json.clone()
+    }
+
+    /// [`current_json`](Self::current_json) together with its version.
+    pub fn current_json_versioned(&self) -> (String, u64) {
+        let inner = self.lock_current();
+        (inner.json.clone(), inner.version)
+    }
+
+    /// Lock the state, without the overlay events that have expired.
+    fn lock_current(&self) -> MutexGuard<'_, StateInner> {
+        let mut inner = lock(&self.inner);
+        inner.prune_overlay_events();
+        inner
+    }
+
+    /// Time since the console last sent a frame, i.e. how old the current state is.
+    pub fn state_age(&self) -> Option<Duration> {
+        lock(&self.inner).last_received.map(|at| at.elapsed())
+    }
+
+    /// Unix milliseconds of the last published state; `None` until one is.
+    pub fn published_at_ms(&self) -> Option<u64> {
+        lock(&self.inner).published_at_ms
+    }
+
+    /// Zone the timestamps of the logs, exports and API are written in (`--timezone`).
+    pub fn timezone(&self) -> TimeZone {
+        lock(&self.inner).timezone.clone()
+    }
+
+    /// Whether the current state was restored from the state file (`--state-file`) at startup
+    /// and neither a frame nor the operator has changed it since.
+    pub fn restored(&self) -> bool {
+        lock(&self.inner).restored
+    }
+
+    /// Everything [`restore`](Self::restore) needs to pick the game up again after a restart.
+    pub(crate) fn snapshot(&self) -> Snapshot {
+        let inner = lock(&self.inner);
+        let event_log = lock(&self.event_log);
+        Snapshot {
+            saved_at_ms: unix_millis(),
+            state: inner.game.clone(),
+            version: inner.version,
+            last_overlay_event_id: inner.last_event_id,
+            events: event_log.entries().cloned().collect(),
+            last_event_id: event_log.last_id(),
+            game_log: lock(&self.game_log).rows().cloned().collect(),
+            summary: lock(&self.summary).clone(),
+        }
+    }
+
+    /// Take over a [`snapshot`](Self::snapshot) saved before a restart, without publishing it.
+    ///
+    /// The state is flagged stale, since the console may have moved on, until the next frame.
+    pub(crate) fn restore(&self, snapshot: Snapshot) {
+        let mut inner = lock(&self.inner);
+        let mut state = snapshot.state;
+        state.stale = true;
+        inner.json = serde_json::to_string(&state).unwrap_or_default();
+        inner.game = state;
+        inner.version = snapshot.version;
+        inner.last_event_id = snapshot.last_overlay_event_id;
+        inner.restored = true;
+        lock(&self.event_log).restore(snapshot.events, snapshot.last_event_id);
+        lock(&self.game_log).restore(snapshot.game_log);
+        *lock(&self.summary) = snapshot.summary;
+    }
+
+    /// Time since the shared state was created, i.e. since the server started.
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Version of the current state; increases with every published state.
+    pub fn version(&self) -> u64 {
+        lock(&self.inner).version
+    }
+
+    /// Block until the state version exceeds `version` or `timeout` elapses.
+    ///
+    /// Returns the state and its version, or `None` on timeout. The lock is
+    /// released while waiting.
+    pub fn wait_for_version(&self, version: u64, timeout: Duration) -> Option<(GameState, u64)> {
+        let inner = lock(&self.inner);
+        let (inner, _) = self
+            .changed
+            .wait_timeout_while(inner, timeout, |inner| inner.version <= version)
+            .unwrap_or_else(PoisonError::into_inner);
+        (inner.version > version).then(|| (inner.game.clone(), inner.version))
+    }
+
+    /// The published states selected by `filter`, oldest first.
+    pub(crate) fn history(&self, filter: &HistoryFilter) -> HistoryPage {
+        lock(&self.history).query(filter)
+    }
+
+    /// The first `limit` logged game events after id `since_id`, oldest first.
+    pub(crate) fn events_since(&self, since_id: u64, limit: usize) -> EventPage {
+        lock(&self.event_log).since(since_id, limit)
+    }
+
+    /// The state changes since the server started (up to the last
+    /// [`GAME_LOG_CAPACITY`](crate::game_log::GAME_LOG_CAPACITY)), as exported to CSV.
+    pub fn game_log(&self) -> GameLog {
+        lock(&self.game_log).clone()
+    }
+
+    /// Box score of the last finished game, kept until the next game starts.
+    pub fn game_summary(&self) -> Option<GameSummary> {
+        lock(&self.summary).summary().cloned()
+    }
+
+    /// Receive every state published from now on through a bounded, drop-oldest queue.
+    pub fn subscribe_states(&self) -> StateReceiver {
+        let queue = Arc::new(StateQueue {
+            items: Mutex::new(VecDeque::with_capacity(SUBSCRIBER_CAPACITY)),
+            ready: Condvar::new(),
+        });
+        lock(&self.subscribers).push(Arc::downgrade(&queue));
+        StateReceiver { queue }
+    }
+
+    /// When the last frame was received from the console, if ever.
+    pub fn last_received(&self) -> Option<Instant> {
+        lock(&self.inner).last_received
+    }
+
+    /// Record keepalive bytes received from the console link.
+    pub(crate) fn record_heartbeats(&self, count: usize) {
+        lock(&self.inner).last_heartbeat = Some(Instant::now());
+        self.metrics.heartbeats_received.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// Classify the console link by the age of the last frame and heartbeat.
+    pub fn link_status(&self, max_age: Duration) -> LinkStatus {
+        let inner = lock(&self.inner);
+        let recent = |at: Option<Instant>| at.is_some_and(|at| at.elapsed() <= max_age);
+        if recent(inner.last_received) {
+            LinkStatus::Active
+        } else if recent(inner.last_heartbeat) {
+            LinkStatus::Idle
+        } else {
+            LinkStatus::Dead
+        }
+    }
+
+    /// Link health and counters for the web status endpoint.
+    pub fn status(&self) -> StatusReport {
+        let (last_received, last_heartbeat, source) = {
+            let inner = lock(&self.inner);
+            (inner.last_received, inner.last_heartbeat, inner.source)
+        };
+        let age_ms = |at: Option<Instant>| at.map(|at| at.elapsed().as_millis() as u64);
+        StatusReport {
+            link: self.link_status(LINK_TIMEOUT),
+            last_frame_age_ms: age_ms(last_received),
+            last_heartbeat_age_ms: age_ms(last_heartbeat),
+            source,
+            metrics: self.metrics.snapshot(),
+        }
+    }
+
+    /// Receive every published update (used by the async push endpoints).
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<Update>> {
+        self.broadcast_tx.subscribe()
+    }
+
+    /// Register a callback receiving published updates on its own thread.
+    ///
+    /// With a non-zero `min_interval`, clock-only updates are coalesced so the
+    /// callback runs at most once per interval, while important events (score,
+    /// period, game state...) are delivered immediately. The last coalesced state
+    /// is always delivered once the interval elapses.
+    ///
+    /// A callback slower than the updates falls behind without losing any event: past
+    /// 64 queued updates, new ones are merged into the last queued one (counted as
+    /// `listener_updates_coalesced` in [`status`](Self::status)).
+    pub fn add_listener<F>(&self, min_interval: Duration, mut callback: F)
+    where
+        F: FnMut(&Update) + Send + 'static,
+    {
+        let queue = Arc::new(ListenerQueue::default());
+        lock(&self.listeners).push(Arc::clone(&queue));
+
+        let metrics = Arc::clone(&self.metrics);
+        let mut deliver = move |update: &Update| {
+            isolate("update listener", &metrics, || callback(update));
+        };
+
+        let handle = thread::spawn(move || {
+            let mut debouncer = Debouncer::new(min_interval);
+            loop {
+                let ready = match queue.recv(debouncer.deadline()) {
+                    Received::Update(update) => debouncer.offer(Arc::unwrap_or_clone(update), Instant::now()),
+                    Received::Timeout => debouncer.flush(Instant::now()),
+                    Received::Closed => {
+                        if let Some(update) = debouncer.flush(Instant::now()) {
+                            deliver(&update);
+                        }
+                        return;
+                    }
+                };
+
+                if let Some(update) = ready {
+                    deliver(&update);
+                }
+            }
+        });
+        lock(&self.listener_threads).push(handle);
+    }
+
+    /// The settings in effect, see [`LiveSettings`].
+    pub fn settings(&self) -> Arc<LiveSettings> {
+        Arc::clone(&lock(&self.settings))
+    }
+
+    /// Replace the settings at once; requests already being answered keep the old ones.
+    pub fn set_settings(&self, settings: LiveSettings) {
+        *lock(&self.settings) = Arc::new(settings);
+    }
+
+    /// Enable [`reload`](Self::reload). Returns `false` if a reloader was already set.
+    pub fn set_reloader(&self, reloader: Reloader) -> bool {
+        self.reloader.set(reloader).is_ok()
+    }
+
+    /// Read the configuration again and apply what can change without a restart, see [`Reloader`].
+    pub fn reload(&self) -> Result<ReloadReport, String> {
+        match self.reloader.get() {
+            Some(reloader) => reloader.reload(self),
+            None => Err("reloading needs a server started from the command line".to_string()),
+        }
+    }
+
+    /// Stop the listeners at shutdown and wait up to `timeout` for them to finish.
+    ///
+    /// Each listener still gets the updates queued for it, then its callback is dropped,
+    /// which flushes and closes the files it writes. Later updates reach no listener.
+    /// Returns whether every listener finished in time.
+    pub fn close_listeners(&self, timeout: Duration) -> bool {
+        for queue in lock(&self.listeners).drain(..) {
+            queue.close();
+        }
+        let threads = std::mem::take(&mut *lock(&self.listener_threads));
+        let deadline = Instant::now() + timeout;
+        while threads.iter().any(|thread| !thread.is_finished()) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let mut finished = true;
+        for thread in threads {
+            if thread.is_finished() {
+                let _ = thread.join();
+            } else {
+                finished = false;
+            }
+        }
+        finished
+    }
+
+    /// Apply a state derived from a freshly received frame.
+    ///
+    /// The receive timestamp is always refreshed, but duplicates (per the configured
+    /// [`Dedupe`] mode) are not published. Returns whether the state was published.
+    pub fn update(&self, state: GameState) -> bool {
+        self.receive(state, None)
+    }
+
+    /// [`update`](Self::update) with a state from a frame read on `source` (`tcp`, `udp`...),
+    /// which the status and the published [`Update`] then name.
+    pub fn update_from(&self, state: GameState, source: &'static str) -> bool {
+        self.receive(state, Some(source))
+    }
+
+    fn receive(&self, mut state: GameState, source: Option<&'static str>) -> bool {
+        let mut inner = lock(&self.inner);
+        inner.last_received = Some(Instant::now());
+        inner.restored = false;
+        // Teams and match details only come from the operator; don't let a frame built before
+        // a change undo them
+        state.teams = inner.game.teams.clone();
+        state.match_info = inner.game.match_info.clone();
+        let new_game = inner.auto_reset_due(&state);
+        if clock::is_running(&state) {
+            inner.last_run = Some(Instant::now());
+        }
+
+        if inner.manual_override {
+            match self.override_mode {
+                // A new game on the console ends an override left over from the last one. Until
+                // then the frame is ignored before it reaches the ticker, which keeps counting
+                // the operator's clock
+                OverrideMode::Hold if !new_game => return false,
+                OverrideMode::Hold => {
+                    info!("New game on the console, manual override released");
+                    inner.manual_override = false;
+                }
+                OverrideMode::Resume => {
+                    info!("Console frame received, manual override released");
+                    inner.manual_override = false;
+                }
+            }
+        }
+        if let (Some(source), Some(previous)) = (source, inner.source) {
+            if source != previous {
+                debug!("Frames now come from {} input (was {})", source, previous);
+            }
+        }
+        inner.source = source.or(inner.source);
+        inner.sync_clock(&mut state);
+        self.derive_bonus(&mut state);
+
+        let duplicate = match self.dedupe {
+            _ if new_game => false,
+            Dedupe::Off => false,
+            Dedupe::Exact => inner.game == state,
+            Dedupe::IgnoreClock => inner.game.eq_ignoring_clock(&state),
+        };
+        if duplicate {
+            inner.store(state);
+            self.metrics.duplicates_suppressed.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+
+        let mut events = events::diff(&inner.game, &state);
+        inner.track_timeout(&mut state, &mut events, self.timeout_length);
+        if new_game {
+            self.start_game(&mut inner, state, events, "idle");
+        } else {
+            self.publish(&mut inner, state, events);
+        }
+        true
+    }
+
+    /// Publish a state entered by the operator and mark it as a manual override.
+    ///
+    /// `edit` receives the current state to modify (or replace); if it fails nothing is published.
+    pub fn set_manual<E>(&self, edit: impl FnOnce(&mut GameState) -> Result<(), E>) -> Result<(), E> {
+        let mut inner = lock(&self.inner);
+        let mut state = inner.game.clone();
+        edit(&mut state)?;
+        inner.sync_clock(&mut state);
+        self.derive_bonus(&mut state);
+        if clock::is_running(&state) {
+            inner.last_run = Some(Instant::now());
+        }
+        inner.manual_override = true;
+        inner.restored = false;
+        inner.source = None;
+        let mut events = events::diff(&inner.game, &state);
+        inner.track_timeout(&mut state, &mut events, self.timeout_length);
+        self.publish(&mut inner, state, events);
+        Ok(())
+    }
+
+    /// Set the bonus of both teams from the opposing team fouls.
+    fn derive_bonus(&self, state: &mut GameState) {
+        state.home_bonus = self.rules.bonus(numeric_value(&state.away_fouls));
+        state.away_bonus = self.rules.bonus(numeric_value(&state.home_fouls));
+    }
+
+    /// Publish new team details; unlike [`set_manual`](Self::set_manual) this is not an override.
+    pub fn set_teams(&self, teams: Teams) {
+        let mut inner = lock(&self.inner);
+        let mut state = inner.game.clone();
+        state.teams = teams;
+        let events = events::diff(&inner.game, &state);
+        self.publish(&mut inner, state, events);
+    }
+
+    /// Publish new match details, like [`set_teams`](Self::set_teams).
+    pub fn set_match(&self, info: MatchInfo) {
+        let mut inner = lock(&self.inner);
+        let mut state = inner.game.clone();
+        state.match_info = info;
+        let events = events::diff(&inner.game, &state);
+        self.publish(&mut inner, state, events);
+    }
+
+    /// Close out the game being tracked and start a new one (`POST /api/control/new-game`).
+    ///
+    /// The current state is published again with a [`GameEvent::NewGame`]: the summary of the
+    /// game is built unless the `Final` period already did, and the game log and line score
+    /// start again. The console's next frames carry on from it.
+    pub fn new_game(&self) {
+        let mut inner = lock(&self.inner);
+        let state = inner.game.clone();
+        self.start_game(&mut inner, state, Vec::new(), "manual");
+    }
+
+    /// Let console frames update the state again.
+    pub fn release_manual(&self) {
+        lock(&self.inner).manual_override = false;
+    }
+
+    /// Whether the current state was set by the operator.
+    pub fn manual_override(&self) -> bool {
+        lock(&self.inner).manual_override
+    }
+
+    /// Interpolate the clock between frames from now on, see [`ClockTicker`].
+    ///
+    /// [`tick_clock`](Self::tick_clock) must then be called regularly, e.g. by [`clock::start_ticker`](crate::clock::start_ticker).
+    pub(crate) fn enable_clock_ticker(&self) {
+        let mut inner = lock(&self.inner);
+        let mut ticker = ClockTicker::default();
+        let now = Instant::now();
+        ticker.sync(&inner.game, now);
+        ticker.sync_shot_clock(&inner.game, now);
+        let mut state = inner.game.clone();
+        state.display_clock = ticker.display(now).unwrap_or_else(|| state.time.clone());
+        state.display_shot_clock = ticker.display_shot_clock(now).unwrap_or_else(|| state.shot_clock.clone());
+        inner.store(state);
+        inner.ticker = Some(ticker);
+    }
+
+    /// Start a new game when a console frame looks like one after the clock hasn't run for
+    /// `idle` (`--auto-reset`), see [`StateInner::auto_reset_due`].
+    pub(crate) fn enable_auto_reset(&self, idle: Duration) {
+        lock(&self.inner).auto_reset = Some(idle);
+    }
+
+    /// Write timestamps in `zone` rather than the system's (`--timezone`).
+    pub(crate) fn set_timezone(&self, zone: TimeZone) {
+        lock(&self.inner).timezone = zone;
+    }
+
+    /// Publish the interpolated clocks if a displayed value changed since the last tick.
+    ///
+    /// Nothing ticks while the input is stale. With [`Dedupe::IgnoreClock`] the new values are
+    /// stored for the JSON API but not pushed, like a console clock change.
+    pub(crate) fn tick_clock(&self) {
+        let mut inner = lock(&self.inner);
+        if inner.game.stale {
+            return;
+        }
+        let Some(ticker) = &inner.ticker else {
+            return;
+        };
+        let now = Instant::now();
+        let display = ticker.display(now).unwrap_or_else(|| inner.game.display_clock.clone());
+        let display_shot_clock = ticker.display_shot_clock(now).unwrap_or_else(|| inner.game.display_shot_clock.clone());
+        if display == inner.game.display_clock && display_shot_clock == inner.game.display_shot_clock {
+            return;
+        }
+
+        let mut state = inner.game.clone();
+        state.display_clock = display;
+        state.display_shot_clock = display_shot_clock;
+        if self.dedupe == Dedupe::IgnoreClock {
+            inner.store(state);
+            return;
+        }
+        self.publish(&mut inner, state, Vec::new());
+    }
+
+    /// Publish the time-out countdown when its remaining seconds changed, and its end.
+    ///
+    /// Called regularly by [`clock::start_timeout_countdown`]. With [`Dedupe::IgnoreClock`] the
+    /// seconds are stored for the JSON API but not pushed, like clock ticks; the end always is.
+    pub(crate) fn tick_timeout(&self) {
+        let mut inner = lock(&self.inner);
+        if inner.game.active_timeout.is_none() {
+            return;
+        }
+        let mut state = inner.game.clone();
+        let mut events = Vec::new();
+        inner.track_timeout(&mut state, &mut events, self.timeout_length);
+        if state.active_timeout == inner.game.active_timeout {
+            return;
+        }
+        if events.is_empty() && self.dedupe == Dedupe::IgnoreClock {
+            inner.store(state);
+            return;
+        }
+        self.publish(&mut inner, state, events);
+    }
+
+    /// Keep the last state on screen but flag it as stale (e.g. the input stream ended).
+    pub fn mark_stale(&self) {
+        let mut inner = lock(&self.inner);
+        let mut state = inner.game.clone();
+        state.stale = true;
+        let events = events::diff(&inner.game, &state);
+        self.publish(&mut inner, state, events);
+    }
+
+    // Publish `state` as the first of a new game, `events` (what changed it) following a
+    // [`GameEvent::NewGame`] for `reason`; see `publish` for what that closes out. Listeners
+    // close their game on the event (game files, summary webhook) and the state file is saved
+    // with the new game at once.
+    fn start_game(&self, inner: &mut StateInner, state: GameState, mut events: Vec<GameEvent>, reason: &str) {
+        inner.last_run = clock::is_running(&state).then(Instant::now);
+        info!("New game ({})", reason);
+        events.insert(0, GameEvent::NewGame { reason: reason.to_string() });
+        self.publish(inner, state, events);
+    }
+
+    // Store `state` as the new current state and push it to every consumer.
+    // Called with the state lock held so updates reach subscribers in order.
+    //
+    // With a [`GameEvent::NewGame`] the game being played is closed out as the current state
+    // shows it: its summary is built if the `Final` period didn't already, and the game log
+    // and line score start again empty.
+    fn publish(&self, inner: &mut StateInner, mut state: GameState, events: Vec<GameEvent>) {
+        let new_game = events.iter().any(|event| matches!(event, GameEvent::NewGame { .. }));
+        inner.attach_overlay_events(&mut state, &events);
+        state.line_score = if new_game {
+            Vec::new()
+        } else {
+            line_score::update(&inner.game.line_score, &inner.game, &state)
+        };
+        let previous = std::mem::replace(&mut inner.game, state.clone());
+        inner.version += 1;
+        self.changed.notify_all();
+
+        lock(&self.subscribers).retain(|queue| match queue.upgrade() {
+            Some(queue) => {
+                queue.push(state.clone());
+                true
+            }
+            None => false,
+        });
+
+        inner.json = serde_json::to_string(&state).unwrap_or_default();
+        let timestamp_ms = unix_millis();
+        inner.published_at_ms = Some(timestamp_ms);
+        let logged_events = lock(&self.event_log).append(&events, inner.version, timestamp_ms);
+        let mut game_log = lock(&self.game_log);
+        if new_game {
+            *game_log = GameLog::default();
+        }
+        game_log.record(timestamp_ms, &state, &events);
+        drop(game_log);
+        let summary = {
+            let mut tracker = lock(&self.summary);
+            let closed = if new_game { tracker.close(&previous, timestamp_ms, &inner.timezone).cloned() } else { None };
+            closed.or_else(|| tracker.observe(&state, &events, timestamp_ms, &inner.timezone).cloned())
+        };
+        if let Some(summary) = &summary {
+            info!("Game over: {}", summary.headline());
+        }
+        let json = serde_json::to_string(&PushedState {
+            state: &state,
+            changes: &logged_events,
+        })
+        .unwrap_or_default();
+        let update = Arc::new(Update {
+            state,
+            events,
+            logged_events,
+            version: inner.version,
+            timestamp_ms,
+            json,
+            source: inner.source,
+            summary,
+        });
+
+        lock(&self.history).push(HistoryEntry {
+            timestamp_ms,
+            update: Arc::clone(&update),
+        });
+
+        let _ = self.broadcast_tx.send(Arc::clone(&update));
+
+        for queue in lock(&self.listeners).iter() {
+            queue.push(&update, &self.metrics);
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{
+    config::StatsdConfig,
+    metrics::{isolate, Metrics},
+    state::SharedState,
+};
+use std::{
+    fmt::Write as _,
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    sync::{atomic::Ordering, Arc},
+    thread,
+    time::Duration,
+};
+use tracing::{info, warn};
+
+/// Largest datagram sent: lines are packed up to this size, which fits a common network's MTU.
+pub const MAX_PACKET: usize = 1432;
+
+/// Pack StatsD lines into as few datagrams as fit, one line per line of text.
+pub fn packets(lines: &[String]) -> Vec<String> {
+    let mut packets: Vec<String> = Vec::new();
+    for line in lines {
+        match packets.last_mut() {
+            Some(packet) if packet.len() + 1 + line.len() <= MAX_PACKET => {
+                packet.push('\n');
+                packet.push_str(line);
+            }
+            _ => packets.push(line.clone()),
+        }
+    }
+    packets
+}
+
+/// `ScoreChange` -> `score_change`, as event names go in a metric name.
+fn snake_case(kind: &str) -> String {
+    let mut name = String::with_capacity(kind.len() + 4);
+    for (i, c) in kind.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            name.push('_');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    name
+}
+
+/// Sends StatsD datagrams to one target, logging failures once per failure streak.
+struct Sender {
+    socket: UdpSocket,
+    target: SocketAddr,
+    failing: bool,
+}
+
+impl Sender {
+    fn send(&mut self, lines: &[String]) {
+        for packet in packets(lines) {
+            // A full socket buffer or an unreachable target only loses the datagram
+            match self.socket.send_to(packet.as_bytes(), self.target) {
+                Ok(_) if self.failing => {
+                    info!("Sending StatsD metrics to {} again", self.target);
+                    self.failing = false;
+                }
+                Ok(_) => {}
+                Err(e) if !self.failing => {
+                    warn!("Failed to send StatsD metrics to {}: {}", self.target, e);
+                    self.failing = true;
+                }
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+/// Totals behind the counters, to send what they grew by since the last flush.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Totals {
+    frames: u64,
+    frame_errors: u64,
+    resyncs: u64,
+    bytes: u64,
+    heartbeats: u64,
+    duplicates: u64,
+}
+
+impl Totals {
+    fn read(metrics: &Metrics) -> Self {
+        let mut totals = Self {
+            heartbeats: metrics.heartbeats_received.load(Ordering::Relaxed),
+            duplicates: metrics.duplicates_suppressed.load(Ordering::Relaxed),
+            ..Self::default()
+        };
+        for peer in metrics.peer_snapshots().values() {
+            totals.frames += peer.frames;
+            totals.frame_errors += peer.errors;
+            totals.resyncs += peer.resyncs;
+            totals.bytes += peer.bytes;
+        }
+        totals
+    }
+}
+
+/// The lines of one flush: counters grown since `previous`, gauges, and the sampled frame timings.
+fn flush_lines(prefix: &str, shared: &SharedState, previous: &mut Totals, sample_every: u64) -> Vec<String> {
+    let totals = Totals::read(&shared.metrics);
+    let mut lines = Vec::new();
+    let counters = [
+        ("frames", totals.frames, previous.frames),
+        ("frame_errors", totals.frame_errors, previous.frame_errors),
+        ("resyncs", totals.resyncs, previous.resyncs),
+        ("bytes", totals.bytes, previous.bytes),
+        ("heartbeats", totals.heartbeats, previous.heartbeats),
+        ("duplicates", totals.duplicates, previous.duplicates),
+    ];
+    for (name, now, before) in counters {
+        if now > before {
+            lines.push(format!("{}.{}:{}|c", prefix, name, now - before));
+        }
+    }
+    *previous = totals;
+
+    let metrics = &shared.metrics;
+    lines.push(format!("{}.connections:{}|g", prefix, metrics.ingest_connected.load(Ordering::Relaxed)));
+    lines.push(format!("{}.ws_clients:{}|g", prefix, metrics.ws_clients.load(Ordering::Relaxed)));
+    if let Some(age) = shared.state_age() {
+        lines.push(format!("{}.update_age_ms:{}|g", prefix, age.as_millis()));
+    }
+
+    let rate = match sample_every {
+        0 | 1 => String::new(),
+        every => format!("|@{}", 1.0 / every as f64),
+    };
+    for timing in metrics.take_frame_timings() {
+        let mut line = format!("{}.frame_ms:", prefix);
+        let _ = write!(line, "{:.3}|ms{}", timing.as_secs_f64() * 1000.0, rate);
+        lines.push(line);
+    }
+    lines
+}
+
+/// Send StatsD metrics to `config.host:port` (`--statsd`): counters and gauges every
+/// `config.interval` from a timer thread of their own, and a counter per important game event
+/// as it happens. The target is looked up once, here.
+pub fn start(config: &StatsdConfig, shared: &Arc<SharedState>) -> io::Result<()> {
+    let target = (config.host.as_str(), config.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} has no address", config.host)))?;
+    let local: SocketAddr = if target.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+    let socket = UdpSocket::bind(local)?;
+    // Never wait on the network: a datagram that can't go at once is lost
+    socket.set_nonblocking(true)?;
+    info!("Sending StatsD metrics to {} as {}.*", target, config.prefix);
+
+    let sample_every = if config.sample_rate > 0.0 { (1.0 / config.sample_rate).round().max(1.0) as u64 } else { 0 };
+    shared.metrics.sample_frame_timings(sample_every);
+
+    let mut events = Sender { socket: socket.try_clone()?, target, failing: false };
+    let prefix = config.prefix.clone();
+    shared.add_listener(Duration::ZERO, move |update| {
+        let lines: Vec<String> = update
+            .events
+            .iter()
+            .filter(|event| event.is_important())
+            .map(|event| format!("{}.events.{}:1|c", prefix, snake_case(event.kind())))
+            .collect();
+        if !lines.is_empty() {
+            events.send(&lines);
+        }
+    });
+
+    let mut timer = Sender { socket, target, failing: false };
+    let (prefix, interval, shared) = (config.prefix.clone(), config.interval, Arc::clone(shared));
+    thread::spawn(move || {
+        isolate("StatsD emitter", &shared.metrics, || {
+            let mut totals = Totals::default();
+            loop {
+                thread::sleep(interval);
+                timer.send(&flush_lines(&prefix, &shared, &mut totals, sample_every));
+            }
+        });
+    });
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+/// Largest accepted custom stylesheet, in bytes.
+pub const MAX_CUSTOM_CSS: usize = 32 * 1024;
+
+/// `Content-Security-Policy` of the overlay pages: the custom CSS may restyle them, but
+/// no script runs that isn't one of the server's own files.
+pub const OVERLAY_CSP: &str = "default-src 'self'; script-src 'self'; style-src 'self' 'unsafe-inline'; \
+    img-src 'self' data: http: https:; font-src 'self' data:; connect-src 'self' ws: wss:; \
+    object-src 'none'; base-uri 'none'; form-action 'none'";
+
+/// Production CSS added to every overlay page (`--custom-css`, `PUT /api/style`).
+///
+/// Kept in memory and saved to its file on every change, so a restart restores it.
+#[derive(Debug)]
+pub struct CustomCss {
+    path: PathBuf,
+    css: RwLock<String>,
+}
+
+impl CustomCss {
+    /// The stylesheet saved in `path`; empty when the file doesn't exist yet.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let css = match fs::read_to_string(path) {
+            Ok(css) => css,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        validate(&css).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Self::new(path, css))
+    }
+
+    /// An empty stylesheet that [`set`](Self::set) will save to `path`.
+    pub fn empty(path: &Path) -> Self {
+        Self::new(path, String::new())
+    }
+
+    fn new(path: &Path, css: String) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            css: RwLock::new(css),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self) -> String {
+        self.css.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Save `css` to the file, replacing it atomically, then use it for the next pages served.
+    pub fn set(&self, css: String) -> io::Result<()> {
+        let tmp = self.path.with_extension("css.tmp");
+        fs::write(&tmp, &css)?;
+        fs::rename(&tmp, &self.path)?;
+        *self.css.write().unwrap_or_else(|e| e.into_inner()) = css;
+        Ok(())
+    }
+
+    /// `html` with the stylesheet as its last `<style>` element, just before `</head>`,
+    /// or `None` when there is nothing to add (or no `</head>` to add it to).
+    pub fn inject(&self, html: &[u8]) -> Option<Vec<u8>> {
+        let css = self.get();
+        if css.trim().is_empty() {
+            return None;
+        }
+        let end = html.windows(7).position(|window| window.eq_ignore_ascii_case(b"</head>"))?;
+        let mut injected = Vec::with_capacity(html.len() + css.len() + 40);
+        injected.extend_from_slice(&html[..end]);
+        injected.extend_from_slice(b"<style id=\"custom-css\">\n");
+        injected.extend_from_slice(css.as_bytes());
+        injected.extend_from_slice(b"\n</style>\n");
+        injected.extend_from_slice(&html[end..]);
+        Some(injected)
+    }
+}
+
+/// Check a stylesheet before it is stored: at most [`MAX_CUSTOM_CSS`] bytes, and nothing
+/// that would close the `<style>` element it is injected into. The CSS itself is not parsed.
+pub fn validate(css: &str) -> Result<(), String> {
+    if css.len() > MAX_CUSTOM_CSS {
+        return Err(format!("Custom CSS is {} bytes, at most {} are allowed", css.len(), MAX_CUSTOM_CSS));
+    }
+    if css.to_ascii_lowercase().contains("</style") {
+        return Err("Custom CSS must not contain </style".to_string());
+    }
+    Ok(())
+}
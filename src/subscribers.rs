@@ -0,0 +1,125 @@
+use crate::{
+    metrics::Metrics,
+    state::{lock, SharedState},
+};
+use std::{
+    io::Write,
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::Ordering,
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+/// Lines buffered per subscriber before it counts as backpressured.
+const QUEUE_CAPACITY: usize = 64;
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Subscriber {
+    peer: SocketAddr,
+    tx: SyncSender<Arc<[u8]>>,
+    /// Kept to shut the socket down when the subscriber is dropped.
+    stream: TcpStream,
+    /// Since when the queue has been full, if it is.
+    full_since: Option<Instant>,
+}
+
+/// Streams every published state to TCP subscribers as one JSON object per line.
+///
+/// The current state is sent first on connect, so subscribers don't have to
+/// wait for the next frame from the console.
+///
+/// Each subscriber has its own writer thread and bounded queue, so a slow
+/// subscriber never delays the others. One whose queue stays full for longer
+/// than `stall_limit` is disconnected.
+pub fn start(address: &str, shared: &Arc<SharedState>, stall_limit: Duration) -> std::io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    info!("Streaming states to subscribers on {}", address);
+
+    let subscribers: Arc<Mutex<Vec<Subscriber>>> = Arc::new(Mutex::new(Vec::new()));
+
+    let metrics = Arc::clone(&shared.metrics);
+    let fan_out = Arc::clone(&subscribers);
+    shared.add_listener(Duration::ZERO, move |update| {
+        let line: Arc<[u8]> = format!("{}\n", update.json).into_bytes().into();
+        let now = Instant::now();
+        lock(&fan_out).retain_mut(|subscriber| match subscriber.tx.try_send(Arc::clone(&line)) {
+            Ok(()) => {
+                subscriber.full_since = None;
+                true
+            }
+            Err(TrySendError::Full(_)) => {
+                let since = *subscriber.full_since.get_or_insert(now);
+                if now.duration_since(since) < stall_limit {
+                    return true;
+                }
+                warn!("Dropping subscriber {}: not reading for {:?}", subscriber.peer, now.duration_since(since));
+                metrics.peers_dropped_backpressure.fetch_add(1, Ordering::Relaxed);
+                let _ = subscriber.stream.shutdown(Shutdown::Both);
+                false
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+    });
+
+    let shared = Arc::clone(shared);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream.and_then(|stream| accept(stream, &subscribers, &shared)) {
+                Ok(()) => {}
+                Err(e) => error!("Error accepting subscriber: {}", e),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn accept(stream: TcpStream, subscribers: &Mutex<Vec<Subscriber>>, shared: &SharedState) -> std::io::Result<()> {
+    let peer = stream.peer_addr()?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let _ = stream.set_nodelay(true);
+    info!("New subscriber: {}", peer);
+
+    let (tx, rx) = mpsc::sync_channel(QUEUE_CAPACITY);
+    let writer = stream.try_clone()?;
+    let metrics = Arc::clone(&shared.metrics);
+    thread::spawn(move || run_writer(peer, writer, rx, &metrics));
+
+    // Queue the current state while holding the list, so any newer state the fan-out
+    // is about to send can only come after it
+    let mut subscribers = lock(subscribers);
+    let current: Arc<[u8]> = format!("{}\n", shared.current_json()).into_bytes().into();
+    let _ = tx.try_send(current);
+    subscribers.push(Subscriber {
+        peer,
+        tx,
+        stream,
+        full_since: None,
+    });
+    Ok(())
+}
+
+// Writer loop for a single subscriber: drain its queue until the socket fails.
+fn run_writer(peer: SocketAddr, mut stream: TcpStream, rx: Receiver<Arc<[u8]>>, metrics: &Metrics) {
+    // The sender is dropped when the subscriber is removed from the fan-out.
+    while let Ok(line) = rx.recv() {
+        match stream.write_all(&line) {
+            Ok(()) => {}
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {
+                warn!("Dropping subscriber {}: write blocked for {:?}", peer, WRITE_TIMEOUT);
+                metrics.peers_dropped_backpressure.fetch_add(1, Ordering::Relaxed);
+                break;
+            }
+            Err(e) => {
+                info!("Subscriber {} disconnected: {}", peer, e);
+                break;
+            }
+        }
+    }
+    let _ = stream.shutdown(Shutdown::Both);
+}
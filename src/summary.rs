@@ -0,0 +1,217 @@
+use crate::{
+    events::{GameEvent, Side},
+    http_client,
+    line_score::PeriodScore,
+    match_info::MatchInfo,
+    numeric_value,
+    state::SharedState,
+    teams::Teams,
+    timezone::TimeZone,
+    GameState,
+};
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
+use tracing::{error, info, warn};
+
+/// How long the summary webhook gets to answer.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Period name the operator sets when the game is over.
+pub const FINAL_PERIOD: &str = "Final";
+
+/// Fouls and time-outs of one team over the whole game.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamTotals {
+    /// Team fouls of every period added up (the console resets them each period).
+    pub fouls: u32,
+    /// Time-outs called.
+    pub timeouts_used: u32,
+    /// Largest lead the team had, in points.
+    pub largest_lead: u32,
+}
+
+/// Box score of a finished game, built when the operator sets the `Final` period.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameSummary {
+    pub teams: Teams,
+    /// Competition, round, venue, tip-off and officials as set when the game ended.
+    #[serde(default)]
+    pub match_info: MatchInfo,
+    pub home_score: Option<u32>,
+    pub away_score: Option<u32>,
+    /// Points per period, as on the overlay's line score.
+    pub line_score: Vec<PeriodScore>,
+    pub home: TeamTotals,
+    pub away: TeamTotals,
+    /// Times a team took the lead from the other one (a tie in between doesn't count as a lead).
+    pub lead_changes: u32,
+    /// Unix milliseconds of the tip-off (first running clock) and of the `Final` period.
+    pub started_at_ms: u64,
+    pub ended_at_ms: u64,
+    /// The same times as RFC 3339 in the server's zone (`--timezone`): `2026-03-14T20:05:42.250+01:00`.
+    #[serde(default)]
+    pub started_at: String,
+    #[serde(default)]
+    pub ended_at: String,
+    /// Wall-clock length of the game, breaks included.
+    pub duration_secs: u64,
+}
+
+impl GameSummary {
+    /// `CAB 80 - 74 SLB, 3 lead changes, 1h 52m`
+    pub fn headline(&self) -> String {
+        let team = |name: &str, fallback: &'static str| if name.is_empty() { fallback.to_string() } else { name.to_string() };
+        let score = |score: Option<u32>| score.map_or("-".to_string(), |score| score.to_string());
+        format!(
+            "{} {} - {} {}, {} lead changes, {}h {:02}m",
+            team(&self.teams.home.abbrev, "HOME"),
+            score(self.home_score),
+            score(self.away_score),
+            team(&self.teams.away.abbrev, "AWAY"),
+            self.lead_changes,
+            self.duration_secs / 3600,
+            self.duration_secs / 60 % 60
+        )
+    }
+}
+
+/// What [`SummaryTracker`] has counted of the game being played.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Tally {
+    started_at_ms: u64,
+    home: TeamTotals,
+    away: TeamTotals,
+    lead_changes: u32,
+    /// Team that led last, ties left out.
+    leader: Option<Side>,
+}
+
+/// Follows the published states and builds the [`GameSummary`] at the end of the game.
+///
+/// A game starts with the first running clock after the previous summary (or after the
+/// server started); its summary is kept until then.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SummaryTracker {
+    tally: Option<Tally>,
+    summary: Option<GameSummary>,
+}
+
+impl SummaryTracker {
+    /// The summary of the last finished game, until the next one starts.
+    pub fn summary(&self) -> Option<&GameSummary> {
+        self.summary.as_ref()
+    }
+
+    /// Whether a game has started and isn't over yet.
+    pub fn in_progress(&self) -> bool {
+        self.tally.is_some()
+    }
+
+    /// Count a state published at `timestamp_ms` with the `events` that produced it.
+    ///
+    /// Returns the summary when this state ended the game, its times written in `zone`.
+    pub fn observe(&mut self, state: &GameState, events: &[GameEvent], timestamp_ms: u64, zone: &TimeZone) -> Option<&GameSummary> {
+        let over = state.period_name.trim() == FINAL_PERIOD;
+        if self.tally.is_none() {
+            if over || state.game_state != "running" {
+                return None;
+            }
+            self.summary = None;
+            self.tally = Some(Tally {
+                started_at_ms: timestamp_ms,
+                ..Tally::default()
+            });
+        }
+        let tally = self.tally.as_mut()?;
+
+        // Fouls going back to 0 are the console's reset for the next period (it sends them
+        // ahead of the new period); any other decrease is the operator correcting a foul.
+        for event in events {
+            match event {
+                GameEvent::FoulChange { side, from: Some(from), to: Some(to) } if *to > 0 => {
+                    let totals = tally.side(*side);
+                    totals.fouls = (totals.fouls + to).saturating_sub(*from);
+                }
+                GameEvent::TimeoutCalled { side } => tally.side(*side).timeouts_used += 1,
+                _ => {}
+            }
+        }
+
+        if let (Some(home), Some(away)) = (numeric_value(&state.home_score), numeric_value(&state.away_score)) {
+            let leader = match home.cmp(&away) {
+                std::cmp::Ordering::Greater => Some(Side::Home),
+                std::cmp::Ordering::Less => Some(Side::Away),
+                std::cmp::Ordering::Equal => None,
+            };
+            if let Some(leader) = leader {
+                if tally.leader.is_some_and(|last| last != leader) {
+                    tally.lead_changes += 1;
+                }
+                tally.leader = Some(leader);
+            }
+            tally.home.largest_lead = tally.home.largest_lead.max(home.saturating_sub(away));
+            tally.away.largest_lead = tally.away.largest_lead.max(away.saturating_sub(home));
+        }
+
+        if !over {
+            return None;
+        }
+        self.close(state, timestamp_ms, zone)
+    }
+
+    /// End the game being played as it stands in `state`, e.g. when a new game is started
+    /// before the operator set the `Final` period. Returns its summary, or `None` if no game
+    /// was in progress.
+    pub fn close(&mut self, state: &GameState, timestamp_ms: u64, zone: &TimeZone) -> Option<&GameSummary> {
+        let tally = self.tally.take()?;
+        let ended_at_ms = timestamp_ms.max(tally.started_at_ms);
+        self.summary = Some(GameSummary {
+            teams: state.teams.clone(),
+            match_info: state.match_info.clone(),
+            home_score: numeric_value(&state.home_score),
+            away_score: numeric_value(&state.away_score),
+            line_score: state.line_score.clone(),
+            home: tally.home,
+            away: tally.away,
+            lead_changes: tally.lead_changes,
+            started_at_ms: tally.started_at_ms,
+            ended_at_ms,
+            started_at: zone.rfc3339(tally.started_at_ms),
+            ended_at: zone.rfc3339(ended_at_ms),
+            duration_secs: (ended_at_ms - tally.started_at_ms) / 1000,
+        });
+        self.summary.as_ref()
+    }
+}
+
+impl Tally {
+    fn side(&mut self, side: Side) -> &mut TeamTotals {
+        match side {
+            Side::Home => &mut self.home,
+            Side::Away => &mut self.away,
+        }
+    }
+}
+
+/// POST every game summary as JSON to the `--summary-webhook` URL in effect, from a state listener.
+pub fn start_webhook(shared: &Arc<SharedState>) {
+    let state = Arc::clone(shared);
+    shared.add_listener(Duration::ZERO, move |update| {
+        let Some(summary) = &update.summary else {
+            return;
+        };
+        let Some(url) = state.settings().summary_webhook.clone() else {
+            return;
+        };
+        let body = serde_json::to_vec(summary).unwrap_or_default();
+        match http_client::post(&url, "application/json", &[], &body, WEBHOOK_TIMEOUT) {
+            Ok(status) if (200..300).contains(&status) => info!("Game summary posted to {}{}", url.host, url.path),
+            Ok(status) => warn!("Summary webhook {}{} answered {}", url.host, url.path, status),
+            Err(e) => error!("Failed to post the game summary to {}{}: {}", url.host, url.path, e),
+        }
+    });
+}
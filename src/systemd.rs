@@ -0,0 +1,220 @@
+use crate::{
+    metrics::LoopState,
+    state::{unix_millis, LinkStatus, SharedState, LINK_TIMEOUT},
+};
+use std::{
+    env, io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+use tracing::{info, warn};
+
+/// A main loop that hasn't gone round for this long is stuck, and the watchdog pings stop.
+pub const LOOP_STALL_AFTER: Duration = Duration::from_secs(10);
+
+/// How often the supervisor looks at the loops while waiting for them to start.
+const STARTUP_POLL: Duration = Duration::from_millis(100);
+
+/// How often the `STATUS=` line is refreshed when systemd doesn't ask for watchdog pings.
+const STATUS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Encode a notification: one `KEY=value` line per field, as `sd_notify(3)` expects. Line
+/// breaks in a value would start a new field, so they become spaces.
+pub fn encode(fields: &[(&str, &str)]) -> Vec<u8> {
+    let mut message = String::new();
+    for (key, value) in fields {
+        message.push_str(key);
+        message.push('=');
+        message.extend(value.chars().map(|c| if c == '\n' || c == '\r' { ' ' } else { c }));
+        message.push('\n');
+    }
+    message.into_bytes()
+}
+
+/// `Ok` while both main loops are alive: started and, unless they can't show it, seen within
+/// `stall_after` of `now_ms`. Otherwise the error says which loop is stuck or not up yet.
+pub fn check_loops(ingest: LoopState, web: LoopState, now_ms: u64, stall_after: Duration) -> Result<(), String> {
+    for (name, state) in [("ingest", ingest), ("web", web)] {
+        match state {
+            LoopState::NotStarted => return Err(format!("{} server not listening yet", name)),
+            LoopState::Unwatched => {}
+            LoopState::LastSeen(at) => {
+                let silent = Duration::from_millis(now_ms.saturating_sub(at));
+                if silent > stall_after {
+                    return Err(format!("{} loop stuck for {}s", name, silent.as_secs()));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Interval between watchdog pings: half the `WATCHDOG_USEC` systemd set for this process,
+/// `None` when it wants none.
+pub fn watchdog_interval(usec: Option<&str>, pid: Option<&str>, own_pid: u32) -> Option<Duration> {
+    // Meant for another process (we were started by a shell under the service)
+    if pid.is_some_and(|pid| pid.trim().parse::<u32>().ok() != Some(own_pid)) {
+        return None;
+    }
+    let usec: u64 = usec?.trim().parse().ok().filter(|&usec| usec > 0)?;
+    Some(Duration::from_micros(usec) / 2)
+}
+
+/// The `NOTIFY_SOCKET` systemd passes to `Type=notify` services.
+pub struct Notifier {
+    #[cfg(unix)]
+    socket: std::os::unix::net::UnixDatagram,
+    #[cfg(unix)]
+    address: std::os::unix::net::SocketAddr,
+    watchdog: Option<Duration>,
+}
+
+impl Notifier {
+    /// The notification socket and watchdog interval of the environment, or `None` when not
+    /// started by systemd (or on Windows). The variables are removed so hook commands don't
+    /// notify in our name: call this before starting any thread.
+    pub fn from_env() -> Option<Self> {
+        let path = env::var("NOTIFY_SOCKET").ok();
+        let watchdog = watchdog_interval(
+            env::var("WATCHDOG_USEC").ok().as_deref(),
+            env::var("WATCHDOG_PID").ok().as_deref(),
+            std::process::id(),
+        );
+        for name in ["NOTIFY_SOCKET", "WATCHDOG_USEC", "WATCHDOG_PID"] {
+            env::remove_var(name);
+        }
+        let path = path.filter(|path| !path.is_empty())?;
+        match Self::open(&path, watchdog) {
+            Ok(notifier) => Some(notifier),
+            Err(e) => {
+                warn!("Cannot use NOTIFY_SOCKET {}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    fn open(path: &str, watchdog: Option<Duration>) -> io::Result<Self> {
+        use std::os::unix::net::{SocketAddr, UnixDatagram};
+        let address = match path.strip_prefix('@') {
+            // An abstract socket name, Linux only
+            #[cfg(target_os = "linux")]
+            Some(name) => {
+                use std::os::linux::net::SocketAddrExt;
+                SocketAddr::from_abstract_name(name.as_bytes())?
+            }
+            _ => SocketAddr::from_pathname(path)?,
+        };
+        Ok(Self { socket: UnixDatagram::unbound()?, address, watchdog })
+    }
+
+    #[cfg(not(unix))]
+    fn open(_path: &str, _watchdog: Option<Duration>) -> io::Result<Self> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "systemd notifications need Unix sockets"))
+    }
+
+    /// Send one notification.
+    pub fn notify(&self, fields: &[(&str, &str)]) -> io::Result<()> {
+        #[cfg(unix)]
+        return self.socket.send_to_addr(&encode(fields), &self.address).map(drop);
+        #[cfg(not(unix))]
+        {
+            let _ = fields;
+            Ok(())
+        }
+    }
+}
+
+/// One line for `systemctl status`: the console link, connections and frames.
+fn status_line(shared: &SharedState) -> String {
+    let metrics = shared.metrics.snapshot();
+    let frames: u64 = metrics.peers.values().map(|peer| peer.frames).sum();
+    let errors: u64 = metrics.peers.values().map(|peer| peer.errors).sum();
+    let link = match shared.link_status(LINK_TIMEOUT) {
+        LinkStatus::Active => "active",
+        LinkStatus::Idle => "idle",
+        LinkStatus::Dead => "down",
+    };
+    format!(
+        "Console link {}, {} connected, {} frames ({} bad), {} web requests",
+        link, metrics.ingest_connected, frames, errors, metrics.web_requests
+    )
+}
+
+/// Tells systemd when the server is up, how it is doing and that it isn't hung.
+#[derive(Clone)]
+pub struct Supervisor {
+    notifier: Arc<Notifier>,
+    stopping: Arc<AtomicBool>,
+}
+
+impl Supervisor {
+    /// Watch `shared` on a thread of its own: `READY=1` once both servers listen, then a
+    /// `STATUS=` line and, when systemd asked for them, `WATCHDOG=1` pings while both main
+    /// loops are alive. A stuck loop stops the pings, so systemd restarts the service.
+    pub fn start(notifier: Notifier, shared: Arc<SharedState>) -> Self {
+        let notifier = Arc::new(notifier);
+        let stopping = Arc::new(AtomicBool::new(false));
+        let (thread_notifier, thread_stopping) = (Arc::clone(&notifier), Arc::clone(&stopping));
+        thread::spawn(move || supervise(&thread_notifier, &shared, &thread_stopping));
+        Self { notifier, stopping }
+    }
+
+    /// The server is shutting down: stop the pings and say so.
+    pub fn stopping(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        let _ = self.notifier.notify(&[("STOPPING", "1"), ("STATUS", "Shutting down")]);
+    }
+}
+
+fn supervise(notifier: &Notifier, shared: &SharedState, stopping: &AtomicBool) {
+    let loops = || {
+        let metrics = &shared.metrics;
+        check_loops(metrics.ingest_beat.state(), metrics.web_beat.state(), unix_millis(), LOOP_STALL_AFTER)
+    };
+    while loops().is_err() {
+        if stopping.load(Ordering::SeqCst) {
+            return;
+        }
+        thread::sleep(STARTUP_POLL);
+    }
+    if let Err(e) = notifier.notify(&[("READY", "1"), ("STATUS", &status_line(shared))]) {
+        warn!("Failed to notify systemd: {}", e);
+    }
+    info!("Told systemd the server is ready");
+
+    let interval = notifier.watchdog.unwrap_or(STATUS_INTERVAL);
+    let mut stuck = false;
+    loop {
+        thread::sleep(interval);
+        if stopping.load(Ordering::SeqCst) {
+            return;
+        }
+        let status = match loops() {
+            Ok(()) => {
+                if stuck {
+                    info!("Main loops running again, watchdog pings resumed");
+                    stuck = false;
+                }
+                status_line(shared)
+            }
+            Err(e) => {
+                if !stuck {
+                    warn!("{}: no more watchdog pings", e);
+                    stuck = true;
+                }
+                e
+            }
+        };
+        let mut fields = vec![("STATUS", status.as_str())];
+        if notifier.watchdog.is_some() && !stuck {
+            fields.push(("WATCHDOG", "1"));
+        }
+        // Best effort: systemd may be restarting itself
+        let _ = notifier.notify(&fields);
+    }
+}
@@ -0,0 +1,793 @@
+use crate::{
+    auth::{self, AuthLimiter},
+    capture::CaptureLog,
+    clock,
+    config::{InputMode, ServerConfig},
+    events::GameEvent,
+    follow::FileFollower,
+    forward::Forwarder,
+    framing::FrameDecoder,
+    game_archive,
+    game_log,
+    hooks,
+    jsonl_logger,
+    metrics::{isolate, IngestConnection, Metrics, PeerStats},
+    notify,
+    osc,
+    parse_valid_frame,
+    persist,
+    push,
+    reload::{self, LiveSettings},
+    state::{lock, SharedState, StateReceiver, Update},
+    statsd,
+    subscribers,
+    summary, xml,
+    GameState,
+};
+use std::{
+    collections::HashMap,
+    io::{ErrorKind, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Condvar, Mutex, PoisonError,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast::{self, error::TryRecvError};
+use tracing::{debug, debug_span, error, info, info_span, warn};
+
+/// Replies (acks, echoes) not accepted by a client within this time drop the connection.
+const CLIENT_WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often the listener checks for a shutdown while no console connects.
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How long a shutdown waits for the console connections to close.
+const INGEST_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Stops a running [`BasketballServer`] from another thread, e.g. on Ctrl+C; clones share
+/// the same signal.
+///
+/// The TCP listener stops accepting and the open console connections are closed (their
+/// session logs synced to the disk), the UDP input stops reading, then
+/// [`run`](BasketballServer::run) returns.
+/// Single-stream inputs hold nothing to close and are read until the process exits.
+#[derive(Clone, Default)]
+pub struct ShutdownHandle(Arc<ShutdownState>);
+
+#[derive(Default)]
+struct ShutdownState {
+    requested: AtomicBool,
+    next_connection: AtomicU64,
+    /// Open console connections, shut down with the server.
+    connections: Mutex<HashMap<u64, TcpStream>>,
+    /// Set once the ingest side has nothing left to close.
+    drained: Mutex<bool>,
+    drained_changed: Condvar,
+}
+
+impl ShutdownHandle {
+    /// Ask the server to stop. Returns at once; see [`wait`](Self::wait).
+    pub fn shutdown(&self) {
+        self.0.requested.store(true, Ordering::SeqCst);
+        for stream in lock(&self.0.connections).values() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.0.requested.load(Ordering::SeqCst)
+    }
+
+    /// Wait up to `timeout` for the ingest side to close its connections after
+    /// [`shutdown`](Self::shutdown). Returns whether it did.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let drained = lock(&self.0.drained);
+        let (drained, _) = self
+            .0
+            .drained_changed
+            .wait_timeout_while(drained, timeout, |drained| !*drained)
+            .unwrap_or_else(PoisonError::into_inner);
+        *drained
+    }
+
+    fn mark_drained(&self) {
+        *lock(&self.0.drained) = true;
+        self.0.drained_changed.notify_all();
+    }
+
+    /// Track a console connection until the returned guard is dropped; closed at once when
+    /// the shutdown has already started. Its id, from 1, names the connection in the log.
+    fn register(&self, stream: &TcpStream) -> RegisteredConnection {
+        let id = self.0.next_connection.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Ok(stream) = stream.try_clone() {
+            lock(&self.0.connections).insert(id, stream);
+            if self.is_requested() {
+                if let Some(stream) = lock(&self.0.connections).get(&id) {
+                    let _ = stream.shutdown(Shutdown::Both);
+                }
+            }
+        }
+        RegisteredConnection { handle: self.clone(), id }
+    }
+
+    fn open_connections(&self) -> usize {
+        lock(&self.0.connections).len()
+    }
+}
+
+/// Removes a connection from its [`ShutdownHandle`] when the handler is done with it.
+struct RegisteredConnection {
+    handle: ShutdownHandle,
+    id: u64,
+}
+
+impl Drop for RegisteredConnection {
+    fn drop(&mut self) {
+        lock(&self.handle.0.connections).remove(&self.id);
+    }
+}
+
+/// The ingest side of the scoreboard: reads console frames from the configured
+/// input and keeps the shared game state up to date.
+///
+/// Besides running it from the binary, the server can be embedded: start
+/// [`run`](Self::run) on its own thread and use [`wait_for_update`](Self::wait_for_update)
+/// or [`subscribe`](Self::subscribe) to react to new states.
+pub struct BasketballServer {
+    config: ServerConfig,
+    shared: Arc<SharedState>,
+    forwarder: Arc<Forwarder>,
+    auth_limiter: Arc<AuthLimiter>,
+    shutdown: ShutdownHandle,
+    /// The TCP listener bound by [`bind`](Self::bind) ahead of [`run`](Self::run).
+    listener: Mutex<Option<TcpListener>>,
+    /// The UDP socket of `--udp-addr`, bound with the TCP listener.
+    udp: Mutex<Option<UdpSocket>>,
+    /// Whether the outputs (logs, archive, hooks…) are started, by [`bind`](Self::bind) or
+    /// [`run`](Self::run), whichever comes first.
+    outputs_started: Mutex<bool>,
+}
+
+impl BasketballServer {
+    pub fn new(config: ServerConfig) -> Self {
+        let shared = Arc::new(SharedState::new(
+            config.dedupe,
+            config.override_mode,
+            config.history_capacity,
+            config.rules,
+            config.timeout_length.unwrap_or(config.rules.timeout_length()),
+        ));
+        shared.set_settings(LiveSettings::from_config(&config));
+        shared.set_timezone(config.timezone.clone());
+        // Before the clock ticker, which starts from the current state
+        if let Some(path) = &config.state_file {
+            persist::restore(path, config.state_max_age, &shared);
+        }
+        let forwarder = Arc::new(Forwarder::start(&config.forward_to, &shared.metrics));
+        clock::start_timeout_countdown(&shared);
+        if let Some(idle) = config.auto_reset {
+            shared.enable_auto_reset(idle);
+        }
+        if config.tick_clock {
+            clock::start_ticker(&shared);
+        }
+        Self {
+            config,
+            shared,
+            forwarder,
+            auth_limiter: Arc::new(AuthLimiter::default()),
+            shutdown: ShutdownHandle::default(),
+            listener: Mutex::default(),
+            udp: Mutex::default(),
+            outputs_started: Mutex::default(),
+        }
+    }
+
+    /// Bind the listener of the TCP input now rather than in [`run`](Self::run), which then
+    /// accepts on it.
+    ///
+    /// Returns the address it is bound to: with port 0 in `--listen`, the port the system
+    /// picked. Binding twice keeps the first listener. The `--udp-addr` socket is bound too,
+    /// see [`udp_address`](Self::udp_address). The outputs start here as well, so a frame sent
+    /// as soon as this returns reaches the logs, hooks and other sinks.
+    pub fn bind(&self) -> std::io::Result<SocketAddr> {
+        let address = {
+            let mut listener = lock(&self.listener);
+            if listener.is_none() {
+                *listener = Some(TcpListener::bind(&self.config.tcp_address)?);
+            }
+            let mut udp = lock(&self.udp);
+            if let (None, Some(address)) = (udp.as_ref(), &self.config.udp_address) {
+                *udp = Some(UdpSocket::bind(address)?);
+            }
+            listener.as_ref().expect("bound above").local_addr()?
+        };
+        self.start_outputs()?;
+        Ok(address)
+    }
+
+    /// The address of the UDP input once [`bind`](Self::bind) has bound it; `None` without
+    /// `--udp-addr`.
+    pub fn udp_address(&self) -> Option<SocketAddr> {
+        lock(&self.udp).as_ref().and_then(|socket| socket.local_addr().ok())
+    }
+
+    /// Handle to stop [`run`](Self::run) from another thread.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// State shared with the web server.
+    pub fn shared(&self) -> Arc<SharedState> {
+        Arc::clone(&self.shared)
+    }
+
+    /// Process-wide counters, including per-peer traffic statistics.
+    pub fn metrics(&self) -> Arc<Metrics> {
+        Arc::clone(&self.shared.metrics)
+    }
+
+    /// Snapshot of the current game state.
+    pub fn get_current_state(&self) -> GameState {
+        self.shared.current()
+    }
+
+    /// Block until a new state is published or `timeout` elapses.
+    ///
+    /// Returns the new state, or `None` on timeout. Only states published after
+    /// the call count; use [`get_current_state`](Self::get_current_state) for the
+    /// current one.
+    pub fn wait_for_update(&self, timeout: Duration) -> Option<GameState> {
+        let version = self.shared.version();
+        self.shared.wait_for_version(version, timeout).map(|(state, _)| state)
+    }
+
+    /// Receive every state accepted from now on.
+    ///
+    /// The receiver is bounded: if the consumer falls more than
+    /// [`SUBSCRIBER_CAPACITY`](crate::state::SUBSCRIBER_CAPACITY) states behind, the
+    /// oldest queued states are dropped so the newest one is never lost and the
+    /// ingest side never blocks. Dropping the receiver unsubscribes it.
+    pub fn subscribe(&self) -> StateReceiver {
+        self.shared.subscribe_states()
+    }
+
+    /// Serve one console connection over any byte stream (a serial link, an in-memory pipe)
+    /// until it ends, as if it came over TCP from `peer`: frames are applied and, with
+    /// [`ServerConfig::ack`], answered, and the traffic counts for `peer`'s IP. The
+    /// `--auth-token` handshake is left to the caller.
+    pub fn serve_connection<S: Read + Write>(&self, stream: S, peer: SocketAddr) -> std::io::Result<()> {
+        if self.config.echo {
+            return echo_connection(stream, peer.to_string());
+        }
+        let _connection = IngestConnection::open(&self.shared.metrics);
+        let stats = self.shared.metrics.peer(peer.ip());
+        handle_connection(stream, peer.to_string(), &stats, &self.config, &self.shared, &self.forwarder)
+    }
+
+    /// Read from the configured input.
+    ///
+    /// For the TCP listener this returns if binding fails, or once a
+    /// [`shutdown`](ShutdownHandle::shutdown) has closed the console connections.
+    /// Single-stream inputs (stdin, a followed file, a pipe) return when the stream
+    /// ends; the last state is then kept and flagged as stale.
+    pub fn run(&self) -> std::io::Result<()> {
+        let result = self.run_outputs_and_input();
+        if result.is_err() {
+            // Nothing is left open that a shutdown would wait for
+            self.shutdown.mark_drained();
+        }
+        result
+    }
+
+    /// Start the configured outputs, once.
+    fn start_outputs(&self) -> std::io::Result<()> {
+        let mut started = lock(&self.outputs_started);
+        if *started {
+            return Ok(());
+        }
+        let shared = &self.shared;
+        if let Some(address) = &self.config.subscriber_address {
+            subscribers::start(address, shared, self.config.backpressure_timeout)?;
+        }
+        if let Some(path) = &self.config.log_csv {
+            game_log::start_file_log(path, shared)?;
+        }
+        if let Some(template) = &self.config.log_jsonl {
+            jsonl_logger::start(template, self.config.log_jsonl_max_size, shared);
+        }
+        if let Some(dir) = &self.config.games_dir {
+            game_archive::start(dir, self.config.game_idle_gap, shared)?;
+        }
+        #[cfg(feature = "sqlite")]
+        if let Some(path) = &self.config.games_db {
+            crate::game_db::start(path, self.config.game_idle_gap, shared)?;
+        }
+        if let Some(path) = &self.config.state_file {
+            persist::start(path, shared);
+        }
+        // Always on: a reload may set the webhook later
+        summary::start_webhook(shared);
+        if !self.config.hooks.is_empty() {
+            hooks::start(&self.config.hooks, self.config.hook_timeout, shared);
+        }
+        if !self.config.osc_targets.is_empty() {
+            osc::start(&self.config.osc_targets, self.config.osc_map.as_deref(), shared)?;
+        }
+        if !self.config.notify.targets.is_empty() {
+            notify::start(&self.config.notify, self.config.court_id.as_deref(), shared);
+        }
+        if let Some(push) = &self.config.push {
+            push::start(push, shared);
+        }
+        if let Some(statsd) = &self.config.statsd {
+            statsd::start(statsd, shared)?;
+        }
+        if let Some(path) = &self.config.xml_file {
+            let names = xml::XmlNames::new(&self.config.xml_names);
+            xml::start(path, self.config.xml_interval, names, self.config.lang, shared);
+        }
+        #[cfg(feature = "mqtt")]
+        if let Some(mqtt) = &self.config.mqtt {
+            crate::mqtt::start(mqtt, self.config.court_id.as_deref(), shared);
+        }
+        if let Some(path) = &self.config.config_file {
+            reload::watch(path.clone(), Arc::clone(shared));
+        }
+        *started = true;
+        Ok(())
+    }
+
+    fn run_outputs_and_input(&self) -> std::io::Result<()> {
+        self.start_outputs()?;
+        let shared = &self.shared;
+        if !matches!(self.config.input, InputMode::Tcp) {
+            self.shutdown.mark_drained();
+            // A blocking read has no loop going round to watch
+            shared.metrics.ingest_beat.unwatched();
+        }
+        match &self.config.input {
+            InputMode::Tcp => return self.run_tcp(),
+            InputMode::Stdin => {
+                isolate("stdin reader", &shared.metrics, || {
+                    ingest_reader(std::io::stdin().lock(), "stdin", "stdin", &self.config, shared, &self.forwarder)
+                });
+            }
+            InputMode::Follow(path) => {
+                let source = path.display().to_string();
+                isolate("file follower", &shared.metrics, || {
+                    ingest_reader(FileFollower::with_offset(path.clone(), self.config.follow_start), "follow", &source, &self.config, shared, &self.forwarder)
+                });
+            }
+            #[cfg(windows)]
+            InputMode::Pipe(name) => {
+                isolate("pipe reader", &shared.metrics, || {
+                    ingest_reader(crate::pipe::open(name), "pipe", &crate::pipe::pipe_path(name), &self.config, shared, &self.forwarder)
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn run_tcp(&self) -> std::io::Result<()> {
+        let listener = match lock(&self.listener).take() {
+            Some(listener) => listener,
+            None => TcpListener::bind(&self.config.tcp_address)?,
+        };
+        info!("Basketball Protocol Server listening on {}", listener.local_addr()?);
+        let udp = match &self.config.udp_address {
+            Some(address) => {
+                let socket = match lock(&self.udp).take() {
+                    Some(socket) => socket,
+                    None => UdpSocket::bind(address)?,
+                };
+                // Wakes up to notice a shutdown while no datagram comes
+                socket.set_read_timeout(Some(ACCEPT_POLL_INTERVAL))?;
+                info!("Reading datagrams on UDP {}", socket.local_addr()?);
+                let config = self.config.clone();
+                let shared = Arc::clone(&self.shared);
+                let forwarder = Arc::clone(&self.forwarder);
+                let shutdown = self.shutdown.clone();
+                Some(thread::spawn(move || {
+                    isolate("UDP reader", &shared.metrics, || read_datagrams(socket, &config, &shared, &forwarder, &shutdown));
+                }))
+            }
+            None => None,
+        };
+        if self.config.echo {
+            info!("Echo mode: connections are echoed back and do not update the game state");
+        }
+        info!("Waiting for connections...");
+
+        let summaries = (!self.config.peer_summary_interval.is_zero()).then(|| {
+            let interval = self.config.peer_summary_interval;
+            let metrics = Arc::clone(&self.shared.metrics);
+            let shutdown = self.shutdown.clone();
+            thread::spawn(move || log_peer_summaries(interval, &metrics, &shutdown))
+        });
+
+        // Polled, so a shutdown is noticed while no console connects
+        listener.set_nonblocking(true)?;
+        while !self.shutdown.is_requested() {
+            self.shared.metrics.ingest_beat.beat();
+            match listener.accept() {
+                Ok((stream, peer)) => {
+                    if let Err(e) = stream.set_nonblocking(false) {
+                        error!("Error accepting connection: {}", e);
+                        continue;
+                    }
+                    let config = self.config.clone();
+                    let shared = Arc::clone(&self.shared);
+                    let forwarder = Arc::clone(&self.forwarder);
+                    let auth_limiter = Arc::clone(&self.auth_limiter);
+                    let registered = self.shutdown.register(&stream);
+                    // Everything the connection logs carries its id and peer address
+                    let span = info_span!("connection", id = registered.id, %peer);
+                    thread::spawn(move || {
+                        let _registered = registered;
+                        let _span = span.entered();
+                        // A panic in the handler only drops this connection; other clients and the
+                        // web server keep running and the panic is counted in the metrics.
+                        let result = isolate("client handler", &shared.metrics, || {
+                            handle_client(stream, &config, &shared, &forwarder, &auth_limiter)
+                        });
+                        if let Some(Err(e)) = result {
+                            error!("Error handling client: {}", e);
+                        }
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL_INTERVAL),
+                Err(e) => {
+                    error!("Error accepting connection: {}", e);
+                }
+            }
+        }
+
+        drop(listener);
+        for reader in [udp, summaries].into_iter().flatten() {
+            let _ = reader.join();
+        }
+        info!("Ingest server stopped accepting, closing the console connections");
+        let deadline = Instant::now() + INGEST_DRAIN_TIMEOUT;
+        while self.shutdown.open_connections() > 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        if self.shutdown.open_connections() > 0 {
+            warn!("{} connections still open after {:?}", self.shutdown.open_connections(), INGEST_DRAIN_TIMEOUT);
+        }
+        self.shutdown.mark_drained();
+        Ok(())
+    }
+}
+
+/// Periodically log one summary line per ingest peer seen so far, until the server shuts down.
+fn log_peer_summaries(interval: Duration, metrics: &Metrics, shutdown: &ShutdownHandle) {
+    let mut next = Instant::now() + interval;
+    while !shutdown.is_requested() {
+        // Short steps, so a shutdown is noticed within one of them
+        let now = Instant::now();
+        if now < next {
+            thread::sleep(ACCEPT_POLL_INTERVAL.min(next - now));
+            continue;
+        }
+        next += interval;
+        for (ip, stats) in metrics.peer_snapshots() {
+            info!("peer {}: {}", ip, stats);
+        }
+    }
+}
+
+/// Feed a chunk of raw bytes read on the `input` kind of input (`tcp`, `udp`, `stdin`...)
+/// through the decoder and apply every complete frame; `source` names the peer or stream in
+/// the log.
+///
+/// `on_frame` is called once per decoded frame with the parse outcome, before the frame is
+/// applied, so whoever sees its state also sees it counted.
+fn ingest_chunk(decoder: &mut FrameDecoder, chunk: &[u8], input: &'static str, source: &str, shared: &SharedState, mut on_frame: impl FnMut(Result<(), &str>)) {
+    let heartbeats = decoder.push(chunk);
+    if heartbeats > 0 {
+        shared.record_heartbeats(heartbeats);
+    }
+    while let Some(result) = decoder.next_frame() {
+        // Only built when debug is on for this module; a disabled span costs next to nothing
+        let _frame = debug_span!("frame", seq = decoder.frames()).entered();
+        match result {
+            Ok(frame) => {
+                on_frame(Ok(()));
+                let timed = shared.metrics.time_next_frame().then(Instant::now);
+                parse_valid_frame(frame, input, shared);
+                if let Some(started) = timed {
+                    shared.metrics.record_frame_timing(started.elapsed());
+                }
+            }
+            Err(e) => {
+                warn!("Failed to parse ProtocolFrame from {}: {}", source, e);
+                on_frame(Err(&e));
+            }
+        }
+    }
+}
+
+/// Read raw protocol bytes from a single input stream (stdin, a followed file) until EOF.
+///
+/// On EOF the last state stays displayed, flagged as stale, and the web server keeps running.
+fn ingest_reader<R: Read>(mut reader: R, input: &'static str, source: &str, config: &ServerConfig, shared: &SharedState, forwarder: &Forwarder) {
+    let _span = info_span!("input", source).entered();
+    info!("Reading protocol bytes from {}", source);
+
+    let mut decoder = FrameDecoder::new(config.lenient, &config.heartbeat_bytes);
+    let mut buffer = [0u8; 1024];
+
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => {
+                info!("{} closed; keeping last state (marked stale)", source);
+                break;
+            }
+            Ok(n) => {
+                forwarder.forward(&buffer[..n]);
+                ingest_chunk(&mut decoder, &buffer[..n], input, source, shared, |_| {});
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                error!("Error reading from {}: {}", source, e);
+                break;
+            }
+        }
+    }
+
+    shared.mark_stale();
+}
+
+/// Read console frames from the datagrams arriving on `socket` until a shutdown.
+///
+/// Each sender has its own decoder, so a frame split over two datagrams is put back
+/// together. Datagrams are forwarded and counted like TCP reads, but not answered, even
+/// with [`ServerConfig::ack`], nor logged to `data_log/`. With an auth token each datagram
+/// must be signed (see [`auth::DatagramSigner`]) and newer than the last one from its sender;
+/// the others are dropped and counted as auth failures.
+fn read_datagrams(socket: UdpSocket, config: &ServerConfig, shared: &SharedState, forwarder: &Forwarder, shutdown: &ShutdownHandle) {
+    let mut decoders: HashMap<SocketAddr, FrameDecoder> = HashMap::new();
+    // The largest UDP payload
+    let mut buffer = vec![0u8; 65_535];
+    let mut verifier = config.auth_token.as_deref().map(auth::DatagramVerifier::new);
+    while !shutdown.is_requested() {
+        match socket.recv_from(&mut buffer) {
+            Ok((n, peer)) => {
+                let _span = info_span!("datagram", %peer).entered();
+                let datagram = match &mut verifier {
+                    Some(verifier) => match verifier.verify(peer.ip(), &buffer[..n]) {
+                        Ok(payload) => payload,
+                        Err(reason) => {
+                            debug!("Dropping a datagram from {}: {}", peer, reason);
+                            shared.metrics.auth_failures.fetch_add(1, Ordering::Relaxed);
+                            continue;
+                        }
+                    },
+                    None => &buffer[..n],
+                };
+                forwarder.forward(datagram);
+                let stats = shared.metrics.peer(peer.ip());
+                let decoder = decoders.entry(peer).or_insert_with(|| {
+                    info!("First datagram from: {}", peer);
+                    stats.add_connection();
+                    FrameDecoder::new(config.lenient, &config.heartbeat_bytes)
+                });
+                stats.add_bytes(datagram.len());
+                ingest_chunk(decoder, datagram, "udp", &peer.to_string(), shared, |outcome| stats.add_frame(outcome.is_ok()));
+                stats.add_resyncs(decoder.take_resyncs());
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted) => {}
+            Err(e) => {
+                error!("Error reading UDP datagrams: {}", e);
+                thread::sleep(ACCEPT_POLL_INTERVAL);
+            }
+        }
+    }
+    info!("UDP input stopped");
+}
+
+// Handle a single TCP client connection: socket setup and authentication, then the
+// generic connection loop
+fn handle_client(mut stream: TcpStream, config: &ServerConfig, shared: &SharedState, forwarder: &Forwarder, auth_limiter: &AuthLimiter) -> std::io::Result<()> {
+    let peer_addr = stream.peer_addr()?;
+    info!("New connection from: {}", peer_addr);
+
+    if let Some(token) = &config.auth_token {
+        let ip = peer_addr.ip();
+        if auth_limiter.is_blocked(ip) {
+            warn!("Refusing {}: too many failed AUTH attempts", peer_addr);
+            shared.metrics.auth_rate_limited.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        if let Err(reason) = auth::read_handshake(&mut stream, token) {
+            warn!("Authentication failed for {}: {}", peer_addr, reason);
+            auth_limiter.record_failure(ip);
+            shared.metrics.auth_failures.fetch_add(1, Ordering::Relaxed);
+            let _ = stream.write_all(format!("ERR {}\n", reason).as_bytes());
+            return Ok(());
+        }
+        auth_limiter.record_success(ip);
+        info!("{} authenticated", peer_addr);
+    }
+
+    // Set read timeout to prevent hanging
+    stream.set_read_timeout(Some(Duration::from_secs(300)))?;
+    stream.set_write_timeout(Some(CLIENT_WRITE_TIMEOUT))?;
+
+    let result = if config.echo {
+        echo_connection(stream, peer_addr.to_string())
+    } else {
+        let _connection = IngestConnection::open(&shared.metrics);
+        let stats = shared.metrics.peer(peer_addr.ip());
+        handle_connection(stream, peer_addr.to_string(), &stats, config, shared, forwarder)
+    };
+
+    match result {
+        Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {
+            warn!("Dropping {}: replies not read within {:?}", peer_addr, CLIENT_WRITE_TIMEOUT);
+            shared.metrics.peers_dropped_backpressure.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// Serve one console connection over any byte stream until it closes.
+///
+/// Raw reads are forwarded and optionally logged to `data_log/`, then decoded
+/// into frames. With [`ServerConfig::ack`] enabled, `ACK\n` or `ERR <reason>\n`
+/// is written back for every frame. Traffic is counted in the peer's `stats`.
+fn handle_connection<S: Read + Write>(mut stream: S, peer: String, stats: &PeerStats, config: &ServerConfig, shared: &SharedState, forwarder: &Forwarder) -> std::io::Result<()> {
+    stats.add_connection();
+
+    // Capture the session's raw bytes only if file logging is enabled. Do not write a header.
+    // Failures to start or write the capture are logged and do not terminate the client connection.
+    let mut capture = if config.log_to_file {
+        match CaptureLog::start(&config.capture) {
+            Ok(capture) => {
+                info!("Logging TCP session to {}", capture.path().display());
+                Some(capture)
+            }
+            Err(e) => {
+                error!("Failed to start the session capture in {}: {}", config.capture.dir.display(), e);
+                None
+            }
+        }
+    } else {
+        info!("Session file logging is disabled for this run");
+        None
+    };
+    // The published updates, to start a new part of the capture with every new game
+    let mut updates = (capture.is_some() && config.capture.per_game).then(|| shared.subscribe());
+
+    let mut decoder = FrameDecoder::new(config.lenient, &config.heartbeat_bytes);
+    let mut buffer = [0u8; 1024];
+    let mut replies = Vec::new();
+
+    loop {
+        match stream.read(&mut buffer) {
+            Ok(0) => {
+                // Connection closed
+                info!("Connection closed by: {}", peer);
+                break;
+            }
+            Ok(n) => {
+                // Relay the raw chunk to downstream targets before anything else
+                forwarder.forward(&buffer[..n]);
+                stats.add_bytes(n);
+
+                // Write each TCP read as a single newline-delimited line containing
+                // a hex-style byte array (matching the debug output), e.g.:
+                // [01, 7F, 02, ...]
+                if let Some(capture) = &mut capture {
+                    if updates.as_mut().is_some_and(new_game_published) {
+                        if let Err(e) = capture.roll() {
+                            warn!("Failed to start a new part of the session capture: {}", e);
+                        }
+                    }
+                    if let Err(e) = capture.write(&buffer[..n]) {
+                        warn!("Failed to write raw bytes to log file: {}", e);
+                    }
+                }
+
+                // Parse every complete ProtocolFrame received so far
+                ingest_chunk(&mut decoder, &buffer[..n], "tcp", &peer, shared, |outcome| {
+                    stats.add_frame(outcome.is_ok());
+                    if config.ack {
+                        match outcome {
+                            Ok(()) => replies.extend_from_slice(b"ACK\n"),
+                            Err(e) => replies.extend_from_slice(format!("ERR {}\n", e).as_bytes()),
+                        }
+                    }
+                });
+                stats.add_resyncs(decoder.take_resyncs());
+                if !replies.is_empty() {
+                    stream.write_all(&replies)?;
+                    replies.clear();
+                }
+            }
+            Err(e) => {
+                error!("Error reading from {}: {}", peer, e);
+                break;
+            }
+        }
+    }
+
+    // Written as it came: synced (and compressed, with --capture-compress) as it is dropped
+    drop(capture);
+    Ok(())
+}
+
+/// Whether a new game was published since the last call; drains `updates`.
+fn new_game_published(updates: &mut broadcast::Receiver<Arc<Update>>) -> bool {
+    let mut new_game = false;
+    loop {
+        match updates.try_recv() {
+            Ok(update) => new_game |= update.events.iter().any(|event| matches!(event, GameEvent::NewGame { .. })),
+            Err(TryRecvError::Lagged(_)) => {}
+            Err(_) => return new_game,
+        }
+    }
+}
+
+/// Longest `PING <n>` line accepted before the bytes are echoed as ordinary data.
+const MAX_PING_LINE: usize = 64;
+
+/// Echo a connection back for link verification (see the `link-test` client).
+///
+/// Every byte is returned unchanged, except `PING <n>\n` lines which are answered
+/// with `PONG <n>\n`. Nothing is decoded, forwarded, logged or applied to the game state.
+fn echo_connection<S: Read + Write>(mut stream: S, peer: String) -> std::io::Result<()> {
+    let mut buffer = [0u8; 1024];
+    let mut pending: Vec<u8> = Vec::new();
+    let mut reply = Vec::new();
+
+    loop {
+        let n = match stream.read(&mut buffer) {
+            Ok(0) => {
+                info!("Echo connection closed by: {}", peer);
+                break;
+            }
+            Ok(n) => n,
+            Err(e) => {
+                error!("Error reading from {}: {}", peer, e);
+                break;
+            }
+        };
+        pending.extend_from_slice(&buffer[..n]);
+
+        loop {
+            if pending.starts_with(b"PING") {
+                match pending.iter().position(|&b| b == b'\n') {
+                    Some(end) => {
+                        let line: Vec<u8> = pending.drain(..=end).collect();
+                        let arg = String::from_utf8_lossy(&line[4..]).trim().to_string();
+                        reply.extend_from_slice(format!("PONG {}\n", arg).as_bytes());
+                        continue;
+                    }
+                    // Wait for the rest of the line
+                    None if pending.len() < MAX_PING_LINE => break,
+                    None => {}
+                }
+            } else if pending.is_empty() || (pending.len() > 1 && b"PING".starts_with(&pending)) {
+                // Possibly the start of a PING split across reads. A lone `P` is echoed
+                // right away since it may just as well be the LRC ending a frame.
+                break;
+            }
+
+            // Echo everything up to the next possible PING
+            let end = pending.iter().skip(1).position(|&b| b == b'P').map_or(pending.len(), |i| i + 1);
+            reply.extend(pending.drain(..end));
+        }
+
+        if !reply.is_empty() {
+            stream.write_all(&reply)?;
+            reply.clear();
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
+};
+
+const MAX_NAME_LEN: usize = 32;
+const MAX_ABBREV_LEN: usize = 3;
+const MAX_LOGO_URL_LEN: usize = 512;
+
+/// Name, abbreviation, color and logo of one team, set by the operator (`PUT /api/teams`).
+///
+/// The console never sends these; empty fields let the overlay fall back to its URL
+/// parameters or defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TeamInfo {
+    #[serde(default)]
+    pub name: String,
+    /// Up to 3 letters, e.g. `CAB`.
+    #[serde(default)]
+    pub abbrev: String,
+    /// `#RRGGBB`.
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Absolute URL, or a path on this server such as `/media/cab.png`.
+    #[serde(default)]
+    pub logo_url: Option<String>,
+}
+
+impl TeamInfo {
+    fn validate(&mut self, side: &str) -> Result<(), String> {
+        self.name = self.name.trim().to_string();
+        if self.name.chars().count() > MAX_NAME_LEN {
+            return Err(format!("{}.name must be at most {} characters", side, MAX_NAME_LEN));
+        }
+
+        self.abbrev = self.abbrev.trim().to_uppercase();
+        if self.abbrev.chars().count() > MAX_ABBREV_LEN || !self.abbrev.chars().all(char::is_alphanumeric) {
+            return Err(format!("{}.abbrev must be at most {} letters or digits", side, MAX_ABBREV_LEN));
+        }
+
+        if let Some(color) = &self.color {
+            let valid = color.len() == 7
+                && color.starts_with('#')
+                && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+            if !valid {
+                return Err(format!("Invalid {}.color {:?}: expected #RRGGBB", side, color));
+            }
+        }
+
+        if let Some(logo_url) = &self.logo_url {
+            let valid = logo_url.len() <= MAX_LOGO_URL_LEN
+                && (logo_url.starts_with('/') || logo_url.starts_with("http://") || logo_url.starts_with("https://"));
+            if !valid {
+                return Err(format!("Invalid {}.logoUrl: expected /path or http(s)://...", side));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Team details for both sides, sent to the overlay with every state.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Teams {
+    pub home: TeamInfo,
+    pub away: TeamInfo,
+}
+
+impl Teams {
+    /// Check every field and normalize it (trimmed, abbreviations upper case).
+    pub fn validate(&mut self) -> Result<(), String> {
+        self.home.validate("home")?;
+        self.away.validate("away")
+    }
+
+    /// Read teams saved by [`save`](Self::save); `None` if the file doesn't exist yet.
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let mut teams: Self = serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        teams
+            .validate()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Some(teams))
+    }
+
+    /// Write the teams to `path`, replacing it atomically so a crash never leaves half a file.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        let tmp = path.with_extension("json.tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(json.as_bytes())?;
+        // On disk before the rename, or a power cut could leave an empty file behind it
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    }
+}
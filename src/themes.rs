@@ -0,0 +1,52 @@
+use tracing::warn;
+
+/// Theme served when neither the URL nor `--theme` picks one.
+pub const DEFAULT_THEME: &str = "classic";
+
+/// An overlay look: an HTML page that links its own stylesheet.
+///
+/// Every theme uses the element ids `overlay.js` fills in (`home-score`, `time`,
+/// `shot-clock`, `home-fouls`, ...), so adding a theme only takes a template.
+#[derive(Debug)]
+pub struct Theme {
+    pub name: &'static str,
+    /// Path of the page among the [`Assets`](crate::assets::Assets).
+    pub page: &'static str,
+}
+
+/// All themes, selectable with `/?theme=<name>`.
+pub const THEMES: [Theme; 3] = [
+    Theme {
+        name: "classic",
+        page: "overlay.html",
+    },
+    Theme {
+        name: "minimal",
+        page: "themes/minimal.html",
+    },
+    Theme {
+        name: "broadcast",
+        page: "themes/broadcast.html",
+    },
+];
+
+/// Pages that show the game: the theme pages, the score bug and the slate, not the admin page.
+pub fn is_overlay_page(path: &str) -> bool {
+    path == "bug.html" || path == "slate.html" || THEMES.iter().any(|theme| theme.page == path)
+}
+
+/// Look up a theme by name.
+pub fn find(name: &str) -> Option<&'static Theme> {
+    THEMES.iter().find(|theme| theme.name == name)
+}
+
+/// The theme to serve for `requested`, falling back to `default` when it is unknown.
+pub fn resolve(requested: Option<&str>, default: &'static Theme) -> &'static Theme {
+    match requested {
+        Some(name) => find(name).unwrap_or_else(|| {
+            warn!("Unknown theme {:?}, serving {:?}", name, default.name);
+            default
+        }),
+        None => default,
+    }
+}
@@ -0,0 +1,134 @@
+/// A Unix time in milliseconds broken down into its UTC calendar date and time of day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UtcDateTime {
+    pub year: u64,
+    pub month: u64,
+    pub day: u64,
+    pub hour: u64,
+    pub minute: u64,
+    pub second: u64,
+    pub millis: u64,
+}
+
+impl UtcDateTime {
+    pub fn from_unix_millis(timestamp_ms: u64) -> Self {
+        let seconds = timestamp_ms / 1000;
+        let (days, time) = (seconds / 86_400, seconds % 86_400);
+
+        // Days since 1970-01-01 to a civil date (Howard Hinnant's algorithm)
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let doe = z % 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+
+        Self {
+            year: yoe + era * 400 + u64::from(month <= 2),
+            month,
+            day,
+            hour: time / 3600,
+            minute: time / 60 % 60,
+            second: time % 60,
+            millis: timestamp_ms % 1000,
+        }
+    }
+
+    /// `2026-03-14T19:05:42.250Z`
+    pub fn iso8601(&self) -> String {
+        format!("{}T{}Z", self.date(), self.time())
+    }
+
+    /// The Unix time in milliseconds of an RFC 3339 date and time with its UTC offset, to the
+    /// minute, the second or a fraction of it: `2026-03-14T19:30Z`, `2026-03-14T20:30:00+01:00`,
+    /// `2026-03-14T19:05:42.250Z` (as [`iso8601`](Self::iso8601) writes it).
+    pub fn parse_rfc3339(text: &str) -> Option<u64> {
+        let (date, time) = text.split_once('T')?;
+        let (time, offset_secs) = match time.strip_suffix('Z') {
+            Some(time) => (time, 0),
+            None => {
+                let at = time.rfind(['+', '-'])?;
+                let (time, offset) = time.split_at(at);
+                let (hours, minutes) = offset[1..].split_once(':')?;
+                if hours.len() != 2 || minutes.len() != 2 {
+                    return None;
+                }
+                let (hours, minutes) = (hours.parse::<i64>().ok()?, minutes.parse::<i64>().ok()?);
+                if hours > 23 || minutes > 59 {
+                    return None;
+                }
+                let sign = if offset.starts_with('-') { -1 } else { 1 };
+                (time, sign * (hours * 3600 + minutes * 60))
+            }
+        };
+        let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+        let millis = match fraction.len() {
+            0 => 0,
+            _ if !fraction.bytes().all(|digit| digit.is_ascii_digit()) || time.len() != 8 => return None,
+            // Truncated to the millisecond
+            _ => format!("{:0<3.3}", fraction).parse::<u64>().ok()?,
+        };
+        let parts: Vec<&str> = time.split(':').collect();
+        if !(2..=3).contains(&parts.len()) || parts.iter().any(|part| part.len() != 2) {
+            return None;
+        }
+        let mut parts = parts.iter().map(|part| part.parse::<u64>().ok());
+        let (hour, minute) = (parts.next()??, parts.next()??);
+        let second = parts.next().map_or(Some(0), |second| second)?;
+        let days = days_since_epoch(date)?;
+        if hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        let local = (days * 86_400 + hour * 3600 + minute * 60 + second) as i64;
+        u64::try_from(local - offset_secs).ok().map(|seconds| seconds * 1000 + millis)
+    }
+
+    /// `2026-03-14 19:05:42.250`, which spreadsheets read as a date and time.
+    pub fn spreadsheet(&self) -> String {
+        format!("{} {}", self.date(), self.time())
+    }
+
+    /// `2026-03-14`
+    pub(crate) fn date(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+
+    /// `19:05:42.250`
+    pub(crate) fn time(&self) -> String {
+        format!("{:02}:{:02}:{:02}.{:03}", self.hour, self.minute, self.second, self.millis)
+    }
+}
+
+/// Days from 1970-01-01 to a `YYYY-MM-DD` date, the inverse of
+/// [`from_unix_millis`](UtcDateTime::from_unix_millis); `None` for a day the month doesn't have.
+fn days_since_epoch(date: &str) -> Option<u64> {
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<u64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    if year < 1970 || !(1..=month_days(year, month)?).contains(&day) {
+        return None;
+    }
+    Some(days_from_civil(year, month, day))
+}
+
+/// Days in `month` (1 to 12) of `year`; `None` for a month that doesn't exist.
+pub(crate) fn month_days(year: u64, month: u64) -> Option<u64> {
+    let leap = year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+    match month {
+        2 if leap => Some(29),
+        2 => Some(28),
+        4 | 6 | 9 | 11 => Some(30),
+        1..=12 => Some(31),
+        _ => None,
+    }
+}
+
+/// Days from 1970-01-01 to a valid date from 1970 on (Howard Hinnant's algorithm).
+pub(crate) fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let (era, yoe) = (y / 400, y % 400);
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
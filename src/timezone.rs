@@ -0,0 +1,376 @@
+//! The time zone every timestamp the server writes out is rendered in (`--timezone`): the JSON
+//! Lines log, the game log CSV, `lastUpdate`, the history, the game summary and the log.
+//!
+//! Zones are read from the system's zoneinfo database (TZif files, as `/etc/localtime` is), so
+//! their summer time follows the rules in force at every date; a POSIX `TZ` rule or a fixed
+//! offset does without the database.
+
+use crate::timestamp::{days_from_civil, month_days, UtcDateTime};
+use std::{env, fs, path::PathBuf, sync::Arc};
+
+/// Where zoneinfo databases are installed, when `$TZDIR` doesn't say.
+const ZONEINFO_DIRS: [&str; 3] = ["/usr/share/zoneinfo", "/usr/lib/zoneinfo", "/usr/share/lib/zoneinfo"];
+
+/// The file the system's own zone is read from, when `$TZ` doesn't name one.
+const LOCALTIME: &str = "/etc/localtime";
+
+/// A time zone: its UTC offset at any time, and timestamps rendered in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeZone(Arc<Zone>);
+
+#[derive(Debug, PartialEq)]
+struct Zone {
+    name: String,
+    /// Never away from UTC, so timestamps end in `Z`.
+    utc: bool,
+    /// Unix seconds of every change of offset, in order, and the offset from each one on.
+    transitions: Vec<i64>,
+    offsets: Vec<i32>,
+    /// Offset before the first transition, or all the time without any.
+    initial: i32,
+    /// Rule past the last transition: a TZif file's footer, or the whole zone for a POSIX rule.
+    rule: Option<PosixRule>,
+}
+
+impl TimeZone {
+    pub fn utc() -> Self {
+        Self::new("UTC", Vec::new(), Vec::new(), 0, None)
+    }
+
+    /// The zone of the machine: `$TZ` if set, else `/etc/localtime`, else UTC (as on Windows,
+    /// which has neither).
+    pub fn system() -> Self {
+        if let Some(tz) = env::var("TZ").ok().filter(|tz| !tz.is_empty()) {
+            let tz = tz.strip_prefix(':').unwrap_or(&tz);
+            let zone = if tz.starts_with('/') { Self::tzif_file(tz, tz.into()) } else { Self::parse_zone(tz).ok() };
+            return zone.unwrap_or_else(Self::utc);
+        }
+        // A link into the database names the zone: /etc/localtime -> /usr/share/zoneinfo/Europe/Lisbon
+        let name = fs::read_link(LOCALTIME)
+            .ok()
+            .and_then(|target| Some(target.to_str()?.rsplit_once("zoneinfo/")?.1.to_string()))
+            .unwrap_or_else(|| "localtime".to_string());
+        Self::tzif_file(LOCALTIME, name).unwrap_or_else(Self::utc)
+    }
+
+    /// A zone as given to `--timezone`: `system` (or `local`), `UTC`, a fixed offset (`+01:00`,
+    /// `-0300`), a zoneinfo name (`Europe/Lisbon`) or a POSIX `TZ` rule (`WET0WEST,M3.5.0/1,M10.5.0`).
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim() {
+            "" | "system" | "local" => Ok(Self::system()),
+            value => Self::parse_zone(value),
+        }
+    }
+
+    fn parse_zone(value: &str) -> Result<Self, String> {
+        if matches!(value, "UTC" | "utc" | "Z") {
+            return Ok(Self::utc());
+        }
+        if let Some(offset) = fixed_offset(value) {
+            return Ok(Self::new(&format_offset(offset), Vec::new(), Vec::new(), offset, None));
+        }
+        if let Some(zone) = Self::zoneinfo(value) {
+            return Ok(zone);
+        }
+        if let Some(rule) = PosixRule::parse(value) {
+            return Ok(Self::new(value, Vec::new(), Vec::new(), rule.std_offset, Some(rule)));
+        }
+        Err(format!(
+            "Unknown time zone {:?}: expected system, UTC, an offset such as +01:00, a zoneinfo name such as Europe/Lisbon or a POSIX TZ rule",
+            value
+        ))
+    }
+
+    /// The zone named `name` in the zoneinfo database (`$TZDIR`, or where systems install it).
+    fn zoneinfo(name: &str) -> Option<Self> {
+        let valid = name.split('/').all(|part| !part.is_empty() && part != "." && part != "..")
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '_' | '-' | '+' | '.'));
+        if !valid {
+            return None;
+        }
+        let dirs = env::var_os("TZDIR").map(PathBuf::from).into_iter().chain(ZONEINFO_DIRS.iter().map(PathBuf::from));
+        dirs.into_iter().find_map(|dir| Self::tzif_file(dir.join(name), name.to_string()))
+    }
+
+    fn tzif_file(path: impl Into<PathBuf>, name: String) -> Option<Self> {
+        parse_tzif(&fs::read(path.into()).ok()?, &name)
+    }
+
+    fn new(name: &str, transitions: Vec<i64>, offsets: Vec<i32>, initial: i32, rule: Option<PosixRule>) -> Self {
+        let utc = initial == 0 && offsets.iter().all(|&offset| offset == 0) && rule.as_ref().is_none_or(|rule| rule.std_offset == 0 && rule.dst.is_none());
+        Self(Arc::new(Zone {
+            name: name.to_string(),
+            utc,
+            transitions,
+            offsets,
+            initial,
+            rule,
+        }))
+    }
+
+    /// `Europe/Lisbon`, `UTC`, `+01:00`, or the POSIX rule as given.
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    /// Seconds east of UTC at `timestamp_ms` (Unix milliseconds): `3600` for Lisbon in summer.
+    pub fn offset_at(&self, timestamp_ms: u64) -> i32 {
+        let zone = &self.0;
+        let secs = (timestamp_ms / 1000) as i64;
+        let after = zone.transitions.partition_point(|&at| at <= secs);
+        if after == zone.transitions.len() {
+            if let Some(rule) = &zone.rule {
+                return rule.offset_at(secs);
+            }
+        }
+        match after {
+            0 => zone.initial,
+            after => zone.offsets[after - 1],
+        }
+    }
+
+    /// `timestamp_ms` broken down in the zone's local time.
+    pub(crate) fn date_time(&self, timestamp_ms: u64) -> UtcDateTime {
+        let local = timestamp_ms as i64 + i64::from(self.offset_at(timestamp_ms)) * 1000;
+        UtcDateTime::from_unix_millis(local.max(0) as u64)
+    }
+
+    /// `timestamp_ms` as RFC 3339 in the zone, to the millisecond:
+    /// `2026-03-29T02:00:00.000+01:00`, or `2026-03-29T01:00:00.000Z` in UTC.
+    pub fn rfc3339(&self, timestamp_ms: u64) -> String {
+        if self.0.utc {
+            return UtcDateTime::from_unix_millis(timestamp_ms).iso8601();
+        }
+        let time = self.date_time(timestamp_ms);
+        format!("{}T{}{}", time.date(), time.time(), format_offset(self.offset_at(timestamp_ms)))
+    }
+}
+
+/// `+01:00`, `-03:00`; seconds (only in some zones' history) are left out.
+fn format_offset(offset: i32) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let minutes = offset.unsigned_abs() / 60;
+    format!("{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+}
+
+/// Seconds east of UTC of `+01:00`, `+0100` or `+01`.
+fn fixed_offset(value: &str) -> Option<i32> {
+    let (sign, digits) = match value.as_bytes().first()? {
+        b'+' => (1, &value[1..]),
+        b'-' => (-1, &value[1..]),
+        _ => return None,
+    };
+    let digits = digits.replacen(':', "", 1);
+    if !matches!(digits.len(), 2 | 4) || !digits.bytes().all(|digit| digit.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits.get(2..).filter(|minutes| !minutes.is_empty()).map_or(Some(0), |minutes| minutes.parse().ok())?;
+    (hours <= 23 && minutes <= 59).then_some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// The zone of a TZif file (RFC 8536), versions 1 to 4, as `name`; leap seconds are ignored.
+fn parse_tzif(data: &[u8], name: &str) -> Option<TimeZone> {
+    let header = |at: usize| -> Option<[usize; 6]> {
+        if data.get(at..at + 4)? != b"TZif" {
+            return None;
+        }
+        let mut counts = [0; 6];
+        for (i, count) in counts.iter_mut().enumerate() {
+            let bytes = data.get(at + 20 + i * 4..at + 24 + i * 4)?;
+            *count = u32::from_be_bytes(bytes.try_into().ok()?) as usize;
+        }
+        Some(counts)
+    };
+    let block_len = |[isut, isstd, leap, time, types, chars]: [usize; 6], time_size: usize| {
+        time * time_size + time + types * 6 + chars + leap * (time_size + 4) + isstd + isut
+    };
+
+    // Version 1 has 32-bit times; later versions repeat the data with 64-bit ones, then a footer
+    let mut counts = header(0)?;
+    let (mut start, mut time_size) = (44, 4);
+    let version = *data.get(4)?;
+    if version >= b'2' {
+        start += block_len(counts, 4);
+        counts = header(start)?;
+        (start, time_size) = (start + 44, 8);
+    }
+    let [_, _, _, time_count, type_count, _] = counts;
+    let times = data.get(start..start + time_count * time_size)?;
+    let indices = data.get(start + time_count * time_size..start + time_count * (time_size + 1))?;
+    let types = data.get(start + time_count * (time_size + 1)..start + time_count * (time_size + 1) + type_count * 6)?;
+    let type_offset = |index: usize| -> Option<i32> { Some(i32::from_be_bytes(types.get(index * 6..index * 6 + 4)?.try_into().ok()?)) };
+
+    let transitions = times
+        .chunks(time_size)
+        .map(|time| match time_size {
+            4 => i64::from(i32::from_be_bytes(time.try_into().unwrap_or_default())),
+            _ => i64::from_be_bytes(time.try_into().unwrap_or_default()),
+        })
+        .collect();
+    let offsets = indices.iter().map(|&index| type_offset(usize::from(index))).collect::<Option<Vec<_>>>()?;
+    let initial = type_offset(0)?;
+
+    let footer_at = start + block_len(counts, time_size);
+    let rule = if version >= b'2' {
+        let footer = data.get(footer_at..)?.strip_prefix(b"\n")?;
+        let end = footer.iter().position(|&byte| byte == b'\n')?;
+        std::str::from_utf8(&footer[..end]).ok().and_then(PosixRule::parse)
+    } else {
+        None
+    };
+    Some(TimeZone::new(name, transitions, offsets, initial, rule))
+}
+
+/// A POSIX `TZ` rule: `WET0WEST,M3.5.0/1,M10.5.0` is UTC+0, and UTC+1 from the last Sunday of
+/// March at 01:00 to the last Sunday of October at 02:00 (local time).
+#[derive(Debug, PartialEq)]
+struct PosixRule {
+    /// Seconds east of UTC (POSIX counts them west, so its sign is the other way round).
+    std_offset: i32,
+    dst: Option<SummerTime>,
+}
+
+#[derive(Debug, PartialEq)]
+struct SummerTime {
+    offset: i32,
+    /// Days and times (seconds after local midnight) of the switches to and from summer time.
+    start: (DayRule, i32),
+    end: (DayRule, i32),
+}
+
+#[derive(Debug, PartialEq)]
+enum DayRule {
+    /// `Jn`: day 1 to 365, February 29 never counted.
+    Julian(u64),
+    /// `n`: day 0 to 365, February 29 counted in leap years.
+    Ordinal(u64),
+    /// `Mm.w.d`: weekday `d` (0 is Sunday) of week `w` (1 to 4, 5 for the last) of month `m`.
+    Month { month: u64, week: u64, weekday: u64 },
+}
+
+impl PosixRule {
+    fn parse(text: &str) -> Option<Self> {
+        let mut rest = text;
+        zone_abbreviation(&mut rest)?;
+        let std_offset = -signed_time(&mut rest, 24)?;
+        if rest.is_empty() {
+            return Some(Self { std_offset, dst: None });
+        }
+        zone_abbreviation(&mut rest)?;
+        let offset = if rest.starts_with(',') { std_offset + 3600 } else { -signed_time(&mut rest, 24)? };
+        rest = rest.strip_prefix(',')?;
+        let start = DayRule::parse(&mut rest)?;
+        rest = rest.strip_prefix(',')?;
+        let end = DayRule::parse(&mut rest)?;
+        rest.is_empty().then_some(Self {
+            std_offset,
+            dst: Some(SummerTime { offset, start, end }),
+        })
+    }
+
+    fn offset_at(&self, secs: i64) -> i32 {
+        let Some(dst) = &self.dst else {
+            return self.std_offset;
+        };
+        // The year at the place; the switches are in its local time, standard time for the
+        // one to summer time and summer time for the one back
+        let year = UtcDateTime::from_unix_millis((secs + i64::from(self.std_offset)).max(0) as u64 * 1000).year;
+        let start = dst.start.0.day(year) * 86_400 + i64::from(dst.start.1 - self.std_offset);
+        let end = dst.end.0.day(year) * 86_400 + i64::from(dst.end.1 - dst.offset);
+        // South of the equator summer time spans the new year
+        let summer = if start <= end { start <= secs && secs < end } else { secs < end || start <= secs };
+        if summer {
+            dst.offset
+        } else {
+            self.std_offset
+        }
+    }
+}
+
+impl DayRule {
+    /// The rule and its optional `/time` (02:00 by default); the time may be negative or past
+    /// 24 hours, as TZif footers allow.
+    fn parse(rest: &mut &str) -> Option<(Self, i32)> {
+        let rule = if let Some(after) = rest.strip_prefix('M') {
+            *rest = after;
+            let month = number(rest)?;
+            *rest = rest.strip_prefix('.')?;
+            let week = number(rest)?;
+            *rest = rest.strip_prefix('.')?;
+            let weekday = number(rest)?;
+            ((1..=12).contains(&month) && (1..=5).contains(&week) && weekday <= 6).then_some(Self::Month { month, week, weekday })?
+        } else if let Some(after) = rest.strip_prefix('J') {
+            *rest = after;
+            Some(number(rest)?).filter(|day| (1..=365).contains(day)).map(Self::Julian)?
+        } else {
+            Some(number(rest)?).filter(|&day| day <= 365).map(Self::Ordinal)?
+        };
+        let time = match rest.strip_prefix('/') {
+            Some(after) => {
+                *rest = after;
+                signed_time(rest, 167)?
+            }
+            None => 2 * 3600,
+        };
+        Some((rule, time))
+    }
+
+    /// Days from 1970-01-01 to the rule's day in `year`.
+    fn day(&self, year: u64) -> i64 {
+        let january_first = days_from_civil(year, 1, 1) as i64;
+        let leap = month_days(year, 2) == Some(29);
+        match *self {
+            Self::Julian(day) => january_first + day as i64 - 1 + i64::from(leap && day >= 60),
+            Self::Ordinal(day) => january_first + day as i64,
+            Self::Month { month, week, weekday } => {
+                let first = days_from_civil(year, month, 1) as i64;
+                // 1970-01-01 was a Thursday
+                let first_weekday = (first + 4).rem_euclid(7);
+                let mut day = first + (weekday as i64 - first_weekday).rem_euclid(7) + (week as i64 - 1) * 7;
+                let last = first + month_days(year, month).unwrap_or(31) as i64 - 1;
+                while day > last {
+                    day -= 7;
+                }
+                day
+            }
+        }
+    }
+}
+
+/// `WET`, or `<+01>` for those that aren't letters.
+fn zone_abbreviation(rest: &mut &str) -> Option<()> {
+    let len = match rest.strip_prefix('<') {
+        Some(quoted) => quoted.find('>')? + 2,
+        None => rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len()),
+    };
+    if len < 3 {
+        return None;
+    }
+    *rest = &rest[len..];
+    Some(())
+}
+
+/// `[+-]hh[:mm[:ss]]` in seconds, hours up to `max_hours`.
+fn signed_time(rest: &mut &str, max_hours: u64) -> Option<i32> {
+    let sign = match rest.as_bytes().first() {
+        Some(b'-') => -1,
+        _ => 1,
+    };
+    *rest = rest.strip_prefix(['+', '-']).unwrap_or(rest);
+    let hours = number(rest).filter(|&hours| hours <= max_hours)?;
+    let mut seconds = hours * 3600;
+    for unit in [60, 1] {
+        let Some(after) = rest.strip_prefix(':') else { break };
+        *rest = after;
+        seconds += number(rest).filter(|&part| part <= 59)? * unit;
+    }
+    Some(sign * seconds as i32)
+}
+
+fn number(rest: &mut &str) -> Option<u64> {
+    let len = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    let value = rest[..len].parse().ok()?;
+    *rest = &rest[len..];
+    Some(value)
+}
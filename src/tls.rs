@@ -0,0 +1,48 @@
+use crate::config::TlsConfig;
+use std::io;
+
+/// Accepts the HTTPS listener's TLS handshakes.
+#[cfg(feature = "tls")]
+pub type TlsAcceptor = tokio_rustls::TlsAcceptor;
+/// Without the `tls` feature there is no HTTPS listener, so no acceptor either.
+#[cfg(not(feature = "tls"))]
+#[derive(Clone)]
+pub enum TlsAcceptor {}
+
+/// Load the PEM certificate chain and private key named by `config`.
+///
+/// Only HTTP/1.1 is offered over ALPN so WebSocket upgrades keep working (wss).
+#[cfg(feature = "tls")]
+pub fn acceptor(config: &TlsConfig) -> io::Result<TlsAcceptor> {
+    use std::sync::Arc;
+    use tokio_rustls::rustls::{
+        self,
+        pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    };
+
+    let invalid = |what: &str, path: &std::path::Path, e: &dyn std::fmt::Display| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("Invalid TLS {} {}: {}", what, path.display(), e))
+    };
+    let certs = CertificateDer::pem_file_iter(&config.cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| invalid("certificate", &config.cert, &e))?;
+    if certs.is_empty() {
+        return Err(invalid("certificate", &config.cert, &"no certificate found"));
+    }
+    let key = PrivateKeyDer::from_pem_file(&config.key).map_err(|e| invalid("key", &config.key, &e))?;
+
+    let mut server_config = rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(io::Error::other)?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| invalid("certificate/key pair", &config.cert, &e))?;
+    server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+/// Never called: the configuration refuses `--tls-cert` without the `tls` feature.
+#[cfg(not(feature = "tls"))]
+pub fn acceptor(_config: &TlsConfig) -> io::Result<TlsAcceptor> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "built without TLS support"))
+}
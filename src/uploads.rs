@@ -0,0 +1,116 @@
+use crate::events::Side;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use tracing::warn;
+
+/// Largest accepted logo, in bytes.
+pub const MAX_LOGO_BYTES: usize = 512 * 1024;
+
+/// URL path the upload directory is served under.
+pub const UPLOADS_URL: &str = "/uploads/";
+
+/// Image formats accepted as team logos. SVG is not one of them: served from this
+/// origin, its scripts would run with the admin page's rights.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageKind {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl ImageKind {
+    /// The format declared by a `Content-Type`, if accepted.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next().unwrap_or_default().trim();
+        match mime.to_ascii_lowercase().as_str() {
+            "image/png" => Some(Self::Png),
+            "image/jpeg" => Some(Self::Jpeg),
+            "image/webp" => Some(Self::Webp),
+            _ => None,
+        }
+    }
+
+    /// The format of `data`, by its signature.
+    pub fn sniff(data: &[u8]) -> Option<Self> {
+        if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else if data.starts_with(b"\xff\xd8\xff") {
+            Some(Self::Jpeg)
+        } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+            Some(Self::Webp)
+        } else {
+            None
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+        }
+    }
+}
+
+/// Team logos uploaded from the admin page (`POST /api/teams/<side>/logo`, `--upload-dir`).
+///
+/// Every upload gets a new file name, so browsers and the overlay never show a cached
+/// older logo; the one it replaces is deleted.
+#[derive(Debug)]
+pub struct Uploads {
+    dir: PathBuf,
+}
+
+impl Uploads {
+    pub fn new(dir: &Path) -> Self {
+        Self { dir: dir.to_path_buf() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Check `data` against its declared `content_type` and save it as `side`'s logo,
+    /// returning its URL path. Invalid uploads give an `InvalidData` error with a message
+    /// for the client.
+    pub fn save_logo(&self, side: Side, content_type: Option<&str>, data: &[u8]) -> io::Result<String> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        if data.len() > MAX_LOGO_BYTES {
+            return Err(invalid(format!("Logo is {} bytes, at most {} are allowed", data.len(), MAX_LOGO_BYTES)));
+        }
+        let declared = content_type
+            .and_then(ImageKind::from_content_type)
+            .ok_or_else(|| invalid("Logo must be image/png, image/jpeg or image/webp".to_string()))?;
+        if ImageKind::sniff(data) != Some(declared) {
+            return Err(invalid(format!("Logo is not a valid {} image", declared.extension().to_uppercase())));
+        }
+
+        fs::create_dir_all(&self.dir)?;
+        let millis = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis());
+        let name = format!("{}-{}.{}", side.as_str(), millis, declared.extension());
+        let tmp = self.dir.join(format!("{}.tmp", name));
+        fs::write(&tmp, data)?;
+        fs::rename(&tmp, self.dir.join(&name))?;
+        Ok(format!("{}{}", UPLOADS_URL, name))
+    }
+
+    /// Delete the upload behind `url`; URLs that aren't uploads are left alone.
+    pub fn remove(&self, url: &str) {
+        let Some(name) = url.strip_prefix(UPLOADS_URL) else {
+            return;
+        };
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return;
+        }
+        if let Err(e) = fs::remove_file(self.dir.join(name)) {
+            if e.kind() != io::ErrorKind::NotFound {
+                warn!("Cannot delete old upload {}: {}", name, e);
+            }
+        }
+    }
+}
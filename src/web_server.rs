@@ -0,0 +1,2282 @@
+use crate::{
+    access_log::AccessEntry,
+    assets::{self, Assets},
+    auth::{self, constant_time_eq, AuthLimiter},
+    clock::{estimate_clock_offset, ClockSample},
+    config::{ServerConfig, TlsConfig},
+    cors::CorsPolicy,
+    courts::{Court, Courts},
+    debounce::Debouncer,
+    events::Side,
+    dto::{ApiState, CourtHealth, CourtSummary, Health, HistoryEntryDto, HistoryResponse, MatchDto, OverlaySettings, ServerTime},
+    event_log::EVENT_LOG_CAPACITY,
+    history::HistoryFilter,
+    locale::Lang,
+    manual::{Command, StatePatch},
+    match_info::MatchInfo,
+    metrics::{Metrics, WsConnection},
+    page_config::{self, PageConfig},
+    prefs::Prefs,
+    reload::LiveSettings,
+    router::{Found, Params, Router},
+    raster::{RenderError, Snapshots, DEFAULT_HEIGHT, DEFAULT_WIDTH},
+    state::{unix_millis, SharedState},
+    style::{self, CustomCss, MAX_CUSTOM_CSS},
+    teams::Teams,
+    themes::{self, DEFAULT_THEME},
+    tls::{self, TlsAcceptor},
+    uploads::{Uploads, MAX_LOGO_BYTES},
+    xml::{self, XmlNames},
+    GameState,
+};
+use futures_util::{
+    future::{self, Either},
+    SinkExt, Stream, StreamExt, TryFutureExt, TryStreamExt,
+};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    future::Future,
+    io::{self, Write},
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream},
+    sync::{broadcast::error::RecvError, watch, Semaphore},
+};
+use tracing::{debug, error, info, warn};
+use warp::{
+    http::{
+        header::{ALLOW, CACHE_CONTROL, CONNECTION, CONTENT_DISPOSITION, CONTENT_ENCODING, CONTENT_SECURITY_POLICY, CONTENT_TYPE, ETAG, HOST, LOCATION, ORIGIN, VARY},
+        HeaderValue, Method, StatusCode, Uri,
+    },
+    hyper::{
+        body::Buf,
+        server::conn::Http,
+        service::{service_fn, Service},
+        Body, Request, Response,
+    },
+    multipart::FormData,
+    path::FullPath,
+    sse::Event,
+    reject::Reject,
+    ws::{Message, WebSocket},
+    filters::BoxedFilter,
+    Filter, Rejection, Reply,
+};
+
+/// Entries returned by `/api/history` when no `limit` is given.
+const DEFAULT_HISTORY_LIMIT: usize = 500;
+/// Most entries a single `/api/history` response may hold.
+const MAX_HISTORY_LIMIT: usize = 5000;
+
+/// Events returned by `/api/events` when no `limit` is given.
+const DEFAULT_EVENTS_LIMIT: usize = 1000;
+
+/// Most WebSocket clients served at once; further upgrades get a 503.
+const MAX_WS_CLIENTS: usize = 64;
+/// How often the web runtime shows it is alive, see [`Metrics::web_beat`].
+const WEB_BEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Most `/api/state?wait=` requests waiting at once; further ones are answered right away.
+const MAX_LONG_POLLS: usize = 64;
+/// Longest accepted `/api/state?wait=`, in seconds.
+const MAX_LONG_POLL_WAIT: u64 = 60;
+/// Largest accepted state override or control command request body.
+const MAX_OVERRIDE_BODY: u64 = 16 * 1024;
+/// Largest accepted WebSocket message; control commands are well under 1 KiB.
+const MAX_WS_MESSAGE: usize = 4 * 1024;
+
+/// Room for the multipart boundaries and part headers around an uploaded logo.
+const MAX_MULTIPART_OVERHEAD: usize = 16 * 1024;
+
+/// Largest accepted request line plus headers; larger ones get a 431.
+const MAX_REQUEST_HEAD: usize = 16 * 1024;
+/// Sent before closing a plain HTTP connection whose request head didn't arrive in time.
+const REQUEST_TIMEOUT_REPLY: &[u8] = b"HTTP/1.1 408 Request Timeout\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+
+/// Interval between keepalive comments on `/events`.
+const SSE_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+/// How often the page of a court waiting for its first frame reloads.
+const WAITING_PAGE_REFRESH_SECS: u64 = 5;
+
+/// Web server settings taken from the command line.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct WebOptions {
+    /// Token required by the admin page and control endpoints; generated at startup when unset.
+    pub admin_token: Option<String>,
+    /// Require the token on the read-only endpoints too.
+    pub lock_read_api: bool,
+    /// Overlay theme served at `/` when the URL has no `?theme=`.
+    pub theme: String,
+    /// Language of the display strings when neither `?lang=` nor `Accept-Language` picks one.
+    pub lang: Lang,
+    /// Overlays show the away team on the home side when the URL has no `?swap=`.
+    pub swap_sides: bool,
+    /// Interval the pages poll `/api/game` at while their WebSocket is down.
+    pub poll_interval: Duration,
+    /// Pages open the WebSocket; they only poll otherwise.
+    pub websocket: bool,
+    /// Directory whose files take precedence over the embedded overlay files.
+    pub web_root: Option<PathBuf>,
+    /// Where `PUT /api/teams` saves the team details.
+    pub teams_file: PathBuf,
+    /// Where `PUT /api/match` saves the match details.
+    pub match_file: PathBuf,
+    /// CSS added to the overlay pages, saved there by `PUT /api/style`.
+    pub custom_css_file: PathBuf,
+    /// Where uploaded team logos are saved, served at `/uploads/`.
+    pub upload_dir: PathBuf,
+    /// How long a running game may go without frames before `/healthz` reports 503.
+    pub health_stale_after: Duration,
+    /// Threads answering web requests.
+    pub workers: usize,
+    /// Connections served at once; further clients wait to be accepted.
+    pub max_connections: usize,
+    /// Time a client gets to send its request headers.
+    pub header_timeout: Duration,
+    /// Interval between pings to each WebSocket client. One that doesn't answer a ping, or
+    /// take a frame, within half of it is disconnected.
+    pub ws_ping_interval: Duration,
+    /// Serve several requests per connection (HTTP/1.1 keep-alive).
+    pub keep_alive: bool,
+    /// Address the web server listens on.
+    pub address: SocketAddr,
+    /// HTTPS listener, served alongside or instead of `address`.
+    pub tls: Option<TlsConfig>,
+    /// How long a shutdown waits for open connections to finish.
+    pub drain_timeout: Duration,
+    /// Origins allowed to call `/api/*` from a browser (`*` for any).
+    pub cors_allowed_origins: Vec<String>,
+    /// Element and attribute names of `/api/state.xml`.
+    pub xml_names: XmlNames,
+    /// Courts served at `/court/<id>/`, each with its own state, teams and match files; the
+    /// first one is also served at `/`. `None` serves only the state the server is started with.
+    pub courts: Option<Courts>,
+}
+
+/// The command line defaults.
+impl Default for WebOptions {
+    fn default() -> Self {
+        Self::from_config(&ServerConfig::default())
+    }
+}
+
+impl WebOptions {
+    pub fn from_config(config: &ServerConfig) -> Self {
+        Self {
+            admin_token: config.admin_token.clone(),
+            lock_read_api: config.lock_read_api,
+            theme: config.theme.clone(),
+            lang: config.lang,
+            swap_sides: config.swap_sides,
+            poll_interval: config.poll_interval,
+            websocket: config.websocket,
+            web_root: config.web_root.clone(),
+            teams_file: config.teams_file.clone(),
+            match_file: config.match_file.clone(),
+            custom_css_file: config.custom_css_file.clone(),
+            upload_dir: config.upload_dir.clone(),
+            health_stale_after: config.health_stale_after,
+            workers: config.web_workers,
+            max_connections: config.web_max_connections,
+            header_timeout: config.web_header_timeout,
+            ws_ping_interval: config.ws_ping_interval,
+            keep_alive: config.web_keep_alive,
+            address: config.web_address,
+            tls: config.tls.clone(),
+            drain_timeout: config.web_drain_timeout,
+            cors_allowed_origins: config.cors_allowed_origins.clone(),
+            xml_names: XmlNames::new(&config.xml_names),
+            courts: None,
+        }
+    }
+}
+
+/// Why a request was refused by [`require_token`].
+#[derive(Debug)]
+enum AuthRejection {
+    /// No token presented (401).
+    Missing,
+    /// Wrong token (403).
+    Invalid,
+    /// Too many wrong tokens from this IP recently (429).
+    RateLimited,
+}
+
+impl Reject for AuthRejection {}
+
+/// Admin token check shared by the protected routes.
+struct WebAuth {
+    token: String,
+    limiter: AuthLimiter,
+    metrics: Arc<Metrics>,
+}
+
+impl WebAuth {
+    fn check(&self, ip: Option<IpAddr>, presented: Option<&str>) -> Result<(), AuthRejection> {
+        if ip.is_some_and(|ip| self.limiter.is_blocked(ip)) {
+            self.metrics.web_auth_rate_limited.fetch_add(1, Ordering::Relaxed);
+            return Err(AuthRejection::RateLimited);
+        }
+        let presented = presented.ok_or(AuthRejection::Missing)?;
+        if !constant_time_eq(presented.as_bytes(), self.token.as_bytes()) {
+            self.metrics.web_auth_failures.fetch_add(1, Ordering::Relaxed);
+            if let Some(ip) = ip {
+                warn!("Wrong admin token from {}", ip);
+                self.limiter.record_failure(ip);
+            }
+            return Err(AuthRejection::Invalid);
+        }
+        if let Some(ip) = ip {
+            self.limiter.record_success(ip);
+        }
+        Ok(())
+    }
+}
+
+/// A client connection, plain or TLS.
+trait Connection: AsyncRead + AsyncWrite + Unpin + Send + 'static {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + 'static> Connection for T {}
+
+/// Stops a web server started with [`start_with_shutdown`]; clones share the same signal.
+#[derive(Debug, Clone)]
+pub struct ShutdownHandle(Arc<watch::Sender<bool>>);
+
+impl ShutdownHandle {
+    pub fn new() -> Self {
+        Self(Arc::new(watch::channel(false).0))
+    }
+
+    /// Ask the server to stop. Returns at once; the server's future completes when it has drained.
+    pub fn shutdown(&self) {
+        self.0.send_replace(true);
+    }
+
+    /// Completes once [`shutdown`](Self::shutdown) was called (immediately if it already was).
+    fn requested(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut rx = self.0.subscribe();
+        async move {
+            let _ = rx.wait_for(|stop| *stop).await;
+        }
+    }
+}
+
+impl Default for ShutdownHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Address of the client, attached to each request by [`serve`] (filters read it with `warp::ext`).
+#[derive(Debug, Clone, Copy)]
+struct RemoteAddr(SocketAddr);
+
+/// Accept HTTP connections on `listener` and answer them with `service`.
+///
+/// Connections are kept alive between requests unless disabled or the client sends
+/// `Connection: close`; pipelined requests are answered in order. At most
+/// `max_connections` are served at once: beyond that nothing is accepted and new clients
+/// wait in the listen backlog until one closes. A client that doesn't finish its request
+/// headers within `header_timeout` gets a 408 (without TLS) and is disconnected, so slow
+/// clients can't hold a slot.
+///
+/// Each request is routed by `dispatch` (after [`select_court`]) before `service` sees it.
+///
+/// Request heads are parsed by hyper: heads with more than 100 headers get a 431, malformed
+/// ones (NUL bytes, garbage) a 400. Heads over [`MAX_REQUEST_HEAD`] bytes get a 431 too,
+/// from hyper when it has buffered that much without finding the end of the head, or once
+/// parsed when the whole head came in one read (see [`head_size`]). Bodies are limited by
+/// the routes that read them.
+///
+/// Once `shutdown` is requested the listener is closed, idle connections are closed and
+/// busy ones after their current response. Returns when they are all gone or
+/// `drain_timeout` has passed.
+async fn serve<S>(
+    service: S,
+    listener: TcpListener,
+    tls: Option<TlsAcceptor>,
+    dispatch: Dispatch,
+    options: &WebOptions,
+    metrics: &Arc<Metrics>,
+    shutdown: &ShutdownHandle,
+) -> io::Result<()>
+where
+    S: Service<Request<Body>, Response = warp::reply::Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send,
+{
+    let address = listener.local_addr()?;
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    info!(
+        "Web server listening on {}://{} ({} workers, at most {} connections)",
+        scheme, address, options.workers, options.max_connections
+    );
+    // Shows the systemd watchdog that the runtime still runs its tasks
+    let beat_metrics = Arc::clone(metrics);
+    let beat = tokio::spawn(async move {
+        loop {
+            beat_metrics.web_beat.beat();
+            tokio::time::sleep(WEB_BEAT_INTERVAL).await;
+        }
+    });
+    let stop = shutdown.requested();
+    tokio::pin!(stop);
+    let connections = Arc::new(Semaphore::new(options.max_connections));
+    let mut http = Http::new();
+    http.http1_keep_alive(options.keep_alive)
+        .http1_header_read_timeout(options.header_timeout)
+        .max_buf_size(MAX_REQUEST_HEAD);
+
+    loop {
+        let permit = tokio::select! {
+            _ = &mut stop => break,
+            permit = Arc::clone(&connections).acquire_owned() => permit.expect("the connection semaphore is never closed"),
+        };
+        let accepted = tokio::select! {
+            _ = &mut stop => break,
+            accepted = listener.accept() => accepted,
+        };
+        let (stream, remote) = match accepted {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                // e.g. out of file descriptors: back off instead of spinning
+                warn!("Cannot accept web connection: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+
+        let service = service.clone();
+        let metrics = Arc::clone(metrics);
+        let courts = options.courts.clone();
+        let dispatch = dispatch.clone();
+        let service = service_fn(move |mut request: Request<Body>| {
+            request.extensions_mut().insert(RemoteAddr(remote));
+            let access = AccessEntry::start(remote, &request, &metrics);
+            if head_size(&request) > MAX_REQUEST_HEAD {
+                return Either::Left(future::ready(Ok(access.finish(head_too_large()))));
+            }
+            if let Some(response) = courts.as_ref().and_then(|courts| select_court(courts, &mut request)) {
+                return Either::Left(future::ready(Ok(access.finish(response))));
+            }
+            if let Some(response) = dispatch.route(&mut request) {
+                return Either::Left(future::ready(Ok(access.finish(response))));
+            }
+            Either::Right(service.clone().call(request).map_ok(|response| access.finish(response)))
+        });
+        let http = http.clone();
+        let tls = tls.clone();
+        let header_timeout = options.header_timeout;
+        let stop = shutdown.requested();
+        tokio::spawn(async move {
+            let _permit = permit;
+            let (io, timeout_reply): (Box<dyn Connection>, _) = match tls {
+                None => match with_timeout_reply(stream) {
+                    Ok((stream, reply)) => (Box::new(stream), Some(reply)),
+                    Err(e) => {
+                        debug!("Cannot set up web connection from {}: {}", remote, e);
+                        return;
+                    }
+                },
+                // The handshake counts against the header timeout, like the headers that follow it
+                #[cfg(feature = "tls")]
+                Some(tls) => match tokio::time::timeout(header_timeout, tls.accept(stream)).await {
+                    Ok(Ok(stream)) => (Box::new(stream), None),
+                    Ok(Err(e)) => {
+                        debug!("TLS handshake with {} failed: {}", remote, e);
+                        return;
+                    }
+                    Err(_) => {
+                        debug!("TLS handshake with {} timed out", remote);
+                        return;
+                    }
+                },
+                #[cfg(not(feature = "tls"))]
+                Some(never) => match never {},
+            };
+            #[cfg(not(feature = "tls"))]
+            let _ = header_timeout;
+
+            let connection = http.serve_connection(io, service).with_upgrades();
+            tokio::pin!(connection);
+            let result = tokio::select! {
+                result = &mut connection => result,
+                _ = stop => {
+                    connection.as_mut().graceful_shutdown();
+                    connection.await
+                }
+            };
+            if let Err(e) = result {
+                debug!("Web connection from {} ended: {}", remote, e);
+                if is_header_timeout(&e) {
+                    // Best effort: the socket's send buffer is empty while a head is awaited
+                    if let Some(mut reply) = timeout_reply {
+                        let _ = reply.write(REQUEST_TIMEOUT_REPLY);
+                    }
+                }
+            }
+        });
+    }
+
+    drop(listener);
+    beat.abort();
+    let open = options.max_connections - connections.available_permits();
+    info!("Web server stopped accepting, draining {} connections", open);
+    let all = u32::try_from(options.max_connections).unwrap_or(u32::MAX);
+    if tokio::time::timeout(options.drain_timeout, connections.acquire_many(all)).await.is_err() {
+        warn!(
+            "{} web connections still open after {:?}, dropping them",
+            options.max_connections - connections.available_permits(),
+            options.drain_timeout
+        );
+    }
+    Ok(())
+}
+
+/// Size of the request line plus headers of `request`, as parsed: hyper's buffer limit only
+/// applies while the head is incomplete, so a larger head read in one go gets through it.
+/// Whitespace around header values isn't kept and so isn't counted.
+fn head_size(request: &Request<Body>) -> usize {
+    // "GET /path HTTP/1.1\r\n", "Name: value\r\n" per header and the blank line
+    let line = request.method().as_str().len() + request.uri().to_string().len() + 12;
+    let headers: usize = request.headers().iter().map(|(name, value)| name.as_str().len() + value.len() + 4).sum();
+    line + headers + 2
+}
+
+/// The 431 of a request head over [`MAX_REQUEST_HEAD`], closing the connection like hyper's.
+fn head_too_large() -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE;
+    response.headers_mut().insert(CONNECTION, HeaderValue::from_static("close"));
+    response
+}
+
+/// Restore the teams and match details saved to `teams_file` and `match_file`.
+fn load_saved(shared: &SharedState, teams_file: &Path, match_file: &Path) {
+    match Teams::load(teams_file) {
+        Ok(Some(teams)) => {
+            info!("Loaded teams from {}", teams_file.display());
+            shared.set_teams(teams);
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Cannot load teams from {}: {}", teams_file.display(), e),
+    }
+    match MatchInfo::load(match_file) {
+        Ok(Some(info)) => {
+            info!("Loaded match details from {}", match_file.display());
+            shared.set_match(info);
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Cannot load match details from {}: {}", match_file.display(), e),
+    }
+}
+
+/// Serve `/court/<id>/...` as `/...` for that court: the [`Court`] goes with the request
+/// (filters read it with `warp::ext`) and `/court/<id>` is added to its `X-Forwarded-Prefix`,
+/// so the pages call the court's API. `/api/...?court=<id>` picks the court of an API call
+/// the same way.
+///
+/// Returns the response when there is no need to route the request: the redirect of
+/// `/court/<id>` to `/court/<id>/`, so the pages' relative links resolve below it, or the 404
+/// of a `?court=` naming no court.
+fn select_court(courts: &Courts, request: &mut Request<Body>) -> Option<Response<Body>> {
+    let path = request.uri().path();
+    let Some(rest) = path.strip_prefix("/court/") else {
+        if !path.starts_with("/api/") {
+            return None;
+        }
+        let id = request.uri().query()?.split('&').find_map(|pair| pair.strip_prefix("court="))?;
+        let Some(court) = courts.find(id) else {
+            return Some(error_reply(StatusCode::NOT_FOUND, &format!("no court {:?}", id), true));
+        };
+        let court = court.clone();
+        request.extensions_mut().insert(court);
+        return None;
+    };
+    let (id, tail) = rest.split_once('/').map_or((rest, None), |(id, tail)| (id, Some(tail)));
+    let court = courts.find(id)?.clone();
+    let query = request.uri().query().map(|query| format!("?{}", query)).unwrap_or_default();
+    let Some(tail) = tail else {
+        // Relative, so it holds behind a proxy's prefix too
+        let redirect = Response::builder().status(StatusCode::PERMANENT_REDIRECT).header(LOCATION, format!("{}/{}", id, query));
+        return Some(redirect.body(Body::empty()).unwrap_or_default());
+    };
+    let mut parts = request.uri().clone().into_parts();
+    parts.path_and_query = Some(format!("/{}{}", tail, query).parse().ok()?);
+    *request.uri_mut() = Uri::from_parts(parts).ok()?;
+
+    let prefix = request
+        .headers()
+        .get("x-forwarded-prefix")
+        .and_then(|value| value.to_str().ok())
+        .map(page_config::forwarded_prefix)
+        .unwrap_or_default();
+    if let Ok(prefix) = HeaderValue::from_str(&format!("{}/court/{}", prefix, court.id())) {
+        request.headers_mut().insert("x-forwarded-prefix", prefix);
+    }
+    request.extensions_mut().insert(court);
+    None
+}
+
+/// `stream` and a second, non-blocking handle on its socket, through which a 408 can still
+/// be sent once hyper has given up on the connection.
+fn with_timeout_reply(stream: TcpStream) -> io::Result<(TcpStream, std::net::TcpStream)> {
+    let stream = stream.into_std()?;
+    let reply = stream.try_clone()?;
+    Ok((TcpStream::from_std(stream)?, reply))
+}
+
+/// Whether hyper closed the connection because the request head didn't arrive in time.
+///
+/// hyper 0.14 tells this error apart only by its message (`is_timeout` doesn't cover it).
+fn is_header_timeout(e: &hyper::Error) -> bool {
+    e.to_string() == "read header from client timeout"
+}
+
+/// Whether a WebSocket read failed on a message over [`MAX_WS_MESSAGE`].
+///
+/// warp hides tungstenite's error type, so only its message tells.
+fn is_message_too_long(e: &warp::Error) -> bool {
+    e.to_string().starts_with("Space limit exceeded: Message too long")
+}
+
+/// Require the admin token, as `Authorization: Bearer <token>` or `?token=<token>`.
+///
+/// With `required` false every request passes, so optional locks share one filter type.
+fn require_token(auth: Arc<WebAuth>, required: bool) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::ext::optional::<RemoteAddr>()
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(move |remote: Option<RemoteAddr>, header: Option<String>, query: HashMap<String, String>| {
+            let auth = Arc::clone(&auth);
+            async move {
+                if !required {
+                    return Ok(());
+                }
+                let presented = header
+                    .as_deref()
+                    .and_then(|h| h.strip_prefix("Bearer "))
+                    .or(query.get("token").map(String::as_str));
+                auth.check(remote.map(|RemoteAddr(addr)| addr.ip()), presented)
+                    .map_err(warp::reject::custom)
+            }
+        })
+        .untuple_one()
+}
+
+/// Who may call a route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    /// Anyone, even with `--lock-read-api`.
+    Public,
+    /// Anyone, unless `--lock-read-api` asks for the admin token.
+    Read,
+    /// Only with the admin token; redirected to HTTPS on the plain HTTP listener next to it.
+    Admin,
+}
+
+/// A route of the [`RouteTable`], put in the request extensions by [`Dispatch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RouteId(usize);
+
+/// The routes, declared with the filters that serve them.
+///
+/// [`route`](Self::route) adds a method and path pattern to the router and returns the
+/// filter the route's handler starts with: it passes the requests [`Dispatch`] routed to
+/// it, once they have the token the route's [`Access`] asks for. Paths under `/api` have a
+/// router of their own, so the static files (`/*file`) never take them.
+struct RouteTable {
+    api: Router<(RouteId, Access)>,
+    pages: Router<(RouteId, Access)>,
+    auth: Arc<WebAuth>,
+    lock_read_api: bool,
+    next: usize,
+}
+
+impl RouteTable {
+    fn new(auth: Arc<WebAuth>, lock_read_api: bool) -> Self {
+        Self {
+            api: Router::new(),
+            pages: Router::new(),
+            auth,
+            lock_read_api,
+            next: 0,
+        }
+    }
+
+    fn route(&mut self, method: Method, pattern: &str, access: Access) -> BoxedFilter<()> {
+        let id = RouteId(self.next);
+        self.next += 1;
+        let router = if is_api_path(pattern) { &mut self.api } else { &mut self.pages };
+        router.add(method, pattern, (id, access));
+        let routed = warp::ext::get::<RouteId>()
+            .and_then(move |routed: RouteId| async move {
+                match routed == id {
+                    true => Ok(()),
+                    false => Err(warp::reject::not_found()),
+                }
+            })
+            .untuple_one();
+        match access {
+            Access::Public => routed.boxed(),
+            Access::Read => routed.and(require_token(Arc::clone(&self.auth), self.lock_read_api)).boxed(),
+            Access::Admin => routed.and(require_token(Arc::clone(&self.auth), true)).boxed(),
+        }
+    }
+
+    fn get(&mut self, pattern: &str, access: Access) -> BoxedFilter<()> {
+        self.route(Method::GET, pattern, access)
+    }
+
+    fn post(&mut self, pattern: &str, access: Access) -> BoxedFilter<()> {
+        self.route(Method::POST, pattern, access)
+    }
+
+    fn put(&mut self, pattern: &str, access: Access) -> BoxedFilter<()> {
+        self.route(Method::PUT, pattern, access)
+    }
+
+    fn patch(&mut self, pattern: &str, access: Access) -> BoxedFilter<()> {
+        self.route(Method::PATCH, pattern, access)
+    }
+
+    fn delete(&mut self, pattern: &str, access: Access) -> BoxedFilter<()> {
+        self.route(Method::DELETE, pattern, access)
+    }
+
+    /// The [`Dispatch`] of the routes declared, with `cors` headers on the 404s and 405s it
+    /// answers itself.
+    fn dispatch(self, cors: Arc<CorsPolicy>) -> Dispatch {
+        Dispatch {
+            api: Arc::new(self.api),
+            pages: Arc::new(self.pages),
+            cors,
+            https_port: None,
+        }
+    }
+}
+
+/// Whether `path` is under `/api`, where errors are JSON.
+fn is_api_path(path: &str) -> bool {
+    path.split('/').find(|segment| !segment.is_empty()) == Some("api")
+}
+
+/// The path parameter `name` of the route, as a `T`; a 404 when it isn't one.
+fn param<T: FromStr + Send + 'static>(name: &'static str) -> impl Filter<Extract = (T,), Error = Rejection> + Clone {
+    warp::ext::get::<Params>().and_then(move |params: Params| async move { params.parse::<T>(name).ok_or_else(warp::reject::not_found) })
+}
+
+/// Routes each request before the route filters see it: records its [`RouteId`] and path
+/// [`Params`], or answers it when no route takes it: 404 for unknown paths, 405 with an
+/// `Allow` header for other methods, as JSON under `/api` and a short page elsewhere. On
+/// the plain HTTP listener next to HTTPS, admin routes are redirected there instead.
+#[derive(Debug, Clone)]
+struct Dispatch {
+    api: Arc<Router<(RouteId, Access)>>,
+    pages: Arc<Router<(RouteId, Access)>>,
+    cors: Arc<CorsPolicy>,
+    https_port: Option<u16>,
+}
+
+impl Dispatch {
+    /// The same routes, with admin routes redirected to HTTPS on `https_port`.
+    fn redirecting_to(&self, https_port: u16) -> Self {
+        Self {
+            https_port: Some(https_port),
+            ..self.clone()
+        }
+    }
+
+    /// Route `request`; returns the response when no route is to serve it.
+    fn route(&self, request: &mut Request<Body>) -> Option<Response<Body>> {
+        let api = is_api_path(request.uri().path());
+        let router = if api { &self.api } else { &self.pages };
+        let response = match router.find(request.method(), request.uri().path()) {
+            Found::Route(&(id, access), params) => {
+                if let (Access::Admin, Some(https_port)) = (access, self.https_port) {
+                    return Some(https_redirect(request, https_port));
+                }
+                request.extensions_mut().insert(id);
+                request.extensions_mut().insert(params);
+                return None;
+            }
+            Found::MethodNotAllowed(allow) => {
+                let mut response = error_reply(StatusCode::METHOD_NOT_ALLOWED, "method not allowed", api);
+                let allow = allow.iter().map(Method::as_str).collect::<Vec<_>>().join(", ");
+                if let Ok(allow) = HeaderValue::from_str(&allow) {
+                    response.headers_mut().insert(ALLOW, allow);
+                }
+                response
+            }
+            Found::NotFound => error_reply(StatusCode::NOT_FOUND, "not found", api),
+        };
+        let origin = request.headers().get(ORIGIN).and_then(|origin| origin.to_str().ok());
+        Some(self.cors.decorate(request.uri().path(), origin, response))
+    }
+}
+
+/// Turn admin auth rejections into JSON 401/403/429 replies, and those of routes that found
+/// nothing to serve into a 404: JSON under `/api` (`api`), a short HTML page elsewhere.
+/// Other rejections keep warp's default handling.
+async fn handle_rejection(rejection: Rejection, api: bool) -> Result<warp::reply::Response, Rejection> {
+    let (status, message) = match rejection.find::<AuthRejection>() {
+        Some(AuthRejection::Missing) => (StatusCode::UNAUTHORIZED, "missing admin token"),
+        Some(AuthRejection::Invalid) => (StatusCode::FORBIDDEN, "invalid admin token"),
+        Some(AuthRejection::RateLimited) => (StatusCode::TOO_MANY_REQUESTS, "too many failed attempts, try again later"),
+        None if rejection.is_not_found() => (StatusCode::NOT_FOUND, "not found"),
+        None => return Err(rejection),
+    };
+    Ok(error_reply(status, message, api || rejection.find::<AuthRejection>().is_some()))
+}
+
+/// An error answer: `{"error": message}` for the API, else a page with the status.
+fn error_reply(status: StatusCode, message: &str, api: bool) -> warp::reply::Response {
+    if !api {
+        let reason = status.canonical_reason().unwrap_or_default();
+        let page = format!("<!DOCTYPE html>\n<title>{0} {1}</title>\n<h1>{0} {1}</h1>\n", status.as_u16(), reason);
+        return warp::reply::with_status(warp::reply::html(page), status).into_response();
+    }
+    let body = warp::reply::json(&serde_json::json!({ "error": message }));
+    warp::reply::with_status(body, status).into_response()
+}
+
+/// The `/courts` page: every court with its score and how long ago its state changed.
+fn courts_page(courts: &[CourtSummary]) -> String {
+    let mut rows = String::new();
+    for court in courts {
+        let (score, updated) = match court.age_secs {
+            _ if !court.has_data => ("-".to_string(), "waiting for data".to_string()),
+            Some(age) => (format!("{} - {}", court.home_score, court.away_score), format!("{} s ago", age)),
+            None => (format!("{} - {}", court.home_score, court.away_score), "no frame yet".to_string()),
+        };
+        let health = if court.healthy { "ok" } else { "unhealthy" };
+        rows += &format!(
+            "<tr><td><a href=\"{}\">Court {}</a></td><td>{}</td><td>{} {}</td><td>{}</td><td>{}</td></tr>\n",
+            court.path, court.id, score, court.period, court.time, updated, health
+        );
+    }
+    format!(
+        "<!DOCTYPE html>\n<meta charset=\"utf-8\">\n<meta http-equiv=\"refresh\" content=\"5\">\n<title>Courts</title>\n<h1>Courts</h1>\n\
+         <table>\n<tr><th>Court</th><th>Score</th><th>Period</th><th>Last update</th><th>Health</th></tr>\n{}</table>\n",
+        rows
+    )
+}
+
+/// The page of a court that has had no frame yet, reloading until its overlay can be shown.
+fn waiting_page(court: &Court) -> warp::reply::Response {
+    let page = format!(
+        "<!DOCTYPE html>\n<meta charset=\"utf-8\">\n<meta http-equiv=\"refresh\" content=\"{}\">\n<title>Court {1}</title>\n<h1>Court {1}: waiting for data</h1>\n",
+        WAITING_PAGE_REFRESH_SECS,
+        court.id()
+    );
+    let mut response = warp::reply::html(page).into_response();
+    response.headers_mut().insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    response
+}
+
+/// Passes requests for `/api` and below, leaving the path to the routes.
+fn api_path() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path::peek()
+        .and_then(|peek: warp::path::Peek| async move {
+            match peek.segments().next() {
+                Some("api") => Ok(()),
+                _ => Err(warp::reject::not_found()),
+            }
+        })
+        .untuple_one()
+}
+
+/// JSON 400 reply for an invalid override or command.
+fn bad_request(message: &str) -> warp::reply::Response {
+    let body = warp::reply::json(&serde_json::json!({ "error": message }));
+    warp::reply::with_status(body, StatusCode::BAD_REQUEST).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamQuery {
+    min_interval_ms: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventsQuery {
+    /// Only events with a higher id.
+    since: Option<u64>,
+    limit: Option<usize>,
+}
+
+/// Query of `/ws` and `/ws/control`.
+#[derive(Debug, Deserialize)]
+struct WsQuery {
+    /// `?events=1` adds the game event feed to the pushed states.
+    events: Option<String>,
+}
+
+impl WsQuery {
+    fn wants_events(&self) -> bool {
+        matches!(self.events.as_deref(), Some("1" | "true"))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    limit: Option<usize>,
+    offset: Option<usize>,
+    /// Only entries published after this Unix time in milliseconds.
+    since: Option<u64>,
+    /// Only entries published at or after this Unix time in milliseconds.
+    from: Option<u64>,
+    /// Only entries published at or before this Unix time in milliseconds.
+    to: Option<u64>,
+    every: Option<usize>,
+}
+
+impl HistoryQuery {
+    fn filter(&self) -> Result<HistoryFilter, String> {
+        let from_ms = match (self.since, self.from) {
+            (Some(since), from) => Some(from.unwrap_or(0).max(since.saturating_add(1))),
+            (None, from) => from,
+        };
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from > to {
+                return Err(format!("from ({}) is after to ({})", from, to));
+            }
+        }
+        let every = self.every.unwrap_or(1);
+        if every == 0 {
+            return Err("every must be at least 1".to_string());
+        }
+        let limit = self.limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+        if limit == 0 {
+            return Err("limit must be at least 1".to_string());
+        }
+        Ok(HistoryFilter {
+            from_ms,
+            to_ms: self.to,
+            every,
+            offset: self.offset.unwrap_or(0),
+            limit: limit.min(MAX_HISTORY_LIMIT),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ThemeQuery {
+    theme: Option<String>,
+}
+
+/// Add CORS headers to the API responses of `routes`, including error replies.
+fn with_cors<F, R>(cors: Arc<CorsPolicy>, routes: F) -> impl Filter<Extract = (warp::reply::Response,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+    R: Reply,
+{
+    warp::path::full()
+        .and(warp::header::optional::<String>("origin"))
+        .and(routes)
+        .map(move |path: FullPath, origin: Option<String>, reply: R| {
+            cors.decorate(path.as_str(), origin.as_deref(), reply.into_response())
+        })
+}
+
+/// Request headers deciding how a static file is sent.
+#[derive(Debug, Clone)]
+struct AssetRequest {
+    gzip: bool,
+    if_none_match: Option<String>,
+    /// Written into HTML pages, see [`PageConfig`].
+    page: PageConfig,
+}
+
+/// `?swap=` of an overlay page, see [`page_config::swap_sides`].
+#[derive(Debug, Deserialize)]
+struct SwapQuery {
+    swap: Option<String>,
+}
+
+fn asset_request(shared: Arc<SharedState>) -> impl Filter<Extract = (AssetRequest,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("accept-encoding")
+        .and(warp::header::optional::<String>("if-none-match"))
+        .and(warp::header::optional::<String>("x-forwarded-prefix"))
+        .and(language(Arc::clone(&shared)))
+        .and(warp::query::<SwapQuery>())
+        .map(
+            move |accept_encoding: Option<String>, if_none_match: Option<String>, prefix: Option<String>, lang: Lang, query: SwapQuery| {
+                let settings = shared.settings();
+                let poll_ms = u64::try_from(settings.poll_interval.as_millis()).unwrap_or(u64::MAX);
+                let ws_enabled = settings.websocket;
+                AssetRequest {
+                    gzip: accept_encoding.as_deref().is_some_and(assets::accepts_gzip),
+                    if_none_match,
+                    page: PageConfig {
+                        api_base: prefix.as_deref().map(page_config::forwarded_prefix).unwrap_or_default(),
+                        poll_ms,
+                        ws_enabled,
+                        lang,
+                        swap_sides: page_config::swap_sides(query.swap.as_deref(), settings.swap_sides),
+                    },
+                }
+            },
+        )
+}
+
+/// On the plain HTTP listener next to HTTPS: send a request for an admin route (see
+/// [`Access::Admin`]) to HTTPS with a 308, so the token never crosses the network in the clear.
+fn https_redirect(request: &Request<Body>, https_port: u16) -> Response<Body> {
+    let host = request.headers().get(HOST).and_then(|host| host.to_str().ok());
+    let host = host.map_or("localhost", host_without_port);
+    let path = request.uri().path_and_query().map_or("/", |path| path.as_str());
+    let location = format!("https://{}:{}{}", host, https_port, path);
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::PERMANENT_REDIRECT;
+    if let Ok(location) = HeaderValue::from_str(&location) {
+        response.headers_mut().insert(LOCATION, location);
+    }
+    response
+}
+
+/// `host` from a `Host` header value `host[:port]`, keeping the brackets of an IPv6 address.
+fn host_without_port(host: &str) -> &str {
+    match host.find(']') {
+        Some(end) if host.starts_with('[') => &host[..=end],
+        _ => host.split(':').next().unwrap_or(host),
+    }
+}
+
+/// Serve a static file from the web root or the embedded copies; 404 if neither has it.
+///
+/// Gzip-compressed when the client accepts it, and `304 Not Modified` when the client's
+/// copy is current. Browsers must revalidate (`no-cache`) so overlay updates show up at once.
+/// Overlay pages get a `Content-Security-Policy` that keeps the custom CSS from loading scripts.
+/// HTML pages carry the request's [`PageConfig`], so they vary with the headers it comes from.
+async fn serve_asset(assets: Arc<Assets>, path: String, request: AssetRequest) -> Result<warp::reply::Response, Rejection> {
+    let asset = assets.get(&path, &request.page).await.ok_or_else(warp::reject::not_found)?;
+    let overlay_page = themes::is_overlay_page(&path);
+    let page = asset.content_type.starts_with("text/html");
+    let varies = asset.gzip.is_some();
+    let gzip_etag = asset.gzip_etag();
+    let (body, etag, gzipped) = match asset.gzip {
+        Some(gzip) if request.gzip => (gzip, gzip_etag, true),
+        _ => (asset.body, asset.etag, false),
+    };
+
+    let not_modified = request
+        .if_none_match
+        .as_deref()
+        .is_some_and(|if_none_match| assets::etag_matches(if_none_match, &etag));
+    let mut response = if not_modified {
+        let mut response = warp::reply::Response::default();
+        *response.status_mut() = StatusCode::NOT_MODIFIED;
+        response
+    } else {
+        let mut response = warp::reply::Response::new(body.into());
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static(asset.content_type));
+        if gzipped {
+            response
+                .headers_mut()
+                .insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        }
+        response
+    };
+
+    let headers = response.headers_mut();
+    if let Ok(etag) = HeaderValue::from_str(&etag) {
+        headers.insert(ETAG, etag);
+    }
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    if page {
+        headers.insert(
+            VARY,
+            HeaderValue::from_static("Accept-Encoding, Accept-Language, Cookie, X-Forwarded-Prefix"),
+        );
+    } else if varies {
+        headers.insert(VARY, HeaderValue::from_static("Accept-Encoding"));
+    }
+    if overlay_page {
+        headers.insert(CONTENT_SECURITY_POLICY, HeaderValue::from_static(style::OVERLAY_CSP));
+    }
+    Ok(response)
+}
+
+/// Apply a manual override and reply with the resulting `/api/state` body, or 400.
+///
+/// With `replace`, fields missing from the patch are reset to blank (POST);
+/// otherwise they keep their current value (PATCH).
+fn override_state(shared: &SharedState, patch: StatePatch, replace: bool, lang: Lang) -> warp::reply::Response {
+    let result = shared.set_manual(|state| {
+        if replace {
+            // Team and match details aren't part of the scoreboard and survive a replace
+            *state = GameState {
+                teams: state.teams.clone(),
+                match_info: state.match_info.clone(),
+                ..GameState::default()
+            };
+        }
+        patch.apply(state)
+    });
+
+    match result {
+        Ok(()) => {
+            info!("State overridden manually ({})", if replace { "replace" } else { "patch" });
+            warp::reply::json(&ApiState::from_shared(shared, lang)).into_response()
+        }
+        Err(e) => bad_request(&e),
+    }
+}
+
+/// `GET /api/state` reply: `body`, or 304 when `If-None-Match` holds its ETag.
+///
+/// The ETag is weak because `ageMs` and `metrics` may differ in a fresh body.
+fn state_reply(body: &ApiState, lang: Lang, if_none_match: Option<&str>) -> warp::reply::Response {
+    let etag = format!("\"{}\"", body.etag(lang));
+    let mut response = if if_none_match.is_some_and(|candidates| assets::etag_matches(candidates, &etag)) {
+        let mut response = warp::reply::Response::default();
+        *response.status_mut() = StatusCode::NOT_MODIFIED;
+        response
+    } else {
+        warp::reply::json(body).into_response()
+    };
+
+    let headers = response.headers_mut();
+    if let Ok(etag) = HeaderValue::from_str(&format!("W/{}", etag)) {
+        headers.insert(ETAG, etag);
+    }
+    headers.insert("x-state-version", HeaderValue::from(body.version));
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    headers.insert(VARY, HeaderValue::from_static("Accept-Language, Cookie"));
+    response
+}
+
+/// Validate, save and apply a new custom stylesheet (`PUT /api/style`); an empty body removes it.
+///
+/// Overlay pages pick it up when they are next loaded.
+fn put_style(custom_css: &CustomCss, body: &[u8]) -> warp::reply::Response {
+    let Ok(css) = std::str::from_utf8(body) else {
+        return bad_request("Custom CSS must be UTF-8 text");
+    };
+    if let Err(e) = style::validate(css) {
+        return bad_request(&e);
+    }
+    if let Err(e) = custom_css.set(css.to_string()) {
+        error!("Cannot save custom CSS to {}: {}", custom_css.path().display(), e);
+        let body = warp::reply::json(&serde_json::json!({ "error": format!("Cannot save custom CSS: {}", e) }));
+        return warp::reply::with_status(body, StatusCode::INTERNAL_SERVER_ERROR).into_response();
+    }
+    info!("Custom CSS set ({} bytes)", css.len());
+    css_reply(css.to_string())
+}
+
+fn css_reply(css: String) -> warp::reply::Response {
+    let mut response = warp::reply::Response::new(css.into());
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/css; charset=utf-8"));
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    response
+}
+
+/// The game log as a CSV download (`GET /api/game-log.csv`).
+fn game_log_csv(shared: &SharedState) -> warp::reply::Response {
+    let mut csv = Vec::new();
+    // Writing to memory can't fail
+    let _ = shared.game_log().write_csv(&mut csv, &shared.timezone());
+    let mut response = warp::reply::Response::new(csv.into());
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/csv; charset=utf-8"));
+    headers.insert(CONTENT_DISPOSITION, HeaderValue::from_static("attachment; filename=\"game-log.csv\""));
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    response
+}
+
+/// Validate, save and publish new team details (`PUT /api/teams`).
+///
+/// They are saved first, so what the overlay shows is what a restart restores.
+fn put_teams(shared: &SharedState, teams_file: &Path, mut teams: Teams) -> warp::reply::Response {
+    if let Err(e) = teams.validate() {
+        return bad_request(&e);
+    }
+    if let Err(e) = teams.save(teams_file) {
+        error!("Cannot save teams to {}: {}", teams_file.display(), e);
+        let body = warp::reply::json(&serde_json::json!({ "error": format!("Cannot save teams: {}", e) }));
+        return warp::reply::with_status(body, StatusCode::INTERNAL_SERVER_ERROR).into_response();
+    }
+    info!("Teams set: {} vs {}", teams.home.name, teams.away.name);
+    shared.set_teams(teams.clone());
+    warp::reply::json(&teams).into_response()
+}
+
+/// Validate, save and publish new match details (`PUT /api/match`), like [`put_teams`].
+fn put_match(shared: &SharedState, match_file: &Path, mut info: MatchInfo) -> warp::reply::Response {
+    if let Err(e) = info.validate() {
+        return bad_request(&e);
+    }
+    if let Err(e) = info.save(match_file) {
+        error!("Cannot save match details to {}: {}", match_file.display(), e);
+        let body = warp::reply::json(&serde_json::json!({ "error": format!("Cannot save match details: {}", e) }));
+        return warp::reply::with_status(body, StatusCode::INTERNAL_SERVER_ERROR).into_response();
+    }
+    info!("Match set: {} {} at {}", info.competition, info.round, info.tip_off.as_deref().unwrap_or("no tip-off time"));
+    shared.set_match(info.clone());
+    warp::reply::json(&MatchDto::new(&info, Some(unix_millis()))).into_response()
+}
+
+/// Save an uploaded logo as `side`'s and point the team's `logoUrl` at it, replying like
+/// `PUT /api/teams`. The previous upload of that side is deleted once the teams are saved.
+async fn upload_logo(
+    shared: Arc<SharedState>,
+    uploads: Arc<Uploads>,
+    teams_file: Arc<PathBuf>,
+    side: String,
+    form: FormData,
+) -> Result<warp::reply::Response, Infallible> {
+    let side = match side.as_str() {
+        "home" => Side::Home,
+        "away" => Side::Away,
+        other => return Ok(bad_request(&format!("Unknown side {:?}: expected home or away", other))),
+    };
+    let (content_type, data) = match read_logo_part(form).await {
+        Ok(Some(part)) => part,
+        Ok(None) => return Ok(bad_request("Expected the image in a form field named logo")),
+        Err((status, e)) => return Ok(error_reply(status, &e, true)),
+    };
+    let url = match uploads.save_logo(side, content_type.as_deref(), &data) {
+        Ok(url) => url,
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => return Ok(bad_request(&e.to_string())),
+        Err(e) => {
+            error!("Cannot save logo to {}: {}", uploads.dir().display(), e);
+            let body = warp::reply::json(&serde_json::json!({ "error": format!("Cannot save logo: {}", e) }));
+            return Ok(warp::reply::with_status(body, StatusCode::INTERNAL_SERVER_ERROR).into_response());
+        }
+    };
+
+    let mut teams = shared.current().teams;
+    let team = match side {
+        Side::Home => &mut teams.home,
+        Side::Away => &mut teams.away,
+    };
+    let previous = team.logo_url.replace(url.clone());
+    let response = put_teams(&shared, &teams_file, teams);
+    match previous {
+        _ if !response.status().is_success() => uploads.remove(&url),
+        Some(previous) if previous != url => uploads.remove(&previous),
+        _ => {}
+    }
+    Ok(response)
+}
+
+/// Content type and bytes of the `logo` field of a multipart form, if it has one; the error
+/// is a 400 for a broken form, a 413 for a logo over [`MAX_LOGO_BYTES`].
+async fn read_logo_part(mut form: FormData) -> Result<Option<(Option<String>, Vec<u8>)>, (StatusCode, String)> {
+    let invalid = |e: warp::Error| (StatusCode::BAD_REQUEST, format!("Invalid multipart body: {}", e));
+    while let Some(part) = form.try_next().await.map_err(invalid)? {
+        if part.name() != "logo" {
+            continue;
+        }
+        let content_type = part.content_type().map(str::to_string);
+        let mut data = Vec::new();
+        let stream = part.stream();
+        tokio::pin!(stream);
+        while let Some(chunk) = stream.try_next().await.map_err(invalid)? {
+            data.extend_from_slice(chunk.chunk());
+            if data.len() > MAX_LOGO_BYTES {
+                return Err((StatusCode::PAYLOAD_TOO_LARGE, format!("Logo must be at most {} bytes", MAX_LOGO_BYTES)));
+            }
+        }
+        return Ok(Some((content_type, data)));
+    }
+    Ok(None)
+}
+
+/// Run an admin page action (`POST /api/control/<action>`) and reply with the resulting `/api/state` body.
+///
+/// The body holds the command's fields, e.g. `{"side": "home", "points": 2}` for `score`.
+fn control(shared: &SharedState, action: &str, mut body: serde_json::Value, lang: Lang) -> warp::reply::Response {
+    let cmd = match action {
+        "score" => "addPoints",
+        "foul" => "addFoul",
+        "timeout" => "addTimeout",
+        "possession" if body.get("side").is_some() => "setPossession",
+        "possession" => "togglePossession",
+        "period" => "advancePeriod",
+        "clock" => "setClock",
+        other => return bad_request(&format!("Unknown control action {:?}", other)),
+    };
+    let Some(fields) = body.as_object_mut() else {
+        return bad_request("Expected a JSON object");
+    };
+    fields.insert("cmd".to_string(), cmd.into());
+
+    let command: Command = match serde_json::from_value(body) {
+        Ok(command) => command,
+        Err(e) => return bad_request(&e.to_string()),
+    };
+    match run_command(shared, &command) {
+        Ok(()) => warp::reply::json(&ApiState::from_shared(shared, lang)).into_response(),
+        Err(e) => bad_request(&e),
+    }
+}
+
+/// Apply an operator command as a manual override; the new state is pushed to every client.
+///
+/// Shared by `POST /api/control/*` and the `/ws/control` socket.
+fn run_command(shared: &SharedState, command: &Command) -> Result<(), String> {
+    shared.set_manual(|state| command.apply(state))?;
+    info!("Control: {:?}", command);
+    Ok(())
+}
+
+/// Reply to a command frame on `/ws/control`: `{"ok": true}` or `{"error": "..."}`.
+fn command_reply(shared: &SharedState, message: &Message) -> Message {
+    let result = match message.to_str() {
+        Ok(text) => serde_json::from_str::<Command>(text)
+            .map_err(|e| e.to_string())
+            .and_then(|command| run_command(shared, &command)),
+        Err(()) => Err("Expected a JSON text frame".to_string()),
+    };
+    let reply = match result {
+        Ok(()) => serde_json::json!({ "ok": true }),
+        Err(e) => serde_json::json!({ "error": e }),
+    };
+    Message::text(reply.to_string())
+}
+
+/// Push the current state, then every published state, as SSE events whose id is the state version.
+/// Each state is followed by the game events it logged, as `game-event` events without an id.
+/// A client too slow for the channel gets the current state in place of the ones it missed.
+///
+/// Nothing is locked while the client is written to: states come from the
+/// broadcast channel. The stream ends, and the client is dropped, as soon as a
+/// write fails or the server shuts down.
+fn state_events(shared: Arc<SharedState>, min_interval: Duration, shutdown: &ShutdownHandle) -> impl Stream<Item = Result<Event, Infallible>> {
+    let stop = shutdown.requested();
+    // Subscribe before taking the snapshot so no update falls in between
+    let mut rx = shared.subscribe();
+    let (current_state, version) = shared.current_json_versioned();
+    let age = shared.state_age();
+
+    async_stream::stream! {
+        // Send the current state immediately, its age (if known) as a comment
+        {
+            let mut event = Event::default().id(version.to_string()).data(current_state);
+            if let Some(age) = age {
+                event = event.comment(format!("age_ms={}", age.as_millis()));
+            }
+            yield Ok(event);
+        }
+
+        let mut debouncer = Debouncer::new(min_interval);
+        // Version of the state sent after the client lagged behind: the older updates still
+        // in the channel are skipped
+        let mut caught_up = 0;
+        tokio::pin!(stop);
+        loop {
+            let next = async {
+                match debouncer.deadline() {
+                    Some(deadline) => {
+                        let deadline = tokio::time::Instant::from_std(deadline);
+                        tokio::time::timeout_at(deadline, rx.recv()).await.ok()
+                    }
+                    None => Some(rx.recv().await),
+                }
+            };
+            let received = tokio::select! {
+                _ = &mut stop => break,
+                received = next => received,
+            };
+
+            let ready = match received {
+                Some(Ok(update)) if update.version <= caught_up => continue,
+                Some(Ok(update)) => debouncer.offer((*update).clone(), std::time::Instant::now()),
+                // Skipped some updates: the current state supersedes them and any held back,
+                // as on `/ws` (their events are left to `/api/events`)
+                Some(Err(RecvError::Lagged(_))) => {
+                    let _ = debouncer.flush(std::time::Instant::now());
+                    let (json, version) = shared.current_json_versioned();
+                    caught_up = version;
+                    yield Ok(Event::default().id(version.to_string()).data(json));
+                    continue;
+                }
+                Some(Err(RecvError::Closed)) => break,
+                None => debouncer.flush(std::time::Instant::now()),
+            };
+
+            if let Some(update) = ready {
+                yield Ok(Event::default().id(update.version.to_string()).data(update.json));
+                for logged in &update.logged_events {
+                    yield Ok(Event::default().event("game-event").data(serde_json::to_string(logged).unwrap_or_default()));
+                }
+            }
+        }
+    }
+}
+
+/// `?lang=` of the requests that reply with display strings.
+#[derive(Debug, Deserialize)]
+struct LangQuery {
+    lang: Option<String>,
+}
+
+/// Language of a request's display strings: `?lang=`, then the `scoreboard_prefs` cookie,
+/// then `Accept-Language`, then `default`.
+///
+/// Languages that aren't supported are skipped, so `?lang=xx` falls back like no `?lang=` at all.
+fn language(shared: Arc<SharedState>) -> impl Filter<Extract = (Lang,), Error = Rejection> + Clone {
+    warp::query::<LangQuery>()
+        .and(prefs())
+        .and(warp::header::optional::<String>("accept-language"))
+        .map(move |query: LangQuery, prefs: Prefs, accept_language: Option<String>| {
+            query
+                .lang
+                .as_deref()
+                .and_then(Lang::parse)
+                .or(prefs.lang)
+                .or_else(|| accept_language.as_deref().and_then(Lang::from_accept_language))
+                .unwrap_or_else(|| shared.settings().lang)
+        })
+}
+
+/// The display preferences of the requesting browser, see [`Prefs`].
+fn prefs() -> impl Filter<Extract = (Prefs,), Error = Infallible> + Clone {
+    warp::header::optional::<String>("cookie")
+        .or(warp::any().map(|| None))
+        .unify()
+        .map(|cookie: Option<String>| cookie.as_deref().map(Prefs::from_cookie_header).unwrap_or_default())
+}
+
+/// Query of `GET /render.png`: width and height in pixels.
+#[derive(Debug, Deserialize)]
+struct RenderQuery {
+    w: Option<u32>,
+    h: Option<u32>,
+}
+
+/// `GET /render.png` reply: the current state as a PNG, or a JSON error.
+fn render_png(shared: &SharedState, snapshots: &Snapshots, query: RenderQuery) -> warp::reply::Response {
+    let (state, version) = shared.current_versioned();
+    let size = (query.w.unwrap_or(DEFAULT_WIDTH), query.h.unwrap_or(DEFAULT_HEIGHT));
+    let (status, message) = match snapshots.png(&state, version, size.0, size.1) {
+        Ok(png) => {
+            let mut response = warp::reply::Response::new(png.into());
+            let headers = response.headers_mut();
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static("image/png"));
+            headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+            headers.insert("x-state-version", HeaderValue::from(version));
+            return response;
+        }
+        Err(RenderError::BadSize(message)) => (StatusCode::BAD_REQUEST, message),
+        #[cfg(not(feature = "raster"))]
+        Err(RenderError::Unsupported) => (
+            StatusCode::NOT_IMPLEMENTED,
+            "PNG snapshots require a build with the raster feature (cargo build --features raster)".to_string(),
+        ),
+        #[cfg(feature = "raster")]
+        Err(RenderError::Failed(e)) => {
+            error!("Cannot render the PNG snapshot: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("Cannot render: {}", e))
+        }
+    };
+    let body = warp::reply::json(&serde_json::json!({ "error": message }));
+    warp::reply::with_status(body, status).into_response()
+}
+
+/// Query of `GET /api/time`: the page's earlier round trips, see [`ClockSample::parse_list`].
+#[derive(Debug, Deserialize)]
+struct TimeQuery {
+    samples: Option<String>,
+}
+
+/// Query of `GET /api/state`; both are optional.
+#[derive(Debug, Deserialize)]
+struct StateQuery {
+    /// Long polling: wait up to this many seconds for a newer state.
+    wait: Option<u64>,
+    /// Version the client already has; the current one when unset.
+    version: Option<u64>,
+}
+
+/// Wait until a state newer than `version` is published, `wait` elapses or the server shuts down.
+///
+/// Waits on the broadcast channel: the state lock isn't held and no worker thread is
+/// blocked, so waiting clients only cost their connection.
+async fn wait_for_state(shared: &SharedState, version: Option<u64>, wait: Duration, shutdown: &ShutdownHandle) {
+    // Subscribe before reading the version so no update falls in between
+    let mut rx = shared.subscribe();
+    let current = shared.version();
+    let version = version.unwrap_or(current);
+    if current > version {
+        return;
+    }
+
+    let deadline = tokio::time::Instant::now() + wait;
+    let stop = shutdown.requested();
+    tokio::pin!(stop);
+    loop {
+        let received = tokio::select! {
+            _ = &mut stop => return,
+            received = tokio::time::timeout_at(deadline, rx.recv()) => received,
+        };
+        match received {
+            Ok(Ok(update)) if update.version <= version => continue,
+            // Newer state (or missed ones, which are newer too), closed channel or timeout
+            _ => return,
+        }
+    }
+}
+
+/// One of a limited number of client slots (WebSockets, long polls), released on drop.
+struct ClientSlot(Arc<AtomicUsize>);
+
+impl ClientSlot {
+    fn acquire(clients: &Arc<AtomicUsize>, max: usize) -> Option<Self> {
+        clients
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| (n < max).then_some(n + 1))
+            .ok()
+            .map(|_| ClientSlot(Arc::clone(clients)))
+    }
+}
+
+impl Drop for ClientSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Push the current state, then every published state, to one WebSocket client.
+///
+/// With `control` (`/ws/control`), every text frame is also a [`Command`] and gets a reply
+/// frame before the state it produces. On shutdown the client gets a `1001 Going Away` close frame.
+/// Clients are pinged every `ping_interval`; those that stop answering or reading frames for
+/// half of it are reaped.
+async fn serve_ws(socket: WebSocket, shared: Arc<SharedState>, shutdown: ShutdownHandle, ping_interval: Duration, control: bool, events: bool) {
+    let pong_timeout = ping_interval / 2;
+    let _connection = WsConnection::open(&shared.metrics);
+    let (mut tx, mut rx) = socket.split();
+    let stop = shutdown.requested();
+    tokio::pin!(stop);
+    let mut updates = shared.subscribe();
+
+    if tx.send(Message::text(shared.current_json())).await.is_err() {
+        return;
+    }
+
+    let mut ping = tokio::time::interval(ping_interval);
+    ping.tick().await;
+    // Set while a ping is unanswered
+    let mut pong_deadline: Option<tokio::time::Instant> = None;
+
+    let close = loop {
+        tokio::select! {
+            _ = &mut stop => break Some(Message::close_with(1001u16, "server shutting down")),
+            update = updates.recv() => {
+                let (json, logged_events) = match update {
+                    Ok(update) => (update.json.clone(), if events { update.logged_events.clone() } else { Vec::new() }),
+                    // Skipped some updates: the current state supersedes them
+                    // (and their events are left to `/api/events`)
+                    Err(RecvError::Lagged(_)) => (shared.current_json(), Vec::new()),
+                    Err(RecvError::Closed) => break None,
+                };
+                let mut messages = vec![Ok(Message::text(json))];
+                for logged in &logged_events {
+                    messages.push(Ok(Message::text(serde_json::to_string(logged).unwrap_or_default())));
+                }
+                match tokio::time::timeout(pong_timeout, tx.send_all(&mut futures_util::stream::iter(messages))).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(_)) => break None,
+                    Err(_) => {
+                        debug!("WebSocket client stopped reading, closing");
+                        shared.metrics.ws_reaped.fetch_add(1, Ordering::Relaxed);
+                        break None;
+                    }
+                }
+            }
+            message = rx.next() => match message {
+                // The reply close frame is sent by the WebSocket layer
+                Some(Ok(message)) if message.is_close() => break None,
+                Some(Ok(message)) if control && (message.is_text() || message.is_binary()) => {
+                    pong_deadline = None;
+                    // Bad commands get an error frame; the socket stays open
+                    if tx.send(command_reply(&shared, &message)).await.is_err() {
+                        break None;
+                    }
+                }
+                // Pongs and anything else the client sends prove it is alive
+                Some(Ok(_)) => pong_deadline = None,
+                Some(Err(e)) => {
+                    debug!("WebSocket error: {}", e);
+                    break is_message_too_long(&e).then(|| Message::close_with(1009u16, "message too big"));
+                }
+                None => break None,
+            },
+            _ = ping.tick() => {
+                if tx.send(Message::ping(Vec::new())).await.is_err() {
+                    break None;
+                }
+                pong_deadline.get_or_insert_with(|| tokio::time::Instant::now() + pong_timeout);
+            }
+            _ = tokio::time::sleep_until(pong_deadline.unwrap_or_else(tokio::time::Instant::now)), if pong_deadline.is_some() => {
+                debug!("WebSocket client did not answer ping, closing");
+                shared.metrics.ws_reaped.fetch_add(1, Ordering::Relaxed);
+                break Some(Message::close_with(1001u16, "ping timeout"));
+            }
+        }
+    };
+
+    // A client that stopped reading must not hold its slot while the close frame waits
+    let _ = tokio::time::timeout(pong_timeout, async {
+        if let Some(close) = close {
+            let _ = tx.send(close).await;
+        }
+        let _ = tx.close().await;
+    })
+    .await;
+}
+
+/// Run the web server until the process exits.
+pub async fn start_web_server(shared: Arc<SharedState>, options: WebOptions) {
+    if let Err(e) = start_with_shutdown(shared, options, ShutdownHandle::new()).await {
+        error!("Web server failed: {}", e);
+    }
+}
+
+/// Run the web server until `shutdown` is requested and its connections are drained.
+///
+/// Fails if the address can't be bound. The address is free again when this returns,
+/// so a new server can be started on it right away.
+pub async fn start_with_shutdown(shared: Arc<SharedState>, options: WebOptions, shutdown: ShutdownHandle) -> io::Result<()> {
+    WebServer::bind(shared, options).await?.run(shutdown).await
+}
+
+/// The listeners of a web server: plain HTTP, or HTTPS with plain HTTP alongside unless
+/// `--no-http` is given.
+enum Listeners {
+    Http(TcpListener),
+    Https { https: TcpListener, acceptor: TlsAcceptor, http: Option<TcpListener> },
+}
+
+/// A web server with its listeners bound, served by [`run`](Self::run).
+///
+/// Binding first tells the addresses the server got, such as the port the system picked for
+/// port 0; [`start_with_shutdown`] does both at once.
+pub struct WebServer {
+    shared: Arc<SharedState>,
+    options: WebOptions,
+    listeners: Listeners,
+}
+
+impl WebServer {
+    /// Bind the addresses of `options`; fails if one can't be bound or the TLS certificate
+    /// can't be loaded.
+    pub async fn bind(shared: Arc<SharedState>, options: WebOptions) -> io::Result<Self> {
+        let listeners = match &options.tls {
+            None => Listeners::Http(TcpListener::bind(options.address).await?),
+            Some(tls) => Listeners::Https {
+                acceptor: tls::acceptor(tls)?,
+                https: TcpListener::bind(tls.address).await?,
+                http: match tls.serve_http {
+                    true => Some(TcpListener::bind(options.address).await?),
+                    false => None,
+                },
+            },
+        };
+        Ok(Self { shared, options, listeners })
+    }
+
+    /// Address of the plain HTTP listener; `None` when only HTTPS is served.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        match &self.listeners {
+            Listeners::Http(http) | Listeners::Https { http: Some(http), .. } => http.local_addr().ok(),
+            Listeners::Https { http: None, .. } => None,
+        }
+    }
+
+    /// Address of the HTTPS listener, with `--tls-cert`.
+    pub fn https_addr(&self) -> Option<SocketAddr> {
+        match &self.listeners {
+            Listeners::Https { https, .. } => https.local_addr().ok(),
+            Listeners::Http(_) => None,
+        }
+    }
+
+    /// Serve until `shutdown` is requested and the connections are drained. The addresses are
+    /// free again when this returns.
+    pub async fn run(self, shutdown: ShutdownHandle) -> io::Result<()> {
+        let admin_url = match (self.https_addr(), self.local_addr()) {
+            (Some(https), _) => format!("https://localhost:{}", https.port()),
+            (None, http) => format!("http://localhost:{}", http.map_or(self.options.address.port(), |http| http.port())),
+        };
+        let Self { shared, options, listeners } = self;
+        // The defaults served to the pages; a configuration reload may change them later
+        shared.set_settings(LiveSettings {
+            theme: options.theme.clone(),
+            lang: options.lang,
+            swap_sides: options.swap_sides,
+            poll_interval: options.poll_interval,
+            websocket: options.websocket,
+            ..(*shared.settings()).clone()
+        });
+        let live = Arc::clone(&shared);
+        let token = options.admin_token.clone().unwrap_or_else(|| {
+            let token = auth::generate_token();
+            warn!("No --admin-token set, generated one for this run: {}", token);
+            warn!("Admin page: {}/admin?token={}", admin_url, token);
+            token
+        });
+        let request_metrics = Arc::clone(&shared.metrics);
+        let auth = Arc::new(WebAuth {
+            token,
+            limiter: AuthLimiter::default(),
+            metrics: Arc::clone(&shared.metrics),
+        });
+        // Each route is declared with the token it needs: the admin token, or for read-only
+        // endpoints none unless `--lock-read-api` is set
+        let mut table = RouteTable::new(auth, options.lock_read_api);
+
+        let assets = match Assets::new(options.web_root.as_deref()) {
+            Ok(assets) => assets,
+            Err(e) => {
+                error!("Cannot use web root {:?}, serving the embedded files: {}", options.web_root, e);
+                Assets::embedded()
+            }
+        };
+        if let Some(web_root) = &options.web_root {
+            info!("Serving overlay files from {} (embedded copies for missing files)", web_root.display());
+        }
+        // Restore the stylesheet saved by the last PUT /api/style
+        let custom_css = match CustomCss::load(&options.custom_css_file) {
+            Ok(custom_css) => custom_css,
+            Err(e) => {
+                warn!("Cannot load custom CSS from {}: {}", options.custom_css_file.display(), e);
+                CustomCss::empty(&options.custom_css_file)
+            }
+        };
+        if !custom_css.get().is_empty() {
+            info!("Adding custom CSS from {} to the overlay pages", options.custom_css_file.display());
+        }
+        let custom_css = Arc::new(custom_css);
+        let assets = Arc::new(assets.with_custom_css(Arc::clone(&custom_css)));
+        let assets_filter = warp::any().map(move || Arc::clone(&assets)).and(asset_request(Arc::clone(&live)));
+
+        let classic = themes::find(DEFAULT_THEME).expect("the default theme is built in");
+        info!("Default overlay theme: {}", themes::resolve(Some(&options.theme), classic).name);
+        let theme_settings = Arc::clone(&live);
+
+        // GET / -> serve the overlay page of `?theme=<name>`, else of the browser's preferred
+        // theme (`scoreboard_prefs` cookie), else of the default theme. A court that has had
+        // no frame yet gets a page saying so instead, which reloads until it has.
+        let index = table
+            .get("/", Access::Public)
+            .and(warp::query::<ThemeQuery>())
+            .and(prefs())
+            .and(assets_filter.clone())
+            .and(warp::ext::optional::<Court>())
+            .and_then(move |query: ThemeQuery, prefs: Prefs, assets: Arc<Assets>, request: AssetRequest, court: Option<Court>| {
+                // Unknown names were warned about when they were set
+                let default_theme = themes::find(&theme_settings.settings().theme).unwrap_or(classic);
+                let theme = themes::resolve(query.theme.as_deref(), prefs.theme.unwrap_or(default_theme));
+                let page = theme.page.to_string();
+                async move {
+                    match court {
+                        Some(court) if !court.has_data() => Ok(waiting_page(&court)),
+                        _ => serve_asset(assets, page, request).await,
+                    }
+                }
+            });
+
+        // GET /bug -> compact score bug (abbreviations, scores, period, clock) for broadcast
+        let bug = table
+            .get("/bug", Access::Public)
+            .and(assets_filter.clone())
+            .and_then(|assets: Arc<Assets>, request: AssetRequest| serve_asset(assets, "bug.html".to_string(), request));
+
+        // GET /slate -> full-screen pregame, halftime and final card for the videoboard
+        let slate = table
+            .get("/slate", Access::Public)
+            .and(assets_filter.clone())
+            .and_then(|assets: Arc<Assets>, request: AssetRequest| serve_asset(assets, "slate.html".to_string(), request));
+
+        // GET /<file> -> overlay files (stylesheets, overlay.js, theme pages, media) from
+        // `--web-root`, falling back to the embedded copies
+        let file_route = table.get("/*file", Access::Public);
+        let files = file_route
+            .clone()
+            .and(param::<String>("file"))
+            .and(assets_filter.clone())
+            .and_then(|file: String, assets: Arc<Assets>, request: AssetRequest| serve_asset(assets, file, request));
+
+        // GET /media/* -> team logos and other assets from static/media, when the files above
+        // don't have them
+        let media = file_route.and(warp::path("media")).and(warp::fs::dir("./static/media"));
+
+        // Restore the teams and match details saved by the last PUT /api/teams and /api/match
+        match &options.courts {
+            Some(courts) => {
+                for court in courts.iter() {
+                    load_saved(&court.shared(), court.teams_file(), court.match_file());
+                }
+            }
+            None => load_saved(&shared, &options.teams_file, &options.match_file),
+        }
+
+        // The court of a `/court/<id>/` request, else the one served at `/`
+        let primary = options.courts.as_ref().map(|courts| courts.primary().clone());
+        let court_filter = warp::ext::optional::<Court>().map(move |court: Option<Court>| court.or_else(|| primary.clone()));
+        let shared_filter = court_filter
+            .clone()
+            .map(move |court: Option<Court>| court.map_or_else(|| Arc::clone(&shared), |court| court.shared()));
+        let teams_file = Arc::new(options.teams_file.clone());
+        let teams_file_filter = court_filter.clone().map(move |court: Option<Court>| match court {
+            Some(court) => Arc::new(court.teams_file().to_path_buf()),
+            None => Arc::clone(&teams_file),
+        });
+        let match_file = Arc::new(options.match_file.clone());
+        let match_file_filter = court_filter.map(move |court: Option<Court>| match court {
+            Some(court) => Arc::new(court.match_file().to_path_buf()),
+            None => Arc::clone(&match_file),
+        });
+
+        // GET /api/game -> return current game state, with its age in seconds in the `Age` header
+        let stream_shutdown = shutdown.clone();
+        let shutdown_filter = warp::any().map(move || stream_shutdown.clone());
+        let game_api = table
+            .get("/api/game", Access::Read)
+            .and(shared_filter.clone())
+            .map(|shared: Arc<SharedState>| {
+                let reply = warp::reply::json(&shared.current());
+                match shared.state_age() {
+                    Some(age) => warp::reply::with_header(reply, "Age", age.as_secs().to_string()).into_response(),
+                    None => reply.into_response(),
+                }
+            });
+
+        // GET /api/state -> camelCase DTO for external tools (vMix, stats laptops), revalidated on
+        // every request: a weak ETag from the state version gets a 304 while the state is unchanged.
+        // Until the first frame arrives this is still 200 with `"state": null`, so clients
+        // always get the metrics and don't need a separate code path for 204.
+        // With `?wait=<secs>&version=<n>` (long polling, for browsers without WebSocket or
+        // EventSource) it answers once the state version exceeds `n`, or after `wait` seconds.
+        let long_polls = Arc::new(AtomicUsize::new(0));
+        let state_api = table
+            .get("/api/state", Access::Read)
+            .and(shared_filter.clone())
+            .and(warp::query::<StateQuery>())
+            .and(language(Arc::clone(&live)))
+            .and(warp::header::optional::<String>("if-none-match"))
+            .and(shutdown_filter.clone())
+            .and_then(move |shared: Arc<SharedState>, query: StateQuery, lang: Lang, if_none_match: Option<String>, shutdown: ShutdownHandle| {
+                let long_polls = Arc::clone(&long_polls);
+                async move {
+                    if let Some(wait) = query.wait.filter(|wait| *wait > 0) {
+                        let wait = Duration::from_secs(wait.min(MAX_LONG_POLL_WAIT));
+                        match ClientSlot::acquire(&long_polls, MAX_LONG_POLLS) {
+                            Some(_slot) => wait_for_state(&shared, query.version, wait, &shutdown).await,
+                            None => debug!("{} long polls already waiting, answering at once", MAX_LONG_POLLS),
+                        }
+                    }
+                    let body = ApiState::from_shared(&shared, lang);
+                    Ok::<_, Rejection>(state_reply(&body, lang, if_none_match.as_deref()))
+                }
+            });
+
+        // GET /api/state.xml -> the same state as XML for CG systems that only read XML, with the
+        // element and attribute names of `--xml-name`
+        let xml_names = Arc::new(options.xml_names.clone());
+        let state_xml_api = table
+            .get("/api/state.xml", Access::Read)
+            .and(shared_filter.clone())
+            .and(language(Arc::clone(&live)))
+            .map(move |shared: Arc<SharedState>, lang: Lang| {
+                let xml = xml::render_api_state(&ApiState::from_shared(&shared, lang), &xml_names);
+                let mut response = warp::reply::Response::new(xml.into());
+                let headers = response.headers_mut();
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/xml; charset=utf-8"));
+                headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+                response
+            });
+
+        // POST /api/state -> replace the state by hand (e.g. the console link died mid-game)
+        // PATCH /api/state -> change only the fields present in the body
+        // Both mark the state as a manual override; see `--override-mode` for what console frames do then.
+        let override_body = warp::body::content_length_limit(MAX_OVERRIDE_BODY).and(warp::body::json::<StatePatch>());
+        let state_post = table
+            .post("/api/state", Access::Admin)
+            .and(shared_filter.clone())
+            .and(override_body)
+            .and(language(Arc::clone(&live)))
+            .map(|shared: Arc<SharedState>, patch: StatePatch, lang: Lang| override_state(&shared, patch, true, lang));
+        let state_patch = table
+            .patch("/api/state", Access::Admin)
+            .and(shared_filter.clone())
+            .and(override_body)
+            .and(language(Arc::clone(&live)))
+            .map(|shared: Arc<SharedState>, patch: StatePatch, lang: Lang| override_state(&shared, patch, false, lang));
+
+        // DELETE /api/state/override -> hand control back to the console
+        let release_override = table
+            .delete("/api/state/override", Access::Admin)
+            .and(shared_filter.clone())
+            .and(language(Arc::clone(&live)))
+            .map(|shared: Arc<SharedState>, lang: Lang| {
+                shared.release_manual();
+                info!("Manual override released");
+                warp::reply::json(&ApiState::from_shared(&shared, lang))
+            });
+
+        // GET /admin -> operator control panel
+        let admin_page = table
+            .get("/admin", Access::Admin)
+            .and(assets_filter.clone())
+            .and_then(|assets: Arc<Assets>, request: AssetRequest| serve_asset(assets, "admin.html".to_string(), request));
+
+        // POST /api/control/new-game -> close out the game and start new trackers (game log,
+        // summary, line score) without waiting for `--auto-reset`
+        let new_game_api = table
+            .post("/api/control/new-game", Access::Admin)
+            .and(shared_filter.clone())
+            .and(language(Arc::clone(&live)))
+            .map(|shared: Arc<SharedState>, lang: Lang| {
+                shared.new_game();
+                warp::reply::json(&ApiState::from_shared(&shared, lang))
+            });
+
+        // POST /api/control/{score,foul,timeout,possession,period,clock} -> admin page actions
+        let control_api = table
+            .post("/api/control/:action", Access::Admin)
+            .and(param::<String>("action"))
+            .and(shared_filter.clone())
+            .and(warp::body::content_length_limit(MAX_OVERRIDE_BODY))
+            .and(warp::body::json::<serde_json::Value>())
+            .and(language(Arc::clone(&live)))
+            .map(|action: String, shared: Arc<SharedState>, body: serde_json::Value, lang: Lang| control(&shared, &action, body, lang));
+
+        // GET /api/teams -> team names, abbreviations, colors and logos (also part of every state)
+        // PUT /api/teams -> replace them; saved to `--teams-file` and restored at startup
+        let teams_get = table
+            .get("/api/teams", Access::Read)
+            .and(shared_filter.clone())
+            .map(|shared: Arc<SharedState>| warp::reply::json(&shared.current().teams));
+        let teams_put = table
+            .put("/api/teams", Access::Admin)
+            .and(shared_filter.clone())
+            .and(teams_file_filter.clone())
+            .and(warp::body::content_length_limit(MAX_OVERRIDE_BODY))
+            .and(warp::body::json::<Teams>())
+            .map(|shared: Arc<SharedState>, teams_file: Arc<PathBuf>, teams: Teams| put_teams(&shared, &teams_file, teams));
+
+        // GET /api/match -> competition, round, venue, tip-off and officials, with the countdown to
+        // the tip-off on the server's clock
+        // PUT /api/match -> replace them; saved to `--match-file` and restored at startup
+        let match_get = table
+            .get("/api/match", Access::Read)
+            .and(shared_filter.clone())
+            .map(|shared: Arc<SharedState>| {
+                let reply = warp::reply::json(&MatchDto::new(&shared.current().match_info, Some(unix_millis())));
+                warp::reply::with_header(reply, "Cache-Control", "no-store")
+            });
+        let match_put = table
+            .put("/api/match", Access::Admin)
+            .and(shared_filter.clone())
+            .and(match_file_filter)
+            .and(warp::body::content_length_limit(MAX_OVERRIDE_BODY))
+            .and(warp::body::json::<MatchInfo>())
+            .map(|shared: Arc<SharedState>, match_file: Arc<PathBuf>, info: MatchInfo| put_match(&shared, &match_file, info));
+
+        // POST /api/teams/{home,away}/logo -> upload a team logo (`multipart/form-data` field `logo`)
+        // and point the team's `logoUrl` at it
+        let uploads = Arc::new(Uploads::new(&options.upload_dir));
+        let logo_upload = table
+            .post("/api/teams/:side/logo", Access::Admin)
+            .and(param::<String>("side"))
+            .and(shared_filter.clone())
+            .and(teams_file_filter)
+            .and(warp::multipart::form().max_length((MAX_LOGO_BYTES + MAX_MULTIPART_OVERHEAD) as u64))
+            .and_then({
+                let uploads = Arc::clone(&uploads);
+                move |side: String, shared: Arc<SharedState>, teams_file: Arc<PathBuf>, form: FormData| {
+                    upload_logo(shared, Arc::clone(&uploads), teams_file, side, form)
+                }
+            });
+
+        // GET /uploads/<file> -> uploaded team logos; names change with every upload, so they never go stale
+        let uploaded_files = table
+            .get("/uploads/*file", Access::Public)
+            .and(warp::path("uploads"))
+            .and(warp::fs::dir(uploads.dir().to_path_buf()))
+            .map(|file: warp::fs::File| {
+                let reply = warp::reply::with_header(file, "X-Content-Type-Options", "nosniff");
+                warp::reply::with_header(reply, CACHE_CONTROL, "public, max-age=31536000, immutable")
+            });
+
+        // GET /api/locale?lang=pt -> period names, game states and labels the overlay pages translate.
+        // Public even with --lock-read-api: the strings are the same for every server.
+        let locale_api = table
+            .get("/api/locale", Access::Public)
+            .and(language(Arc::clone(&live)))
+            .map(|lang: Lang| {
+                let reply = warp::reply::json(&lang.strings());
+                warp::reply::with_header(reply, "Vary", "Accept-Language, Cookie")
+            });
+
+        // GET /api/overlay -> overlay defaults from the command line (`--swap-sides`) and the
+        // browser's preferences (`scoreboard_prefs` cookie), plus the themes to choose from
+        let overlay_settings = Arc::clone(&live);
+        let overlay_api = table
+            .get("/api/overlay", Access::Read)
+            .and(prefs())
+            .map(move |prefs: Prefs| {
+                let settings = OverlaySettings {
+                    swap_sides: overlay_settings.settings().swap_sides,
+                    scale: prefs.scale,
+                    themes: themes::THEMES.iter().map(|theme| theme.name).collect(),
+                };
+                warp::reply::with_header(warp::reply::json(&settings), VARY, "Cookie")
+            });
+
+        // GET /api/style -> the custom CSS added to the overlay pages
+        // PUT /api/style -> replace it (text/css body, empty to remove); saved to `--custom-css`
+        let style_get = table
+            .get("/api/style", Access::Read)
+            .map({
+                let custom_css = Arc::clone(&custom_css);
+                move || css_reply(custom_css.get())
+            });
+        let style_put = table
+            .put("/api/style", Access::Admin)
+            .and(warp::body::content_length_limit(MAX_CUSTOM_CSS as u64))
+            .and(warp::body::bytes())
+            .map(move |body: warp::hyper::body::Bytes| put_style(&custom_css, &body));
+
+        // GET /render.png?w=800&h=200 -> the scoreboard as a still image, for video switchers
+        // that can pull images but not render HTML (`raster` feature; 501 without it)
+        let snapshots = Arc::new(Snapshots::default());
+        let render_api = table
+            .get("/render.png", Access::Read)
+            .and(shared_filter.clone())
+            .and(warp::query::<RenderQuery>())
+            .map(move |shared: Arc<SharedState>, query: RenderQuery| render_png(&shared, &snapshots, query));
+
+        // GET /healthz -> 200 when the pipeline is healthy, 503 when a running game gets no frames.
+        // With several courts it lists them all, and fails when one does (its input couldn't
+        // start, ...); `/court/<id>/healthz` only checks that court.
+        // Public even with --lock-read-api so monitoring needs no token.
+        let health_stale_after = options.health_stale_after;
+        let health_courts = options.courts.clone();
+        let healthz = table
+            .get("/healthz", Access::Public)
+            .and(shared_filter.clone())
+            .and(warp::ext::optional::<Court>())
+            .map(move |shared: Arc<SharedState>, selected: Option<Court>| {
+                let mut health = Health::from_shared(&shared, health_stale_after);
+                let courts: Vec<&Court> = match (&selected, &health_courts) {
+                    (Some(court), _) => vec![court],
+                    (None, Some(courts)) => courts.iter().collect(),
+                    (None, None) => Vec::new(),
+                };
+                health.courts = courts.iter().map(|court| CourtHealth::new(court, health_stale_after)).collect();
+                health.healthy &= health.courts.iter().all(|court| court.healthy);
+                let status = if health.healthy {
+                    StatusCode::OK
+                } else {
+                    StatusCode::SERVICE_UNAVAILABLE
+                };
+                let reply = warp::reply::with_status(warp::reply::json(&health), status);
+                warp::reply::with_header(reply, "Cache-Control", "no-store")
+            });
+
+        // GET /courts -> with several courts, a page listing them with their scores and the
+        // age of their states; GET /api/courts -> the same as JSON
+        let court_list = options.courts.clone();
+        let court_summaries = warp::any().and_then(move || {
+            let summaries = court_list
+                .as_ref()
+                .map(|courts| courts.iter().map(|court| CourtSummary::new(court, health_stale_after)).collect::<Vec<_>>());
+            async move { summaries.ok_or_else(warp::reject::not_found) }
+        });
+        let courts_index = table
+            .get("/courts", Access::Read)
+            .and(court_summaries.clone())
+            .map(|courts: Vec<CourtSummary>| warp::reply::with_header(warp::reply::html(courts_page(&courts)), "Cache-Control", "no-store"));
+        let courts_api = table
+            .get("/api/courts", Access::Read)
+            .and(court_summaries)
+            .map(|courts: Vec<CourtSummary>| warp::reply::with_header(warp::reply::json(&courts), "Cache-Control", "no-store"));
+
+        // GET /api/history?limit=500&offset=0&from=<unix millis>&to=<unix millis>&every=1
+        // -> recent published states, oldest first, with the number selected in X-Total-Count
+        let history_api = table
+            .get("/api/history", Access::Read)
+            .and(shared_filter.clone())
+            .and(warp::query::<HistoryQuery>())
+            .map(|shared: Arc<SharedState>, query: HistoryQuery| {
+                let filter = match query.filter() {
+                    Ok(filter) => filter,
+                    Err(e) => return bad_request(&e),
+                };
+                let page = shared.history(&filter);
+                let zone = shared.timezone();
+                let body = HistoryResponse {
+                    entries: page.entries.iter().map(|entry| HistoryEntryDto::new(entry, &zone)).collect(),
+                    truncated: page.truncated,
+                };
+                let mut response = warp::reply::json(&body).into_response();
+                let headers = response.headers_mut();
+                headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+                headers.insert("x-total-count", HeaderValue::from(page.total));
+                response
+            });
+
+        // GET /api/events?since=<id>&limit=1000 -> logged game events after `since`, oldest first
+        let events_feed_api = table
+            .get("/api/events", Access::Read)
+            .and(shared_filter.clone())
+            .and(warp::query::<EventsQuery>())
+            .map(|shared: Arc<SharedState>, query: EventsQuery| {
+                let limit = query.limit.unwrap_or(DEFAULT_EVENTS_LIMIT).min(EVENT_LOG_CAPACITY);
+                let page = shared.events_since(query.since.unwrap_or(0), limit);
+                warp::reply::with_header(warp::reply::json(&page), "Cache-Control", "no-store")
+            });
+
+        // GET /api/game-log.csv -> every state change since the server started, as a spreadsheet
+        let game_log_api = table
+            .get("/api/game-log.csv", Access::Admin)
+            .and(shared_filter.clone())
+            .map(|shared: Arc<SharedState>| game_log_csv(&shared));
+
+        // POST /api/reload -> read the configuration again, like SIGHUP; 422 keeps the old one
+        let reload_shared = Arc::clone(&live);
+        let reload_api = table
+            .post("/api/reload", Access::Admin)
+            .map(move || match reload_shared.reload() {
+                Ok(report) => warp::reply::json(&report).into_response(),
+                Err(e) => {
+                    warn!("Reload failed, keeping the current configuration: {}", e);
+                    let body = warp::reply::json(&serde_json::json!({ "error": e }));
+                    warp::reply::with_status(body, StatusCode::UNPROCESSABLE_ENTITY).into_response()
+                }
+            });
+
+        // GET /api/summary -> box score of the last finished game, until the next one starts
+        let summary_api = table
+            .get("/api/summary", Access::Read)
+            .and(shared_filter.clone())
+            .map(|shared: Arc<SharedState>| match shared.game_summary() {
+                Some(summary) => warp::reply::with_header(warp::reply::json(&summary), "Cache-Control", "no-store").into_response(),
+                None => {
+                    let body = warp::reply::json(&serde_json::json!({ "error": "no finished game yet" }));
+                    warp::reply::with_status(body, StatusCode::NOT_FOUND).into_response()
+                }
+            });
+
+        // GET /api/time -> the server's clock and state version, for overlays estimating their
+        // clock offset (`clockValidAtMs` is on the server's clock); with `?samples=` of their
+        // round trips so far, the offset estimated from them
+        let time_api = table
+            .get("/api/time", Access::Read)
+            .and(shared_filter.clone())
+            .and(warp::query::<TimeQuery>())
+            .map(|shared: Arc<SharedState>, query: TimeQuery| {
+                let samples = match ClockSample::parse_list(query.samples.as_deref().unwrap_or_default()) {
+                    Ok(samples) => samples,
+                    Err(e) => return bad_request(&e),
+                };
+                let body = ServerTime {
+                    server_time_ms: unix_millis(),
+                    version: shared.version(),
+                    offset_ms: estimate_clock_offset(&samples),
+                };
+                warp::reply::with_header(warp::reply::json(&body), "Cache-Control", "no-store").into_response()
+            });
+
+        // GET /api/status -> link health and metrics (including per-peer traffic totals)
+        let status_api = table
+            .get("/api/status", Access::Read)
+            .and(shared_filter.clone())
+            .map(|shared: Arc<SharedState>| {
+                warp::reply::json(&shared.status())
+            });
+
+        // GET /metrics -> web request count and duration percentiles for Prometheus
+        let metrics_api = table
+            .get("/metrics", Access::Read)
+            .and(shared_filter.clone())
+            .map(|shared: Arc<SharedState>| {
+                let mut response = warp::reply::Response::new(shared.metrics.snapshot().to_prometheus().into());
+                let headers = response.headers_mut();
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/plain; version=0.0.4; charset=utf-8"));
+                headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+                response
+            });
+
+        // GET /api/stream -> SSE endpoint
+        // Optional `?min_interval_ms=500` coalesces clock-only updates for slow consumers;
+        // score, period and game state changes are always sent immediately.
+        let stream_api = table
+            .get("/api/stream", Access::Read)
+            .and(shared_filter.clone())
+            .and(warp::query::<StreamQuery>())
+            .and(shutdown_filter.clone())
+            .map(|shared: Arc<SharedState>, query: StreamQuery, shutdown: ShutdownHandle| {
+                let min_interval = Duration::from_millis(query.min_interval_ms.unwrap_or(0));
+                warp::sse::reply(warp::sse::keep_alive().stream(state_events(shared, min_interval, &shutdown)))
+            });
+
+        // GET /events -> plain SSE for signage browsers without reliable WebSockets:
+        // every state change, with a `: keepalive` comment every 15 seconds
+        let events_api = table
+            .get("/events", Access::Read)
+            .and(shared_filter.clone())
+            .and(warp::header::optional::<u64>("last-event-id"))
+            .and(shutdown_filter.clone())
+            .map(|shared: Arc<SharedState>, last_event_id: Option<u64>, shutdown: ShutdownHandle| {
+                // A reconnecting client gets the current state again, whatever it missed
+                if let Some(id) = last_event_id {
+                    debug!("SSE client reconnecting after event {}", id);
+                }
+                let keep_alive = warp::sse::keep_alive().interval(SSE_KEEPALIVE_INTERVAL).text("keepalive");
+                warp::sse::reply(keep_alive.stream(state_events(shared, Duration::ZERO, &shutdown)))
+            });
+
+        // GET /ws -> WebSocket push of the current state and every update;
+        // `?events=1` sends each update's game events (`{"id", "type", ...}`) after it
+        // GET /ws/control -> the same push, plus admin page commands (`{"cmd": "addPoints", ...}`,
+        // as for POST /api/control) answered with `{"ok": true}` or `{"error": "..."}`
+        let ws_clients = Arc::new(AtomicUsize::new(0));
+        let ws_slots = Arc::clone(&ws_clients);
+        let ping_interval = options.ws_ping_interval;
+        let upgrade = move |ws: warp::ws::Ws, shared: Arc<SharedState>, shutdown: ShutdownHandle, control: bool, events: bool| {
+            let ws = ws.max_message_size(MAX_WS_MESSAGE).max_frame_size(MAX_WS_MESSAGE);
+            let Some(slot) = ClientSlot::acquire(&ws_slots, MAX_WS_CLIENTS) else {
+                warn!("Refusing WebSocket client: {} already connected", MAX_WS_CLIENTS);
+                shared.metrics.ws_refused.fetch_add(1, Ordering::Relaxed);
+                // Browsers only show the close reason, not an HTTP error body
+                return ws
+                    .on_upgrade(move |mut socket| async move {
+                        let reason = format!("too many WebSocket clients ({} connected)", MAX_WS_CLIENTS);
+                        let _ = tokio::time::timeout(ping_interval / 2, socket.send(Message::close_with(1013u16, reason))).await;
+                    })
+                    .into_response();
+            };
+            ws.on_upgrade(move |socket| async move {
+                serve_ws(socket, shared, shutdown, ping_interval, control, events).await;
+                drop(slot);
+            })
+            .into_response()
+        };
+        let ws_push = upgrade.clone();
+        let ws_api = table
+            .get("/ws", Access::Read)
+            .and(warp::ws())
+            .and(shared_filter.clone())
+            .and(shutdown_filter.clone())
+            .and(warp::query::<WsQuery>())
+            .map(move |ws: warp::ws::Ws, shared: Arc<SharedState>, shutdown: ShutdownHandle, query: WsQuery| {
+                ws_push(ws, shared, shutdown, false, query.wants_events())
+            });
+        let ws_control = table
+            .get("/ws/control", Access::Admin)
+            .and(warp::ws())
+            .and(shared_filter.clone())
+            .and(shutdown_filter)
+            .and(warp::query::<WsQuery>())
+            .map(move |ws: warp::ws::Ws, shared: Arc<SharedState>, shutdown: ShutdownHandle, query: WsQuery| {
+                upgrade(ws, shared, shutdown, true, query.wants_events())
+            });
+
+        // OPTIONS /api/* -> CORS preflight for browser apps on other origins
+        let cors = Arc::new(CorsPolicy::new(&options.cors_allowed_origins));
+        if cors.is_enabled() {
+            info!("CORS enabled for {:?}", options.cors_allowed_origins);
+        }
+        let preflight_cors = Arc::clone(&cors);
+        let preflight = table
+            .route(Method::OPTIONS, "/api/*path", Access::Public)
+            .and(warp::header::<String>("origin"))
+            .and(warp::header::<String>("access-control-request-method"))
+            .map(move |origin: String, method: String| preflight_cors.preflight(&origin, &method));
+
+        let api_routes = api_path()
+            .and(
+                preflight
+                    .or(game_api)
+                    .or(state_api)
+                    .or(state_xml_api)
+                    .or(courts_api)
+                    .or(state_post)
+                    .or(state_patch)
+                    .or(release_override)
+                    .or(teams_get)
+                    .or(teams_put)
+                    .or(logo_upload)
+                    .or(match_get)
+                    .or(match_put)
+                    .or(style_get)
+                    .or(style_put)
+                    .or(locale_api)
+                    .or(overlay_api)
+                    .or(history_api)
+                    .or(events_feed_api)
+                    .or(game_log_api)
+                    .or(summary_api)
+                    .or(reload_api)
+                    .or(time_api)
+                    .or(status_api)
+                    .or(stream_api)
+                    .or(new_game_api)
+                    .or(control_api)
+                    .recover(|rejection| handle_rejection(rejection, true)),
+            );
+        let routes = api_routes
+            .or(index)
+            .or(bug)
+            .or(slate)
+            .or(render_api)
+            .or(healthz)
+            .or(metrics_api)
+            .or(courts_index)
+            .or(events_api)
+            .or(ws_api)
+            .or(ws_control)
+            .or(admin_page)
+            .or(files)
+            .or(media)
+            .or(uploaded_files)
+            .recover(|rejection| handle_rejection(rejection, false));
+        // Boxed: the future of the whole route tree is large enough to overflow a worker's
+        // stack in debug builds
+        let routes = with_cors(Arc::clone(&cors), routes).boxed();
+        let dispatch = table.dispatch(cors);
+
+        match listeners {
+            Listeners::Http(http) => serve(warp::service(routes), http, None, dispatch, &options, &request_metrics, &shutdown).await?,
+            Listeners::Https { https, acceptor, http } => {
+                let https_port = https.local_addr()?.port();
+                let https = serve(warp::service(routes.clone()), https, Some(acceptor), dispatch.clone(), &options, &request_metrics, &shutdown);
+                if let Some(http) = http {
+                    // Same routes over plain HTTP, except what needs the admin token
+                    let http = serve(warp::service(routes), http, None, dispatch.redirecting_to(https_port), &options, &request_metrics, &shutdown);
+                    tokio::try_join!(https, http)?;
+                } else {
+                    https.await?;
+                }
+            }
+        }
+
+        // Upgraded WebSockets outlive their HTTP connection; they got their close frame by now
+        let deadline = tokio::time::Instant::now() + options.drain_timeout;
+        while ws_clients.load(Ordering::SeqCst) > 0 && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        info!("Web server stopped");
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+use crate::{
+    dto::{ApiState, StateDto},
+    locale::Lang,
+    state::SharedState,
+    GameState,
+};
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::{error, info};
+
+/// Default shortest time between two rewrites of `--xml-file` while only the clock changes.
+pub const DEFAULT_XML_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Keys of the name map: the elements, then the attributes as `element.attribute`. The default
+/// name of a key is its last part: `home.score` is the `score` attribute of `<home>`.
+pub const KEYS: &[&str] = &[
+    "scoreboard",
+    "home",
+    "away",
+    "clock",
+    "shot_clock",
+    "period",
+    "match",
+    "scoreboard.version",
+    "scoreboard.stale",
+    "home.name",
+    "home.abbrev",
+    "home.score",
+    "home.fouls",
+    "home.timeouts",
+    "home.bonus",
+    "home.possession",
+    "away.name",
+    "away.abbrev",
+    "away.score",
+    "away.fouls",
+    "away.timeouts",
+    "away.bonus",
+    "away.possession",
+    "clock.time",
+    "clock.running",
+    "shot_clock.time",
+    "shot_clock.running",
+    "period.name",
+    "period.display",
+    "match.competition",
+    "match.round",
+    "match.venue",
+    "match.tip_off",
+    "match.officials",
+];
+
+/// An XML name we are willing to write: a letter or `_`, then letters, digits, `_`, `-` or `.`.
+/// Colons (namespaces) are left out on purpose.
+pub fn valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        && !name.to_ascii_lowercase().starts_with("xml")
+}
+
+/// Element and attribute name of each [`KEYS`] entry, for CG templates that expect their own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlNames {
+    names: HashMap<&'static str, String>,
+}
+
+impl Default for XmlNames {
+    fn default() -> Self {
+        let names = KEYS
+            .iter()
+            .map(|&key| (key, key.rsplit('.').next().unwrap_or(key).to_string()))
+            .collect();
+        Self { names }
+    }
+}
+
+impl XmlNames {
+    /// The default names with those of `--xml-name` applied; keys and names are checked when
+    /// the options are parsed.
+    pub fn new(renames: &[(&'static str, String)]) -> Self {
+        let mut names = Self::default();
+        for (key, name) in renames {
+            names.names.insert(key, name.clone());
+        }
+        names
+    }
+
+    fn name<'a>(&'a self, key: &'a str) -> &'a str {
+        self.names.get(key).map_or(key, String::as_str)
+    }
+}
+
+/// Escape text for an attribute value in double quotes.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// One empty element with its attributes, on its own indented line.
+fn element(xml: &mut String, names: &XmlNames, key: &str, attributes: &[(&str, String)]) {
+    let _ = write!(xml, "  <{}", names.name(key));
+    for (attribute, value) in attributes {
+        let _ = write!(xml, " {}=\"{}\"", names.name(&format!("{}.{}", key, attribute)), escape(value));
+    }
+    xml.push_str("/>\n");
+}
+
+/// Render a state as XML: `<scoreboard>` with `<home>`, `<away>`, `<clock>`, `<shot_clock>`,
+/// `<period>` and `<match>` elements whose attributes hold the values of `/api/state`, empty while
+/// the console shows a blank. `period.display` is the period name in `lang`, `match.officials`
+/// the officials separated by `, `.
+pub fn render(state: &GameState, version: u64, lang: Lang, names: &XmlNames) -> String {
+    render_dto(Some(&StateDto::localized(state, lang)), version, state.stale, names)
+}
+
+/// [`render`] of a state already converted; without one (no frame yet) `<scoreboard>` is empty.
+fn render_dto(state: Option<&StateDto>, version: u64, stale: bool, names: &XmlNames) -> String {
+    let number = |value: Option<u32>| value.map(|value| value.to_string()).unwrap_or_default();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = write!(
+        xml,
+        "<{} {}=\"{}\" {}=\"{}\"",
+        names.name("scoreboard"),
+        names.name("scoreboard.version"),
+        version,
+        names.name("scoreboard.stale"),
+        stale
+    );
+    let Some(state) = state else {
+        xml.push_str("/>\n");
+        return xml;
+    };
+    xml.push_str(">\n");
+
+    let sides = [
+        ("home", &state.teams.home, state.home_score, state.home_fouls, state.home_timeouts, state.home_bonus),
+        ("away", &state.teams.away, state.away_score, state.away_fouls, state.away_timeouts, state.away_bonus),
+    ];
+    for (side, team, score, fouls, timeouts, bonus) in sides {
+        // The serde names, `bonus` and `doubleBonus`, as in `/api/state`
+        let bonus = bonus
+            .and_then(|bonus| serde_json::to_value(bonus).ok())
+            .and_then(|bonus| bonus.as_str().map(String::from))
+            .unwrap_or_default();
+        let attributes = [
+            ("name", team.name.clone()),
+            ("abbrev", team.abbrev.clone()),
+            ("score", number(score)),
+            ("fouls", number(fouls)),
+            ("timeouts", number(timeouts)),
+            ("bonus", bonus),
+            ("possession", (state.possession.as_deref() == Some(side)).to_string()),
+        ];
+        element(&mut xml, names, side, &attributes);
+    }
+    let running = (state.game_state == "running").to_string();
+    element(&mut xml, names, "clock", &[("time", state.display_clock.clone()), ("running", running)]);
+    let shot_clock = match &state.shot_clock {
+        Some(shot_clock) => match shot_clock.tenths {
+            Some(tenths) => (format!("{}.{}", shot_clock.seconds, tenths), shot_clock.running.to_string()),
+            None => (shot_clock.seconds.to_string(), shot_clock.running.to_string()),
+        },
+        None => (String::new(), false.to_string()),
+    };
+    element(&mut xml, names, "shot_clock", &[("time", shot_clock.0), ("running", shot_clock.1)]);
+    element(
+        &mut xml,
+        names,
+        "period",
+        &[("name", state.period_name.clone()), ("display", state.display.period_name.clone())],
+    );
+    let info = &state.match_info.info;
+    let attributes = [
+        ("competition", info.competition.clone()),
+        ("round", info.round.clone()),
+        ("venue", info.venue.clone()),
+        ("tip_off", info.tip_off.clone().unwrap_or_default()),
+        ("officials", info.officials.join(", ")),
+    ];
+    element(&mut xml, names, "match", &attributes);
+    let _ = writeln!(xml, "</{}>", names.name("scoreboard"));
+    xml
+}
+
+/// The body of `GET /api/state.xml`: [`render`] of what `/api/state` returns.
+pub(crate) fn render_api_state(body: &ApiState, names: &XmlNames) -> String {
+    render_dto(body.state.as_ref(), body.version, body.stale, names)
+}
+
+/// Replace `path` with `xml` through a temporary file, so a CG system polling it never reads
+/// half a file. Not synced to the disk: it is rewritten all game long and a lost write is
+/// replaced by the next one.
+fn write_file(path: &Path, xml: &str) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    fs::File::create(&tmp)?.write_all(xml.as_bytes())?;
+    fs::rename(&tmp, path)
+}
+
+/// Rewrite `path` with the XML of every published state (`--xml-file`): at once for a score,
+/// foul, period or other change, at most every `interval` while only the clock runs.
+pub fn start(path: &Path, interval: Duration, names: XmlNames, lang: Lang, shared: &SharedState) {
+    info!("Writing the state as XML to {}", path.display());
+    let path = path.to_path_buf();
+    let mut failing = false;
+    shared.add_listener(interval, move |update| {
+        let xml = render(&update.state, update.version, lang, &names);
+        match write_file(&path, &xml) {
+            Ok(()) if failing => {
+                info!("Writing the XML file {} again", path.display());
+                failing = false;
+            }
+            Ok(()) => {}
+            // Logged once per failure streak, not once per state
+            Err(e) if !failing => {
+                error!("Failed to write the XML file {}: {}", path.display(), e);
+                failing = true;
+            }
+            Err(_) => {}
+        }
+    });
+}
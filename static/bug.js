@@ -0,0 +1,42 @@
+// Compact score bug; the live data comes from common.js like the full overlay
+
+function showTeams() {
+    document.getElementById('home-abbrev').textContent = teams.home.abbrev;
+    document.getElementById('away-abbrev').textContent = teams.away.abbrev;
+}
+showTeams();
+
+function setText(id, value) {
+    const element = document.getElementById(id);
+    const text = String(value).trim();
+    if (element.textContent !== text) {
+        element.textContent = text;
+        return true;
+    }
+    return false;
+}
+
+function flashIfChanged(id, value) {
+    if (setText(id, value)) {
+        const element = document.getElementById(id);
+        element.classList.add('updated');
+        setTimeout(() => element.classList.remove('updated'), 500);
+    }
+}
+
+function updateBug(data) {
+    if (updateTeams(data.teams)) {
+        showTeams();
+    }
+    flashIfChanged('home-score', data.home_score);
+    flashIfChanged('away-score', data.away_score);
+    updateBonusAndPossession(data);
+    playEvents(data.events);
+    setText('period', shortPeriod(data.period_name));
+    setText('time', currentClock(data));
+    document.getElementById('time').classList.toggle('paused', data.game_state === 'paused');
+    document.querySelector('.scoreboard').classList.toggle('stale', !!data.stale);
+}
+
+connectLiveState(updateBug);
+renderClockEvery(clock => setText('time', clock));
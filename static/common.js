@@ -0,0 +1,559 @@
+// Shared by every overlay page (overlay themes, /bug): URL options, the OBS
+// background and the live state connection. Load it before the page's own script.
+
+const urlParams = new URLSearchParams(window.location.search);
+// Written into every page by the server: { apiBase, pollMs, wsEnabled, lang, swapSides }. apiBase is the
+// path the server is reached under behind a reverse proxy (X-Forwarded-Prefix), '' otherwise.
+window.SCOREBOARD_CONFIG = window.SCOREBOARD_CONFIG || readPageConfig();
+const apiBase = window.SCOREBOARD_CONFIG.apiBase || '';
+
+function readPageConfig() {
+    const element = document.getElementById('scoreboard-config');
+    try {
+        return element ? JSON.parse(element.textContent) : {};
+    } catch (e) {
+        console.error('Error parsing page config:', e);
+        return {};
+    }
+}
+// Only needed when the server locks the read endpoints (--lock-read-api)
+const token = urlParams.get('token');
+const tokenQuery = token ? `?token=${encodeURIComponent(token)}` : '';
+
+// Teams: `?home=Madeira&away=Galomar`, logos with `?homeLogo=<url>`, colors with
+// `?homeColor=1E90FF` and 3-letter abbreviations with `?homeAbbrev=CAB`. URL values win
+// over the ones the operator sets with PUT /api/teams (sent with every state), which win
+// over the defaults (HOME/AWAY, the first 3 letters of the name).
+function resolveTeam(side, server) {
+    server = server || {};
+    const name = urlParams.get(side) || server.name || side.toUpperCase();
+    // A name from the URL replaces the operator's team, abbreviation included
+    const serverAbbrev = urlParams.get(side) ? '' : server.abbrev;
+    const abbrev = urlParams.get(`${side}Abbrev`) || serverAbbrev || name.replace(/[^\p{L}\p{N}]/gu, '').slice(0, 3);
+    const color = urlParams.get(`${side}Color`);
+    return {
+        name,
+        abbrev: abbrev.slice(0, 3).toUpperCase(),
+        logo: urlParams.get(`${side}Logo`) || server.logoUrl || '',
+        color: /^[0-9a-fA-F]{6}$/.test(color || '') ? `#${color}` : (server.color || ''),
+    };
+}
+
+// Sides: `?swap=1` shows the away team in the home slots and the other way round (team
+// names, logos, colors, scores, fouls, time-outs, bonus and possession), for venues whose
+// camera sees the bench the other way. `?swap=0` keeps the normal sides; without the
+// parameter the server's --swap-sides decides. The server settles that in `swapSides` of
+// the page config (page_config::swap_sides): the URL wins, and both set to swap is swapped
+// once, never back. URL team parameters still name the real teams: `?home=` is the home
+// team wherever it is shown.
+const swapParam = urlParams.get('swap');
+let sidesSwapped = typeof window.SCOREBOARD_CONFIG.swapSides === 'boolean'
+    ? window.SCOREBOARD_CONFIG.swapSides
+    : swapParam === '1' || swapParam === 'true';
+
+function resolveTeams(serverTeams) {
+    const home = resolveTeam('home', serverTeams && serverTeams.home);
+    const away = resolveTeam('away', serverTeams && serverTeams.away);
+    return sidesSwapped ? { home: away, away: home } : { home, away };
+}
+
+// The state as the page's slots show it: home fields in the away slots when swapped.
+// `teams` is left alone, resolveTeams swaps it.
+function swapSides(data) {
+    if (!sidesSwapped) {
+        return data;
+    }
+    const swapped = { ...data };
+    for (const field of ['score', 'fouls', 'timeouts', 'bonus']) {
+        swapped[`home_${field}`] = data[`away_${field}`];
+        swapped[`away_${field}`] = data[`home_${field}`];
+    }
+    swapped.possession = { home: 'away', away: 'home' }[data.possession] || data.possession;
+    if (data.active_timeout) {
+        swapped.active_timeout = { ...data.active_timeout, side: data.active_timeout.side === 'home' ? 'away' : 'home' };
+    }
+    if (data.line_score) {
+        swapped.line_score = data.line_score.map(period => ({ ...period, home: period.away, away: period.home }));
+    }
+    if (data.changes) {
+        swapped.changes = data.changes.map(change => (change.side ? { ...change, side: change.side === 'home' ? 'away' : 'home' } : change));
+    }
+    return swapped;
+}
+
+let teams = resolveTeams();
+
+function applyTeamColors() {
+    for (const side of ['home', 'away']) {
+        if (teams[side].color) {
+            document.documentElement.style.setProperty(`--${side}-color`, teams[side].color);
+        } else {
+            document.documentElement.style.removeProperty(`--${side}-color`);
+        }
+    }
+}
+applyTeamColors();
+
+// Take the teams from a state; true when anything the page shows changed
+function updateTeams(serverTeams) {
+    const resolved = resolveTeams(serverTeams);
+    if (JSON.stringify(resolved) === JSON.stringify(teams)) {
+        return false;
+    }
+    teams = resolved;
+    applyTeamColors();
+    return true;
+}
+
+// Bonus badges (`home_bonus`: "bonus" or "doubleBonus", set by the server's --rules) and
+// the possession arrows. Pages place `#home-bonus`, `#home-possession` and the away ones
+// where they fit; missing elements are skipped.
+const BONUS_LABELS = { bonus: 'BONUS', doubleBonus: '+BONUS' };
+
+function updateBonusAndPossession(data) {
+    for (const side of ['home', 'away']) {
+        const badge = document.getElementById(`${side}-bonus`);
+        if (badge) {
+            const label = BONUS_LABELS[data[`${side}_bonus`]] || '';
+            badge.textContent = label;
+            badge.classList.toggle('visible', label !== '');
+        }
+        const arrow = document.getElementById(`${side}-possession`);
+        if (arrow) {
+            arrow.classList.toggle('active', data.possession === side);
+        }
+    }
+}
+
+// Server clock: how far the server's clock (GET /api/time) is ahead of this machine's,
+// estimated NTP-style from a few round trips and refreshed every minute. Each request sends
+// the round trips timed so far (`?samples=sent,received,server;...`) and the server answers
+// with the offset estimated from them (`offsetMs`), so pages and server share one formula:
+// the sample with the shortest round trip, taken as answered halfway through it. Event
+// expiry and the running game clock are computed on the corrected time.
+const CLOCK_SAMPLES = 3;
+const CLOCK_RESYNC_MS = 60000;
+let clockOffsetMs = 0;
+
+function serverNow() {
+    return Date.now() + clockOffsetMs;
+}
+
+// samples: [{ sentMs, receivedMs, serverTimeMs }], local times around each request
+function syncServerClock(samples = []) {
+    const query = new URLSearchParams(token ? { token } : {});
+    if (samples.length) {
+        query.set('samples', samples.map(s => `${s.sentMs},${s.receivedMs},${s.serverTimeMs}`).join(';'));
+    }
+    const sentMs = Date.now();
+    fetch(`${apiBase}/api/time?${query}`, { cache: 'no-store' })
+        .then(response => response.json())
+        .then(time => {
+            if (typeof time.offsetMs === 'number') {
+                clockOffsetMs = time.offsetMs;
+            }
+            // The reply to the last samples only carries the offset
+            if (samples.length < CLOCK_SAMPLES) {
+                samples.push({ sentMs, receivedMs: Date.now(), serverTimeMs: time.serverTimeMs });
+                syncServerClock(samples);
+            }
+        })
+        .catch(error => console.error('Error fetching server time:', error));
+}
+
+// The game clock to show now. While it runs, the server stamps the state with the server
+// time its clock was read (`clock_valid_at_ms`), so pages count down between pushes on the
+// corrected clock, and a throttled background tab is right again as soon as it renders.
+// Formatted like the console: `MM:SS` (whole seconds rounded up), `SS.t` under a minute.
+function clockTenths(time) {
+    const minutes = /^\s*(\d+):(\d+)\s*$/.exec(time || '');
+    if (minutes) {
+        return (parseInt(minutes[1]) * 60 + parseInt(minutes[2])) * 10;
+    }
+    const tenths = /^\s*(\d+)\.(\d)\s*$/.exec(time || '');
+    return tenths ? parseInt(tenths[1]) * 10 + parseInt(tenths[2]) : null;
+}
+
+function formatTenths(tenths) {
+    const pad = n => String(n).padStart(2, '0');
+    if (tenths >= 600) {
+        const seconds = Math.ceil(tenths / 10);
+        return `${pad(Math.floor(seconds / 60))}:${pad(seconds % 60)}`;
+    }
+    return `${pad(Math.floor(tenths / 10))}.${tenths % 10}`;
+}
+
+function currentClock(data) {
+    const tenths = clockTenths(data.time);
+    if (!data.clock_valid_at_ms || data.stale || tenths === null) {
+        return data.display_clock || data.time;
+    }
+    const elapsed = Math.floor((serverNow() - data.clock_valid_at_ms) / 100);
+    return formatTenths(Math.max(0, tenths - Math.max(0, elapsed)));
+}
+
+// Render the clock of the last state every 100 ms (the console's resolution)
+const CLOCK_RENDER_MS = 100;
+
+function renderClockEvery(render) {
+    setInterval(() => {
+        if (lastState) {
+            render(currentClock(lastState));
+        }
+    }, CLOCK_RENDER_MS);
+}
+
+// End-of-period banner and horn flash. The server sends each event (`events`) with the
+// states until it expires, so a page that reconnects meanwhile still plays it; each id is
+// played once. Pages provide `#period-banner`; the `.scoreboard` flashes.
+const MAX_EVENT_MS = 5000;
+const playedEvents = new Set();
+let bannerTimer = null;
+let flashTimer = null;
+
+// "3 Quarter" -> "END OF 3RD", "Overtime" -> "END OF OT"
+function endOfPeriodText(period) {
+    const quarter = /^(\d+) Quarter$/.exec(period || '');
+    if (quarter) {
+        const suffix = { 1: 'ST', 2: 'ND', 3: 'RD' }[quarter[1]] || 'TH';
+        return `END OF ${quarter[1]}${suffix}`;
+    }
+    if (period === 'Overtime') {
+        return 'END OF OT';
+    }
+    return 'END OF PERIOD';
+}
+
+function playEvents(events) {
+    for (const event of events || []) {
+        if (playedEvents.has(event.id)) {
+            continue;
+        }
+        playedEvents.add(event.id);
+        // Expiry is on the server's clock; never play longer than the server keeps the event
+        const remaining = Math.min(event.expiresAtMs - serverNow(), MAX_EVENT_MS);
+        if (remaining <= 0) {
+            continue;
+        }
+
+        const scoreboard = document.querySelector('.scoreboard');
+        if (scoreboard) {
+            clearTimeout(flashTimer);
+            scoreboard.classList.add('buzzer');
+            flashTimer = setTimeout(() => scoreboard.classList.remove('buzzer'), Math.min(remaining, 1500));
+        }
+        const banner = document.getElementById('period-banner');
+        if (banner && event.type === 'PeriodEnded') {
+            clearTimeout(bannerTimer);
+            banner.textContent = endOfPeriodText(event.period);
+            banner.classList.add('visible');
+            bannerTimer = setTimeout(() => banner.classList.remove('visible'), remaining);
+        }
+    }
+}
+
+// Display language: `?lang=pt`, else the browser's Accept-Language, else the server's
+// --lang. GET /api/locale answers with the strings in that language; until it does (or
+// when it fails) the server's English names are shown.
+let locale = null;
+
+// "2 Quarter" -> "2º Período" with ?lang=pt; names the server doesn't translate are kept
+function localPeriodName(periodName) {
+    return (locale && locale.periodNames[periodName]) || periodName;
+}
+
+// "2 Quarter" -> "Q2", "Overtime" -> "OT", other names in the display language
+function shortPeriod(periodName) {
+    const quarter = /^(\d+) Quarter$/.exec(periodName || '');
+    if (quarter) {
+        return `Q${quarter[1]}`;
+    }
+    if (periodName === 'Overtime') {
+        return 'OT';
+    }
+    return localPeriodName(periodName) || '-';
+}
+
+// Elements with `data-label="fouls"` (or "timeouts") get the label in the display language
+function applyLabels() {
+    for (const element of document.querySelectorAll('[data-label]')) {
+        const label = locale.labels[element.dataset.label];
+        if (label) {
+            element.textContent = label;
+        }
+    }
+}
+
+function loadLocale(onLoaded) {
+    const lang = urlParams.get('lang') || window.SCOREBOARD_CONFIG.lang;
+    fetch(`${apiBase}/api/locale${lang ? `?lang=${encodeURIComponent(lang)}` : ''}`)
+        .then(response => response.json())
+        .then(strings => {
+            locale = strings;
+            applyLabels();
+            onLoaded();
+        })
+        .catch(error => {
+            console.error('Error fetching strings:', error);
+        });
+}
+
+// Overlay defaults: --swap-sides from the server's command line and the scale saved with
+// the settings widget (below). URL parameters win over both.
+function loadOverlaySettings(onLoaded) {
+    fetch(`${apiBase}/api/overlay${tokenQuery}`)
+        .then(response => response.json())
+        .then(settings => {
+            if (!(scaleParam > 0)) {
+                applyScale(settings.scale);
+            }
+            // A --swap-sides reloaded since the page was served
+            if (swapParam === null && settings.swapSides !== sidesSwapped) {
+                sidesSwapped = settings.swapSides;
+                onLoaded();
+            }
+        })
+        .catch(error => {
+            console.error('Error fetching overlay settings:', error);
+        });
+}
+
+// Page background for OBS browser sources: transparent (default), or a solid
+// chroma key color with `?bg=chroma&color=00FF00`
+function setBackground(mode, color) {
+    if (mode !== 'chroma') {
+        return;
+    }
+    const keyColor = /^[0-9a-fA-F]{6}$/.test(color || '') ? color : '00FF00';
+    document.documentElement.style.background = `#${keyColor}`;
+    document.body.classList.add('chroma');
+}
+setBackground(urlParams.get('bg'), urlParams.get('color'));
+
+// Size the scoreboard with `?scale=1.5`; pages apply it through the --scale variable
+const scaleParam = parseFloat(urlParams.get('scale'));
+
+function applyScale(scale) {
+    if (scale > 0) {
+        document.documentElement.style.setProperty('--scale', scale);
+    }
+}
+applyScale(scaleParam);
+
+// Settings widget: theme, scale and language for this browser, saved in the
+// `scoreboard_prefs` cookie (`theme=minimal&scale=1.5&lang=pt`). The server reads it for `/`,
+// /api/locale and /api/overlay, so the choice sticks without bookmarking query strings; URL
+// parameters still win. Overlay pages open it with the S key (OBS: Interact), the admin
+// page shows it in place.
+const PREFS_COOKIE = 'scoreboard_prefs';
+const PREFS_MAX_AGE_SECS = 365 * 24 * 3600;
+const PREFS_LANGUAGES = { en: 'English', pt: 'Português', fr: 'Français' };
+let settingsWidget = null;
+
+function readPrefs() {
+    const cookie = document.cookie.split(';').map(c => c.trim()).find(c => c.startsWith(`${PREFS_COOKIE}=`));
+    return new URLSearchParams(cookie ? cookie.slice(PREFS_COOKIE.length + 1) : '');
+}
+
+function savePrefs(prefs) {
+    const value = new URLSearchParams(Object.entries(prefs).filter(([, v]) => v)).toString();
+    document.cookie = value
+        ? `${PREFS_COOKIE}=${value}; path=/; max-age=${PREFS_MAX_AGE_SECS}; SameSite=Lax`
+        : `${PREFS_COOKIE}=; path=/; max-age=0`;
+}
+
+function buildSettingsWidget(themes) {
+    const prefs = readPrefs();
+    const form = document.createElement('form');
+    form.className = 'settings-widget';
+
+    const select = (name, choices, defaultLabel) => {
+        const element = document.createElement('select');
+        element.name = name;
+        element.add(new Option(defaultLabel, ''));
+        for (const [value, label] of Object.entries(choices)) {
+            element.add(new Option(label, value, false, prefs.get(name) === value));
+        }
+        return element;
+    };
+    const scale = document.createElement('input');
+    Object.assign(scale, { name: 'scale', type: 'number', min: '0.1', max: '5', step: '0.1', placeholder: '1', value: prefs.get('scale') || '' });
+    const save = document.createElement('button');
+    save.textContent = 'Save';
+    const reset = document.createElement('button');
+    reset.type = 'button';
+    reset.textContent = 'Reset';
+
+    const theme = select('theme', Object.fromEntries(themes.map(name => [name, name])), 'Server default');
+    const lang = select('lang', PREFS_LANGUAGES, 'Browser default');
+    const fields = [['Theme', theme], ['Scale', scale], ['Language', lang]];
+    for (const [text, input] of fields) {
+        const label = document.createElement('label');
+        label.append(`${text} `, input);
+        form.append(label, ' ');
+    }
+    form.append(save, ' ', reset);
+
+    // Reload so `/` serves the chosen theme and the strings come in the chosen language
+    form.addEventListener('submit', event => {
+        event.preventDefault();
+        savePrefs({ theme: theme.value, scale: scale.value, lang: lang.value });
+        window.location.reload();
+    });
+    reset.addEventListener('click', () => {
+        savePrefs({});
+        window.location.reload();
+    });
+    return form;
+}
+
+// Put the widget into `container`, or float it over an overlay page when none is given
+function showSettingsWidget(container) {
+    fetch(`${apiBase}/api/overlay${tokenQuery}`)
+        .then(response => response.json())
+        .then(settings => {
+            settingsWidget = buildSettingsWidget(settings.themes || []);
+            if (!container) {
+                settingsWidget.style.cssText = 'position: fixed; top: 8px; right: 8px; z-index: 1000; padding: 8px;'
+                    + ' font: 14px sans-serif; color: #eee; background: rgba(20, 20, 20, 0.95); border-radius: 6px;';
+            }
+            (container || document.body).append(settingsWidget);
+        })
+        .catch(error => {
+            console.error('Error fetching overlay settings:', error);
+        });
+}
+
+function enableSettingsShortcut() {
+    document.addEventListener('keydown', event => {
+        if (event.key !== 's' && event.key !== 'S') {
+            return;
+        }
+        if (settingsWidget) {
+            settingsWidget.hidden = !settingsWidget.hidden;
+        } else {
+            showSettingsWidget(null);
+        }
+    });
+}
+
+// Live updates: prefer the WebSocket push, poll /api/game while it is unavailable.
+// connectLiveState(onState) calls onState with every state (same JSON as /api/game),
+// and once more with the last one when the display strings or the server's side setting
+// arrive. States are passed through swapSides. The admin page passes
+// { path: '/ws/control', onReply, swap: false, settings: <element> } to also send commands
+// with sendLiveCommand, always see the real sides and show the settings widget in place.
+const POLL_INTERVAL_MS = window.SCOREBOARD_CONFIG.pollMs || 1000;
+const MAX_RECONNECT_DELAY_MS = 10000;
+let pollTimer = null;
+let reconnectDelay = 1000;
+let onLiveState = () => {};
+let lastState = null;
+let liveSocketPath = '/ws';
+let liveSocket = null;
+let onLiveReply = () => {};
+
+function handleStateMessage(text) {
+    try {
+        const data = JSON.parse(text);
+        // `{"ok": true}` or `{"error": "..."}` answers a command on /ws/control
+        if ('ok' in data || 'error' in data) {
+            onLiveReply(data);
+        } else {
+            onLiveState(data);
+        }
+    } catch (e) {
+        console.error('Error parsing data:', e);
+    }
+}
+
+function pollState() {
+    fetch(`${apiBase}/api/game${tokenQuery}`)
+        .then(response => response.json())
+        .then(data => {
+            if (data) {
+                onLiveState(data);
+            }
+        })
+        .catch(error => {
+            console.error('Error fetching state:', error);
+        });
+}
+
+function startPolling() {
+    if (pollTimer === null) {
+        pollState();
+        pollTimer = setInterval(pollState, POLL_INTERVAL_MS);
+    }
+}
+
+function stopPolling() {
+    if (pollTimer !== null) {
+        clearInterval(pollTimer);
+        pollTimer = null;
+    }
+}
+
+function connectWebSocket() {
+    if (!('WebSocket' in window) || window.SCOREBOARD_CONFIG.wsEnabled === false) {
+        startPolling();
+        return;
+    }
+
+    const protocol = window.location.protocol === 'https:' ? 'wss:' : 'ws:';
+    const socket = new WebSocket(`${protocol}//${window.location.host}${apiBase}${liveSocketPath}${tokenQuery}`);
+
+    socket.onopen = () => {
+        // The server sends the current state right away
+        liveSocket = socket;
+        stopPolling();
+        reconnectDelay = 1000;
+    };
+
+    socket.onmessage = (event) => handleStateMessage(event.data);
+
+    socket.onclose = () => {
+        liveSocket = null;
+        startPolling();
+        setTimeout(connectWebSocket, reconnectDelay);
+        reconnectDelay = Math.min(reconnectDelay * 2, MAX_RECONNECT_DELAY_MS);
+    };
+}
+
+// Send a command on /ws/control; false while the socket is down (use the REST endpoint then)
+function sendLiveCommand(command) {
+    if (!liveSocket || liveSocket.readyState !== WebSocket.OPEN) {
+        return false;
+    }
+    liveSocket.send(JSON.stringify(command));
+    return true;
+}
+
+function connectLiveState(onState, options = {}) {
+    liveSocketPath = options.path || '/ws';
+    onLiveReply = options.onReply || (() => {});
+    onLiveState = (state) => {
+        lastState = state;
+        onState(swapSides(state));
+    };
+    // Show the last state again once its names can be translated or its sides are known
+    const showAgain = () => {
+        if (lastState) {
+            onState(swapSides(lastState));
+        }
+    };
+    loadLocale(showAgain);
+    if (options.settings) {
+        showSettingsWidget(options.settings);
+    } else {
+        enableSettingsShortcut();
+    }
+    if (options.swap === false) {
+        sidesSwapped = false;
+    } else {
+        loadOverlaySettings(showAgain);
+    }
+    syncServerClock();
+    setInterval(() => syncServerClock(), CLOCK_RESYNC_MS);
+    connectWebSocket();
+}
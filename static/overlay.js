@@ -1,16 +1,6 @@
 // State management
 let currentGameState = null;
 
-// Get URL parameters
-const urlParams = new URLSearchParams(window.location.search);
-const homeTeamName = urlParams.get('home') || 'HOME';
-const awayTeamName = urlParams.get('away') || 'AWAY';
-const homeLogo = urlParams.get('homeLogo') || '';
-const awayLogo = urlParams.get('awayLogo') || '';
-
-// Update team names
-document.getElementById('home-team-name').textContent = homeTeamName;
-document.getElementById('away-team-name').textContent = awayTeamName;
 
 // Update logos
 function setLogo(team, logoUrl) {
@@ -29,39 +19,36 @@ function setLogo(team, logoUrl) {
         placeholder.style.display = 'block';
     }
 }
-setLogo('home', homeLogo);
-setLogo('away', awayLogo);
-
-// Connect to SSE endpoint
-const evtSource = new EventSource('/api/stream');
-
-evtSource.onopen = () => {
-    // Connection opened
-};
-
-evtSource.onerror = () => {
-    // Connection error
-};
-
-evtSource.onmessage = (event) => {
-    try {
-        const data = JSON.parse(event.data);
-        updateScoreboard(data);
-    } catch (e) {
-        console.error('Error parsing data:', e);
-    }
-};
+
+// Team names and logos (teams comes from common.js)
+function showTeams() {
+    document.getElementById('home-team-name').textContent = teams.home.name;
+    document.getElementById('away-team-name').textContent = teams.away.name;
+    setLogo('home', teams.home.logo);
+    setLogo('away', teams.away.logo);
+}
+showTeams();
+
+connectLiveState(updateScoreboard);
+renderClockEvery(clock => {
+    updateTime(clock);
+    updateTimeoutBanner(currentGameState);
+});
 
 function updateScoreboard(data) {
+    if (updateTeams(data.teams)) {
+        showTeams();
+    }
+
     // Update scores with animation only if changed
     updateElementIfChanged('home-score', data.home_score);
     updateElementIfChanged('away-score', data.away_score);
     
     // Update time with subtle animation
-    updateTime(data.time);
+    updateTime(currentClock(data));
     
     // Update period
-    document.getElementById('period').textContent = data.period_name;
+    document.getElementById('period').textContent = localPeriodName(data.period_name);
     
     // Update fouls
     updateFouls('home', data.home_fouls);
@@ -71,16 +58,74 @@ function updateScoreboard(data) {
     updateTimeouts('home', data.home_timeouts);
     updateTimeouts('away', data.away_timeouts);
     
-    // Update shot clock
-    updateShotClock(data.shot_clock);
+    // Bonus badges and possession arrow (no arrow when possession is unknown)
+    updateBonusAndPossession(data);
+    
+    // End of period banner and horn flash
+    playEvents(data.events);
+
+    // "+3" over the scores that just changed
+    popScoreChanges(data.changes);
+
+    // "TIMEOUT – HOME 0:42" while a time-out is played
+    updateTimeoutBanner(data);
+    
+    // Update shot clock (hidden when blanked or never received)
+    updateShotClock(data.display_shot_clock || data.shot_clock);
     
     // Update game state (pause dot)
     updateGameState(data.game_state);
     
+    // Dim the scoreboard when the input has gone away
+    updateStale(data.stale);
+    
     // Store current state
     currentGameState = data;
 }
 
+// Pushed states carry the `changes` that produced them, each with a unique id; the state a
+// page gets on (re)connecting has none, so nothing is replayed
+const SCORE_POP_MS = 1500;
+const poppedChanges = new Set();
+const popTimers = {};
+
+function popScoreChanges(changes) {
+    for (const change of changes || []) {
+        if (change.type !== 'ScoreChange' || poppedChanges.has(change.id)) {
+            continue;
+        }
+        poppedChanges.add(change.id);
+        const element = document.getElementById(`${change.side}-score`);
+        if (!element || !(change.delta > 0)) {
+            continue;
+        }
+        element.dataset.pop = `+${change.delta}`;
+        // Restart the animation when a second basket follows quickly
+        element.classList.remove('popped');
+        void element.offsetWidth;
+        element.classList.add('popped');
+        clearTimeout(popTimers[change.side]);
+        popTimers[change.side] = setTimeout(() => element.classList.remove('popped'), SCORE_POP_MS);
+    }
+}
+
+// The server starts a time-out (`active_timeout`) when a team's count goes down, counts it
+// down (--rules, --timeout-length) and drops it early once the clock runs again. Between
+// pushes the banner counts to `endsAtMs` on the server's clock.
+function updateTimeoutBanner(data) {
+    const banner = document.getElementById('timeout-banner');
+    if (!banner) return;
+
+    const timeout = data && !data.stale ? data.active_timeout : null;
+    const remaining = timeout ? Math.max(0, Math.ceil((timeout.endsAtMs - serverNow()) / 1000)) : 0;
+    banner.classList.toggle('visible', remaining > 0);
+    if (remaining === 0) return;
+
+    banner.dataset.side = timeout.side;
+    banner.querySelector('.timeout-team').textContent = teams[timeout.side].name.toUpperCase();
+    banner.querySelector('.timeout-countdown').textContent = `${Math.floor(remaining / 60)}:${String(remaining % 60).padStart(2, '0')}`;
+}
+
 function updateElementIfChanged(id, value) {
     const element = document.getElementById(id);
     if (!element) return;
@@ -149,7 +194,11 @@ function updateShotClock(shotClock) {
     const element = document.getElementById('shot-clock');
     if (!element) return;
     
-    const newValue = shotClock || '--';
+    const newValue = (shotClock || '').trim();
+    const hidden = newValue === '' || newValue === '-';
+    element.classList.toggle('hidden', hidden);
+    element.classList.toggle('low', !hidden && parseFloat(newValue) < 5);
+    if (hidden) return;
     
     if (element.textContent !== newValue) {
         element.textContent = newValue;
@@ -169,14 +218,9 @@ function updateGameState(gameState) {
     }
 }
 
-// Initial fetch to get current state
-fetch('/api/game')
-    .then(response => response.json())
-    .then(data => {
-        if (data) {
-            updateScoreboard(data);
-        }
-    })
-    .catch(error => {
-        console.error('Error fetching initial state:', error);
-    });
+function updateStale(stale) {
+    const scoreboard = document.querySelector('.scoreboard');
+    if (!scoreboard) return;
+    
+    scoreboard.classList.toggle('stale', !!stale);
+}
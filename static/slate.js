@@ -0,0 +1,128 @@
+// Full-screen slate for the videoboard: a "starting soon" card before the game, the score
+// at halftime, the result with the line score (`line_score`, points per period) after the
+// final buzzer. While the game is played the page stays empty. Same live data as the overlay.
+// The match details (`match_info`) head the card; before the game it also shows the scheduled
+// tip-off and the countdown to it.
+
+// Whether the clock has run since the page last saw a game start, so a 0-0 first quarter
+// stopped after the tip-off isn't taken for the pregame
+let clockHasRun = false;
+
+function showTeams() {
+    for (const side of ['home', 'away']) {
+        document.getElementById(`${side}-team-name`).textContent = teams[side].name;
+        const logo = document.getElementById(`${side}-logo`);
+        logo.src = teams[side].logo;
+        logo.style.display = teams[side].logo ? 'block' : 'none';
+    }
+}
+showTeams();
+
+// "pregame", "halftime", "final", or "live" while the game is played
+function slatePhase(data) {
+    const period = (data.period_name || '').trim();
+    if (period === 'Halftime') {
+        return 'halftime';
+    }
+    if (period === 'Final') {
+        return 'final';
+    }
+    if (period === '-' || period === '') {
+        clockHasRun = false;
+        return 'pregame';
+    }
+    clockHasRun = clockHasRun || data.game_state === 'running';
+    const scoreless = !(parseInt(data.home_score) > 0) && !(parseInt(data.away_score) > 0);
+    return period === '1 Quarter' && scoreless && !clockHasRun ? 'pregame' : 'live';
+}
+
+function slateTitle(phase) {
+    if (phase === 'pregame') {
+        return 'STARTING SOON';
+    }
+    return localPeriodName(phase === 'final' ? 'Final' : 'Halftime').toUpperCase();
+}
+
+// One column per finished period and the total; `-` where the console showed a blank
+function renderLineScore(data) {
+    const table = document.getElementById('line-score');
+    const periods = data.line_score || [];
+    table.replaceChildren();
+    table.hidden = periods.length === 0;
+    if (table.hidden) return;
+
+    const row = cells => {
+        const tr = document.createElement('tr');
+        for (const [text, heading] of cells) {
+            const cell = document.createElement(heading ? 'th' : 'td');
+            cell.textContent = text;
+            tr.append(cell);
+        }
+        table.append(tr);
+    };
+    const value = points => (points === null || points === undefined ? '-' : String(points));
+    row([['', true], ...periods.map(period => [shortPeriod(period.period), true]), ['T', true]]);
+    for (const side of ['home', 'away']) {
+        row([[teams[side].abbrev, true], ...periods.map(period => [value(period[side]), false]), [String(data[`${side}_score`]).trim(), false]]);
+    }
+}
+
+// `Liga Betclic · Round 12 · Pavilhão do Funchal`
+function showMatch(info) {
+    const line = [info.competition, info.round, info.venue].filter(part => part).join(' \u00B7 ');
+    const element = document.getElementById('slate-match');
+    element.textContent = line;
+    element.hidden = !line;
+}
+
+// The countdown comes from `GET /api/match`, worked out on the server's clock, once a second
+// while the pregame card is up
+const COUNTDOWN_REFRESH_MS = 1000;
+let countdownTimer = null;
+
+function refreshCountdown() {
+    fetch(`${apiBase}/api/match${tokenQuery}`, { cache: 'no-store' })
+        .then(response => (response.ok ? response.json() : null))
+        .then(match => {
+            if (match) {
+                document.getElementById('tip-off-countdown').textContent = match.countdown ? match.countdown.text : '';
+            }
+        })
+        .catch(() => {});
+}
+
+// `2026-03-14T20:30:00+01:00` is shown as `TIP-OFF 20:30`, the venue's local time as entered
+function showTipOff(phase, info) {
+    const tipOff = phase === 'pregame' ? info.tipOff : null;
+    document.getElementById('slate-tip-off').hidden = !tipOff;
+    if (!tipOff) {
+        clearInterval(countdownTimer);
+        countdownTimer = null;
+        document.getElementById('tip-off-countdown').textContent = '';
+        return;
+    }
+    document.getElementById('tip-off-time').textContent = `TIP-OFF ${tipOff.slice(11, 16)}`;
+    if (countdownTimer === null) {
+        refreshCountdown();
+        countdownTimer = setInterval(refreshCountdown, COUNTDOWN_REFRESH_MS);
+    }
+}
+
+function updateSlate(data) {
+    if (updateTeams(data.teams)) {
+        showTeams();
+    }
+    const phase = slatePhase(data);
+    const slate = document.getElementById('slate');
+    slate.dataset.phase = phase;
+    slate.classList.toggle('stale', !!data.stale);
+    document.getElementById('slate-title').textContent = phase === 'live' ? '' : slateTitle(phase);
+    document.getElementById('home-score').textContent = String(data.home_score).trim();
+    document.getElementById('away-score').textContent = String(data.away_score).trim();
+    renderLineScore(data);
+    const info = data.match_info || {};
+    showMatch(info);
+    showTipOff(phase, info);
+}
+
+connectLiveState(updateSlate);
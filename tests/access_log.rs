@@ -0,0 +1,73 @@
+//! The access log's counts and timings: every answered request is counted, and the durations
+//! of the recent ones are summed up as p50, p95 and max in `/healthz` and `GET /metrics`.
+
+mod common;
+
+use common::{wait_until, Harness};
+
+const ADMIN: (&str, &str) = ("Authorization", "Bearer secret");
+
+/// The value of the `name` sample (with its labels) in a Prometheus text page.
+fn sample(page: &str, name: &str) -> Option<f64> {
+    page.lines().find_map(|line| line.strip_prefix(name)?.strip_prefix(' ')?.parse().ok())
+}
+
+fn metrics(server: &Harness) -> String {
+    let response = server.get("/metrics");
+    assert_eq!(response.status, 200);
+    assert_eq!(response.header("content-type"), Some("text/plain; version=0.0.4; charset=utf-8"));
+    assert_eq!(response.header("cache-control"), Some("no-store"));
+    response.text()
+}
+
+#[test]
+fn every_request_is_counted_and_timed() {
+    let server = Harness::start("");
+    // Logged once answered, so not this one itself
+    let first = metrics(&server);
+    assert!(first.contains("# TYPE scoreboard_web_requests_total counter\n") && first.contains("# TYPE scoreboard_web_request_duration_ms summary\n"));
+    assert_eq!(sample(&first, "scoreboard_web_requests_total"), Some(0.0));
+    assert_eq!(sample(&first, "scoreboard_web_request_duration_ms_count"), Some(0.0));
+    assert!(!first.contains("quantile"), "no durations yet");
+
+    let statuses: Vec<u16> = [
+        server.get("/api/state"),
+        server.get("/api/state"),
+        server.get("/api/teams"),
+        server.get("/api/nothing"),
+        server.request("DELETE", "/api/state", &[ADMIN], b""),
+        server.request("POST", "/api/control/score", &[], b""),
+    ]
+    .iter()
+    .map(|response| response.status)
+    .collect();
+    assert_eq!(statuses, [200, 200, 200, 404, 405, 401]);
+
+    // These six and the first page of metrics, whatever their status
+    let made = 7.0;
+    wait_until("the requests to be logged", || sample(&metrics(&server), "scoreboard_web_request_duration_ms_count").is_some_and(|count| count >= made));
+    let page = metrics(&server);
+    assert!(sample(&page, "scoreboard_web_requests_total").is_some_and(|count| count >= made), "{}", page);
+    let quantile = |q: &str| sample(&page, &format!("scoreboard_web_request_duration_ms{{quantile=\"{}\"}}", q)).unwrap();
+    let (p50, p95, max) = (quantile("0.5"), quantile("0.95"), quantile("1"));
+    assert!(0.0 <= p50 && p50 <= p95 && p95 <= max, "{}", page);
+
+    // The same window in /healthz
+    let health = server.get("/healthz").json();
+    let durations = &health["requestDurations"];
+    assert!(durations["count"].as_f64().is_some_and(|count| count >= made), "{}", health);
+    assert!(health["webRequests"].as_f64().is_some_and(|count| count >= made), "{}", health);
+    assert!(durations["p50Ms"].as_f64().unwrap() <= durations["p95Ms"].as_f64().unwrap());
+    assert!(durations["p95Ms"].as_f64().unwrap() <= durations["maxMs"].as_f64().unwrap());
+}
+
+#[test]
+fn metrics_need_the_token_when_reads_are_locked() {
+    let server = Harness::start("--lock-read-api");
+    assert_eq!(server.get("/metrics").status, 401);
+    assert_eq!(server.request("GET", "/metrics", &[("Authorization", "Bearer wrong")], b"").status, 403);
+    assert_eq!(server.get("/metrics?token=secret").status, 200);
+    assert_eq!(server.request("GET", "/metrics", &[ADMIN], b"").status, 200);
+    assert_eq!(server.get("/healthz").status, 200, "monitoring needs no token");
+    assert_eq!(server.request("POST", "/metrics", &[ADMIN], b"").status, 405);
+}
@@ -0,0 +1,72 @@
+//! The `/admin` control panel and the endpoints behind it: a whole game run by hand with no
+//! console connected, over `POST /api/control/*` and `/ws/control`.
+
+mod common;
+
+use common::{Harness, WebSocket, WsMessage};
+use serde_json::Value;
+
+const ADMIN: (&str, &str) = ("Authorization", "Bearer secret");
+
+fn control(server: &Harness, action: &str, body: &str) -> Value {
+    let response = server.request("POST", &format!("/api/control/{}", action), &[ADMIN, ("Content-Type", "application/json")], body.as_bytes());
+    assert_eq!(response.status, 200, "{} {}: {}", action, body, response.text());
+    response.json()["state"].clone()
+}
+
+#[test]
+fn the_panel_needs_the_admin_token() {
+    let server = Harness::start("");
+    assert_eq!(server.get("/admin").status, 401);
+    let page = server.request("GET", "/admin", &[ADMIN], b"");
+    assert_eq!(page.status, 200);
+    assert!(page.text().contains("<title>Scoreboard Control</title>"));
+    assert_eq!(server.request("POST", "/api/control/score", &[("Content-Type", "application/json")], br#"{"side": "home", "points": 2}"#).status, 401);
+}
+
+#[test]
+fn a_game_is_run_by_hand_without_a_console() {
+    let server = Harness::start("");
+    control(&server, "clock", r#"{"clock": "10:00", "running": true}"#);
+    control(&server, "period", "{}");
+    control(&server, "score", r#"{"side": "home", "points": 2}"#);
+    control(&server, "score", r#"{"side": "away", "points": 3}"#);
+    control(&server, "score", r#"{"side": "home", "points": 1}"#);
+    control(&server, "foul", r#"{"side": "away"}"#);
+    control(&server, "timeout", r#"{"side": "home"}"#);
+    control(&server, "possession", "{}");
+    control(&server, "possession", "{}");
+    control(&server, "period", "{}");
+    let state = control(&server, "clock", r#"{"clock": "07:30", "running": false}"#);
+
+    assert_eq!((state["homeScore"].as_u64(), state["awayScore"].as_u64()), (Some(3), Some(3)));
+    assert_eq!((state["awayFouls"].as_u64(), state["homeTimeouts"].as_u64()), (Some(1), Some(1)));
+    assert_eq!((state["possession"].as_str(), state["periodName"].as_str()), (Some("away"), Some("2 Quarter")));
+    assert_eq!((state["clock"].as_str(), state["gameState"].as_str()), (Some("07:30"), Some("paused")));
+    assert_eq!(server.shared.status().source, None, "no console involved");
+
+    // A mistake is taken back, and what can't be is refused
+    let state = control(&server, "score", r#"{"side": "home", "points": -1}"#);
+    assert_eq!(state["homeScore"], 2);
+    let refused = server.request("POST", "/api/control/foul", &[ADMIN, ("Content-Type", "application/json")], br#"{"side": "home", "delta": -1}"#);
+    assert_eq!(refused.status, 400);
+    assert!(refused.text().contains("homeFouls cannot go below 0"), "{}", refused.text());
+}
+
+#[test]
+fn commands_over_the_control_socket_come_back_as_pushed_states() {
+    let server = Harness::start("");
+    assert_eq!(WebSocket::open(server.web, "/ws/control", &[]).status, 401);
+
+    let mut panel = WebSocket::open(server.web, "/ws/control", &[ADMIN]);
+    assert_eq!(panel.status, 101);
+    panel.next_text();
+    panel.send_text(r#"{"cmd": "addPoints", "side": "away", "points": 3}"#);
+    assert_eq!(panel.next_text(), serde_json::json!({ "ok": true }));
+    assert_eq!(panel.next_text()["away_score"], "  3");
+
+    panel.send_text(r#"{"cmd": "addPoints", "side": "away"}"#);
+    assert!(panel.next_text()["error"].as_str().is_some_and(|error| error.contains("points")));
+    panel.send_text("not a command");
+    assert!(matches!(panel.next(), WsMessage::Text(text) if text.contains("error")), "the socket stays open");
+}
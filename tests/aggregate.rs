@@ -0,0 +1,220 @@
+//! Season totals over a directory of recorded games with `aggregate`.
+
+mod common;
+
+use common::{fresh_dir, run_ok, Run};
+use scoreboard_rust::{GameState, ProtocolFrame};
+use serde_json::json;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A running game at tip-off between `home` and `away`.
+fn tip_off(home: &str, away: &str) -> GameState {
+    let mut state = GameState::default();
+    state.game_state = "running".to_string();
+    state.period_name = "1 Quarter".to_string();
+    state.time = "10:00".to_string();
+    state.home_score = "0".to_string();
+    state.away_score = "0".to_string();
+    state.home_fouls = "0".to_string();
+    state.away_fouls = "0".to_string();
+    state.teams.home.abbrev = home.to_string();
+    state.teams.away.abbrev = away.to_string();
+    state
+}
+
+/// The states of a game, each one an edit of the one before.
+fn game(home: &str, away: &str, edits: &[&dyn Fn(&mut GameState)]) -> Vec<GameState> {
+    let mut states = vec![tip_off(home, away)];
+    for edit in edits {
+        let mut state = states.last().unwrap().clone();
+        edit(&mut state);
+        states.push(state);
+    }
+    states
+}
+
+/// A game file as `--games-dir` records it.
+fn write_archive(path: &Path, started_at_ms: u64, states: &[GameState]) {
+    let mut lines = vec![json!({"kind": "game", "id": 1, "startedAtMs": started_at_ms, "teams": states[0].teams}).to_string()];
+    for (i, state) in states.iter().enumerate() {
+        let record = json!({"kind": "snapshot", "timestampMs": started_at_ms + 60_000 * i as u64, "version": i + 1, "state": state});
+        lines.push(record.to_string());
+    }
+    fs::write(path, lines.join("\n") + "\n").unwrap();
+}
+
+fn scores(home: u32, away: u32) -> impl Fn(&mut GameState) {
+    move |state| {
+        state.home_score = home.to_string();
+        state.away_score = away.to_string();
+    }
+}
+
+fn fouls(home: u32, away: u32) -> impl Fn(&mut GameState) {
+    move |state| {
+        state.home_fouls = home.to_string();
+        state.away_fouls = away.to_string();
+    }
+}
+
+fn period(name: &'static str, time: &'static str) -> impl Fn(&mut GameState) {
+    move |state| {
+        state.period_name = name.to_string();
+        state.time = time.to_string();
+    }
+}
+
+/// Three games over three days, a corrupt file, a game that stops at half time and files
+/// that aren't recordings.
+fn season_dir() -> PathBuf {
+    let dir = fresh_dir("aggregate_season");
+
+    // CAB beat SLB 80-74; the fouls of two periods add up
+    let first = game(
+        "CAB",
+        "SLB",
+        &[&scores(40, 38), &fouls(3, 4), &fouls(0, 0), &period("2 Quarter", "10:00"), &fouls(2, 1), &scores(80, 74), &period("Final", "00:00")],
+    );
+    write_archive(&dir.join("game-000001.jsonl"), 1_773_500_000_000, &first);
+
+    // POR beat SLB 78-75 in overtime; the operator never set Final, the clock ran out
+    let second = game(
+        "SLB",
+        "POR",
+        &[&scores(70, 70), &period("4 Quarter", "00:00"), &period("Overtime", "05:00"), &scores(75, 78), &period("Overtime", "00:00")],
+    );
+    write_archive(&dir.join("game-000002.jsonl"), 1_773_586_400_000, &second);
+
+    // CAB won 60-58 at POR, from a --log-jsonl log written with --timezone +01:00
+    let third = game("POR", "CAB", &[&scores(58, 60), &period("Final", "00:00")]);
+    let log: Vec<String> = third
+        .iter()
+        .enumerate()
+        .map(|(i, state)| json!({"kind": "state", "ts": format!("2026-03-16T20:0{}:00.000+01:00", i), "version": i, "state": state}).to_string())
+        .collect();
+    fs::write(dir.join("scoreboard-2026-03-16.jsonl"), log.join("\n")).unwrap();
+
+    let mut corrupt = fs::read_to_string(dir.join("game-000001.jsonl")).unwrap();
+    corrupt.truncate(corrupt.len() / 2);
+    fs::write(dir.join("game-000003.jsonl"), corrupt).unwrap();
+    write_archive(&dir.join("game-000004.jsonl"), 1_773_700_000_000, &game("CAB", "POR", &[&scores(30, 31), &period("2 Quarter", "00:00")]));
+    fs::write(dir.join("stats.sqlite"), "SQLite format 3\0").unwrap();
+    fs::write(dir.join("notes.txt"), "not a game").unwrap();
+    dir
+}
+
+fn aggregate(dir: &Path, options: &[&str]) -> Run {
+    run_ok(&[&["aggregate", dir.to_str().unwrap()], options].concat())
+}
+
+#[test]
+fn three_games_make_a_season() {
+    let run = aggregate(&season_dir(), &[]);
+    let lines: Vec<&str> = run.stderr.lines().filter_map(|line| line.split_once("aggregate: ")).map(|(_, game)| game).filter(|line| !line.starts_with("Skipped")).collect();
+    assert_eq!(
+        lines,
+        [
+            "2026-03-14 14:53 UTC  CAB 80 - 74 SLB",
+            "2026-03-15 14:53 UTC  SLB 75 - 78 POR  (OT)",
+            "2026-03-16 19:00 UTC  POR 58 - 60 CAB",
+        ],
+        "{}",
+        run.stderr
+    );
+    let season = run.json();
+    assert_eq!((season["games"][0]["homeFouls"].as_u64(), season["games"][0]["awayFouls"].as_u64()), (Some(5), Some(5)));
+
+    let teams: Vec<(&str, u64, u64, u64, u64, u64)> = season["teams"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|team| {
+            let count = |name: &str| team[name].as_u64().unwrap();
+            (team["team"].as_str().unwrap(), count("wins"), count("losses"), count("pointsFor"), count("pointsAgainst"), count("overtimes"))
+        })
+        .collect();
+    assert_eq!(teams, [("CAB", 2, 0, 140, 132, 0), ("POR", 1, 1, 136, 135, 1), ("SLB", 0, 2, 149, 158, 1)]);
+    assert_eq!(season["teams"][0]["averageFouls"], 2.5);
+
+    let skipped: Vec<(&str, &str)> = season["skipped"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|skipped| (skipped["file"].as_str().unwrap(), skipped["reason"].as_str().unwrap()))
+        .collect();
+    assert_eq!(skipped.len(), 3, "{:?}", skipped);
+    assert!(skipped[0].0 == "game-000003.jsonl" && skipped[0].1.starts_with("corrupt: line "), "{:?}", skipped);
+    assert!(skipped[1].0 == "game-000004.jsonl" && skipped[1].1.starts_with("incomplete game"), "{:?}", skipped);
+    assert_eq!(skipped[2].0, "stats.sqlite");
+    assert!(run.stderr.contains("Skipped stats.sqlite: "), "{}", run.stderr);
+}
+
+#[test]
+fn the_season_as_csv() {
+    let csv = aggregate(&season_dir(), &["--csv"]).text();
+    let lines: Vec<&str> = csv.split("\r\n").collect();
+    assert_eq!(lines[0], "team,games,wins,losses,points_for,points_against,average_fouls,overtimes");
+    assert_eq!(lines[1], "CAB,2,2,0,140,132,2.5,0");
+    assert_eq!(lines[4], "", "a blank line between the tables");
+    assert!(lines[5].starts_with("started_utc,home,away,"), "{}", lines[5]);
+    assert_eq!(lines[7], "2026-03-15 14:53:20.000,SLB,POR,75,78,0,0,0,yes,game-000002.jsonl");
+}
+
+/// A console frame as a `data_log/` capture line.
+fn capture_line(message: &str) -> String {
+    let bytes = ProtocolFrame::new(0x7F, 0x20, message.as_bytes().to_vec()).to_bytes();
+    let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+    format!("[{}]", hex.join(", "))
+}
+
+#[test]
+fn games_are_rebuilt_from_captures() {
+    let dir = fresh_dir("aggregate_capture");
+    // Message 18: clock on (` `) or off (`"`), mm:ss, time-outs, period; message 30: scores
+    let frames = ["18 01000    4 ", "305 50 48", "18\"00000    4 "];
+    let capture: Vec<String> = frames.iter().map(|frame| capture_line(frame)).collect();
+    fs::write(dir.join("session-1773515100.250.log"), capture.join("\n")).unwrap();
+
+    let season = aggregate(&dir, &[]).json();
+    assert_eq!(season["skipped"], json!([]));
+    assert_eq!(season["games"].as_array().unwrap().len(), 1);
+    let game = &season["games"][0];
+    assert_eq!((game["home"].as_str(), game["homeScore"].as_u64(), game["awayScore"].as_u64()), (Some("HOME"), Some(50), Some(48)));
+    assert_eq!(game["startedAtMs"], 1_773_515_100_250u64);
+    assert_eq!(season["teams"][0]["team"], "HOME");
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn games_are_rebuilt_from_a_games_database() {
+    use scoreboard_rust::{game_archive::GameStore, game_db::GameDb};
+
+    let dir = fresh_dir("aggregate_db");
+    let mut db = GameDb::open(&dir.join("games.sqlite")).unwrap();
+    let mut record = |id: u64, started_at_ms: u64, states: &[GameState]| {
+        db.start_game(id, started_at_ms, &states[0].teams).unwrap();
+        for (i, state) in states.iter().enumerate() {
+            db.snapshot(id, started_at_ms + 60_000 * i as u64, i as u64 + 1, state, &[]).unwrap();
+        }
+    };
+    record(1, 1_773_500_000_000, &game("CAB", "SLB", &[&scores(40, 38), &fouls(3, 4), &scores(80, 74), &period("Final", "00:00")]));
+    record(2, 1_773_586_400_000, &game("SLB", "POR", &[&scores(30, 31), &period("2 Quarter", "00:00")]));
+    record(3, 1_773_672_800_000, &game("POR", "CAB", &[&scores(58, 60), &period("Final", "00:00")]));
+
+    let season = aggregate(&dir, &[]).json();
+    let games: Vec<(&str, &str, u64, u64)> = season["games"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|game| (game["home"].as_str().unwrap(), game["away"].as_str().unwrap(), game["homeScore"].as_u64().unwrap(), game["awayScore"].as_u64().unwrap()))
+        .collect();
+    assert_eq!(games, [("CAB", "SLB", 80, 74), ("POR", "CAB", 58, 60)]);
+    assert!(season["games"].as_array().unwrap().iter().all(|game| game["file"] == "games.sqlite"));
+    assert_eq!(season["teams"][0], json!({"team": "CAB", "games": 2, "wins": 2, "losses": 0, "pointsFor": 140, "pointsAgainst": 132, "averageFouls": 1.5, "overtimes": 0}));
+    assert_eq!(season["skipped"].as_array().unwrap().len(), 1);
+    assert_eq!(season["skipped"][0]["file"], "games.sqlite");
+    assert!(season["skipped"][0]["reason"].as_str().unwrap().starts_with("game 2: incomplete game"), "{}", season["skipped"]);
+}
@@ -0,0 +1,39 @@
+//! `GET /api/state` against golden files: before the first frame and during a game, with what
+//! changes from run to run (the age, the time of the last update and of the clock) blanked.
+
+mod common;
+
+use common::{clock, fouls, scores, Harness};
+use serde_json::Value;
+
+/// The body of `GET /api/state` on `server`, pretty-printed, with `ageMs`, `lastUpdate` and
+/// `state.clockValidAtMs` set to `"..."` when present, after checking its headers.
+fn api_state(server: &Harness) -> String {
+    let response = server.get("/api/state");
+    assert_eq!(response.status, 200);
+    assert_eq!(response.header("content-type"), Some("application/json"));
+    assert_eq!(response.header("cache-control"), Some("no-cache"), "revalidated by ETag");
+    let mut body = response.json();
+    for field in ["ageMs", "lastUpdate"] {
+        if !body[field].is_null() {
+            body[field] = Value::from("...");
+        }
+    }
+    if !body["state"]["clockValidAtMs"].is_null() {
+        body["state"]["clockValidAtMs"] = Value::from("...");
+    }
+    serde_json::to_string_pretty(&body).unwrap() + "\n"
+}
+
+#[test]
+fn before_the_first_frame_the_state_is_null() {
+    let server = Harness::start("");
+    assert_eq!(api_state(&server), include_str!("golden/api_state_empty.json"));
+}
+
+#[test]
+fn a_game_in_progress_matches_the_golden_file() {
+    let mut server = Harness::start("");
+    server.play(&[scores(61, 58), clock(true), fouls(3, 5)]);
+    assert_eq!(api_state(&server), include_str!("golden/api_state.json"));
+}
@@ -0,0 +1,60 @@
+//! The `AUTH <token>` handshake of `--auth-token` on the console input.
+
+mod common;
+
+use common::{scores, Harness};
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Everything the server sends until it closes the connection.
+fn reply(stream: &mut TcpStream) -> String {
+    stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    let mut reply = Vec::new();
+    let _ = stream.read_to_end(&mut reply);
+    String::from_utf8_lossy(&reply).into_owned()
+}
+
+#[test]
+fn frames_after_the_right_token_are_applied() {
+    let server = Harness::start("--auth-token s3cret");
+    let mut stream = TcpStream::connect(server.ingest).unwrap();
+    stream.write_all(b"AUTH s3cret\r\n").unwrap();
+    stream.write_all(&scores(12, 9).to_bytes()).unwrap();
+    server.wait_for_version(1);
+    assert_eq!(server.shared.current().home_score.trim(), "12");
+}
+
+#[test]
+fn a_wrong_token_is_answered_and_closed() {
+    let server = Harness::start("--auth-token s3cret");
+    let mut stream = TcpStream::connect(server.ingest).unwrap();
+    stream.write_all(b"AUTH guess\n").unwrap();
+    stream.write_all(&scores(12, 9).to_bytes()).unwrap();
+    assert_eq!(reply(&mut stream), "ERR invalid token\n");
+    assert_eq!(server.shared.version(), 0, "nothing applied");
+    assert_eq!(server.shared.status().metrics.auth_failures, 1);
+}
+
+#[test]
+fn a_handshake_trickled_in_runs_out_of_time() {
+    let server = Harness::start("--auth-token s3cret");
+    let mut stream = TcpStream::connect(server.ingest).unwrap();
+    let started = Instant::now();
+    let mut writer = stream.try_clone().unwrap();
+    // One byte every half second keeps each read short but takes 7 seconds in all
+    thread::spawn(move || {
+        for byte in b"AUTH s3cret-s3\n" {
+            if writer.write_all(&[*byte]).is_err() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(500));
+        }
+    });
+    assert_eq!(reply(&mut stream), "ERR no AUTH received in time\n");
+    let elapsed = started.elapsed();
+    assert!((Duration::from_secs(4)..Duration::from_secs(6)).contains(&elapsed), "{:?}", elapsed);
+}
@@ -0,0 +1,67 @@
+//! `--subscribers` clients that stop reading: dropped after `--backpressure-timeout`, without
+//! holding up the ones that read.
+
+mod common;
+
+use common::{wait_until, Harness};
+use std::{
+    io::{BufRead, BufReader, Read},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn a_subscriber_that_never_reads_is_dropped_and_the_others_keep_up() {
+    let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let server = Harness::start(&format!("--subscribers {} --backpressure-timeout 1", address));
+
+    let stalled = TcpStream::connect(address).unwrap();
+    let reader = TcpStream::connect(address).unwrap();
+    reader.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    let last_read = Arc::new(AtomicU32::new(0));
+    let reading = {
+        let last_read = Arc::clone(&last_read);
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines().map_while(Result::ok) {
+                let state: serde_json::Value = serde_json::from_str(&line).unwrap();
+                let home = state["home_score"].as_str().unwrap().trim().parse().unwrap_or(0);
+                last_read.store(home, Ordering::Relaxed);
+            }
+        })
+    };
+
+    // Updates until the stalled client's socket buffers and queue are full and it is dropped
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let mut home = 0;
+    while server.shared.status().metrics.peers_dropped_backpressure == 0 {
+        assert!(Instant::now() < deadline, "the stalled subscriber was never dropped");
+        home = (home + 1) % 1000;
+        server
+            .shared
+            .set_manual(|state| {
+                state.home_score = format!("{:>3}", home);
+                state.match_info.officials = vec!["x".repeat(48); 5];
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+        if home % 100 == 0 {
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    // The reading subscriber has every state up to the last one
+    wait_until("the reader to catch up", || last_read.load(Ordering::Relaxed) == home);
+    assert!(!reading.is_finished(), "still connected");
+    assert_eq!(server.shared.status().metrics.peers_dropped_backpressure, 1);
+
+    // The stalled one was shut down: what it has buffered ends
+    stalled.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    let mut rest = Vec::new();
+    let _ = (&stalled).take(64 << 20).read_to_end(&mut rest);
+    assert!(!rest.is_empty());
+}
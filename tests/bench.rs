@@ -0,0 +1,45 @@
+//! `scoreboard-rust bench` loading a server in the test process.
+
+mod common;
+
+use common::{run, Harness};
+use std::net::TcpListener;
+
+#[test]
+fn every_frame_is_acknowledged() {
+    // Acknowledging every frame
+    let server = Harness::start("--ack");
+    let bench = run(&["bench", "--target", &server.target(), "--connections", "2", "--rate", "500", "--frames", "50", "--seed", "7", "--json"], b"");
+    assert_eq!(bench.code, Some(0), "{}", bench.stderr);
+    let report = bench.json();
+    let counts = ["connected", "sent", "acked", "refused", "unanswered", "unexpected"].map(|count| report[count].as_u64().unwrap());
+    assert_eq!(counts, [2, 100, 100, 0, 0, 0], "{}", report);
+    assert_eq!(report["errors"], serde_json::json!([]));
+    let latency = |at: &str| report["latency"][at].as_f64().unwrap();
+    assert!(latency("p50Ms") <= latency("p99Ms") && latency("p99Ms") <= latency("maxMs"));
+}
+
+#[test]
+fn nobody_listening_fails() {
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let target = format!("127.0.0.1:{}", port);
+    let bench = run(&["bench", "--target", &target, "--connections", "2", "--frames", "1", "--json"], b"");
+    assert_eq!(bench.code, Some(1));
+    let report = bench.json();
+    assert_eq!((report["connected"].as_u64(), report["sent"].as_u64()), (Some(0), Some(0)));
+    assert_eq!(report["errors"].as_array().unwrap().len(), 2);
+    assert!(report["latency"].is_null());
+}
+
+#[test]
+fn options() {
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let target = format!("127.0.0.1:{}", port);
+    let bench = run(&["bench", "--target", &target, "--connections", "8", "--rate", "25", "--duration", "30s", "--frames", "1", "--json"], b"");
+    assert_eq!(bench.json()["connections"], 8);
+    for args in ["--connections 0", "--rate fast", "--target localhost"] {
+        let mut line = vec!["bench"];
+        line.extend(args.split_whitespace());
+        assert_eq!(run(&line, b"").code, Some(2), "{:?}", args);
+    }
+}
@@ -0,0 +1,69 @@
+//! Bonus badges and the possession arrow: `homeBonus`, `awayBonus` and `possession` in the
+//! state as each rule set derives them, pushed live, and the elements every overlay fills in.
+
+mod common;
+
+use common::{fouls, Harness, WebSocket};
+use scoreboard_rust::{Bonus, RuleSet};
+
+const ADMIN: (&str, &str) = ("Authorization", "Bearer secret");
+
+#[test]
+fn each_rule_set_has_its_own_bonus_thresholds() {
+    assert_eq!(RuleSet::Fiba.bonus(Some(3)), None);
+    assert_eq!(RuleSet::Fiba.bonus(Some(4)), Some(Bonus::Single));
+    assert_eq!(RuleSet::Nba.bonus(Some(4)), None);
+    assert_eq!(RuleSet::Nba.bonus(Some(5)), Some(Bonus::Single));
+    assert_eq!(RuleSet::Ncaa.bonus(Some(6)), None);
+    assert_eq!(RuleSet::Ncaa.bonus(Some(7)), Some(Bonus::Single));
+    assert_eq!(RuleSet::Ncaa.bonus(Some(10)), Some(Bonus::Double));
+    assert_eq!(RuleSet::Fiba.bonus(None), None, "a blank count is no bonus");
+}
+
+#[test]
+fn the_state_carries_the_bonus_and_possession() {
+    let mut server = Harness::start("--rules ncaa");
+    let (_, empty) = server.get_json("/api/state");
+    assert_eq!(empty["state"], serde_json::Value::Null);
+
+    // The console counts up to 9, so the double bonus only comes with fouls set by hand
+    server.play(&[fouls(7, 6)]);
+    let (status, body) = server.get_json("/api/state");
+    assert_eq!(status, 200);
+    let state = &body["state"];
+    // The home team's fouls give away free throws
+    assert_eq!(state["homeBonus"], serde_json::Value::Null);
+    assert_eq!(state["awayBonus"], "bonus");
+    assert_eq!(state["possession"], serde_json::Value::Null, "unknown until the operator sets it");
+
+    let mut client = WebSocket::open(server.web, "/ws", &[]);
+    assert_eq!(client.next_text()["away_bonus"], "bonus");
+    let set = server.request("POST", "/api/control/possession", &[ADMIN, ("Content-Type", "application/json")], br#"{"side": "away"}"#);
+    assert_eq!(set.status, 200, "{}", set.text());
+    assert_eq!(client.next_text()["possession"], "away", "pushed live");
+    assert_eq!(server.get_json("/api/state").1["state"]["possession"], "away");
+
+    // Fouls going back below the threshold (a new half) clear the badge
+    server.play(&[fouls(0, 2)]);
+    let pushed = client.next_text();
+    assert_eq!((pushed.get("home_bonus"), pushed.get("away_bonus")), (None, None));
+    let state = server.get_json("/api/state").1["state"].clone();
+    assert_eq!((state["homeBonus"].clone(), state["awayBonus"].clone()), (serde_json::Value::Null, serde_json::Value::Null));
+}
+
+#[test]
+fn every_overlay_has_the_badges_and_arrows() {
+    let server = Harness::start("");
+    let pages = ["/?theme=classic", "/?theme=minimal", "/?theme=broadcast", "/bug"];
+    for path in pages {
+        let page = server.get(path);
+        assert_eq!(page.status, 200, "{}", path);
+        let page = page.text();
+        for id in ["home-bonus", "away-bonus", "home-possession", "away-possession"] {
+            assert!(page.contains(&format!("id=\"{}\"", id)), "{} has no #{}", path, id);
+        }
+    }
+    let script = server.get("/common.js").text();
+    assert!(script.contains("function updateBonusAndPossession"));
+    assert!(script.contains("doubleBonus: '+BONUS'"));
+}
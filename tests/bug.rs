@@ -0,0 +1,57 @@
+//! `/bug`: the compact score bug next to the full overlay, both fed by the same live data and
+//! the team abbreviations set by the operator.
+
+mod common;
+
+use common::{clock, scores, Harness, WebSocket};
+
+#[test]
+fn the_bug_is_its_own_template_on_the_shared_script() {
+    let server = Harness::start("");
+    let bug = server.get("/bug");
+    assert_eq!((bug.status, bug.header("content-type")), (200, Some("text/html; charset=utf-8")));
+    let page = bug.text();
+    assert!(page.contains("<title>Basketball Score Bug</title>") && page.contains("href=\"bug.css\""));
+    for id in ["home-abbrev", "away-abbrev", "home-score", "away-score", "period", "time"] {
+        assert!(page.contains(&format!("id=\"{}\"", id)), "no #{}", id);
+    }
+    assert!(!page.contains("home-fouls"), "fouls and time-outs are for the full layout");
+
+    // One data layer: both pages load common.js, neither opens a connection of its own
+    assert!(server.get("/").text().contains("<script src=\"common.js\"></script>") && page.contains("<script src=\"common.js\"></script>"));
+    let common = server.get("/common.js").text();
+    assert!(common.contains("function connectLiveState(") && common.contains("new WebSocket("));
+    for (script, render) in [("/bug.js", "connectLiveState(updateBug);"), ("/overlay.js", "connectLiveState(updateScoreboard);")] {
+        let script = server.get(script).text();
+        assert!(script.contains(render) && !script.contains("new WebSocket(") && !script.contains("fetch(`${apiBase}/api/game"), "{}", render);
+    }
+}
+
+#[test]
+fn both_pages_get_the_abbreviations_and_scores_of_a_game() {
+    let mut server = Harness::start("");
+    let mut overlay = WebSocket::open(server.web, "/ws", &[]);
+    let mut bug = WebSocket::open(server.web, "/ws", &[]);
+    overlay.next_text();
+    bug.next_text();
+
+    let teams = r##"{"home": {"name": "CAB Madeira", "abbrev": "CAB", "color": "#1E90FF"}, "away": {"name": "Benfica", "abbrev": "SLB"}}"##;
+    let put = server.request("PUT", "/api/teams", &[("Authorization", "Bearer secret"), ("Content-Type", "application/json")], teams.as_bytes());
+    assert_eq!(put.status, 200, "{}", put.text());
+    server.play(&[clock(true), scores(45, 38)]);
+    for page in [&mut overlay, &mut bug] {
+        let state = loop {
+            let state = page.next_text();
+            if state["home_score"] == " 45" {
+                break state;
+            }
+        };
+        assert_eq!((state["teams"]["home"]["abbrev"].as_str(), state["teams"]["away"]["abbrev"].as_str()), (Some("CAB"), Some("SLB")));
+        assert_eq!(state["time"], "08:12");
+    }
+
+    let long = r#"{"home": {"name": "CAB Madeira", "abbrev": "MADEIRA"}, "away": {"name": "Benfica"}}"#;
+    let refused = server.request("PUT", "/api/teams", &[("Authorization", "Bearer secret"), ("Content-Type", "application/json")], long.as_bytes());
+    assert_eq!(refused.status, 400);
+    assert!(refused.text().contains("home.abbrev must be at most 3"), "{}", refused.text());
+}
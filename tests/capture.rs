@@ -0,0 +1,171 @@
+//! Session captures rolled into parts, gzipped when done and deleted past the retention
+//! limits, and the compressed ones replayed as they were written.
+
+mod common;
+
+use common::{fresh_dir, run_ok, scores, Harness};
+use scoreboard_rust::{
+    capture::{self, CaptureLog},
+    CaptureConfig,
+};
+use std::{
+    fs::{self, File},
+    io::Read,
+    net::TcpListener,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+fn names(paths: &[PathBuf]) -> Vec<String> {
+    paths.iter().map(|path| path.file_name().unwrap().to_string_lossy().to_string()).collect()
+}
+
+/// Wait until `dir` holds `expected` captures whose names pass `done`, as the background clean-up leaves them.
+fn wait_for_captures(dir: &Path, expected: usize, done: impl Fn(&[String]) -> bool) -> Vec<PathBuf> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let captures = capture::captures(dir).unwrap();
+        if captures.len() == expected && done(&names(&captures)) {
+            return captures;
+        }
+        assert!(Instant::now() < deadline, "captures never settled: {:?}", names(&captures));
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// The reads a capture holds, one per line (`[01, 7F, 47]`).
+fn capture_lines(part: &Path) -> Vec<Vec<u8>> {
+    capture::read_capture(part)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let bytes = line.trim().trim_start_matches('[').trim_end_matches(']');
+            bytes.split(',').map(str::trim).filter(|byte| !byte.is_empty()).map(|byte| u8::from_str_radix(byte, 16).unwrap()).collect()
+        })
+        .collect()
+}
+
+/// Every byte `scoreboard-rust replay` sends of a capture, at full speed.
+fn replayed(part: &Path) -> Vec<u8> {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let receiver = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut bytes = Vec::new();
+        stream.read_to_end(&mut bytes).unwrap();
+        bytes
+    });
+    run_ok(&["replay", part.to_str().unwrap(), "--target", &address, "--rate", "1000", "--speed", "100x"]);
+    receiver.join().unwrap()
+}
+
+#[test]
+fn a_capture_rolls_to_a_new_part_past_its_size_limit() {
+    let dir = fresh_dir("capture_roll");
+    let config = CaptureConfig { dir: dir.clone(), max_size: Some(200), ..CaptureConfig::default() };
+    let mut log = CaptureLog::start(&config).unwrap();
+    let first = log.path();
+    let reads: Vec<Vec<u8>> = (0..6).map(|home| scores(home, 0).to_bytes()).collect();
+    for read in &reads {
+        log.write(read).unwrap();
+    }
+    let last = log.path();
+    drop(log);
+
+    let parts = capture::captures(&dir).unwrap();
+    assert!(parts.len() > 1, "{:?}", names(&parts));
+    assert_eq!((parts.first(), parts.last()), (Some(&first), Some(&last)));
+    assert!(names(&parts)[1].ends_with(".2.log"), "{:?}", names(&parts));
+    for part in &parts {
+        assert!(fs::metadata(part).unwrap().len() <= 200, "{} is over the limit", part.display());
+    }
+    // The parts in order hold every read, one per line
+    let lines: Vec<Vec<u8>> = parts
+        .iter()
+        .flat_map(|part| capture_lines(part))
+        .collect();
+    assert_eq!(lines, reads);
+
+    // A line longer than the limit still goes in a part of its own
+    let config = CaptureConfig { dir: fresh_dir("capture_roll_long"), max_size: Some(10), ..CaptureConfig::default() };
+    let mut log = CaptureLog::start(&config).unwrap();
+    log.write(&scores(1, 2).to_bytes()).unwrap();
+    log.write(&scores(3, 4).to_bytes()).unwrap();
+    drop(log);
+    assert_eq!(capture::captures(&config.dir).unwrap().len(), 2);
+}
+
+#[test]
+fn a_compressed_capture_replays_as_it_was_written() {
+    let dir = fresh_dir("capture_compress");
+    let config = CaptureConfig { dir: dir.clone(), max_size: Some(200), compress: true, ..CaptureConfig::default() };
+    let mut log = CaptureLog::start(&config).unwrap();
+    let reads: Vec<Vec<u8>> = (0..6).map(|home| scores(home, home).to_bytes()).collect();
+    for read in &reads {
+        log.write(read).unwrap();
+    }
+    let parts = capture::captures(&dir).unwrap().len();
+    drop(log);
+
+    // Done parts are gzipped in the background, the last one once the session ends
+    let compressed = wait_for_captures(&dir, parts, |names| names.iter().all(|name| name.ends_with(".log.gz")));
+    let replayed_bytes: Vec<u8> = compressed.iter().flat_map(|part| replayed(part)).collect();
+    assert_eq!(replayed_bytes, reads.concat());
+
+    // The same text as the capture before compression
+    let plain = dir.join("session-1.1.log");
+    let text = "[01, 7F, 47]\n[02, 03]\n";
+    fs::write(&plain, text).unwrap();
+    let gz = capture::compress_capture(&plain).unwrap();
+    assert!(!plain.exists());
+    assert_eq!(capture::read_capture(&gz).unwrap(), text);
+}
+
+#[test]
+fn retention_deletes_the_oldest_captures() {
+    let dir = fresh_dir("capture_retention");
+    // Oldest first; millis aren't padded, so 5 comes before 40
+    let captures = ["session-100.5.log", "session-100.40.log.gz", "session-200.1.log", "session-200.1.2.log.gz", "session-300.0.log"];
+    for name in captures.iter().rev() {
+        fs::write(dir.join(name), "[01]\n").unwrap();
+    }
+    fs::write(dir.join("notes.txt"), "not a capture").unwrap();
+
+    let keep = CaptureConfig { dir: dir.clone(), keep: Some(3), ..CaptureConfig::default() };
+    let deleted = capture::prune(&keep).unwrap();
+    assert_eq!(names(&deleted), ["session-100.5.log", "session-100.40.log.gz"]);
+    assert_eq!(names(&capture::captures(&dir).unwrap()), &captures[2..]);
+    assert!(dir.join("notes.txt").exists(), "only captures are deleted");
+
+    // Last written 10 days ago
+    let old = SystemTime::now() - Duration::from_secs(10 * 24 * 3600);
+    File::options().write(true).open(dir.join("session-300.0.log")).unwrap().set_modified(old).unwrap();
+    let max_age = CaptureConfig { dir: dir.clone(), max_age: Some(Duration::from_secs(7 * 24 * 3600)), ..CaptureConfig::default() };
+    assert_eq!(names(&capture::prune(&max_age).unwrap()), ["session-300.0.log"]);
+
+    // A session being written is never deleted, however many are kept
+    let mut log = CaptureLog::start(&CaptureConfig { keep: Some(1), ..keep.clone() }).unwrap();
+    log.write(&scores(1, 0).to_bytes()).unwrap();
+    let remaining = wait_for_captures(&dir, 1, |_| true);
+    assert_eq!(remaining, [log.path()]);
+}
+
+#[test]
+fn a_new_game_starts_a_new_part_of_the_session_capture() {
+    let dir = fresh_dir("capture_per_game");
+    let mut config = Harness::config(&format!("--capture-dir {} --capture-per-game", dir.display()));
+    assert!(config.capture.per_game);
+    // Captured, unlike the harness's `dev` default
+    config.log_to_file = true;
+    let mut server = Harness::with_config(config);
+
+    server.play(&[scores(50, 48)]);
+    server.shared.new_game();
+    server.play(&[scores(0, 2)]);
+    server.stop();
+    let parts = capture::captures(&dir).unwrap();
+    assert_eq!(parts.len(), 2, "{:?}", names(&parts));
+    let games: Vec<Vec<u8>> = parts.iter().map(|part| capture_lines(part).concat()).collect();
+    assert_eq!(games, [scores(50, 48).to_bytes(), scores(0, 2).to_bytes()]);
+}
@@ -0,0 +1,258 @@
+//! Command lines parsed into the server configuration, and the help and subcommands around it.
+
+mod common;
+
+use common::{run, run_ok};
+use scoreboard_rust::{InputMode, LogFormat, RuleSet, ServerConfig};
+use std::{path::PathBuf, time::Duration};
+
+fn parse(args: &str) -> Result<ServerConfig, String> {
+    let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+    ServerConfig::from_args(&args)
+}
+
+#[test]
+fn defaults_listen_on_tcp_and_log_sessions() {
+    let config = parse("").unwrap();
+    assert_eq!(config.input, InputMode::Tcp);
+    assert_eq!(config.tcp_address, "0.0.0.0:4001");
+    assert_eq!(config.web_address, "0.0.0.0:3030".parse().unwrap());
+    assert!(config.log_to_file);
+    assert!(!config.lenient);
+    assert_eq!(config.rules, RuleSet::Fiba);
+}
+
+#[test]
+fn addresses_rules_and_flags() {
+    let config = parse(
+        "dev --listen 127.0.0.1:8888 --web-address 127.0.0.1:8080 --lenient --ack --rules nba --timeout-length 90 --poll-interval 250",
+    )
+    .unwrap();
+    assert!(!config.log_to_file);
+    assert_eq!(config.tcp_address, "127.0.0.1:8888");
+    assert_eq!(config.web_address, "127.0.0.1:8080".parse().unwrap());
+    assert!(config.lenient && config.ack);
+    assert_eq!(config.rules, RuleSet::Nba);
+    assert_eq!(config.timeout_length, Some(Duration::from_secs(90)));
+    assert_eq!(config.poll_interval, Duration::from_millis(250));
+}
+
+#[test]
+fn aliases_and_attached_values() {
+    let config = parse("--tcp-addr=127.0.0.1:8888 --web-addr 127.0.0.1:8080 --ruleset NBA --format json --lenient").unwrap();
+    assert_eq!(config.tcp_address, "127.0.0.1:8888");
+    assert_eq!(config.web_address, "127.0.0.1:8080".parse().unwrap());
+    assert_eq!(config.rules, RuleSet::Nba);
+    assert_eq!(config.log_format, LogFormat::Json);
+    assert!(config.lenient);
+}
+
+#[test]
+fn repeated_options_take_the_last_value_or_add_up() {
+    let config = parse("--rules nba --rules ncaa --forward 127.0.0.1:9001 --forward 127.0.0.1:9002").unwrap();
+    assert_eq!(config.rules, RuleSet::Ncaa);
+    assert_eq!(config.forward_to, ["127.0.0.1:9001".parse().unwrap(), "127.0.0.1:9002".parse().unwrap()]);
+}
+
+#[test]
+fn other_inputs() {
+    assert_eq!(parse("stdin").unwrap().input, InputMode::Stdin);
+    assert_eq!(
+        parse("--follow capture.bin").unwrap().input,
+        InputMode::Follow(PathBuf::from("capture.bin"))
+    );
+}
+
+#[test]
+fn named_pipe_input() {
+    for args in ["--listen pipe:", r"--listen pipe:a\b", "--listen pipe:a/b"] {
+        let error = parse(args).expect_err(args);
+        assert!(error.contains("Invalid pipe name"), "{}: {}", args, error);
+    }
+    #[cfg(windows)]
+    {
+        assert_eq!(parse("--listen pipe:scoreboard").unwrap().input, InputMode::Pipe("scoreboard".to_string()));
+        assert!(parse("--listen pipe:scoreboard --ack").unwrap_err().contains("--ack only applies to TCP input"));
+    }
+    #[cfg(not(windows))]
+    assert!(parse("--listen pipe:scoreboard").unwrap_err().contains("only available on Windows"));
+}
+
+#[test]
+fn invalid_combinations_are_rejected() {
+    for (args, message) in [
+        ("stdin --follow capture.bin", "--follow conflicts with stdin"),
+        ("--listen 0.0.0.0:4001 stdin", "stdin conflicts with --listen"),
+        ("stdin --ack", "--ack only applies to TCP input"),
+        ("--follow capture.bin --auth-token secret", "--auth-token only applies to TCP input"),
+        ("--echo --ack", "--echo and --ack can't be combined"),
+        ("--tls-cert cert.pem", "--tls-cert and --tls-key must be given together"),
+        ("--no-http", "--no-http requires --tls-cert and --tls-key"),
+        ("--mqtt-qos 1", "--mqtt-qos requires --mqtt-broker"),
+    ] {
+        let error = parse(args).expect_err(args);
+        assert!(error.contains(message), "{}: {}", args, error);
+    }
+}
+
+#[test]
+fn invalid_values_name_the_option() {
+    for (args, option) in [
+        ("--rules wnba", "--rules"),
+        ("--web-address localhost", "--web-address"),
+        ("--poll-interval 10", "--poll-interval"),
+        ("--listen 4001", "4001"),
+        ("--bogus", "--bogus"),
+        ("--theme", "--theme"),
+        ("--mqtt-broker broker --mqtt-qos 2", "--mqtt-qos"),
+        ("--hook Goal=echo", "--hook"),
+        ("--hook Horn=", "--hook"),
+    ] {
+        let error = parse(args).expect_err(args);
+        assert!(error.contains(option), "{}: {}", args, error);
+    }
+}
+
+fn parse_with_env(vars: &[(&str, &str)], args: &str) -> Result<ServerConfig, String> {
+    let vars = vars.iter().map(|(name, value)| (name.to_string(), value.to_string()));
+    let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+    ServerConfig::from_env_and_args(vars, &args)
+}
+
+#[test]
+fn environment_sets_options() {
+    let config = parse_with_env(
+        &[
+            ("SCOREBOARD_TCP_ADDR", "127.0.0.1:5001"),
+            ("SCOREBOARD_WEB_ADDR", "127.0.0.1:8080"),
+            ("SCOREBOARD_AUTH_TOKEN", "s3cret"),
+            ("SCOREBOARD_ADMIN_TOKEN", "admin"),
+            ("SCOREBOARD_DEV", "true"),
+            ("SCOREBOARD_NO_MDNS", "1"),
+            ("SCOREBOARD_LENIENT", "off"),
+            ("SCOREBOARD_WEB_WORKERS", "2"),
+            ("SCOREBOARD_FORWARD", "127.0.0.1:9001, 127.0.0.1:9002"),
+            ("PATH", "/usr/bin"),
+        ],
+        "",
+    )
+    .unwrap();
+    assert_eq!(config.tcp_address, "127.0.0.1:5001");
+    assert_eq!(config.web_address, "127.0.0.1:8080".parse().unwrap());
+    assert_eq!(config.auth_token.as_deref(), Some("s3cret"));
+    assert_eq!(config.admin_token.as_deref(), Some("admin"));
+    assert!(!config.log_to_file && !config.mdns && !config.lenient);
+    assert_eq!(config.web_workers, 2);
+    assert_eq!(config.forward_to, ["127.0.0.1:9001".parse().unwrap(), "127.0.0.1:9002".parse().unwrap()]);
+}
+
+#[test]
+fn command_line_wins_over_environment() {
+    let vars = [
+        ("SCOREBOARD_WEB_ADDRESS", "127.0.0.1:8080"),
+        ("SCOREBOARD_RULES", "nba"),
+        ("SCOREBOARD_STDIN", "yes"),
+        ("SCOREBOARD_CORS_ORIGIN", "http://a.example"),
+    ];
+    let config = parse_with_env(&vars, "--web-address 127.0.0.1:9090 --follow feed.bin --cors-origin http://b.example").unwrap();
+    assert_eq!(config.web_address, "127.0.0.1:9090".parse().unwrap());
+    assert_eq!(config.rules, RuleSet::Nba);
+    assert_eq!(config.input, InputMode::Follow(PathBuf::from("feed.bin")));
+    assert_eq!(config.cors_allowed_origins, ["http://b.example"]);
+    // Unset options keep their defaults
+    assert_eq!(config.tcp_address, "0.0.0.0:4001");
+}
+
+#[test]
+fn environment_errors_name_the_variable() {
+    let error = |vars: &[(&str, &str)]| parse_with_env(vars, "").unwrap_err();
+    assert!(error(&[("SCOREBOARD_WEB_WORKERS", "many")]).contains("SCOREBOARD_WEB_WORKERS"));
+    assert!(error(&[("SCOREBOARD_ACK", "maybe")]).contains("Invalid SCOREBOARD_ACK"));
+    assert!(error(&[("SCOREBOARD_WEB_ADRESS", "127.0.0.1:8080")]).contains("Unknown setting SCOREBOARD_WEB_ADRESS"));
+    let size = error(&[("SCOREBOARD_LOG_JSONL", "log.jsonl"), ("SCOREBOARD_LOG_JSONL_MAX_SIZE", "lots")]);
+    assert!(size.contains("(set by SCOREBOARD_LOG_JSONL_MAX_SIZE)"), "{}", size);
+    // Token values are never part of a message
+    let token = error(&[("SCOREBOARD_AUTH_TOKEN", "s3cret"), ("SCOREBOARD_STDIN", "1")]);
+    assert!(!token.contains("s3cret") && token.contains("SCOREBOARD_AUTH_TOKEN"), "{}", token);
+}
+
+#[test]
+fn nested_variables_set_the_options_of_a_section() {
+    let config = parse_with_env(
+        &[
+            ("SCOREBOARD_STATSD__ADDRESS", "metrics.local:8126"),
+            ("SCOREBOARD_STATSD__PREFIX", "arena"),
+            ("SCOREBOARD_STATSD__INTERVAL", "5"),
+            ("SCOREBOARD_STATSD__SAMPLE_RATE", "0.5"),
+        ],
+        "",
+    )
+    .unwrap();
+    let statsd = config.statsd.unwrap();
+    assert_eq!((statsd.host.as_str(), statsd.port, statsd.prefix.as_str()), ("metrics.local", 8126, "arena"));
+    assert_eq!((statsd.interval, statsd.sample_rate), (Duration::from_secs(5), 0.5));
+
+    let error = parse_with_env(&[("SCOREBOARD_STATSD__SAMPLE_RATE", "most")], "").unwrap_err();
+    assert!(error.contains("(set by SCOREBOARD_STATSD__SAMPLE_RATE)"), "{}", error);
+    for unknown in ["SCOREBOARD_MQTT__HOSTNAME", "SCOREBOARD_WEB__ADDRESS", "SCOREBOARD_COURT__1__LISTEN"] {
+        let error = parse_with_env(&[(unknown, "x")], "").unwrap_err();
+        assert!(error.contains(unknown), "{}: {}", unknown, error);
+    }
+}
+
+#[test]
+fn config_file_is_overridden_by_environment_and_command_line() {
+    let file = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("cli-config.conf");
+    std::fs::write(&file, "# venue defaults\nweb-address 127.0.0.1:8080\nrules ncaa\ntheme minimal\nlenient\nforward 127.0.0.1:9001\n").unwrap();
+    let args = format!("--config {} --theme broadcast", file.display());
+    let config = parse_with_env(&[("SCOREBOARD_RULES", "nba")], &args).unwrap();
+    assert_eq!(config.web_address, "127.0.0.1:8080".parse().unwrap());
+    assert_eq!(config.rules, RuleSet::Nba);
+    assert_eq!(config.theme, "broadcast");
+    assert!(config.lenient);
+    assert_eq!(config.forward_to, ["127.0.0.1:9001".parse().unwrap()]);
+
+    std::fs::write(&file, "web-adress 127.0.0.1:8080\n").unwrap();
+    let error = parse_with_env(&[], &args).unwrap_err();
+    assert!(error.contains("Unknown option web-adress") && error.contains("line 1"), "{}", error);
+}
+
+#[test]
+fn help_lists_the_commands_and_options() {
+    let help = run_ok(&["--help"]).text();
+    for expected in ["Usage: scoreboard-rust [OPTIONS]", "serve", "examples", "send", "simulate", "replay", "--tcp-addr <HOST:PORT>", "--lenient", "--format"] {
+        assert!(help.contains(expected), "{}: {}", expected, help);
+    }
+    let send = run_ok(&["send", "--help"]).text();
+    assert!(send.contains("Usage: scoreboard-rust send [OPTIONS]") && send.contains("--hex <BYTES>"), "{}", send);
+    assert!(run_ok(&["--version"]).text().starts_with("scoreboard-rust "));
+}
+
+#[test]
+fn usage_errors_exit_with_status_2() {
+    for (args, message) in [
+        (&["--bogus"][..], "unexpected argument '--bogus'"),
+        (&["--rules", "wnba"], "invalid value 'wnba' for '--rules <fiba|nba|ncaa>'"),
+        (&["dev", "send"], "invalid value 'send'"),
+        (&["serve", "stdin", "--follow", "feed.bin"], "--follow conflicts with stdin"),
+        (&["send", "--home", "2", "--hex", "01"], "cannot be used with"),
+        (&["frobnicate"], "frobnicate"),
+    ] {
+        let failed = run(args, b"");
+        assert_eq!(failed.code, Some(2), "{:?}: {}", args, failed.stderr);
+        assert!(failed.stderr.contains(message), "{:?}: {}", args, failed.stderr);
+    }
+}
+
+#[test]
+fn examples_print_the_demo_frames() {
+    let text = run_ok(&["examples"]).text();
+    assert!(text.contains("bytes: 01 7F 02 47 33 30 35 20 38 30 20 37 34 03 24"), "{}", text);
+    assert!(text.contains("state: 80 - 74, fouls 4-5, 4 Quarter 45.3 (paused), time-outs 3-2"), "{}", text);
+
+    let examples = run_ok(&["examples", "--json"]).json();
+    let examples = examples.as_array().unwrap();
+    assert_eq!(examples.len(), 4);
+    let last = &examples[3]["state"];
+    assert_eq!((last["home_score"].as_str(), last["time"].as_str()), (Some(" 80"), Some("45.3")));
+}
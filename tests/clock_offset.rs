@@ -0,0 +1,94 @@
+//! The overlay pages' clock offset: estimated NTP-style by [`estimate_clock_offset`], which
+//! `GET /api/time?samples=` answers with, so the pages and the server share one formula.
+
+mod common;
+
+use common::Harness;
+use scoreboard_rust::{estimate_clock_offset, ClockSample};
+use serde_json::Value;
+
+fn sample(sent_ms: f64, received_ms: f64, server_time_ms: f64) -> ClockSample {
+    ClockSample { sent_ms, received_ms, server_time_ms }
+}
+
+#[test]
+fn the_shortest_round_trip_is_taken_as_answered_halfway() {
+    // The server 5 s ahead, answering halfway through a 100 ms round trip
+    assert_eq!(estimate_clock_offset(&[sample(1_000.0, 1_100.0, 6_050.0)]), Some(5_000.0));
+    // And behind
+    assert_eq!(estimate_clock_offset(&[sample(10_000.0, 10_040.0, 7_020.0)]), Some(-3_000.0));
+    assert_eq!(estimate_clock_offset(&[sample(0.0, 1.0, 10.0)]), Some(9.5), "half a millisecond");
+
+    // The 20 ms round trip wins over slower ones, whichever comes first
+    let samples = [sample(0.0, 300.0, 5_290.0), sample(1_000.0, 1_020.0, 6_010.0), sample(2_000.0, 2_150.0, 7_000.0)];
+    assert_eq!(estimate_clock_offset(&samples), Some(5_000.0));
+    let mut reversed = samples;
+    reversed.reverse();
+    assert_eq!(estimate_clock_offset(&reversed), Some(5_000.0));
+    // Of equal ones, the first
+    assert_eq!(estimate_clock_offset(&[sample(0.0, 20.0, 110.0), sample(100.0, 120.0, 310.0)]), Some(100.0));
+    // A reply instantly
+    assert_eq!(estimate_clock_offset(&[sample(500.0, 500.0, 400.0), sample(0.0, 20.0, 10.0)]), Some(-100.0));
+
+    // A reply before its request: the page's clock was set back, the sample is useless
+    assert_eq!(estimate_clock_offset(&[sample(1_000.0, 900.0, 0.0), sample(0.0, 40.0, 1_020.0)]), Some(1_000.0));
+    assert_eq!(estimate_clock_offset(&[sample(1_000.0, 900.0, 0.0)]), None);
+    assert_eq!(estimate_clock_offset(&[]), None);
+}
+
+#[test]
+fn samples_are_parsed_from_the_query() {
+    assert_eq!(ClockSample::parse_list(""), Ok(vec![]));
+    assert_eq!(
+        ClockSample::parse_list("1000,1100,6050;2000.5,2010,7005"),
+        Ok(vec![sample(1_000.0, 1_100.0, 6_050.0), sample(2_000.5, 2_010.0, 7_005.0)])
+    );
+    assert_eq!(ClockSample::parse_list("1, 2, 3;"), Ok(vec![sample(1.0, 2.0, 3.0)]), "spaces and a trailing ;");
+    for bad in ["1,2", "1,2,3,4", "1,2,x", "1,,3", "1,2,NaN", "1,2,inf", ";;1;2;3"] {
+        assert!(ClockSample::parse_list(bad).is_err(), "{}", bad);
+    }
+}
+
+fn time(server: &Harness, query: &str) -> (u16, Value) {
+    let response = server.get(&format!("/api/time{}", query));
+    if response.status == 200 {
+        assert_eq!(response.header("cache-control"), Some("no-store"));
+    }
+    (response.status, response.json())
+}
+
+#[test]
+fn api_time_answers_with_the_same_estimate() {
+    let server = Harness::start("");
+    let (status, plain) = time(&server, "");
+    assert_eq!(status, 200);
+    assert!(plain["serverTimeMs"].as_u64().is_some_and(|ms| ms > 1_600_000_000_000), "{}", plain);
+    assert_eq!((plain["version"].as_u64(), &plain["offsetMs"]), (Some(0), &Value::Null));
+
+    // What the pages send: their times around the earlier requests, `,` and `;` encoded
+    for samples in ["1000,1100,6050", "0,300,5290;1000,1020,6010;2000,2150,7000", "0,1,10", "1000,900,0;0,40,1020"] {
+        let expected = estimate_clock_offset(&ClockSample::parse_list(samples).unwrap()).unwrap();
+        let encoded = samples.replace(',', "%2C").replace(';', "%3B");
+        for query in [format!("?samples={}", samples), format!("?samples={}", encoded)] {
+            let (status, body) = time(&server, &query);
+            assert_eq!((status, body["offsetMs"].as_f64()), (200, Some(expected)), "{}", query);
+        }
+    }
+    assert_eq!(time(&server, "?samples=").1["offsetMs"], Value::Null);
+    assert_eq!(time(&server, "?samples=1000,900,0").1["offsetMs"], Value::Null, "no usable sample");
+
+    // A page on this machine is near enough to the server's own clock
+    let (_, first) = time(&server, "");
+    let now = first["serverTimeMs"].as_f64().unwrap();
+    let (_, estimated) = time(&server, &format!("?samples={},{},{}", now - 1.0, now + 1.0, now));
+    assert!(estimated["offsetMs"].as_f64().is_some_and(|offset| offset.abs() < 5.0), "{}", estimated);
+
+    // The pages take the server's estimate rather than working out their own
+    let script = server.get("/common.js").text();
+    assert!(script.contains("query.set('samples'") && script.contains("clockOffsetMs = time.offsetMs"));
+    assert!(!script.contains("sentMs + ") && !script.contains("receivedMs) / 2"), "no formula of its own");
+
+    let (status, error) = time(&server, "?samples=1,2");
+    assert_eq!(status, 400);
+    assert!(error["error"].as_str().unwrap().contains("Invalid clock sample"), "{}", error);
+}
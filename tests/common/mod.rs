@@ -0,0 +1,660 @@
+//! What the integration tests share: the ingest and web servers on ports picked by the system,
+//! console frames, a bare HTTP client, Server-Sent Events reader and WebSocket client, the
+//! `scoreboard-rust` commands and scratch directories.
+
+#![allow(dead_code)]
+
+use scoreboard_rust::{
+    tcp_server,
+    web_server::{self, WebOptions, WebServer},
+    BasketballServer, ProtocolFrame, ServerConfig, SharedState,
+};
+use serde_json::Value;
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// Options that pick the input, after which the harness doesn't add its `--listen`.
+const INPUT_OPTIONS: [&str; 4] = ["stdin", "--listen", "--tcp-addr", "--follow"];
+
+/// An ingest server and a web server sharing one state, stopped through their shutdown
+/// handles when dropped.
+pub struct Harness {
+    pub ingest: SocketAddr,
+    /// The plain HTTP listener, or the HTTPS one with `--no-http`.
+    pub web: SocketAddr,
+    /// The HTTPS listener, with `--tls-cert`.
+    pub https: Option<SocketAddr>,
+    /// The UDP input, with `--udp-addr`.
+    pub udp: Option<SocketAddr>,
+    pub shared: Arc<SharedState>,
+    console: Option<TcpStream>,
+    ingest_shutdown: tcp_server::ShutdownHandle,
+    web_shutdown: web_server::ShutdownHandle,
+    servers: Vec<JoinHandle<io::Result<()>>>,
+}
+
+impl Harness {
+    /// The configuration [`start`](Self::start) runs: `dev` (no session captures), no mDNS,
+    /// the admin token `secret`, both servers on port 0 of the loopback and the files the web
+    /// server writes (teams, match, CSS, uploads) in a fresh directory, unless `args` say
+    /// otherwise.
+    pub fn config(args: &str) -> ServerConfig {
+        Self::config_args(&split(args))
+    }
+
+    /// [`config`](Self::config) with the arguments already split, for values with spaces.
+    pub fn config_args(args: &[String]) -> ServerConfig {
+        let given = |option: &str| args.iter().any(|arg| arg == option);
+        let mut line = split("dev --no-mdns --admin-token secret --web-address 127.0.0.1:0");
+        if !INPUT_OPTIONS.iter().any(|option| given(option)) {
+            line.extend(split("--listen 127.0.0.1:0"));
+        }
+        line.extend_from_slice(args);
+        let mut config = ServerConfig::from_args(&line).unwrap_or_else(|e| panic!("{:?}: {}", line, e));
+        let dir = fresh_dir(&format!("harness-{}-{}", std::process::id(), NEXT_DIR.fetch_add(1, Ordering::Relaxed)));
+        if !given("--teams-file") {
+            config.teams_file = dir.join("teams.json");
+        }
+        if !given("--match-file") {
+            config.match_file = dir.join("match.json");
+        }
+        if !given("--custom-css") {
+            config.custom_css_file = dir.join("custom.css");
+        }
+        if !given("--upload-dir") {
+            config.upload_dir = dir.join("uploads");
+        }
+        config
+    }
+
+    /// Both servers on ports picked by the system, with the extra command line `args`.
+    pub fn start(args: &str) -> Self {
+        Self::with_config(Self::config(args))
+    }
+
+    /// Both servers on the given addresses, e.g. those of a harness stopped before.
+    pub fn start_on(ingest: SocketAddr, web: SocketAddr, args: &str) -> Self {
+        let mut config = Self::config(args);
+        config.tcp_address = ingest.to_string();
+        config.web_address = web;
+        Self::with_config(config)
+    }
+
+    /// Both servers as `config` asks; see [`config`](Self::config) for a starting point.
+    pub fn with_config(config: ServerConfig) -> Self {
+        let options = WebOptions::from_config(&config);
+        let server = BasketballServer::new(config);
+        let ingest = server.bind().expect("the ingest address is free");
+        let udp = server.udp_address();
+        let shared = server.shared();
+        let ingest_shutdown = server.shutdown_handle();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let web_server = runtime.block_on(WebServer::bind(Arc::clone(&shared), options)).expect("the web address is free");
+        let https = web_server.https_addr();
+        let web = web_server.local_addr().or(https).unwrap();
+        let web_shutdown = web_server::ShutdownHandle::new();
+        let servers = vec![thread::spawn(move || server.run()), {
+            let shutdown = web_shutdown.clone();
+            thread::spawn(move || runtime.block_on(web_server.run(shutdown)))
+        }];
+        Self { ingest, web, https, udp, shared, console: None, ingest_shutdown, web_shutdown, servers }
+    }
+
+    /// The ingest address as the `--to` of the client commands take it.
+    pub fn target(&self) -> String {
+        self.ingest.to_string()
+    }
+
+    /// Write `frames` back to back, in one write, on the console connection.
+    pub fn send_frames(&mut self, frames: &[ProtocolFrame]) {
+        let bytes: Vec<u8> = frames.iter().flat_map(ProtocolFrame::to_bytes).collect();
+        self.send_bytes(&bytes);
+    }
+
+    /// Write `bytes` on the console connection, opened by the first write.
+    pub fn send_bytes(&mut self, bytes: &[u8]) {
+        self.console().write_all(bytes).unwrap();
+    }
+
+    /// The console connection, opened on first use and kept until the harness stops.
+    pub fn console(&mut self) -> &mut TcpStream {
+        let ingest = self.ingest;
+        self.console.get_or_insert_with(|| TcpStream::connect(ingest).unwrap())
+    }
+
+    /// Write `frames` and wait for each to be published.
+    pub fn play(&mut self, frames: &[ProtocolFrame]) {
+        for frame in frames {
+            let version = self.shared.version();
+            self.send_frames(std::slice::from_ref(frame));
+            self.wait_for_version(version + 1);
+        }
+    }
+
+    /// Wait for the state to reach `version`.
+    pub fn wait_for_version(&self, version: u64) {
+        wait_until(&format!("version {}", version), || self.shared.version() >= version);
+    }
+
+    /// `method path` on the web server, with the extra `headers` and `body`.
+    pub fn request(&self, method: &str, path: &str, headers: &[(&str, &str)], body: &[u8]) -> Response {
+        http(self.web, method, path, headers, body)
+    }
+
+    /// `GET path` on the web server.
+    pub fn get(&self, path: &str) -> Response {
+        self.request("GET", path, &[], b"")
+    }
+
+    /// `GET path` on the web server: the status code and the body as JSON.
+    pub fn get_json(&self, path: &str) -> (u16, Value) {
+        let response = self.get(path);
+        (response.status, response.json())
+    }
+
+    /// Stop both servers and wait for them; returns the ingest and web addresses, free again.
+    pub fn stop(mut self) -> (SocketAddr, SocketAddr) {
+        self.shutdown();
+        for server in self.servers.drain(..) {
+            server.join().unwrap().unwrap();
+        }
+        (self.ingest, self.web)
+    }
+
+    fn shutdown(&mut self) {
+        self.console = None;
+        self.ingest_shutdown.shutdown();
+        self.web_shutdown.shutdown();
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// A web server's answer.
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    /// Names in lower case.
+    pub headers: Vec<(String, String)>,
+    /// Without the chunked transfer encoding.
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// The value of the header `name` (any case).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        let name = name.to_ascii_lowercase();
+        self.headers.iter().find(|(header, _)| *header == name).map(|(_, value)| value.as_str())
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or_else(|e| panic!("not JSON ({}): {}", e, self.text()))
+    }
+}
+
+/// `method path` on the web server at `address`, with the extra `headers` and `body`, on a
+/// connection of its own.
+pub fn http(address: impl ToSocketAddrs, method: &str, path: &str, headers: &[(&str, &str)], body: &[u8]) -> Response {
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    let mut request = format!("{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n", method, path);
+    for (name, value) in headers {
+        request += &format!("{}: {}\r\n", name, value);
+    }
+    if !body.is_empty() {
+        request += &format!("Content-Length: {}\r\n", body.len());
+    }
+    stream.write_all(&[request.as_bytes(), b"\r\n", body].concat()).unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    parse_response(&response)
+}
+
+/// A whole HTTP/1.1 response as read from its connection.
+pub fn parse_response(response: &[u8]) -> Response {
+    let end = response.windows(4).position(|window| window == b"\r\n\r\n").expect("a complete head");
+    let head = String::from_utf8_lossy(&response[..end]);
+    let mut lines = head.lines();
+    let status = lines.next().unwrap().split_whitespace().nth(1).unwrap().parse().unwrap();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+    let mut body = response[end + 4..].to_vec();
+    if headers.iter().any(|(name, value)| name == "transfer-encoding" && value.contains("chunked")) {
+        body = dechunk(&body);
+    }
+    Response { status, headers, body }
+}
+
+fn dechunk(mut chunked: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    while let Some(line_end) = chunked.windows(2).position(|window| window == b"\r\n") {
+        let size = usize::from_str_radix(String::from_utf8_lossy(&chunked[..line_end]).trim(), 16).unwrap();
+        if size == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunked[line_end + 2..line_end + 2 + size]);
+        chunked = &chunked[line_end + 2 + size + 2..];
+    }
+    body
+}
+
+/// One Server-Sent Event: its `event` and `id` fields, its `data` lines joined, and the
+/// comments (`: keepalive`) that came with it.
+#[derive(Debug, Default)]
+pub struct ServerEvent {
+    pub event: Option<String>,
+    pub id: Option<String>,
+    pub data: String,
+    pub comments: Vec<String>,
+}
+
+impl ServerEvent {
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.data).unwrap_or_else(|e| panic!("not JSON ({}): {:?}", e, self))
+    }
+}
+
+/// A Server-Sent Events stream read off a connection of its own, event by event.
+pub struct EventStream {
+    reader: BufReader<TcpStream>,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    chunked: bool,
+    /// Body bytes read but not yet split into events.
+    body: Vec<u8>,
+}
+
+impl EventStream {
+    /// `GET path` on the web server at `address` with the extra `headers`.
+    pub fn open(address: impl ToSocketAddrs, path: &str, headers: &[(&str, &str)]) -> Self {
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\nAccept: text/event-stream\r\n", path);
+        for (name, value) in headers {
+            request += &format!("{}: {}\r\n", name, value);
+        }
+        stream.write_all(format!("{}\r\n", request).as_bytes()).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            let mut line = Vec::new();
+            assert!(reader.read_until(b'\n', &mut line).unwrap() > 0, "the connection closed in the head");
+            head.extend_from_slice(&line);
+        }
+        let head = parse_response(&head);
+        let chunked = head.header("transfer-encoding").is_some_and(|value| value.contains("chunked"));
+        Self { reader, status: head.status, headers: head.headers, chunked, body: Vec::new() }
+    }
+
+    /// The next event, failing the test if none comes within ten seconds.
+    pub fn next(&mut self) -> ServerEvent {
+        self.next_within(Duration::from_secs(10)).expect("another event")
+    }
+
+    /// The next event, `None` if none came within `timeout` or the stream ended.
+    pub fn next_within(&mut self, timeout: Duration) -> Option<ServerEvent> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(end) = self.body.windows(2).position(|window| window == b"\n\n") {
+                let block: Vec<u8> = self.body.drain(..end + 2).collect();
+                return Some(parse_event(&String::from_utf8_lossy(&block)));
+            }
+            let left = deadline.checked_duration_since(Instant::now()).filter(|left| !left.is_zero())?;
+            self.reader.get_ref().set_read_timeout(Some(left)).unwrap();
+            if !self.read_more() {
+                return None;
+            }
+        }
+    }
+
+    /// Read the next chunk of the body; `false` at its end or on a timeout.
+    fn read_more(&mut self) -> bool {
+        if !self.chunked {
+            let mut buffer = [0; 4096];
+            return match self.reader.read(&mut buffer) {
+                Ok(0) | Err(_) => false,
+                Ok(n) => {
+                    self.body.extend_from_slice(&buffer[..n]);
+                    true
+                }
+            };
+        }
+        let mut size = String::new();
+        if self.reader.read_line(&mut size).unwrap_or(0) == 0 {
+            return false;
+        }
+        let size = usize::from_str_radix(size.trim(), 16).unwrap();
+        if size == 0 {
+            return false;
+        }
+        let mut chunk = vec![0; size + 2];
+        self.reader.read_exact(&mut chunk).unwrap();
+        self.body.extend_from_slice(&chunk[..size]);
+        true
+    }
+}
+
+fn parse_event(block: &str) -> ServerEvent {
+    let mut event = ServerEvent::default();
+    let mut data = Vec::new();
+    for line in block.lines() {
+        if let Some(comment) = line.strip_prefix(':') {
+            event.comments.push(comment.trim().to_string());
+            continue;
+        }
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value).to_string();
+        match field {
+            "event" => event.event = Some(value),
+            "id" => event.id = Some(value),
+            "data" => data.push(value),
+            _ => {}
+        }
+    }
+    event.data = data.join("\n");
+    event
+}
+
+/// A WebSocket frame from the server.
+#[derive(Debug, PartialEq)]
+pub enum WsMessage {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    /// The close code, if any, and reason.
+    Close(Option<u16>, String),
+}
+
+/// A WebSocket client on a connection of its own: frames are sent masked, as from a browser,
+/// and pings are only answered by [`WebSocket::next_text`].
+pub struct WebSocket {
+    stream: TcpStream,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+}
+
+impl WebSocket {
+    /// Upgrade `GET path` on the web server at `address`; the status is 101 if it was accepted.
+    pub fn open(address: impl ToSocketAddrs, path: &str, headers: &[(&str, &str)]) -> Self {
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut request = format!(
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n",
+            path
+        );
+        for (name, value) in headers {
+            request += &format!("{}: {}\r\n", name, value);
+        }
+        stream.write_all(format!("{}\r\n", request).as_bytes()).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        // Byte by byte, so that no frame is read along with the head
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            let mut byte = [0];
+            assert_eq!(stream.read(&mut byte).unwrap(), 1, "the connection closed in the head");
+            head.push(byte[0]);
+        }
+        let head = parse_response(&head);
+        Self { stream, status: head.status, headers: head.headers }
+    }
+
+    /// Send a text frame.
+    pub fn send_text(&mut self, text: &str) {
+        self.send(0x1, text.as_bytes());
+    }
+
+    /// Send a frame with `opcode` and `payload`, masked.
+    pub fn send(&mut self, opcode: u8, payload: &[u8]) {
+        let mask = [0x37, 0xfa, 0x21, 0x3d];
+        let mut frame = vec![0x80 | opcode];
+        match payload.len() {
+            len @ 0..=125 => frame.push(0x80 | len as u8),
+            len @ 126..=0xffff => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(0x80 | 127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().zip(mask.iter().cycle()).map(|(byte, mask)| byte ^ mask));
+        // The server may have closed already; what it sent back tells the test
+        let _ = self.stream.write_all(&frame);
+    }
+
+    /// The next frame, failing the test if none comes within ten seconds.
+    pub fn next(&mut self) -> WsMessage {
+        self.next_within(Duration::from_secs(10)).expect("another WebSocket frame")
+    }
+
+    /// The next frame, `None` if none came within `timeout` or the connection ended.
+    pub fn next_within(&mut self, timeout: Duration) -> Option<WsMessage> {
+        self.stream.set_read_timeout(Some(timeout)).unwrap();
+        let mut head = [0; 2];
+        self.stream.read_exact(&mut head).ok()?;
+        self.stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        let len = match head[1] & 0x7f {
+            126 => {
+                let mut len = [0; 2];
+                self.stream.read_exact(&mut len).unwrap();
+                u16::from_be_bytes(len) as usize
+            }
+            127 => {
+                let mut len = [0; 8];
+                self.stream.read_exact(&mut len).unwrap();
+                u64::from_be_bytes(len) as usize
+            }
+            len => len as usize,
+        };
+        let mut payload = vec![0; len];
+        self.stream.read_exact(&mut payload).unwrap();
+        Some(match head[0] & 0x0f {
+            0x1 => WsMessage::Text(String::from_utf8(payload).unwrap()),
+            0x2 => WsMessage::Binary(payload),
+            0x8 if payload.len() >= 2 => {
+                WsMessage::Close(Some(u16::from_be_bytes([payload[0], payload[1]])), String::from_utf8_lossy(&payload[2..]).into_owned())
+            }
+            0x8 => WsMessage::Close(None, String::new()),
+            0x9 => WsMessage::Ping(payload),
+            0xa => WsMessage::Pong(payload),
+            opcode => panic!("unexpected opcode {:#x}", opcode),
+        })
+    }
+
+    /// The next text frame as JSON, answering the pings before it.
+    pub fn next_text(&mut self) -> Value {
+        loop {
+            match self.next() {
+                WsMessage::Text(text) => return serde_json::from_str(&text).unwrap_or_else(|e| panic!("not JSON ({}): {}", e, text)),
+                WsMessage::Ping(payload) => self.send(0xa, &payload),
+                other => panic!("expected a text frame, got {:?}", other),
+            }
+        }
+    }
+}
+
+/// Accept one request on `listener`, standing in for a webhook: answer `status` (`204 No
+/// Content`) and return its request line and body.
+pub fn receive_post(listener: &TcpListener, status: &str) -> (String, String) {
+    let (stream, _) = listener.accept().unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).unwrap();
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).unwrap();
+    reader.get_mut().write_all(format!("HTTP/1.1 {}\r\n\r\n", status).as_bytes()).unwrap();
+    (request_line.trim().to_string(), String::from_utf8(body).unwrap())
+}
+
+/// What a `scoreboard-rust` command did.
+#[derive(Debug)]
+pub struct Run {
+    /// The exit code, `None` when killed by a signal.
+    pub code: Option<i32>,
+    pub stdout: Vec<u8>,
+    /// The log.
+    pub stderr: String,
+}
+
+impl Run {
+    /// The standard output as text.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.stdout).into_owned()
+    }
+
+    /// The standard output as JSON.
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.stdout).unwrap_or_else(|e| panic!("not JSON ({}): {}\n{}", e, self.text(), self.stderr))
+    }
+
+    /// Every line of the standard output as JSON.
+    pub fn json_lines(&self) -> Vec<Value> {
+        self.text().lines().map(|line| serde_json::from_str(line).unwrap_or_else(|e| panic!("not JSON ({}): {}", e, line))).collect()
+    }
+}
+
+/// The `scoreboard-rust` binary with `args`, ready to be spawned.
+pub fn command(args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_scoreboard-rust"));
+    command.args(args).env_remove("RUST_LOG");
+    command
+}
+
+/// Run `scoreboard-rust` with `args` and `input` on its standard input.
+pub fn run(args: &[&str], input: &[u8]) -> Run {
+    let mut child = command(args).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let input = input.to_vec();
+    // Written from a thread so a command that prints a lot before reading can't block it
+    let writer = thread::spawn(move || {
+        let _ = stdin.write_all(&input);
+    });
+    let output = child.wait_with_output().unwrap();
+    writer.join().unwrap();
+    Run {
+        code: output.status.code(),
+        stdout: output.stdout,
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    }
+}
+
+/// Run `scoreboard-rust` with `args` and expect it to succeed.
+pub fn run_ok(args: &[&str]) -> Run {
+    let run = run(args, b"");
+    assert_eq!(run.code, Some(0), "{:?} failed:\n{}", args, run.stderr);
+    run
+}
+
+/// `scoreboard-rust send --to target` with the `fields` (`--home 3 --away 1`).
+pub fn send(target: &str, fields: &str) -> Run {
+    let mut args = vec!["send", "--to", target];
+    args.extend(fields.split_whitespace());
+    run_ok(&args)
+}
+
+/// `scoreboard-rust simulate` of the scenario `script` to `target`, as fast as it goes.
+pub fn simulate(target: &str, script: &str) -> Run {
+    let file = fresh_dir(&format!("scenario-{}-{}", std::process::id(), NEXT_DIR.fetch_add(1, Ordering::Relaxed))).join("game.txt");
+    fs::write(&file, script).unwrap();
+    run_ok(&["simulate", "--target", target, "--speed", "1000x", "--scenario", file.to_str().unwrap()])
+}
+
+/// Split a command line on whitespace.
+pub fn split(line: &str) -> Vec<String> {
+    line.split_whitespace().map(String::from).collect()
+}
+
+/// Wait up to five seconds for `done`, failing the test with `what` if it never is.
+pub fn wait_until(what: &str, mut done: impl FnMut() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !done() {
+        assert!(Instant::now() < deadline, "{} never came", what);
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// An empty directory `name` under the target's scratch directory.
+pub fn fresh_dir(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// The scores message, e.g. `305 45 38`.
+pub fn scores(home: u32, away: u32) -> ProtocolFrame {
+    ProtocolFrame::new(0x7F, b'G', format!("305{:>3}{:>3}", home, away).into_bytes())
+}
+
+/// The game clock at 08:12 of the 2nd period, running or stopped.
+pub fn clock(running: bool) -> ProtocolFrame {
+    clock_at("0812", running)
+}
+
+/// The game clock at `mmss` (`0812`) of the 2nd period, running or stopped.
+pub fn clock_at(mmss: &str, running: bool) -> ProtocolFrame {
+    clock_with_timeouts(mmss, running, 2, 2)
+}
+
+/// The game clock at `mmss` of the 2nd period with the time-outs left to each side.
+pub fn clock_with_timeouts(mmss: &str, running: bool, home: u8, away: u8) -> ProtocolFrame {
+    let status = if running { 0x80 } else { 0x82 };
+    let timeouts = [b'0' + home, b'0' + away];
+    ProtocolFrame::new(0x7F, b'G', [b"18".as_slice(), &[status], b"5", mmss.as_bytes(), &timeouts, b"1  2 "].concat())
+}
+
+/// The shot clock message showing `digits`: seconds (`24`), or with `tenths` seconds and
+/// tenths (`43` for 4.3), running or stopped.
+pub fn shot_clock(digits: &str, running: bool, tenths: bool) -> ProtocolFrame {
+    let status = 0x80 | if running { 0 } else { 0x02 } | if tenths { 0x10 } else { 0 };
+    ProtocolFrame::new(0x7F, b'G', [b"50".as_slice(), &[status], digits.as_bytes()].concat())
+}
+
+/// The shot clock message blanking it, as on a dead ball.
+pub fn shot_clock_blank() -> ProtocolFrame {
+    ProtocolFrame::new(0x7F, b'G', [b"50".as_slice(), &[0x8A], b"  "].concat())
+}
+
+/// The team fouls message.
+pub fn fouls(home: u32, away: u32) -> ProtocolFrame {
+    ProtocolFrame::new(0x7F, b'G', format!("315{:>2}{:>2}    ", home, away).into_bytes())
+}
@@ -0,0 +1,66 @@
+//! Static files gzip-compressed for clients that accept it, with strong ETags and `304 Not
+//! Modified`, while the API stays uncached.
+
+mod common;
+
+use common::Harness;
+use flate2::read::GzDecoder;
+use std::io::Read;
+
+fn gunzip(body: &[u8]) -> String {
+    let mut text = String::new();
+    GzDecoder::new(body).read_to_string(&mut text).unwrap();
+    text
+}
+
+#[test]
+fn gzip_bodies_decompress_to_the_original() {
+    let server = Harness::start("");
+    for (path, original) in [("/overlay.js", include_str!("../static/overlay.js")), ("/overlay.css", include_str!("../static/overlay.css"))] {
+        let plain = server.get(path);
+        assert_eq!((plain.header("content-encoding"), plain.text().as_str()), (None, original), "{}", path);
+        assert_eq!(plain.header("vary"), Some("Accept-Encoding"));
+
+        let gzipped = server.request("GET", path, &[("Accept-Encoding", "br, gzip;q=0.8")], b"");
+        assert_eq!(gzipped.header("content-encoding"), Some("gzip"), "{}", path);
+        assert!(gzipped.body.len() < original.len());
+        assert_eq!(gunzip(&gzipped.body), original);
+        assert_ne!(gzipped.header("etag"), plain.header("etag"), "one ETag per encoding");
+
+        // Refused gzip gets the identity body
+        let refused = server.request("GET", path, &[("Accept-Encoding", "gzip;q=0")], b"");
+        assert_eq!((refused.header("content-encoding"), refused.body.len()), (None, original.len()));
+    }
+
+    // Overlay pages too, their page config included
+    let page = server.request("GET", "/", &[("Accept-Encoding", "gzip")], b"");
+    assert_eq!(page.header("content-encoding"), Some("gzip"));
+    assert_eq!(gunzip(&page.body), server.get("/").text());
+}
+
+#[test]
+fn a_matching_etag_is_not_modified() {
+    let server = Harness::start("");
+    let first = server.get("/overlay.css");
+    let etag = first.header("etag").unwrap().to_string();
+    assert!(etag.starts_with('"') && !etag.starts_with("W/"), "strong: {}", etag);
+    assert_eq!(first.header("cache-control"), Some("no-cache"));
+
+    let again = server.request("GET", "/overlay.css", &[("If-None-Match", &etag)], b"");
+    assert_eq!((again.status, again.body.len(), again.header("etag")), (304, 0, Some(etag.as_str())));
+    let listed = server.request("GET", "/overlay.css", &[("If-None-Match", &format!("\"other\", {}", etag))], b"");
+    assert_eq!(listed.status, 304);
+    assert_eq!(server.request("GET", "/overlay.css", &[("If-None-Match", "\"other\"")], b"").status, 200);
+
+    let gzip_etag = server.request("GET", "/overlay.css", &[("Accept-Encoding", "gzip")], b"").header("etag").unwrap().to_string();
+    assert_eq!(server.request("GET", "/overlay.css", &[("Accept-Encoding", "gzip"), ("If-None-Match", &gzip_etag)], b"").status, 304);
+    assert_eq!(server.request("GET", "/overlay.css", &[("If-None-Match", &gzip_etag)], b"").status, 200, "not the identity body");
+}
+
+#[test]
+fn api_replies_are_not_cached() {
+    let server = Harness::start("");
+    for path in ["/api/history", "/api/match", "/healthz"] {
+        assert_eq!(server.get(path).header("cache-control"), Some("no-store"), "{}", path);
+    }
+}
@@ -0,0 +1,149 @@
+//! `scoreboard-rust convert` between wire frames, captures, hex and JSON.
+
+mod common;
+
+use common::fresh_dir;
+use scoreboard_rust::{GameState, ProtocolFrame};
+use serde_json::Value;
+use std::{fs, path::PathBuf};
+
+/// Clock with its status byte (running, 08:12), scores, fouls, and a type the server doesn't read.
+fn frames() -> Vec<u8> {
+    let clock = [b"18".as_slice(), &[0x80], b"50812221  2 "].concat();
+    [
+        ProtocolFrame::new(0x7F, b'G', clock).to_bytes(),
+        ProtocolFrame::new(0x7F, b'G', b"305 45 38".to_vec()).to_bytes(),
+        ProtocolFrame::new(0x7F, b'G', b"315 3 1    ".to_vec()).to_bytes(),
+        ProtocolFrame::new(0x7F, 0x20, b"19xxxx".to_vec()).to_bytes(),
+    ]
+    .concat()
+}
+
+/// What the command logs when done: frames, errors, bytes outside frames and records skipped.
+#[derive(Debug, Default, PartialEq)]
+struct Summary {
+    frames: u64,
+    errors: u64,
+    skipped_bytes: u64,
+    skipped_records: u64,
+}
+
+/// `convert --from from --to to` of `input` through the standard input and output.
+fn convert(input: &[u8], from: &str, to: &str, options: &[&str]) -> (Vec<u8>, Summary) {
+    let run = common::run(&[&["convert", "--from", from, "--to", to], options].concat(), input);
+    let line = run.stderr.lines().find_map(|line| line.split_once("Converted ")).unwrap_or_else(|| panic!("no summary: {}", run.stderr)).1;
+    let counts: Vec<u64> = line.split(|c: char| !c.is_ascii_digit()).filter(|word| !word.is_empty()).map(|word| word.parse().unwrap()).collect();
+    let summary = Summary { frames: counts[0], errors: counts[1], skipped_bytes: counts[2], skipped_records: counts[3] };
+    assert_eq!(run.code, Some(i32::from(summary.errors > 0)), "{}", run.stderr);
+    (run.stdout, summary)
+}
+
+fn run(input: &[u8], from: &str, to: &str) -> (Vec<u8>, Summary) {
+    convert(input, from, to, &[])
+}
+
+fn records(jsonl: &[u8]) -> Vec<Value> {
+    String::from_utf8(jsonl.to_vec()).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+}
+
+#[test]
+fn frames_survive_the_trip_through_json() {
+    let (jsonl, summary) = run(&frames(), "frames", "jsonl");
+    assert_eq!(summary, Summary { frames: 4, ..Summary::default() });
+    let lines = records(&jsonl);
+    assert_eq!(lines[1]["offset"], 21, "where the frame starts");
+    assert_eq!((lines[1]["type"].as_str(), lines[1]["message"].as_str()), (Some("30"), Some("305 45 38")));
+    assert_eq!(lines[3]["state"]["home_score"], " 45", "the state after each frame");
+    assert_eq!(lines[3]["state"]["time"], "08:12");
+
+    let (back, _) = run(&jsonl, "jsonl", "frames");
+    assert_eq!(back, frames());
+
+    let (json, _) = run(&frames(), "frames", "json");
+    assert_eq!(run(&json, "json", "frames").0, frames());
+    let (hex, _) = run(&frames(), "frames", "hex");
+    assert_eq!(run(&hex, "hex", "frames").0, frames());
+}
+
+#[test]
+fn a_capture_converts_back_to_the_same_frames() {
+    let capture = fs::read(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/validate/clean.log")).unwrap();
+    let (frames, summary) = run(&capture, "capture", "frames");
+    // The capture starts in the middle of a frame and holds a heartbeat byte
+    assert_eq!((summary.frames, summary.errors, summary.skipped_bytes), (8, 0, 11));
+
+    let (jsonl, _) = run(&capture, "capture", "jsonl");
+    assert_eq!(records(&jsonl)[0]["line"], 2, "the line the frame ends on");
+    let (lines, _) = run(&frames, "frames", "capture");
+    assert!(String::from_utf8(lines.clone()).unwrap().starts_with("[01, 7F, 02, 20, 31, 38,"));
+    assert_eq!(run(&lines, "capture", "frames").0, frames);
+}
+
+#[test]
+fn states_become_the_frames_that_set_them() {
+    let mut state = GameState::default();
+    state.home_score = " 45".to_string();
+    state.away_score = " 38".to_string();
+    state.time = "08:12".to_string();
+    state.game_state = "running".to_string();
+    state.period_name = "2 Quarter".to_string();
+    state.home_fouls = "3".to_string();
+    state.away_fouls = "1".to_string();
+    let mut later = state.clone();
+    later.away_score = " 40".to_string();
+    // As `dump` prints them, then as a `--log-jsonl` line, after a record that's neither
+    let input = format!(
+        "{}\n{{\"kind\": \"event\"}}\n{}\n",
+        serde_json::to_string(&state).unwrap(),
+        serde_json::json!({"kind": "state", "state": later})
+    );
+
+    let (frames, summary) = run(input.as_bytes(), "jsonl", "frames");
+    // Clock, scores and fouls for the first state, the scores alone for the second
+    assert_eq!((summary.frames, summary.skipped_records), (4, 1));
+    let (jsonl, _) = run(&frames, "frames", "jsonl");
+    let last = &records(&jsonl)[3]["state"];
+    assert_eq!((last["home_score"].as_str(), last["away_score"].as_str()), (Some(" 45"), Some(" 40")));
+    assert_eq!((last["time"].as_str(), last["period_name"].as_str(), last["game_state"].as_str()), (Some("08:12"), Some("2 Quarter"), Some("running")));
+    assert_eq!((last["home_fouls"].as_str(), last["away_fouls"].as_str()), (Some("3"), Some("1")));
+}
+
+#[test]
+fn bad_records_are_counted_and_the_rest_converted() {
+    let mut input = frames();
+    // Corrupt the LRC of the scores frame
+    input[21 + 14] ^= 0x01;
+    let (out, summary) = run(&input, "frames", "frames");
+    assert_eq!((summary.frames, summary.errors), (3, 1));
+    assert_eq!(out.len(), input.len() - 15);
+
+    let (lenient, summary) = convert(&input, "frames", "jsonl", &["--lenient"]);
+    assert_eq!(summary.errors, 0, "kept with their LRC");
+    assert_eq!(run(&lenient, "jsonl", "frames").1.errors, 1, "refused again without --lenient");
+
+    let (_, summary) = run(b"01 7F zz\n017F0247333035203435203338 03 25\n", "hex", "frames");
+    assert_eq!((summary.frames, summary.errors), (1, 1));
+    let (out, summary) = run(b"{\"message\": \"305 45 38\"}\nnot json\n", "jsonl", "frames");
+    assert_eq!((summary.frames, summary.errors), (1, 1));
+    assert_eq!(out, ProtocolFrame::new(0x7F, b'G', b"305 45 38".to_vec()).to_bytes());
+}
+
+#[test]
+fn files_and_arguments() {
+    let dir = fresh_dir("convert-files");
+    let (input, output) = (dir.join("game.bin"), dir.join("game.jsonl"));
+    fs::write(&input, frames()).unwrap();
+    let run = common::run(&["convert", "--from", "frames", "--to", "jsonl", input.to_str().unwrap(), output.to_str().unwrap()], b"");
+    assert_eq!((run.code, run.stdout.len()), (Some(0), 0), "{}", run.stderr);
+    assert_eq!(records(&fs::read(&output).unwrap()).len(), 4);
+    // `-` is the standard input
+    let run = common::run(&["convert", "--from", "frames", "--to", "jsonl", "-", output.to_str().unwrap()], &frames());
+    assert_eq!(run.code, Some(0));
+    assert_eq!(records(&fs::read(&output).unwrap()).len(), 4);
+
+    for args in ["--from hex", "--from pcap --to json"] {
+        let mut line = vec!["convert"];
+        line.extend(args.split_whitespace());
+        assert_eq!(common::run(&line, b"").code, Some(2), "{:?}", args);
+    }
+}
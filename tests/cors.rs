@@ -0,0 +1,54 @@
+//! `--cors-origin`: browser apps on other origins calling `/api/*`, their preflights, and the
+//! origins and paths that get no CORS headers.
+
+mod common;
+
+use common::{Harness, Response};
+
+const APP: &str = "https://stats.example.com";
+
+fn preflight(server: &Harness, origin: &str, method: &str) -> Response {
+    server.request("OPTIONS", "/api/state", &[("Origin", origin), ("Access-Control-Request-Method", method), ("Access-Control-Request-Headers", "authorization")], b"")
+}
+
+#[test]
+fn an_allowed_origin_passes_the_preflight_and_reads_the_api() {
+    let server = Harness::start(&format!("--cors-origin {}/", APP));
+    let allowed = preflight(&server, APP, "PATCH");
+    assert_eq!(allowed.status, 204);
+    assert_eq!(allowed.header("access-control-allow-origin"), Some(APP));
+    assert!(allowed.header("access-control-allow-methods").is_some_and(|methods| methods.contains("PATCH")));
+    assert!(allowed.header("access-control-allow-headers").is_some_and(|headers| headers.contains("Authorization")));
+    assert_eq!(allowed.header("access-control-max-age"), Some("600"));
+
+    let state = server.request("GET", "/api/state", &[("Origin", APP)], b"");
+    assert_eq!((state.status, state.header("access-control-allow-origin")), (200, Some(APP)));
+    assert!(state.headers.iter().any(|(name, value)| name == "vary" && value.contains("Origin")), "{:?}", state.headers);
+    // Error replies too, or the browser hides them from the app
+    let refused = server.request("PATCH", "/api/state", &[("Origin", APP), ("Content-Type", "application/json")], b"{}");
+    assert_eq!((refused.status, refused.header("access-control-allow-origin")), (401, Some(APP)));
+}
+
+#[test]
+fn other_origins_methods_and_paths_get_no_cors_headers() {
+    let server = Harness::start(&format!("--cors-origin {}", APP));
+    let other = preflight(&server, "https://evil.example.com", "GET");
+    assert_eq!((other.status, other.header("access-control-allow-origin")), (403, None));
+    assert_eq!(preflight(&server, APP, "TRACE").status, 403);
+
+    let state = server.request("GET", "/api/state", &[("Origin", "https://evil.example.com")], b"");
+    assert_eq!((state.status, state.header("access-control-allow-origin")), (200, None));
+    let page = server.request("GET", "/", &[("Origin", APP)], b"");
+    assert_eq!((page.status, page.header("access-control-allow-origin")), (200, None), "only the API");
+}
+
+#[test]
+fn any_origin_with_a_star_and_none_by_default() {
+    let server = Harness::start("--cors-origin *");
+    let state = server.request("GET", "/api/state", &[("Origin", "https://anything.example")], b"");
+    assert_eq!(state.header("access-control-allow-origin"), Some("*"));
+
+    let server = Harness::start("");
+    assert_eq!(preflight(&server, APP, "GET").status, 403);
+    assert_eq!(server.request("GET", "/api/state", &[("Origin", APP)], b"").header("access-control-allow-origin"), None);
+}
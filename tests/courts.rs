@@ -0,0 +1,259 @@
+//! Several courts from one config file: their own input, id and files, one web server for all
+//! with an index of the courts, and a court that can't start leaving the others running.
+
+mod common;
+
+use common::{fresh_dir, http, scores};
+use scoreboard_rust::{
+    web_server::{ShutdownHandle, WebOptions, WebServer},
+    Courts, RuleSet, ServerConfig, SharedState,
+};
+use std::{
+    fs,
+    io::Write,
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Write `text` as the config file of a fresh directory `name` and parse it with `args`.
+fn parse_file(name: &str, text: &str, args: &str) -> (PathBuf, Result<ServerConfig, String>) {
+    let dir = fresh_dir(name);
+    let file = dir.join("scoreboard.conf");
+    fs::write(&file, text.replace("{dir}", &dir.display().to_string())).unwrap();
+    let mut args: Vec<String> = args.split_whitespace().map(String::from).collect();
+    args.extend(["--config".to_string(), file.display().to_string()]);
+    (dir, ServerConfig::from_args(&args))
+}
+
+/// `GET path` on the web server: the status code, the `Location` header and the body.
+fn get(web: SocketAddr, path: &str) -> (u16, Option<String>, String) {
+    let response = http(web, "GET", path, &[], b"");
+    (response.status, response.header("location").map(String::from), response.text())
+}
+
+fn json(web: SocketAddr, path: &str) -> serde_json::Value {
+    http(web, "GET", path, &[], b"").json()
+}
+
+fn wait_for_home(shared: &SharedState, home: u32) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while shared.current().home_score.trim() != home.to_string() {
+        assert!(Instant::now() < deadline, "the frame giving home {} never arrived", home);
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn court_sections_of_the_config_file() {
+    let text = "\
+# shared by every court
+no-mdns
+dev
+listen 127.0.0.1:4001
+teams-file {dir}/teams.json
+log-csv {dir}/game.csv
+rules nba
+
+[[court]]
+# the first court keeps the shared listener
+xml-file {dir}/court-a.xml
+
+[[court]]
+court-id annex
+listen 127.0.0.1:4002
+rules ncaa
+";
+    let (dir, config) = parse_file("courts_config", text, "--tick-clock");
+    let config = config.unwrap();
+    assert_eq!(config.courts.len(), 2);
+    let (first, annex) = (&config.courts[0], &config.courts[1]);
+    assert_eq!((first.court_id.as_deref(), annex.court_id.as_deref()), (Some("1"), Some("annex")));
+    assert_eq!((first.tcp_address.as_str(), annex.tcp_address.as_str()), ("127.0.0.1:4001", "127.0.0.1:4002"));
+    assert_eq!((first.rules, annex.rules), (RuleSet::Nba, RuleSet::Ncaa));
+    assert!(first.tick_clock && annex.tick_clock, "the command line applies to every court");
+
+    // Files a court doesn't name are its own, beside the shared ones
+    assert_eq!(first.teams_file, dir.join("court-1/teams.json"));
+    assert_eq!(annex.log_csv.as_deref(), Some(dir.join("court-annex/game.csv").as_path()));
+    assert_eq!(first.xml_file.as_deref(), Some(dir.join("court-a.xml").as_path()));
+    assert_eq!(annex.xml_file, None);
+    assert_eq!(config.primary_court().court_id.as_deref(), Some("1"));
+
+    // An input on the command line can't pick the input of every court
+    let (_, config) = parse_file("courts_config_input", text, "--listen 127.0.0.1:5000");
+    assert_eq!(config.unwrap().courts[1].tcp_address, "127.0.0.1:4002");
+
+    let refused = [
+        ("[[court]]\nweb-address 127.0.0.1:8080\n", "web-address applies to the whole server"),
+        ("[[court]]\ncourt-id a\n[[court]]\ncourt-id a\n", "Duplicate court-id \"a\""),
+        ("[[court]]\ncourt-id court/2\n", "served at /court/<id>"),
+        ("[court]\n", "Unknown section [court]"),
+        ("[[court]]\nrules nhl\n", "line 2"),
+    ];
+    for (text, error) in refused {
+        let (_, config) = parse_file("courts_config_refused", text, "");
+        let e = config.unwrap_err();
+        assert!(e.contains(error), "{}: {}", text, e);
+    }
+}
+
+#[test]
+fn variables_set_the_options_of_one_court() {
+    let dir = fresh_dir("courts_env");
+    let file = dir.join("scoreboard.conf");
+    fs::write(&file, "no-mdns\nlisten 127.0.0.1:4001\nrules nba\n\n[[court]]\n\n[[court]]\nlisten 127.0.0.1:4002\nrules ncaa\n").unwrap();
+    let parse = |vars: &[(&str, &str)], args: &str| {
+        let vars = vars.iter().map(|(name, value)| (name.to_string(), value.to_string()));
+        let mut args: Vec<String> = args.split_whitespace().map(String::from).collect();
+        args.extend(["--config".to_string(), file.display().to_string()]);
+        ServerConfig::from_env_and_args(vars, &args)
+    };
+
+    let vars = [
+        ("SCOREBOARD_RULES", "fiba"),
+        ("SCOREBOARD_COURT__2__RULES", "nba"),
+        ("SCOREBOARD_COURT__2__LISTEN", "127.0.0.1:5002"),
+        ("SCOREBOARD_COURT__2__STATSD__ADDRESS", "metrics.local"),
+        ("SCOREBOARD_COURT__2__STATSD__PREFIX", "arena.court2"),
+    ];
+    let config = parse(&vars, "").unwrap();
+    let (first, second) = (&config.courts[0], &config.courts[1]);
+    assert_eq!((first.rules, second.rules), (RuleSet::Fiba, RuleSet::Nba), "over the section and the other variables");
+    assert_eq!((first.tcp_address.as_str(), second.tcp_address.as_str()), ("127.0.0.1:4001", "127.0.0.1:5002"));
+    assert_eq!(first.statsd, None);
+    assert_eq!(second.statsd.as_ref().map(|statsd| (statsd.host.as_str(), statsd.prefix.as_str())), Some(("metrics.local", "arena.court2")));
+    // The command line still wins, but picks no court's input
+    let config = parse(&vars, "--rules ncaa --listen 127.0.0.1:6000").unwrap();
+    assert_eq!((config.courts[1].rules, config.courts[1].tcp_address.as_str()), (RuleSet::Ncaa, "127.0.0.1:5002"));
+
+    let error = parse(&[("SCOREBOARD_COURT__3__RULES", "nba")], "").unwrap_err();
+    assert!(error.contains("SCOREBOARD_COURT__3__RULES") && error.contains("2 [[court]] sections"), "{}", error);
+    let error = parse(&[("SCOREBOARD_COURT__1__WEB_ADDRESS", "127.0.0.1:8080")], "").unwrap_err();
+    assert!(error.contains("web-address applies to the whole server") && error.contains("SCOREBOARD_COURT__1__WEB_ADDRESS"), "{}", error);
+}
+
+#[test]
+fn one_web_server_for_two_courts_one_of_which_cannot_start() {
+    // Court 2's port is taken: it fails, court 1 keeps running
+    let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+    let text = format!(
+        "no-mdns\ndev\nweb-address 127.0.0.1:0\nadmin-token secret\nteams-file {{dir}}/teams.json\n\
+         [[court]]\nlisten 127.0.0.1:0\n[[court]]\nlisten 127.0.0.1:{}\n",
+        taken.local_addr().unwrap().port()
+    );
+    let (dir, config) = parse_file("courts_web", &text, "");
+    let config = config.unwrap();
+    fs::create_dir_all(dir.join("court-2")).unwrap();
+    fs::write(dir.join("court-2/teams.json"), r#"{"home": {"name": "Galomar"}, "away": {}}"#).unwrap();
+
+    let courts = Courts::new(&config);
+    let ingest = courts.find("1").unwrap().bind().unwrap();
+    courts.start();
+    let mut options = WebOptions::from_config(&config);
+    options.courts = Some(courts.clone());
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server = runtime.block_on(WebServer::bind(courts.primary().shared(), options)).unwrap();
+    let web = server.local_addr().unwrap();
+    let shutdown = ShutdownHandle::new();
+    let running = {
+        let shutdown = shutdown.clone();
+        thread::spawn(move || runtime.block_on(server.run(shutdown)))
+    };
+
+    let mut console = TcpStream::connect(ingest).unwrap();
+    console.write_all(&scores(12, 9).to_bytes()).unwrap();
+    wait_for_home(&courts.find("1").unwrap().shared(), 12);
+
+    // Each court under its prefix, the first one at / too
+    assert_eq!(json(web, "/court/1/api/game")["home_score"].as_str().map(str::trim), Some("12"));
+    assert_eq!(json(web, "/api/game")["home_score"].as_str().map(str::trim), Some("12"));
+    assert_ne!(json(web, "/court/2/api/game")["home_score"].as_str().map(str::trim), Some("12"));
+    assert_eq!(json(web, "/court/2/api/teams")["home"]["name"], "Galomar", "court 2 loads its own teams file");
+    assert_eq!(get(web, "/court/2?theme=minimal").1.as_deref(), Some("2/?theme=minimal"));
+    assert_eq!(get(web, "/court/3/api/game").0, 404);
+    let page = get(web, "/court/1/").2;
+    assert!(page.contains(r#""apiBase":"/court/1""#), "the page calls its court's API");
+    assert!(get(web, "/court/2/").2.contains("waiting for data"), "court 2 never had a frame");
+
+    // The failed court is reported, and fails the server's health check
+    let (status, _, body) = get(web, "/healthz");
+    assert_eq!(status, 503);
+    let health: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let courts_health = health["courts"].as_array().unwrap();
+    assert_eq!(courts_health.len(), 2);
+    assert_eq!((courts_health[0]["id"].as_str(), courts_health[0]["healthy"].as_bool()), (Some("1"), Some(true)));
+    assert_eq!((courts_health[1]["id"].as_str(), courts_health[1]["healthy"].as_bool()), (Some("2"), Some(false)));
+    assert!(courts_health[1]["error"].as_str().is_some_and(|error| !error.is_empty()));
+    assert_eq!(get(web, "/court/1/healthz").0, 200);
+    assert_eq!(get(web, "/court/2/healthz").0, 503);
+    assert!(courts.find("2").unwrap().failure().is_some());
+
+    shutdown.shutdown();
+    running.join().unwrap().unwrap();
+    courts.shutdown();
+    assert!(courts.wait(Duration::from_secs(5)));
+    drop(taken);
+}
+
+#[test]
+fn two_courts_fed_different_frames_serve_their_own_state() {
+    let text = "no-mdns\ndev\nweb-address 127.0.0.1:0\nteams-file {dir}/teams.json\n[[court]]\nlisten 127.0.0.1:0\n[[court]]\nlisten 127.0.0.1:0\n";
+    let (_, config) = parse_file("courts_index", text, "");
+    let config = config.unwrap();
+    let courts = Courts::new(&config);
+    let ingests = [courts.find("1").unwrap().bind().unwrap(), courts.find("2").unwrap().bind().unwrap()];
+    courts.start();
+    let mut options = WebOptions::from_config(&config);
+    options.courts = Some(courts.clone());
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server = runtime.block_on(WebServer::bind(courts.primary().shared(), options)).unwrap();
+    let web = server.local_addr().unwrap();
+    let shutdown = ShutdownHandle::new();
+    let running = {
+        let shutdown = shutdown.clone();
+        thread::spawn(move || runtime.block_on(server.run(shutdown)))
+    };
+
+    // Before any frame: each overlay waits, the index says so
+    let (status, _, page) = get(web, "/court/2/");
+    assert_eq!(status, 200);
+    assert!(page.contains("Court 2: waiting for data") && page.contains(r#"http-equiv="refresh""#), "{}", page);
+    let listed = json(web, "/api/courts");
+    assert_eq!(listed.as_array().map(Vec::len), Some(2));
+    assert_eq!((listed[0]["id"].as_str(), listed[0]["path"].as_str()), (Some("1"), Some("court/1/")));
+    assert_eq!((listed[1]["hasData"].as_bool(), listed[1]["ageSecs"].as_u64()), (Some(false), None));
+    assert_eq!(get(web, "/courts").2.matches("waiting for data").count(), 2);
+
+    let mut consoles = ingests.map(|ingest| TcpStream::connect(ingest).unwrap());
+    consoles[0].write_all(&scores(12, 9).to_bytes()).unwrap();
+    consoles[1].write_all(&scores(30, 41).to_bytes()).unwrap();
+    wait_for_home(&courts.find("1").unwrap().shared(), 12);
+    wait_for_home(&courts.find("2").unwrap().shared(), 30);
+
+    // `?court=` picks the court of an API call; without it the first court answers
+    let state = |path: &str| json(web, path)["state"].clone();
+    assert_eq!((state("/api/state?court=1")["homeScore"].as_u64(), state("/api/state?court=1")["awayScore"].as_u64()), (Some(12), Some(9)));
+    assert_eq!((state("/api/state?court=2")["homeScore"].as_u64(), state("/api/state?court=2")["awayScore"].as_u64()), (Some(30), Some(41)));
+    assert_eq!(state("/api/state")["homeScore"].as_u64(), Some(12));
+    assert_eq!(state("/court/2/api/state")["homeScore"].as_u64(), Some(30));
+    let unknown = http(web, "GET", "/api/state?court=9", &[], b"");
+    assert_eq!((unknown.status, unknown.json()["error"].as_str()), (404, Some("no court \"9\"")));
+
+    // The overlay replaces the waiting page, and the index shows both scores
+    let page = get(web, "/court/2/").2;
+    assert!(page.contains(r#""apiBase":"/court/2""#) && !page.contains("waiting for data"));
+    let listed = json(web, "/api/courts");
+    assert_eq!((listed[0]["homeScore"].as_str(), listed[0]["awayScore"].as_str()), (Some("12"), Some("9")));
+    assert_eq!((listed[1]["homeScore"].as_str(), listed[1]["awayScore"].as_str()), (Some("30"), Some("41")));
+    assert!(listed[1]["hasData"].as_bool().unwrap() && listed[1]["ageSecs"].as_u64().is_some_and(|age| age < 5));
+    let index = get(web, "/courts").2;
+    assert!(index.contains(r#"<a href="court/2/">Court 2</a>"#) && index.contains("30 - 41") && index.contains(" s ago"), "{}", index);
+    assert!(!index.contains("waiting for data"));
+
+    shutdown.shutdown();
+    running.join().unwrap().unwrap();
+    courts.shutdown();
+    assert!(courts.wait(Duration::from_secs(5)));
+}
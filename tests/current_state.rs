@@ -0,0 +1,82 @@
+//! New clients start from the cached current state, sent as soon as they connect, before any
+//! new frame arrives.
+
+mod common;
+
+use common::{scores, EventStream, Harness};
+use scoreboard_rust::Teams;
+use std::{
+    io::{BufRead, BufReader},
+    net::{TcpListener, TcpStream},
+    time::{Duration, Instant},
+};
+
+#[test]
+fn a_subscriber_first_reads_the_cached_state() {
+    let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let mut server = Harness::start(&format!("--subscribers {}", address));
+    server.play(&[scores(12, 9)]);
+
+    let subscriber = TcpStream::connect(address).unwrap();
+    subscriber.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let mut lines = BufReader::new(subscriber).lines();
+    let first = lines.next().unwrap().unwrap();
+    assert_eq!(first, server.shared.current_json(), "byte for byte");
+
+    // Then the next state, after it
+    server.play(&[scores(14, 9)]);
+    let next: serde_json::Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+    assert_eq!(next["home_score"], " 14");
+}
+
+#[test]
+fn an_event_stream_opens_with_the_cached_state_and_its_age() {
+    let mut server = Harness::start("");
+    server.play(&[scores(12, 9)]);
+    let (json, version) = server.shared.current_json_versioned();
+
+    let opened = Instant::now();
+    let mut stream = EventStream::open(server.web, "/api/stream", &[]);
+    let first = stream.next();
+    assert!(opened.elapsed() < Duration::from_secs(1), "not waiting for a frame");
+    assert_eq!(first.data, json);
+    assert_eq!(first.id, Some(version.to_string()));
+    let age = first.comments.iter().find_map(|comment| comment.strip_prefix("age_ms=")).expect("the age comment");
+    assert!(age.parse::<u64>().is_ok_and(|age| age < 5000), "{}", age);
+
+    // Before any frame there is no age to give
+    let server = Harness::start("");
+    let first = EventStream::open(server.web, "/api/stream", &[]).next();
+    assert_eq!((first.data, first.id.as_deref()), (server.shared.current_json(), Some("0")));
+    assert!(first.comments.is_empty(), "{:?}", first.comments);
+}
+
+#[test]
+fn an_event_stream_that_lagged_behind_gets_the_current_state() {
+    let server = Harness::start("");
+    // Big states, so the stream stops while the client reads none and the channel overflows
+    let mut teams = Teams::default();
+    teams.home.name = "H".repeat(300_000);
+    server.shared.set_teams(teams);
+    let mut stream = EventStream::open(server.web, "/events", &[]);
+    stream.next();
+
+    let mut state = server.shared.current();
+    for home in 1..=250 {
+        state.home_score = format!("{:3}", home);
+        server.shared.update(state.clone());
+    }
+    let last = server.shared.version().to_string();
+
+    // What the sockets held, then the current state at once: not the updates still queued
+    let mut ids = Vec::new();
+    while ids.last() != Some(&last) {
+        // States only, not the game events after them
+        ids.extend(stream.next().id);
+    }
+    assert!(ids.len() < 100, "{} states to catch up: {:?}", ids.len(), ids);
+    assert!(ids.windows(2).all(|pair| pair[0].parse::<u64>().unwrap() < pair[1].parse::<u64>().unwrap()), "{:?}", ids);
+    while let Some(event) = stream.next_within(Duration::from_millis(300)) {
+        assert_eq!(event.id, None, "no older state after it");
+    }
+}
@@ -0,0 +1,66 @@
+//! Custom CSS from `PUT /api/style`: added to the overlay pages served, saved to `--custom-css`
+//! and served again by the next server started on the same file.
+
+mod common;
+
+use common::{fresh_dir, Harness};
+
+const ADMIN: (&str, &str) = ("Authorization", "Bearer secret");
+const SNIPPET: &str = ".score { color: #ff6600; }";
+
+/// Whether the page served for `path` carries the snippet in its custom `<style>`.
+fn styled(server: &Harness, path: &str) -> bool {
+    let page = server.get(path);
+    assert_eq!(page.status, 200, "{}", path);
+    let text = page.text();
+    match text.find("<style id=\"custom-css\">") {
+        Some(start) => {
+            let style = &text[start..start + text[start..].find("</style>").unwrap()];
+            assert!(text[start..].contains("</head>"), "in the head");
+            style.contains(SNIPPET)
+        }
+        None => false,
+    }
+}
+
+#[test]
+fn the_snippet_is_served_and_survives_a_restart() {
+    let file = fresh_dir("custom-css").join("custom.css");
+    let args = format!("--custom-css {}", file.display());
+    let server = Harness::start(&args);
+    assert!(!styled(&server, "/"));
+
+    let put = server.request("PUT", "/api/style", &[ADMIN, ("Content-Type", "text/css")], SNIPPET.as_bytes());
+    assert!(put.status < 300, "{} {}", put.status, put.text());
+    assert_eq!(server.get("/api/style").text(), SNIPPET);
+    for path in ["/", "/?theme=minimal", "/bug", "/slate"] {
+        assert!(styled(&server, path), "{}", path);
+    }
+    let admin = server.request("GET", "/admin", &[ADMIN], b"");
+    assert_eq!(admin.status, 200);
+    assert!(!admin.text().contains(SNIPPET), "only the overlays");
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), SNIPPET);
+
+    let (ingest, web) = server.stop();
+    let server = Harness::start_on(ingest, web, &args);
+    assert!(styled(&server, "/"));
+    assert!(styled(&server, "/bug"));
+    assert_eq!(server.get("/api/style").text(), SNIPPET);
+
+    // Emptied, it is gone from the pages and stays gone
+    let put = server.request("PUT", "/api/style", &[ADMIN, ("Content-Type", "text/css"), ("Content-Length", "0")], b"");
+    assert!(put.status < 300, "{}", put.status);
+    assert!(!styled(&server, "/"));
+    let (ingest, web) = server.stop();
+    assert!(!styled(&Harness::start_on(ingest, web, &args), "/"));
+}
+
+#[test]
+fn a_snippet_closing_the_style_element_is_refused() {
+    let server = Harness::start("");
+    let put = server.request("PUT", "/api/style", &[ADMIN, ("Content-Type", "text/css")], b"a{}</style><script>");
+    assert_eq!(put.status, 400);
+    let anonymous = server.request("PUT", "/api/style", &[("Content-Type", "text/css")], SNIPPET.as_bytes());
+    assert_eq!(anonymous.status, 401);
+    assert!(!styled(&server, "/"));
+}
@@ -0,0 +1,114 @@
+//! PID file locking, `stop` and log rotation of the background mode, without daemonizing.
+#![cfg(unix)]
+
+mod common;
+
+use common::fresh_dir;
+use scoreboard_rust::{
+    daemon::{self, PidFile, StopOutcome},
+    ServerConfig,
+};
+use std::{
+    cell::Cell,
+    fs, io,
+    path::Path,
+    time::Duration,
+};
+
+#[test]
+fn a_second_instance_refuses_the_locked_pid_file() {
+    let path = fresh_dir("daemon-lock").join("scoreboard.pid");
+    let mut first = PidFile::acquire(&path).unwrap();
+    first.write_pid(4242).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "4242\n");
+
+    let e = PidFile::acquire(&path).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+    assert!(e.to_string().contains("PID 4242"), "{}", e);
+    // The running instance's PID is left alone
+    assert_eq!(daemon::running_pid(&path).unwrap(), Some(4242));
+
+    first.remove();
+    assert!(!path.exists());
+    assert_eq!(daemon::running_pid(&path).unwrap(), None);
+    PidFile::acquire(&path).unwrap();
+}
+
+#[test]
+fn a_pid_file_left_by_a_crash_is_stale() {
+    let path = fresh_dir("daemon-stale").join("scoreboard.pid");
+    fs::write(&path, "4242\n").unwrap();
+    assert_eq!(daemon::running_pid(&path).unwrap(), None, "no process holds the lock");
+
+    let mut pid_file = PidFile::acquire(&path).unwrap();
+    pid_file.write_pid(7).unwrap();
+    assert_eq!(daemon::running_pid(&path).unwrap(), Some(7));
+}
+
+#[test]
+fn stop_signals_the_holder_and_waits_for_it_to_exit() {
+    let path = fresh_dir("daemon-stop").join("scoreboard.pid");
+    let mut pid_file = PidFile::acquire(&path).unwrap();
+    pid_file.write_pid(4242).unwrap();
+
+    let signalled = Cell::new(None);
+    let outcome = daemon::stop(&path, Duration::from_secs(5), |pid| {
+        signalled.set(Some(pid));
+        // The server shuts down cleanly
+        pid_file.remove();
+        Ok(())
+    });
+    assert_eq!(outcome.unwrap(), StopOutcome::Stopped(4242));
+    assert_eq!(signalled.get(), Some(4242));
+}
+
+#[test]
+fn stop_reports_a_server_that_does_not_exit() {
+    let path = fresh_dir("daemon-hung").join("scoreboard.pid");
+    let mut pid_file = PidFile::acquire(&path).unwrap();
+    pid_file.write_pid(4242).unwrap();
+    let outcome = daemon::stop(&path, Duration::from_millis(300), |_| Ok(()));
+    assert_eq!(outcome.unwrap(), StopOutcome::StillRunning(4242));
+}
+
+#[test]
+fn stop_without_a_running_server_signals_nobody() {
+    let dir = fresh_dir("daemon-none");
+    let never = |_| -> io::Result<()> { panic!("nothing to signal") };
+    assert_eq!(daemon::stop(&dir.join("missing.pid"), Duration::ZERO, never).unwrap(), StopOutcome::NotRunning);
+    fs::write(dir.join("stale.pid"), "4242\n").unwrap();
+    assert_eq!(daemon::stop(&dir.join("stale.pid"), Duration::ZERO, never).unwrap(), StopOutcome::NotRunning);
+}
+
+#[test]
+fn rotation_shifts_the_old_logs_and_drops_the_oldest() {
+    let dir = fresh_dir("daemon-rotate");
+    let log = dir.join("scoreboard.log");
+    for (name, text) in [("scoreboard.log", "current"), ("scoreboard.log.1", "one"), ("scoreboard.log.3", "three")] {
+        fs::write(dir.join(name), text).unwrap();
+    }
+    daemon::rotate_files(&log, 3).unwrap();
+    assert!(!log.exists());
+    assert_eq!(fs::read_to_string(dir.join("scoreboard.log.1")).unwrap(), "current");
+    assert_eq!(fs::read_to_string(dir.join("scoreboard.log.2")).unwrap(), "one");
+    assert_eq!(fs::read_to_string(dir.join("scoreboard.log.3")).unwrap(), "three");
+
+    fs::write(&log, "newer").unwrap();
+    daemon::rotate_files(&log, 3).unwrap();
+    assert_eq!(fs::read_to_string(dir.join("scoreboard.log.1")).unwrap(), "newer");
+    assert_eq!(fs::read_to_string(dir.join("scoreboard.log.3")).unwrap(), "one", "the oldest is gone");
+}
+
+#[test]
+fn daemon_options_are_parsed_and_checked() {
+    let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    let config = ServerConfig::from_args(&args(&["--daemon", "--pid-file", "/run/scoreboard.pid", "--log-file", "/var/log/scoreboard.log", "--log-max-size", "5M"])).unwrap();
+    assert!(config.daemon);
+    assert_eq!(config.pid_file.as_deref(), Some(Path::new("/run/scoreboard.pid")));
+    assert_eq!(config.log_file.as_deref(), Some(Path::new("/var/log/scoreboard.log")));
+    assert_eq!(config.log_max_size, 5 << 20);
+
+    assert!(ServerConfig::from_args(&args(&["--daemon", "--tui"])).is_err());
+    assert!(ServerConfig::from_args(&args(&["--daemon", "stdin"])).is_err());
+    assert!(ServerConfig::from_args(&args(&["--log-max-size", "lots"])).is_err());
+}
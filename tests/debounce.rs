@@ -0,0 +1,64 @@
+//! Game events diffed from consecutive states, and `/api/stream?min_interval_ms=` holding clock
+//! ticks back for slow consumers while anything else goes out at once.
+
+mod common;
+
+use common::{clock_at, fouls, scores, EventStream, Harness, ServerEvent};
+use scoreboard_rust::{GameEvent, Side};
+use std::time::{Duration, Instant};
+
+#[test]
+fn changes_between_states_become_events() {
+    let mut server = Harness::start("");
+    server.play(&[scores(12, 9)]);
+    let mut updates = server.shared.subscribe();
+    server.play(&[scores(14, 9), fouls(1, 0), clock_at("0812", true), clock_at("0811", true)]);
+
+    let mut events = Vec::new();
+    while let Ok(update) = updates.try_recv() {
+        events.push(update.events.clone());
+    }
+    assert_eq!(events[0], [GameEvent::ScoreChange { side: Side::Home, from: Some(12), to: Some(14), delta: 2 }]);
+    assert_eq!(events[1], [GameEvent::FoulChange { side: Side::Home, from: None, to: Some(1) }, GameEvent::FoulChange { side: Side::Away, from: None, to: Some(0) }]);
+    assert!(events[2].contains(&GameEvent::GameStateChange { from: "paused".into(), to: "running".into() }), "{:?}", events[2]);
+    assert_eq!(events[3], [GameEvent::ClockChange { time: "08:11".into() }]);
+}
+
+/// The next state on `stream`, skipping the `game-event`s after the states before it.
+fn next_state(stream: &mut EventStream) -> ServerEvent {
+    loop {
+        let event = stream.next();
+        if event.event.is_none() {
+            return event;
+        }
+    }
+}
+
+#[test]
+fn clock_ticks_are_held_back_but_scores_are_not() {
+    let mut server = Harness::start("");
+    server.play(&[scores(0, 0), clock_at("0812", true)]);
+    let mut stream = EventStream::open(server.web, "/api/stream?min_interval_ms=1000", &[]);
+    assert_eq!(next_state(&mut stream).json()["time"], "08:12", "the current state first");
+
+    // The first tick goes out, the next ones wait for the interval
+    let ticks: Vec<_> = (5..=11).rev().map(|second| clock_at(&format!("08{:02}", second), true)).collect();
+    server.play(&ticks);
+    assert_eq!(next_state(&mut stream).json()["time"], "08:11");
+
+    // A score is sent at once, with the latest clock and the events of the ticks it carries
+    let scored = Instant::now();
+    server.play(&[scores(2, 0)]);
+    let state = next_state(&mut stream);
+    assert!(scored.elapsed() < Duration::from_millis(500), "{:?}", scored.elapsed());
+    assert_eq!((state.json()["time"].as_str(), state.json()["home_score"].as_str().map(str::trim)), (Some("08:05"), Some("2")));
+    let event = stream.next();
+    assert_eq!((event.event.as_deref(), event.json()["type"].as_str(), event.json()["delta"].as_i64()), (Some("game-event"), Some("ScoreChange"), Some(2)));
+
+    // Ticks after it are flushed once the interval has passed, the last one winning
+    server.play(&[clock_at("0804", true), clock_at("0803", true)]);
+    assert!(stream.next_within(Duration::from_millis(500)).is_none(), "held back");
+    let state = stream.next_within(Duration::from_secs(2)).expect("the flushed ticks");
+    assert_eq!(state.json()["time"], "08:03");
+    assert!(scored.elapsed() >= Duration::from_millis(1000), "{:?}", scored.elapsed());
+}
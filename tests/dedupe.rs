@@ -0,0 +1,45 @@
+//! `--dedupe`: consoles retransmit the same frame, and only a change is published.
+
+mod common;
+
+use common::{clock_at, fouls, scores, wait_until, Harness};
+use scoreboard_rust::ProtocolFrame;
+
+/// Play `frames` in one write on a server started with `args`, then a fouls frame marking
+/// the end: the versions published in all, and the frames suppressed as duplicates.
+fn published(args: &str, frames: &[ProtocolFrame]) -> (u64, u64) {
+    let mut server = Harness::start(args);
+    let mut updates = server.shared.subscribe();
+    server.send_frames(&[frames, &[fouls(4, 4)]].concat());
+    wait_until("the marking frame", || server.shared.current().home_fouls.trim() == "4");
+    let mut received = 0;
+    while updates.try_recv().is_ok() {
+        received += 1;
+    }
+    assert_eq!(received, server.shared.version(), "one update per version");
+    (server.shared.version(), server.shared.status().metrics.duplicates_suppressed)
+}
+
+#[test]
+fn the_same_frame_five_times_is_one_update() {
+    let five = vec![scores(12, 9); 5];
+    assert_eq!(published("", &five), (2, 4));
+    assert_eq!(published("--dedupe exact", &five), (2, 4));
+    assert_eq!(published("--dedupe off", &five), (6, 0));
+
+    // A repeat after a change is a change again
+    assert_eq!(published("", &[scores(12, 9), scores(14, 9), scores(12, 9), scores(12, 9)]), (4, 1));
+}
+
+#[test]
+fn clock_only_changes_are_kept_but_not_published_when_asked() {
+    let ticks = [clock_at("0812", true), clock_at("0811", true), clock_at("0810", true)];
+    assert_eq!(published("", &ticks), (4, 0));
+
+    let mut server = Harness::start("--dedupe ignore-clock");
+    server.send_frames(&[&ticks[..], &[fouls(4, 4)]].concat());
+    wait_until("the marking frame", || server.shared.current().home_fouls.trim() == "4");
+    assert_eq!(server.shared.version(), 2, "the first clock frame and the fouls");
+    assert_eq!(server.shared.status().metrics.duplicates_suppressed, 2);
+    assert_eq!(server.shared.current().time, "08:10", "the stored state has the latest clock");
+}
@@ -0,0 +1,160 @@
+//! `scoreboard-rust dump` of a raw frame feed, and of a running server's `--subscribers` stream.
+
+mod common;
+
+use common::{command, scores, wait_until, Harness, Run};
+use scoreboard_rust::{GameState, ProtocolFrame};
+use serde_json::Value;
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    process::{Child, ChildStdout, Stdio},
+    thread,
+};
+
+fn frame(message: &str) -> Vec<u8> {
+    ProtocolFrame::new(0x7F, b'G', message.as_bytes().to_vec()).to_bytes()
+}
+
+/// Scores 2-0, the same frame again, a frame with a bad LRC, then 2-3.
+fn feed() -> Vec<u8> {
+    let mut corrupt = frame("305  9  9");
+    *corrupt.last_mut().unwrap() ^= 0x01;
+    [frame("305  2  0"), frame("305  2  0"), corrupt, frame("305  2  3")].concat()
+}
+
+fn free_address() -> String {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string()
+}
+
+fn spawn(args: &[&str]) -> Child {
+    command(args).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap()
+}
+
+fn finish(child: Child) -> Run {
+    let output = child.wait_with_output().unwrap();
+    Run {
+        code: output.status.code(),
+        stdout: output.stdout,
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    }
+}
+
+/// `dump --listen` fed `feed` a byte at a time by a source that then closes.
+fn dump(feed: Vec<u8>, options: &[&str]) -> Run {
+    let address = free_address();
+    let child = spawn(&[&["dump", "--listen", &address], options].concat());
+    let mut source = None;
+    wait_until("the dump to listen", || {
+        source = TcpStream::connect(&address).ok();
+        source.is_some()
+    });
+    let mut source = source.unwrap();
+    for byte in feed {
+        source.write_all(&[byte]).unwrap();
+    }
+    drop(source);
+    let dump = finish(child);
+    assert_eq!(dump.code, Some(0), "{}", dump.stderr);
+    dump
+}
+
+#[test]
+fn states_are_written_once_per_change() {
+    let dump = dump(feed(), &[]);
+    assert!(dump.stderr.contains("Feed closed after 3 frames (1 invalid), 2 objects written"), "{}", dump.stderr);
+
+    let states = dump.json_lines();
+    assert_eq!(states.len(), 2);
+    assert_eq!((states[0]["home_score"].as_str(), states[0]["away_score"].as_str()), (Some("  2"), Some("  0")));
+    assert_eq!(states[1]["away_score"], "  3");
+}
+
+#[test]
+fn events_and_pretty_output() {
+    let events = dump(feed(), &["--events"]).json_lines();
+    // Both scores appear with the first frame, then the away team scores
+    assert_eq!(events.len(), 3);
+    assert!(events.iter().all(|event| event["type"] == "ScoreChange"), "{:?}", events);
+    assert_eq!((events[2]["side"].as_str(), events[2]["delta"].as_i64()), (Some("away"), Some(3)));
+
+    let text = dump(frame("305  2  0"), &["--pretty"]).text();
+    assert!(text.starts_with("{\n  \""), "{}", text);
+    assert_eq!(serde_json::from_str::<Value>(&text).unwrap()["home_score"], "  2");
+}
+
+#[test]
+fn arguments_are_checked() {
+    for (args, error) in [
+        ("--events", "--connect"),
+        ("--connect a:1 --listen b:2", "cannot be used with"),
+        ("--connect a:1 --lenient", "cannot be used with"),
+        ("--listen 8888", "host:port"),
+        ("--connect a:1 --bogus", "--bogus"),
+    ] {
+        let mut line = vec!["dump"];
+        line.extend(args.split_whitespace());
+        let dump = common::run(&line, b"");
+        assert_eq!(dump.code, Some(2), "{:?}", args);
+        assert!(dump.stderr.contains(error), "{:?}: {}", args, dump.stderr);
+    }
+}
+
+/// The next JSON line a running dump printed.
+fn next_line(lines: &mut std::io::Lines<BufReader<ChildStdout>>) -> Value {
+    serde_json::from_str(&lines.next().expect("a line").unwrap()).unwrap()
+}
+
+#[test]
+fn connect_subscribes_to_a_running_server() {
+    let address = free_address();
+    let mut server = Harness::start(&format!("--subscribers {}", address));
+    server.play(&[scores(12, 9)]);
+
+    let mut states = spawn(&["dump", "--connect", &address]);
+    let mut events = spawn(&["dump", "--connect", &address, "--events"]);
+    let mut state_lines = BufReader::new(states.stdout.take().unwrap()).lines();
+    let mut event_lines = BufReader::new(events.stdout.take().unwrap()).lines();
+
+    // The current state first, then each change
+    let first = next_line(&mut state_lines);
+    assert_eq!((first["home_score"].as_str(), first["away_score"].as_str()), (Some(" 12"), Some("  9")));
+    let opening: Vec<Value> = (0..2).map(|_| next_line(&mut event_lines)).collect();
+    assert!(opening.iter().all(|event| event["type"] == "ScoreChange"), "{:?}", opening);
+
+    server.play(&[scores(14, 9)]);
+    let second = next_line(&mut state_lines);
+    assert_eq!(second["home_score"], " 14");
+    assert!(second.get("changes").is_none(), "states are printed as --listen prints them: {}", second);
+    let event = next_line(&mut event_lines);
+    assert_eq!((event["side"].as_str(), event["delta"].as_i64()), (Some("home"), Some(2)));
+
+    for mut child in [states, events] {
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
+
+#[test]
+fn lines_that_are_not_states_are_skipped() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let state = |home: &str| {
+        let mut state = GameState::default();
+        state.home_score = home.to_string();
+        serde_json::to_string(&state).unwrap()
+    };
+    let lines = [state(" 12"), "not json".to_string(), String::new(), state(" 12"), state(" 15")];
+    let stream = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        for line in lines {
+            writeln!(stream, "{}", line).unwrap();
+        }
+    });
+    let dump = finish(spawn(&["dump", "--connect", &address]));
+    stream.join().unwrap();
+    assert_eq!(dump.code, Some(0), "{}", dump.stderr);
+    assert!(dump.stderr.contains("Feed closed after 3 states (1 invalid), 2 objects written"), "{}", dump.stderr);
+    let states = dump.json_lines();
+    assert_eq!((states[0]["home_score"].as_str(), states[1]["home_score"].as_str()), (Some(" 12"), Some(" 15")));
+}
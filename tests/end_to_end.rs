@@ -0,0 +1,115 @@
+//! The whole pipeline in one process: console frames over TCP into the ingest server, and the
+//! game out of the web server's JSON API, both on ports picked by the system.
+
+mod common;
+
+use common::{clock, fouls, scores, Harness};
+use serde_json::Value;
+use std::{thread, time::Duration};
+
+#[test]
+fn a_console_frame_reaches_the_overlay_json() {
+    let mut harness = Harness::start("");
+    let (status, body) = harness.get_json("/api/state");
+    assert_eq!((status, &body["state"]), (200, &Value::Null), "no state before the first frame");
+
+    harness.send_frames(&[scores(45, 38)]);
+    harness.wait_for_version(1);
+    let (status, body) = harness.get_json("/api/state");
+    assert_eq!(status, 200);
+    assert_eq!((body["state"]["homeScore"].as_u64(), body["state"]["awayScore"].as_u64()), (Some(45), Some(38)));
+    assert_eq!(body["version"], 1);
+    assert_eq!(body["stale"], false);
+    assert_eq!(harness.get_json("/api/game").1["home_score"].as_str().map(str::trim), Some("45"));
+    harness.stop();
+}
+
+#[test]
+fn frames_that_fail_to_parse_are_counted_and_skipped() {
+    let mut harness = Harness::start("");
+    let mut corrupt = scores(99, 99).to_bytes();
+    *corrupt.last_mut().unwrap() ^= 0x01;
+    // A bad LRC, bytes that are no frame, then a good frame on the same connection
+    harness.send_bytes(&[corrupt.as_slice(), b"garbage", &scores(12, 9).to_bytes()].concat());
+    harness.wait_for_version(1);
+
+    let (_, body) = harness.get_json("/api/state");
+    assert_eq!((body["state"]["homeScore"].as_u64(), body["state"]["awayScore"].as_u64()), (Some(12), Some(9)));
+    assert_eq!((body["metrics"]["framesReceived"].as_u64(), body["metrics"]["frameErrors"].as_u64()), (Some(1), Some(1)));
+    let (status, health) = harness.get_json("/healthz");
+    assert_eq!(status, 200);
+    assert_eq!(health["ingest"]["frameErrors"], 1);
+    assert_eq!(health["ingest"]["errorRate"], 0.5);
+    harness.stop();
+}
+
+#[test]
+fn frames_in_one_write_or_split_across_writes_all_apply_once() {
+    let mut harness = Harness::start("");
+    harness.send_frames(&[clock(false), scores(20, 18), fouls(3, 1)]);
+    harness.wait_for_version(3);
+    let (_, body) = harness.get_json("/api/state");
+    let state = &body["state"];
+    assert_eq!((state["clock"].as_str(), state["homeScore"].as_u64(), state["homeFouls"].as_u64()), (Some("08:12"), Some(20), Some(3)));
+    assert_eq!(body["version"], 3, "one published state per frame");
+
+    // Half a frame changes nothing until the rest comes
+    let frame = scores(22, 18).to_bytes();
+    let (head, tail) = frame.split_at(frame.len() / 2);
+    harness.send_bytes(head);
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(harness.get_json("/api/state").1["state"]["homeScore"], 20);
+    harness.send_bytes(tail);
+    harness.wait_for_version(4);
+    let (_, body) = harness.get_json("/api/state");
+    assert_eq!(body["state"]["homeScore"], 22);
+    assert_eq!(body["metrics"]["framesReceived"], 4);
+    assert_eq!(body["metrics"]["frameErrors"], 0);
+
+    // The console repeats its messages: repeats publish nothing
+    harness.send_frames(&[scores(22, 18), scores(22, 18), fouls(3, 1), scores(24, 18)]);
+    harness.wait_for_version(5);
+    let (_, body) = harness.get_json("/api/state");
+    assert_eq!((body["version"].as_u64(), body["state"]["homeScore"].as_u64()), (Some(5), Some(24)));
+    assert_eq!(body["metrics"]["framesReceived"], 8);
+    assert_eq!(body["metrics"]["duplicatesSuppressed"], 3);
+    harness.stop();
+}
+
+#[test]
+fn a_running_game_without_frames_goes_stale() {
+    let mut harness = Harness::start("--health-stale-after 1");
+    harness.send_frames(&[clock(true)]);
+    harness.wait_for_version(1);
+    let (status, health) = harness.get_json("/healthz");
+    assert_eq!((status, health["gameState"].as_str()), (200, Some("running")));
+
+    thread::sleep(Duration::from_millis(1300));
+    let (status, health) = harness.get_json("/healthz");
+    assert_eq!((status, &health["healthy"]), (503, &Value::Bool(false)));
+    assert!(health["ingest"]["lastFrameAgeMs"].as_u64().unwrap() > 1000, "{}", health);
+    assert!(harness.get_json("/api/state").1["ageMs"].as_u64().unwrap() > 1000);
+
+    // The next frame brings it back
+    harness.send_frames(&[scores(2, 0)]);
+    harness.wait_for_version(2);
+    assert_eq!(harness.get_json("/healthz").0, 200);
+    harness.stop();
+}
+
+#[test]
+fn servers_restart_on_the_same_ports() {
+    let mut harness = Harness::start("");
+    harness.send_frames(&[scores(50, 48)]);
+    harness.wait_for_version(1);
+    let (ingest, web) = harness.stop();
+
+    // The addresses are free again at once, and the new servers start from an empty state
+    let mut harness = Harness::start_on(ingest, web, "");
+    assert_eq!((harness.ingest, harness.web), (ingest, web));
+    assert_eq!(harness.get_json("/api/state").1["state"], Value::Null);
+    harness.send_frames(&[scores(2, 0)]);
+    harness.wait_for_version(1);
+    assert_eq!(harness.get_json("/api/state").1["state"]["homeScore"], 2);
+    harness.stop();
+}
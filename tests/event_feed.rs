@@ -0,0 +1,53 @@
+//! `GET /api/events?since=<id>`: the game events of each published state, in order, with ids
+//! counting up from 1.
+
+mod common;
+
+use common::{fouls, scores, Harness};
+use serde_json::{json, Value};
+
+/// The events of `page` without their timestamps, which are checked separately.
+fn without_timestamps(page: &Value) -> Vec<Value> {
+    let events = page["events"].as_array().unwrap();
+    let stamps: Vec<u64> = events.iter().map(|event| event["timestampMs"].as_u64().unwrap()).collect();
+    assert!(stamps.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", stamps);
+    events
+        .iter()
+        .map(|event| {
+            let mut event = event.clone();
+            event.as_object_mut().unwrap().remove("timestampMs");
+            event
+        })
+        .collect()
+}
+
+#[test]
+fn three_updates_give_the_exact_sequence() {
+    let mut server = Harness::start("");
+    server.play(&[scores(2, 0), fouls(1, 0), scores(2, 3)]);
+
+    let (status, page) = server.get_json("/api/events?since=0");
+    assert_eq!(status, 200);
+    assert_eq!(
+        without_timestamps(&page),
+        [
+            // The first state: the scores appear, without a delta
+            json!({ "id": 1, "version": 1, "type": "ScoreChange", "side": "home", "from": null, "to": 2, "delta": 0 }),
+            json!({ "id": 2, "version": 1, "type": "ScoreChange", "side": "away", "from": null, "to": 0, "delta": 0 }),
+            json!({ "id": 3, "version": 2, "type": "FoulChange", "side": "home", "from": null, "to": 1 }),
+            json!({ "id": 4, "version": 2, "type": "FoulChange", "side": "away", "from": null, "to": 0 }),
+            json!({ "id": 5, "version": 3, "type": "ScoreChange", "side": "away", "from": 0, "to": 3, "delta": 3 }),
+        ]
+    );
+    assert_eq!((page["firstId"].as_u64(), page["lastId"].as_u64(), page["truncated"].as_bool()), (Some(1), Some(5), Some(false)));
+
+    // Only what came after `since`, and pages of `limit`
+    let (_, after) = server.get_json("/api/events?since=4");
+    assert_eq!(after["events"].as_array().unwrap().len(), 1);
+    assert_eq!(after["events"][0]["id"], 5);
+    let (_, first) = server.get_json("/api/events?since=0&limit=2");
+    assert_eq!(first["events"].as_array().unwrap().iter().map(|event| event["id"].as_u64().unwrap()).collect::<Vec<_>>(), [1, 2]);
+    assert_eq!(first["truncated"], true);
+    let (_, none) = server.get_json("/api/events?since=5");
+    assert_eq!((none["events"].as_array().unwrap().len(), none["lastId"].as_u64()), (0, Some(5)));
+}
@@ -0,0 +1,51 @@
+//! `/events`: plain Server-Sent Events for signage browsers, read off a raw TCP connection.
+
+mod common;
+
+use common::{scores, EventStream, Harness};
+use std::time::Duration;
+
+#[test]
+fn every_state_change_is_an_event() {
+    let mut server = Harness::start("");
+    server.play(&[scores(12, 9)]);
+
+    let mut stream = EventStream::open(server.web, "/events", &[]);
+    assert_eq!(stream.status, 200);
+    assert_eq!(stream.headers.iter().find(|(name, _)| name == "content-type").map(|(_, value)| value.as_str()), Some("text/event-stream"));
+    assert_eq!(stream.next().data, server.shared.current_json());
+
+    server.play(&[scores(14, 9), scores(14, 11)]);
+    for (version, home, away, side) in [(2, " 14", "  9", "home"), (3, " 14", " 11", "away")] {
+        let event = stream.next();
+        assert_eq!(event.id, Some(version.to_string()));
+        assert_eq!((event.json()["home_score"].as_str(), event.json()["away_score"].as_str()), (Some(home), Some(away)));
+        // Then what changed, as its game event
+        let change = stream.next();
+        assert_eq!((change.event.as_deref(), change.id.as_deref()), (Some("game-event"), None));
+        assert_eq!(change.json()["side"], side);
+    }
+}
+
+#[test]
+fn a_reconnecting_client_gets_the_current_state_again() {
+    let mut server = Harness::start("");
+    server.play(&[scores(12, 9)]);
+    let mut stream = EventStream::open(server.web, "/events", &[]);
+    let first = stream.next();
+    drop(stream);
+
+    server.play(&[scores(14, 9), scores(16, 9)]);
+    let mut stream = EventStream::open(server.web, "/events", &[("Last-Event-ID", first.id.as_deref().unwrap())]);
+    let again = stream.next();
+    assert_eq!((again.id.as_deref(), again.json()["home_score"].as_str()), (Some("3"), Some(" 16")), "not the states in between");
+}
+
+#[test]
+fn an_idle_stream_gets_keepalive_comments() {
+    let server = Harness::start("");
+    let mut stream = EventStream::open(server.web, "/events", &[]);
+    stream.next();
+    let keepalive = stream.next_within(Duration::from_secs(17)).expect("a keepalive within 15 seconds");
+    assert_eq!((keepalive.comments, keepalive.data), (vec!["keepalive".to_string()], String::new()));
+}
@@ -0,0 +1,72 @@
+//! `--follow`: a file of raw frames read as it grows, from `--follow-offset` at first and
+//! again from the start once it is rotated or truncated.
+
+mod common;
+
+use common::{clock, fresh_dir, scores, wait_until, Harness};
+use scoreboard_rust::{FollowStart, ServerConfig};
+use std::{fs, io::Write, thread, time::Duration};
+
+#[test]
+fn a_followed_file_is_read_as_it_grows_rotates_and_shrinks() {
+    let dir = fresh_dir("follow_input");
+    let file = dir.join("frames.bin");
+    let server = Harness::start(&format!("--follow {}", file.display()));
+    let home = || server.shared.current().home_score.trim().to_string();
+
+    // Not there yet: the follower waits for it
+    fs::write(&file, scores(1, 2).to_bytes()).unwrap();
+    wait_until("the first frame", || home() == "1");
+
+    fs::OpenOptions::new().append(true).open(&file).unwrap().write_all(&scores(3, 4).to_bytes()).unwrap();
+    wait_until("the appended frame", || home() == "3");
+
+    // Rotated: a new file under the same name is read from its start
+    fs::rename(&file, dir.join("frames.bin.1")).unwrap();
+    fs::write(&file, [scores(5, 6).to_bytes(), clock(true).to_bytes()].concat()).unwrap();
+    wait_until("the rotated file", || home() == "5" && server.shared.current().time == "08:12");
+
+    // Truncated in place to less than was read
+    fs::write(&file, scores(7, 8).to_bytes()).unwrap();
+    wait_until("the truncated file", || home() == "7");
+    assert_eq!(server.shared.current().away_score.trim(), "8");
+    assert_eq!(server.shared.status().source, Some("follow"));
+}
+
+/// A server following with `args`, its unused listener on a free port.
+fn follow(args: &str) -> Harness {
+    let mut config = Harness::config(args);
+    config.tcp_address = "127.0.0.1:0".to_string();
+    Harness::with_config(config)
+}
+
+#[test]
+fn following_from_the_end_skips_the_frames_already_written() {
+    let dir = fresh_dir("follow_offset");
+    let file = dir.join("frames.bin");
+    let first = scores(1, 2).to_bytes();
+    fs::write(&file, [first.clone(), scores(3, 4).to_bytes()].concat()).unwrap();
+    let server = follow(&format!("--follow {} --follow-offset end", file.display()));
+    let home = || server.shared.current().home_score.trim().to_string();
+
+    // Time for the follower to open the file, as a bridge would start writing later
+    thread::sleep(Duration::from_millis(500));
+    fs::OpenOptions::new().append(true).open(&file).unwrap().write_all(&scores(5, 6).to_bytes()).unwrap();
+    wait_until("the appended frame", || home() == "5");
+    assert_eq!(server.shared.version(), 1, "only the frame written after the start");
+
+    // From a byte offset: the second frame on
+    let server = follow(&format!("--follow {} --follow-offset {}", file.display(), first.len()));
+    wait_until("the frames after the offset", || server.shared.current().home_score.trim() == "5");
+    assert_eq!(server.shared.version(), 2);
+}
+
+#[test]
+fn the_follow_offset_takes_bytes_or_end() {
+    let parse = |line: &str| ServerConfig::from_args(&line.split_whitespace().map(String::from).collect::<Vec<_>>());
+    assert_eq!(parse("--follow f.bin").unwrap().follow_start, FollowStart::Offset(0));
+    assert_eq!(parse("--follow f.bin --follow-offset end").unwrap().follow_start, FollowStart::End);
+    assert_eq!(parse("--follow f.bin --follow-offset 4096").unwrap().follow_start, FollowStart::Offset(4096));
+    assert!(parse("--follow f.bin --follow-offset start").unwrap_err().contains("--follow-offset"));
+    assert!(parse("--follow-offset end").unwrap_err().contains("only applies to --follow"));
+}
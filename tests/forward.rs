@@ -0,0 +1,54 @@
+//! `--forward`: the raw console stream relayed to downstream targets, one of which is down.
+
+mod common;
+
+use common::{clock, scores, wait_until, Harness};
+use std::{
+    io::Read,
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::Arc,
+    time::Duration,
+};
+
+/// The next `len` bytes of `stream`.
+fn read_exactly(stream: &mut TcpStream, len: usize) -> Vec<u8> {
+    stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    let mut received = vec![0; len];
+    stream.read_exact(&mut received).unwrap();
+    received
+}
+
+#[test]
+fn a_live_target_gets_every_byte_while_a_dead_one_waits() {
+    let live = TcpListener::bind("127.0.0.1:0").unwrap();
+    // A port nobody listens on, until the target comes up later
+    let dead: SocketAddr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let mut server = Harness::start(&format!("--forward {} --forward {}", live.local_addr().unwrap(), dead));
+
+    // Garbage between frames is relayed too: the stream goes on unchanged
+    let sent = [scores(12, 9).to_bytes(), b"noise".to_vec(), clock(true).to_bytes()].concat();
+    server.send_bytes(&sent);
+    let (mut downstream, _) = live.accept().unwrap();
+    assert_eq!(read_exactly(&mut downstream, sent.len()), sent);
+    server.wait_for_version(2);
+    assert_eq!(server.shared.current().home_score.trim(), "12", "forwarding doesn't hold the input up");
+
+    let shared = Arc::clone(&server.shared);
+    let targets = || shared.status().metrics.forward_targets;
+    wait_until("the live target's count", || targets()[&live.local_addr().unwrap()].bytes_forwarded == sent.len() as u64);
+    let down = &targets()[&dead];
+    assert!(!down.connected && down.bytes_forwarded == 0, "{:?}", down);
+
+    // The dead target comes up on the next retry: what it missed is dropped, not replayed,
+    // and it gets the live stream from then on
+    let revived = TcpListener::bind(dead).unwrap();
+    let (mut late, _) = revived.accept().unwrap();
+    wait_until("the revived target to connect", || targets()[&dead].connected);
+    let missed = targets()[&dead].chunks_dropped;
+    assert!(missed >= 1, "the queued chunks are counted as dropped");
+    let next = scores(14, 9).to_bytes();
+    server.send_bytes(&next);
+    assert_eq!(read_exactly(&mut late, next.len()), next);
+    wait_until("the revived target's count", || targets()[&dead].bytes_forwarded == next.len() as u64);
+    assert_eq!(targets()[&dead].chunks_dropped, missed);
+}
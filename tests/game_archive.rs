@@ -0,0 +1,73 @@
+//! Games recorded by a server in the test process, read back like `history` does.
+
+mod common;
+
+use common::{simulate, wait_until, Harness};
+use scoreboard_rust::{game_archive::GameArchive, TeamInfo, Teams};
+use std::{fs, path::Path};
+
+/// Start a server recording games to `dir`, acknowledging every frame.
+fn start_server(dir: &Path) -> Harness {
+    let mut config = Harness::config("--ack");
+    config.games_dir = Some(dir.to_path_buf());
+    Harness::with_config(config)
+}
+
+const GAME: &str = "\
+- 0:00 start
+- 0:02 score home 2
+- 0:03 score away 3
+- 0:04 foul away
+- 0:05 start
+- 0:08 score home 3
+- 0:09 period
+- 0:10 start
+- 0:12 score away 2
+- 0:14 end
+";
+
+fn play(target: &str) {
+    simulate(target, GAME);
+}
+
+#[test]
+fn simulated_games_are_recorded_and_listed() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("game_archive");
+    let _ = fs::remove_dir_all(&dir);
+    let server = start_server(&dir);
+    let (target, shared) = (server.target(), &server.shared);
+    let archive = GameArchive::new(&dir);
+
+    let team = |name: &str, abbrev: &str| TeamInfo { name: name.to_string(), abbrev: abbrev.to_string(), ..TeamInfo::default() };
+    shared.set_teams(Teams { home: team("Madeira", "CAB"), away: team("Benfica", "SLB") });
+    play(&target);
+    // The operator closes the game; the next tip-off starts another one
+    shared.set_manual(|state| {
+        state.period_name = "Final".to_string();
+        Ok::<_, ()>(())
+    })
+    .unwrap();
+    play(&target);
+    wait_until("both games recorded", || archive.list().unwrap().len() == 2);
+
+    let games = archive.list().unwrap();
+    let first = &games[0];
+    assert_eq!((first.id, first.home_score, first.away_score), (1, Some(5), Some(5)));
+    assert!(first.ended_at_ms.is_some_and(|end| end >= first.started_at_ms));
+    assert_eq!(first.teams.home.abbrev, "CAB");
+    assert!(first.line().contains("CAB 5 - 5 SLB  (over)"), "{}", first.line());
+    assert_eq!((games[1].id, games[1].ended_at_ms), (2, None));
+
+    let game = archive.game(1).unwrap().unwrap();
+    assert_eq!(game.end_reason.as_deref(), Some("final"));
+    let scores: Vec<i64> = game
+        .events
+        .iter()
+        .filter(|event| event["type"] == "ScoreChange")
+        .map(|event| event["delta"].as_i64().unwrap())
+        .collect();
+    assert_eq!(scores, [2, 3, 3, 2]);
+    assert!(game.events.windows(2).all(|pair| pair[0]["id"].as_u64() < pair[1]["id"].as_u64()));
+    assert_eq!(game.snapshots.last().unwrap()["state"]["period_name"], "Final");
+    assert!(archive.game(3).unwrap().is_none());
+}
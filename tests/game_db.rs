@@ -0,0 +1,82 @@
+//! Games recorded to an SQLite database (`sqlite` feature), read back like `history --db` does.
+
+#![cfg(feature = "sqlite")]
+
+mod common;
+
+use common::{fresh_dir, run_ok, simulate, wait_until, Harness};
+use scoreboard_rust::{
+    game_archive::{self, GameRecorder},
+    game_db::GameDb,
+    TeamInfo, Teams,
+};
+
+const GAME: &str = "\
+- 0:00 start
+- 0:02 score home 2
+- 0:03 score away 3
+- 0:04 foul away
+- 0:05 start
+- 0:08 score home 3
+- 0:09 period
+- 0:10 start
+- 0:12 score away 2
+- 0:14 end
+";
+
+#[test]
+fn a_simulated_game_is_recorded_in_memory() {
+    let server = Harness::start("--ack");
+    let db = GameDb::open_in_memory().unwrap();
+    let recorder = GameRecorder::with_store(db.clone(), game_archive::DEFAULT_IDLE_GAP).unwrap();
+    game_archive::record(recorder, "memory".to_string(), &server.shared);
+
+    let team = |name: &str, abbrev: &str| TeamInfo { name: name.to_string(), abbrev: abbrev.to_string(), ..TeamInfo::default() };
+    server.shared.set_teams(Teams { home: team("Madeira", "CAB"), away: team("Benfica", "SLB") });
+    simulate(&server.target(), GAME);
+    server
+        .shared
+        .set_manual(|state| {
+            state.period_name = "Final".to_string();
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+    wait_until("the game to end", || db.list().unwrap().first().is_some_and(|game| game.ended_at_ms.is_some()));
+
+    let games = db.list().unwrap();
+    assert_eq!(games.len(), 1);
+    let game = &games[0];
+    assert_eq!((game.id, game.home_score, game.away_score), (1, Some(5), Some(5)));
+    assert!(game.line().contains("CAB 5 - 5 SLB  (over)"), "{}", game.line());
+
+    let game = db.game(1).unwrap().unwrap();
+    assert_eq!(game.end_reason.as_deref(), Some("final"));
+    let scores: Vec<i64> = game
+        .events
+        .iter()
+        .filter(|event| event["type"] == "ScoreChange")
+        .map(|event| event["delta"].as_i64().unwrap())
+        .collect();
+    assert_eq!(scores, [2, 3, 3, 2]);
+    assert!(game.events.windows(2).all(|pair| pair[0]["id"].as_u64() < pair[1]["id"].as_u64()));
+    assert_eq!(game.snapshots.last().unwrap()["state"]["period_name"], "Final");
+    assert!(game.snapshots.iter().all(|snapshot| snapshot["timestampMs"].as_u64() >= Some(game.summary.started_at_ms)));
+    assert!(db.game(2).unwrap().is_none());
+}
+
+#[test]
+fn history_reads_a_games_database() {
+    let path = fresh_dir("game_db").join("games.sqlite");
+    let mut config = Harness::config("--ack");
+    config.games_db = Some(path.clone());
+    let server = Harness::with_config(config);
+    simulate(&server.target(), GAME);
+    let db = GameDb::open(&path).unwrap();
+    wait_until("the game to be recorded", || !db.list().unwrap().is_empty());
+
+    let path = path.to_str().unwrap();
+    let listed = run_ok(&["history", "list", "--db", path, "--json"]).json();
+    assert_eq!((listed[0]["id"].as_u64(), listed[0]["homeScore"].as_u64()), (Some(1), Some(5)));
+    let shown = run_ok(&["history", "show", "#1", "--db", path]).json();
+    assert!(shown["events"].as_array().is_some_and(|events| !events.is_empty()), "{}", shown);
+}
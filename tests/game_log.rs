@@ -0,0 +1,66 @@
+//! The game log's CSV export against a golden file.
+
+use scoreboard_rust::{GameEvent, GameLog, GameState, Side, TimeZone};
+
+/// 2026-03-14 19:05:42.250 UTC
+const TIP_OFF_MS: u64 = 1_773_515_142_250;
+
+fn state(time: &str, period: &str, scores: (&str, &str), fouls: (&str, &str)) -> GameState {
+    let mut state = GameState::default();
+    state.time = time.to_string();
+    state.period_name = period.to_string();
+    (state.home_score, state.away_score) = (scores.0.to_string(), scores.1.to_string());
+    (state.home_fouls, state.away_fouls) = (fouls.0.to_string(), fouls.1.to_string());
+    (state.home_timeouts, state.away_timeouts) = ("2".to_string(), "2".to_string());
+    state
+}
+
+fn small_log() -> GameLog {
+    let mut log = GameLog::default();
+    let game_state = |from: &str, to: &str| GameEvent::GameStateChange { from: from.to_string(), to: to.to_string() };
+
+    assert!(log.record(TIP_OFF_MS, &state("10:00", "1 Quarter", ("  0", "  0"), ("0", "0")), &[game_state("paused", "running")]));
+    // Clock ticks don't make a row
+    assert!(!log.record(
+        TIP_OFF_MS + 1_000,
+        &state("09:59", "1 Quarter", ("  0", "  0"), ("0", "0")),
+        &[GameEvent::ClockChange { time: "09:59".to_string() }],
+    ));
+    log.record(
+        TIP_OFF_MS + 12_005,
+        &state("09:48", "1 Quarter", ("  3", "  0"), ("0", "1")),
+        &[
+            GameEvent::ScoreChange { side: Side::Home, from: Some(0), to: Some(3), delta: 3 },
+            GameEvent::FoulChange { side: Side::Away, from: Some(0), to: Some(1) },
+            GameEvent::ClockChange { time: "09:48".to_string() },
+        ],
+    );
+    // Period names are quoted in the event text, so the field is quoted in the CSV
+    log.record(
+        TIP_OFF_MS + 3_600_000,
+        &state("10:00", "2 Quarter", ("  3", "   "), ("0", "1")),
+        &[
+            GameEvent::PeriodChange { from: "1 Quarter".to_string(), to: "2 Quarter".to_string() },
+            GameEvent::ScoreChange { side: Side::Away, from: Some(0), to: None, delta: 0 },
+        ],
+    );
+    log
+}
+
+#[test]
+fn csv_matches_the_golden_file() {
+    let mut csv = Vec::new();
+    small_log().write_csv(&mut csv, &TimeZone::utc()).unwrap();
+    assert_eq!(String::from_utf8(csv).unwrap(), include_str!("golden/game_log.csv"));
+}
+
+#[test]
+fn full_logs_drop_their_oldest_rows() {
+    let mut log = GameLog::new(2);
+    for n in 0..3 {
+        let state = state("10:00", "1 Quarter", (&n.to_string(), "0"), ("0", "0"));
+        log.record(TIP_OFF_MS + n, &state, &[GameEvent::Horn]);
+    }
+    let scores: Vec<&str> = log.rows().map(|row| row.home_score.as_str()).collect();
+    assert_eq!(scores, ["1", "2"]);
+}
@@ -0,0 +1,63 @@
+//! `/healthz`: 200 while frames keep coming or the game is stopped, 503 once a running game
+//! has gone without frames for `--health-stale-after`.
+
+mod common;
+
+use common::{clock_at, Harness};
+use serde_json::Value;
+use std::{thread, time::Duration};
+
+fn health(server: &Harness) -> (u16, Value) {
+    let response = server.get("/healthz");
+    assert_eq!(response.header("cache-control"), Some("no-store"));
+    (response.status, response.json())
+}
+
+fn set_running(server: &Harness, running: bool) {
+    server
+        .shared
+        .set_manual(|state| {
+            state.time = "08:12".to_string();
+            state.game_state = if running { "running" } else { "paused" }.to_string();
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn a_fed_or_stopped_game_is_healthy() {
+    let mut server = Harness::start("--health-stale-after 1");
+    let (status, body) = health(&server);
+    assert_eq!((status, body["healthy"].as_bool(), body["gameState"].as_str()), (200, Some(true), Some("paused")));
+    assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!((body["ingest"]["framesReceived"].as_u64(), body["ingest"]["lastFrameAgeMs"].as_u64()), (Some(0), None));
+
+    server.play(&[clock_at("0812", true)]);
+    let (status, body) = health(&server);
+    assert_eq!((status, body["gameState"].as_str(), body["ingest"]["framesReceived"].as_u64()), (200, Some("running"), Some(1)));
+    assert_eq!((body["ingest"]["connectedPeers"].as_u64(), body["ingest"]["errorRate"].as_f64()), (Some(1), Some(0.0)));
+    assert!(body["ingest"]["lastFrameAgeMs"].as_u64().is_some_and(|age| age < 1000));
+    assert!(body["webRequests"].as_u64().is_some_and(|requests| requests >= 1));
+
+    // Stopped, a game may go without frames for as long as it likes
+    server.play(&[clock_at("0812", false)]);
+    thread::sleep(Duration::from_millis(1200));
+    assert_eq!(health(&server).0, 200);
+}
+
+#[test]
+fn a_running_game_without_frames_is_unhealthy() {
+    let mut server = Harness::start("--health-stale-after 1");
+    server.play(&[clock_at("0812", true)]);
+    thread::sleep(Duration::from_millis(1200));
+    let (status, body) = health(&server);
+    assert_eq!((status, body["healthy"].as_bool()), (503, Some(false)));
+    assert!(body["ingest"]["lastFrameAgeMs"].as_u64().is_some_and(|age| age >= 1000));
+
+    // Set by hand rather than by frames: running without any frame at all
+    let server = Harness::start("--health-stale-after 1");
+    set_running(&server, true);
+    assert_eq!(health(&server).0, 503);
+    set_running(&server, false);
+    assert_eq!(health(&server).0, 200);
+}
@@ -0,0 +1,50 @@
+//! Keepalive bytes between frames: counted as heartbeats rather than skipped as garbage, and
+//! telling an idle link from a dead one.
+
+mod common;
+
+use common::{scores, wait_until, Harness};
+use scoreboard_rust::LinkStatus;
+use std::{thread, time::Duration};
+
+/// Heartbeats and resyncs counted on a server started with `args` after `bytes`, ending with
+/// a frame so that they have all been read.
+fn counted(args: &str, bytes: &[u8]) -> (u64, u64) {
+    let mut server = Harness::start(args);
+    server.send_bytes(&[bytes, &scores(99, 0).to_bytes()].concat());
+    wait_until("the last frame", || server.shared.current().home_score.trim() == "99");
+    let metrics = server.shared.status().metrics;
+    let resyncs = metrics.peers.values().map(|peer| peer.resyncs).sum();
+    (metrics.heartbeats_received, resyncs)
+}
+
+#[test]
+fn heartbeat_bytes_between_frames_are_not_garbage() {
+    let stream = [&[0x00][..], &scores(12, 9).to_bytes(), &[0x00, 0x00], &scores(14, 9).to_bytes()].concat();
+    assert_eq!(counted("", &stream), (3, 0));
+    assert_eq!(counted("--heartbeat-byte none", &stream), (0, 2), "skipped to find the frames");
+
+    // Other bytes when asked, and only those
+    let custom = [&[0xAA][..], &scores(12, 9).to_bytes(), &[0x55, 0xAA]].concat();
+    assert_eq!(counted("--heartbeat-byte aa --heartbeat-byte 0x55", &custom), (3, 0));
+    assert_eq!(counted("--heartbeat-byte aa", &[0x00, 0xAA]), (1, 1));
+}
+
+#[test]
+fn heartbeats_alone_keep_the_link_idle_rather_than_dead() {
+    let mut server = Harness::start("");
+    let window = Duration::from_millis(300);
+    server.play(&[scores(12, 9)]);
+    assert_eq!(server.shared.link_status(window), LinkStatus::Active);
+
+    thread::sleep(window);
+    server.send_bytes(&[0x00]);
+    wait_until("the heartbeat", || server.shared.status().metrics.heartbeats_received == 1);
+    assert_eq!(server.shared.link_status(window), LinkStatus::Idle);
+    assert!(server.shared.status().last_heartbeat_age_ms.is_some_and(|age| age < 300));
+
+    thread::sleep(window);
+    assert_eq!(server.shared.link_status(window), LinkStatus::Dead);
+    let (_, body) = server.get_json("/api/status");
+    assert_eq!(body["metrics"]["heartbeats_received"], 1);
+}
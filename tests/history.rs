@@ -0,0 +1,110 @@
+//! `/api/history`: the last `--history-capacity` published states, the oldest rotating out, and
+//! the query parameters picking among them.
+
+mod common;
+
+use common::{scores, Harness};
+use serde_json::Value;
+use std::{thread, time::Duration};
+
+/// Versions and home scores of the entries of a `/api/history` reply.
+fn entries(body: &Value) -> Vec<(u64, u64)> {
+    body["entries"].as_array().unwrap().iter().map(|entry| (entry["version"].as_u64().unwrap(), entry["state"]["homeScore"].as_u64().unwrap())).collect()
+}
+
+#[test]
+fn the_oldest_states_rotate_out() {
+    let mut server = Harness::start("--history-capacity 5");
+    let frames: Vec<_> = (1..=8).map(|home| scores(home, 0)).collect();
+    server.play(&frames);
+
+    let response = server.get("/api/history");
+    assert_eq!((response.status, response.header("x-total-count")), (200, Some("5")));
+    let body = response.json();
+    assert_eq!(entries(&body), [(4, 4), (5, 5), (6, 6), (7, 7), (8, 8)]);
+    assert_eq!(body["truncated"], false);
+    let entry = &body["entries"][0];
+    assert!(entry["timestampMs"].as_u64().is_some() && entry["timestamp"].is_string());
+    assert_eq!(entry["clock"], entry["state"]["clock"]);
+
+    // And again once full
+    server.play(&[scores(9, 0), scores(10, 0)]);
+    assert_eq!(entries(&server.get_json("/api/history").1), [(6, 6), (7, 7), (8, 8), (9, 9), (10, 10)]);
+}
+
+#[test]
+fn limits_offsets_and_times_pick_entries() {
+    let mut server = Harness::start("--history-capacity 5");
+    // Apart, so the times below fall between entries
+    play_apart(&mut server, 1..=5);
+    let (_, all) = server.get_json("/api/history");
+
+    let newest = server.get("/api/history?limit=2");
+    assert_eq!(newest.header("x-total-count"), Some("5"));
+    assert_eq!((entries(&newest.json()), newest.json()["truncated"].as_bool()), (vec![(4, 4), (5, 5)], Some(true)));
+    assert_eq!(entries(&server.get_json("/api/history?limit=2&offset=1").1), [(3, 3), (4, 4)]);
+    assert_eq!(entries(&server.get_json("/api/history?every=2").1), [(1, 1), (3, 3), (5, 5)]);
+
+    // `since` is after a time, `from` and `to` include it
+    let third = all["entries"][2]["timestampMs"].as_u64().unwrap();
+    let since = entries(&server.get_json(&format!("/api/history?since={}", third)).1);
+    assert!(since.iter().all(|(version, _)| *version > 3) && since.ends_with(&[(5, 5)]), "{:?}", since);
+    let (_, until) = server.get_json(&format!("/api/history?to={}", third));
+    assert!(entries(&until).contains(&(3, 3)));
+    assert!(until["entries"].as_array().unwrap().iter().all(|entry| entry["timestampMs"].as_u64() <= Some(third)));
+
+    for bad in ["limit=0", "every=0", "from=2&to=1", "limit=many"] {
+        assert_eq!(server.get(&format!("/api/history?{}", bad)).status, 400, "{}", bad);
+    }
+}
+
+/// Play the home scores `homes`, each published in a later millisecond than the one before.
+fn play_apart(server: &mut Harness, homes: std::ops::RangeInclusive<u32>) {
+    for home in homes {
+        thread::sleep(Duration::from_millis(5));
+        server.play(&[scores(home, 0)]);
+    }
+}
+
+#[test]
+fn downsampling_keeps_every_nth_entry_of_the_window() {
+    let mut server = Harness::start("--history-capacity 10");
+    play_apart(&mut server, 1..=10);
+    assert_eq!(entries(&server.get_json("/api/history?every=3").1), [(1, 1), (4, 4), (7, 7), (10, 10)]);
+    let response = server.get("/api/history?every=4");
+    assert_eq!((response.header("x-total-count"), entries(&response.json())), (Some("3"), vec![(1, 1), (5, 5), (9, 9)]));
+    assert_eq!(entries(&server.get_json("/api/history?every=20").1), [(1, 1)]);
+    // `limit` and `offset` count the kept entries, newest first
+    assert_eq!(entries(&server.get_json("/api/history?every=3&limit=2").1), [(7, 7), (10, 10)]);
+    assert_eq!(entries(&server.get_json("/api/history?every=3&limit=2&offset=1").1), [(4, 4), (7, 7)]);
+}
+
+#[test]
+fn a_window_straddling_the_rotation_point_starts_at_the_oldest_kept_entry() {
+    let mut server = Harness::start("--history-capacity 5");
+    play_apart(&mut server, 1..=4);
+    let (_, before) = server.get_json("/api/history");
+    let second = before["entries"][1]["timestampMs"].as_u64().unwrap();
+
+    // Versions 1 to 3 rotate out, the window opens on version 2 and closes on 6
+    play_apart(&mut server, 5..=8);
+    let (_, after) = server.get_json("/api/history");
+    assert_eq!(entries(&after)[0], (4, 4));
+    let sixth = after["entries"][2]["timestampMs"].as_u64().unwrap();
+
+    let window = format!("/api/history?from={}&to={}", second, sixth);
+    let response = server.get(&window);
+    assert_eq!((response.header("x-total-count"), entries(&response.json())), (Some("3"), vec![(4, 4), (5, 5), (6, 6)]));
+    assert_eq!(entries(&server.get_json(&format!("{}&every=2", window)).1), [(4, 4), (6, 6)]);
+    // A window that rotated out entirely is empty, not an error
+    let gone = server.get(&format!("/api/history?from={}&to={}", second, second));
+    assert_eq!((gone.status, gone.header("x-total-count"), entries(&gone.json())), (200, Some("0"), vec![]));
+}
+
+#[test]
+fn a_capacity_of_zero_keeps_nothing() {
+    let mut server = Harness::start("--history-capacity 0");
+    server.play(&[scores(1, 0), scores(2, 0)]);
+    let response = server.get("/api/history");
+    assert_eq!((response.header("x-total-count"), entries(&response.json())), (Some("0"), vec![]));
+}
@@ -0,0 +1,97 @@
+//! Commands run on game events, through `sh -c`.
+#![cfg(unix)]
+
+mod common;
+
+use common::{fresh_dir, Harness};
+use scoreboard_rust::SharedState;
+use std::{
+    fs,
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+fn start_server(hooks: &[&str], hook_timeout: Option<&str>) -> Harness {
+    let mut args = Vec::new();
+    for hook in hooks {
+        args.extend(["--hook".to_string(), hook.to_string()]);
+    }
+    if let Some(timeout) = hook_timeout {
+        args.extend(["--hook-timeout".to_string(), timeout.to_string()]);
+    }
+    Harness::with_config(Harness::config_args(&args))
+}
+
+fn set_home_score(shared: &SharedState, score: &str) {
+    shared
+        .set_manual(|state| {
+            state.home_score = score.to_string();
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+}
+
+/// The lines of `path` once it has `count` of them, waiting up to 5 seconds.
+fn wait_for_lines(path: &Path, count: usize) -> Vec<String> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let lines: Vec<String> = fs::read_to_string(path).unwrap_or_default().lines().map(String::from).collect();
+        if lines.len() >= count || Instant::now() >= deadline {
+            return lines;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn hook_runs_once_per_matching_event_with_its_json_and_the_score() {
+    let dir = fresh_dir("hooks_once");
+    let out = dir.join("runs.txt");
+    let hook = format!("ScoreChange=echo \"$SB_EVENT $SB_HOME_SCORE $(cat)\" >> {}", out.display());
+    let server = start_server(&[&hook], None);
+    let shared = &server.shared;
+
+    set_home_score(shared, "  2");
+    set_home_score(shared, "  5");
+    // Not a score change: no run
+    shared
+        .set_manual(|state| {
+            state.home_fouls = "1".to_string();
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+
+    let mut lines = wait_for_lines(&out, 2);
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(lines.len(), wait_for_lines(&out, 2).len(), "no further runs");
+    assert_eq!(lines.len(), 2, "{:?}", lines);
+    // Hooks run concurrently, so in any order
+    lines.sort();
+    for (line, score) in lines.iter().zip([2, 5]) {
+        let (prefix, json) = line.split_at(line.find('{').expect("event JSON on stdin"));
+        assert_eq!(prefix.trim(), format!("ScoreChange {}", score));
+        let event: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(event["type"], "ScoreChange");
+        assert_eq!(event["to"], score);
+    }
+}
+
+#[test]
+fn runaway_hooks_are_killed_after_the_timeout() {
+    let dir = fresh_dir("hooks_timeout");
+    let started = dir.join("started.txt");
+    let finished = dir.join("finished.txt");
+    let hook = format!(
+        "ScoreChange=echo started >> {}; sleep 5; echo finished >> {}",
+        started.display(),
+        finished.display()
+    );
+    let server = start_server(&[&hook], Some("1"));
+    let shared = &server.shared;
+
+    set_home_score(shared, "  3");
+    assert_eq!(wait_for_lines(&started, 1), ["started"]);
+    thread::sleep(Duration::from_millis(1500));
+    assert!(!finished.exists(), "the hook was killed before finishing");
+}
@@ -0,0 +1,117 @@
+//! HTTPS with a certificate signed by a test CA (`tests/fixtures/tls`), fetched with a rustls
+//! client: the same routes and WebSockets, and admin routes over plain HTTP sent to HTTPS.
+#![cfg(feature = "tls")]
+
+mod common;
+
+use common::{http, parse_response, scores, Harness, Response};
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+    sync::Arc,
+    time::Duration,
+};
+use tokio_rustls::rustls::{
+    self,
+    pki_types::{pem::PemObject, CertificateDer, ServerName},
+    ClientConnection, StreamOwned,
+};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tls");
+
+fn tls_server(extra: &str) -> Harness {
+    Harness::start(&format!("--tls-cert {0}/cert.pem --tls-key {0}/key.pem --https-address 127.0.0.1:0 {1}", FIXTURES, extra))
+}
+
+/// A TLS connection to `address` trusting only the test CA.
+fn connect(address: SocketAddr) -> StreamOwned<ClientConnection, TcpStream> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(CertificateDer::from_pem_file(format!("{}/ca.pem", FIXTURES)).unwrap()).unwrap();
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let connection = ClientConnection::new(Arc::new(config), ServerName::try_from("localhost").unwrap()).unwrap();
+    let tcp = TcpStream::connect(address).unwrap();
+    tcp.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    StreamOwned::new(connection, tcp)
+}
+
+fn https_get(address: SocketAddr, path: &str) -> Response {
+    let mut stream = connect(address);
+    stream.write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path).as_bytes()).unwrap();
+    let mut response = Vec::new();
+    // Servers may close without a TLS close_notify; what was read is complete
+    let _ = stream.read_to_end(&mut response);
+    parse_response(&response)
+}
+
+#[test]
+fn the_api_is_served_over_https() {
+    let mut server = tls_server("");
+    let https = server.https.unwrap();
+    server.play(&[scores(45, 38)]);
+    let state = https_get(https, "/api/state");
+    assert_eq!(state.status, 200);
+    assert_eq!(state.json()["state"]["homeScore"], 45);
+    assert!(https_get(https, "/").text().contains("<title>Basketball Scoreboard Overlay</title>"));
+
+    // And alongside, plain HTTP for browser sources
+    assert_eq!(server.get("/api/state").json()["state"]["homeScore"], 45);
+}
+
+#[test]
+fn websockets_upgrade_over_tls() {
+    let mut server = tls_server("");
+    server.play(&[scores(45, 38)]);
+    let mut stream = connect(server.https.unwrap());
+    stream
+        .write_all(b"GET /ws HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n")
+        .unwrap();
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        let mut byte = [0];
+        stream.read_exact(&mut byte).unwrap();
+        head.push(byte[0]);
+    }
+    assert_eq!(parse_response(&head).status, 101);
+    // The first frame is the current state, as text
+    let mut frame = [0; 4];
+    stream.read_exact(&mut frame).unwrap();
+    assert_eq!(frame[0], 0x81);
+    let mut payload = vec![0; u16::from_be_bytes([frame[2], frame[3]]) as usize];
+    stream.read_exact(&mut payload).unwrap();
+    assert_eq!(String::from_utf8(payload).unwrap(), server.shared.current_json());
+}
+
+#[test]
+fn admin_routes_over_plain_http_are_redirected() {
+    let server = tls_server("");
+    let https_port = server.https.unwrap().port();
+    let redirect = http(server.web, "PATCH", "/api/state?x=1", &[], b"{}");
+    assert_eq!(redirect.status, 308);
+    assert_eq!(redirect.header("location"), Some(format!("https://localhost:{}/api/state?x=1", https_port).as_str()));
+    assert_eq!(http(server.web, "GET", "/admin", &[], b"").status, 308);
+    assert_eq!(server.get("/api/state").status, 200, "reads stay on HTTP");
+
+    // With --no-http there is only HTTPS
+    let server = tls_server("--no-http");
+    assert_eq!(Some(server.web), server.https);
+    assert_eq!(https_get(server.web, "/api/state").status, 200);
+}
+
+#[test]
+fn a_client_that_does_not_trust_the_certificate_is_refused() {
+    let server = tls_server("");
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(CertificateDer::from_pem_file(format!("{}/cert.pem", FIXTURES)).unwrap()).unwrap();
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let connection = ClientConnection::new(Arc::new(config), ServerName::try_from("localhost").unwrap()).unwrap();
+    let mut stream = StreamOwned::new(connection, TcpStream::connect(server.https.unwrap()).unwrap());
+    assert!(stream.write_all(b"GET / HTTP/1.1\r\n\r\n").and_then(|_| stream.flush()).and_then(|_| stream.read(&mut [0; 16])).is_err());
+}
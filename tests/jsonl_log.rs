@@ -0,0 +1,72 @@
+//! The JSON Lines log: line format from a running server, and file rollover.
+
+mod common;
+
+use common::{fresh_dir, send, wait_until, Harness};
+use scoreboard_rust::{JsonlLog, TimeZone};
+use serde_json::Value;
+use std::{fs, path::Path};
+
+/// 2026-03-14 23:59:59.500 UTC
+const BEFORE_MIDNIGHT_MS: u64 = 1_773_532_799_500;
+
+fn read_lines(path: &Path) -> Vec<Value> {
+    fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn states_and_events_are_logged_as_lines() {
+    let dir = fresh_dir("jsonl_lines");
+    let server = Harness::start(&format!("--ack --timezone UTC --log-jsonl {}/scores-%Y%m%d.jsonl", dir.display()));
+
+    send(&server.target(), "--home 12 --away 9");
+
+    // The listener writes on its own thread
+    let mut lines = Vec::new();
+    wait_until("three lines", || {
+        if let Some(entry) = fs::read_dir(&dir).unwrap().next() {
+            lines = read_lines(&entry.unwrap().path());
+        }
+        lines.len() == 3
+    });
+
+    let ts = lines[0]["ts"].as_str().unwrap();
+    assert!(ts.len() == 24 && ts.ends_with('Z') && ts.as_bytes()[10] == b'T', "{}", ts);
+    assert_eq!(lines[0]["kind"], "state");
+    assert_eq!(lines[0]["version"], 1);
+    assert_eq!(lines[0]["state"]["home_score"], " 12");
+
+    assert!(lines[1..].iter().all(|line| line["kind"] == "event" && line["ts"] == ts && line["version"] == 1));
+    assert_eq!((lines[1]["type"].as_str(), lines[1]["side"].as_str()), (Some("ScoreChange"), Some("home")));
+    assert_eq!((lines[2]["id"].as_u64(), lines[2]["to"].as_u64()), (Some(2), Some(9)));
+}
+
+#[test]
+fn files_roll_over_at_midnight_and_at_the_size_cap() {
+    let dir = fresh_dir("jsonl_rollover");
+    let line = |n: u32| format!("{{\"n\":{}}}", n); // 8 bytes with its line end
+    let mut log = JsonlLog::new(format!("{}/scores-%Y%m%d.jsonl", dir.display()), Some(20), TimeZone::utc());
+
+    log.append(BEFORE_MIDNIGHT_MS, &[line(1), line(2)], false).unwrap();
+    // Past the cap: next part
+    log.append(BEFORE_MIDNIGHT_MS + 100, &[line(3)], true).unwrap();
+    // Midnight: new date, first part again
+    log.append(BEFORE_MIDNIGHT_MS + 1_000, &[line(4)], false).unwrap();
+    log.append(BEFORE_MIDNIGHT_MS + 2_000, &[line(5)], false).unwrap();
+    assert_eq!(log.path().unwrap(), dir.join("scores-20260315.jsonl"));
+
+    let numbers = |file: &str| -> Vec<u64> { read_lines(&dir.join(file)).iter().map(|line| line["n"].as_u64().unwrap()).collect() };
+    assert_eq!(numbers("scores-20260314.jsonl"), [1, 2]);
+    assert_eq!(numbers("scores-20260314.1.jsonl"), [3]);
+    assert_eq!(numbers("scores-20260315.jsonl"), [4, 5]);
+
+    // After a restart, parts without room are skipped
+    let mut log = JsonlLog::new(format!("{}/scores-%Y%m%d.jsonl", dir.display()), Some(20), TimeZone::utc());
+    log.append(BEFORE_MIDNIGHT_MS + 3_000, &[line(6)], false).unwrap();
+    assert_eq!(numbers("scores-20260315.jsonl"), [4, 5]);
+    assert_eq!(numbers("scores-20260315.1.jsonl"), [6]);
+}
@@ -0,0 +1,82 @@
+//! The web server under load and over kept-alive connections: 200 requests at once, several
+//! requests per connection, pipelining, `Connection: close` and clients too slow to send
+//! their headers.
+
+mod common;
+
+use common::{http, Harness};
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    sync::{Arc, Barrier},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn two_hundred_concurrent_requests_all_complete() {
+    let server = Harness::start("--web-workers 2");
+    let web = server.web;
+    let start = Arc::new(Barrier::new(200));
+    let clients: Vec<_> = (0..200)
+        .map(|n| {
+            let start = Arc::clone(&start);
+            thread::spawn(move || {
+                start.wait();
+                let path = if n % 2 == 0 { "/api/state" } else { "/overlay.css" };
+                http(web, "GET", path, &[], b"").status
+            })
+        })
+        .collect();
+    let statuses: Vec<u16> = clients.into_iter().map(|client| client.join().unwrap()).collect();
+    assert_eq!(statuses, vec![200; 200]);
+}
+
+#[test]
+fn requests_share_a_connection_in_order_until_it_is_closed() {
+    let server = Harness::start("");
+    let mut stream = TcpStream::connect(server.web).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+    // One request, then two pipelined in one write, the last asking to close
+    stream.write_all(b"GET /api/time HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    let mut first = [0; 15];
+    stream.read_exact(&mut first).unwrap();
+    assert_eq!(&first, b"HTTP/1.1 200 OK");
+    stream
+        .write_all(b"GET /api/locale HTTP/1.1\r\nHost: localhost\r\n\r\nGET /api/state HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    let mut rest = Vec::new();
+    stream.read_to_end(&mut rest).expect("closed after the last request");
+    let rest = String::from_utf8_lossy(&rest);
+    assert_eq!(rest.matches("HTTP/1.1 200 OK").count(), 2, "{}", rest);
+    let (locale, state) = (rest.find("\"labels\""), rest.find("\"manualOverride\""));
+    assert!(locale.is_some() && locale < state, "answered in order: {}", rest);
+}
+
+#[test]
+fn without_keep_alive_each_request_gets_its_own_connection() {
+    let server = Harness::start("--no-keep-alive");
+    let mut stream = TcpStream::connect(server.web).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream.write_all(b"GET /api/time HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply).expect("closed after one request");
+    assert!(reply.starts_with(b"HTTP/1.1 200 OK"));
+}
+
+#[test]
+fn a_client_too_slow_with_its_headers_is_timed_out() {
+    let server = Harness::start("--web-header-timeout 1");
+    let mut slow = TcpStream::connect(server.web).unwrap();
+    slow.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let started = Instant::now();
+    slow.write_all(b"GET /api/state HTTP/1.1\r\nHost: loc").unwrap();
+    let mut reply = Vec::new();
+    let _ = slow.read_to_end(&mut reply);
+    assert!(reply.starts_with(b"HTTP/1.1 408 Request Timeout"), "{}", String::from_utf8_lossy(&reply));
+    assert!(started.elapsed() < Duration::from_secs(3));
+
+    // Others are served meanwhile and after
+    assert_eq!(server.get("/api/state").status, 200);
+}
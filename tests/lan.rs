@@ -0,0 +1,101 @@
+//! Host and port options, `--public`, and the reachable addresses and warnings logged at startup.
+
+use scoreboard_rust::{
+    lan::{self, Interface},
+    ServerConfig,
+};
+use std::net::{IpAddr, SocketAddr};
+
+fn interfaces() -> Vec<Interface> {
+    let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+    vec![
+        Interface::new("lo", ip("127.0.0.1")),
+        Interface::new("eth0", ip("192.168.1.20")),
+        Interface::new("wlan0", ip("10.0.0.7")),
+        Interface::new("lo", ip("::1")),
+        Interface::new("eth0", ip("fe80::1c2:3ff:fe4a:5b6c")),
+        Interface::new("eth0", ip("2001:db8::20")),
+    ]
+}
+
+fn reachable(bind: &str) -> Vec<String> {
+    lan::reachable_addresses(bind.parse::<SocketAddr>().unwrap(), &interfaces())
+        .into_iter()
+        .map(|(address, interface)| format!("{} {}", address, interface).trim_end().to_string())
+        .collect()
+}
+
+#[test]
+fn a_wildcard_listener_is_reached_on_every_interface() {
+    assert_eq!(reachable("0.0.0.0:3030"), ["192.168.1.20:3030 eth0", "10.0.0.7:3030 wlan0", "127.0.0.1:3030 lo"]);
+    // Link-local IPv6 needs a zone in the URL, so it's left out
+    assert_eq!(
+        reachable("[::]:3030"),
+        ["192.168.1.20:3030 eth0", "10.0.0.7:3030 wlan0", "[2001:db8::20]:3030 eth0", "127.0.0.1:3030 lo", "[::1]:3030 lo"]
+    );
+}
+
+#[test]
+fn a_bound_address_is_only_reached_there() {
+    assert_eq!(reachable("192.168.1.20:4001"), ["192.168.1.20:4001 eth0"]);
+    assert_eq!(reachable("127.0.0.1:3030"), ["127.0.0.1:3030 lo"]);
+    // An address of no listed interface, e.g. when they can't be listed
+    assert_eq!(reachable("172.16.0.9:3030"), ["172.16.0.9:3030"]);
+    assert!(lan::reachable_addresses("0.0.0.0:3030".parse().unwrap(), &[]).is_empty());
+}
+
+fn config(line: &str) -> Result<ServerConfig, String> {
+    ServerConfig::from_args(&line.split_whitespace().map(String::from).collect::<Vec<_>>())
+}
+
+#[test]
+fn hosts_and_ports_are_set_apart() {
+    let config = config("--tcp-host 127.0.0.1 --web-port 8080 --tcp-port 5001").unwrap();
+    assert_eq!(config.tcp_address, "127.0.0.1:5001");
+    assert_eq!(config.web_address, "0.0.0.0:8080".parse().unwrap());
+
+    let config = self::config("--listen [::1]:4001 --web-address [::]:3030 --web-host ::1").unwrap();
+    assert_eq!(config.tcp_address, "[::1]:4001");
+    assert_eq!(config.web_address, "[::1]:3030".parse().unwrap());
+    let config = self::config("--tcp-addr scoreboard-1.local:4001 --web-address localhost:3030").unwrap();
+    assert_eq!(config.tcp_address, "scoreboard-1.local:4001", "looked up when the listener binds");
+    assert!(config.web_address.ip().is_loopback());
+
+    for refused in ["--listen ::1:4001", "--web-address 127.0.0.1", "--tcp-port 70000", "--web-host bad_host!", "--tcp-addr 10.0.0.1:x"] {
+        assert!(self::config(refused).is_err(), "{}", refused);
+    }
+    assert!(self::config("stdin --tcp-port 5001").is_err());
+}
+
+#[test]
+fn public_binds_all_interfaces() {
+    let config = config("--public --listen 127.0.0.1:5001 --web-address 127.0.0.1:8080 --udp-addr 127.0.0.1:5002").unwrap();
+    assert!(config.public);
+    assert_eq!(config.tcp_address, "0.0.0.0:5001");
+    assert_eq!(config.web_address, "0.0.0.0:8080".parse().unwrap());
+    assert_eq!(config.udp_address.as_deref(), Some("0.0.0.0:5002"));
+    assert!(self::config("--public --web-host 127.0.0.1").unwrap_err().contains("--web-host"));
+    assert!(self::config("--public stdin").is_ok());
+}
+
+fn warnings(line: &str) -> Vec<String> {
+    lan::exposure_warnings(&config(line).unwrap())
+}
+
+#[test]
+fn the_default_addresses_warn_without_authentication() {
+    // The defaults bind all interfaces: no --public needed to be open to the network
+    let default = warnings("");
+    assert_eq!(default.len(), 2, "{:?}", default);
+    assert!(default[0].contains("0.0.0.0:4001") && default[0].contains("--auth-token"), "{}", default[0]);
+    assert!(default[1].contains("0.0.0.0:3030") && default[1].contains("--lock-read-api"), "{}", default[1]);
+    assert_eq!(warnings("--public"), default);
+
+    assert!(warnings("--auth-token s3cret --lock-read-api").is_empty());
+    assert!(warnings("--listen 127.0.0.1:4001 --web-address [::1]:3030").is_empty());
+    assert!(warnings("stdin --web-host localhost").is_empty());
+    // One side on the network is enough
+    let udp = warnings("--listen 127.0.0.1:4001 --udp-addr 192.168.1.20:4002 --web-address 127.0.0.1:3030");
+    assert_eq!(udp.len(), 1, "{:?}", udp);
+    assert!(udp[0].contains("192.168.1.20:4002"), "{}", udp[0]);
+}
@@ -0,0 +1,78 @@
+//! Display strings per request: `?lang=pt` and `?lang=fr`, `Accept-Language`, `--lang`, and
+//! English or the configured default for languages there are no strings for.
+
+mod common;
+
+use common::Harness;
+use scoreboard_rust::Lang;
+use serde_json::Value;
+
+const ADMIN: (&str, &str) = ("Authorization", "Bearer secret");
+
+/// A server showing the 4th quarter, paused.
+fn fourth_quarter(args: &str) -> Harness {
+    let server = Harness::start(args);
+    let set = server.request(
+        "POST",
+        "/api/state",
+        &[ADMIN, ("Content-Type", "application/json")],
+        br#"{"periodName": "4 Quarter", "gameState": "paused", "homeScore": 70}"#,
+    );
+    assert_eq!(set.status, 200, "{}", set.text());
+    server
+}
+
+fn state(server: &Harness, path: &str, headers: &[(&str, &str)]) -> Value {
+    let response = server.request("GET", path, headers, b"");
+    assert_eq!(response.status, 200);
+    response.json()["state"].clone()
+}
+
+#[test]
+fn portuguese_display_strings() {
+    let server = fourth_quarter("");
+    let state = state(&server, "/api/state?lang=pt", &[]);
+    assert_eq!(state["display"]["lang"], "pt");
+    assert_eq!(state["display"]["periodName"], "4º Período");
+    assert_eq!(state["display"]["gameState"], "parado");
+    // The data itself stays as the console names it
+    assert_eq!((state["periodName"].as_str(), state["gameState"].as_str()), (Some("4 Quarter"), Some("paused")));
+    assert_eq!(state["homeScore"], 70);
+
+    let strings = server.get("/api/locale?lang=pt").json();
+    assert_eq!(strings["labels"]["fouls"], "Faltas");
+    assert_eq!(strings["periodNames"]["Overtime"], "Prorrogação");
+    assert_eq!(Lang::Pt.period_name("Halftime"), "Intervalo");
+}
+
+#[test]
+fn french_display_strings() {
+    let server = fourth_quarter("");
+    let state = state(&server, "/api/state", &[("Accept-Language", "de-DE, fr-CA;q=0.8, en;q=0.5")]);
+    assert_eq!(state["display"]["lang"], "fr");
+    assert_eq!(state["display"]["periodName"], "4e quart-temps");
+    assert_eq!(state["display"]["gameState"], "arrêté");
+    assert_eq!(Lang::Fr.period_name("1 Quarter"), "1er quart-temps");
+
+    let strings = server.request("GET", "/api/locale", &[("Accept-Language", "fr")], b"").json();
+    assert_eq!(strings["labels"]["timeouts"], "Temps morts");
+    assert_eq!(strings["gameStates"]["running"], "en cours");
+}
+
+#[test]
+fn unknown_languages_fall_back() {
+    let server = fourth_quarter("");
+    let english = state(&server, "/api/state?lang=xx", &[("Accept-Language", "de, it;q=0.9")]);
+    assert_eq!(english["display"]["lang"], "en");
+    assert_eq!(english["display"]["periodName"], "4 Quarter");
+    assert_eq!(Lang::parse("es"), None);
+
+    // To the configured default, and ?lang= skips to the next choice rather than to English
+    let server = fourth_quarter("--lang pt");
+    assert_eq!(state(&server, "/api/state?lang=xx", &[])["display"]["lang"], "pt");
+    assert_eq!(state(&server, "/api/state?lang=xx", &[("Accept-Language", "fr")])["display"]["lang"], "fr");
+    assert_eq!(state(&server, "/api/state?lang=en", &[("Accept-Language", "fr")])["display"]["lang"], "en");
+
+    // Names set by hand aren't translated
+    assert_eq!(Lang::Pt.period_name("Prolongamento 2"), "Prolongamento 2");
+}
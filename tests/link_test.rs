@@ -0,0 +1,50 @@
+//! `--echo` and `scoreboard-rust link-test`: round trips over a link, and frames lost on one
+//! that swallows them.
+
+mod common;
+
+use common::Harness;
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+#[test]
+fn an_echo_server_returns_every_test_frame_and_keeps_the_game() {
+    let server = Harness::start("--echo");
+    let run = common::run(&["link-test", &server.target(), "--count", "5", "--interval", "10"], b"");
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    assert!(run.text().starts_with("5 sent, 5 received, 0 lost (0.0% loss); rtt min/avg/max = "), "{}", run.text());
+    assert_eq!(server.shared.version(), 0, "echoed frames aren't applied");
+}
+
+#[test]
+fn pings_are_answered_and_other_bytes_echoed() {
+    let server = Harness::start("--echo");
+    let mut stream = TcpStream::connect(server.ingest).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream.write_all(b"PING 42\nraw \x01\x7f bytes").unwrap();
+    let expected = b"PONG 42\nraw \x01\x7f bytes";
+    let mut received = vec![0; expected.len()];
+    stream.read_exact(&mut received).unwrap();
+    assert_eq!(received, expected);
+}
+
+#[test]
+fn a_link_that_swallows_frames_loses_them() {
+    let sink = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = sink.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        let (mut stream, _) = sink.accept().unwrap();
+        let _ = std::io::copy(&mut stream, &mut std::io::sink());
+    });
+    let run = common::run(&["link-test", &address, "--count", "2", "--interval", "0", "--timeout", "100"], b"");
+    assert_eq!(run.code, Some(1));
+    assert_eq!(run.text().trim(), "2 sent, 0 received, 2 lost (100.0% loss)");
+    assert_eq!(run.stderr.matches("lost").count(), 2, "{}", run.stderr);
+
+    assert_eq!(common::run(&["link-test", "--count", "many"], b"").code, Some(2));
+    assert_eq!(common::run(&["link-test", "--bogus"], b"").code, Some(2));
+}
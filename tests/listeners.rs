@@ -0,0 +1,47 @@
+//! Update listeners that fall behind: their queue stays bounded but loses no event.
+
+use scoreboard_rust::{BasketballServer, GameEvent, ServerConfig};
+use std::{
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+
+#[test]
+fn a_listener_that_falls_behind_gets_every_event_and_the_last_state() {
+    let shared = BasketballServer::new(ServerConfig::default()).shared();
+    let (open, gate) = mpsc::channel::<()>();
+    let gate = Mutex::new(gate);
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&received);
+    shared.add_listener(Duration::ZERO, move |update| {
+        // Stuck until the test has published everything
+        let _ = gate.lock().unwrap().recv();
+        sink.lock().unwrap().push((update.version, update.events.clone()));
+    });
+
+    for points in 1..=300 {
+        shared
+            .set_manual(|state| {
+                state.home_score = format!("{:>3}", points);
+                state.time = format!("{:02}:00", points % 60);
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+    }
+    drop(open);
+    assert!(shared.close_listeners(Duration::from_secs(5)));
+
+    let received = received.lock().unwrap();
+    assert!(received.len() <= 65, "{} updates queued", received.len());
+    assert_eq!(received.last().unwrap().0, shared.version(), "the last state arrives");
+    let scores: Vec<u32> = received
+        .iter()
+        .flat_map(|(_, events)| events)
+        .filter_map(|event| match event {
+            GameEvent::ScoreChange { to, .. } => *to,
+            _ => None,
+        })
+        .collect();
+    assert_eq!(scores, (1..=300).collect::<Vec<_>>(), "every score change, in order");
+    assert!(shared.status().metrics.listener_updates_coalesced > 0);
+}
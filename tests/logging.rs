@@ -0,0 +1,98 @@
+//! Log events of a console connection carry the connection's and the frame's span fields.
+
+mod common;
+
+use common::Harness;
+use scoreboard_rust::ProtocolFrame;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::Write,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Subscriber,
+};
+use tracing_subscriber::{layer::Context, layer::SubscriberExt, registry::LookupSpan, Layer};
+
+/// Field values as the log shows them.
+#[derive(Debug, Clone, Default)]
+struct Fields(BTreeMap<String, String>);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_string(), format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+}
+
+/// An event with the spans it happened in, outermost first.
+#[derive(Debug, Clone)]
+struct Captured {
+    fields: Fields,
+    spans: Vec<(&'static str, Fields)>,
+}
+
+impl Captured {
+    fn span(&self, name: &str) -> Option<&Fields> {
+        self.spans.iter().find(|(span, _)| *span == name).map(|(_, fields)| fields)
+    }
+}
+
+/// Keeps every event instead of writing it.
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<Captured>>>);
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Capture {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        ctx.span(id).unwrap().extensions_mut().insert(fields);
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let spans = ctx
+            .event_scope(event)
+            .map(|scope| scope.from_root().map(|span| (span.name(), span.extensions().get::<Fields>().cloned().unwrap_or_default())).collect())
+            .unwrap_or_default();
+        self.0.lock().unwrap().push(Captured { fields, spans });
+    }
+}
+
+#[test]
+fn a_parse_error_names_its_connection_and_frame() {
+    let capture = Capture::default();
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(capture.clone())).unwrap();
+
+    let mut server = Harness::start("");
+    let console = server.console();
+    let local = console.local_addr().unwrap();
+    let good = ProtocolFrame::new(0x7F, 0x20, b"305 45 38".to_vec()).to_bytes();
+    let mut bad = good.clone();
+    *bad.last_mut().unwrap() ^= 0x01;
+    console.write_all(&good).unwrap();
+    console.write_all(&bad).unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let parse_error = loop {
+        let found = capture.0.lock().unwrap().iter().find(|event| event.fields.0.get("message").is_some_and(|message| message.starts_with("Failed to parse"))).cloned();
+        match found {
+            Some(event) => break event,
+            None if Instant::now() < deadline => thread::sleep(Duration::from_millis(20)),
+            None => panic!("no parse error logged"),
+        }
+    };
+    let connection = parse_error.span("connection").expect("in the connection span");
+    assert_eq!(connection.0.get("id").map(String::as_str), Some("1"));
+    assert_eq!(connection.0.get("peer"), Some(&local.to_string()));
+    let frame = parse_error.span("frame").expect("in the frame span");
+    assert_eq!(frame.0.get("seq").map(String::as_str), Some("2"), "the second frame of the connection");
+}
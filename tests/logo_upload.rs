@@ -0,0 +1,77 @@
+//! `POST /api/teams/<side>/logo`: an uploaded logo is saved under `--upload-dir`, set as the
+//! team's `logoUrl` and served back from `/uploads/`; anything but a small PNG, JPEG or WebP
+//! is refused.
+
+mod common;
+
+use common::{fresh_dir, Harness, Response};
+use std::{fs, thread, time::Duration};
+
+const ADMIN: (&str, &str) = ("Authorization", "Bearer secret");
+
+/// A 1x1 transparent PNG.
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x01\0\0\0\x01\x08\x06\0\0\0\x1f\x15\xc4\x89\0\0\0\x0dIDATx\x9cc\xf8\x0f\0\x00\x01\x01\x01\0\x18\xdd\x8d\xb0\0\0\0\0IEND\xaeB`\x82";
+
+/// `data` as the `field` of a multipart form declared as `content_type`.
+fn upload(server: &Harness, side: &str, field: &str, content_type: &str, data: &[u8]) -> Response {
+    let body = [
+        format!("--x\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"logo\"\r\nContent-Type: {}\r\n\r\n", field, content_type).as_bytes(),
+        data,
+        b"\r\n--x--\r\n",
+    ]
+    .concat();
+    server.request("POST", &format!("/api/teams/{}/logo", side), &[ADMIN, ("Content-Type", "multipart/form-data; boundary=x")], &body)
+}
+
+#[test]
+fn an_uploaded_png_is_served_back() {
+    let dir = fresh_dir("logo_upload");
+    let server = Harness::start(&format!("--upload-dir {}", dir.display()));
+
+    let response = upload(&server, "home", "logo", "image/png", PNG);
+    assert_eq!(response.status, 200, "{}", response.text());
+    let url = response.json()["home"]["logoUrl"].as_str().unwrap().to_string();
+    assert!(url.starts_with("/uploads/home-") && url.ends_with(".png"), "{}", url);
+    assert_eq!(server.get_json("/api/teams").1["home"]["logoUrl"], url.as_str());
+    assert_eq!(server.shared.current().teams.home.logo_url.as_deref(), Some(url.as_str()));
+    assert_eq!(fs::read(dir.join(&url["/uploads/".len()..])).unwrap(), PNG, "saved under --upload-dir");
+
+    let logo = server.get(&url);
+    assert_eq!(logo.status, 200);
+    assert_eq!(logo.header("content-type"), Some("image/png"));
+    assert_eq!(logo.body, PNG);
+
+    // A new upload gets a new name, and the old file goes
+    thread::sleep(Duration::from_millis(5));
+    let replaced = upload(&server, "home", "logo", "image/png", PNG).json()["home"]["logoUrl"].as_str().unwrap().to_string();
+    assert_ne!(replaced, url);
+    assert_eq!(server.get(&replaced).body, PNG);
+    assert_eq!(server.get(&url).status, 404);
+    assert_eq!(server.get_json("/api/teams").1["away"]["logoUrl"], serde_json::Value::Null, "the other side is untouched");
+}
+
+#[test]
+fn other_uploads_are_refused() {
+    let server = Harness::start("");
+    let refused = |response: Response, status: u16, expected: &str| {
+        assert_eq!(response.status, status, "{}", response.text());
+        assert!(response.text().contains(expected), "{}", response.text());
+    };
+    refused(upload(&server, "home", "logo", "image/svg+xml", b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"), 400, "must be image/png");
+    refused(upload(&server, "home", "logo", "image/jpeg", PNG), 400, "not a valid JPG");
+    refused(upload(&server, "home", "logo", "image/png", b"GIF89a"), 400, "not a valid PNG");
+    refused(upload(&server, "home", "image", "image/png", PNG), 400, "field named logo");
+    refused(upload(&server, "court", "logo", "image/png", PNG), 400, "Unknown side");
+    let large = [PNG, &vec![0; 512 * 1024]].concat();
+    let too_large = upload(&server, "away", "logo", "image/png", &large);
+    assert_eq!((too_large.status, too_large.json()["error"].as_str()), (413, Some("Logo must be at most 524288 bytes")));
+    assert_eq!(upload(&server, "away", "logo", "image/png", &[PNG, &vec![0; 1024 * 1024]].concat()).status, 413, "over the body limit");
+    let anonymous = server.request("POST", "/api/teams/home/logo", &[("Content-Type", "multipart/form-data; boundary=x")], b"--x--\r\n");
+    assert_eq!(anonymous.status, 401);
+    assert_eq!(server.get_json("/api/teams").1["home"]["logoUrl"], serde_json::Value::Null);
+
+    // Only files in the upload directory are served
+    for path in ["/uploads/../teams.json", "/uploads/%2e%2e/teams.json", "/uploads/..%2fteams.json", "/uploads/"] {
+        assert_eq!(server.get(path).status, 404, "{}", path);
+    }
+}
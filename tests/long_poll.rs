@@ -0,0 +1,71 @@
+//! Long polling, `GET /api/state?wait=<secs>&version=<n>`: held until a newer state is
+//! published by another thread, or answered with the current one when the wait runs out.
+
+mod common;
+
+use common::{http, scores, Harness};
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn a_waiter_is_answered_when_another_thread_publishes() {
+    let mut server = Harness::start("");
+    server.play(&[scores(10, 8)]);
+    let version = server.shared.version();
+
+    let web = server.web;
+    let started = Instant::now();
+    let waiter = thread::spawn(move || http(web, "GET", &format!("/api/state?wait=30&version={}", version), &[], b""));
+    thread::sleep(Duration::from_millis(300));
+    assert!(!waiter.is_finished(), "nothing newer was published yet");
+
+    server.play(&[scores(12, 8)]);
+    let response = waiter.join().unwrap();
+    assert_eq!(response.status, 200);
+    let body = response.json();
+    assert_eq!(body["version"], version + 1);
+    assert_eq!(body["state"]["homeScore"], 12);
+    assert!(started.elapsed() < Duration::from_secs(10), "answered on the update, not at the end of the wait");
+}
+
+#[test]
+fn a_wait_that_runs_out_returns_the_current_state() {
+    let mut server = Harness::start("");
+    server.play(&[scores(10, 8)]);
+    let version = server.shared.version();
+
+    let started = Instant::now();
+    let response = server.get(&format!("/api/state?wait=1&version={}", version));
+    let waited = started.elapsed();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.json()["version"], version);
+    assert_eq!(response.json()["state"]["homeScore"], 10);
+    assert!(waited >= Duration::from_millis(900) && waited < Duration::from_secs(5), "{:?}", waited);
+
+    // A client that is behind is answered at once
+    let started = Instant::now();
+    assert_eq!(server.get("/api/state?wait=30&version=0").json()["version"], version);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn wait_for_version_is_woken_by_a_writer_and_times_out_otherwise() {
+    let mut server = Harness::start("");
+    server.play(&[scores(1, 0)]);
+    let version = server.shared.version();
+    assert!(server.shared.wait_for_version(version, Duration::from_millis(200)).is_none());
+
+    let shared = Arc::clone(&server.shared);
+    let writer = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        let mut state = shared.current();
+        state.home_score = "  3".to_string();
+        shared.update(state);
+    });
+    let (state, newer) = server.shared.wait_for_version(version, Duration::from_secs(10)).expect("the writer's state");
+    writer.join().unwrap();
+    assert_eq!((state.home_score.as_str(), newer), ("  3", version + 1));
+}
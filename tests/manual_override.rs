@@ -0,0 +1,75 @@
+//! `POST`/`PATCH /api/state`: the operator correcting the state by hand, and what console frames
+//! do afterwards under each `--override-mode`.
+
+mod common;
+
+use common::{fouls, scores, Harness, Response};
+
+const ADMIN: (&str, &str) = ("Authorization", "Bearer secret");
+
+fn patch(server: &Harness, body: &str) -> Response {
+    server.request("PATCH", "/api/state", &[ADMIN, ("Content-Type", "application/json")], body.as_bytes())
+}
+
+#[test]
+fn a_patch_changes_only_the_home_score() {
+    let mut server = Harness::start("");
+    server.play(&[scores(61, 58), fouls(3, 5)]);
+    let (_, before) = server.get_json("/api/state");
+
+    let response = patch(&server, r#"{"homeScore": 63}"#);
+    assert_eq!(response.status, 200, "{}", response.text());
+    let after = response.json();
+    assert_eq!(after["manualOverride"], true);
+    assert_eq!(after["state"]["homeScore"], 63);
+    for field in ["awayScore", "homeFouls", "awayFouls", "clock", "gameState", "periodName"] {
+        assert_eq!(after["state"][field], before["state"][field], "{}", field);
+    }
+    assert_eq!(server.shared.current().home_score, " 63");
+}
+
+#[test]
+fn invalid_or_unauthorized_patches_change_nothing() {
+    let mut server = Harness::start("");
+    server.play(&[scores(61, 58)]);
+    let version = server.shared.version();
+
+    assert_eq!(patch(&server, r#"{"homeScore": 63, "awayFouls": 500}"#).status, 400);
+    assert_eq!(patch(&server, r#"{"homeScores": 63}"#).status, 400, "unknown field");
+    let anonymous = server.request("PATCH", "/api/state", &[("Content-Type", "application/json")], br#"{"homeScore": 63}"#);
+    assert_eq!(anonymous.status, 401);
+    assert_eq!((server.shared.version(), server.shared.current().home_score.as_str()), (version, " 61"));
+}
+
+#[test]
+fn a_post_replaces_the_whole_state() {
+    let mut server = Harness::start("");
+    server.play(&[scores(61, 58), fouls(3, 5)]);
+    let response = server.request("POST", "/api/state", &[ADMIN, ("Content-Type", "application/json")], br#"{"homeScore": 2, "clock": "10:00"}"#);
+    assert_eq!(response.status, 200, "{}", response.text());
+    let state = &response.json()["state"];
+    assert_eq!((state["homeScore"].as_u64(), state["clock"].as_str()), (Some(2), Some("10:00")));
+    assert_eq!((state["awayScore"].as_u64(), state["homeFouls"].as_u64()), (None, None), "the rest is cleared");
+}
+
+#[test]
+fn the_console_resumes_or_is_held_off_until_released() {
+    let mut server = Harness::start("");
+    server.play(&[scores(61, 58)]);
+    patch(&server, r#"{"homeScore": 63}"#);
+    server.play(&[scores(64, 58)]);
+    let (_, state) = server.get_json("/api/state");
+    assert_eq!((state["manualOverride"].as_bool(), state["state"]["homeScore"].as_u64()), (Some(false), Some(64)));
+
+    let mut server = Harness::start("--override-mode hold");
+    server.play(&[scores(61, 58)]);
+    patch(&server, r#"{"homeScore": 63}"#);
+    server.send_frames(&[scores(64, 58)]);
+    common::wait_until("the frame to be read", || server.shared.status().metrics.peers.values().any(|peer| peer.frames == 2));
+    assert_eq!(server.shared.current().home_score, " 63", "ignored while held");
+
+    let released = server.request("DELETE", "/api/state/override", &[ADMIN], b"");
+    assert_eq!((released.status, released.json()["manualOverride"].as_bool()), (200, Some(false)));
+    server.play(&[scores(66, 58)]);
+    assert_eq!(server.shared.current().home_score, " 66");
+}
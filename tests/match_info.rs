@@ -0,0 +1,128 @@
+//! Match details: validation of the tip-off time, the countdown to it, and keeping them on file.
+
+use scoreboard_rust::{BasketballServer, Countdown, MatchInfo, ServerConfig};
+use std::{fs, path::Path};
+
+/// Unix milliseconds of an RFC 3339 time, through the same parser as the tip-off.
+fn at(time: &str) -> u64 {
+    MatchInfo { tip_off: Some(time.to_string()), ..MatchInfo::default() }.tip_off_ms().unwrap()
+}
+
+fn tip_off(time: &str) -> MatchInfo {
+    MatchInfo { tip_off: Some(time.to_string()), ..MatchInfo::default() }
+}
+
+fn countdown(tip_off_time: &str, now: &str) -> Option<Countdown> {
+    tip_off(tip_off_time).countdown(at(now))
+}
+
+#[test]
+fn tip_off_needs_a_date_time_and_offset() {
+    for valid in ["2026-03-14T20:30:00+01:00", "2026-03-14T19:30Z", "2026-03-14T16:30-03:00", "2028-02-29T12:00:00Z"] {
+        assert!(tip_off(valid).validate().is_ok(), "{}", valid);
+    }
+    assert_eq!(at("2026-03-14T20:30:00+01:00"), at("2026-03-14T19:30Z"));
+    assert_eq!(at("2026-03-14T16:30-03:00"), at("2026-03-14T19:30:00Z"));
+
+    for invalid in [
+        "2026-03-14T20:30:00",
+        "2026-03-14 20:30:00+01:00",
+        "2026-03-14T20:30+1",
+        "2026-02-29T20:30Z",
+        "2026-04-31T20:30Z",
+        "2026-03-14T24:00Z",
+        "2026-03-14T20:3Z",
+        "20:30",
+    ] {
+        let error = tip_off(invalid).validate().unwrap_err();
+        assert!(error.contains("tipOff"), "{}: {}", invalid, error);
+    }
+}
+
+#[test]
+fn validation_trims_and_limits_the_fields() {
+    let mut info = MatchInfo {
+        competition: "  Liga Betclic ".to_string(),
+        round: "Round 12".to_string(),
+        venue: "Pavilhão do Funchal".to_string(),
+        tip_off: Some(" 2026-03-14T20:30:00+01:00 ".to_string()),
+        officials: vec![" A. Silva ".to_string(), " ".to_string(), "R. Costa".to_string()],
+    };
+    info.validate().unwrap();
+    assert_eq!(info.competition, "Liga Betclic");
+    assert_eq!(info.tip_off.as_deref(), Some("2026-03-14T20:30:00+01:00"));
+    assert_eq!(info.officials, ["A. Silva", "R. Costa"]);
+    assert!(!info.is_empty());
+    assert!(MatchInfo::default().validate().is_ok());
+    let mut blank = tip_off(" ");
+    blank.validate().unwrap();
+    assert_eq!(blank.tip_off, None);
+
+    let mut long = MatchInfo { venue: "x".repeat(65), ..MatchInfo::default() };
+    assert!(long.validate().unwrap_err().contains("venue"));
+    let mut crowded = MatchInfo { officials: vec!["Ref".to_string(); 6], ..MatchInfo::default() };
+    assert!(crowded.validate().is_err());
+
+    let json: MatchInfo = serde_json::from_str(r#"{"competition": "Taça", "tipOff": "2026-03-14T19:30Z"}"#).unwrap();
+    assert_eq!((json.competition.as_str(), json.officials.len()), ("Taça", 0));
+    assert!(serde_json::from_str::<MatchInfo>(r#"{"kickOff": "2026-03-14T19:30Z"}"#).is_err(), "unknown fields");
+}
+
+#[test]
+fn countdown_runs_across_midnight() {
+    let text = |countdown: Option<Countdown>| countdown.map(|countdown| countdown.text);
+
+    // Late game on New Year's Eve, in UTC
+    let left = countdown("2027-01-01T00:10:00+00:00", "2026-12-31T23:50:00Z").unwrap();
+    assert_eq!((left.seconds, left.text.as_str()), (1200, "20:00"));
+
+    // Past midnight at the venue, still the day before in UTC
+    assert_eq!(text(countdown("2026-10-17T00:30:00+01:00", "2026-10-16T22:45:30Z")), Some("44:30".to_string()));
+    // Before midnight at the venue, already the next day in UTC
+    assert_eq!(text(countdown("2026-10-16T23:15-03:00", "2026-10-17T01:00Z")), Some("1:15:00".to_string()));
+    // Days ahead, hours keep counting
+    assert_eq!(text(countdown("2026-10-18T20:00Z", "2026-10-16T18:59:59Z")), Some("49:00:01".to_string()));
+
+    // Rounded up to the second, gone at the tip-off
+    assert_eq!(tip_off("2026-10-16T20:00Z").countdown(at("2026-10-16T20:00Z") - 1).unwrap().text, "00:01");
+    assert_eq!(countdown("2026-10-16T20:00Z", "2026-10-16T20:00Z"), None);
+    assert_eq!(countdown("2026-10-16T20:00Z", "2026-10-17T00:00Z"), None);
+    assert_eq!(MatchInfo::default().countdown(0), None);
+}
+
+#[test]
+fn match_details_survive_frames_and_are_saved() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("match_info");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("match.json");
+
+    let mut info = MatchInfo {
+        competition: "Liga Betclic".to_string(),
+        venue: "Pavilhão do Funchal".to_string(),
+        tip_off: Some("2026-03-14T20:30:00+01:00".to_string()),
+        ..MatchInfo::default()
+    };
+    info.validate().unwrap();
+    assert_eq!(MatchInfo::load(&file).unwrap(), None);
+    info.save(&file).unwrap();
+    assert_eq!(MatchInfo::load(&file).unwrap(), Some(info.clone()));
+    fs::write(&file, r#"{"tipOff": "tomorrow"}"#).unwrap();
+    assert!(MatchInfo::load(&file).is_err());
+
+    let server = BasketballServer::new(ServerConfig::default());
+    let shared = server.shared();
+    shared.set_match(info.clone());
+    let mut frame = shared.current();
+    frame.match_info = MatchInfo::default();
+    frame.home_score = "  2".to_string();
+    shared.update(frame);
+    assert_eq!(shared.current().match_info, info, "the console never changes them");
+}
+
+#[test]
+fn match_file_option() {
+    let args = |line: &str| line.split_whitespace().map(String::from).collect::<Vec<_>>();
+    assert_eq!(ServerConfig::from_args(&[]).unwrap().match_file, Path::new("match.json"));
+    assert_eq!(ServerConfig::from_args(&args("--match-file /srv/court-2/match.json")).unwrap().match_file, Path::new("/srv/court-2/match.json"));
+}
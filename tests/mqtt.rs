@@ -0,0 +1,304 @@
+//! MQTT publishing against an in-process broker that records what it receives.
+#![cfg(feature = "mqtt")]
+
+mod common;
+
+use common::Harness;
+use scoreboard_rust::{MqttConfig, ServerConfig};
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, Arc},
+    thread,
+    time::Duration,
+};
+
+/// A packet read by the broker: its first byte and body.
+fn read_packet(stream: &mut TcpStream) -> Option<(u8, Vec<u8>)> {
+    let mut byte = [0; 1];
+    stream.read_exact(&mut byte).ok()?;
+    let first = byte[0];
+    let (mut length, mut shift) = (0usize, 0);
+    loop {
+        stream.read_exact(&mut byte).ok()?;
+        length |= ((byte[0] & 0x7F) as usize) << shift;
+        shift += 7;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+    }
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body).ok()?;
+    Some((first, body))
+}
+
+/// An MQTT string at `at`, and the offset after it.
+fn read_str(body: &[u8], at: usize) -> (String, usize) {
+    let length = u16::from_be_bytes([body[at], body[at + 1]]) as usize;
+    (String::from_utf8(body[at + 2..at + 2 + length].to_vec()).unwrap(), at + 2 + length)
+}
+
+#[derive(Debug)]
+enum Received {
+    /// Client id, will topic, will message and the connect flags.
+    Connect { client_id: String, will_topic: String, will: String, flags: u8 },
+    Publish { topic: String, payload: String, retain: bool, qos: u8 },
+}
+
+/// Accept clients one after the other, answer CONNACK, PUBACK and PINGREQ, and report every
+/// CONNECT and PUBLISH. With `hang_up_after_state`, the first connection is closed once a state
+/// has been published on it.
+fn start_broker_with(hang_up_after_state: bool) -> (u16, mpsc::Receiver<Received>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut hang_up = hang_up_after_state;
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            while let Some((first, body)) = read_packet(&mut stream) {
+                match first >> 4 {
+                    1 => {
+                        let (protocol, at) = read_str(&body, 0);
+                        assert_eq!((protocol.as_str(), body[at]), ("MQTT", 4));
+                        let flags = body[at + 1];
+                        let (client_id, at) = read_str(&body, at + 4);
+                        let (will_topic, at) = read_str(&body, at);
+                        let (will, _) = read_str(&body, at);
+                        let _ = tx.send(Received::Connect { client_id, will_topic, will, flags });
+                        stream.write_all(&[0x20, 2, 0, 0]).unwrap();
+                    }
+                    3 => {
+                        let qos = (first >> 1) & 3;
+                        let (topic, mut at) = read_str(&body, 0);
+                        if qos > 0 {
+                            stream.write_all(&[0x40, 2, body[at], body[at + 1]]).unwrap();
+                            at += 2;
+                        }
+                        let payload = String::from_utf8(body[at..].to_vec()).unwrap();
+                        let state = topic.ends_with("/state");
+                        let _ = tx.send(Received::Publish { topic, payload, retain: first & 1 == 1, qos });
+                        if state && hang_up {
+                            hang_up = false;
+                            break;
+                        }
+                    }
+                    12 => stream.write_all(&[0xD0, 0]).unwrap(),
+                    _ => {}
+                }
+            }
+        }
+    });
+    (port, rx)
+}
+
+fn start_broker() -> (u16, mpsc::Receiver<Received>) {
+    start_broker_with(false)
+}
+
+fn start_server(broker_port: u16, qos: u8) -> Harness {
+    let mut config = Harness::config("--court-id court-2");
+    config.mqtt = Some(MqttConfig { host: "127.0.0.1".to_string(), port: broker_port, qos, discovery: None });
+    Harness::with_config(config)
+}
+
+/// The next PUBLISH, skipping those on other topics.
+fn next_publish(rx: &mpsc::Receiver<Received>, wanted: &str) -> (String, bool, u8) {
+    loop {
+        match rx.recv_timeout(Duration::from_secs(5)).expect("publish from the bridge") {
+            Received::Publish { topic, payload, retain, qos } if topic == wanted => return (payload, retain, qos),
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn publishes_the_retained_state_and_events_under_the_court() {
+    let (port, rx) = start_broker();
+    let server = start_server(port, 0);
+    let shared = &server.shared;
+
+    match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+        Received::Connect { client_id, will_topic, will, flags } => {
+            assert_eq!(client_id, "scoreboard-court-2");
+            assert_eq!((will_topic.as_str(), will.as_str()), ("scoreboard/court-2/status", "offline"));
+            assert_eq!(flags & 0x24, 0x24, "will flag and will retain set: {:#x}", flags);
+        }
+        other => panic!("expected CONNECT first, got {:?}", other),
+    }
+    assert_eq!(next_publish(&rx, "scoreboard/court-2/status"), ("online".to_string(), true, 0));
+
+    shared
+        .set_manual(|state| {
+            state.home_score = " 12".to_string();
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+    let (payload, retain, qos) = next_publish(&rx, "scoreboard/court-2/state");
+    assert!(retain, "the state is retained");
+    assert_eq!(qos, 0);
+    let state: serde_json::Value = serde_json::from_str(&payload).unwrap();
+    assert_eq!(state["homeScore"], 12);
+
+    let (payload, retain, _) = next_publish(&rx, "scoreboard/court-2/events");
+    assert!(!retain, "events are not retained");
+    let event: serde_json::Value = serde_json::from_str(&payload).unwrap();
+    assert_eq!(event["type"], "ScoreChange");
+    assert_eq!(event["to"], 12);
+    assert!(event["id"].as_u64().is_some());
+}
+
+#[test]
+fn qos_1_publishes_wait_for_the_acknowledgement() {
+    let (port, rx) = start_broker();
+    let server = start_server(port, 1);
+    let shared = &server.shared;
+
+    assert_eq!(next_publish(&rx, "scoreboard/court-2/status"), ("online".to_string(), true, 1));
+    for score in [2, 4] {
+        shared
+            .set_manual(|state| {
+                state.away_score = format!("{:3}", score);
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+        let (payload, _, qos) = next_publish(&rx, "scoreboard/court-2/state");
+        assert_eq!(qos, 1);
+        let state: serde_json::Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(state["awayScore"], score);
+    }
+}
+
+#[test]
+fn reconnects_and_sends_the_latest_state_again() {
+    let (port, rx) = start_broker_with(true);
+    let server = start_server(port, 0);
+
+    assert_eq!(next_publish(&rx, "scoreboard/court-2/status"), ("online".to_string(), true, 0));
+    server
+        .shared
+        .set_manual(|state| {
+            state.home_score = "  7".to_string();
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+    next_publish(&rx, "scoreboard/court-2/state");
+
+    // The broker hung up: the bridge connects again, goes online and republishes the state
+    while !matches!(rx.recv_timeout(Duration::from_secs(5)).expect("a second connection"), Received::Connect { .. }) {}
+    assert_eq!(next_publish(&rx, "scoreboard/court-2/status"), ("online".to_string(), true, 0));
+    let (payload, retain, _) = next_publish(&rx, "scoreboard/court-2/state");
+    assert!(retain);
+    let state: serde_json::Value = serde_json::from_str(&payload).unwrap();
+    assert_eq!(state["homeScore"], 7);
+}
+
+/// Options of Home Assistant's MQTT sensor and of its device, as documented, that the
+/// discovery configs may use.
+const SENSOR_OPTIONS: &[&str] = &[
+    "name",
+    "unique_id",
+    "state_topic",
+    "value_template",
+    "availability_topic",
+    "payload_available",
+    "payload_not_available",
+    "icon",
+    "device",
+    "origin",
+    "state_class",
+    "device_class",
+    "options",
+];
+const DEVICE_OPTIONS: &[&str] = &["identifiers", "name", "manufacturer", "model", "sw_version"];
+
+/// Check a sensor config against the schema and return it.
+fn sensor_config(payload: &str) -> serde_json::Value {
+    let config: serde_json::Value = serde_json::from_str(payload).unwrap();
+    for key in config.as_object().unwrap().keys() {
+        assert!(SENSOR_OPTIONS.contains(&key.as_str()), "unknown sensor option {}", key);
+    }
+    for key in config["device"].as_object().unwrap().keys() {
+        assert!(DEVICE_OPTIONS.contains(&key.as_str()), "unknown device option {}", key);
+    }
+    for required in ["name", "unique_id", "state_topic", "value_template", "availability_topic"] {
+        assert!(config[required].is_string(), "{} is a string", required);
+    }
+    assert!(!config["device"]["identifiers"].as_array().unwrap().is_empty());
+    assert_eq!(config["state_topic"], "scoreboard/court-2/state");
+    assert_eq!(config["availability_topic"], "scoreboard/court-2/status");
+    assert_eq!((&config["payload_available"], &config["payload_not_available"]), (&"online".into(), &"offline".into()));
+    assert_eq!(config["device"]["identifiers"], serde_json::json!(["scoreboard_court-2"]));
+    config
+}
+
+#[test]
+fn home_assistant_discovery_announces_the_sensors_and_removes_them_on_shutdown() {
+    let (port, rx) = start_broker();
+    let config = Harness::config(&format!("--court-id court-2 --mqtt-broker 127.0.0.1:{} --mqtt-discovery", port));
+    assert_eq!(config.mqtt.as_ref().unwrap().discovery.as_deref(), Some("homeassistant"));
+    let server = Harness::with_config(config);
+    let shared = Arc::clone(&server.shared);
+
+    // The configs come first, retained, then the bridge goes online
+    let mut configs = Vec::new();
+    loop {
+        match rx.recv_timeout(Duration::from_secs(5)).expect("publish from the bridge") {
+            Received::Publish { topic, payload, retain, .. } if topic.starts_with("homeassistant/") => {
+                assert!(retain, "discovery configs are retained");
+                configs.push((topic, payload));
+            }
+            Received::Publish { topic, payload, .. } if topic == "scoreboard/court-2/status" => {
+                assert_eq!(payload, "online");
+                break;
+            }
+            _ => {}
+        }
+    }
+    let topics: Vec<&str> = configs.iter().map(|(topic, _)| topic.as_str()).collect();
+    assert_eq!(
+        topics,
+        ["home_score", "away_score", "clock", "period", "game_state"].map(|field| format!("homeassistant/sensor/scoreboard_court-2_{}/config", field))
+    );
+
+    let home = sensor_config(&configs[0].1);
+    assert_eq!(home["unique_id"], "scoreboard_court-2_home_score");
+    assert_eq!(home["value_template"], "{{ value_json.homeScore }}");
+    assert_eq!(home["state_class"], "measurement");
+    let game_state = sensor_config(&configs[4].1);
+    assert_eq!(game_state["value_template"], "{{ value_json.gameState }}");
+    assert_eq!(game_state["device_class"], "enum");
+    assert_eq!(game_state["options"], serde_json::json!(["paused", "running"]));
+    for (_, payload) in &configs {
+        sensor_config(payload);
+    }
+
+    // A graceful shutdown empties the configs, which removes the sensors, then goes offline
+    server.stop();
+    assert!(shared.close_listeners(Duration::from_secs(5)));
+    let mut removed = Vec::new();
+    loop {
+        match rx.recv_timeout(Duration::from_secs(5)).expect("publish from the bridge") {
+            Received::Publish { topic, payload, retain, .. } if topic.starts_with("homeassistant/") => {
+                assert!(payload.is_empty() && retain, "an empty retained config removes the sensor");
+                removed.push(topic);
+            }
+            Received::Publish { topic, payload, .. } if topic == "scoreboard/court-2/status" => {
+                assert_eq!(payload, "offline");
+                break;
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(removed, topics);
+}
+
+#[test]
+fn discovery_options() {
+    let args = |line: &str| line.split_whitespace().map(String::from).collect::<Vec<_>>();
+    let config = ServerConfig::from_args(&args("--mqtt-broker broker --mqtt-discovery-prefix ha/discovery")).unwrap();
+    assert_eq!(config.mqtt.unwrap().discovery.as_deref(), Some("ha/discovery"));
+    assert_eq!(ServerConfig::from_args(&args("--mqtt-broker broker")).unwrap().mqtt.unwrap().discovery, None);
+    assert!(ServerConfig::from_args(&args("--mqtt-discovery")).is_err(), "needs a broker");
+    assert!(ServerConfig::from_args(&args("--mqtt-broker broker --mqtt-discovery-prefix ha/#")).is_err());
+}
@@ -0,0 +1,162 @@
+//! Two games played back to back through one server: `--auto-reset` and the manual new game.
+
+mod common;
+
+use common::{receive_post, simulate, wait_until, Harness};
+use scoreboard_rust::{
+    game_archive::GameArchive,
+    HttpUrl, PeriodScore, ServerConfig, SharedState,
+};
+use std::{
+    fs,
+    net::TcpListener,
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+/// Start a server with `config`, acknowledging every frame.
+fn start_server(mut config: ServerConfig) -> Harness {
+    config.ack = true;
+    Harness::with_config(config)
+}
+
+// Two quarters, 5-3 then 3-2; the clock stops at the end and nobody sets `Final`
+const GAME: &str = "\
+- 0:00 start
+- 0:02 score home 2
+- 0:03 score away 3
+- 0:05 score home 3
+- 0:06 period
+- 0:07 start
+- 0:08 score away 2
+- 0:09 score home 3
+- 0:10 end
+";
+
+/// Play [`GAME`] from a console just reset: the simulator sends 0-0 in the first period first.
+fn play(target: &str) {
+    simulate(target, GAME);
+}
+
+/// Send one frame with the `send` fields in `fields` (`--home 0 --away 0`) and wait for its `ACK`.
+fn send(target: &str, fields: &str) {
+    let replies = common::send(target, fields).text();
+    assert!(replies.lines().next().is_some_and(|reply| reply.ends_with(": ACK")), "{}", replies);
+}
+
+/// The `event` column of the game log rows that started a game.
+fn new_game_rows(shared: &SharedState) -> Vec<String> {
+    shared.game_log().rows().filter(|row| row.event.contains("New game")).map(|row| row.event.clone()).collect()
+}
+
+#[test]
+fn a_console_reset_after_the_idle_time_starts_a_new_game() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("new_game");
+    let _ = fs::remove_dir_all(&dir);
+    let webhook = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut config = Harness::config("");
+    config.auto_reset = Some(Duration::from_secs(1));
+    config.games_dir = Some(dir.clone());
+    config.summary_webhook = Some(HttpUrl::parse(&format!("http://127.0.0.1:{}/summary", webhook.local_addr().unwrap().port())).unwrap());
+    let server = start_server(config);
+    let (target, shared) = (server.target(), &server.shared);
+
+    play(&target);
+    assert!(new_game_rows(shared).is_empty());
+    let first_rows = shared.game_log().rows().count();
+    assert!(first_rows > 0);
+
+    // The clock has stopped for longer than --auto-reset: the console reset starts game 2
+    thread::sleep(Duration::from_millis(1200));
+    play(&target);
+
+    // Game 1 is closed out as it ended, without a Final period
+    let summary: serde_json::Value = serde_json::from_str(&receive_post(&webhook, "204 No Content").1).unwrap();
+    assert_eq!((summary["homeScore"].as_u64(), summary["awayScore"].as_u64()), (Some(8), Some(5)));
+    assert_eq!(summary["lineScore"][0]["period"], "1 Quarter");
+
+    let rows: Vec<_> = shared.game_log().rows().cloned().collect();
+    assert!(rows[0].event.starts_with("New game (idle)"), "{}", rows[0].event);
+    assert_eq!(new_game_rows(shared).len(), 1);
+    let last = rows.last().unwrap();
+    assert_eq!((last.home_score.as_str(), last.away_score.as_str()), ("8", "5"));
+    assert_eq!(
+        shared.current().line_score,
+        [PeriodScore { period: "1 Quarter".to_string(), home: Some(5), away: Some(3) }],
+        "only game 2's periods"
+    );
+
+    let archive = GameArchive::new(&dir);
+    wait_until("both games recorded", || archive.list().unwrap().len() == 2);
+    let games = archive.list().unwrap();
+    assert_eq!((games[0].home_score, games[0].away_score), (Some(8), Some(5)));
+    assert_eq!(archive.game(1).unwrap().unwrap().end_reason.as_deref(), Some("reset"));
+    assert_eq!(games[1].ended_at_ms, None);
+}
+
+#[test]
+fn the_period_going_back_to_the_first_is_enough_to_start_a_new_game() {
+    let webhook = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut config = Harness::config("");
+    config.auto_reset = Some(Duration::from_secs(1));
+    config.summary_webhook = Some(HttpUrl::parse(&format!("http://127.0.0.1:{}/summary", webhook.local_addr().unwrap().port())).unwrap());
+    let server = start_server(config);
+    let (target, shared) = (server.target(), &server.shared);
+    play(&target);
+    thread::sleep(Duration::from_millis(1200));
+
+    // This console resets the clock and period first, the scores still 8-5
+    send(&target, "--time 10:00 --period 1 --state paused");
+    let summary: serde_json::Value = serde_json::from_str(&receive_post(&webhook, "204 No Content").1).unwrap();
+    assert_eq!((summary["homeScore"].as_u64(), summary["awayScore"].as_u64()), (Some(8), Some(5)));
+    let rows = new_game_rows(shared);
+    assert_eq!(rows.len(), 1);
+    assert!(rows[0].starts_with("New game (idle)"), "{}", rows[0]);
+
+    // Its scores follow into the new game without starting another
+    send(&target, "--home 0 --away 0");
+    assert_eq!(new_game_rows(shared).len(), 1);
+    assert_eq!(shared.current().home_score.trim(), "0");
+}
+
+#[test]
+fn a_frame_clearing_nothing_after_the_idle_time_keeps_the_game() {
+    let mut config = Harness::config("");
+    config.auto_reset = Some(Duration::from_secs(1));
+    let server = start_server(config);
+    let (target, shared) = (server.target(), &server.shared);
+    play(&target);
+    thread::sleep(Duration::from_millis(1200));
+
+    send(&target, "--fouls 1,2");
+    send(&target, "--home 9 --away 5");
+    assert!(new_game_rows(shared).is_empty());
+}
+
+#[test]
+fn games_in_play_are_only_reset_by_hand() {
+    let mut config = Harness::config("");
+    config.auto_reset = Some(Duration::from_secs(60));
+    let server = start_server(config);
+    let (target, shared) = (server.target(), &server.shared);
+
+    // Played again right away: not idle, so it carries on as the same game
+    play(&target);
+    play(&target);
+    assert!(new_game_rows(shared).is_empty());
+
+    shared.new_game();
+    let rows: Vec<_> = shared.game_log().rows().cloned().collect();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].event, "New game (manual)");
+    assert!(shared.current().line_score.is_empty());
+}
+
+#[test]
+fn auto_reset_option() {
+    let args = |line: &str| line.split_whitespace().map(String::from).collect::<Vec<_>>();
+    assert_eq!(ServerConfig::from_args(&args("--auto-reset 600")).unwrap().auto_reset, Some(Duration::from_secs(600)));
+    assert_eq!(ServerConfig::from_args(&[]).unwrap().auto_reset, None);
+    assert!(ServerConfig::from_args(&args("--auto-reset 0")).is_err());
+}
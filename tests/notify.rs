@@ -0,0 +1,111 @@
+//! Slack and Discord notifications: payloads against golden files, options, and delivery.
+
+mod common;
+
+use common::{receive_post, wait_until, Harness};
+use scoreboard_rust::{
+    notify::{self, Message, Service, Templates},
+    GameEvent, GameState, HttpUrl, MatchInfo, NotifyTarget, ServerConfig, TeamInfo, Teams,
+};
+use std::{
+    net::TcpListener,
+    time::Duration,
+};
+
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(String::from).collect()
+}
+
+fn final_state() -> GameState {
+    let mut state = GameState::default();
+    state.home_score = " 80".to_string();
+    state.away_score = " 74".to_string();
+    state.period_name = "Final".to_string();
+    state.time = "00:00".to_string();
+    state.teams = Teams {
+        home: TeamInfo { name: "Madeira".to_string(), abbrev: "CAB".to_string(), color: Some("#1F4E9C".to_string()), ..TeamInfo::default() },
+        away: TeamInfo { name: "Benfica & Friends".to_string(), abbrev: "SLB".to_string(), color: Some("#E30613".to_string()), ..TeamInfo::default() },
+    };
+    state.match_info = MatchInfo { competition: "Liga Betclic".to_string(), round: "Round 12".to_string(), ..MatchInfo::default() };
+    state
+}
+
+fn payload(service: Service) -> String {
+    let message = Message::new(notify::FINAL, None, &final_state(), Some("court-2"), &Templates::default());
+    serde_json::to_string_pretty(&message.payload(service)).unwrap() + "\n"
+}
+
+#[test]
+fn final_payloads_match_the_golden_files() {
+    assert_eq!(payload(Service::Slack), include_str!("golden/notify_final.slack.json"));
+    assert_eq!(payload(Service::Discord), include_str!("golden/notify_final.discord.json"));
+}
+
+#[test]
+fn templates_fill_in_the_placeholders() {
+    let templates = Templates::new(&[(notify::FINAL, "{home_abbrev} {home_score}-{away_score} {away_abbrev} on {court}, {round}".to_string())]);
+    let message = Message::new(notify::FINAL, None, &final_state(), Some("court-2"), &templates);
+    assert_eq!(message.text, "CAB 80-74 SLB on court-2, Round 12");
+    assert_eq!(message.title, "Final");
+    assert_eq!(message.footer, "Final · court-2");
+
+    let mut state = final_state();
+    state.period_name = "2 Quarter".to_string();
+    state.teams = Teams::default();
+    state.match_info = MatchInfo::default();
+    let event = GameEvent::PeriodEnded { period: "2 Quarter".to_string() };
+    let message = Message::new("PeriodEnded", Some(&event), &state, None, &Templates::default());
+    assert_eq!(message.text, "End of 2 Quarter: Home 80 - 74 Away");
+    assert_eq!((message.title.as_str(), message.footer.as_str()), ("Period ended", "2 Quarter · 00:00"));
+    assert_eq!(message.context, "");
+}
+
+#[test]
+fn notify_options() {
+    let config = ServerConfig::from_args(&args("--discord-webhook http://127.0.0.1:9/hook")).unwrap();
+    assert_eq!(config.notify.events, ["PeriodEnded", "Final"]);
+    assert_eq!(config.notify.min_interval, Duration::from_secs(5));
+
+    let mut options = args("--slack-webhook http://hooks.local/T1 --notify-events final,timeoutcalled --notify-min-interval 30");
+    options.extend(["--notify-template".to_string(), "Final=Full time: {home_name} {home_score}".to_string()]);
+    let config = ServerConfig::from_args(&options).unwrap();
+    assert_eq!(config.notify.targets, [NotifyTarget { service: Service::Slack, url: HttpUrl::parse("http://hooks.local/T1").unwrap() }]);
+    assert_eq!(config.notify.events, ["Final", "TimeoutCalled"]);
+    assert_eq!(config.notify.templates, [("Final", "Full time: {home_name} {home_score}".to_string())]);
+    assert_eq!(config.notify.min_interval, Duration::from_secs(30));
+
+    for line in [
+        "--notify-events Final",
+        "--slack-webhook http://h/x --notify-events ClockChange",
+        "--slack-webhook http://h/x --notify-template Final={home_points}",
+        "--slack-webhook http://h/x --notify-template Final={home_name",
+        "--slack-webhook ftp://h/x",
+    ] {
+        assert!(ServerConfig::from_args(&args(line)).is_err(), "{}", line);
+    }
+}
+
+#[test]
+fn final_is_posted_and_retried() {
+    let webhook = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://127.0.0.1:{}/api/webhooks/1/abc", webhook.local_addr().unwrap().port());
+    let server = Harness::start(&format!("--discord-webhook {}", url));
+    let shared = &server.shared;
+
+    shared
+        .set_manual(|state| {
+            state.period_name = "Final".to_string();
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+    // The first attempt fails, the retry a second later gets through
+    let first = receive_post(&webhook, "503 Service Unavailable").1;
+    let second = receive_post(&webhook, "204 No Content").1;
+    assert_eq!(first, second);
+    let body: serde_json::Value = serde_json::from_str(&second).unwrap();
+    assert_eq!(body["embeds"][0]["title"], "Final");
+
+    wait_until("the count of the notification", || shared.status().metrics.notifications_sent > 0);
+    let metrics = shared.status().metrics;
+    assert_eq!((metrics.notification_retries, metrics.notifications_failed, metrics.notifications_dropped), (1, 0, 0));
+}
@@ -0,0 +1,53 @@
+//! What the overlay pages serve for OBS browser sources: no page background of their own, no
+//! shadow over a chroma key (`?bg=chroma`), and the `?scale=` the script applies.
+
+mod common;
+
+use common::Harness;
+
+const STYLESHEETS: [&str; 5] = ["/overlay.css", "/themes/minimal.css", "/themes/broadcast.css", "/bug.css", "/slate.css"];
+
+/// The declarations of the first rule for exactly `selector` in `css`.
+fn rule<'a>(css: &'a str, selector: &str) -> Option<&'a str> {
+    let start = css.find(&format!("\n{} {{", selector))?;
+    let body = &css[start..];
+    Some(&body[body.find('{')? + 1..body.find('}')?])
+}
+
+#[test]
+fn pages_leave_the_background_to_the_browser_source() {
+    let server = Harness::start("");
+    for path in STYLESHEETS {
+        let css = server.get(path);
+        assert_eq!((css.status, css.header("content-type")), (200, Some("text/css; charset=utf-8")), "{}", path);
+        let css = css.text();
+        let body = rule(&css, "body").unwrap_or_else(|| panic!("{} has no body rule", path));
+        assert!(body.contains("background: transparent;"), "{}: {}", path, body);
+    }
+}
+
+#[test]
+fn shadows_are_dropped_over_a_chroma_key() {
+    let server = Harness::start("");
+    for path in STYLESHEETS {
+        let css = server.get(path).text();
+        if css.contains("text-shadow: ") {
+            let reset = rule(&css, "body.chroma *").unwrap_or_else(|| panic!("{} keeps its shadows over the key", path));
+            assert!(reset.contains("text-shadow: none !important;") && reset.contains("box-shadow: none !important;"), "{}", path);
+        }
+        assert!(css.contains("scale(var(--scale, 1))") || css.contains("* var(--scale, 1)"), "{} ignores ?scale=", path);
+    }
+}
+
+#[test]
+fn the_page_script_reads_bg_color_and_scale() {
+    let server = Harness::start("");
+    let script = server.get("/common.js");
+    assert_eq!(script.status, 200);
+    let script = script.text();
+    assert!(script.contains("setBackground(urlParams.get('bg'), urlParams.get('color'));"));
+    assert!(script.contains("parseFloat(urlParams.get('scale'))"));
+    for page in ["/", "/?theme=minimal", "/?theme=broadcast", "/bug", "/slate"] {
+        assert!(server.get(&format!("{}{}bg=chroma&color=00FF00&scale=1.5", page, if page.contains('?') { "&" } else { "?" })).text().contains("<script src=\"common.js\"></script>"), "{}", page);
+    }
+}
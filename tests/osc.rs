@@ -0,0 +1,104 @@
+//! The OSC encoder against known-good packets, the address map, and messages sent over UDP.
+
+mod common;
+
+use common::Harness;
+use scoreboard_rust::osc::{self, AddressMap, OscArg};
+use std::{net::UdpSocket, time::Duration};
+
+#[test]
+fn encodes_the_spec_examples() {
+    // OSC 1.0 specification, "Examples"
+    let packet = osc::encode("/oscillator/4/frequency", &[OscArg::Float(440.0)]);
+    assert_eq!(
+        packet,
+        [
+            0x2f, 0x6f, 0x73, 0x63, 0x69, 0x6c, 0x6c, 0x61, 0x74, 0x6f, 0x72, 0x2f, 0x34, 0x2f, 0x66, 0x72, 0x65, 0x71,
+            0x75, 0x65, 0x6e, 0x63, 0x79, 0x00, 0x2c, 0x66, 0x00, 0x00, 0x43, 0xdc, 0x00, 0x00,
+        ]
+    );
+
+    let packet = osc::encode(
+        "/foo",
+        &[
+            OscArg::Int(1000),
+            OscArg::Int(-1),
+            OscArg::Str("hello".to_string()),
+            OscArg::Float(1.234),
+            OscArg::Float(5.678),
+        ],
+    );
+    assert_eq!(
+        packet,
+        [
+            0x2f, 0x66, 0x6f, 0x6f, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x69, 0x69, 0x73, 0x66, 0x66, 0x00, 0x00, 0x00, 0x00,
+            0x03, 0xe8, 0xff, 0xff, 0xff, 0xff, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x00, 0x00, 0x00, 0x3f, 0x9d, 0xf3, 0xb6,
+            0x40, 0xb5, 0xb2, 0x2d,
+        ]
+    );
+}
+
+#[test]
+fn strings_always_get_a_null_and_padding() {
+    // A 4-byte string needs a whole word of nulls; no arguments is still a "," type tag
+    assert_eq!(osc::encode("/abc", &[]), b"/abc\0\0\0\0,\0\0\0");
+    assert_eq!(
+        osc::encode("/scoreboard/clock", &[OscArg::Str("9:58".to_string())]),
+        b"/scoreboard/clock\0\0\0,s\0\09:58\0\0\0\0"
+    );
+    assert_eq!(
+        osc::encode("/scoreboard/home/score", &[OscArg::Int(42)]),
+        b"/scoreboard/home/score\0\0,i\0\0\0\0\0\x2a"
+    );
+}
+
+#[test]
+fn address_map_overrides_and_turns_off_keys() {
+    let map = AddressMap::parse("# cues of the lighting desk\nhome.score /eos/cue/1/12/fire\nclock off\n", "cues.map").unwrap();
+    assert_eq!(map.address("home.score"), Some("/eos/cue/1/12/fire"));
+    assert_eq!(map.address("clock"), None);
+    assert_eq!(map.address("event.period_end"), Some("/scoreboard/event/period_end"));
+
+    for (text, message) in [
+        ("home.points /x", "Unknown OSC key home.points in cues.map line 1"),
+        ("\nclock cue", "Invalid OSC address \"cue\" in cues.map line 2"),
+        ("clock", "Expected a key and an OSC address in cues.map line 1"),
+    ] {
+        let error = AddressMap::parse(text, "cues.map").expect_err(text);
+        assert!(error.contains(message), "{}: {}", text, error);
+    }
+}
+
+#[test]
+fn score_changes_are_sent_to_the_targets() {
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    receiver.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let mut config = Harness::config("");
+    config.osc_targets = vec![receiver.local_addr().unwrap()];
+    let server = Harness::with_config(config);
+    let shared = &server.shared;
+
+    let mut buf = [0; 512];
+    let mut received = Vec::new();
+    // The first score only sets the value; points scored after it also fire the trigger
+    for (score, messages) in [("  0", 1), ("  2", 2)] {
+        shared
+            .set_manual(|state| {
+                state.home_score = score.to_string();
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+        for _ in 0..messages {
+            let (len, _) = receiver.recv_from(&mut buf).unwrap();
+            received.push(buf[..len].to_vec());
+        }
+    }
+    assert_eq!(
+        received,
+        [
+            osc::encode("/scoreboard/home/score", &[OscArg::Int(0)]),
+            osc::encode("/scoreboard/home/score", &[OscArg::Int(2)]),
+            osc::encode("/scoreboard/event/home_score", &[]),
+        ]
+    );
+}
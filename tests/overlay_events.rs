@@ -0,0 +1,74 @@
+//! End-of-period and horn events in the state's `events`: one entry per occurrence, with its
+//! own id, carried by every state until it expires.
+
+mod common;
+
+use common::{clock_at, scores, Harness, WebSocket};
+use scoreboard_rust::ProtocolFrame;
+use serde_json::Value;
+
+/// The game clock stopped at `mmss` of the 2nd period, with the horn sounding or not.
+fn horn(mmss: &str, sounding: bool) -> ProtocolFrame {
+    let status = if sounding { 0x86 } else { 0x82 };
+    ProtocolFrame::new(0x7F, b'G', [b"18".as_slice(), &[status], b"5", mmss.as_bytes(), b"221  2 "].concat())
+}
+
+/// `(type, id)` of each event in the current `/api/state`.
+fn events(server: &Harness) -> Vec<(String, u64)> {
+    let (_, body) = server.get_json("/api/state");
+    body["state"]["events"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|event| (event["type"].as_str().unwrap().to_string(), event["id"].as_u64().unwrap()))
+        .collect()
+}
+
+#[test]
+fn each_occurrence_is_in_the_state_exactly_once() {
+    let mut server = Harness::start("");
+    server.play(&[clock_at("0001", true)]);
+    assert_eq!(events(&server), []);
+
+    server.play(&[clock_at("0000", false)]);
+    let ended = events(&server);
+    assert_eq!(ended.len(), 1);
+    assert_eq!(ended[0].0, "PeriodEnded");
+    let (_, body) = server.get_json("/api/state");
+    assert!(body["state"]["events"][0]["period"].is_string());
+    assert!(body["state"]["events"][0]["expiresAtMs"].as_u64().is_some());
+
+    // Later states carry it along without adding it again
+    server.play(&[scores(40, 38), scores(42, 38)]);
+    assert_eq!(events(&server), ended);
+
+    // The horn is one event however many frames it sounds over
+    server.play(&[horn("0000", true), scores(44, 38)]);
+    server.send_frames(&[horn("0000", true)]);
+    server.play(&[scores(46, 38)]);
+    let with_horn = events(&server);
+    assert_eq!(with_horn.len(), 2);
+    assert_eq!(with_horn[0], ended[0]);
+    assert_eq!(with_horn[1].0, "Horn");
+    assert!(with_horn[1].1 > ended[0].1);
+
+    // Sounding again is another occurrence
+    server.play(&[horn("0000", false), horn("0000", true)]);
+    let again = events(&server);
+    assert_eq!(again.iter().map(|(kind, _)| kind.as_str()).collect::<Vec<_>>(), ["PeriodEnded", "Horn", "Horn"]);
+    assert!(again[2].1 > again[1].1);
+}
+
+#[test]
+fn pushed_states_carry_the_same_event() {
+    let mut server = Harness::start("");
+    server.play(&[clock_at("0001", true)]);
+    let mut client = WebSocket::open(server.web, "/ws", &[]);
+    client.next_text();
+
+    server.play(&[clock_at("0000", false), scores(2, 0)]);
+    let ids = |state: &Value| -> Vec<u64> { state["events"].as_array().unwrap().iter().map(|event| event["id"].as_u64().unwrap()).collect() };
+    let first = ids(&client.next_text());
+    assert_eq!(first.len(), 1);
+    assert_eq!(ids(&client.next_text()), first);
+}
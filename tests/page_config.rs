@@ -0,0 +1,83 @@
+//! The settings written into every served page as `window.SCOREBOARD_CONFIG`: taken from the
+//! server's config and the request, with the API base from a plain `X-Forwarded-Prefix` only.
+
+mod common;
+
+use common::Harness;
+use scoreboard_rust::{forwarded_prefix, Lang, PageConfig};
+use serde_json::{json, Value};
+
+/// The JSON block `inject` wrote into `html`.
+fn injected(html: &str) -> Value {
+    let start = html.find("id=\"scoreboard-config\">").expect("a page config") + 23;
+    let end = start + html[start..].find("</script>").unwrap();
+    serde_json::from_str(&html[start..end]).unwrap()
+}
+
+fn config(api_base: &str) -> PageConfig {
+    PageConfig { api_base: api_base.to_string(), poll_ms: 1000, ws_enabled: true, lang: Lang::En, swap_sides: false }
+}
+
+#[test]
+fn the_config_goes_before_the_end_of_the_head() {
+    let html = "<html><head><title>Overlay</title></HEAD><body></body></html>";
+    let page = String::from_utf8(config("/scoreboard").inject(html.as_bytes()).unwrap()).unwrap();
+    assert!(page.starts_with("<html><head><title>Overlay</title><script type=\"application/json\" id=\"scoreboard-config\">"));
+    assert!(page.ends_with("</script>\n</HEAD><body></body></html>"));
+    assert_eq!(injected(&page), json!({ "apiBase": "/scoreboard", "pollMs": 1000, "wsEnabled": true, "lang": "en", "swapSides": false }));
+
+    // Each value as it is set
+    let other = PageConfig { poll_ms: 250, ws_enabled: false, lang: Lang::Pt, swap_sides: true, ..config("") };
+    let page = String::from_utf8(other.inject(html.as_bytes()).unwrap()).unwrap();
+    assert_eq!(injected(&page), json!({ "apiBase": "", "pollMs": 250, "wsEnabled": false, "lang": "pt", "swapSides": true }));
+
+    // Nothing can close the element, and a page without a head gets nothing
+    let page = String::from_utf8(config("</script><script>alert(1)").inject(html.as_bytes()).unwrap()).unwrap();
+    assert_eq!(page.matches("</script>").count(), 1, "{}", page);
+    assert_eq!(injected(&page)["apiBase"], "</script><script>alert(1)");
+    assert_eq!(config("").inject(b"<html><body></body></html>"), None);
+}
+
+#[test]
+fn only_a_plain_path_is_taken_as_the_prefix() {
+    assert_eq!(forwarded_prefix("/scoreboard"), "/scoreboard");
+    assert_eq!(forwarded_prefix(" /venue/court-1/ "), "/venue/court-1");
+    assert_eq!(forwarded_prefix("/a_b.c~d"), "/a_b.c~d");
+    assert_eq!(forwarded_prefix("/"), "", "the root");
+    for rejected in ["scoreboard", "//evil.example", "https://evil.example/x", "/a/../admin", "/./x", "/a b", "/x\"><script>", "/x?y=1", "/x#y"] {
+        assert_eq!(forwarded_prefix(rejected), "", "{}", rejected);
+    }
+}
+
+/// The config of the page served for `path` with `headers`.
+fn page_config(server: &Harness, path: &str, headers: &[(&str, &str)]) -> Value {
+    let page = server.request("GET", path, headers, b"");
+    assert_eq!(page.status, 200, "{}", path);
+    injected(&page.text())
+}
+
+#[test]
+fn served_pages_follow_the_config_and_the_proxy() {
+    let server = Harness::start("");
+    let defaults = page_config(&server, "/", &[]);
+    assert_eq!(defaults, json!({ "apiBase": "", "pollMs": 1000, "wsEnabled": true, "lang": "en", "swapSides": false }));
+    for path in ["/bug", "/slate", "/?theme=minimal"] {
+        assert_eq!(page_config(&server, path, &[]), defaults, "{}", path);
+    }
+
+    // Behind a proxy, and not for a prefix that isn't a plain path
+    assert_eq!(page_config(&server, "/", &[("X-Forwarded-Prefix", "/scoreboard/")])["apiBase"], "/scoreboard");
+    assert_eq!(page_config(&server, "/bug", &[("X-Forwarded-Prefix", "/venue/court-1")])["apiBase"], "/venue/court-1");
+    for rejected in ["//evil.example", "/a/../admin", "scoreboard", "/x\"><script>"] {
+        assert_eq!(page_config(&server, "/", &[("X-Forwarded-Prefix", rejected)])["apiBase"], "", "{}", rejected);
+    }
+
+    let configured = Harness::start("--poll-interval 250 --no-websocket --lang pt --swap-sides");
+    assert_eq!(
+        page_config(&configured, "/", &[("X-Forwarded-Prefix", "/scoreboard")]),
+        json!({ "apiBase": "/scoreboard", "pollMs": 250, "wsEnabled": false, "lang": "pt", "swapSides": true })
+    );
+    // The request still picks its language and swap
+    let request = page_config(&configured, "/?swap=0", &[("Accept-Language", "fr")]);
+    assert_eq!((request["lang"].as_str(), request["swapSides"].as_bool()), (Some("fr"), Some(false)));
+}
@@ -0,0 +1,54 @@
+//! Panics injected into a listener and into an edit holding the state's lock: each is counted,
+//! and the server goes on publishing.
+
+mod common;
+
+use common::{scores, wait_until, Harness};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+#[test]
+fn a_panicking_listener_only_loses_its_own_update() {
+    let mut server = Harness::start("");
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&seen);
+    server.shared.add_listener(Duration::ZERO, move |update| {
+        if update.state.home_score.trim() == "13" {
+            panic!("injected at 13");
+        }
+        sink.lock().unwrap().push(update.state.home_score.trim().to_string());
+    });
+
+    server.play(&[scores(12, 9), scores(13, 9), scores(14, 9)]);
+    wait_until("the update after the panic", || seen.lock().unwrap().last().map(String::as_str) == Some("14"));
+    assert_eq!(*seen.lock().unwrap(), ["12", "14"]);
+    let metrics = server.shared.status().metrics;
+    assert_eq!(metrics.handler_panics, 1);
+    assert_eq!(metrics.last_panic.as_deref(), Some("update listener: injected at 13"));
+}
+
+#[test]
+fn a_panic_holding_the_state_lock_leaves_it_usable() {
+    let mut server = Harness::start("");
+    server.play(&[scores(12, 9)]);
+    let shared = Arc::clone(&server.shared);
+    let edit = thread::spawn(move || shared.set_manual(|_| -> Result<(), ()> { panic!("injected in an edit") }));
+    assert!(edit.join().is_err());
+
+    // The poisoned lock is taken over: reads, frames and edits all carry on
+    assert_eq!(server.shared.current().home_score.trim(), "12");
+    server.play(&[scores(15, 9)]);
+    assert_eq!(server.shared.current().home_score.trim(), "15");
+    server
+        .shared
+        .set_manual(|state| {
+            state.away_score = " 10".to_string();
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+    let (status, body) = server.get_json("/api/state");
+    assert_eq!((status, body["state"]["homeScore"].as_u64(), body["state"]["awayScore"].as_u64()), (200, Some(15), Some(10)));
+}
@@ -0,0 +1,117 @@
+//! Per-peer traffic totals: kept across a console's reconnects, served by `/api/status` and
+//! logged every `--peer-summary-interval`.
+
+mod common;
+
+use common::{command, scores, wait_until, Harness};
+use std::{
+    fmt,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    process::Stdio,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use tracing::{
+    field::{Field, Visit},
+    Event, Subscriber,
+};
+use tracing_subscriber::{layer::Context, layer::SubscriberExt, Layer};
+
+#[test]
+fn totals_add_up_over_two_connections_from_one_ip() {
+    let server = Harness::start("");
+    let mut corrupt = scores(50, 0).to_bytes();
+    *corrupt.last_mut().unwrap() ^= 0x01;
+    let first = [scores(12, 9).to_bytes(), b"xx".to_vec(), corrupt].concat();
+    let second = [scores(14, 9).to_bytes(), scores(16, 9).to_bytes()].concat();
+
+    for bytes in [&first, &second] {
+        let mut console = TcpStream::connect(server.ingest).unwrap();
+        console.write_all(bytes).unwrap();
+    }
+    wait_until("the second connection's frames", || server.shared.current().home_score.trim() == "16");
+    wait_until("both connections counted", || {
+        server.shared.status().metrics.peers.values().any(|peer| peer.connections == 2 && peer.bytes == (first.len() + second.len()) as u64)
+    });
+
+    let (_, status) = server.get_json("/api/status");
+    let peer = &status["metrics"]["peers"]["127.0.0.1"];
+    assert_eq!(peer["connections"], 2);
+    assert_eq!(peer["bytes"], first.len() + second.len());
+    assert_eq!((peer["frames"].as_u64(), peer["errors"].as_u64(), peer["resyncs"].as_u64()), (Some(3), Some(1), Some(1)));
+}
+
+#[test]
+fn the_totals_are_logged_at_the_interval() {
+    let free = || TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+    let (ingest, web) = (free(), free());
+    let mut child = command(&["dev", "--no-mdns", "--listen", &ingest, "--web-address", &web, "--peer-summary-interval", "1"])
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let (lines, log) = mpsc::channel();
+    let stderr = child.stderr.take().unwrap();
+    thread::spawn(move || {
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            let _ = lines.send(line);
+        }
+    });
+
+    let wait_for = |wanted: &str| loop {
+        let line = log.recv_timeout(Duration::from_secs(10)).unwrap_or_else(|_| panic!("no log line with {:?}", wanted));
+        if line.contains(wanted) {
+            return line;
+        }
+    };
+    wait_for("Waiting for connections");
+    let frame = scores(12, 9).to_bytes();
+    TcpStream::connect(&ingest).unwrap().write_all(&frame).unwrap();
+    // Logged again every second: one of the lines has the frame in
+    let summary = wait_for(&format!("peer 127.0.0.1: 1 frames, 0 errors, 0 resyncs (1 connections, {} bytes)", frame.len()));
+    assert!(summary.contains("INFO"), "{}", summary);
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+/// Keeps the message of every event logged in this process.
+#[derive(Clone, Default)]
+struct Messages(Arc<Mutex<Vec<String>>>);
+
+impl Visit for Messages {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0.lock().unwrap().push(format!("{:?}", value));
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for Messages {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        event.record(&mut self.clone());
+    }
+}
+
+impl Messages {
+    fn summaries(&self) -> usize {
+        self.0.lock().unwrap().iter().filter(|message| message.starts_with("peer ")).count()
+    }
+}
+
+#[test]
+fn the_summaries_stop_with_the_server() {
+    let messages = Messages::default();
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(messages.clone())).unwrap();
+    let mut config = Harness::config("");
+    config.peer_summary_interval = Duration::from_millis(200);
+    let mut server = Harness::with_config(config);
+    server.play(&[scores(12, 9)]);
+    wait_until("a summary line", || messages.summaries() > 0);
+
+    server.stop();
+    let logged = messages.summaries();
+    thread::sleep(Duration::from_millis(600));
+    assert_eq!(messages.summaries(), logged, "no summary after the shutdown");
+}
@@ -0,0 +1,109 @@
+//! Saving the state to `--state-file` and picking the game up again after a restart.
+
+mod common;
+
+use common::{fresh_dir, Harness};
+use scoreboard_rust::{GameState, SharedState};
+use std::{fs, path::Path, thread, time::Duration};
+
+fn start_server(state_file: &Path, max_age: Duration) -> Harness {
+    let mut config = Harness::config("");
+    config.state_file = Some(state_file.to_path_buf());
+    config.state_max_age = max_age;
+    Harness::with_config(config)
+}
+
+fn edit(shared: &SharedState, edit: impl FnOnce(&mut GameState)) {
+    shared
+        .set_manual(|state| {
+            edit(state);
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+}
+
+/// Half a game on a first server, which then shuts down and saves it.
+fn play_first_half(state_file: &Path) -> (GameState, u64, usize) {
+    let server = start_server(state_file, Duration::from_secs(60));
+    let shared = &server.shared;
+    edit(shared, |state| {
+        state.game_state = "running".to_string();
+        state.period_name = "1".to_string();
+        state.home_score = "0".to_string();
+        state.away_score = "0".to_string();
+        state.home_fouls = "0".to_string();
+        state.away_fouls = "0".to_string();
+    });
+    edit(shared, |state| state.home_score = "2".to_string());
+    // Away takes the lead: the first lead change
+    edit(shared, |state| state.away_score = "5".to_string());
+    edit(shared, |state| state.away_fouls = "3".to_string());
+
+    assert!(shared.close_listeners(Duration::from_secs(5)));
+    let (state, version) = shared.current_versioned();
+    (state, version, shared.game_log().rows().count())
+}
+
+#[test]
+fn a_restarted_server_carries_on_with_the_saved_game() {
+    let dir = fresh_dir("persist_restart");
+    let state_file = dir.join("state.json");
+    let (saved, version, rows) = play_first_half(&state_file);
+    assert!(state_file.exists(), "saved at shutdown");
+
+    let server = start_server(&state_file, Duration::from_secs(60));
+    let shared = &server.shared;
+    let restored = shared.current();
+    assert!(shared.restored());
+    assert!(restored.stale, "stale until the console sends a frame");
+    assert_eq!((restored.home_score.as_str(), restored.away_score.as_str()), ("2", "5"));
+    assert_eq!(restored.away_fouls, saved.away_fouls);
+    assert_eq!(shared.version(), version, "versions go on from the saved one");
+    assert_eq!(shared.game_log().rows().count(), rows);
+
+    // A console frame ends the restored spell
+    let mut frame = restored;
+    frame.stale = false;
+    shared.update(frame);
+    assert!(!shared.restored());
+    assert!(!shared.current().stale);
+
+    // The second lead change and the final, counted with the fouls of the first server
+    edit(shared, |state| state.home_score = "7".to_string());
+    edit(shared, |state| state.period_name = "Final".to_string());
+    let summary = shared.game_summary().expect("summary of the finished game");
+    assert_eq!(summary.lead_changes, 2);
+    assert_eq!(summary.away.fouls, 3);
+    assert_eq!(summary.away.largest_lead, 3);
+    assert_eq!(summary.home.largest_lead, 2);
+    assert!(shared.game_log().rows().count() > rows);
+}
+
+#[test]
+fn a_state_file_older_than_the_max_age_is_ignored() {
+    let dir = fresh_dir("persist_max_age");
+    let state_file = dir.join("state.json");
+    play_first_half(&state_file);
+    thread::sleep(Duration::from_millis(20));
+
+    let server = start_server(&state_file, Duration::ZERO);
+    let shared = &server.shared;
+    assert!(!shared.restored());
+    assert_eq!(shared.current(), GameState::default());
+    assert_eq!(shared.game_log().rows().count(), 0);
+}
+
+#[test]
+fn a_missing_or_broken_state_file_starts_afresh() {
+    let dir = fresh_dir("persist_broken");
+    let missing_server = start_server(&dir.join("missing.json"), Duration::from_secs(60));
+    let missing = &missing_server.shared;
+    assert!(!missing.restored());
+
+    let broken = dir.join("broken.json");
+    fs::write(&broken, "{\"savedAtMs\": ").unwrap();
+    let server = start_server(&broken, Duration::from_secs(60));
+    let shared = &server.shared;
+    assert!(!shared.restored());
+    assert_eq!(shared.version(), 0);
+}
@@ -0,0 +1,76 @@
+//! The reader behind `pipe:<name>` inputs, over a mock source that fails to open, breaks and
+//! ends: reads carry on with the next connection.
+
+use scoreboard_rust::pipe::ReconnectingReader;
+use std::{
+    collections::VecDeque,
+    io::{self, Read},
+    time::{Duration, Instant},
+};
+
+/// One connection of the mock source: the chunks its reads return, then how it ends.
+struct Connection {
+    chunks: VecDeque<&'static [u8]>,
+    end: Option<io::ErrorKind>,
+}
+
+impl Connection {
+    fn new(chunks: &[&'static [u8]], end: Option<io::ErrorKind>) -> Self {
+        Self { chunks: chunks.iter().copied().collect(), end }
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.chunks.pop_front() {
+            Some(chunk) => {
+                buf[..chunk.len()].copy_from_slice(chunk);
+                Ok(chunk.len())
+            }
+            None => match self.end {
+                Some(kind) => Err(io::Error::from(kind)),
+                None => Ok(0),
+            },
+        }
+    }
+}
+
+/// A source whose opens return `opens` in turn, counting them.
+fn source(opens: Vec<io::Result<Connection>>) -> impl FnMut() -> io::Result<Connection> {
+    let mut opens: VecDeque<_> = opens.into();
+    move || opens.pop_front().unwrap_or_else(|| Err(io::Error::other("no more connections")))
+}
+
+#[test]
+fn reads_go_on_across_failed_opens_broken_pipes_and_ends() {
+    let mut reader = ReconnectingReader::new(
+        "mock".to_string(),
+        source(vec![
+            Err(io::Error::from(io::ErrorKind::NotFound)),
+            Ok(Connection::new(&[b"ab", b"c"], Some(io::ErrorKind::BrokenPipe))),
+            Ok(Connection::new(&[b"de"], None)),
+            Ok(Connection::new(&[b"f"], None)),
+        ]),
+    );
+    let started = Instant::now();
+    let mut read = [0; 6];
+    reader.read_exact(&mut read).unwrap();
+    assert_eq!(&read, b"abcdef");
+    // The failed open was retried after the first backoff, and only that one waited
+    let waited = started.elapsed();
+    assert!(waited >= Duration::from_millis(250) && waited < Duration::from_secs(2), "{:?}", waited);
+}
+
+#[test]
+fn other_errors_are_returned_without_reconnecting() {
+    let mut opens = 0;
+    let mut reader = ReconnectingReader::new("mock".to_string(), || {
+        opens += 1;
+        Ok(Connection::new(&[b"a"], Some(io::ErrorKind::InvalidData)))
+    });
+    let mut buf = [0; 4];
+    assert_eq!(reader.read(&mut buf).unwrap(), 1);
+    assert_eq!(reader.read(&mut buf).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    drop(reader);
+    assert_eq!(opens, 1);
+}
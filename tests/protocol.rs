@@ -0,0 +1,80 @@
+//! `scoreboard-rust protocol info`: the protocol tables, checked against the parser.
+
+mod common;
+
+use common::{run, run_ok};
+use scoreboard_rust::{
+    apply_frame,
+    basketball_parser::{MESSAGES, PROTOCOL},
+    GameState, ProtocolFrame,
+};
+
+#[test]
+fn message_18_lists_all_14_byte_positions() {
+    let message = MESSAGES.iter().find(|message| message.id == "18").unwrap();
+    assert_eq!(message.length, 14);
+    let positions: Vec<usize> = message.fields.iter().flat_map(|field| field.offset..field.offset + field.size).collect();
+    assert_eq!(positions, (0..14).collect::<Vec<_>>(), "every byte once, in order");
+}
+
+#[test]
+fn every_message_is_described_byte_by_byte() {
+    for message in &MESSAGES {
+        let positions: Vec<usize> = message.fields.iter().flat_map(|field| field.offset..field.offset + field.size).collect();
+        assert_eq!(positions, (0..message.length).collect::<Vec<_>>(), "message {}", message.id);
+        assert_eq!(message.fields[0].range, format!("\"{}\"", message.id));
+    }
+}
+
+#[test]
+fn the_lengths_are_the_ones_the_parser_needs() {
+    for message in &MESSAGES {
+        let mut bytes = message.id.as_bytes().to_vec();
+        bytes.resize(message.length - 1, b'0');
+        let short = ProtocolFrame::new(0x7F, b'G', bytes.clone());
+        assert!(!apply_frame(&short, &mut GameState::default()), "message {} one byte short", message.id);
+        bytes.push(b'0');
+        assert!(apply_frame(&ProtocolFrame::new(0x7F, b'G', bytes), &mut GameState::default()), "message {}", message.id);
+    }
+}
+
+/// `message` applied to a fresh state, which must take it.
+fn applied(message: &[u8]) -> GameState {
+    let mut state = GameState::default();
+    assert!(apply_frame(&ProtocolFrame::new(0x7F, b'G', message.to_vec()), &mut state), "{:?}", String::from_utf8_lossy(message));
+    state
+}
+
+#[test]
+fn every_message_round_trips_through_the_parser() {
+    for message in &MESSAGES {
+        let mut bytes = message.id.as_bytes().to_vec();
+        bytes.resize(message.length, b'1');
+        let base = applied(&bytes);
+        // Changing a field moves the state exactly when the table says the parser reads it
+        for field in message.fields.iter().skip(1) {
+            let mut changed = bytes.clone();
+            changed[field.offset..field.offset + field.size].fill(b'2');
+            let moved = applied(&changed) != base;
+            assert_eq!(moved, field.read, "message {} field {}", message.id, field.name);
+        }
+    }
+}
+
+#[test]
+fn info_as_text_and_json() {
+    let text = run_ok(&["protocol", "info"]).text();
+    assert!(text.contains("bodet-basketball  Bodet Scorepad, basketball"));
+    assert!(text.contains("Message 18  Game Time and Time-outs  (14 bytes, frames of 20)"));
+    assert!(text.contains("Extension   ack"));
+
+    let json = run_ok(&["protocol", "info", "--json"]).json();
+    let protocol = &json["protocols"][0];
+    assert_eq!(protocol["id"], PROTOCOL.id);
+    assert_eq!(protocol["messages"].as_array().unwrap().len(), 5);
+    assert_eq!(protocol["messages"][1]["fields"][2], serde_json::json!({
+        "offset": 3, "size": 3, "name": "home_score", "meaning": "home score, right-aligned", "range": "'  0'-'999'", "read": true
+    }));
+    assert_eq!(run(&["protocol", "info", "bodet-basketball"], b"").code, Some(0));
+    assert_eq!(run(&["protocol", "info", "daktronics"], b"").code, Some(2));
+}
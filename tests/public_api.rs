@@ -0,0 +1,184 @@
+//! Uses the crate the way an embedding application would: only through its public API.
+
+use scoreboard_rust::{
+    apply_frame, parse_raw_data, BasketballServer, GameState, ProtocolFrame, ServerConfig,
+};
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpStream},
+    sync::{mpsc, Arc},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// An embedded server with its input on a port the system picks, running in a thread of its
+/// own; returns it with the input's address.
+fn embedded_server() -> (Arc<BasketballServer>, SocketAddr) {
+    let mut config = ServerConfig::default();
+    config.tcp_address = "127.0.0.1:0".to_string();
+    config.log_to_file = false;
+    config.mdns = false;
+    let server = Arc::new(BasketballServer::new(config));
+    let address = server.bind().expect("the ingest listener is bound");
+    let running = Arc::clone(&server);
+    thread::spawn(move || running.run());
+    (server, address)
+}
+
+/// The console's scores message for a home score of `home`.
+fn home_scores(home: u32) -> Vec<u8> {
+    ProtocolFrame::new(0x7F, 0x20, format!("305{:>3}  0", home).into_bytes()).to_bytes()
+}
+
+#[test]
+fn parsed_frames_update_a_game_state() {
+    let scores = ProtocolFrame::new(0x7F, 0x20, b"305 45 38".to_vec());
+    let frame = parse_raw_data(&scores.to_bytes(), false).expect("a valid frame");
+
+    let mut state = GameState::default();
+    assert!(apply_frame(&frame, &mut state));
+    assert_eq!(state.home_score, " 45");
+    assert_eq!(state.away_score, " 38");
+
+    let mut corrupt = scores.to_bytes();
+    *corrupt.last_mut().unwrap() ^= 0x01;
+    assert!(parse_raw_data(&corrupt, false).is_err());
+}
+
+#[test]
+fn embedded_server_publishes_console_frames() {
+    let mut config = ServerConfig::default();
+    // On a port the system picks
+    config.tcp_address = "127.0.0.1:0".to_string();
+    config.log_to_file = false;
+    config.mdns = false;
+
+    let server = BasketballServer::new(config);
+    let address = server.bind().expect("the ingest listener is bound");
+    let updates = server.subscribe();
+    let shared = server.shared();
+    thread::spawn(move || server.run());
+
+    let mut console = TcpStream::connect(address).expect("the ingest listener accepts connections");
+    console
+        .write_all(&ProtocolFrame::new(0x7F, 0x20, b"305 12  9".to_vec()).to_bytes())
+        .unwrap();
+
+    let state = updates.recv_timeout(Duration::from_secs(5)).expect("a published state");
+    assert_eq!(state.home_score, " 12");
+    assert_eq!(state.away_score, "  9");
+    assert_eq!(shared.current().home_score, " 12");
+}
+
+#[test]
+fn waiting_for_the_states_a_writer_thread_sends() {
+    let (server, address) = embedded_server();
+    let started = Instant::now();
+    assert!(server.wait_for_update(Duration::from_millis(200)).is_none(), "nothing was sent yet");
+    assert!(started.elapsed() >= Duration::from_millis(200));
+
+    let updates = server.subscribe();
+    let (go, ready) = mpsc::channel();
+    let writer = thread::spawn(move || {
+        let mut console = TcpStream::connect(address).unwrap();
+        ready.recv().unwrap();
+        thread::sleep(Duration::from_millis(100));
+        for home in 1..=10 {
+            console.write_all(&home_scores(home)).unwrap();
+            thread::sleep(Duration::from_millis(5));
+        }
+    });
+    go.send(()).unwrap();
+
+    // The wait returns the first state published after it started
+    let first = server.wait_for_update(Duration::from_secs(5)).expect("the writer's first state");
+    assert_eq!(first.home_score, "  1");
+    writer.join().unwrap();
+    let received: Vec<String> = (1..=10).map(|_| updates.recv_timeout(Duration::from_secs(5)).expect("a state").home_score).collect();
+    assert_eq!(received, (1..=10).map(|home| format!("{:>3}", home)).collect::<Vec<_>>(), "every state, in order");
+    assert!(updates.try_recv().is_none());
+    assert_eq!(server.get_current_state().home_score, " 10");
+}
+
+#[test]
+fn a_subscriber_that_falls_behind_keeps_the_newest_states() {
+    let (server, address) = embedded_server();
+    let updates = server.subscribe();
+    let shared = server.shared();
+    let mut console = TcpStream::connect(address).unwrap();
+    for home in 1..=100 {
+        console.write_all(&home_scores(home)).unwrap();
+    }
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while shared.version() < 100 {
+        assert!(Instant::now() < deadline, "only {} states published", shared.version());
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    // Never read while the writer went on: the queue kept the last 64
+    let mut received = Vec::new();
+    while let Some(state) = updates.try_recv() {
+        received.push(state.home_score.trim().parse::<u32>().unwrap());
+    }
+    assert_eq!(received, (37..=100).collect::<Vec<_>>());
+}
+
+/// A connection held in memory: reads hand out `input` a few bytes at a time, writes are kept.
+struct Duplex {
+    input: Vec<u8>,
+    read: usize,
+    output: Vec<u8>,
+}
+
+impl Read for Duplex {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let n = (self.input.len() - self.read).min(buffer.len()).min(7);
+        buffer[..n].copy_from_slice(&self.input[self.read..self.read + n]);
+        self.read += n;
+        Ok(n)
+    }
+}
+
+impl Write for Duplex {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.output.extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn a_connection_over_an_in_memory_stream_is_served_and_acknowledged() {
+    let mut corrupt = home_scores(50);
+    *corrupt.last_mut().unwrap() ^= 0x01;
+    let input = [home_scores(12), corrupt, b"noise".to_vec(), home_scores(14)].concat();
+    let peer = "192.0.2.7:4001".parse().unwrap();
+
+    let mut config = ServerConfig::default();
+    config.log_to_file = false;
+    config.mdns = false;
+    config.ack = true;
+    let server = BasketballServer::new(config.clone());
+    let mut stream = Duplex { input: input.clone(), read: 0, output: Vec::new() };
+    server.serve_connection(&mut stream, peer).unwrap();
+
+    let replies = String::from_utf8(stream.output).unwrap();
+    let replies: Vec<&str> = replies.lines().collect();
+    assert_eq!(replies.len(), 3, "{:?}", replies);
+    assert_eq!((replies[0], replies[2]), ("ACK", "ACK"));
+    assert!(replies[1].starts_with("ERR "), "{:?}", replies);
+    assert_eq!(server.get_current_state().home_score, " 14");
+    let traffic = &server.metrics().snapshot().peers[&peer.ip()];
+    assert_eq!((traffic.connections, traffic.bytes, traffic.frames, traffic.errors), (1, input.len() as u64, 2, 1));
+
+    // Without acks nothing is written back
+    config.ack = false;
+    let server = BasketballServer::new(config);
+    let mut stream = Duplex { input, read: 0, output: Vec::new() };
+    server.serve_connection(&mut stream, peer).unwrap();
+    assert!(stream.output.is_empty());
+    assert_eq!(server.get_current_state().home_score, " 14");
+}
@@ -0,0 +1,181 @@
+//! Pushing the state to a remote sink that fails now and then, or isn't there at first.
+
+mod common;
+
+use common::Harness;
+use flate2::read::GzDecoder;
+use scoreboard_rust::{push, ServerConfig, SharedState};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// A request the sink accepted: its headers (lower case names) and gunzipped JSON body.
+struct Received {
+    headers: Vec<(String, String)>,
+    body: serde_json::Value,
+}
+
+/// Answer requests on `listener`, failing those for which `fail(n)` holds (`n` from 1), and
+/// report the accepted ones.
+fn start_sink(listener: TcpListener, fail: fn(usize) -> bool) -> mpsc::Receiver<Received> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for (n, stream) in listener.incoming().enumerate() {
+            let stream = stream.unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut headers = Vec::new();
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            loop {
+                line.clear();
+                reader.read_line(&mut line).unwrap();
+                let Some((name, value)) = line.trim_end().split_once(": ") else {
+                    break;
+                };
+                headers.push((name.to_ascii_lowercase(), value.to_string()));
+            }
+            let length: usize = headers.iter().find(|(name, _)| name == "content-length").unwrap().1.parse().unwrap();
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            if fail(n + 1) {
+                reader.get_mut().write_all(b"HTTP/1.1 503 Service Unavailable\r\n\r\n").unwrap();
+                continue;
+            }
+            reader.get_mut().write_all(b"HTTP/1.1 204 No Content\r\n\r\n").unwrap();
+            let mut json = String::new();
+            GzDecoder::new(&body[..]).read_to_string(&mut json).unwrap();
+            let _ = tx.send(Received { headers, body: serde_json::from_str(&json).unwrap() });
+        }
+    });
+    rx
+}
+
+fn start_server(push_args: &str) -> Harness {
+    Harness::start(push_args)
+}
+
+fn set_home(shared: &SharedState, score: u32) {
+    shared
+        .set_manual(|state| {
+            state.home_score = format!("{:3}", score);
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+}
+
+/// The remote's view: the state with every diff applied, and the events in arrival order.
+#[derive(Default)]
+struct Remote {
+    state: serde_json::Map<String, serde_json::Value>,
+    events: Vec<serde_json::Value>,
+    dropped: u64,
+}
+
+impl Remote {
+    fn apply(&mut self, body: &serde_json::Value) {
+        let state = body["state"].as_object().unwrap().clone();
+        if body["full"] == true {
+            self.state = state;
+        } else {
+            self.state.extend(state);
+        }
+        self.events.extend(body["events"].as_array().unwrap().iter().cloned());
+        self.dropped += body["eventsDropped"].as_u64().unwrap_or(0);
+    }
+
+    /// Apply what arrives until the home score is `home`.
+    fn until_home(&mut self, rx: &mpsc::Receiver<Received>, home: u32, mut check: impl FnMut(&Received)) {
+        while self.state.get("homeScore") != Some(&home.into()) {
+            let received = rx.recv_timeout(Duration::from_secs(10)).expect("a push from the server");
+            check(&received);
+            self.apply(&received.body);
+        }
+    }
+}
+
+#[test]
+fn diffs_and_events_get_through_a_flaky_remote() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://127.0.0.1:{}/ingest", listener.local_addr().unwrap().port());
+    let rx = start_sink(listener, |n| n % 2 == 0);
+    let server = start_server(&format!("--push-url {} --push-token s3cret --push-interval 50 --push-diff", url));
+    let shared = &server.shared;
+
+    for score in 1..=6 {
+        set_home(shared, score);
+        thread::sleep(Duration::from_millis(30));
+    }
+    let mut remote = Remote::default();
+    let mut diffs = 0;
+    remote.until_home(&rx, 6, |received| {
+        let header = |name: &str| received.headers.iter().find(|(header, _)| header == name).map(|(_, value)| value.as_str());
+        assert_eq!(header("authorization"), Some("Bearer s3cret"));
+        assert_eq!(header("content-encoding"), Some("gzip"));
+        assert_eq!(header("content-type"), Some("application/json"));
+        assert!(received.body["version"].as_u64().is_some());
+        diffs += usize::from(received.body["full"] == false);
+    });
+    assert_eq!(remote.state["homeScore"], 6);
+    assert_eq!(remote.state["awayScore"], serde_json::Value::Null, "fields sent once are kept");
+    let scores: Vec<u64> = remote.events.iter().filter(|event| event["type"] == "ScoreChange").map(|event| event["to"].as_u64().unwrap()).collect();
+    assert_eq!(scores, [1, 2, 3, 4, 5, 6], "every event once, in order");
+    assert!(diffs > 0, "only the first push carries the whole state");
+
+    // The sender counts the delivery once the answer is in
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while shared.status().metrics.push_lag_ms > 0 {
+        assert!(Instant::now() < deadline, "the lag never went back to 0");
+        thread::sleep(Duration::from_millis(10));
+    }
+    let metrics = shared.status().metrics;
+    assert!(metrics.push_failures >= 1, "every other request fails");
+    assert!(metrics.push_sent >= 1);
+    assert_eq!(metrics.push_events_dropped, 0);
+}
+
+#[test]
+fn an_unreachable_remote_gets_the_newest_state_and_the_buffered_events() {
+    // Reserve a port, and only listen on it once the updates are in the outbox
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let server = start_server(&format!("--push-url http://127.0.0.1:{}/ingest --push-buffer 2 --push-interval 10", port));
+    let shared = &server.shared;
+    for score in 1..=5 {
+        set_home(shared, score);
+    }
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while shared.status().metrics.push_failures == 0 {
+        assert!(Instant::now() < deadline, "the first push never failed");
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(shared.status().metrics.push_lag_ms > 0, "the lag grows while the remote is away");
+
+    let rx = start_sink(TcpListener::bind(("127.0.0.1", port)).unwrap(), |_| false);
+    let mut remote = Remote::default();
+    remote.until_home(&rx, 5, |received| assert_eq!(received.body["full"], true, "no diffs without --push-diff"));
+    let scores: Vec<u64> = remote.events.iter().map(|event| event["to"].as_u64().unwrap()).collect();
+    assert_eq!(scores, [4, 5], "the newest events fit the buffer");
+    assert_eq!(remote.dropped, 3);
+    assert_eq!(shared.status().metrics.push_events_dropped, 3);
+}
+
+#[test]
+fn diff_keeps_the_changed_fields() {
+    let previous = serde_json::json!({ "homeScore": 2, "awayScore": 0, "clock": "09:58" });
+    let state = serde_json::json!({ "homeScore": 4, "awayScore": 0, "clock": "09:41" });
+    assert_eq!(push::diff(&previous, &state), serde_json::json!({ "homeScore": 4, "clock": "09:41" }));
+}
+
+#[test]
+fn push_options() {
+    let args = |line: &str| line.split_whitespace().map(String::from).collect::<Vec<_>>();
+    let push = ServerConfig::from_args(&args("--push-url http://cloud.local/api/push")).unwrap().push.unwrap();
+    assert_eq!((push.token, push.interval, push.diff, push.buffer), (None, Duration::from_millis(500), false, 1000));
+    assert!(ServerConfig::from_args(&args("--push-token abc")).is_err(), "needs --push-url");
+    assert!(ServerConfig::from_args(&args("--push-url http://cloud.local/ --push-buffer 0")).is_err());
+    assert!(ServerConfig::from_args(&args("--push-url ftp://cloud.local/")).is_err());
+}
@@ -0,0 +1,69 @@
+//! The `changes` of a pushed state: the game events that produced it, for the pages to
+//! animate, with the ids of `/api/events`, built once for every client.
+
+mod common;
+
+use common::{fouls, scores, EventStream, Harness, WebSocket};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// The `changes` of `state` without their timestamps.
+fn changes(state: &Value) -> Vec<Value> {
+    let Some(changes) = state["changes"].as_array() else {
+        return Vec::new();
+    };
+    changes
+        .iter()
+        .map(|change| {
+            assert!(change["timestampMs"].as_u64().is_some(), "{}", change);
+            let mut change = change.clone();
+            change.as_object_mut().unwrap().remove("timestampMs");
+            change
+        })
+        .collect()
+}
+
+#[test]
+fn a_score_change_is_pushed_as_exactly_one_score_change() {
+    let mut server = Harness::start("");
+    server.play(&[scores(12, 9), fouls(1, 2)]);
+    let mut ws = WebSocket::open(server.web, "/ws", &[]);
+    let mut sse = EventStream::open(server.web, "/events", &[]);
+    // The state a client connects to carries none
+    assert_eq!(ws.next_text().get("changes"), None);
+    assert_eq!(sse.next().json().get("changes"), None);
+
+    server.play(&[scores(15, 9)]);
+    let version = server.shared.version();
+    let expected = [json!({ "id": 5, "version": version, "type": "ScoreChange", "side": "home", "from": 12, "to": 15, "delta": 3 })];
+    let pushed = ws.next_text();
+    assert_eq!(pushed["home_score"], " 15");
+    assert_eq!(changes(&pushed), expected);
+    let streamed = sse.next().json();
+    assert_eq!(changes(&streamed), expected);
+    // The same events, ids and times for every client, as in the event log
+    assert_eq!(pushed["changes"], streamed["changes"]);
+    assert_eq!(server.get_json("/api/events?since=4").1["events"], pushed["changes"]);
+
+    // The away side, down
+    server.play(&[scores(15, 7)]);
+    assert_eq!(
+        changes(&ws.next_text()),
+        [json!({ "id": 6, "version": version + 1, "type": "ScoreChange", "side": "away", "from": 9, "to": 7, "delta": -2 })]
+    );
+    // Both at once are one each; other changes aren't score changes
+    server.play(&[scores(17, 10)]);
+    let both = changes(&ws.next_text());
+    assert_eq!(
+        both.iter().map(|change| (change["type"].as_str(), change["side"].as_str(), change["delta"].as_i64())).collect::<Vec<_>>(),
+        [(Some("ScoreChange"), Some("home"), Some(2)), (Some("ScoreChange"), Some("away"), Some(3))]
+    );
+    server.play(&[fouls(2, 2)]);
+    let foul = changes(&ws.next_text());
+    assert_eq!((foul.len(), foul[0]["type"].as_str()), (1, Some("FoulChange")));
+
+    // Nothing to replay for a client connecting again
+    let mut again = WebSocket::open(server.web, "/ws", &[]);
+    assert_eq!(again.next_text().get("changes"), None);
+    assert_eq!(again.next_within(Duration::from_millis(300)), None);
+}
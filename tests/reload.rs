@@ -0,0 +1,74 @@
+//! Reloading the configuration file of a running server.
+
+mod common;
+
+use common::fresh_dir;
+use scoreboard_rust::{BasketballServer, Lang, ReloadReport, Reloader, ServerConfig, TeamInfo, Teams};
+use std::{fs, time::Duration};
+
+#[test]
+fn reload_applies_the_safe_changes_and_reports_the_others() {
+    let dir = fresh_dir("reload");
+    let file = dir.join("scoreboard.conf");
+    let teams_file = dir.join("teams.json");
+    fs::write(
+        &file,
+        format!(
+            "# court 1\nweb-address 127.0.0.1:3999\ntheme minimal\nlang pt\nteams-file {}\nno-mdns\n",
+            teams_file.display()
+        ),
+    )
+    .unwrap();
+    let args = vec!["--config".to_string(), file.display().to_string(), "--poll-interval".to_string(), "500".to_string()];
+    let config = ServerConfig::from_env_and_args(Vec::new(), &args).unwrap();
+    assert_eq!(config.config_file.as_deref(), Some(file.as_path()));
+    assert!(!config.mdns);
+
+    let server = BasketballServer::new(config.clone());
+    let shared = server.shared();
+    assert!(shared.set_reloader(Reloader::new(Vec::new(), args, config)));
+    assert_eq!(shared.settings().theme, "minimal");
+    assert_eq!(shared.settings().lang, Lang::Pt);
+
+    // Nothing changed yet
+    assert_eq!(shared.reload().unwrap(), ReloadReport::default());
+
+    fs::write(
+        &file,
+        format!(
+            "web-address 127.0.0.1:4000\ntheme broadcast\nlang pt\nswap-sides\nsummary-webhook http://127.0.0.1:9/hook\n\
+             poll-interval 2000\nteams-file {}\nno-mdns\n",
+            teams_file.display()
+        ),
+    )
+    .unwrap();
+    let team = |name: &str, abbrev: &str| TeamInfo { name: name.to_string(), abbrev: abbrev.to_string(), ..TeamInfo::default() };
+    let teams = Teams { home: team("Madeira", "CAB"), away: team("Benfica", "SLB") };
+    teams.save(&teams_file).unwrap();
+
+    let report = shared.reload().unwrap();
+    assert_eq!(report.applied, ["theme", "swap_sides", "summary_webhook", "teams"]);
+    assert_eq!(report.restart_required, ["web_address"]);
+    let settings = shared.settings();
+    assert_eq!(settings.theme, "broadcast");
+    assert!(settings.swap_sides);
+    assert_eq!(settings.summary_webhook.as_ref().map(|url| url.path.as_str()), Some("/hook"));
+    // The command line still wins over the file
+    assert_eq!(settings.poll_interval, Duration::from_millis(500));
+    assert_eq!(shared.current().teams, teams);
+
+    // A broken file leaves everything as it was
+    fs::write(&file, "theme classic\nweb-workers many\n").unwrap();
+    let error = shared.reload().unwrap_err();
+    assert!(error.contains("line 2"), "{}", error);
+    assert_eq!(shared.settings(), settings);
+    fs::remove_file(&file).unwrap();
+    assert!(shared.reload().is_err());
+    assert_eq!(shared.settings().theme, "broadcast");
+}
+
+#[test]
+fn reload_needs_a_reloader() {
+    let server = BasketballServer::new(ServerConfig::default());
+    assert!(server.shared().reload().is_err());
+}
@@ -0,0 +1,59 @@
+//! `GET /render.png`: a PNG of the requested size with the `raster` feature, 501 without it,
+//! and 400 for sizes out of range either way.
+
+mod common;
+
+use common::Harness;
+#[cfg(feature = "raster")]
+use common::{scores, Response};
+
+/// The width and height in the IHDR chunk, which a PNG must start with after its signature.
+#[cfg(feature = "raster")]
+fn png_size(png: &Response) -> (u32, u32) {
+    let body = &png.body;
+    assert_eq!(&body[..8], b"\x89PNG\r\n\x1a\n", "the PNG signature");
+    assert_eq!(&body[8..16], b"\0\0\0\x0dIHDR", "a 13-byte IHDR chunk first");
+    let number = |at: usize| u32::from_be_bytes(body[at..at + 4].try_into().unwrap());
+    (number(16), number(20))
+}
+
+#[cfg(feature = "raster")]
+#[test]
+fn the_png_has_the_requested_size() {
+    let mut server = Harness::start("");
+    server.play(&[scores(45, 38)]);
+    let png = server.get("/render.png?w=640&h=160");
+    assert_eq!(png.status, 200);
+    assert_eq!(png.header("content-type"), Some("image/png"));
+    assert_eq!(png_size(&png), (640, 160));
+    assert_eq!(png_size(&server.get("/render.png")), (800, 200), "the default size");
+    assert_eq!(png_size(&server.get("/render.png?w=1920&h=1080")), (1920, 1080), "the largest");
+
+    // Cached for the version, drawn again for a new one
+    let version = png.header("x-state-version").unwrap().to_string();
+    assert_eq!(server.get("/render.png?w=640&h=160").body, png.body);
+    server.play(&[scores(47, 38)]);
+    let updated = server.get("/render.png?w=640&h=160");
+    assert_ne!(updated.header("x-state-version"), Some(version.as_str()));
+    assert_ne!(updated.body, png.body);
+    assert_eq!(png_size(&updated), (640, 160));
+}
+
+#[cfg(not(feature = "raster"))]
+#[test]
+fn without_the_feature_there_is_no_png() {
+    let server = Harness::start("");
+    let response = server.get("/render.png");
+    assert_eq!(response.status, 501);
+    assert!(response.json()["error"].as_str().unwrap().contains("raster"));
+}
+
+#[test]
+fn sizes_out_of_range_are_refused() {
+    let server = Harness::start("");
+    for query in ["w=99", "h=39", "w=1921", "h=1081&w=800", "w=0&h=0"] {
+        let response = server.get(&format!("/render.png?{}", query));
+        assert_eq!(response.status, 400, "{}", query);
+        assert!(response.json()["error"].as_str().unwrap().contains("out of range"), "{}", query);
+    }
+}
@@ -0,0 +1,112 @@
+//! Seeking and stepping through a capture with `scoreboard-rust replay`, controlled through
+//! its standard input.
+
+mod common;
+
+use common::{command, fresh_dir, run, Run};
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    path::PathBuf,
+    process::Stdio,
+    thread::{self, JoinHandle},
+};
+
+/// Five lines, one byte each, plus lines a capture may hold that aren't bytes.
+const CAPTURE: &str = "\
+[01]
+[0x02]
+not a capture line
+
+[03]
+[04, ]
+[05]
+";
+
+fn capture(name: &str, text: &str) -> PathBuf {
+    let file = fresh_dir(name).join("session.log");
+    std::fs::write(&file, text).unwrap();
+    file
+}
+
+/// A stand-in server: every byte of the one connection it takes.
+fn receiver() -> (String, JoinHandle<Vec<u8>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let received = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut bytes = Vec::new();
+        stream.read_to_end(&mut bytes).unwrap();
+        bytes
+    });
+    (address, received)
+}
+
+/// Replay `CAPTURE` with `options` and the `controls` typed up front; returns the bytes sent
+/// and what was said.
+fn replay(name: &str, options: &[&str], controls: &str) -> (Vec<u8>, Run) {
+    let file = capture(name, CAPTURE);
+    let (address, received) = receiver();
+    let run = run(&[&["replay", file.to_str().unwrap(), "--target", &address], options].concat(), controls.as_bytes());
+    assert_eq!(run.code, Some(0), "{}", run.stderr);
+    (received.join().unwrap(), run)
+}
+
+#[test]
+fn capture_lines_are_indexed_by_recorded_time() {
+    let (out, run) = replay("replay_index", &["--rate", "2", "--speed", "1000x", "--start-at", "0:01"], "");
+    assert_eq!(out, [0x03, 0x04, 0x05], "the first line at or after the time");
+    assert!(run.stderr.contains("(5 lines, 0:02)"), "{}", run.stderr);
+    assert!(run.stderr.contains("Replay over, 3 lines sent"), "{}", run.stderr);
+
+    let file = capture("replay_broken", "[01, 7G]");
+    let broken = common::run(&["replay", file.to_str().unwrap(), "--target", "127.0.0.1:9"], b"");
+    assert_eq!(broken.code, Some(1));
+    assert!(broken.stderr.contains("line 1"), "{}", broken.stderr);
+}
+
+#[test]
+fn seek_and_step_through_a_paused_replay() {
+    // The first line may go out before the pause is read
+    let (out, run) = replay("replay_seek", &[], " \ng 0:02\nn\nn\n");
+    let out = out.strip_prefix(&[0x01]).unwrap_or(&out);
+    assert_eq!(out, [0x03, 0x04], "only the stepped lines, from the seek on");
+    let said = run.text();
+    assert!(said.contains("Jumped to 0:02 / 0:05, line 2"), "{}", said);
+    assert!(said.lines().last().unwrap().starts_with("Stepped to 0:04 / 0:05, line 4"), "{}", said);
+}
+
+#[test]
+fn resuming_plays_on_to_the_stop() {
+    let (out, _) = replay("replay_resume", &["--speed", "1000x", "--start-at", "0:01", "--stop-at", "0:04"], " \nn\n \n");
+    assert_eq!(out, [0x02, 0x03, 0x04]);
+}
+
+#[test]
+fn looping_goes_back_to_the_start() {
+    let file = capture("replay_loop", CAPTURE);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let mut child = command(&["replay", file.to_str().unwrap(), "--target", &address, "--start-at", "0:03", "--loop", "--speed", "100x"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut lines = [0; 5];
+    stream.read_exact(&mut lines).unwrap();
+    assert_eq!(lines, [0x04, 0x05, 0x04, 0x05, 0x04]);
+    child.stdin.take().unwrap().write_all(b"q\n").unwrap();
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn controls_and_options_are_checked() {
+    let (_, run) = replay("replay_controls", &["--speed", "1000x"], "g 1:75\n");
+    assert!(run.stderr.contains("Invalid time \"1:75\""), "{}", run.stderr);
+
+    for args in [&["game.log", "--speed", "fast"][..], &["game.log", "--start-at", "2:00", "--stop-at", "1:00"], &["--loop"]] {
+        assert_eq!(common::run(&[&["replay"], args].concat(), b"").code, Some(2), "{:?}", args);
+    }
+}
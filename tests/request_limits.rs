@@ -0,0 +1,167 @@
+//! Malformed and oversized requests: heads over 16 KiB get a 431 however they arrive,
+//! garbage a 400, and truncated, pipelined or random bytes never stop the server answering.
+
+mod common;
+
+use common::{parse_response, scores, Harness};
+use std::{
+    io::{Read, Write},
+    net::{Shutdown, TcpStream},
+    thread,
+    time::Duration,
+};
+
+/// Largest request line plus headers the server accepts.
+const MAX_HEAD: usize = 16 * 1024;
+
+/// Write `bytes` in pieces of `chunk` and read whatever comes back until the server closes.
+fn exchange(server: &Harness, bytes: &[u8], chunk: usize) -> Vec<u8> {
+    talk(server, bytes, chunk, false)
+}
+
+/// Write `bytes` and close the sending side, as a client that gives up; the server drops
+/// the connection rather than wait for the rest of a request.
+fn hang_up(server: &Harness, bytes: &[u8]) -> Vec<u8> {
+    talk(server, bytes, usize::MAX, true)
+}
+
+fn talk(server: &Harness, bytes: &[u8], chunk: usize, hang_up: bool) -> Vec<u8> {
+    let mut stream = TcpStream::connect(server.web).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    for piece in bytes.chunks(chunk) {
+        // The server may close on a bad head before it has all of it
+        if stream.write_all(piece).is_err() {
+            break;
+        }
+        if chunk < bytes.len() {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+    if hang_up {
+        let _ = stream.shutdown(Shutdown::Write);
+    }
+    let mut reply = Vec::new();
+    let _ = stream.read_to_end(&mut reply);
+    reply
+}
+
+/// A `GET /api/state` head of exactly `size` bytes, padded with one header.
+fn head_of(size: usize) -> Vec<u8> {
+    let start = "GET /api/state HTTP/1.1\r\nHost: localhost\r\nX-Pad: ";
+    let end = "\r\nConnection: close\r\n\r\n";
+    format!("{}{}{}", start, "a".repeat(size - start.len() - end.len()), end).into_bytes()
+}
+
+/// The status codes of the responses in `reply`, in order.
+fn statuses(reply: &[u8]) -> Vec<u16> {
+    String::from_utf8_lossy(reply)
+        .match_indices("HTTP/1.1 ")
+        .filter_map(|(at, _)| String::from_utf8_lossy(&reply[at + 9..at + 12]).parse().ok())
+        .collect()
+}
+
+fn assert_still_answers(server: &Harness) {
+    let response = server.get("/api/state");
+    assert_eq!(response.status, 200);
+    assert!(response.json()["version"].is_u64());
+}
+
+#[test]
+fn heads_over_16_kib_get_a_431_however_they_arrive() {
+    let server = Harness::start("");
+    for chunk in [usize::MAX, 4096, 1000] {
+        assert_eq!(parse_response(&exchange(&server, &head_of(MAX_HEAD), chunk)).status, 200, "the limit itself");
+    }
+    for size in [MAX_HEAD + 1, 24 * 1024, 100 * 1024] {
+        // In one write, in pieces and byte by byte at the end of the buffer
+        for chunk in [usize::MAX, 4096, 1000] {
+            let reply = exchange(&server, &head_of(size), chunk);
+            let response = parse_response(&reply);
+            assert_eq!(response.status, 431, "{} bytes in pieces of {}", size, chunk);
+            assert_eq!(statuses(&reply).len(), 1, "and the connection closed");
+        }
+    }
+
+    let many: String = (0..101).map(|n| format!("X-Header-{}: {}\r\n", n, n)).collect();
+    let reply = exchange(&server, format!("GET /api/state HTTP/1.1\r\nHost: localhost\r\n{}\r\n", many).as_bytes(), usize::MAX);
+    assert_eq!(parse_response(&reply).status, 431, "more than 100 headers");
+    let long_line = format!("GET /api/state?pad={} HTTP/1.1\r\nHost: localhost\r\n\r\n", "a".repeat(MAX_HEAD));
+    assert_eq!(parse_response(&exchange(&server, long_line.as_bytes(), usize::MAX)).status, 431, "a long request line");
+    assert_still_answers(&server);
+}
+
+#[test]
+fn truncated_requests_are_dropped() {
+    let mut server = Harness::start("");
+    server.play(&[scores(10, 8)]);
+    let request = b"POST /api/control/score HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer secret\r\nContent-Type: application/json\r\nContent-Length: 32\r\nConnection: close\r\n\r\n{\"side\": \"home\", \"points\": 2}   ";
+    // Cut everywhere: in the request line, the headers and the body
+    for end in (0..request.len()).step_by(7) {
+        let reply = hang_up(&server, &request[..end]);
+        assert!(statuses(&reply).iter().all(|status| (400..500).contains(status)), "cut at {}: {}", end, String::from_utf8_lossy(&reply));
+    }
+    assert_eq!(server.shared.current().home_score, " 10", "no command was run");
+    assert_eq!(parse_response(&exchange(&server, request, usize::MAX)).status, 200, "the whole request");
+    assert_still_answers(&server);
+
+    assert_eq!(server.shared.current().home_score, " 12");
+
+    // One that stops sending and keeps the connection open times out
+    let server = Harness::start("--web-header-timeout 1");
+    let mut stream = TcpStream::connect(server.web).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    stream.write_all(b"GET /api/state HTTP/1.1\r\nHost: loc").unwrap();
+    let mut reply = Vec::new();
+    let _ = stream.read_to_end(&mut reply);
+    assert_eq!(statuses(&reply), [408]);
+    assert_still_answers(&server);
+}
+
+#[test]
+fn pipelined_garbage_is_answered_with_a_400_after_the_good_requests() {
+    let server = Harness::start("");
+    let good = b"GET /api/state HTTP/1.1\r\nHost: localhost\r\n\r\n";
+    for garbage in [
+        &b"\x00\x01\x02\x03\r\n\r\n"[..],
+        b"GET /api/\x00state HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        b"GET /api/state HTTP/9.9\r\n\r\n",
+        b"GET /api/state HTTP/1.1\r\nBad Header\r\n\r\n",
+        b"\xff\xfe\xfd not http at all\r\n\r\n",
+    ] {
+        let reply = exchange(&server, &[&good[..], good, garbage, good].concat(), usize::MAX);
+        assert_eq!(statuses(&reply), [200, 200, 400], "{:?}", String::from_utf8_lossy(garbage));
+    }
+    assert_still_answers(&server);
+}
+
+#[test]
+fn random_bytes_never_stop_the_server() {
+    let server = Harness::start("");
+    let request = b"POST /api/state HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: 15\r\n\r\n{\"homeScore\":1}";
+    // A fixed xorshift sequence, so a failure can be replayed
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    for round in 0..200 {
+        let mut bytes = request.to_vec();
+        if round % 2 == 0 {
+            // A valid request with a few bytes flipped
+            for _ in 0..1 + next() % 4 {
+                let at = (next() % bytes.len() as u64) as usize;
+                bytes[at] = next() as u8;
+            }
+        } else {
+            // Pure noise, with line ends now and then
+            bytes = (0..next() % 2048).map(|_| if next() % 16 == 0 { b'\n' } else { next() as u8 }).collect();
+        }
+        let reply = hang_up(&server, &bytes);
+        if !reply.is_empty() {
+            assert!(reply.starts_with(b"HTTP/1.1 "), "round {}: {}", round, String::from_utf8_lossy(&reply));
+        }
+    }
+    assert_still_answers(&server);
+}
@@ -0,0 +1,129 @@
+//! The router: which pattern wins when several take a path, the parameters it extracts,
+//! and the 404 and 405 the web server answers from it.
+
+mod common;
+
+use common::Harness;
+use scoreboard_rust::router::{Found, Params, Router};
+use warp::http::Method;
+
+/// The value routed to for `method path`, and its parameters.
+fn route<'a>(router: &'a Router<&'static str>, method: Method, path: &str) -> (&'a str, Params) {
+    match router.find(&method, path) {
+        Found::Route(value, params) => (*value, params),
+        other => panic!("{} {}: {:?}", method, path, other),
+    }
+}
+
+#[test]
+fn literals_win_over_parameters_which_win_over_the_rest() {
+    let mut router = Router::new();
+    router
+        .add(Method::GET, "/*file", "file")
+        .add(Method::GET, "/api/:name", "api param")
+        .add(Method::GET, "/api/state", "state")
+        .add(Method::GET, "/api/:name/override", "override param")
+        .add(Method::GET, "/api/state/:rest", "state param")
+        .add(Method::GET, "/", "index");
+
+    assert_eq!(route(&router, Method::GET, "/api/state").0, "state");
+    assert_eq!(route(&router, Method::GET, "/api/teams").0, "api param");
+    // Compared from the left: the literal `state` decides before the later segments
+    assert_eq!(route(&router, Method::GET, "/api/state/override").0, "state param");
+    assert_eq!(route(&router, Method::GET, "/api/match/override").0, "override param");
+    assert_eq!(route(&router, Method::GET, "/api/match/override/x").0, "file");
+    assert_eq!(route(&router, Method::GET, "/bug.css").0, "file");
+    assert_eq!(route(&router, Method::GET, "/").0, "index", "`*file` takes one segment or more");
+    // The order of adding doesn't matter, except between equal patterns
+    let mut reversed = Router::new();
+    reversed.add(Method::GET, "/api/state", "state").add(Method::GET, "/api/:name", "first").add(Method::GET, "/api/:other", "second");
+    assert_eq!(route(&reversed, Method::GET, "/api/state").0, "state");
+    assert_eq!(route(&reversed, Method::GET, "/api/match").0, "first");
+}
+
+#[test]
+fn parameters_are_extracted_by_name() {
+    let mut router = Router::new();
+    router
+        .add(Method::POST, "/api/teams/:side/logo", "logo")
+        .add(Method::GET, "/court/:id/history/:limit", "history")
+        .add(Method::GET, "/uploads/*file", "upload");
+
+    let (_, params) = route(&router, Method::POST, "/api/teams/home/logo");
+    assert_eq!(params.get("side"), Some("home"));
+    assert_eq!(params.get("logo"), None);
+
+    let (_, params) = route(&router, Method::GET, "/court/annex/history/50");
+    assert_eq!((params.get("id"), params.parse::<u32>("limit")), (Some("annex"), Some(50)));
+    assert_eq!(params.parse::<u32>("id"), None, "not a number");
+    let (_, params) = route(&router, Method::GET, "/court/annex/history/-1");
+    assert_eq!(params.parse::<u32>("limit"), None);
+
+    // The rest joined again, as sent: no percent-decoding, empty segments dropped
+    let (_, params) = route(&router, Method::GET, "/uploads/logos//home%20team.png");
+    assert_eq!(params.get("file"), Some("logos/home%20team.png"));
+    // Trailing and doubled slashes don't matter
+    assert_eq!(route(&router, Method::POST, "//api/teams/away/logo/").1.get("side"), Some("away"));
+    assert_eq!(router.find(&Method::GET, "/uploads"), Found::NotFound, "`*file` needs a segment");
+}
+
+#[test]
+fn other_methods_are_not_allowed_and_other_paths_not_found() {
+    let mut router = Router::new();
+    router
+        .add(Method::GET, "/api/state", "get")
+        .add(Method::POST, "/api/state", "post")
+        .add(Method::PATCH, "/api/state", "patch")
+        .add(Method::POST, "/api/control/:action", "control")
+        .add(Method::OPTIONS, "/api/*path", "preflight");
+
+    assert_eq!(router.find(&Method::DELETE, "/api/state"), Found::MethodNotAllowed(vec![Method::GET, Method::POST, Method::PATCH]));
+    assert_eq!(router.find(&Method::GET, "/api/control/score"), Found::MethodNotAllowed(vec![Method::POST]));
+    assert_eq!(route(&router, Method::OPTIONS, "/api/state").0, "preflight");
+    // A path only a catch-all of another method takes isn't known
+    assert_eq!(router.find(&Method::GET, "/api/nothing"), Found::NotFound);
+    assert_eq!(router.find(&Method::GET, "/api/control/score/extra"), Found::NotFound);
+    assert_eq!(router.find(&Method::GET, "/elsewhere"), Found::NotFound);
+}
+
+#[test]
+#[should_panic(expected = "must be the last segment")]
+fn a_rest_parameter_must_come_last() {
+    Router::new().add(Method::GET, "/uploads/*file/raw", ());
+}
+
+#[test]
+#[should_panic(expected = "twice")]
+fn a_parameter_name_is_used_once() {
+    Router::new().add(Method::GET, "/court/:id/:id", ());
+}
+
+const ADMIN: (&str, &str) = ("Authorization", "Bearer secret");
+
+#[test]
+fn the_server_answers_404_and_405_from_the_routes() {
+    let server = Harness::start("");
+
+    let missing = server.get("/api/nothing");
+    assert_eq!((missing.status, missing.json()["error"].as_str()), (404, Some("not found")));
+    assert_eq!(server.request("POST", "/api/nothing", &[ADMIN], b"{}").status, 404);
+    let page = server.get("/nothing.html");
+    assert_eq!(page.status, 404);
+    assert!(page.header("content-type").unwrap().starts_with("text/html"), "a page outside /api");
+    assert_eq!(server.request("DELETE", "/nothing.html", &[], b"").status, 404, "files are only looked up, not routes");
+
+    let wrong = server.request("DELETE", "/api/state", &[ADMIN], b"");
+    assert_eq!((wrong.status, wrong.json()["error"].as_str()), (405, Some("method not allowed")));
+    assert_eq!(wrong.header("allow"), Some("GET, POST, PATCH"));
+    assert_eq!(server.get("/api/control/score").header("allow"), Some("POST"));
+    let page = server.request("POST", "/bug", &[], b"");
+    assert_eq!((page.status, page.header("allow")), (405, Some("GET")));
+    assert!(page.text().contains("405 Method Not Allowed"));
+
+    // Before the token is asked for, and with the parameters the routes take
+    assert_eq!(server.request("PUT", "/api/control/score", &[], b"").status, 405);
+    assert_eq!(server.request("POST", "/api/control/score", &[], b"").status, 401);
+    let logo = server.request("POST", "/api/teams/visitors/logo", &[ADMIN, ("Content-Type", "multipart/form-data; boundary=x")], b"--x--\r\n");
+    assert_eq!(logo.status, 400, "{}", logo.text());
+    assert_eq!(server.get("/api/state/").status, 200, "a trailing slash");
+}
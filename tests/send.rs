@@ -0,0 +1,45 @@
+//! `scoreboard-rust send` against a server running in the test process.
+
+mod common;
+
+use common::{run, send, Harness};
+
+#[test]
+fn game_fields_are_acknowledged_and_applied() {
+    let server = Harness::start("--ack");
+    let sent = send(&server.target(), "--home 80 --away 74 --period 4 --time 2:30 --fouls 4,5 --timeouts 3,2 --state running");
+    let text = sent.text();
+    let replies: Vec<&str> = text.lines().collect();
+    assert_eq!(replies.len(), 3, "{:?}", replies);
+    assert!(replies.iter().all(|reply| reply.ends_with(": ACK")), "{:?}", replies);
+
+    let state = server.shared.current();
+    assert_eq!((state.home_score.as_str(), state.away_score.as_str()), (" 80", " 74"));
+    assert_eq!((state.home_fouls.as_str(), state.away_fouls.as_str()), ("4", "5"));
+    assert_eq!((state.home_timeouts.as_str(), state.away_timeouts.as_str()), ("3", "2"));
+    assert_eq!(state.time, "02:30");
+    assert_eq!(state.period_name, "4 Quarter");
+    assert_eq!(state.game_state, "running");
+}
+
+#[test]
+fn corrupt_hex_is_refused_and_repeats_are_sent() {
+    let server = Harness::start("--ack");
+    let address = server.target();
+    // Scores 1-2 with a wrong LRC
+    let corrupt = run(&["send", "--to", &address, "--hex", "017F024733303520203120203203FF"], b"");
+    assert_eq!(corrupt.code, Some(1), "{:?}", corrupt);
+    assert!(corrupt.text().contains(": ERR"), "{}", corrupt.text());
+
+    let repeated = send(&address, "--home 1 --away 2 --repeat 3 --interval 10ms");
+    assert_eq!(repeated.text().lines().count(), 3, "{}", repeated.text());
+}
+
+#[test]
+fn incomplete_fields_are_rejected() {
+    for args in ["--home 3", "--period 2", "--fouls 12,1", "--time 2:75", "--possession home", "--hex 0", ""] {
+        let mut line = vec!["send", "--to", "127.0.0.1:9"];
+        line.extend(args.split_whitespace());
+        assert_eq!(run(&line, b"").code, Some(2), "{:?}", args);
+    }
+}
@@ -0,0 +1,68 @@
+//! The Windows service's control events: when the server is stopped and what the service
+//! manager is told.
+
+use scoreboard_rust::service::{Control, ControlHandler, Response, ServiceOptions, ServiceState};
+
+fn report(state: ServiceState) -> Response {
+    Response { handled: true, shut_down: false, report: Some(state) }
+}
+
+#[test]
+fn stop_shuts_the_running_server_down_once() {
+    let mut handler = ControlHandler::new();
+    assert_eq!(handler.state(), ServiceState::StartPending);
+    assert_eq!(handler.started(), report(ServiceState::Running));
+    assert!(handler.state().accepts_stop());
+
+    assert_eq!(handler.control(Control::Interrogate), report(ServiceState::Running));
+    assert_eq!(
+        handler.control(Control::Stop),
+        Response { handled: true, shut_down: true, report: Some(ServiceState::StopPending) }
+    );
+    assert!(!handler.state().accepts_stop());
+    // Windows shutting down meanwhile: already on the way
+    assert_eq!(handler.control(Control::Shutdown), Response { handled: true, shut_down: false, report: None });
+    assert_eq!(handler.control(Control::Interrogate), report(ServiceState::StopPending));
+
+    assert_eq!(handler.stopped(), report(ServiceState::Stopped));
+    assert_eq!(handler.control(Control::Stop), Response { handled: true, shut_down: false, report: None });
+}
+
+#[test]
+fn windows_shutdown_stops_it_like_stop() {
+    let mut handler = ControlHandler::new();
+    handler.started();
+    assert_eq!(
+        handler.control(Control::Shutdown),
+        Response { handled: true, shut_down: true, report: Some(ServiceState::StopPending) }
+    );
+}
+
+#[test]
+fn a_stop_while_starting_waits_for_the_server() {
+    let mut handler = ControlHandler::new();
+    assert_eq!(
+        handler.control(Control::Stop),
+        Response { handled: true, shut_down: false, report: Some(ServiceState::StopPending) },
+        "nothing to stop yet"
+    );
+    assert_eq!(handler.started(), Response { handled: true, shut_down: true, report: None });
+    assert_eq!(handler.state(), ServiceState::StopPending);
+}
+
+#[test]
+fn other_controls_are_not_handled() {
+    let mut handler = ControlHandler::new();
+    handler.started();
+    assert_eq!(handler.control(Control::Other), Response::default());
+    assert_eq!(handler.state(), ServiceState::Running);
+}
+
+#[test]
+fn the_service_logs_to_files() {
+    let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    let options = ServiceOptions::from_args(&args(&["--listen", "0.0.0.0:4001", "dev"]));
+    assert_eq!(options.server_args, ["--listen", "0.0.0.0:4001", "dev", "--log-dir", "logs"]);
+    let options = ServiceOptions::from_args(&args(&["--log-dir", r"C:\scoreboard\logs"]));
+    assert_eq!(options.server_args, ["--log-dir", r"C:\scoreboard\logs"]);
+}
@@ -0,0 +1,115 @@
+//! The shot clock: `shotClock` in the state DTO from the console's display, and the ticker
+//! counting it down between frames with `--tick-clock`, from 24 to 0 and no further.
+
+mod common;
+
+use common::{shot_clock, shot_clock_blank, wait_until, Harness};
+use scoreboard_rust::{ClockTicker, GameState, StateDto};
+use serde_json::{json, Value};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+fn dto(shot_clock: &str, running: bool, display: &str) -> Value {
+    let mut state = GameState::default();
+    state.shot_clock = shot_clock.to_string();
+    state.shot_clock_running = running;
+    state.display_shot_clock = display.to_string();
+    serde_json::to_value(StateDto::from(&state)).unwrap()["shotClock"].clone()
+}
+
+#[test]
+fn the_dto_has_seconds_tenths_and_whether_it_runs() {
+    assert_eq!(dto("24", true, ""), json!({ "seconds": 24, "tenths": null, "running": true }));
+    assert_eq!(dto(" 8", false, ""), json!({ "seconds": 8, "tenths": null, "running": false }));
+    assert_eq!(dto("4.3", true, ""), json!({ "seconds": 4, "tenths": 3, "running": true }));
+    assert_eq!(dto(" 0", true, ""), json!({ "seconds": 0, "tenths": null, "running": true }));
+    assert_eq!(dto("0.0", true, ""), json!({ "seconds": 0, "tenths": 0, "running": true }));
+    // The ticker's value when there is one
+    assert_eq!(dto("24", true, "17"), json!({ "seconds": 17, "tenths": null, "running": true }));
+    assert_eq!(dto("5.0", true, "2.7"), json!({ "seconds": 2, "tenths": 7, "running": true }));
+
+    // Hidden until received and while blanked
+    assert_eq!(dto("-", false, ""), Value::Null);
+    assert_eq!(dto("", false, ""), Value::Null);
+    assert_eq!(dto("", true, ""), Value::Null);
+    assert_eq!(serde_json::to_value(StateDto::from(&GameState::default())).unwrap()["shotClock"], Value::Null);
+}
+
+fn running(shot_clock: &str) -> GameState {
+    let mut state = GameState::default();
+    state.shot_clock = shot_clock.to_string();
+    state.shot_clock_running = true;
+    state
+}
+
+#[test]
+fn the_ticker_counts_from_24_to_0_and_stays() {
+    let start = Instant::now();
+    let at = |millis: u64| start + Duration::from_millis(millis);
+    let mut ticker = ClockTicker::default();
+    ticker.sync_shot_clock(&running("24"), start);
+
+    // Whole seconds are rounded up: 24 is shown for the whole first second
+    let shown = |millis| ticker.display_shot_clock(at(millis));
+    assert_eq!(shown(0).as_deref(), Some("24"));
+    assert_eq!(shown(900).as_deref(), Some("24"));
+    assert_eq!(shown(1_000).as_deref(), Some("23"));
+    assert_eq!(shown(23_000).as_deref(), Some("1"));
+    assert_eq!(shown(23_900).as_deref(), Some("1"));
+    assert_eq!(shown(24_000).as_deref(), Some("0"));
+    assert_eq!(shown(60_000).as_deref(), Some("0"), "never below");
+
+    // In tenths, down to 0.0
+    ticker.sync_shot_clock(&running("4.3"), start);
+    let shown = |millis| ticker.display_shot_clock(at(millis));
+    assert_eq!(shown(0).as_deref(), Some("4.3"));
+    assert_eq!(shown(4_200).as_deref(), Some("0.1"));
+    assert_eq!(shown(4_300).as_deref(), Some("0.0"));
+    assert_eq!(shown(10_000).as_deref(), Some("0.0"));
+
+    // A new value from the console starts over from it
+    ticker.sync_shot_clock(&running("14"), at(30_000));
+    assert_eq!(ticker.display_shot_clock(at(31_000)).as_deref(), Some("13"));
+
+    // Nothing to count while stopped or blanked
+    let mut stopped = running("24");
+    stopped.shot_clock_running = false;
+    ticker.sync_shot_clock(&stopped, start);
+    assert_eq!(ticker.display_shot_clock(at(5_000)), None);
+    ticker.sync_shot_clock(&running(""), start);
+    assert_eq!(ticker.display_shot_clock(at(5_000)), None);
+    ticker.sync_shot_clock(&running("-"), start);
+    assert_eq!(ticker.display_shot_clock(at(5_000)), None);
+}
+
+fn shown(server: &Harness) -> Value {
+    server.get_json("/api/state").1["state"]["shotClock"].clone()
+}
+
+#[test]
+fn the_server_ticks_the_shot_clock_down_to_zero() {
+    let mut server = Harness::start("--tick-clock");
+    assert_eq!(shown(&server), Value::Null, "no shot clock received yet");
+
+    server.play(&[shot_clock(" 2", true, false)]);
+    assert_eq!(shown(&server)["seconds"], 2);
+    wait_until("the shot clock to run out", || shown(&server)["seconds"] == 0);
+    thread::sleep(Duration::from_millis(500));
+    assert_eq!(shown(&server), json!({ "seconds": 0, "tenths": null, "running": true }), "never below");
+
+    // Stopped, it shows the console's value
+    server.play(&[shot_clock("24", false, false)]);
+    thread::sleep(Duration::from_millis(1500));
+    assert_eq!(shown(&server), json!({ "seconds": 24, "tenths": null, "running": false }));
+
+    // In tenths, and hidden on a dead ball
+    server.play(&[shot_clock("09", true, true)]);
+    wait_until("the tenths to tick", || shown(&server)["tenths"].as_u64().is_some_and(|tenths| tenths < 9));
+    assert_eq!(shown(&server)["seconds"], 0);
+    server.play(&[shot_clock_blank()]);
+    assert_eq!(shown(&server), Value::Null);
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(shown(&server), Value::Null);
+}
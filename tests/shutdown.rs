@@ -0,0 +1,84 @@
+//! Stopping a server through its shutdown handles, as Ctrl+C does, without losing log lines.
+
+mod common;
+
+use common::simulate;
+use scoreboard_rust::{BasketballServer, ServerConfig};
+use std::{
+    fs,
+    io::Read,
+    net::TcpStream,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+const GAME: &str = "\
+- 0:00 start
+- 0:02 score home 2
+- 0:03 foul away
+- 0:04 start
+- 0:06 score away 3
+- 0:07 timeout home
+- 0:08 start
+- 0:09 period
+";
+
+#[test]
+fn shutdown_closes_connections_and_flushes_the_logs() {
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("shutdown");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let mut config = ServerConfig::default();
+    config.tcp_address = "127.0.0.1:0".to_string();
+    config.log_to_file = false;
+    config.mdns = false;
+    config.ack = true;
+    config.log_csv = Some(dir.join("game.csv"));
+    config.log_jsonl = Some(dir.join("log.jsonl").display().to_string());
+    config.games_dir = Some(dir.join("games"));
+
+    let server = BasketballServer::new(config);
+    let address = server.bind().unwrap();
+    let shared = server.shared();
+    let shutdown = server.shutdown_handle();
+    // A slow listener: updates still queued for it when the server stops must reach it
+    let delivered = Arc::new(AtomicU64::new(0));
+    let counter = Arc::clone(&delivered);
+    shared.add_listener(Duration::ZERO, move |update| {
+        thread::sleep(Duration::from_millis(5));
+        counter.store(update.version, Ordering::SeqCst);
+    });
+    let ingest = thread::spawn(move || server.run());
+    let mut idle = TcpStream::connect(address).unwrap();
+
+    simulate(&address.to_string(), GAME);
+    let version = shared.version();
+
+    shutdown.shutdown();
+    assert!(shutdown.wait(Duration::from_secs(5)), "connections not closed");
+    ingest.join().unwrap().unwrap();
+    // The console connection left open was closed by the server
+    idle.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    assert_eq!(idle.read(&mut [0; 16]).unwrap_or(0), 0);
+    assert!(TcpStream::connect(address).is_err(), "still accepting");
+
+    assert!(shared.close_listeners(Duration::from_secs(5)));
+    assert_eq!(delivered.load(Ordering::SeqCst), version);
+
+    let csv = fs::read_to_string(dir.join("game.csv")).unwrap();
+    assert_eq!(csv.lines().count(), 1 + shared.game_log().rows().count());
+    let jsonl = fs::read_to_string(dir.join("log.jsonl")).unwrap();
+    let last_state = jsonl
+        .lines()
+        .rev()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .find(|line| line["kind"] == "state")
+        .unwrap();
+    assert_eq!(last_state["version"], version);
+    let game = fs::read_to_string(dir.join("games").join("game-000001.jsonl")).unwrap();
+    assert!(game.contains(r#""type":"PeriodChange","from":"1 Quarter","to":"2 Quarter""#), "{}", game);
+}
@@ -0,0 +1,53 @@
+//! `scoreboard-rust simulate` playing scenarios against a server in the test process.
+
+mod common;
+
+use common::{fresh_dir, run, simulate, Harness};
+use std::fs;
+
+const REHEARSAL: &str = "\
+# Opening minutes of a rehearsal
+- 0:00 start
+- 0:02 score home 2
+- 0:03 score away 3
+- 0:04 foul away   # stops the clock
+- 0:05 start
+- 0:06 timeout home
+- 0:08 score home 1
+- 0:08 period
+";
+
+#[test]
+fn scenario_ends_in_its_final_state() {
+    let server = Harness::start("--ack");
+    let lines: Vec<String> = simulate(&server.target(), REHEARSAL).text().lines().map(String::from).collect();
+    assert_eq!(lines.len(), 8, "{:#?}", lines);
+    assert!(lines[1].contains("HOME scores 2"), "{}", lines[1]);
+
+    let state = server.shared.current();
+    assert_eq!((state.home_score.as_str(), state.away_score.as_str()), ("  3", "  3"));
+    assert_eq!(state.period_name, "2 Quarter");
+    assert_eq!(state.time, "10:00");
+    assert_eq!(state.game_state, "paused");
+    // The period started afresh: team fouls reset, one FIBA time-out used in the first half
+    assert_eq!((state.away_fouls.as_str(), state.home_timeouts.as_str()), ("0", "1"));
+}
+
+#[test]
+fn scenario_errors_name_the_line() {
+    let file = fresh_dir("simulate-errors").join("test.yaml");
+    for (script, error) in [
+        ("- 0:00 start\n- 0:05 scroe home 2\n", "test.yaml:2: unknown event `scroe`"),
+        ("- 0:05 start\n\n- 0:01 stop\n", "test.yaml:3: 0:01 comes before the previous step at 0:05"),
+        ("- 0:05 score home 4\n", "test.yaml:1: `score` needs a side and 1, 2 or 3 points"),
+        ("- 0:05 foul\n", "test.yaml:1: `foul` needs home or away"),
+        ("start\n", "test.yaml:1: expected a step"),
+        ("- 0:01 end\n- 0:02 start\n", "test.yaml:2: nothing can follow `end`"),
+        ("# nothing\n", "test.yaml: the scenario has no steps"),
+    ] {
+        fs::write(&file, script).unwrap();
+        let run = run(&["simulate", "--target", "127.0.0.1:9", "--scenario", file.to_str().unwrap()], b"");
+        assert_eq!(run.code, Some(2), "{:?}: {}", script, run.stderr);
+        assert!(run.stderr.contains(error), "{:?}: {}", script, run.stderr);
+    }
+}
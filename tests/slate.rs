@@ -0,0 +1,98 @@
+//! `/slate`: the full-screen card for the videoboard, its template and the live data each of
+//! its three cards is drawn from: the teams before the game, the half score at halftime, the
+//! result and the line score after the final buzzer.
+
+mod common;
+
+use common::{Harness, Response, WebSocket};
+use serde_json::{json, Value};
+
+const ADMIN: (&str, &str) = ("Authorization", "Bearer secret");
+
+fn patch(server: &Harness, body: &str) -> Response {
+    let response = server.request("PATCH", "/api/state", &[ADMIN, ("Content-Type", "application/json")], body.as_bytes());
+    assert_eq!(response.status, 200, "{}", response.text());
+    response
+}
+
+/// The next pushed state in `period`.
+fn pushed_in(ws: &mut WebSocket, period: &str) -> Value {
+    loop {
+        let state = ws.next_text();
+        if state["period_name"] == period {
+            return state;
+        }
+    }
+}
+
+#[test]
+fn the_slate_is_its_own_template_on_the_shared_script() {
+    let server = Harness::start("");
+    let slate = server.get("/slate");
+    assert_eq!((slate.status, slate.header("content-type")), (200, Some("text/html; charset=utf-8")));
+    let page = slate.text();
+    assert!(page.contains("<title>Basketball Slate</title>") && page.contains("href=\"slate.css\""));
+    for id in ["slate", "slate-title", "home-team-name", "away-team-name", "home-score", "away-score", "line-score", "slate-tip-off", "tip-off-countdown"] {
+        assert!(page.contains(&format!("id=\"{}\"", id)), "no #{}", id);
+    }
+    assert!(page.contains("<script src=\"common.js\"></script>") && page.contains("<script src=\"slate.js\"></script>"));
+
+    // One card per phase, from the live data, titled in the page's language
+    let script = server.get("/slate.js").text();
+    assert!(script.contains("connectLiveState(updateSlate);") && !script.contains("new WebSocket("));
+    for phase in ["'pregame'", "'halftime'", "'final'", "'live'"] {
+        assert!(script.contains(phase), "{}", phase);
+    }
+    assert!(script.contains("'STARTING SOON'") && script.contains("localPeriodName(phase === 'final' ? 'Final' : 'Halftime')"));
+    let css = server.get("/slate.css");
+    assert_eq!(css.status, 200);
+    assert!(css.text().contains("[data-phase=\"live\"]"), "the card is hidden while the game is played");
+}
+
+#[test]
+fn the_pregame_card_has_the_team_names() {
+    let server = Harness::start("");
+    let teams = r#"{"home": {"name": "CAB Madeira", "abbrev": "CAB"}, "away": {"name": "Benfica", "abbrev": "SLB"}}"#;
+    let put = server.request("PUT", "/api/teams", &[ADMIN, ("Content-Type", "application/json")], teams.as_bytes());
+    assert_eq!(put.status, 200, "{}", put.text());
+
+    // Before any data: no period, no scores, no line score
+    let mut ws = WebSocket::open(server.web, "/ws", &[]);
+    let state = ws.next_text();
+    assert_eq!((state["teams"]["home"]["name"].as_str(), state["teams"]["away"]["name"].as_str()), (Some("CAB Madeira"), Some("Benfica")));
+    assert!(state["period_name"].as_str().is_none_or(|period| period.trim().is_empty() || period == "-"), "{}", state);
+    assert!(state["line_score"].as_array().is_none_or(Vec::is_empty), "{}", state);
+}
+
+#[test]
+fn the_halftime_and_final_cards_have_the_scores_by_period() {
+    let server = Harness::start("");
+    let mut ws = WebSocket::open(server.web, "/ws", &[]);
+    ws.next_text();
+    let quarter = |server: &Harness, period: &str, home: u32, away: u32| {
+        patch(server, &format!(r#"{{"periodName": "{}"}}"#, period));
+        patch(server, &format!(r#"{{"homeScore": {}, "awayScore": {}}}"#, home, away));
+    };
+
+    quarter(&server, "1 Quarter", 18, 22);
+    quarter(&server, "2 Quarter", 40, 38);
+    patch(&server, r#"{"periodName": "Halftime"}"#);
+    let halftime = pushed_in(&mut ws, "Halftime");
+    assert_eq!((halftime["home_score"].as_str(), halftime["away_score"].as_str()), (Some(" 40"), Some(" 38")));
+    assert_eq!(
+        halftime["line_score"],
+        json!([{ "period": "1 Quarter", "home": 18, "away": 22 }, { "period": "2 Quarter", "home": 22, "away": 16 }])
+    );
+
+    quarter(&server, "3 Quarter", 61, 58);
+    quarter(&server, "4 Quarter", 80, 74);
+    patch(&server, r#"{"periodName": "Final"}"#);
+    let result = pushed_in(&mut ws, "Final");
+    assert_eq!((result["home_score"].as_str(), result["away_score"].as_str()), (Some(" 80"), Some(" 74")));
+    let line: Vec<_> = result["line_score"].as_array().unwrap().iter().map(|period| (period["home"].as_u64(), period["away"].as_u64())).collect();
+    assert_eq!(line, [(Some(18), Some(22)), (Some(22), Some(16)), (Some(21), Some(20)), (Some(19), Some(16))]);
+
+    // The titles of both cards, translated
+    let strings = server.get("/api/locale?lang=pt").json();
+    assert_eq!((strings["periodNames"]["Halftime"].as_str(), strings["periodNames"]["Final"].as_str()), (Some("Intervalo"), Some("Fim do jogo")));
+}
@@ -0,0 +1,60 @@
+//! `GET /api/state` revalidation: `304 Not Modified` while the state stays the same, `200` with
+//! a new ETag once anything in it changes, including changes stored without being published.
+
+mod common;
+
+use common::{clock_at, scores, wait_until, Harness, WebSocket};
+
+const ADMIN: (&str, &str) = ("Authorization", "Bearer secret");
+
+/// The status of `GET /api/state` revalidating `etag`, and the ETag it returned.
+fn revalidate(server: &Harness, etag: &str) -> (u16, String) {
+    let response = server.request("GET", "/api/state", &[("If-None-Match", etag)], b"");
+    (response.status, response.header("etag").unwrap().to_string())
+}
+
+#[test]
+fn unchanged_is_not_modified_and_an_update_is_sent_again() {
+    let mut server = Harness::start("");
+    server.play(&[scores(10, 8)]);
+    let first = server.get("/api/state");
+    assert_eq!(first.status, 200);
+    let etag = first.header("etag").unwrap().to_string();
+    assert!(etag.starts_with("W/\""), "{}", etag);
+    assert_eq!(first.header("x-state-version"), Some(server.shared.version().to_string().as_str()));
+
+    let (status, again) = revalidate(&server, &etag);
+    assert_eq!((status, again.as_str()), (304, etag.as_str()));
+
+    server.play(&[scores(12, 8)]);
+    let (status, updated) = revalidate(&server, &etag);
+    assert_eq!(status, 200);
+    assert_ne!(updated, etag);
+    assert_eq!(revalidate(&server, &updated).0, 304);
+
+    // An operator edit is a change too
+    let edit = server.request("PATCH", "/api/state", &[ADMIN, ("Content-Type", "application/json")], br#"{"awayScore": 9}"#);
+    assert_eq!(edit.status, 200, "{}", edit.text());
+    assert_eq!(revalidate(&server, &updated).0, 200);
+}
+
+#[test]
+fn a_clock_stored_but_not_pushed_changes_the_etag() {
+    let mut server = Harness::start("--dedupe ignore-clock");
+    server.play(&[clock_at("1000", true)]);
+    let etag = server.get("/api/state").header("etag").unwrap().to_string();
+    let version = server.shared.version();
+
+    server.send_frames(&[clock_at("0959", true)]);
+    wait_until("the new clock", || server.shared.current().time == "09:59");
+    assert_eq!(server.shared.version(), version, "not published");
+    let response = server.request("GET", "/api/state", &[("If-None-Match", &etag)], b"");
+    assert_eq!(response.status, 200);
+    assert_eq!(response.json()["state"]["clock"], "09:59");
+    assert_ne!(response.header("etag"), Some(etag.as_str()));
+
+    // And clients connecting now start from it
+    let mut client = WebSocket::open(server.web, "/ws", &[]);
+    assert_eq!(client.next_text()["time"], "09:59");
+    assert!(server.shared.current_json().contains("\"time\":\"09:59\""));
+}
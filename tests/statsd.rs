@@ -0,0 +1,103 @@
+//! StatsD output: datagrams captured on a local UDP socket, checked line by line.
+
+mod common;
+
+use common::{scores, Harness};
+use scoreboard_rust::{statsd, ServerConfig, StatsdConfig};
+use std::{
+    net::UdpSocket,
+    time::{Duration, Instant},
+};
+
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(String::from).collect()
+}
+
+/// Collect the lines received until `done` holds for them, or fail after 5 seconds.
+fn lines_until(socket: &UdpSocket, done: impl Fn(&[String]) -> bool) -> Vec<String> {
+    socket.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut lines = Vec::new();
+    let mut buf = [0u8; 2048];
+    while !done(&lines) {
+        assert!(Instant::now() < deadline, "missing StatsD lines, got {:?}", lines);
+        if let Ok(n) = socket.recv(&mut buf) {
+            assert!(n <= statsd::MAX_PACKET);
+            lines.extend(String::from_utf8(buf[..n].to_vec()).unwrap().lines().map(String::from));
+        }
+    }
+    lines
+}
+
+#[test]
+fn counters_gauges_events_and_timings() {
+    let collector = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut config = Harness::config("");
+    config.statsd = Some(StatsdConfig {
+        host: "127.0.0.1".to_string(),
+        port: collector.local_addr().unwrap().port(),
+        prefix: "arena.court2".to_string(),
+        interval: Duration::from_millis(200),
+        sample_rate: 1.0,
+    });
+    let mut server = Harness::with_config(config);
+    server.send_frames(&[scores(2, 0)]);
+
+    let has = |lines: &[String], line: &str| lines.iter().any(|received| received == line);
+    let lines = lines_until(&collector, |lines| {
+        has(lines, "arena.court2.frames:1|c") && has(lines, "arena.court2.events.score_change:1|c")
+    });
+    assert!(has(&lines, "arena.court2.connections:1|g"), "{:?}", lines);
+    assert!(has(&lines, "arena.court2.ws_clients:0|g"), "{:?}", lines);
+    let timing = lines.iter().find_map(|line| line.strip_prefix("arena.court2.frame_ms:")).unwrap();
+    let millis = timing.strip_suffix("|ms").expect("all frames are timed at rate 1");
+    assert!(millis.parse::<f64>().unwrap() >= 0.0);
+    for line in &lines {
+        let (name, value) = line.split_once(':').unwrap();
+        assert!(name.starts_with("arena.court2."), "{}", line);
+        assert!(["|c", "|g", "|ms"].iter().any(|kind| value.ends_with(kind)), "{}", line);
+    }
+
+    // Counters send what they grew by: nothing new, no frame line
+    let lines = lines_until(&collector, |lines| lines.iter().filter(|line| line.contains(".connections:")).count() >= 2);
+    assert!(!lines.iter().any(|line| line.contains(".frames:")), "{:?}", lines);
+    assert!(lines.iter().any(|line| line.starts_with("arena.court2.update_age_ms:")), "{:?}", lines);
+
+    server.stop();
+}
+
+#[test]
+fn lines_are_packed_into_datagrams() {
+    let lines: Vec<String> = (0..100).map(|i| format!("scoreboard.metric_{:03}:{}|c", i, i)).collect();
+    let packets = statsd::packets(&lines);
+    assert!(packets.len() > 1);
+    assert!(packets.iter().all(|packet| packet.len() <= statsd::MAX_PACKET));
+    assert_eq!(packets.join("\n").lines().collect::<Vec<_>>(), lines);
+}
+
+#[test]
+fn statsd_options() {
+    let config = ServerConfig::from_args(&args("--statsd metrics.local")).unwrap();
+    let statsd = config.statsd.unwrap();
+    assert_eq!((statsd.host.as_str(), statsd.port, statsd.prefix.as_str()), ("metrics.local", 8125, "scoreboard"));
+    assert_eq!((statsd.interval, statsd.sample_rate), (Duration::from_secs(10), 0.1));
+
+    let config = ServerConfig::from_args(&args(
+        "--statsd-prefix arena.court-2 --statsd [::1]:9125 --statsd-interval 5 --statsd-sample-rate 1",
+    ))
+    .unwrap();
+    let statsd = config.statsd.unwrap();
+    assert_eq!((statsd.host.as_str(), statsd.port, statsd.prefix.as_str()), ("::1", 9125, "arena.court-2"));
+    assert_eq!((statsd.interval, statsd.sample_rate), (Duration::from_secs(5), 1.0));
+
+    for line in [
+        "--statsd-prefix arena",
+        "--statsd 127.0.0.1:8125 --statsd-prefix arena:court",
+        "--statsd 127.0.0.1:8125 --statsd-prefix arena..court",
+        "--statsd 127.0.0.1:8125 --statsd-interval 0",
+        "--statsd 127.0.0.1:8125 --statsd-sample-rate 1.5",
+        "--statsd 127.0.0.1:port",
+    ] {
+        assert!(ServerConfig::from_args(&args(line)).is_err(), "{}", line);
+    }
+}
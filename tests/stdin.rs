@@ -0,0 +1,68 @@
+//! `scoreboard-rust stdin`: frames piped in however the writes split them, and the state kept
+//! but flagged stale once the pipe closes.
+
+mod common;
+
+use common::{clock, command, fresh_dir, http, scores};
+use serde_json::Value;
+use std::{
+    io::Write,
+    net::{SocketAddr, TcpListener, TcpStream},
+    process::Stdio,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// `/api/state` once `done` holds for it, waiting up to ten seconds for the server to start
+/// and the frames to arrive.
+fn state_when(web: SocketAddr, what: &str, done: impl Fn(&Value) -> bool) -> Value {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        if TcpStream::connect(web).is_ok() {
+            let state = http(web, "GET", "/api/state", &[], b"").json();
+            if done(&state) {
+                return state;
+            }
+        }
+        assert!(Instant::now() < deadline, "{} never came", what);
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn frames_split_across_writes_apply_and_the_end_of_input_goes_stale() {
+    let web = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let mut child = command(&["stdin", "dev", "--no-mdns", "--admin-token", "secret", "--web-address", &web.to_string()])
+        .current_dir(fresh_dir("stdin_input"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+
+    // A frame cut in the middle, noise before the next, then two frames in one write
+    let first = scores(12, 9).to_bytes();
+    let (head, tail) = first.split_at(5);
+    stdin.write_all(head).unwrap();
+    stdin.flush().unwrap();
+    thread::sleep(Duration::from_millis(200));
+    stdin.write_all(&[tail, b"\xff\xfe".as_slice()].concat()).unwrap();
+    stdin.write_all(&[scores(14, 9).to_bytes(), clock(true).to_bytes()].concat()).unwrap();
+    stdin.flush().unwrap();
+
+    let state = state_when(web, "the clock frame", |state| state["state"]["clock"] == "08:12");
+    assert_eq!((state["state"]["homeScore"].as_u64(), state["state"]["awayScore"].as_u64()), (Some(14), Some(9)));
+    assert_eq!(state["stale"], false);
+    let status = http(web, "GET", "/api/status", &[], b"").json();
+    assert_eq!(status["source"], "stdin");
+
+    // The pipe closes: the game stays, flagged stale, and the server keeps serving it
+    drop(stdin);
+    let state = state_when(web, "the stale flag", |state| state["stale"] == true);
+    assert_eq!(state["state"]["homeScore"].as_u64(), Some(14));
+    assert_eq!(child.try_wait().unwrap(), None, "the web server outlives the input");
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
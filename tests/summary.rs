@@ -0,0 +1,96 @@
+//! The box score a server builds when a simulated game goes Final, and its webhook.
+
+mod common;
+
+use common::{receive_post, simulate, Harness};
+use scoreboard_rust::{
+    GameSummary, HttpUrl, MatchInfo, PeriodScore, TeamInfo, TeamTotals, Teams, TimeZone,
+};
+use std::net::TcpListener;
+
+/// Start a server posting summaries to `webhook`, acknowledging every frame.
+fn start_server(webhook: &str) -> Harness {
+    let mut config = Harness::config("--ack");
+    config.summary_webhook = Some(HttpUrl::parse(webhook).unwrap());
+    config.timezone = TimeZone::parse("+01:00").unwrap();
+    Harness::with_config(config)
+}
+
+// Away leads, home takes the lead back twice; 4 away fouls over two quarters
+const GAME: &str = "\
+- 0:00 start
+- 0:02 score away 3
+- 0:03 foul away
+- 0:04 start
+- 0:05 score home 2
+- 0:06 foul away
+- 0:07 start
+- 0:08 score home 3
+- 0:09 timeout away
+- 0:10 start
+- 0:12 foul home
+- 0:13 start
+- 0:14 period
+- 0:15 start
+- 0:16 foul away
+- 0:17 start
+- 0:18 score away 3
+- 0:19 foul away
+- 0:20 start
+- 0:21 score home 2
+- 0:22 timeout home
+- 0:23 start
+- 0:24 end
+";
+
+#[test]
+fn final_period_builds_the_box_score_and_posts_it() {
+    let webhook = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://127.0.0.1:{}/hooks/final", webhook.local_addr().unwrap().port());
+    let server = start_server(&url);
+    let (target, shared) = (server.target(), &server.shared);
+
+    let team = |name: &str, abbrev: &str| TeamInfo { name: name.to_string(), abbrev: abbrev.to_string(), ..TeamInfo::default() };
+    shared.set_teams(Teams { home: team("Madeira", "CAB"), away: team("Benfica", "SLB") });
+    let info = MatchInfo { competition: "Liga Betclic".to_string(), venue: "Pavilhão do Funchal".to_string(), ..MatchInfo::default() };
+    shared.set_match(info.clone());
+    simulate(&target, GAME);
+    assert_eq!(shared.game_summary(), None, "no summary before Final");
+
+    shared
+        .set_manual(|state| {
+            state.period_name = "Final".to_string();
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+    let summary: GameSummary = shared.game_summary().expect("summary once Final");
+    assert_eq!(summary.teams.home.abbrev, "CAB");
+    assert_eq!(summary.teams.away.abbrev, "SLB");
+    assert_eq!(summary.match_info, info);
+    assert_eq!((summary.home_score, summary.away_score), (Some(7), Some(6)));
+    assert_eq!(
+        summary.line_score,
+        [
+            PeriodScore { period: "1 Quarter".to_string(), home: Some(5), away: Some(3) },
+            PeriodScore { period: "2 Quarter".to_string(), home: Some(2), away: Some(3) },
+        ]
+    );
+    assert_eq!(summary.home, TeamTotals { fouls: 1, timeouts_used: 1, largest_lead: 2 });
+    assert_eq!(summary.away, TeamTotals { fouls: 4, timeouts_used: 1, largest_lead: 3 });
+    assert_eq!(summary.lead_changes, 3);
+    assert!(summary.ended_at_ms >= summary.started_at_ms);
+    assert_eq!(summary.duration_secs, (summary.ended_at_ms - summary.started_at_ms) / 1000);
+    assert_eq!(summary.started_at, TimeZone::parse("+01:00").unwrap().rfc3339(summary.started_at_ms));
+    assert!(summary.ended_at.ends_with("+01:00"), "{}", summary.ended_at);
+    assert!(summary.headline().starts_with("CAB 7 - 6 SLB, 3 lead changes, 0h 00m"), "{}", summary.headline());
+
+    let (request_line, body) = receive_post(&webhook, "204 No Content");
+    assert_eq!(request_line, "POST /hooks/final HTTP/1.1");
+    let posted: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(posted["homeScore"], 7);
+    assert_eq!(posted["away"]["fouls"], 4);
+    assert_eq!(posted["leadChanges"], 3);
+    assert_eq!(posted["startedAt"], summary.started_at);
+    assert_eq!(posted["lineScore"][1]["period"], "2 Quarter");
+    assert_eq!(posted["matchInfo"]["competition"], "Liga Betclic");
+}
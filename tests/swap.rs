@@ -0,0 +1,50 @@
+//! Swapped sides on the overlays: `?swap=` of the page against the server's `--swap-sides`,
+//! settled in the config written into each page, and the data that never changes sides.
+
+mod common;
+
+use common::{scores, Harness};
+use serde_json::Value;
+
+/// The `swapSides` written into the page served for `path`.
+fn swapped(server: &Harness, path: &str) -> bool {
+    let page = server.get(path);
+    assert_eq!(page.status, 200, "{}", path);
+    let text = page.text();
+    let start = text.find("id=\"scoreboard-config\">").expect("a page config") + 23;
+    let end = start + text[start..].find("</script>").unwrap();
+    let config: Value = serde_json::from_str(&text[start..end]).unwrap();
+    config["swapSides"].as_bool().expect("swapSides")
+}
+
+#[test]
+fn the_url_swaps_a_single_page() {
+    let server = Harness::start("");
+    for path in ["/", "/?theme=minimal", "/bug", "/slate"] {
+        assert!(!swapped(&server, path), "{}", path);
+    }
+    for path in ["/?swap=1", "/?theme=broadcast&swap=true", "/bug?swap=1", "/slate?swap=1"] {
+        assert!(swapped(&server, path), "{}", path);
+    }
+    assert!(!swapped(&server, "/?swap=0"));
+    assert!(!swapped(&server, "/?swap=maybe"), "other values leave it to the server");
+}
+
+#[test]
+fn with_both_set_the_url_wins_and_sides_are_swapped_once() {
+    let mut server = Harness::start("--swap-sides");
+    server.play(&[scores(45, 38)]);
+    assert!(swapped(&server, "/"), "the server's default");
+    assert!(swapped(&server, "/?swap=maybe"));
+    // Both asking for the swap is one swap, not a swap back
+    assert!(swapped(&server, "/?swap=1"));
+    assert!(swapped(&server, "/bug?swap=true"));
+    // The URL wins over the default
+    assert!(!swapped(&server, "/?swap=0"));
+    assert!(!swapped(&server, "/bug?swap=false"));
+
+    // The data keeps the real sides whatever the pages show
+    assert_eq!(server.get_json("/api/overlay").1["swapSides"], true);
+    let state = server.get_json("/api/state?swap=1").1;
+    assert_eq!((state["state"]["homeScore"].as_u64(), state["state"]["awayScore"].as_u64()), (Some(45), Some(38)));
+}
@@ -0,0 +1,54 @@
+//! systemd notifications: the message encoding and when the main loops count as alive.
+
+use scoreboard_rust::{
+    systemd::{self, LOOP_STALL_AFTER},
+    LoopBeat, LoopState,
+};
+use std::time::Duration;
+
+const NOW_MS: u64 = 1_773_515_142_250;
+
+#[test]
+fn notifications_are_key_value_lines() {
+    let message = systemd::encode(&[("READY", "1"), ("STATUS", "Console link active, 1 connected")]);
+    assert_eq!(message, b"READY=1\nSTATUS=Console link active, 1 connected\n");
+    // A line break would start a field of its own
+    assert_eq!(systemd::encode(&[("STATUS", "two\nlines\r\n")]), b"STATUS=two lines  \n");
+    assert_eq!(systemd::encode(&[]), b"");
+}
+
+#[test]
+fn both_loops_must_be_up_and_going_round() {
+    let seen = |ago_ms: u64| LoopState::LastSeen(NOW_MS - ago_ms);
+    let check = |ingest, web| systemd::check_loops(ingest, web, NOW_MS, LOOP_STALL_AFTER);
+
+    assert_eq!(check(seen(100), seen(1_000)), Ok(()));
+    assert_eq!(check(seen(10_000), seen(0)), Ok(()), "stuck only after the stall time");
+    assert_eq!(check(LoopState::Unwatched, seen(500)), Ok(()), "stdin input has no loop to watch");
+
+    assert_eq!(check(seen(100), LoopState::NotStarted), Err("web server not listening yet".to_string()));
+    assert_eq!(check(LoopState::NotStarted, seen(100)), Err("ingest server not listening yet".to_string()));
+    assert_eq!(check(seen(100), seen(12_500)), Err("web loop stuck for 12s".to_string()));
+    assert_eq!(check(seen(60_000), LoopState::Unwatched), Err("ingest loop stuck for 60s".to_string()));
+}
+
+#[test]
+fn loop_beats_record_what_they_saw() {
+    let beat = LoopBeat::default();
+    assert_eq!(beat.state(), LoopState::NotStarted);
+    beat.beat();
+    let LoopState::LastSeen(at) = beat.state() else { panic!("{:?}", beat.state()) };
+    assert_eq!(systemd::check_loops(LoopState::LastSeen(at), LoopState::Unwatched, at + 5, LOOP_STALL_AFTER), Ok(()));
+    beat.unwatched();
+    assert_eq!(beat.state(), LoopState::Unwatched);
+}
+
+#[test]
+fn watchdog_pings_at_half_the_timeout_of_our_own_process() {
+    assert_eq!(systemd::watchdog_interval(Some("30000000"), None, 42), Some(Duration::from_secs(15)));
+    assert_eq!(systemd::watchdog_interval(Some("30000000"), Some("42"), 42), Some(Duration::from_secs(15)));
+    assert_eq!(systemd::watchdog_interval(Some("30000000"), Some("7"), 42), None, "for another process");
+    assert_eq!(systemd::watchdog_interval(None, Some("42"), 42), None);
+    assert_eq!(systemd::watchdog_interval(Some("0"), None, 42), None);
+    assert_eq!(systemd::watchdog_interval(Some("soon"), None, 42), None);
+}
@@ -0,0 +1,101 @@
+//! `GET`/`PUT /api/teams`: team details validated, merged into every state, pushed to the
+//! overlay as the admin page edits them and kept in `--teams-file` across a restart.
+
+mod common;
+
+use common::{fresh_dir, scores, Harness, Response, WebSocket, WsMessage};
+use serde_json::json;
+use std::{
+    fs,
+    time::{Duration, Instant},
+};
+
+const TEAMS: &str = r##"{"home": {"name": "CAB Madeira", "abbrev": "CAB", "color": "#1E90FF", "logoUrl": "/media/CAB.svg"}, "away": {"name": "Benfica", "abbrev": "SLB", "color": "#FF0000"}}"##;
+
+fn put(server: &Harness, body: &str) -> Response {
+    server.request("PUT", "/api/teams", &[("Authorization", "Bearer secret"), ("Content-Type", "application/json")], body.as_bytes())
+}
+
+#[test]
+fn teams_survive_a_restart() {
+    let dir = fresh_dir("teams_restart");
+    let args = format!("--teams-file {}", dir.join("teams.json").display());
+    let mut server = Harness::start(&args);
+    assert_eq!(put(&server, TEAMS).status, 200);
+    server.play(&[scores(45, 38)]);
+    let (_, state) = server.get_json("/api/state");
+    assert_eq!((state["state"]["teams"]["home"]["name"].as_str(), state["state"]["homeScore"].as_u64()), (Some("CAB Madeira"), Some(45)));
+    server.stop();
+
+    let server = Harness::start(&args);
+    let (status, teams) = server.get_json("/api/teams");
+    assert_eq!(status, 200);
+    assert_eq!(teams["home"], serde_json::json!({ "name": "CAB Madeira", "abbrev": "CAB", "color": "#1E90FF", "logoUrl": "/media/CAB.svg" }));
+    assert_eq!((teams["away"]["abbrev"].as_str(), teams["away"]["logoUrl"].as_str()), (Some("SLB"), None));
+    assert_eq!(server.shared.current().teams.away.name, "Benfica", "in the state before any frame");
+}
+
+#[test]
+fn invalid_or_unauthorized_teams_are_refused() {
+    let server = Harness::start("");
+    let refused = |body: &str, expected: &str| {
+        let response = put(&server, body);
+        assert_eq!(response.status, 400, "{}", body);
+        assert!(response.text().contains(expected), "{}: {}", body, response.text());
+    };
+    refused(r#"{"home": {"name": "A", "abbrev": "ABCD"}, "away": {"name": "B"}}"#, "home.abbrev must be at most");
+    refused(r#"{"home": {"name": "A"}, "away": {"name": "B", "color": "red"}}"#, "Invalid away.color");
+    refused(r#"{"home": {"name": "A", "logoUrl": "javascript:alert(1)"}, "away": {"name": "B"}}"#, "Invalid home.logoUrl");
+
+    let anonymous = server.request("PUT", "/api/teams", &[("Content-Type", "application/json")], TEAMS.as_bytes());
+    assert_eq!(anonymous.status, 401);
+    assert_eq!(server.get_json("/api/teams").1["home"]["name"], "");
+}
+
+#[test]
+fn names_edited_on_the_admin_page_reach_the_overlay_and_the_file() {
+    let dir = fresh_dir("teams_edit");
+    let file = dir.join("teams.json");
+    let mut server = Harness::start(&format!("--teams-file {}", file.display()));
+    assert_eq!(put(&server, TEAMS).status, 200);
+    server.play(&[scores(45, 38)]);
+    let mut overlay = WebSocket::open(server.web, "/ws", &[]);
+    assert_eq!(overlay.next_text()["teams"]["home"]["name"], "CAB Madeira");
+
+    // The fields and button the page edits them with
+    let admin = server.request("GET", "/admin", &[("Authorization", "Bearer secret")], b"").text();
+    assert!(admin.contains(r#"<input class="team-name" data-field="name""#) && admin.contains(r#"id="apply-teams""#));
+
+    // What the page sends: the teams it got, with the names and abbreviations typed in
+    let mut edited = server.get_json("/api/teams").1;
+    edited["home"]["name"] = json!("FC Porto");
+    edited["home"]["abbrev"] = json!("fcp");
+    edited["away"]["name"] = json!("Sporting CP");
+    edited["away"]["abbrev"] = json!("scp");
+    let sent = Instant::now();
+    let response = put(&server, &edited.to_string());
+    assert_eq!(response.status, 200, "{}", response.text());
+    let expected = json!({
+        "home": { "name": "FC Porto", "abbrev": "FCP", "color": "#1E90FF", "logoUrl": "/media/CAB.svg" },
+        "away": { "name": "Sporting CP", "abbrev": "SCP", "color": "#FF0000", "logoUrl": null },
+    });
+    assert_eq!(response.json(), expected);
+
+    // Pushed to the overlay within a second
+    assert_eq!(overlay.next_text()["teams"], expected);
+    assert!(sent.elapsed() < Duration::from_secs(1), "{:?}", sent.elapsed());
+    assert_eq!(server.get_json("/api/state").1["state"]["teams"], expected);
+
+    // Saved whole, with no temporary file left beside it
+    assert_eq!(serde_json::from_slice::<serde_json::Value>(&fs::read(&file).unwrap()).unwrap(), expected);
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1, "{:?}", fs::read_dir(&dir).unwrap().collect::<Vec<_>>());
+
+    // A refused edit names the field for the page to mark, and changes nothing
+    edited["away"]["abbrev"] = json!("SPORT");
+    let refused = put(&server, &edited.to_string());
+    assert_eq!(refused.status, 400);
+    assert!(refused.json()["error"].as_str().unwrap().starts_with("away.abbrev"), "{}", refused.text());
+    assert!(!matches!(overlay.next_within(Duration::from_millis(500)), Some(WsMessage::Text(_))), "nothing pushed");
+    assert_eq!(server.get_json("/api/teams").1, expected);
+    assert_eq!(serde_json::from_slice::<serde_json::Value>(&fs::read(&file).unwrap()).unwrap(), expected);
+}
@@ -0,0 +1,106 @@
+//! Overlay themes: the page served at `/` for each `?theme=`, `--theme` as the default, the
+//! `scoreboard_prefs` cookie between the two, and the fallback for names that aren't a theme.
+
+mod common;
+
+use common::{scores, Harness};
+
+/// The stylesheet of the page served for `path`, which tells the themes apart.
+fn stylesheet(server: &Harness, path: &str) -> String {
+    stylesheet_with(server, path, None)
+}
+
+/// [`stylesheet`] for a browser sending `cookie`.
+fn stylesheet_with(server: &Harness, path: &str, cookie: Option<&str>) -> String {
+    let page = match cookie {
+        Some(cookie) => server.request("GET", path, &[("Cookie", cookie)], b""),
+        None => server.get(path),
+    };
+    assert_eq!(page.status, 200, "{}", path);
+    assert_eq!(page.header("content-type"), Some("text/html; charset=utf-8"));
+    let text = page.text();
+    let start = text.find("<link rel=\"stylesheet\" href=\"").expect("a stylesheet") + 29;
+    text[start..].split('"').next().unwrap().to_string()
+}
+
+#[test]
+fn each_theme_serves_its_own_template() {
+    let server = Harness::start("");
+    assert_eq!(stylesheet(&server, "/"), "overlay.css");
+    assert_eq!(stylesheet(&server, "/?theme=classic"), "overlay.css");
+    assert_eq!(stylesheet(&server, "/?theme=minimal"), "themes/minimal.css");
+    assert_eq!(stylesheet(&server, "/?theme=broadcast"), "themes/broadcast.css");
+    assert_eq!(stylesheet(&server, "/?theme=neon"), "overlay.css", "unknown themes fall back");
+
+    // Every theme fills in the same elements from the same data
+    for theme in ["classic", "minimal", "broadcast"] {
+        let page = server.get(&format!("/?theme={}", theme)).text();
+        for id in ["home-score", "away-score", "time"] {
+            assert!(page.contains(&format!("id=\"{}\"", id)), "{} has no #{}", theme, id);
+        }
+        assert!(page.contains("overlay.js"), "{}", theme);
+    }
+}
+
+#[test]
+fn the_default_comes_from_the_config_or_the_cookie() {
+    let server = Harness::start("--theme minimal");
+    assert_eq!(stylesheet(&server, "/"), "themes/minimal.css");
+    assert_eq!(stylesheet(&server, "/?theme=neon"), "themes/minimal.css");
+    assert_eq!(stylesheet(&server, "/?theme=broadcast"), "themes/broadcast.css");
+
+    let cookie = server.request("GET", "/", &[("Cookie", "scoreboard_prefs=theme=broadcast")], b"");
+    assert!(cookie.text().contains("themes/broadcast.css"));
+}
+
+#[test]
+fn the_query_wins_over_the_cookie_which_wins_over_the_default() {
+    let mut server = Harness::start("--theme minimal");
+    server.play(&[scores(45, 38)]);
+    let broadcast = Some("scoreboard_prefs=theme=broadcast");
+
+    // Cookie only, query only, both
+    assert_eq!(stylesheet_with(&server, "/", broadcast), "themes/broadcast.css");
+    assert_eq!(stylesheet_with(&server, "/?theme=classic", None), "overlay.css");
+    assert_eq!(stylesheet_with(&server, "/?theme=classic", broadcast), "overlay.css");
+    // A query naming no theme is no query
+    assert_eq!(stylesheet_with(&server, "/?theme=neon", broadcast), "themes/broadcast.css");
+    assert_eq!(stylesheet_with(&server, "/?theme=", broadcast), "themes/broadcast.css");
+
+    // The language the same way, and the scale, which only the cookie sets on the server
+    let lang = |path: &str, cookie: Option<&str>| {
+        let headers: Vec<(&str, &str)> = cookie.map(|cookie| ("Cookie", cookie)).into_iter().collect();
+        server.request("GET", path, &headers, b"").json()["state"]["display"]["lang"].as_str().unwrap().to_string()
+    };
+    let pt = Some("scoreboard_prefs=lang=pt");
+    assert_eq!(lang("/api/state", None), "en");
+    assert_eq!(lang("/api/state", pt), "pt");
+    assert_eq!(lang("/api/state?lang=fr", None), "fr");
+    assert_eq!(lang("/api/state?lang=fr", pt), "fr");
+    let scale = |cookie: &str| server.request("GET", "/api/overlay", &[("Cookie", cookie)], b"").json()["scale"].clone();
+    assert_eq!(scale("scoreboard_prefs=scale=1.5"), 1.5);
+
+    // Among other cookies, quoted, and with junk around what parses
+    for cookie in [
+        "session=abc; scoreboard_prefs=theme=broadcast; other=1",
+        "scoreboard_prefs=\"theme=broadcast&scale=1.5\"",
+        "scoreboard_prefs=scale=99&theme=broadcast&lang=xx&junk&=&theme",
+    ] {
+        assert_eq!(stylesheet_with(&server, "/", Some(cookie)), "themes/broadcast.css", "{}", cookie);
+    }
+    // Junk alone leaves the defaults
+    for cookie in [
+        "scoreboard_prefs=theme=neon&scale=abc&lang=xx",
+        "scoreboard_prefs=scale=-1&scale=0&scale=NaN&scale=inf",
+        "scoreboard_prefs",
+        "scoreboard_prefs=",
+        ";;=; =theme=broadcast",
+        "prefs=theme=broadcast",
+        "scoreboard_prefs=%74heme=broadcast",
+        "scoreboard_prefs=theme=broadcast\u{fffd}",
+    ] {
+        assert_eq!(stylesheet_with(&server, "/", Some(cookie)), "themes/minimal.css", "{}", cookie);
+        assert_eq!(scale(cookie), serde_json::Value::Null, "{}", cookie);
+        assert_eq!(lang("/api/state", Some(cookie)), "en", "{}", cookie);
+    }
+}
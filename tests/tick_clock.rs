@@ -0,0 +1,91 @@
+//! `--tick-clock`: the running clock counted down between frames as `displayClock`, snapped to
+//! the console's clock when it changes, held at 0:00 and still while stopped.
+
+mod common;
+
+use common::{clock_at, scores, wait_until, Harness};
+use std::{thread, time::Duration};
+
+fn display_clock(server: &Harness) -> String {
+    server.get_json("/api/state").1["state"]["displayClock"].as_str().unwrap().to_string()
+}
+
+#[test]
+fn the_clock_counts_down_between_frames_and_snaps_to_the_console() {
+    let mut server = Harness::start("--tick-clock");
+    server.play(&[clock_at("0812", true)]);
+    assert_eq!(display_clock(&server), "08:12");
+    wait_until("the clock to tick", || display_clock(&server) == "08:10");
+    assert_eq!(server.get_json("/api/state").1["state"]["clock"], "08:12", "the console's clock is kept");
+
+    // A frame without a new clock doesn't set it back
+    server.play(&[scores(2, 0)]);
+    let display = display_clock(&server);
+    assert!(display.as_str() <= "08:10", "{}", display);
+
+    // The console's clock wins as soon as it changes, ahead of or behind the ticker
+    server.play(&[clock_at("0830", true)]);
+    let snapped = display_clock(&server);
+    assert!(snapped == "08:30" || snapped == "08:29", "{}", snapped);
+    server.play(&[clock_at("0700", true)]);
+    let snapped = display_clock(&server);
+    assert!(snapped == "07:00" || snapped == "06:59", "{}", snapped);
+}
+
+#[test]
+fn the_clock_stops_at_zero() {
+    let mut server = Harness::start("--tick-clock");
+    server.play(&[clock_at("0001", true)]);
+    wait_until("the clock to run out", || display_clock(&server) == "00.0");
+    thread::sleep(Duration::from_millis(500));
+    assert_eq!(display_clock(&server), "00.0", "never below");
+}
+
+#[test]
+fn a_stopped_clock_or_a_period_break_does_not_tick() {
+    let mut server = Harness::start("--tick-clock");
+    server.play(&[clock_at("0812", false)]);
+    thread::sleep(Duration::from_millis(1500));
+    assert_eq!(display_clock(&server), "08:12");
+
+    server
+        .shared
+        .set_manual(|state| {
+            state.time = "05:00".to_string();
+            state.game_state = "running".to_string();
+            state.period_name = "Halftime".to_string();
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+    thread::sleep(Duration::from_millis(1500));
+    assert_eq!(display_clock(&server), "05:00");
+}
+
+#[test]
+fn without_the_option_the_display_is_the_console_clock() {
+    let mut server = Harness::start("");
+    server.play(&[clock_at("0812", true)]);
+    thread::sleep(Duration::from_millis(1500));
+    assert_eq!(display_clock(&server), "08:12");
+}
+
+#[test]
+fn a_held_override_keeps_its_clock_against_the_console() {
+    let mut server = Harness::start("--tick-clock --override-mode hold");
+    server.play(&[clock_at("0812", false)]);
+    server
+        .shared
+        .set_manual(|state| {
+            state.time = "05:00".to_string();
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+    assert_eq!(display_clock(&server), "05:00");
+
+    // The console's running clock is ignored while held, ticker included
+    server.send_frames(&[clock_at("0700", true)]);
+    wait_until("the frame to be read", || server.shared.status().metrics.peers.values().any(|peer| peer.frames == 2));
+    thread::sleep(Duration::from_millis(1500));
+    assert_eq!(display_clock(&server), "05:00");
+    assert_eq!(server.get_json("/api/state").1["state"]["clock"], "05:00");
+}
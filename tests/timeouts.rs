@@ -0,0 +1,90 @@
+//! Time-outs: a team's count going down while the clock is stopped starts one of
+//! `--timeout-length`, shown as `activeTimeout` in the state, until it runs out, the clock runs
+//! again or the operator puts the count back up.
+
+mod common;
+
+use common::{clock_with_timeouts, wait_until, Harness};
+use serde_json::Value;
+
+fn active_timeout(server: &Harness) -> Value {
+    server.get_json("/api/state").1["state"]["activeTimeout"].clone()
+}
+
+/// The time-out events logged so far, as `(type, side)`.
+fn timeout_events(server: &Harness) -> Vec<(String, String)> {
+    server.get_json("/api/events?since=0").1["events"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|event| matches!(event["type"].as_str(), Some("TimeoutCalled" | "TimeoutEnded")))
+        .map(|event| (event["type"].as_str().unwrap().to_string(), event["side"].as_str().unwrap().to_string()))
+        .collect()
+}
+
+fn events(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+    expected.iter().map(|(kind, side)| (kind.to_string(), side.to_string())).collect()
+}
+
+#[test]
+fn a_time_out_is_detected_and_runs_out() {
+    let mut server = Harness::start("--timeout-length 2");
+    server.play(&[clock_with_timeouts("0812", false, 2, 2)]);
+    assert_eq!(active_timeout(&server), Value::Null, "the first count isn't a time-out");
+
+    server.play(&[clock_with_timeouts("0812", false, 1, 2)]);
+    let timeout = active_timeout(&server);
+    assert_eq!(timeout["side"], "home");
+    assert_eq!(timeout["remainingSeconds"], 2);
+    let ends_at = timeout["endsAtMs"].as_u64().unwrap();
+    let now = server.get_json("/api/time").1["serverTimeMs"].as_u64().unwrap();
+    assert!(ends_at > now && ends_at <= now + 2_000, "{} {}", ends_at, now);
+    assert_eq!(timeout_events(&server), events(&[("TimeoutCalled", "home")]));
+
+    // Counted down by the server, then over on its own
+    wait_until("the time-out to count down", || active_timeout(&server)["remainingSeconds"] == 1);
+    wait_until("the time-out to run out", || active_timeout(&server).is_null());
+    assert_eq!(timeout_events(&server), events(&[("TimeoutCalled", "home"), ("TimeoutEnded", "home")]));
+}
+
+#[test]
+fn a_time_out_ends_early_when_the_clock_runs_or_the_count_goes_back_up() {
+    let mut server = Harness::start("--timeout-length 60");
+    server.play(&[clock_with_timeouts("0812", false, 2, 2), clock_with_timeouts("0812", false, 2, 1)]);
+    assert_eq!(active_timeout(&server)["side"], "away");
+
+    // The clock running again
+    server.send_frames(&[clock_with_timeouts("0812", true, 2, 1)]);
+    wait_until("the clock to end the time-out", || active_timeout(&server).is_null());
+    assert_eq!(timeout_events(&server), events(&[("TimeoutCalled", "away"), ("TimeoutEnded", "away")]));
+
+    // The operator taking a time-out back
+    server.send_frames(&[clock_with_timeouts("0811", false, 2, 1)]);
+    server.send_frames(&[clock_with_timeouts("0811", false, 1, 1)]);
+    wait_until("a home time-out", || active_timeout(&server)["side"] == "home");
+    server.send_frames(&[clock_with_timeouts("0811", false, 2, 1)]);
+    wait_until("the count to end the time-out", || active_timeout(&server).is_null());
+    let taken_back = [("TimeoutCalled", "away"), ("TimeoutEnded", "away"), ("TimeoutCalled", "home"), ("TimeoutEnded", "home")];
+    assert_eq!(timeout_events(&server), events(&taken_back));
+
+    // One frame taking a time-out and starting the clock: none to play
+    server.send_frames(&[clock_with_timeouts("0810", true, 1, 1)]);
+    wait_until("the frame", || server.get_json("/api/state").1["state"]["homeTimeouts"] == 1);
+    assert_eq!(active_timeout(&server), Value::Null);
+    assert_eq!(timeout_events(&server).len(), 5, "called, but nothing to end");
+}
+
+#[test]
+fn a_time_out_right_after_another_replaces_it() {
+    let mut server = Harness::start("--timeout-length 60");
+    server.play(&[clock_with_timeouts("0500", false, 3, 3), clock_with_timeouts("0500", false, 2, 3)]);
+    assert_eq!(active_timeout(&server)["side"], "home");
+
+    server.send_frames(&[clock_with_timeouts("0500", false, 2, 2)]);
+    wait_until("the away time-out", || active_timeout(&server)["side"] == "away");
+    assert_eq!(active_timeout(&server)["remainingSeconds"], 60, "a full one");
+    assert_eq!(
+        timeout_events(&server),
+        events(&[("TimeoutCalled", "home"), ("TimeoutCalled", "away"), ("TimeoutEnded", "home")])
+    );
+}
@@ -0,0 +1,116 @@
+//! Timestamps in a configured zone: offsets across summer time changes, from the zoneinfo
+//! database and from POSIX rules, and the exports written in them.
+
+use scoreboard_rust::{BasketballServer, GameEvent, GameLog, GameState, JsonlLog, ServerConfig, TimeZone};
+use std::{fs, path::Path};
+
+/// 2026-03-29 01:00 UTC: Lisbon goes from 01:00 WET to 02:00 WEST.
+const SPRING_FORWARD_MS: u64 = 1_774_746_000_000;
+/// 2026-10-25 01:00 UTC: Lisbon goes back from 02:00 WEST to 01:00 WET.
+const FALL_BACK_MS: u64 = 1_792_890_000_000;
+/// 2040-03-25 01:00 UTC, past the transitions listed in zoneinfo files.
+const SPRING_FORWARD_2040_MS: u64 = 2_216_250_000_000;
+
+/// Portugal's rule: last Sunday of March at 01:00 to last Sunday of October at 02:00.
+const LISBON_RULE: &str = "WET0WEST,M3.5.0/1,M10.5.0";
+
+fn assert_lisbon(zone: &TimeZone) {
+    let at = |timestamp_ms: u64| zone.rfc3339(timestamp_ms);
+    assert_eq!(at(SPRING_FORWARD_MS - 1), "2026-03-29T00:59:59.999+00:00");
+    assert_eq!(at(SPRING_FORWARD_MS), "2026-03-29T02:00:00.000+01:00");
+    assert_eq!(at(FALL_BACK_MS - 1), "2026-10-25T01:59:59.999+01:00");
+    assert_eq!(at(FALL_BACK_MS), "2026-10-25T01:00:00.000+00:00");
+    assert_eq!(at(SPRING_FORWARD_2040_MS - 1), "2040-03-25T00:59:59.999+00:00");
+    assert_eq!(at(SPRING_FORWARD_2040_MS), "2040-03-25T02:00:00.000+01:00");
+    assert_eq!((zone.offset_at(SPRING_FORWARD_MS - 1), zone.offset_at(SPRING_FORWARD_MS)), (0, 3600));
+}
+
+#[test]
+fn lisbon_from_the_zoneinfo_database() {
+    // The TZif file from tzdata, so the test doesn't depend on the machine having the database
+    // (Windows, containers); `TZDIR` is searched before the system directories
+    std::env::set_var("TZDIR", Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/zoneinfo"));
+    let zone = TimeZone::parse("Europe/Lisbon").unwrap();
+    assert_eq!(zone.name(), "Europe/Lisbon");
+    assert_lisbon(&zone);
+}
+
+#[test]
+fn posix_rules_do_without_the_database() {
+    let zone = TimeZone::parse(LISBON_RULE).unwrap();
+    assert_eq!(zone.name(), LISBON_RULE);
+    assert_lisbon(&zone);
+
+    // South of the equator summer time spans the new year: first Sunday of October at 02:00
+    // to first Sunday of April at 03:00
+    let sydney = TimeZone::parse("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+    assert_eq!(sydney.rfc3339(1_768_478_400_000), "2026-01-15T23:00:00.000+11:00");
+    assert_eq!(sydney.rfc3339(1_784_116_800_000), "2026-07-15T22:00:00.000+10:00");
+
+    let no_summer = TimeZone::parse("<-03>3").unwrap();
+    assert_eq!(no_summer.rfc3339(SPRING_FORWARD_MS), "2026-03-28T22:00:00.000-03:00");
+}
+
+#[test]
+fn fixed_offsets_and_utc() {
+    let zone = |value: &str| TimeZone::parse(value).unwrap();
+    assert_eq!(zone("UTC").rfc3339(SPRING_FORWARD_MS), "2026-03-29T01:00:00.000Z");
+    assert_eq!(zone("+01:00").rfc3339(FALL_BACK_MS), "2026-10-25T02:00:00.000+01:00");
+    assert_eq!(zone("-0330").rfc3339(FALL_BACK_MS), "2026-10-24T21:30:00.000-03:30");
+    assert_eq!(zone("+05").name(), "+05:00");
+    assert!(TimeZone::parse("system").is_ok());
+
+    for invalid in ["Mars/Olympus_Mons", "+25:00", "../../etc/passwd", "/etc/localtime", "WET0WEST,M13.5.0,M10.5.0"] {
+        let error = TimeZone::parse(invalid).unwrap_err();
+        assert!(error.contains("Unknown time zone"), "{}: {}", invalid, error);
+    }
+}
+
+#[test]
+fn exports_are_written_in_the_zone() {
+    let zone = TimeZone::parse(LISBON_RULE).unwrap();
+
+    let mut log = GameLog::default();
+    for timestamp_ms in [SPRING_FORWARD_MS - 60_000, SPRING_FORWARD_MS + 60_000] {
+        log.record(timestamp_ms, &GameState::default(), &[GameEvent::Horn]);
+    }
+    let mut csv = Vec::new();
+    log.write_csv(&mut csv, &zone).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let wall_clocks: Vec<&str> = csv.lines().map(|line| line.split(',').next().unwrap()).collect();
+    assert_eq!(wall_clocks, ["wall_clock", "2026-03-29T00:59:00.000+00:00", "2026-03-29T02:01:00.000+01:00"]);
+
+    // File names take the date in the zone: 23:30 UTC is already the next day at +01:00
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("timezone_jsonl");
+    let _ = fs::remove_dir_all(&dir);
+    let mut jsonl = JsonlLog::new(format!("{}/scores-%Y%m%d.jsonl", dir.display()), None, TimeZone::parse("+01:00").unwrap());
+    jsonl.append(FALL_BACK_MS - 150 * 60_000, &["{}".to_string()], false).unwrap();
+    assert_eq!(jsonl.path().unwrap(), dir.join("scores-20261024.jsonl"));
+    jsonl.append(FALL_BACK_MS - 90 * 60_000, &["{}".to_string()], false).unwrap();
+    assert_eq!(jsonl.path().unwrap(), dir.join("scores-20261025.jsonl"));
+}
+
+#[test]
+fn published_states_are_stamped_in_the_configured_zone() {
+    let mut config = ServerConfig::default();
+    config.timezone = TimeZone::parse("-03:00").unwrap();
+    let shared = BasketballServer::new(config).shared();
+    assert_eq!(shared.published_at_ms(), None);
+
+    let mut frame = shared.current();
+    frame.home_score = "  2".to_string();
+    shared.update(frame);
+    let published = shared.published_at_ms().expect("published");
+    assert_eq!(shared.timezone().name(), "-03:00");
+    assert!(shared.timezone().rfc3339(published).ends_with("-03:00"));
+}
+
+#[test]
+fn timezone_option() {
+    let args = |line: &str| line.split_whitespace().map(String::from).collect::<Vec<_>>();
+    let config = ServerConfig::from_args(&args(&format!("--timezone {}", LISBON_RULE))).unwrap();
+    assert_eq!(config.timezone.name(), LISBON_RULE);
+    assert_eq!(ServerConfig::from_args(&args("--timezone -03:00")).unwrap().timezone.name(), "-03:00");
+    let error = ServerConfig::from_args(&args("--timezone Atlantis/Capital")).unwrap_err();
+    assert!(error.contains("--timezone") && error.contains("Atlantis/Capital"), "{}", error);
+}
@@ -0,0 +1,177 @@
+//! The terminal dashboard: its layout drawn on a plain buffer, the `--subscribers` feed of
+//! `scoreboard-rust tui`, and the command refusing to run without a terminal.
+
+mod common;
+
+use common::run;
+use ratatui::{buffer::Buffer, layout::Rect, widgets::Widget};
+use scoreboard_rust::{
+    dashboard::{self, big_text, Dashboard, Update},
+    GameState, TeamInfo,
+};
+use std::{
+    io::Write,
+    net::{TcpListener, TcpStream},
+    sync::mpsc::Receiver,
+    thread,
+    time::Duration,
+};
+
+fn state(home: &str, away: &str, time: &str) -> GameState {
+    let mut state = GameState::default();
+    state.home_score = home.to_string();
+    state.away_score = away.to_string();
+    state.time = time.to_string();
+    state.period_name = "2 Quarter".to_string();
+    state.home_fouls = "3".to_string();
+    state.away_fouls = "1".to_string();
+    state.home_timeouts = "2".to_string();
+    state.away_timeouts = "1".to_string();
+    state.game_state = "running".to_string();
+    state
+}
+
+/// The lines `dashboard` draws on a `width` by `height` terminal, without trailing blanks.
+fn screen(dashboard: &Dashboard, width: u16, height: u16) -> Vec<String> {
+    let area = Rect::new(0, 0, width, height);
+    let mut buffer = Buffer::empty(area);
+    dashboard.render(area, &mut buffer);
+    (0..height)
+        .map(|y| (0..width).map(|x| buffer[(x, y)].symbol()).collect::<String>().trim_end().to_string())
+        .collect()
+}
+
+#[test]
+fn scores_and_clock_share_the_digit_rows() {
+    let mut game = state(" 45", " 38", "08:12");
+    game.teams.home = TeamInfo { abbrev: "CAB".to_string(), ..TeamInfo::default() };
+    game.possession = "away".to_string();
+    game.shot_clock = "14".to_string();
+    let mut dashboard = Dashboard::default();
+    dashboard.apply(game);
+    dashboard.set_link("connected to 127.0.0.1:9200");
+
+    let lines = screen(&dashboard, 60, 24);
+    assert_eq!(lines.len(), 24);
+    assert_eq!(lines[0], format!("CAB{}2 Quarter{}AWAY", " ".repeat(22), " ".repeat(22)));
+    // 45, 08:12 and 38, top rows
+    assert_eq!(big_text("08:12")[0], "███ ███     █ ███");
+    assert_eq!(lines[2], format!("█ █ ███{}███ ███     █ ███{}███ ███", " ".repeat(14), " ".repeat(15)));
+    assert!(lines[8].starts_with("Fouls 3  Time-outs 2"), "{}", lines[8]);
+    assert!(lines[8].contains("possession >"), "{}", lines[8]);
+    assert!(lines[8].ends_with("Fouls 1  Time-outs 1"), "{}", lines[8]);
+    assert!(lines[9].starts_with("Link: connected to 127.0.0.1:9200"), "{}", lines[9]);
+    assert!(lines[9].contains("Shot clock 14"), "{}", lines[9]);
+    assert!(lines[9].ends_with("running"), "{}", lines[9]);
+    assert!(lines[10].starts_with(" Events ─"), "{}", lines[10]);
+    assert_eq!(lines.last().unwrap(), "q quit, e hide events");
+}
+
+#[test]
+fn event_pane_lists_the_latest_events_and_toggles() {
+    let mut fouled = state(" 47", " 38", "07:40");
+    fouled.away_fouls = "2".to_string();
+    // Clock ticks are not listed
+    let mut dashboard = Dashboard::default();
+    for state in [state(" 45", " 38", "08:12"), state(" 47", " 38", "07:55"), state(" 47", " 38", "07:54"), fouled] {
+        dashboard.apply(state);
+    }
+
+    let lines = screen(&dashboard, 60, 24);
+    let pane = lines.iter().position(|line| line.starts_with(" Events")).expect("event pane");
+    let events: Vec<&String> = lines[pane + 1..lines.len() - 1].iter().filter(|line| !line.is_empty()).collect();
+    assert_eq!(events.len(), 2, "{:?}", events);
+    assert!(events[0].starts_with("  07:55  ") && events[0].contains("45 -> 47"), "{}", events[0]);
+    assert!(events[1].starts_with("  07:40  ") && events[1].contains("1 -> 2"), "{}", events[1]);
+
+    // A short terminal keeps the newest events
+    let lines = screen(&dashboard, 60, 13);
+    assert_eq!(lines[11], events[1].as_str());
+    assert_eq!(lines[12], "q quit, e hide events");
+
+    dashboard.toggle_events();
+    let lines = screen(&dashboard, 60, 24);
+    assert!(!lines.iter().any(|line| line.contains("Events") || line.contains("07:40")), "{:?}", lines);
+    assert_eq!(lines.last().unwrap(), "q quit, e show events");
+}
+
+#[test]
+fn a_stale_state_is_marked_on_the_link_line() {
+    let mut game = state(" 45", " 38", "08:12");
+    game.stale = true;
+    let mut dashboard = Dashboard::default();
+    dashboard.apply(game);
+    dashboard.set_link("no console");
+    assert!(screen(&dashboard, 60, 24)[9].starts_with("Link: no console (stale)"));
+}
+
+#[test]
+fn without_a_state_the_link_is_shown() {
+    let mut dashboard = Dashboard::default();
+    dashboard.set_link("cannot connect to 127.0.0.1:9200");
+    let lines = screen(&dashboard, 50, 10);
+    assert_eq!(lines[0].trim(), "No game state yet");
+    assert_eq!(lines[1].trim(), "Link: cannot connect to 127.0.0.1:9200");
+    assert_eq!(lines[9], "q quit, e hide events");
+}
+
+/// A `--subscribers` stream that sends `states`, then stays open; returns its address and
+/// the connection once the dashboard has taken it.
+fn subscribers(states: Vec<GameState>) -> (String, thread::JoinHandle<TcpStream>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let stream = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        for state in states {
+            stream.write_all(format!("{}\n", serde_json::to_string(&state).unwrap()).as_bytes()).unwrap();
+        }
+        stream
+    });
+    (address, stream)
+}
+
+fn next(updates: &Receiver<Update>) -> Update {
+    updates.recv_timeout(Duration::from_secs(5)).expect("an update from the feed")
+}
+
+#[test]
+fn the_feed_follows_a_subscribers_stream() {
+    let (address, stream) = subscribers(vec![state(" 45", " 38", "08:12"), state(" 47", " 38", "07:55")]);
+    let updates = dashboard::subscribe(&address);
+    let mut dashboard = Dashboard::default();
+
+    match next(&updates) {
+        Update::Link(link) => assert_eq!(link, format!("connected to {}", address)),
+        other => panic!("expected the link first, got {:?}", other),
+    }
+    for _ in 0..2 {
+        dashboard.update(next(&updates));
+    }
+    dashboard.set_link(format!("connected to {}", address));
+    let lines = screen(&dashboard, 60, 24);
+    assert!(lines.iter().any(|line| line.starts_with("  07:55  ") && line.contains("45 -> 47")), "{:?}", lines);
+
+    // The server going away is reported, then the feed retries
+    drop(stream.join().unwrap());
+    match next(&updates) {
+        Update::Link(link) => assert_eq!(link, format!("disconnected from {}, retrying", address)),
+        other => panic!("expected the link, got {:?}", other),
+    }
+}
+
+#[test]
+fn the_feed_reports_a_server_it_cannot_reach() {
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let address = format!("127.0.0.1:{}", port);
+    match next(&dashboard::subscribe(&address)) {
+        Update::Link(link) => assert!(link.starts_with(&format!("cannot connect to {}: ", address)), "{}", link),
+        other => panic!("expected the link, got {:?}", other),
+    }
+}
+
+#[test]
+fn the_command_needs_a_terminal() {
+    let run = run(&["tui", "127.0.0.1:9"], b"");
+    assert_eq!(run.code, Some(1));
+    assert!(run.stderr.contains("the dashboard needs a terminal on standard output"), "{}", run.stderr);
+}
@@ -0,0 +1,133 @@
+//! TCP and UDP input into one server: both update the same state, tagged with their source.
+
+mod common;
+
+use common::{run_ok, scores, Harness};
+use ring::hmac;
+use scoreboard_rust::{ServerConfig, SharedState};
+use std::{
+    net::UdpSocket,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Wait for the state to show `home` points and return its source.
+fn source_once_home_has(shared: &SharedState, home: u32) -> Option<&'static str> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while shared.current().home_score.trim() != home.to_string() {
+        assert!(Instant::now() < deadline, "the frame giving home {} never arrived", home);
+        thread::sleep(Duration::from_millis(10));
+    }
+    shared.status().source
+}
+
+#[test]
+fn both_inputs_feed_one_state() {
+    let mut server = Harness::start("--udp-addr 127.0.0.1:0");
+    let shared = Arc::clone(&server.shared);
+    let udp_address = server.udp.unwrap();
+    let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    server.send_frames(&[scores(2, 0)]);
+    assert_eq!(source_once_home_has(&shared, 2), Some("tcp"));
+    udp.send_to(&scores(4, 0).to_bytes(), udp_address).unwrap();
+    assert_eq!(source_once_home_has(&shared, 4), Some("udp"));
+    // A frame split over two datagrams
+    let frame = scores(6, 0).to_bytes();
+    udp.send_to(&frame[..5], udp_address).unwrap();
+    udp.send_to(&frame[5..], udp_address).unwrap();
+    assert_eq!(source_once_home_has(&shared, 6), Some("udp"));
+    server.send_frames(&[scores(8, 0)]);
+    assert_eq!(source_once_home_has(&shared, 8), Some("tcp"));
+
+    // Both inputs stop together
+    server.stop();
+    assert!(UdpSocket::bind(udp_address).is_ok(), "the UDP socket is closed");
+}
+
+/// Send `bytes` as one datagram to `address` with `send`, signed when `auth_token` is set.
+fn send_datagram(address: &str, bytes: &[u8], auth_token: Option<&str>) {
+    let hex: String = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+    let mut args = vec!["send", "--to", address, "--udp", "--hex", &hex];
+    if let Some(token) = auth_token {
+        args.extend(["--auth-token", token]);
+    }
+    run_ok(&args);
+}
+
+/// The datagrams `send --udp --auth-token` sends for each of `frames`, caught on a socket
+/// of the test's own.
+fn signed_datagrams(frames: &[&[u8]], token: &str) -> Vec<Vec<u8>> {
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    receiver.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let address = receiver.local_addr().unwrap().to_string();
+    let mut buffer = [0u8; 256];
+    frames
+        .iter()
+        .map(|frame| {
+            send_datagram(&address, frame, Some(token));
+            let (n, _) = receiver.recv_from(&mut buffer).unwrap();
+            buffer[..n].to_vec()
+        })
+        .collect()
+}
+
+#[test]
+fn datagrams_are_signed_with_hmac_sha256_over_a_counter() {
+    let message: &[u8] = b"what do ya want for nothing?";
+    let datagrams = signed_datagrams(&[message, message], "Jefe");
+    let key = hmac::Key::new(hmac::HMAC_SHA256, b"Jefe");
+    let mut counters = Vec::new();
+    for datagram in &datagrams {
+        let (signed, tag) = datagram.split_at(datagram.len() - 32);
+        assert_eq!(&signed[..message.len()], message, "the payload comes first");
+        assert_eq!(signed.len(), message.len() + 8, "then an 8-byte counter");
+        assert!(hmac::verify(&key, signed, tag).is_ok(), "the tag covers the payload and the counter");
+        counters.push(u64::from_be_bytes(signed[message.len()..].try_into().unwrap()));
+    }
+    assert!(counters[0] < counters[1], "{:?}", counters);
+}
+
+#[test]
+fn with_an_auth_token_only_signed_datagrams_are_applied() {
+    let server = Harness::start("--udp-addr 127.0.0.1:0 --auth-token s3cret");
+    let udp_address = server.udp.unwrap().to_string();
+
+    send_datagram(&udp_address, &scores(3, 0).to_bytes(), None);
+    send_datagram(&udp_address, &scores(5, 0).to_bytes(), Some("guess"));
+    send_datagram(&udp_address, &scores(7, 0).to_bytes(), Some("s3cret"));
+    assert_eq!(source_once_home_has(&server.shared, 7), Some("udp"));
+    assert_eq!(server.shared.version(), 1, "the unsigned and the badly signed datagrams were dropped");
+    assert_eq!(server.shared.status().metrics.auth_failures, 2);
+}
+
+#[test]
+fn a_replayed_datagram_is_dropped() {
+    let server = Harness::start("--udp-addr 127.0.0.1:0 --auth-token s3cret");
+    let udp_address = server.udp.unwrap();
+    let (three, five) = (scores(3, 0).to_bytes(), scores(5, 0).to_bytes());
+    let datagrams = signed_datagrams(&[&three, &five], "s3cret");
+    let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    udp.send_to(&datagrams[0], udp_address).unwrap();
+    source_once_home_has(&server.shared, 3);
+    udp.send_to(&datagrams[1], udp_address).unwrap();
+    source_once_home_has(&server.shared, 5);
+
+    // Captured on the network and sent again, from the same address or another port
+    udp.send_to(&datagrams[0], udp_address).unwrap();
+    UdpSocket::bind("127.0.0.1:0").unwrap().send_to(&datagrams[1], udp_address).unwrap();
+    common::wait_until("the replays to be refused", || server.shared.status().metrics.auth_failures == 2);
+    assert_eq!(server.shared.current().home_score.trim(), "5");
+    assert_eq!(server.shared.version(), 2);
+}
+
+#[test]
+fn udp_needs_tcp_input() {
+    let args = |line: &str| line.split_whitespace().map(String::from).collect::<Vec<_>>();
+    assert!(ServerConfig::from_args(&args("stdin --udp-addr 0.0.0.0:4002")).unwrap_err().contains("--udp-addr"));
+    assert!(ServerConfig::from_args(&args("--echo --udp-addr 0.0.0.0:4002")).is_err());
+    assert!(ServerConfig::from_args(&args("--udp-addr 4002")).is_err());
+    assert!(ServerConfig::from_args(&args("--tcp-addr 0.0.0.0:4001 --listen 0.0.0.0:4005")).is_err(), "two inputs");
+}
@@ -0,0 +1,84 @@
+//! `scoreboard-rust validate` over the capture, hex and raw fixtures in `tests/fixtures/validate`.
+
+mod common;
+
+use common::{fresh_dir, run, Run};
+use serde_json::{json, Value};
+use std::{fs, path::PathBuf};
+
+fn fixture(name: &str) -> String {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/validate").join(name).display().to_string()
+}
+
+fn validate(file: &str, options: &[&str]) -> Run {
+    let mut args = vec!["validate", file];
+    args.extend(options);
+    run(&args, b"")
+}
+
+/// The JSON report on `file` and the exit code.
+fn report(file: &str, options: &[&str]) -> (Value, Option<i32>) {
+    let run = validate(&fixture(file), &[options, &["--json"]].concat());
+    (run.json(), run.code)
+}
+
+#[test]
+fn a_clean_capture_passes() {
+    let (report, code) = report("clean.log", &[]);
+    assert_eq!(report["format"], "capture");
+    assert_eq!((report["frames"].as_u64(), report["parsed"].as_u64()), (Some(8), Some(7)));
+    assert_eq!(report["messageTypes"]["18"], 3);
+    assert_eq!(report["messageTypes"]["30"], 3);
+    assert_eq!(report["ignored"]["19"], 1, "types the server doesn't read are no error");
+    assert_eq!(report["errors"], json!({}));
+    // The capture starts in the middle of a frame; a heartbeat sits between two frames
+    assert_eq!((report["skippedBytes"].as_u64(), report["heartbeats"].as_u64()), (Some(10), Some(1)));
+
+    let first = &report["firstState"];
+    assert_eq!((first["time"].as_str(), first["game_state"].as_str()), (Some("10:00"), Some("paused")));
+    let last = &report["lastState"];
+    assert_eq!((last["home_score"].as_str(), last["away_score"].as_str(), last["time"].as_str()), (Some("  2"), Some("  3"), Some("09:58")));
+    assert_eq!(report["suspicious"], json!([]));
+    assert_eq!(code, Some(0));
+    assert_eq!(validate(&fixture("clean.log"), &[]).code, Some(0));
+}
+
+#[test]
+fn a_corrupt_frame_fails() {
+    let (report, code) = report("corrupt.hex", &[]);
+    assert_eq!(report["format"], "hex", "detected");
+    assert_eq!((report["frames"].as_u64(), report["parsed"].as_u64()), (Some(3), Some(2)));
+    assert_eq!(report["errors"]["LRC validation failed"], 1);
+    assert_eq!(code, Some(1));
+
+    // As the server would with --lenient
+    let (lenient, code) = self::report("corrupt.hex", &["--lenient"]);
+    assert_eq!((lenient["errors"].clone(), code), (json!({}), Some(0)));
+}
+
+#[test]
+fn suspicious_transitions_fail_only_when_strict() {
+    let (report, code) = report("score_fix.bin", &[]);
+    assert_eq!(report["format"], "raw");
+    assert_eq!(report["suspicious"], json!([{ "frame": 3, "description": "home score went down from 15 to 13" }]));
+    assert_eq!(code, Some(0));
+    assert_eq!(validate(&fixture("score_fix.bin"), &["--strict"]).code, Some(1));
+}
+
+#[test]
+fn a_forced_format_reads_the_file_as_told() {
+    // The capture's hex lists read as plain hex text: the brackets aren't hex
+    let run = validate(&fixture("clean.log"), &["--format", "hex"]);
+    assert_eq!(run.code, Some(1));
+    assert!(run.stderr.contains("line 1: Invalid hex [30"), "{}", run.stderr);
+
+    // Raw bytes of the hex file are text, no frame in them
+    let (report, code) = report("corrupt.hex", &["--format", "raw"]);
+    assert_eq!((report["parsed"].as_u64(), code), (Some(0), Some(1)));
+
+    let hex = fresh_dir("validate-hex").join("frames.txt");
+    fs::write(&hex, "01 7f\n0x02,20 # comment\n3330").unwrap();
+    let run = validate(hex.to_str().unwrap(), &["--format", "hex", "--json"]);
+    assert_eq!(run.json()["bytes"], 6, "01 7F 02 20 33 30");
+    assert_eq!(validate(&fixture("clean.log"), &["--format", "pcap"]).code, Some(2));
+}
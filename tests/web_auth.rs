@@ -0,0 +1,87 @@
+//! The admin token on the web server: missing (401), wrong (403) and, after too many wrong
+//! ones from an address, refused outright (429), each with a JSON body and counted.
+
+mod common;
+
+use common::{command, Harness, Response};
+use std::{
+    io::{BufRead, BufReader},
+    net::TcpListener,
+    process::Stdio,
+};
+
+fn patch(server: &Harness, headers: &[(&str, &str)], query: &str) -> Response {
+    let headers = [headers, &[("Content-Type", "application/json")]].concat();
+    server.request("PATCH", &format!("/api/state{}", query), &headers, br#"{"homeScore": 2}"#)
+}
+
+#[test]
+fn missing_wrong_and_right_tokens() {
+    let server = Harness::start("");
+    let missing = patch(&server, &[], "");
+    assert_eq!((missing.status, missing.json()), (401, serde_json::json!({ "error": "missing admin token" })));
+    let wrong = patch(&server, &[("Authorization", "Bearer guess")], "");
+    assert_eq!((wrong.status, wrong.json()), (403, serde_json::json!({ "error": "invalid admin token" })));
+    assert_eq!(patch(&server, &[], "?token=guess").status, 403);
+    assert_eq!(server.shared.version(), 0, "nothing changed");
+
+    assert_eq!(patch(&server, &[("Authorization", "Bearer secret")], "").status, 200);
+    assert_eq!(patch(&server, &[], "?token=secret").status, 200);
+    let metrics = server.shared.status().metrics;
+    assert_eq!((metrics.web_auth_failures, metrics.web_auth_rate_limited), (2, 0));
+
+    // Reads stay public
+    assert_eq!(server.get("/api/state").status, 200);
+}
+
+#[test]
+fn too_many_wrong_tokens_are_rate_limited() {
+    let server = Harness::start("");
+    for _ in 0..5 {
+        assert_eq!(patch(&server, &[("Authorization", "Bearer guess")], "").status, 403);
+    }
+    let limited = patch(&server, &[("Authorization", "Bearer secret")], "");
+    assert_eq!(limited.status, 429, "even the right token, for a while");
+    assert_eq!(limited.json()["error"], "too many failed attempts, try again later");
+    assert_eq!(server.get("/admin").status, 429);
+    let metrics = server.shared.status().metrics;
+    assert_eq!((metrics.web_auth_failures, metrics.web_auth_rate_limited), (5, 2));
+    assert_eq!(server.get_json("/api/state").1["metrics"]["webAuthFailures"], 5);
+}
+
+#[test]
+fn reads_can_be_locked_too() {
+    let server = Harness::start("--lock-read-api");
+    assert_eq!(server.get("/api/state").status, 401);
+    assert_eq!(server.get("/api/state?token=secret").status, 200);
+    assert_eq!(server.request("GET", "/api/state", &[("Authorization", "Bearer secret")], b"").status, 200);
+}
+
+/// The admin token `dev` logs when none is configured.
+fn generated_token() -> String {
+    let free = || TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+    let mut child = command(&["dev", "--no-mdns", "--listen", &free(), "--web-address", &free()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stderr = child.stderr.take().unwrap();
+    let line = BufReader::new(stderr)
+        .lines()
+        .map_while(Result::ok)
+        .find(|line| line.contains("generated one for this run"))
+        .expect("the generated token is logged");
+    child.kill().unwrap();
+    child.wait().unwrap();
+    line.rsplit(' ').next().unwrap().to_string()
+}
+
+#[test]
+fn a_generated_token_is_128_random_bits() {
+    let (first, second) = (generated_token(), generated_token());
+    for token in [&first, &second] {
+        assert_eq!(token.len(), 32, "{}", token);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()), "{}", token);
+    }
+    assert_ne!(first, second);
+}
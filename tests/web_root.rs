@@ -0,0 +1,61 @@
+//! `--web-root`: overlay files from a directory, the embedded copies for the rest, and nothing
+//! from outside it.
+
+mod common;
+
+use common::{fresh_dir, http, Harness};
+use std::fs;
+
+/// A server whose web root has its own stylesheet, script, logo and an empty directory, next to
+/// a file it must not serve.
+fn server(name: &str) -> Harness {
+    let dir = fresh_dir(name);
+    fs::write(dir.join("secret.txt"), "do not serve").unwrap();
+    let root = dir.join("www");
+    fs::create_dir_all(root.join("media/empty")).unwrap();
+    fs::write(root.join("overlay.css"), "body { background: transparent; color: red; }").unwrap();
+    fs::write(root.join("custom.js"), "console.log('custom');").unwrap();
+    fs::write(root.join("media/logo.svg"), "<svg xmlns=\"http://www.w3.org/2000/svg\"/>").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(dir.join("secret.txt"), root.join("link.txt")).unwrap();
+    Harness::start(&format!("--web-root {}", root.display()))
+}
+
+#[test]
+fn files_in_the_root_win_and_the_rest_is_embedded() {
+    let server = server("web_root_files");
+    let css = server.get("/overlay.css");
+    assert_eq!((css.text().as_str(), css.header("content-type")), ("body { background: transparent; color: red; }", Some("text/css; charset=utf-8")));
+    let js = server.get("/custom.js");
+    assert_eq!((js.status, js.header("content-type")), (200, Some("text/javascript; charset=utf-8")));
+    let svg = server.get("/media/logo.svg");
+    assert_eq!((svg.status, svg.header("content-type")), (200, Some("image/svg+xml")));
+
+    // Not in the root: the embedded copy
+    let embedded = server.get("/overlay.js");
+    assert_eq!((embedded.status, embedded.text()), (200, include_str!("../static/overlay.js").to_string()));
+    assert_eq!(server.get("/media/CAB.svg").header("content-type"), Some("image/svg+xml"));
+}
+
+#[test]
+fn paths_out_of_the_root_and_directories_are_not_found() {
+    let server = server("web_root_traversal");
+    let paths = [
+        "/../secret.txt",
+        "/../../etc/passwd",
+        "/media/../../secret.txt",
+        "/%2e%2e/secret.txt",
+        "/..%2fsecret.txt",
+        "/media%2f..%2f..%2fsecret.txt",
+        "/..%5csecret.txt",
+        "/media/",
+        "/media",
+        "/media/empty",
+        "/link.txt",
+    ];
+    for path in paths {
+        let response = http(server.web, "GET", path, &[], b"");
+        assert!(matches!(response.status, 400 | 404), "{}: {}", path, response.status);
+        assert!(!response.text().contains("do not serve") && !response.text().contains("root:"), "{}", path);
+    }
+}
@@ -0,0 +1,51 @@
+//! Stopping the web server: open connections are closed properly, requests in flight are
+//! answered, and a new server can take the same port within the same process.
+
+mod common;
+
+use common::{http, scores, EventStream, Harness, WebSocket, WsMessage};
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn stopping_closes_every_connection_and_frees_the_port() {
+    let mut server = Harness::start("");
+    server.play(&[scores(12, 9)]);
+    let web = server.web;
+
+    let mut socket = WebSocket::open(web, "/ws", &[]);
+    socket.next_text();
+    let mut stream = EventStream::open(web, "/events", &[]);
+    stream.next();
+    let mut idle = TcpStream::connect(web).unwrap();
+    idle.write_all(b"GET /api/time HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    idle.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    let mut answered = [0; 15];
+    idle.read_exact(&mut answered).unwrap();
+    let version = server.shared.version();
+    let long_poll = thread::spawn(move || http(web, "GET", &format!("/api/state?wait=30&version={}", version), &[], b""));
+    thread::sleep(Duration::from_millis(200));
+
+    let stopping = Instant::now();
+    let (ingest, web) = server.stop();
+    assert!(stopping.elapsed() < Duration::from_secs(5), "drained in {:?}", stopping.elapsed());
+
+    assert_eq!(socket.next(), WsMessage::Close(Some(1001), "server shutting down".to_string()));
+    assert!(stream.next_within(Duration::from_secs(5)).is_none(), "the event stream ends");
+    let mut rest = Vec::new();
+    assert!(idle.read_to_end(&mut rest).is_ok(), "the kept-alive connection is closed");
+    let long_poll = long_poll.join().unwrap();
+    assert_eq!((long_poll.status, long_poll.json()["version"].as_u64()), (200, Some(version)), "answered, not dropped");
+
+    // A new server on the same port, and the clients reconnect to it
+    let mut server = Harness::start_on(ingest, web, "");
+    assert_eq!(server.web, web);
+    let mut socket = WebSocket::open(web, "/ws", &[]);
+    assert_eq!(socket.next_text()["home_score"], "-", "a fresh state");
+    server.play(&[scores(2, 0)]);
+    assert_eq!(socket.next_text()["home_score"], "  2");
+}
@@ -0,0 +1,87 @@
+//! `/ws`: the current state on connect, then every published state, with its game events
+//! after it on `?events=1`.
+
+mod common;
+
+use common::{scores, Harness, WebSocket, WsMessage};
+use std::time::{Duration, Instant};
+
+#[test]
+fn a_websocket_client_gets_the_current_state_then_every_update() {
+    let mut server = Harness::start("");
+    server.play(&[scores(12, 9)]);
+
+    let mut client = WebSocket::open(server.web, "/ws", &[]);
+    assert_eq!(client.status, 101);
+    assert_eq!(client.next(), WsMessage::Text(server.shared.current_json()));
+
+    server.play(&[scores(14, 9), scores(14, 11)]);
+    assert_eq!(client.next_text()["home_score"], " 14");
+    assert_eq!(client.next_text()["away_score"], " 11");
+    assert_eq!(client.next_within(Duration::from_millis(300)), None, "states only, no events");
+}
+
+#[test]
+fn events_follow_the_state_they_belong_to() {
+    let mut server = Harness::start("");
+    server.play(&[scores(12, 9)]);
+    let mut client = WebSocket::open(server.web, "/ws?events=1", &[]);
+    client.next_text();
+
+    server.play(&[scores(15, 9)]);
+    let state = client.next_text();
+    assert_eq!(state["home_score"], " 15");
+    let event = client.next_text();
+    assert_eq!((event["type"].as_str(), event["side"].as_str(), event["delta"].as_i64()), (Some("ScoreChange"), Some("home"), Some(3)));
+    assert_eq!(event["version"], server.shared.version());
+}
+
+#[test]
+fn clients_over_the_cap_are_closed_with_try_again_later() {
+    let server = Harness::start("");
+    let mut clients: Vec<WebSocket> = (0..64).map(|_| WebSocket::open(server.web, "/ws", &[])).collect();
+    for client in &mut clients {
+        client.next_text();
+    }
+    let mut refused = WebSocket::open(server.web, "/ws", &[]);
+    assert_eq!(refused.next(), WsMessage::Close(Some(1013), "too many WebSocket clients (64 connected)".to_string()));
+
+    // A slot is free again once a client goes
+    clients.pop().unwrap().send(0x8, &1000u16.to_be_bytes());
+    common::wait_until("the slot to be free", || {
+        let mut client = WebSocket::open(server.web, "/ws", &[]);
+        matches!(client.next(), WsMessage::Text(_))
+    });
+}
+
+#[test]
+fn a_client_that_stops_answering_pings_is_disconnected() {
+    let mut server = Harness::start("--ws-ping-interval 1");
+    let mut silent = WebSocket::open(server.web, "/ws", &[]);
+    let mut answering = WebSocket::open(server.web, "/ws", &[]);
+    silent.next_text();
+    answering.next_text();
+
+    // Pinged every second, with half a second to answer
+    let until = Instant::now() + Duration::from_millis(2500);
+    let mut pings = 0;
+    while let Some(message) = answering.next_within(until.saturating_duration_since(Instant::now())) {
+        let WsMessage::Ping(payload) = message else {
+            panic!("expected a ping, got {:?}", message);
+        };
+        answering.send(0xa, &payload);
+        pings += 1;
+    }
+    assert!(pings >= 2, "{} pings", pings);
+
+    // The silent one got a single ping and then the close frame, and the connection ends
+    assert!(matches!(silent.next(), WsMessage::Ping(_)));
+    assert_eq!(silent.next(), WsMessage::Close(Some(1001), "ping timeout".to_string()));
+    assert_eq!(silent.next_within(Duration::from_secs(2)), None);
+    common::wait_until("the client to be counted out", || server.shared.status().metrics.ws_clients == 1);
+    assert_eq!(server.shared.status().metrics.ws_reaped, 1);
+
+    // The other one still gets the updates
+    server.play(&[scores(2, 0)]);
+    assert_eq!(answering.next_text()["home_score"], "  2");
+}
@@ -0,0 +1,43 @@
+//! Commands over a WebSocket: `/ws/control` takes them with the admin token and every `/ws`
+//! client gets the state they produce in the normal push; `/ws` itself only pushes.
+
+mod common;
+
+use common::{scores, Harness, WebSocket};
+use std::time::Duration;
+
+const ADMIN: (&str, &str) = ("Authorization", "Bearer secret");
+
+#[test]
+fn a_command_comes_back_to_every_client_as_a_pushed_state() {
+    let mut server = Harness::start("");
+    server.play(&[scores(10, 8)]);
+    let mut panel = WebSocket::open(server.web, "/ws/control", &[ADMIN]);
+    let mut viewer = WebSocket::open(server.web, "/ws", &[]);
+    assert_eq!((panel.next_text()["home_score"].as_str(), viewer.next_text()["home_score"].as_str()), (Some(" 10"), Some(" 10")));
+
+    panel.send_text(r#"{"cmd": "addPoints", "side": "home", "points": 2}"#);
+    assert_eq!(panel.next_text(), serde_json::json!({ "ok": true }));
+    assert_eq!(panel.next_text()["home_score"], " 12");
+    assert_eq!(viewer.next_text()["home_score"], " 12");
+    assert!(server.shared.manual_override());
+
+    // The same validation as POST /api/control/score
+    panel.send_text(r#"{"cmd": "addPoints", "side": "home", "points": -20}"#);
+    let error = panel.next_text()["error"].as_str().unwrap().to_string();
+    let rest = server.request("POST", "/api/control/score", &[ADMIN, ("Content-Type", "application/json")], br#"{"side": "home", "points": -20}"#);
+    assert_eq!(rest.status, 400);
+    assert_eq!(rest.json()["error"], error.as_str());
+    assert_eq!(viewer.next_within(Duration::from_millis(300)), None, "nothing was published");
+}
+
+#[test]
+fn the_push_socket_ignores_commands() {
+    let mut server = Harness::start("");
+    server.play(&[scores(10, 8)]);
+    let mut viewer = WebSocket::open(server.web, "/ws", &[ADMIN]);
+    viewer.next_text();
+    viewer.send_text(r#"{"cmd": "addPoints", "side": "home", "points": 2}"#);
+    assert_eq!(viewer.next_within(Duration::from_millis(300)), None);
+    assert_eq!(server.shared.current().home_score, " 10");
+}
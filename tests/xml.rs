@@ -0,0 +1,93 @@
+//! The XML output for CG systems against a golden file, renamed, and as a file on disk.
+
+mod common;
+
+use common::{fresh_dir, wait_until, Harness};
+use scoreboard_rust::{
+    xml::{self, XmlNames},
+    Bonus, GameState, Lang, ServerConfig,
+};
+use std::{fs, time::Duration};
+
+/// Late in the fourth quarter, with a team name that needs escaping, a blank away score and the
+/// match details.
+fn fixed_state() -> GameState {
+    let mut state = GameState::default();
+    state.home_score = " 80".to_string();
+    state.away_score = "   ".to_string();
+    state.time = "01:24".to_string();
+    state.period_name = "4 Quarter".to_string();
+    state.home_fouls = "4".to_string();
+    state.away_fouls = "5".to_string();
+    state.home_timeouts = "1".to_string();
+    state.away_timeouts = "2".to_string();
+    state.game_state = "running".to_string();
+    state.shot_clock = "4.3".to_string();
+    state.shot_clock_running = true;
+    state.possession = "away".to_string();
+    state.home_bonus = Some(Bonus::Single);
+    state.teams.home.name = "Benfica & \"Friends\"".to_string();
+    state.teams.home.abbrev = "SLB".to_string();
+    state.teams.away.name = "Madeira".to_string();
+    state.teams.away.abbrev = "CAB".to_string();
+    state.match_info.competition = "Liga Betclic".to_string();
+    state.match_info.round = "Round 12".to_string();
+    state.match_info.venue = "Pavilhão do Funchal".to_string();
+    state.match_info.tip_off = Some("2026-03-14T20:30:00+01:00".to_string());
+    state.match_info.officials = vec!["A. Silva".to_string(), "R. Costa".to_string()];
+    state
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn xml_matches_the_golden_file() {
+    let xml = xml::render(&fixed_state(), 42, Lang::Pt, &XmlNames::default());
+    assert_eq!(xml, include_str!("golden/state.xml"));
+}
+
+#[test]
+fn names_come_from_the_config() {
+    let config = ServerConfig::from_args(&args(&["--xml-name", "home=Team1", "--xml-name", "home.score=Pts", "--xml-name", "scoreboard=SB"])).unwrap();
+    let xml = xml::render(&fixed_state(), 42, Lang::En, &XmlNames::new(&config.xml_names));
+    assert!(xml.contains("<SB version=\"42\" stale=\"false\">"), "{}", xml);
+    assert!(xml.contains("<Team1 name=\"Benfica &amp; &quot;Friends&quot;\" abbrev=\"SLB\" Pts=\"80\" "), "{}", xml);
+    assert!(xml.contains("<away name=\"Madeira\" abbrev=\"CAB\" score=\"\" "), "{}", xml);
+    assert!(xml.ends_with("</SB>\n"), "{}", xml);
+
+    assert!(ServerConfig::from_args(&args(&["--xml-name", "home.points=Pts"])).is_err(), "unknown key");
+    assert!(ServerConfig::from_args(&args(&["--xml-name", "home=Team 1"])).is_err(), "not an XML name");
+    assert!(ServerConfig::from_args(&args(&["--xml-name", "home.score=1st"])).is_err(), "not an XML name");
+}
+
+#[test]
+fn the_xml_file_follows_the_state() {
+    let dir = fresh_dir("xml_file");
+    let path = dir.join("scoreboard.xml");
+    let mut config = Harness::config("");
+    config.xml_file = Some(path.clone());
+    config.xml_interval = Duration::from_millis(50);
+    let server = Harness::with_config(config);
+    let shared = &server.shared;
+
+    shared
+        .set_manual(|state| {
+            *state = fixed_state();
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+    let written = |score: &str| fs::read_to_string(&path).is_ok_and(|xml| xml.contains(&format!("score=\"{}\"", score)));
+    wait_until("the XML file has the score", || written("80"));
+    assert!(!dir.join("scoreboard.xml.tmp").exists(), "renamed into place");
+    assert!(!dir.join("scoreboard.xml.tmp").exists(), "renamed into place");
+
+    shared
+        .set_manual(|state| {
+            state.home_score = " 82".to_string();
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+    wait_until("the XML file has the new score", || written("82"));
+}